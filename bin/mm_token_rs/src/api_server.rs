@@ -0,0 +1,217 @@
+use std::net::SocketAddr;
+
+use mm_token_utils::env::get_env;
+use provider_utils::http_providers::{HttpProviders, ProviderHealth};
+use rocket::{
+    get, http::Status, post, response::status::Custom, routes, serde::json::Json, Build, Rocket,
+    State,
+};
+
+use crate::{
+    core::{ApiService, DumpGroup, WalletRole},
+    types::{
+        BatchApprove, Buyers, Deployer, DeploymentChecklist, Dump, LaunchStatus, MarketMakers,
+        NetworkStatus, PoolSnapshot, Quote, Sellers,
+    },
+};
+
+/**
+ * http server of the ApiService getters under `/api`, listening on `API_BIND_ADDR`
+ */
+pub fn build_api_server(api_service: ApiService) -> Rocket<Build> {
+    let bind_addr: SocketAddr = get_env("API_BIND_ADDR", Some("127.0.0.1:8000".to_string()))
+        .parse()
+        .expect("invalid API_BIND_ADDR");
+
+    rocket::build()
+        .configure(
+            rocket::Config::figment()
+                .merge(("address", bind_addr.ip()))
+                .merge(("port", bind_addr.port())),
+        )
+        .manage(api_service)
+        .mount(
+            "/api",
+            routes![
+                network_status,
+                deployment_checklist,
+                deployer,
+                buyers,
+                auto_buyers,
+                sellers,
+                market_makers,
+                launch_process,
+                batch_approve,
+                dump,
+                quote,
+                pool_snapshot,
+                providers_health,
+            ],
+        )
+}
+
+// APIs
+#[get("/network_status")]
+async fn network_status(
+    api_service: &State<ApiService>,
+) -> Result<Json<NetworkStatus>, Custom<String>> {
+    let network_status = api_service.get_network_status().await.map_err(|err| {
+        log::error!("[/api/network_status] {:?}", err);
+        Custom(Status::InternalServerError, err.to_string())
+    })?;
+    log::info!("[/api/network_status] Response: {:#?}", network_status);
+    Ok(Json(network_status))
+}
+
+#[get("/deployment_checklist")]
+async fn deployment_checklist(api_service: &State<ApiService>) -> Json<DeploymentChecklist> {
+    let deployment_checklist = api_service.get_deployment_checklist().await;
+    log::info!(
+        "[/api/deployment_checklist] Response: {:#?}",
+        deployment_checklist
+    );
+    Json(deployment_checklist)
+}
+
+#[get("/deployer")]
+async fn deployer(api_service: &State<ApiService>) -> Json<Deployer> {
+    let deployer = api_service.get_deployer().await;
+    log::info!("[/api/deployer] Response: {:#?}", deployer);
+    Json(deployer)
+}
+
+#[get("/buyers")]
+async fn buyers(api_service: &State<ApiService>) -> Json<Buyers> {
+    let buyers = api_service.get_buyers().await;
+    log::info!("[/api/buyers] Response: {:#?}", buyers);
+    Json(buyers)
+}
+
+#[get("/auto_buyers")]
+async fn auto_buyers(api_service: &State<ApiService>) -> Json<Buyers> {
+    let auto_buyers = api_service.get_auto_buyers().await;
+    log::info!("[/api/auto_buyers] Response: {:#?}", auto_buyers);
+    Json(auto_buyers)
+}
+
+#[get("/sellers")]
+async fn sellers(api_service: &State<ApiService>) -> Json<Sellers> {
+    let sellers = api_service.get_sellers().await;
+    log::info!("[/api/sellers] Response: {:#?}", sellers);
+    Json(sellers)
+}
+
+#[get("/market_makers")]
+async fn market_makers(api_service: &State<ApiService>) -> Json<MarketMakers> {
+    let market_makers = api_service.get_market_makers().await;
+    log::info!("[/api/market_makers] Response: {:#?}", market_makers);
+    Json(market_makers)
+}
+
+#[post("/launch")]
+async fn launch_process(api_service: &State<ApiService>) -> Json<LaunchStatus> {
+    let launch_status = api_service.launch_process().await;
+    log::info!("[/api/launch] Response: {:#?}", launch_status);
+    Json(launch_status)
+}
+
+/// approve the active router from wallets `index_from..=index_to` of role buyer | auto_buyer | seller | market_maker_<n>
+#[post("/batch_approve?<role>&<index_from>&<index_to>")]
+async fn batch_approve(
+    api_service: &State<ApiService>,
+    role: &str,
+    index_from: u32,
+    index_to: u32,
+) -> Result<Json<BatchApprove>, Custom<String>> {
+    let role: WalletRole = role
+        .parse()
+        .map_err(|err: anyhow::Error| Custom(Status::BadRequest, err.to_string()))?;
+    let batch_approve = api_service
+        .batch_approve(role, index_from, index_to)
+        .await
+        .map_err(|err| {
+            log::error!("[/api/batch_approve] {:?}", err);
+            Custom(Status::InternalServerError, err.to_string())
+        })?;
+    log::info!("[/api/batch_approve] Response: {:#?}", batch_approve);
+    Ok(Json(batch_approve))
+}
+
+/// sell the tokens of the buyer | auto_buyer | seller | market_maker wallets in the background,
+/// `interval_min..=interval_max` seconds apart, only with `confirm=true` and one dump at a time
+#[post("/dump?<group>&<interval_min>&<interval_max>&<confirm>")]
+async fn dump(
+    api_service: &State<ApiService>,
+    group: &str,
+    interval_min: u32,
+    interval_max: u32,
+    confirm: Option<bool>,
+) -> Result<Json<Dump>, Custom<String>> {
+    let group: DumpGroup = group
+        .parse()
+        .map_err(|err: anyhow::Error| Custom(Status::BadRequest, err.to_string()))?;
+    if confirm != Some(true) {
+        return Err(Custom(
+            Status::BadRequest,
+            format!(
+                "every {:?} wallet will sell all its tokens, resend with confirm=true to proceed",
+                group
+            ),
+        ));
+    }
+    let dump = api_service
+        .dump(group, interval_min, interval_max)
+        .await
+        .map_err(|err| {
+            log::error!("[/api/dump] {:?}", err);
+            Custom(Status::InternalServerError, err.to_string())
+        })?;
+    log::info!("[/api/dump] Response: {:#?}", dump);
+    Ok(Json(dump))
+}
+
+/// quote `amount` ETH (buy) or tokens (`side=sell`) on the active router without sending a tx
+#[get("/quote?<amount>&<side>")]
+async fn quote(
+    api_service: &State<ApiService>,
+    amount: &str,
+    side: Option<&str>,
+) -> Result<Json<Quote>, Custom<String>> {
+    let is_buy = match side.unwrap_or("buy") {
+        "buy" => true,
+        "sell" => false,
+        side => {
+            return Err(Custom(
+                Status::BadRequest,
+                format!("invalid side {:?}, expect buy | sell", side),
+            ))
+        }
+    };
+    let quote = api_service.get_quote(amount, is_buy).await.map_err(|err| {
+        log::error!("[/api/quote] {:?}", err);
+        Custom(Status::InternalServerError, err.to_string())
+    })?;
+    log::info!("[/api/quote] Response: {:#?}", quote);
+    Ok(Json(quote))
+}
+
+/// reserves, price and FDV of the pool of the active router
+#[get("/pool/snapshot")]
+async fn pool_snapshot(
+    api_service: &State<ApiService>,
+) -> Result<Json<PoolSnapshot>, Custom<String>> {
+    let pool_snapshot = api_service.get_pool_snapshot().await.map_err(|err| {
+        log::error!("[/api/pool/snapshot] {:?}", err);
+        Custom(Status::InternalServerError, err.to_string())
+    })?;
+    log::info!("[/api/pool/snapshot] Response: {:#?}", pool_snapshot);
+    Ok(Json(pool_snapshot))
+}
+
+/// latency and block number of every rpc of the network, probed concurrently
+#[get("/providers/health")]
+async fn providers_health(api_service: &State<ApiService>) -> Json<Vec<ProviderHealth>> {
+    let provider_healths = HttpProviders::probe_all(&api_service.env.listen_network, false).await;
+    log::info!("[/api/providers/health] Response: {:#?}", provider_healths);
+    Json(provider_healths)
+}
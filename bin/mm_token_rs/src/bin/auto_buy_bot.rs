@@ -3,25 +3,28 @@ use std::{
     time::Duration,
 };
 
-use cached::TimedCache;
-use ethers::{
-    providers::Middleware,
-    types::{H256, U256},
-};
+use ethers::{providers::Middleware, types::U256};
 use mm_token_rs::{
     constants::Env,
-    core::{AutoBuyService, GasPrice},
+    core::{AutoBuyService, GasPrice, MessageTransportService},
+    utils::new_tx_hashes_cache,
 };
-use mm_token_utils::{env::get_env, log::setup_logger};
-use provider_utils::http_providers::HttpProviders;
-use tokio::{
-    sync::{Mutex, RwLock},
-    task::JoinSet,
+use mm_token_utils::{
+    env::{get_env, load_env},
+    log::setup_logger,
 };
+use provider_utils::{http_providers::HttpProviders, provider_pool::ProviderPool};
+use tokio::{sync::RwLock, task::JoinSet};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenv::dotenv().ok();
+    let result = run().await;
+    MessageTransportService::flush().await;
+    result
+}
+
+async fn run() -> anyhow::Result<()> {
+    load_env();
     setup_logger(None)?;
     let mut set = JoinSet::new();
     let env = Env::new();
@@ -31,25 +34,19 @@ async fn main() -> anyhow::Result<()> {
 
     let fetched_gas_price = http_provider.get_gas_price().await?;
     let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
-    let provider_index: Arc<RwLock<usize>> = Arc::new(RwLock::new(
-        HttpProviders::init_provider_index(&env.listen_network, false).await?,
-    ));
+    let provider_pool = ProviderPool::init(&env.listen_network, false).await?;
     set.spawn(GasPrice::fetch_periodically(
         exit.clone(),
-        env.listen_network,
-        provider_index.clone(),
+        provider_pool.clone(),
         gas_price.clone(),
         Duration::from_secs(3),
     ));
     set.spawn(HttpProviders::fetch_periodically(
-        env.listen_network,
-        false,
         Some(exit.clone()),
-        provider_index.clone(),
+        provider_pool.clone(),
     ));
 
-    let tx_hashes_cache: Arc<Mutex<TimedCache<H256, bool>>> =
-        Arc::new(Mutex::new(TimedCache::with_lifespan(120)));
+    let tx_hashes_cache = new_tx_hashes_cache();
 
     let auto_buy_event_listen_enabled: bool = get_env("AUTO_BUY_EVENT_LISTEN_ENABLED", None)
         .parse()
@@ -57,12 +54,12 @@ async fn main() -> anyhow::Result<()> {
     if auto_buy_event_listen_enabled {
         let env = env.clone();
         let gas_price = gas_price.clone();
-        let provider_index = provider_index.clone();
+        let provider_pool = provider_pool.clone();
         let http_provider = http_provider.clone();
         let tx_hashes_cache_clone = tx_hashes_cache.clone();
         set.spawn(async {
             let mut auto_buy_service =
-                AutoBuyService::new(env, gas_price, provider_index, http_provider);
+                AutoBuyService::new(env, gas_price, provider_pool, http_provider);
             auto_buy_service.init().await?;
             auto_buy_service
                 .start_event_mode(tx_hashes_cache_clone)
@@ -78,13 +75,13 @@ async fn main() -> anyhow::Result<()> {
     if auto_buy_mempool_listen_enabled {
         let env = env.clone();
         let gas_price = gas_price.clone();
-        let provider_index = provider_index.clone();
+        let provider_pool = provider_pool.clone();
         let http_provider = http_provider.clone();
         let tx_hashes_cache_clone = tx_hashes_cache.clone();
 
         set.spawn(async {
             let mut auto_buy_service =
-                AutoBuyService::new(env, gas_price, provider_index, http_provider);
+                AutoBuyService::new(env, gas_price, provider_pool, http_provider);
             auto_buy_service.init().await?;
             auto_buy_service
                 .start_mempool_mode(tx_hashes_cache_clone)
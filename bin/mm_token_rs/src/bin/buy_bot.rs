@@ -9,16 +9,22 @@ use ethers::{
 };
 use mm_token_rs::{
     constants::Env,
-    core::{BuyService, GasPrice},
+    core::{BuyService, GasPrice, MessageTransportService},
 };
-use mm_token_utils::log::setup_logger;
-use provider_utils::http_providers::HttpProviders;
+use mm_token_utils::{env::load_env, log::setup_logger};
+use provider_utils::{http_providers::HttpProviders, provider_pool::ProviderPool};
 use tokio::{sync::RwLock, task::JoinSet};
 
 #[allow(clippy::never_loop)]
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenv::dotenv().ok();
+    let result = run().await;
+    MessageTransportService::flush().await;
+    result
+}
+
+async fn run() -> anyhow::Result<()> {
+    load_env();
     setup_logger(None)?;
     let mut set = JoinSet::new();
     let env = Env::new();
@@ -27,28 +33,23 @@ async fn main() -> anyhow::Result<()> {
         Arc::new(HttpProviders::get_healthy_provider(&env.listen_network, false).await?);
     let fetched_gas_price = http_provider.get_gas_price().await?;
     let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
-    let provider_index: Arc<RwLock<usize>> = Arc::new(RwLock::new(
-        HttpProviders::init_provider_index(&env.listen_network, false).await?,
-    ));
+    let provider_pool = ProviderPool::init(&env.listen_network, false).await?;
     set.spawn(GasPrice::fetch_periodically(
         exit.clone(),
-        env.listen_network,
-        provider_index.clone(),
+        provider_pool.clone(),
         gas_price.clone(),
         Duration::from_secs(3),
     ));
     set.spawn(start_event_mode(
         env.clone(),
         gas_price,
-        provider_index.clone(),
+        provider_pool.clone(),
         http_provider.clone(),
     ));
 
     set.spawn(HttpProviders::fetch_periodically(
-        env.listen_network,
-        false,
         Some(exit.clone()),
-        provider_index.clone(),
+        provider_pool.clone(),
     ));
 
     while let Some(res) = set.join_next().await {
@@ -73,10 +74,10 @@ async fn main() -> anyhow::Result<()> {
 async fn start_event_mode(
     env: Env,
     gas_price: Arc<RwLock<U256>>,
-    provider_index: Arc<RwLock<usize>>,
+    provider_pool: ProviderPool,
     http_provider: Arc<Provider<Http>>,
 ) -> anyhow::Result<()> {
-    let mut buy_service = BuyService::new(env, gas_price, provider_index, http_provider);
+    let mut buy_service = BuyService::new(env, gas_price, provider_pool, http_provider);
     buy_service.init().await?;
     buy_service.start_event_mode().await?;
     Ok(())
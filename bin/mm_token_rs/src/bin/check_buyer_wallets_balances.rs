@@ -1,12 +1,21 @@
 use std::sync::Arc;
 
-use mm_token_rs::{constants::Env, core::WalletService};
-use mm_token_utils::log::setup_logger;
+use mm_token_rs::{
+    constants::Env,
+    core::{MessageTransportService, WalletService},
+};
+use mm_token_utils::{env::load_env, log::setup_logger};
 use provider_utils::http_providers::HttpProviders;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenv::dotenv().ok();
+    let result = run().await;
+    MessageTransportService::flush().await;
+    result
+}
+
+async fn run() -> anyhow::Result<()> {
+    load_env();
     setup_logger(None)?;
     let env = Env::new();
     let http_provider =
@@ -3,15 +3,16 @@ use std::str::FromStr;
 use ethers::signers::Signer;
 use mm_token_rs::types::CheckMnemonicWalletInfo;
 use mm_token_utils::{
-    env::get_env,
+    env::{get_env, load_env},
     log::setup_logger,
+    signer::WalletSigner,
     utils::{get_wallet_path_prefix, load_mnemonic_wallet},
 };
 use provider_utils::enums::ENetwork;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenv::dotenv().ok();
+    load_env();
     setup_logger(None)?;
 
     let network_str = get_env("LISTEN_NETWORK", None);
@@ -23,15 +24,31 @@ async fn main() -> anyhow::Result<()> {
         .parse()
         .unwrap();
 
-    let hd_wallet_path_prefix = get_wallet_path_prefix(listen_network);
+    // the path of the network unless the mnemonic was derived elsewhere at another one
+    let hd_wallet_path_prefix = get_env("CHECKED_MNEMONIC_PATH_PREFIX", Some("".to_string()));
+    let hd_wallet_path_prefix = if hd_wallet_path_prefix.is_empty() {
+        get_wallet_path_prefix(listen_network)
+    } else {
+        hd_wallet_path_prefix
+    };
     let mut list_wallets_info = Vec::<CheckMnemonicWalletInfo>::new();
     for index_wallet in 0..checked_mnemonic_wallet_count {
-        let wallet = load_mnemonic_wallet(&checked_mnemonic, index_wallet)?;
-        let secret_key = wallet.signer().to_bytes();
-        let private_key: String = secret_key
-            .iter()
-            .map(|b| format!("{:02X}", b).to_lowercase())
-            .collect();
+        let wallet = load_mnemonic_wallet(
+            &checked_mnemonic,
+            index_wallet,
+            listen_network,
+            Some(&hd_wallet_path_prefix),
+        )?;
+        // remote signer keys never leave the signer
+        let private_key: String = match &wallet {
+            WalletSigner::Local(local_wallet) => local_wallet
+                .signer()
+                .to_bytes()
+                .iter()
+                .map(|b| format!("{:02X}", b).to_lowercase())
+                .collect(),
+            WalletSigner::Remote(_) => "".to_string(),
+        };
         let wallet_info = CheckMnemonicWalletInfo {
             path: hd_wallet_path_prefix.clone() + &index_wallet.to_string(),
             address: wallet.address(),
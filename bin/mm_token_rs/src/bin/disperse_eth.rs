@@ -1,17 +1,29 @@
 use std::{env, sync::Arc};
 
-use ethers::utils::parse_ether;
-use mm_token_rs::{constants::Env, core::WalletService};
+use ethers::{providers::Middleware, types::U256};
+use mm_token_rs::{
+    constants::Env,
+    core::{MessageTransportService, WalletService},
+    types::DisperseEthAmount,
+};
 use mm_token_utils::{
     constants::{DISPERSE_ROUTERS, ZERO_ADDRESS},
     env::get_env,
+    env::load_env,
     log::setup_logger,
 };
 use provider_utils::http_providers::HttpProviders;
+use tokio::sync::RwLock;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenv::dotenv().ok();
+    let result = run().await;
+    MessageTransportService::flush().await;
+    result
+}
+
+async fn run() -> anyhow::Result<()> {
+    load_env();
     setup_logger(None)?;
 
     let args: Vec<String> = env::args().collect();
@@ -26,6 +38,8 @@ async fn main() -> anyhow::Result<()> {
     let env = Env::new();
     let http_provider =
         Arc::new(HttpProviders::get_healthy_provider(&env.listen_network, false).await?);
+    let fetched_gas_price = http_provider.get_gas_price().await?;
+    let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
     let wallet_service = WalletService::new(env.clone(), http_provider);
 
     let disperse_router = *DISPERSE_ROUTERS.get(&env.listen_network).unwrap();
@@ -39,7 +53,7 @@ async fn main() -> anyhow::Result<()> {
     let disperse_eth_private_key = get_env("DISPERSE_ETH_PRIVATE_KEY", None);
     let disperse_eth_mnemonic = get_env("DISPERSE_ETH_MNEMONIC", None);
 
-    let disperse_eth_amount = parse_ether(args[1].parse::<String>().unwrap())?;
+    let disperse_eth_amount: DisperseEthAmount = args[1].parse()?;
     let disperse_eth_wallet_index_from: u32 = args[2].parse().unwrap();
     let disperse_eth_wallet_index_to: u32 = args[3].parse().unwrap();
     if disperse_eth_wallet_index_from > disperse_eth_wallet_index_to {
@@ -49,7 +63,7 @@ async fn main() -> anyhow::Result<()> {
 
     log::info!(
         "Config params:\nDISPERSE_ETH_AMOUNT: {:#?}\nDISPERSE_ETH_WALLET_INDEX_FROM: {:#?}\nDISPERSE_ETH_WALLET_INDEX_TO: {:#?}",
-        args[1].parse::<f64>().unwrap(),
+        args[1],
         disperse_eth_wallet_index_from,
         disperse_eth_wallet_index_to
     );
@@ -62,6 +76,7 @@ async fn main() -> anyhow::Result<()> {
             disperse_router,
             disperse_eth_wallet_index_from,
             disperse_eth_wallet_index_to,
+            gas_price,
         )
         .await?;
     Ok(())
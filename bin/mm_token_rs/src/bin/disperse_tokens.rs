@@ -1,16 +1,28 @@
 use std::{env, sync::Arc};
 
-use mm_token_rs::{constants::Env, core::WalletService};
+use ethers::{providers::Middleware, types::U256};
+use mm_token_rs::{
+    constants::Env,
+    core::{MessageTransportService, WalletService},
+};
 use mm_token_utils::{
     constants::{DISPERSE_ROUTERS, ZERO_ADDRESS},
     env::get_env,
+    env::load_env,
     log::setup_logger,
 };
 use provider_utils::http_providers::HttpProviders;
+use tokio::sync::RwLock;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenv::dotenv().ok();
+    let result = run().await;
+    MessageTransportService::flush().await;
+    result
+}
+
+async fn run() -> anyhow::Result<()> {
+    load_env();
     setup_logger(None)?;
 
     let args: Vec<String> = env::args().collect();
@@ -25,6 +37,8 @@ async fn main() -> anyhow::Result<()> {
     let env = Env::new();
     let http_provider =
         Arc::new(HttpProviders::get_healthy_provider(&env.listen_network, false).await?);
+    let fetched_gas_price = http_provider.get_gas_price().await?;
+    let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
     let wallet_service = WalletService::new(env.clone(), http_provider);
     let disperse_router = *DISPERSE_ROUTERS.get(&env.listen_network).unwrap();
     if disperse_router == *ZERO_ADDRESS {
@@ -70,6 +84,7 @@ async fn main() -> anyhow::Result<()> {
             wallet_index_to,
             disperse_token_amount_min,
             disperse_token_amount_max,
+            gas_price,
         )
         .await?;
 
@@ -1,8 +1,11 @@
 use std::{env, sync::Arc};
 
 use ethers::{providers::Middleware, types::U256};
-use mm_token_rs::{constants::Env, core::WalletService};
-use mm_token_utils::log::setup_logger;
+use mm_token_rs::{
+    constants::Env,
+    core::{DumpGroup, MessageTransportService, WalletService},
+};
+use mm_token_utils::{env::load_env, log::setup_logger};
 use provider_utils::http_providers::HttpProviders;
 use tokio::sync::RwLock;
 
@@ -10,13 +13,19 @@ const DEFAULT_INTERVAL: u32 = 600;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenv::dotenv().ok();
+    let result = run().await;
+    MessageTransportService::flush().await;
+    result
+}
+
+async fn run() -> anyhow::Result<()> {
+    load_env();
     setup_logger(None)?;
 
     let args: Vec<String> = env::args().collect();
-    let (dump_interval_min, dump_interval_max) = if args.len() != 3 {
+    let (dump_interval_min, dump_interval_max) = if args.len() < 3 {
         log::warn!(
-            "Function {} need 2 params: <dump-interval-min> <dump-interval-max>, set config to default 600s",
+            "Function {} need 2 params: <dump-interval-min> <dump-interval-max> [group], set config to default 600s",
             args[0]
         );
 
@@ -28,6 +37,7 @@ async fn main() -> anyhow::Result<()> {
 
         (dump_interval_min, dump_interval_max)
     };
+    let dump_group: DumpGroup = args.get(3).map_or("buyer", String::as_str).parse()?;
 
     let env = Env::new();
     let http_provider =
@@ -36,7 +46,7 @@ async fn main() -> anyhow::Result<()> {
     let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
     let wallet_service = WalletService::new(env, http_provider);
     wallet_service
-        .dump_all(gas_price, dump_interval_min, dump_interval_max)
+        .dump_group(dump_group, gas_price, dump_interval_min, dump_interval_max)
         .await?;
 
     Ok(())
@@ -6,16 +6,22 @@ use std::{
 use ethers::{providers::Middleware, types::U256};
 use mm_token_rs::{
     constants::Env,
-    core::{GasPrice, MevBuyService},
+    core::{GasPrice, MessageTransportService, MevBuyService},
 };
-use mm_token_utils::log::setup_logger;
-use provider_utils::http_providers::HttpProviders;
+use mm_token_utils::{env::load_env, log::setup_logger};
+use provider_utils::{http_providers::HttpProviders, provider_pool::ProviderPool};
 use tokio::{sync::RwLock, task::JoinSet};
 
 #[allow(clippy::never_loop)]
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenv::dotenv().ok();
+    let result = run().await;
+    MessageTransportService::flush().await;
+    result
+}
+
+async fn run() -> anyhow::Result<()> {
+    load_env();
     setup_logger(None)?;
 
     let mut set = JoinSet::new();
@@ -27,24 +33,19 @@ async fn main() -> anyhow::Result<()> {
     let fetched_gas_price = http_provider.get_gas_price().await?;
     let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
 
-    let provider_index: Arc<RwLock<usize>> = Arc::new(RwLock::new(
-        HttpProviders::init_provider_index(&env.listen_network, false).await?,
-    ));
+    let provider_pool = ProviderPool::init(&env.listen_network, false).await?;
     set.spawn(GasPrice::fetch_periodically(
         exit.clone(),
-        env.listen_network,
-        provider_index.clone(),
+        provider_pool.clone(),
         gas_price.clone(),
         Duration::from_secs(3),
     ));
     set.spawn(HttpProviders::fetch_periodically(
-        env.listen_network,
-        false,
         Some(exit.clone()),
-        provider_index.clone(),
+        provider_pool.clone(),
     ));
     set.spawn(async {
-        let sell_service = MevBuyService::new(env, gas_price, provider_index, http_provider);
+        let sell_service = MevBuyService::new(env, gas_price, provider_pool, http_provider);
         sell_service.start().await?;
         Ok(())
     });
@@ -1,9 +1,15 @@
-use mm_token_rs::core::MigrationService;
-use mm_token_utils::log::setup_logger;
+use mm_token_rs::core::{MessageTransportService, MigrationService};
+use mm_token_utils::{env::load_env, log::setup_logger};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenv::dotenv().ok();
+    let result = run().await;
+    MessageTransportService::flush().await;
+    result
+}
+
+async fn run() -> anyhow::Result<()> {
+    load_env();
     setup_logger(None)?;
 
     let migration_service = MigrationService::new();
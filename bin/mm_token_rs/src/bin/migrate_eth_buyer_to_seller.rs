@@ -1,15 +1,26 @@
 use std::sync::Arc;
 
-use mm_token_rs::{constants::Env, core::WalletService};
-use mm_token_utils::log::setup_logger;
+use mm_token_rs::{
+    constants::Env,
+    core::{MessageTransportService, WalletService},
+    utils::register_shutdown,
+};
+use mm_token_utils::{env::load_env, log::setup_logger};
 use provider_utils::http_providers::HttpProviders;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenv::dotenv().ok();
+    let result = run().await;
+    MessageTransportService::flush().await;
+    result
+}
+
+async fn run() -> anyhow::Result<()> {
+    load_env();
     setup_logger(None)?;
 
     let env = Env::new();
+    register_shutdown(&env);
     let http_provider =
         Arc::new(HttpProviders::get_healthy_provider(&env.listen_network, false).await?);
     let wallet_service = WalletService::new(env, http_provider);
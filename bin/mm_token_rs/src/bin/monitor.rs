@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use mm_token_rs::{
+    constants::Env,
+    core::{MessageTransportService, MonitorService},
+};
+use mm_token_utils::{env::load_env, log::setup_logger};
+use provider_utils::http_providers::HttpProviders;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let result = run().await;
+    MessageTransportService::flush().await;
+    result
+}
+
+async fn run() -> anyhow::Result<()> {
+    load_env();
+    setup_logger(None)?;
+    let env = Env::new();
+    let http_provider =
+        Arc::new(HttpProviders::get_healthy_provider(&env.listen_network, false).await?);
+
+    let monitor_service = MonitorService::new(env, http_provider);
+    monitor_service.start().await?;
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use std::{env, sync::Arc};
+
+use ethers::{providers::Middleware, types::U256};
+use mm_token_rs::{
+    constants::Env,
+    core::{MessageTransportService, WalletRole, WalletService},
+};
+use mm_token_utils::{env::load_env, log::setup_logger};
+use provider_utils::http_providers::HttpProviders;
+use tokio::sync::RwLock;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let result = run().await;
+    MessageTransportService::flush().await;
+    result
+}
+
+async fn run() -> anyhow::Result<()> {
+    load_env();
+    setup_logger(None)?;
+
+    let args: Vec<String> = env::args().collect();
+    if args.len() != 2 {
+        log::warn!(
+            "Function {} need 1 param: <WALLET_ROLE> (buyer | auto_buyer | seller | market_maker_<n>)",
+            args[0]
+        );
+        return Ok(());
+    }
+    let role: WalletRole = args[1].parse()?;
+
+    let env = Env::new();
+    let http_provider =
+        Arc::new(HttpProviders::get_healthy_provider(&env.listen_network, false).await?);
+    let fetched_gas_price = http_provider.get_gas_price().await?;
+    let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
+
+    let wallet_service = WalletService::new(env, http_provider);
+    wallet_service.repair_wallets(role, gas_price).await?;
+
+    Ok(())
+}
@@ -1,16 +1,26 @@
-use ethers::{
-    middleware::SignerMiddleware,
-    signers::{LocalWallet, Signer},
-    types::H160,
+use ethers::{middleware::SignerMiddleware, signers::Signer, types::H160};
+use mm_token_rs::{
+    constants::Env,
+    core::{MessageTransportService, WalletService},
+};
+use mm_token_utils::{
+    abi::MemeTokenControllerAbigen,
+    env::{get_env, load_env},
+    log::setup_logger,
+    utils::load_private_key_wallet,
 };
-use mm_token_rs::{constants::Env, core::WalletService};
-use mm_token_utils::{abi::MemeTokenControllerAbigen, env::get_env, log::setup_logger};
 use provider_utils::http_providers::HttpProviders;
 use std::sync::Arc;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    dotenv::dotenv().ok();
+    let result = run().await;
+    MessageTransportService::flush().await;
+    result
+}
+
+async fn run() -> anyhow::Result<()> {
+    load_env();
     setup_logger(None)?;
 
     let env = Env::new();
@@ -19,8 +29,7 @@ async fn main() -> anyhow::Result<()> {
     // let token_contract = MemeTokenAbigen::new(env.token_address, http_provider.clone());
 
     let deployer_private_key = get_env("DEPLOYER_PRIVATE_KEY", Some("".to_string()));
-    let deployer_wallet = deployer_private_key
-        .parse::<LocalWallet>()
+    let deployer_wallet = load_private_key_wallet(&deployer_private_key)
         .unwrap()
         .with_chain_id(env.clone().chain_id.as_u64());
 
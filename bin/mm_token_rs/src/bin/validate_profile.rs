@@ -0,0 +1,42 @@
+use mm_token_utils::{
+    log::setup_logger,
+    profile::{encrypt_profile, get_profile_name, read_profile, validate_profile},
+};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    setup_logger(None)?;
+
+    let Some(profile_name) = get_profile_name() else {
+        panic!("expect --profile NAME");
+    };
+    let is_encrypt = std::env::args().any(|arg| arg == "--encrypt");
+
+    let profile = read_profile(&profile_name)?;
+    let errors = validate_profile(&profile);
+    if !errors.is_empty() {
+        for error in &errors {
+            log::error!("[validate_profile] {}", error);
+        }
+        return Err(anyhow::anyhow!(
+            "profile {:?} has {} invalid fields",
+            profile_name,
+            errors.len()
+        ));
+    }
+    log::info!(
+        "[validate_profile] profile {:?} is valid, {} fields",
+        profile_name,
+        profile.len()
+    );
+
+    if is_encrypt {
+        let encrypted_path = encrypt_profile(&profile_name)?;
+        log::info!(
+            "[validate_profile] encrypted into {:?}, remove the plain profile once checked",
+            encrypted_path
+        );
+    }
+
+    Ok(())
+}
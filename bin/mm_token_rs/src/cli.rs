@@ -0,0 +1,529 @@
+use std::{
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+
+use anyhow::anyhow;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use ethers::{providers::Middleware, types::U256};
+use mm_token_utils::constants::{DISPERSE_ROUTERS, UNISWAP2_ROUTERS, ZERO_ADDRESS};
+use provider_utils::{http_providers::HttpProviders, provider_pool::ProviderPool};
+use tokio::{sync::RwLock, task::JoinSet};
+
+use crate::{
+    constants::Env,
+    core::{
+        DumpGroup, GasPrice, MarketMakerService, MigrationService, SellService, SnipeService,
+        WalletService,
+    },
+    types::DisperseEthAmount,
+    utils::{new_tx_hashes_cache, register_shutdown},
+};
+
+/**
+ * every operational task of the bins behind one binary, the env vars of the bins are the defaults of the flags
+ */
+#[derive(Debug, Parser)]
+#[command(name = "mm_token_rs", version)]
+pub struct Cli {
+    /// campaign profile loaded instead of `.env`
+    #[arg(long, global = true, env = "PROFILE")]
+    pub profile: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// send eth to each buyer wallet of the index range
+    DisperseEth {
+        /// eth sent to each wallet, e.g. 0.05, or a random amount within a range, e.g. 0.04..0.06
+        amount: DisperseEthAmount,
+        #[command(flatten)]
+        range: WalletIndexRange,
+        #[arg(long, env = "DISPERSE_ETH_PRIVATE_KEY", hide_env_values = true)]
+        private_key: String,
+        #[arg(long, env = "DISPERSE_ETH_MNEMONIC", hide_env_values = true)]
+        mnemonic: String,
+    },
+    /// send a random token amount between min and max to each buyer wallet of the index range
+    DisperseTokens {
+        #[command(flatten)]
+        range: WalletIndexRange,
+        amount_min: u128,
+        amount_max: u128,
+        #[arg(long, env = "DISPERSE_TOKEN_PRIVATE_KEY", hide_env_values = true)]
+        private_key: String,
+        #[arg(long, env = "DISPERSE_TOKEN_MNEMONIC", hide_env_values = true)]
+        mnemonic: String,
+    },
+    /// move the tokens or the eth between the wallet groups
+    Migrate {
+        #[arg(value_enum)]
+        kind: MigrateKind,
+    },
+    /// approve the uniswap v2 router to spend the tokens of each seller wallet of the index range
+    Approve {
+        #[command(flatten)]
+        range: WalletIndexRange,
+    },
+    /// sell every token of a wallet group, one wallet per random interval
+    Dump {
+        /// seconds
+        #[arg(default_value_t = 600)]
+        interval_min: u32,
+        /// seconds
+        #[arg(default_value_t = 600)]
+        interval_max: u32,
+        /// buyer, auto_buyer, seller or market_maker
+        #[arg(long, default_value = "buyer")]
+        group: DumpGroup,
+    },
+    /// run the market maker of the token mm config
+    MarketMake,
+    /// sell into the buys of the token
+    AutoSell {
+        #[arg(long, env = "AUTO_SELL_EVENT_LISTEN_ENABLED", action = clap::ArgAction::Set)]
+        event: bool,
+        /// base / blast do not stream the mempool
+        #[arg(long, env = "AUTO_SELL_MEMPOOL_LISTEN_ENABLED", action = clap::ArgAction::Set)]
+        mempool: bool,
+    },
+    /// buy the token as soon as the trading is enabled
+    Snipe,
+}
+
+/// inclusive range of wallet indexes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Args)]
+pub struct WalletIndexRange {
+    pub index_from: u32,
+    pub index_to: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MigrateKind {
+    /// tokens of each buyer wallet to the seller wallet of the same index
+    TokenBuyerToSeller,
+    /// eth of each buyer wallet to the seller wallet of the same index
+    EthBuyerToSeller,
+    /// tokens of every wallet to the migration wallets
+    AllToken,
+    /// eth of every wallet to the migration wallets
+    AllEth,
+}
+
+impl Command {
+    pub async fn run(self) -> anyhow::Result<()> {
+        match self {
+            Command::DisperseEth {
+                amount,
+                range,
+                private_key,
+                mnemonic,
+            } => {
+                range.validate()?;
+                let env = Env::new();
+                let disperse_router = disperse_router(&env)?;
+                let http_provider = Arc::new(
+                    HttpProviders::get_healthy_provider(&env.listen_network, false).await?,
+                );
+                let fetched_gas_price = http_provider.get_gas_price().await?;
+                let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
+                WalletService::new(env, http_provider)
+                    .disperse_eth(
+                        &private_key,
+                        &mnemonic,
+                        amount,
+                        disperse_router,
+                        range.index_from,
+                        range.index_to,
+                        gas_price,
+                    )
+                    .await
+            }
+            Command::DisperseTokens {
+                range,
+                amount_min,
+                amount_max,
+                private_key,
+                mnemonic,
+            } => {
+                range.validate()?;
+                if amount_min > amount_max {
+                    return Err(anyhow!(
+                        "[Cli.run] amount min {} is greater than amount max {}",
+                        amount_min,
+                        amount_max
+                    ));
+                }
+                let env = Env::new();
+                let disperse_router = disperse_router(&env)?;
+                let http_provider = Arc::new(
+                    HttpProviders::get_healthy_provider(&env.listen_network, false).await?,
+                );
+                let fetched_gas_price = http_provider.get_gas_price().await?;
+                let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
+                WalletService::new(env, http_provider)
+                    .disperse_tokens(
+                        disperse_router,
+                        &private_key,
+                        &mnemonic,
+                        range.index_from,
+                        range.index_to,
+                        amount_min,
+                        amount_max,
+                        gas_price,
+                    )
+                    .await
+            }
+            Command::Migrate { kind } => match kind {
+                MigrateKind::AllToken => MigrationService::new().migrate_all_token().await,
+                MigrateKind::AllEth => MigrationService::new().migrate_all_eth().await,
+                MigrateKind::TokenBuyerToSeller | MigrateKind::EthBuyerToSeller => {
+                    let env = Env::new();
+                    register_shutdown(&env);
+                    let http_provider = Arc::new(
+                        HttpProviders::get_healthy_provider(&env.listen_network, false).await?,
+                    );
+                    let wallet_service = WalletService::new(env, http_provider);
+                    if kind == MigrateKind::TokenBuyerToSeller {
+                        wallet_service.migrate_token_buyer_to_seller().await
+                    } else {
+                        wallet_service.migrate_eth_buyer_to_seller().await
+                    }
+                }
+            },
+            Command::Approve { range } => {
+                range.validate()?;
+                let env = Env::new();
+                let Some(uniswapv2_router_address) = UNISWAP2_ROUTERS.get(&env.listen_network)
+                else {
+                    return Err(anyhow!(
+                        "[Cli.run] UNISWAP2_ROUTERS not found in {:?}",
+                        env.listen_network
+                    ));
+                };
+                let http_provider = Arc::new(
+                    HttpProviders::get_healthy_provider(&env.listen_network, false).await?,
+                );
+                WalletService::new(env, http_provider)
+                    .approve_max_to_seller(
+                        uniswapv2_router_address,
+                        range.index_from,
+                        range.index_to,
+                    )
+                    .await
+            }
+            Command::Dump {
+                interval_min,
+                interval_max,
+                group,
+            } => {
+                let env = Env::new();
+                let http_provider = Arc::new(
+                    HttpProviders::get_healthy_provider(&env.listen_network, false).await?,
+                );
+                let fetched_gas_price = http_provider.get_gas_price().await?;
+                let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
+                WalletService::new(env, http_provider)
+                    .dump_group(group, gas_price, interval_min, interval_max)
+                    .await
+            }
+            Command::MarketMake => {
+                let env = Env::new();
+                register_shutdown(&env);
+                let http_provider = Arc::new(
+                    HttpProviders::get_healthy_provider(&env.listen_network, false).await?,
+                );
+                let fetched_gas_price = http_provider.get_gas_price().await?;
+                let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
+                let provider_pool = ProviderPool::init(&env.listen_network, false).await?;
+                MarketMakerService::new(env, gas_price, provider_pool, http_provider)
+                    .market_make()
+                    .await
+            }
+            Command::AutoSell { event, mempool } => auto_sell(event, mempool).await,
+            Command::Snipe => snipe().await,
+        }
+    }
+}
+
+impl WalletIndexRange {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.index_from > self.index_to {
+            return Err(anyhow!(
+                "[WalletIndexRange.validate] index from {} is greater than index to {}",
+                self.index_from,
+                self.index_to
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn disperse_router(env: &Env) -> anyhow::Result<ethers::types::Address> {
+    match DISPERSE_ROUTERS.get(&env.listen_network) {
+        Some(disperse_router) if disperse_router != &*ZERO_ADDRESS => Ok(*disperse_router),
+        _ => Err(anyhow!(
+            "[Cli.run] please config disperse router for {:?} network",
+            env.listen_network
+        )),
+    }
+}
+
+/**
+ * sell services of the event and / or mempool mode, with the gas price and provider index refreshed in the background
+ */
+pub async fn auto_sell(
+    event_listen_enabled: bool,
+    mempool_listen_enabled: bool,
+) -> anyhow::Result<()> {
+    let mut set = JoinSet::new();
+    let env = Env::new();
+    let exit = env.exit.clone();
+    let http_provider =
+        Arc::new(HttpProviders::get_healthy_provider(&env.listen_network, false).await?);
+
+    let fetched_gas_price = http_provider.get_gas_price().await?;
+    let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
+    let provider_pool = ProviderPool::init(&env.listen_network, false).await?;
+    set.spawn(GasPrice::fetch_periodically(
+        exit.clone(),
+        provider_pool.clone(),
+        gas_price.clone(),
+        Duration::from_secs(3),
+    ));
+    set.spawn(HttpProviders::fetch_periodically(
+        Some(exit.clone()),
+        provider_pool.clone(),
+    ));
+
+    let tx_hashes_cache = new_tx_hashes_cache();
+
+    if event_listen_enabled {
+        let env = env.clone();
+        let gas_price = gas_price.clone();
+        let provider_pool = provider_pool.clone();
+        let http_provider = http_provider.clone();
+        let tx_hashes_cache_clone = tx_hashes_cache.clone();
+        set.spawn(async {
+            let mut sell_service = SellService::new(env, gas_price, provider_pool, http_provider);
+            sell_service.init().await?;
+            sell_service.start_event_mode(tx_hashes_cache_clone).await?;
+            Ok(())
+        });
+    }
+
+    // NOTE: base/blast not support stream mempool
+    if mempool_listen_enabled {
+        let env = env.clone();
+        let gas_price = gas_price.clone();
+        let provider_pool = provider_pool.clone();
+        let http_provider = http_provider.clone();
+        let tx_hashes_cache_clone = tx_hashes_cache.clone();
+
+        set.spawn(async {
+            let mut sell_service = SellService::new(env, gas_price, provider_pool, http_provider);
+            sell_service.init().await?;
+            sell_service
+                .start_mempool_mode(tx_hashes_cache_clone)
+                .await?;
+            Ok(())
+        });
+    }
+
+    while let Some(res) = set.join_next().await {
+        log::error!("program exited, res {:?}", res);
+        // gracefully shutdown
+        exit.store(true, Ordering::Relaxed);
+    }
+
+    Ok(())
+}
+
+/**
+ * snipe service of the event mode, with the gas price and provider index refreshed in the background
+ */
+pub async fn snipe() -> anyhow::Result<()> {
+    let mut set = JoinSet::new();
+    let env = Env::new();
+    let exit = env.exit.clone();
+    let http_provider =
+        Arc::new(HttpProviders::get_healthy_provider(&env.listen_network, false).await?);
+    let fetched_gas_price = http_provider.get_gas_price().await?;
+    let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
+
+    let provider_pool = ProviderPool::init(&env.listen_network, false).await?;
+    set.spawn(GasPrice::fetch_periodically(
+        exit.clone(),
+        provider_pool.clone(),
+        gas_price.clone(),
+        Duration::from_secs(3),
+    ));
+    set.spawn(HttpProviders::fetch_periodically(
+        Some(exit.clone()),
+        provider_pool.clone(),
+    ));
+    set.spawn(async {
+        let mut snipe_service = SnipeService::new(env, gas_price, provider_pool, http_provider);
+        snipe_service.init().await?;
+        snipe_service.start_event_mode().await?;
+        Ok(())
+    });
+
+    while let Some(res) = set.join_next().await {
+        log::error!("program exited, res {:?}", res);
+        // gracefully shutdown
+        exit.store(true, Ordering::Relaxed);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::utils::parse_ether;
+
+    fn parse(args: &[&str]) -> Command {
+        Cli::try_parse_from(["mm_token_rs"].iter().chain(args))
+            .unwrap()
+            .command
+    }
+
+    #[test]
+    fn parse_wallet_subcommands() {
+        match parse(&[
+            "disperse-eth",
+            "0.05",
+            "2",
+            "9",
+            "--private-key",
+            "0x01",
+            "--mnemonic",
+            "",
+        ]) {
+            Command::DisperseEth {
+                amount,
+                range,
+                private_key,
+                ..
+            } => {
+                assert_eq!(
+                    amount,
+                    DisperseEthAmount::Uniform(parse_ether("0.05").unwrap())
+                );
+                assert_eq!(
+                    range,
+                    WalletIndexRange {
+                        index_from: 2,
+                        index_to: 9
+                    }
+                );
+                assert_eq!(private_key, "0x01");
+            }
+            command => panic!("unexpected {:?}", command),
+        }
+        assert!(matches!(
+            parse(&[
+                "disperse-eth",
+                "0.04..0.06",
+                "0",
+                "1",
+                "--private-key",
+                "",
+                "--mnemonic",
+                ""
+            ]),
+            Command::DisperseEth {
+                amount: DisperseEthAmount::Random { .. },
+                ..
+            }
+        ));
+        assert!(Cli::try_parse_from([
+            "mm_token_rs",
+            "disperse-eth",
+            "abc",
+            "0",
+            "1",
+            "--private-key",
+            "",
+            "--mnemonic",
+            ""
+        ])
+        .is_err());
+
+        match parse(&[
+            "disperse-tokens",
+            "0",
+            "4",
+            "100",
+            "200",
+            "--private-key",
+            "",
+            "--mnemonic",
+            "test test",
+        ]) {
+            Command::DisperseTokens {
+                range,
+                amount_min,
+                amount_max,
+                mnemonic,
+                ..
+            } => {
+                assert_eq!((range.index_from, range.index_to), (0, 4));
+                assert_eq!((amount_min, amount_max), (100, 200));
+                assert_eq!(mnemonic, "test test");
+            }
+            command => panic!("unexpected {:?}", command),
+        }
+
+        assert!(matches!(
+            parse(&["migrate", "eth-buyer-to-seller"]),
+            Command::Migrate {
+                kind: MigrateKind::EthBuyerToSeller
+            }
+        ));
+        assert!(Cli::try_parse_from(["mm_token_rs", "migrate", "everything"]).is_err());
+
+        match parse(&["approve", "3", "1"]) {
+            Command::Approve { range } => assert!(range.validate().is_err()),
+            command => panic!("unexpected {:?}", command),
+        }
+
+        assert!(matches!(
+            parse(&["dump"]),
+            Command::Dump {
+                interval_min: 600,
+                interval_max: 600,
+                group: DumpGroup::Buyer
+            }
+        ));
+        assert!(matches!(
+            parse(&["dump", "60", "120", "--group", "auto_buyer"]),
+            Command::Dump {
+                interval_min: 60,
+                interval_max: 120,
+                group: DumpGroup::AutoBuyer
+            }
+        ));
+    }
+
+    #[test]
+    fn parse_bot_subcommands() {
+        assert!(matches!(parse(&["market-make"]), Command::MarketMake));
+        assert!(matches!(
+            parse(&["snipe", "--profile", "launch"]),
+            Command::Snipe
+        ));
+        assert!(matches!(
+            parse(&["auto-sell", "--event", "true", "--mempool", "false"]),
+            Command::AutoSell {
+                event: true,
+                mempool: false
+            }
+        ));
+        assert!(Cli::try_parse_from(["mm_token_rs"]).is_err());
+    }
+}
@@ -6,13 +6,217 @@ use std::{
 use ethers::types::{Address, U64};
 use mm_token_utils::env::get_env;
 use provider_utils::{enums::ENetwork, networks::NETWORKS};
+use serde::{Deserialize, Serialize};
+
+// bounded queue between mempool ingestion and its worker pool
+pub const MEMPOOL_QUEUE_SIZE: usize = 1024;
+
+// where buy/sell tax is read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TokenTaxMode {
+    #[default]
+    Env,
+    Contract,
+}
+
+impl FromStr for TokenTaxMode {
+    type Err = anyhow::Error;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "Env" => Ok(TokenTaxMode::Env),
+            "Contract" => Ok(TokenTaxMode::Contract),
+            _ => Err(anyhow::anyhow!("invalid TOKEN_TAX_MODE {:?}", mode)),
+        }
+    }
+}
+
+// how an auto buy amount is split over the auto buyer wallets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EBuyDistribution {
+    // drain the wallets fitting the amount, the rest on one random wallet
+    #[default]
+    Greedy,
+    // same amount on every wallet, capped by its spendable balance
+    EvenSplit,
+    // random fractions of the amount, capped by the spendable balance
+    RandomWeighted,
+}
+
+impl FromStr for EBuyDistribution {
+    type Err = anyhow::Error;
+
+    fn from_str(distribution: &str) -> Result<Self, Self::Err> {
+        match distribution {
+            "Greedy" => Ok(EBuyDistribution::Greedy),
+            "EvenSplit" => Ok(EBuyDistribution::EvenSplit),
+            "RandomWeighted" => Ok(EBuyDistribution::RandomWeighted),
+            _ => Err(anyhow::anyhow!(
+                "invalid AUTO_BUY_DISTRIBUTION {:?}",
+                distribution
+            )),
+        }
+    }
+}
+
+// which uniswapv2 swap method is used for the token
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SwapMethodMode {
+    // detect fee on transfer tokens by simulation, cached per token
+    #[default]
+    Auto,
+    FeeOnTransfer,
+    Standard,
+}
+
+impl FromStr for SwapMethodMode {
+    type Err = anyhow::Error;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "Auto" => Ok(SwapMethodMode::Auto),
+            "FeeOnTransfer" => Ok(SwapMethodMode::FeeOnTransfer),
+            "Standard" => Ok(SwapMethodMode::Standard),
+            _ => Err(anyhow::anyhow!("invalid SWAP_METHOD_MODE {:?}", mode)),
+        }
+    }
+}
+
+// which pool version the universal router swaps through
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UniversalSwapVersion {
+    #[default]
+    V2,
+    V3,
+}
+
+impl FromStr for UniversalSwapVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        match version {
+            "V2" => Ok(UniversalSwapVersion::V2),
+            "V3" => Ok(UniversalSwapVersion::V3),
+            _ => Err(anyhow::anyhow!(
+                "invalid UNIVERSAL_SWAP_VERSION {:?}",
+                version
+            )),
+        }
+    }
+}
+
+// how `Uniswap3Service` picks the v3 pool among the fee tiers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum V3PoolSelection {
+    // first tier of V3_FEE_TIER_PREFERENCE whose pool passes the min liquidity check
+    #[default]
+    Preference,
+    // best quote of the simulate amount among the pools passing the min liquidity check
+    BestQuote,
+}
+
+impl FromStr for V3PoolSelection {
+    type Err = anyhow::Error;
+
+    fn from_str(selection: &str) -> Result<Self, Self::Err> {
+        match selection {
+            "Preference" => Ok(V3PoolSelection::Preference),
+            "BestQuote" => Ok(V3PoolSelection::BestQuote),
+            _ => Err(anyhow::anyhow!("invalid V3_POOL_SELECTION {:?}", selection)),
+        }
+    }
+}
+
+// how the active trading tx and the first buys are submitted at launch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LaunchOrdering {
+    // sent concurrently, positions and blocks are arbitrary
+    #[default]
+    Concurrent,
+    // sent as a single bundle: active trading first, then buys by wallet index
+    Bundle,
+}
+
+impl FromStr for LaunchOrdering {
+    type Err = anyhow::Error;
+
+    fn from_str(ordering: &str) -> Result<Self, Self::Err> {
+        match ordering {
+            "Concurrent" => Ok(LaunchOrdering::Concurrent),
+            "Bundle" => Ok(LaunchOrdering::Bundle),
+            _ => Err(anyhow::anyhow!("invalid LAUNCH_ORDERING {:?}", ordering)),
+        }
+    }
+}
+
+// per token settings, one entry per token of the `TOKENS` json array
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenConfig {
+    pub address: Address,
+    #[serde(default)]
+    pub buy_tax: f32,
+    #[serde(default)]
+    pub sell_tax: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub floor_price: Option<f64>,
+    // ACTIVE_ROUTER of the token, the ACTIVE_ROUTER env when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub router: Option<String>,
+    // mm config file of the token, groups of different tokens must not share mnemonics
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mm_config_path: Option<String>,
+}
+
+impl TokenConfig {
+    /// `TOKENS` when set, otherwise a single token from TOKEN_ADDRESS / TOKEN_BUY_TAX / TOKEN_SELL_TAX / FLOOR_PRICE
+    pub fn from_env() -> anyhow::Result<Vec<Self>> {
+        let tokens = get_env("TOKENS", Some("".to_string()));
+        if !tokens.trim().is_empty() {
+            return Self::parse_tokens(&tokens);
+        }
+
+        let floor_price = get_env("FLOOR_PRICE", Some("".to_string()));
+        Ok(vec![Self {
+            address: Address::from_str(&get_env("TOKEN_ADDRESS", None))?,
+            buy_tax: get_env("TOKEN_BUY_TAX", Some("0".to_string()))
+                .parse()
+                .unwrap_or(0.0),
+            sell_tax: get_env("TOKEN_SELL_TAX", Some("0".to_string()))
+                .parse()
+                .unwrap_or(0.0),
+            floor_price: if floor_price.is_empty() {
+                None
+            } else {
+                Some(floor_price.parse()?)
+            },
+            router: None,
+            mm_config_path: None,
+        }])
+    }
+
+    pub fn parse_tokens(tokens: &str) -> anyhow::Result<Vec<Self>> {
+        let tokens: Vec<Self> = serde_json::from_str(tokens)
+            .map_err(|err| anyhow::anyhow!("invalid TOKENS {:?}", err))?;
+        if tokens.is_empty() {
+            return Err(anyhow::anyhow!("TOKENS is empty"));
+        }
+        Ok(tokens)
+    }
+}
 
 #[derive(Debug, Clone, Default)]
 pub struct Env {
     pub listen_network: ENetwork,
     pub chain_id: U64,
+    // address of `token`, the token services of this env trade
     pub token_address: Address,
+    pub token: TokenConfig,
+    // every configured token, `token` is the first one unless `for_token` picked another
+    pub tokens: Vec<TokenConfig>,
     pub exit: Arc<AtomicBool>,
+    // simulate trades with eth_call instead of broadcasting them
+    pub dry_run: bool,
 }
 
 impl Env {
@@ -25,13 +229,36 @@ impl Env {
             panic!("NETWORKS {:?} not found", listen_network);
         };
 
-        let token_address = Address::from_str(&get_env("TOKEN_ADDRESS", None)).unwrap();
+        let tokens = TokenConfig::from_env().unwrap();
+        let dry_run: bool = get_env("DRY_RUN", Some("false".to_string()))
+            .parse()
+            .unwrap();
 
         Self {
             listen_network,
             chain_id: U64::from(network_config.chain_id),
-            token_address,
+            token_address: tokens[0].address,
+            token: tokens[0].clone(),
+            tokens,
             exit: Arc::new(AtomicBool::new(false)),
+            dry_run,
         }
     }
+
+    /// same network, exit flag and flags, trading `token`
+    pub fn for_token(&self, token: &TokenConfig) -> Self {
+        Self {
+            token_address: token.address,
+            token: token.clone(),
+            ..self.clone()
+        }
+    }
+
+    /// one env per configured token
+    pub fn token_envs(&self) -> Vec<Self> {
+        self.tokens
+            .iter()
+            .map(|token| self.for_token(token))
+            .collect()
+    }
 }
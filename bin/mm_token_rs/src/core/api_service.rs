@@ -1,26 +1,44 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    future::Future,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
 
 use crate::{
+    routers::{fetch_pool_state, fetch_v3_pool_liquidity, RouterService},
     types::*,
-    utils::{compute_system_wallets, get_mm_config},
+    utils::{
+        compute_system_wallets, fetch_token_info, format_token_amount, get_launched_at,
+        get_mm_config, get_native_symbol, save_launched_at, PnlLedger, PriceOracle,
+    },
 };
+use chrono::Utc;
 use ethers::{
     providers::{Http, Middleware, Provider},
     types::{Address, U256},
-    utils::{format_units, parse_ether},
+    utils::{format_units, parse_ether, parse_units},
 };
 use mm_token_utils::{
-    abi::{IUniswapV2PairAbigen, MemeTokenAbigen},
+    abi::{IUniswapV2PairAbigen, MemeTokenAbigen, UniswapV3PoolAbigen},
     constants::{
-        Erc20Details, AVABOT_ROUTERS, UNISWAP2_ROUTERS, WRAPPED_NATIVE_TOKENS, ZERO_ADDRESS,
+        ERouter, Erc20Details, AVABOT_ROUTERS, UNISWAP2_ROUTERS, WRAPPED_NATIVE_TOKENS,
+        ZERO_ADDRESS,
     },
     env::get_env,
 };
 use provider_utils::http_providers::HttpProviders;
+use tokio::sync::RwLock;
 
-use crate::constants::Env;
+use crate::constants::{Env, LaunchOrdering};
 
-use super::LaunchingProcessService;
+use super::{
+    DumpGroup, LaunchingProcessService, MessageTransportService, PhaseReport, WalletRole,
+    WalletService,
+};
 
 #[derive(Debug, Clone)]
 pub struct ApiService {
@@ -29,6 +47,7 @@ pub struct ApiService {
     pub uniswapv2_router_address: Address,
     pub avabot_router_address: Address,
     pub weth: Erc20Details,
+    pub price_oracle: PriceOracle,
     buyer_mnemonic: String,
     buyer_surplus_balance: U256,
     buyer_wallets_count: u32,
@@ -38,6 +57,13 @@ pub struct ApiService {
     seller_wallets_count: u32,
     auto_sell_min_percent: u32,
     auto_sell_max_percent: u32,
+    // spenders sellers must approve besides the active router
+    seller_approval_extra_spenders: Vec<Address>,
+    // held while a launch_process call runs, clones share it
+    launch_lock: Arc<AtomicBool>,
+    launch_state_path: String,
+    // held by the background dump, a second one is rejected until it is done
+    dump_lock: Arc<AtomicBool>,
 }
 
 #[warn(unused_variables)]
@@ -48,6 +74,11 @@ impl ApiService {
         else {
             panic!("[ApiService] http_provider not found");
         };
+        Self::new_with_provider(env, Arc::new(http_provider))
+    }
+
+    /// `http_provider` instead of the first rpc of the network
+    pub fn new_with_provider(env: Env, http_provider: Arc<Provider<Http>>) -> Self {
         let Some(avabot_router_address) = AVABOT_ROUTERS.get(&env.listen_network) else {
             panic!("AVABOT_ROUTERS not found in {:?}", env.listen_network);
         };
@@ -60,12 +91,29 @@ impl ApiService {
                 env.listen_network
             );
         };
+        let price_oracle = PriceOracle::from_env(&env.listen_network, http_provider.clone());
+        // parsed once, a bad address fails the startup instead of the checklist request
+        let seller_approval_extra_spenders =
+            get_env("SELLER_APPROVAL_EXTRA_SPENDERS", Some("".to_string()))
+                .split(',')
+                .map(str::trim)
+                .filter(|spender| !spender.is_empty())
+                .map(|spender| {
+                    Address::from_str(spender).unwrap_or_else(|err| {
+                        panic!(
+                            "SELLER_APPROVAL_EXTRA_SPENDERS {:?} invalid: {:?}",
+                            spender, err
+                        )
+                    })
+                })
+                .collect();
         Self {
             env,
-            http_provider: Arc::new(http_provider),
+            http_provider,
             uniswapv2_router_address: *uniswapv2_router_address,
             avabot_router_address: *avabot_router_address,
             weth: weth.clone(),
+            price_oracle,
             buyer_mnemonic: get_env("BUYER_MNEMONIC", None),
             buyer_surplus_balance: parse_ether(get_env("BUYER_SURPLUS_BALANCE", None)).unwrap(),
             buyer_wallets_count: get_env("BUYER_WALLETS_COUNT", None).parse().unwrap(),
@@ -75,12 +123,16 @@ impl ApiService {
             seller_wallets_count: get_env("SELLER_WALLETS_COUNT", None).parse().unwrap(),
             auto_sell_min_percent: get_env("AUTO_SELL_MIN_PERCENT", None).parse().unwrap(),
             auto_sell_max_percent: get_env("AUTO_SELL_MAX_PERCENT", None).parse().unwrap(),
+            seller_approval_extra_spenders,
+            launch_lock: Arc::new(AtomicBool::new(false)),
+            launch_state_path: get_env("LAUNCH_STATE_PATH", Some("launch_state.json".to_string())),
+            dump_lock: Arc::new(AtomicBool::new(false)),
         }
     }
 
-    pub async fn get_network_status(&self) -> NetworkStatus {
+    pub async fn get_network_status(&self) -> anyhow::Result<NetworkStatus> {
         let network_str = get_env("LISTEN_NETWORK", None);
-        let current_block_number = self.http_provider.get_block_number().await.unwrap();
+        let current_block_number = self.http_provider.get_block_number().await?;
         let Some(weth) = WRAPPED_NATIVE_TOKENS.get(&self.env.listen_network) else {
             panic!(
                 "WRAPPED_NATIVE_TOKENS not found in {:?}",
@@ -88,14 +140,11 @@ impl ApiService {
             );
         };
 
-        let token_info_call =
-            MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
-        let token_symbol: String = token_info_call.symbol().call().await.unwrap();
-        let token_name: String = token_info_call.name().call().await.unwrap();
-        let token_decimals: u8 = token_info_call.decimals().call().await.unwrap();
-        let token_total_supply: U256 = token_info_call.total_supply().call().await.unwrap();
+        let token_info =
+            fetch_token_info(self.env.token_address, self.http_provider.clone()).await?;
+        let (pair_contract, pair_fee_tier) = self.get_pair_info(weth.address).await;
 
-        NetworkStatus {
+        Ok(NetworkStatus {
             network: NetworkStatusNetworkInfo {
                 name: network_str,
                 chain_id: self.env.chain_id.as_u64(),
@@ -104,25 +153,28 @@ impl ApiService {
             token: NetworkStatusTokenInfo {
                 address: self.env.token_address,
                 is_deployed: true,
-                symbol: token_symbol,
-                name: token_name,
-                decimals: token_decimals,
-                total_supply: (token_total_supply / U256::exp10(token_decimals as usize)).as_u128(),
+                symbol: token_info.symbol,
+                name: token_info.name,
+                decimals: token_info.decimals,
+                total_supply: (token_info.total_supply / U256::exp10(token_info.decimals as usize))
+                    .as_u128(),
                 token_template: TokenTemplate::BaseMemeTokenV1,
                 router_contract: self.uniswapv2_router_address,
-                pair_contract: *ZERO_ADDRESS, // TODO: pair address ?
+                pair_contract,
+                pair_fee_tier,
                 weth: weth.address,
             },
             router: NetworkStatusRouterInfo {
                 avabot: self.avabot_router_address,
             },
-        }
+        })
     }
 
     pub async fn get_deployment_checklist(&self) -> DeploymentChecklist {
         let buyer_system_wallets = compute_system_wallets(
             &self.buyer_mnemonic,
             self.buyer_wallets_count,
+            self.env.listen_network,
             &self.env.token_address,
             self.http_provider.clone(),
         )
@@ -153,6 +205,7 @@ impl ApiService {
         let seller_system_wallets = compute_system_wallets(
             &self.seller_mnemonic,
             self.seller_wallets_count,
+            self.env.listen_network,
             &self.env.token_address,
             self.http_provider.clone(),
         )
@@ -163,16 +216,29 @@ impl ApiService {
         let mut seller_approval_status = true;
         let mut seller_approval: String = "".to_string();
 
+        // sellers must approve the active router, plus any extra configured spender
+        let mut spenders = match self.get_active_router_address() {
+            Ok(active_router_address) => vec![active_router_address],
+            Err(err) => {
+                log::warn!("[ApiService] {:?}, check uniswapv2 router instead", err);
+                vec![self.uniswapv2_router_address]
+            }
+        };
+        spenders.extend(self.seller_approval_extra_spenders.iter().copied());
+
         for (wallet_address, _wallet) in seller_system_wallets {
-            let allowance = uniswapv2_pair
-                .allowance(wallet_address, self.uniswapv2_router_address)
-                .call()
-                .await
-                .unwrap();
+            for spender in spenders.iter() {
+                let allowance = uniswapv2_pair
+                    .allowance(wallet_address, *spender)
+                    .call()
+                    .await
+                    .unwrap();
 
-            if allowance == U256::zero() {
-                seller_approval_status = false;
-                seller_approval += &(wallet_address.to_string() + ", ");
+                if allowance == U256::zero() {
+                    seller_approval_status = false;
+                    seller_approval += &(wallet_address.to_string() + ", ");
+                    break;
+                }
             }
         }
         let mut seller_approval_info = "".to_string();
@@ -181,11 +247,7 @@ impl ApiService {
                 "Address ".to_owned() + &seller_approval + "have not been approved yet";
         }
 
-        // TODO: update later
-        let (reserve0, _reserve1, _): (u128, u128, u32) =
-            uniswapv2_pair.get_reserves().call().await.unwrap();
-
-        let liquidity_added_status: bool = reserve0 == 0;
+        let liquidity_added = self.get_liquidity_added().await;
 
         DeploymentChecklist {
             token_deployed: TokenDeployed { status: true },
@@ -201,11 +263,113 @@ impl ApiService {
                 status: seller_approval_status,
                 info: seller_approval_info,
             },
-            liquidity_added: LiquidityAdded {
-                status: liquidity_added_status,
-                info: "".to_string(),
-            },
+            liquidity_added,
+        }
+    }
+
+    /// token and weth depth of the active router pair, a v3 pool (with a fee tier) reads its in range liquidity
+    async fn get_liquidity_added(&self) -> LiquidityAdded {
+        let (pair_address, pair_fee_tier) = self.get_pair_info(self.weth.address).await;
+        if pair_address == *ZERO_ADDRESS {
+            return LiquidityAdded {
+                status: false,
+                info: "pair not created".to_string(),
+            };
+        }
+
+        let reserves = match pair_fee_tier {
+            Some(_) => {
+                fetch_v3_pool_liquidity(pair_address, self.weth.address, self.http_provider.clone())
+                    .await
+            }
+            None => self.get_v2_reserves(pair_address).await,
+        };
+        let (token_amount, weth_amount) = match reserves {
+            Ok(reserves) => reserves,
+            Err(err) => {
+                log::warn!("[ApiService.get_liquidity_added] {:?}", err);
+                return LiquidityAdded {
+                    status: false,
+                    info: format!("reserves of {:?} not read: {}", pair_address, err),
+                };
+            }
+        };
+        let token_decimals: u8 =
+            MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone())
+                .decimals()
+                .call()
+                .await
+                .unwrap_or(18);
+
+        LiquidityAdded {
+            status: !token_amount.is_zero() && !weth_amount.is_zero(),
+            info: format!(
+                "{} token / {} {}",
+                format_units(token_amount, token_decimals as usize).unwrap_or_default(),
+                format_units(weth_amount, self.weth.decimals as usize).unwrap_or_default(),
+                self.weth.symbol
+            ),
+        }
+    }
+
+    /// (token reserve, weth reserve) of a uniswapv2 pair
+    async fn get_v2_reserves(&self, pair_address: Address) -> anyhow::Result<(U256, U256)> {
+        let uniswapv2_pair = IUniswapV2PairAbigen::new(pair_address, self.http_provider.clone());
+        let (reserve0, reserve1, _): (u128, u128, u32) =
+            uniswapv2_pair.get_reserves().call().await?;
+        let token0: Address = uniswapv2_pair.token_0().call().await?;
+        if token0 == self.weth.address {
+            return Ok((U256::from(reserve1), U256::from(reserve0)));
+        }
+
+        Ok((U256::from(reserve0), U256::from(reserve1)))
+    }
+
+    /// pair of the active router, zero address until the pair is created
+    async fn get_pair_info(&self, weth_address: Address) -> (Address, Option<u32>) {
+        let router_service = RouterService::new(
+            self.env.clone(),
+            Arc::new(RwLock::new(U256::zero())),
+            self.http_provider.clone(),
+        );
+        let pair_address = match router_service
+            .get_pair_address(&self.env.token_address, &weth_address, false)
+            .await
+        {
+            Ok((pair_address, _)) => pair_address,
+            Err(err) => {
+                log::warn!("[ApiService.get_pair_info] pair not found: {:?}", err);
+                return (*ZERO_ADDRESS, None);
+            }
+        };
+        // aerodrome pools keep v2 style reserves
+        if pair_address == *ZERO_ADDRESS
+            || matches!(
+                router_service.active_router,
+                ERouter::Uniswap2Routers | ERouter::Aerodrome
+            )
+        {
+            return (pair_address, None);
+        }
+
+        // universal router may trade a v2 pair, which has no fee getter
+        let uniswapv3_pool = UniswapV3PoolAbigen::new(pair_address, self.http_provider.clone());
+        let pair_fee_tier: Option<u32> = uniswapv3_pool.fee().call().await.ok();
+
+        (pair_address, pair_fee_tier)
+    }
+
+    fn get_active_router_address(&self) -> anyhow::Result<Address> {
+        let router_service = RouterService::new(
+            self.env.clone(),
+            Arc::new(RwLock::new(U256::zero())),
+            self.http_provider.clone(),
+        );
+        if router_service.active_router == ERouter::UniversalRouters {
+            return Err(anyhow::anyhow!("UNIVERSAL_ROUTERS not supported"));
         }
+
+        router_service.get_router_address()
     }
 
     pub async fn get_deployer(&self) -> Deployer {
@@ -234,6 +398,7 @@ impl ApiService {
         let buyer_system_wallets = compute_system_wallets(
             &self.buyer_mnemonic,
             self.buyer_wallets_count,
+            self.env.listen_network,
             &self.env.token_address,
             self.http_provider.clone(),
         )
@@ -258,12 +423,7 @@ impl ApiService {
                 address: *wallet_address,
                 balance: format_units(wallet_context.eth_balance, self.weth.decimals as usize)
                     .expect("Failed to format units"),
-                token_balance: format_units(
-                    wallet_context.token_balance,
-                    (token_decimals + 6) as usize,
-                )
-                .expect("Failed to format units")
-                    + "M",
+                token_balance: format_token_amount(wallet_context.token_balance, token_decimals),
             };
             total_balance += wallet_context.eth_balance;
             total_token_balance += wallet_context.token_balance;
@@ -277,12 +437,9 @@ impl ApiService {
             status: BuyersStatus {
                 total_balance: format_units(total_balance, self.weth.decimals as usize)
                     .expect("Failed to format units"),
-                total_token_balance: format_units(
-                    total_token_balance,
-                    (token_decimals + 9) as usize,
-                )
-                .expect("Failed to format units")
-                    + "B",
+                total_token_balance: format_token_amount(total_token_balance, token_decimals),
+                raw_total_balance: total_balance,
+                raw_total_token_balance: total_token_balance,
             },
             list: list_wallets_info,
         }
@@ -292,6 +449,7 @@ impl ApiService {
         let buyer_system_wallets = compute_system_wallets(
             &self.auto_buyer_mnemonic,
             self.auto_buyer_wallets_count,
+            self.env.listen_network,
             &self.env.token_address,
             self.http_provider.clone(),
         )
@@ -316,12 +474,7 @@ impl ApiService {
                 address: *wallet_address,
                 balance: format_units(wallet_context.eth_balance, self.weth.decimals as usize)
                     .expect("Failed to format units"),
-                token_balance: format_units(
-                    wallet_context.token_balance,
-                    (token_decimals + 6) as usize,
-                )
-                .expect("Failed to format units")
-                    + "M",
+                token_balance: format_token_amount(wallet_context.token_balance, token_decimals),
             };
             total_balance += wallet_context.eth_balance;
             total_token_balance += wallet_context.token_balance;
@@ -335,12 +488,9 @@ impl ApiService {
             status: BuyersStatus {
                 total_balance: format_units(total_balance, self.weth.decimals as usize)
                     .expect("Failed to format units"),
-                total_token_balance: format_units(
-                    total_token_balance,
-                    (token_decimals + 6) as usize,
-                )
-                .expect("Failed to format units")
-                    + "M",
+                total_token_balance: format_token_amount(total_token_balance, token_decimals),
+                raw_total_balance: total_balance,
+                raw_total_token_balance: total_token_balance,
             },
             list: list_wallets_info,
         }
@@ -361,6 +511,7 @@ impl ApiService {
         let seller_system_wallets = compute_system_wallets(
             &self.seller_mnemonic,
             self.seller_wallets_count,
+            self.env.listen_network,
             &self.env.token_address,
             self.http_provider.clone(),
         )
@@ -392,22 +543,10 @@ impl ApiService {
                 address: wallet_address,
                 balance: format_units(wallet_context.eth_balance, weth.decimals as usize)
                     .expect("Failed to format units"),
-                token_balance: format_units(
-                    wallet_context.token_balance,
-                    (token_decimals + 6) as usize,
-                )
-                .expect("Failed to format units")
-                    + "M",
+                token_balance: format_token_amount(wallet_context.token_balance, token_decimals),
                 approvals: ApprovalsSellers {
-                    token_router: format_units(
-                        allowance_uniswapv2_router,
-                        (weth.decimals + 12) as usize,
-                    )
-                    .expect("Failed to format units")
-                        + "T",
-                    ava_router: format_units(allowance_ava_router, (weth.decimals + 12) as usize)
-                        .expect("Failed to format units")
-                        + "T",
+                    token_router: format_token_amount(allowance_uniswapv2_router, token_decimals),
+                    ava_router: format_token_amount(allowance_ava_router, token_decimals),
                 },
             };
             total_balance += wallet_context.eth_balance;
@@ -424,12 +563,9 @@ impl ApiService {
             status: SellersStatus {
                 total_balance: format_units(total_balance, weth.decimals as usize)
                     .expect("Failed to format units"),
-                total_token_balance: format_units(
-                    total_token_balance,
-                    (token_decimals + 6) as usize,
-                )
-                .expect("Failed to format units")
-                    + "M",
+                total_token_balance: format_token_amount(total_token_balance, token_decimals),
+                raw_total_balance: total_balance,
+                raw_total_token_balance: total_token_balance,
             },
             list: list_wallets_info,
         }
@@ -441,6 +577,17 @@ impl ApiService {
         let mut total_balance = U256::from(0);
         let token_contract =
             MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
+        let token_decimals = match token_contract.decimals().call().await {
+            Ok(token_decimals) => token_decimals,
+            Err(err) => {
+                log::warn!(
+                    "[ApiService] token decimals unavailable, format with {}: {:?}",
+                    self.weth.decimals,
+                    err
+                );
+                self.weth.decimals as u8
+            }
+        };
         // MM configs
         for (mm_index, group_setting) in mm_config.groups.iter().enumerate() {
             let mm_group_wallets = compute_system_wallets(
@@ -448,6 +595,7 @@ impl ApiService {
                 group_setting
                     .max_wallets_count
                     .unwrap_or(mm_config.default_settings.max_wallets_count),
+                self.env.listen_network,
                 &self.env.token_address,
                 self.http_provider.clone(),
             )
@@ -475,25 +623,16 @@ impl ApiService {
                     address: wallet_address,
                     balance: format_units(wallet_context.eth_balance, self.weth.decimals as usize)
                         .expect("Failed to format units"),
-                    token_balance: format_units(
+                    token_balance: format_token_amount(
                         wallet_context.token_balance,
-                        (self.weth.decimals + 6) as usize,
-                    )
-                    .expect("Failed to format units")
-                        + "M",
+                        token_decimals,
+                    ),
                     approvals: ApprovalsMarketMakers {
-                        token_router: format_units(
+                        token_router: format_token_amount(
                             allowance_uniswapv2_router,
-                            (self.weth.decimals + 12) as usize,
-                        )
-                        .expect("Failed to format units")
-                            + "T",
-                        ava_router: format_units(
-                            allowance_ava_router,
-                            (self.weth.decimals + 12) as usize,
-                        )
-                        .expect("Failed to format units")
-                            + "T",
+                            token_decimals,
+                        ),
+                        ava_router: format_token_amount(allowance_ava_router, token_decimals),
                     },
                 };
                 total_balance += wallet_context.eth_balance;
@@ -521,14 +660,272 @@ impl ApiService {
         }
     }
 
-    pub async fn launch_process(&self) -> LaunchStatus {
-        let mut status = LaunchStatus {
-            active_trading: StepStatus::Pending,
-            buyers_bot_launch: StepStatus::Pending,
-            migrate_tokens_to_seller: StepStatus::Pending,
-            start_auto_sell: StepStatus::Pending,
-            market_making_launch: StepStatus::Pending,
+    /// Approve max token to the active router from wallets `index_from..=index_to` of the role
+    pub async fn batch_approve(
+        &self,
+        role: WalletRole,
+        index_from: u32,
+        index_to: u32,
+    ) -> anyhow::Result<BatchApprove> {
+        let wallet_service = WalletService::new(self.env.clone(), self.http_provider.clone());
+        let (mnemonic, _) = wallet_service.get_role_wallets(role)?;
+        // approve txs are priced by the node, the router gas price is not used
+        let router_service = RouterService::new(
+            self.env.clone(),
+            Arc::new(RwLock::new(U256::zero())),
+            self.http_provider.clone(),
+        );
+
+        wallet_service
+            .batch_approve(&mnemonic, index_from, index_to, &router_service)
+            .await
+    }
+
+    /// Reserves, price and FDV of the pool of the active router, for dashboards polling the pool state
+    pub async fn get_pool_snapshot(&self) -> anyhow::Result<PoolSnapshot> {
+        let router_service = RouterService::new(
+            self.env.clone(),
+            Arc::new(RwLock::new(U256::zero())),
+            self.http_provider.clone(),
+        );
+        let router = router_service.active_router;
+        let (pool_address, _) = router_service
+            .get_pair_address(&self.env.token_address, &self.weth.address, false)
+            .await?;
+        if pool_address == *ZERO_ADDRESS {
+            return Err(anyhow::anyhow!(
+                "[ApiService.get_pool_snapshot] pool not created"
+            ));
+        }
+
+        let (pool_state, token_info, eth_usd_price, block_number) = tokio::join!(
+            fetch_pool_state(
+                router,
+                pool_address,
+                self.env.token_address,
+                self.weth.address,
+                self.http_provider.clone(),
+                router_service.get_token_native_price(router, pool_address, true),
+            ),
+            fetch_token_info(self.env.token_address, self.http_provider.clone()),
+            self.price_oracle.get_native_usd_price(),
+            self.http_provider.get_block_number()
+        );
+        let (token_reserve, weth_reserve, price_native) = pool_state?;
+        let token_info = token_info?;
+        let price_usd = price_native * eth_usd_price?;
+        let total_supply: f64 =
+            format_units(token_info.total_supply, token_info.decimals as usize)?.parse()?;
+
+        Ok(PoolSnapshot {
+            pool_address,
+            token_reserve: format_units(token_reserve, token_info.decimals as usize)?,
+            weth_reserve: format_units(weth_reserve, self.weth.decimals as usize)?,
+            price_native,
+            price_usd,
+            fdv: price_usd * total_supply,
+            block_number: block_number?.as_u64(),
+        })
+    }
+
+    /// Eth in / out of each wallet group from the pnl ledger, the token holdings valued at the pool price
+    pub async fn get_pnl(&self) -> anyhow::Result<PnlReport> {
+        let router_service = RouterService::new(
+            self.env.clone(),
+            Arc::new(RwLock::new(U256::zero())),
+            self.http_provider.clone(),
+        );
+        let router = router_service.active_router;
+        let (pool_address, _) = router_service
+            .get_pair_address(&self.env.token_address, &self.weth.address, false)
+            .await?;
+        if pool_address == *ZERO_ADDRESS {
+            return Err(anyhow::anyhow!("[ApiService.get_pnl] pool not created"));
+        }
+        let (token_price, token_info) = tokio::join!(
+            router_service.get_token_native_price(router, pool_address, true),
+            fetch_token_info(self.env.token_address, self.http_provider.clone())
+        );
+        let (token_price, token_info) = (token_price?, token_info?);
+
+        let ledger_totals = PnlLedger::from_env().totals(&self.env.token_address)?;
+        let mut groups = Vec::new();
+        for group in [
+            WalletGroup::Buyer,
+            WalletGroup::AutoBuyer,
+            WalletGroup::Seller,
+            WalletGroup::MarketMaker,
+        ] {
+            let totals = ledger_totals.get(&group).copied().unwrap_or_default();
+            groups.push(PnlGroupReport::new(
+                group,
+                totals.eth_spent,
+                totals.eth_received,
+                self.get_group_token_holdings(group).await?,
+                token_info.decimals,
+                token_price,
+            )?);
+        }
+
+        Ok(PnlReport {
+            native_symbol: get_native_symbol(&self.env.listen_network).to_string(),
+            token_price,
+            groups,
+        })
+    }
+
+    async fn get_group_token_holdings(&self, group: WalletGroup) -> anyhow::Result<U256> {
+        let mnemonics = match group {
+            WalletGroup::Buyer => vec![(self.buyer_mnemonic.clone(), self.buyer_wallets_count)],
+            WalletGroup::AutoBuyer => vec![(
+                self.auto_buyer_mnemonic.clone(),
+                self.auto_buyer_wallets_count,
+            )],
+            WalletGroup::Seller => vec![(self.seller_mnemonic.clone(), self.seller_wallets_count)],
+            WalletGroup::MarketMaker => {
+                let mm_config = get_mm_config();
+                mm_config
+                    .groups
+                    .iter()
+                    .map(|group_setting| {
+                        (
+                            group_setting.mnemonic.clone(),
+                            group_setting
+                                .max_wallets_count
+                                .unwrap_or(mm_config.default_settings.max_wallets_count),
+                        )
+                    })
+                    .collect()
+            }
+        };
+
+        let mut token_holdings = U256::zero();
+        for (mnemonic, wallets_count) in mnemonics {
+            let system_wallets = compute_system_wallets(
+                &mnemonic,
+                wallets_count,
+                self.env.listen_network,
+                &self.env.token_address,
+                self.http_provider.clone(),
+            )
+            .await?;
+            for wallet_context in system_wallets.values() {
+                token_holdings += wallet_context.read().await.token_balance;
+            }
+        }
+
+        Ok(token_holdings)
+    }
+
+    /// Quote `amount` ETH for a buy, `amount` tokens for a sell, on the active router, no tx is sent
+    pub async fn get_quote(&self, amount: &str, is_buy: bool) -> anyhow::Result<Quote> {
+        let token_info =
+            fetch_token_info(self.env.token_address, self.http_provider.clone()).await?;
+        let (decimals_in, decimals_out) = if is_buy {
+            (self.weth.decimals as u32, token_info.decimals as u32)
+        } else {
+            (token_info.decimals as u32, self.weth.decimals as u32)
         };
+        let amount_in: U256 = parse_units(amount, decimals_in)
+            .map_err(|err| {
+                anyhow::anyhow!(
+                    "[ApiService.get_quote] invalid amount {:?}: {}",
+                    amount,
+                    err
+                )
+            })?
+            .into();
+        // quotes read the chain only, the router gas price is not used
+        let (amount_out, price_impact_bps, pool_address, router) = RouterService::new(
+            self.env.clone(),
+            Arc::new(RwLock::new(U256::zero())),
+            self.http_provider.clone(),
+        )
+        .quote(amount_in, is_buy)
+        .await?;
+
+        Ok(Quote {
+            is_buy,
+            router,
+            pool_address,
+            amount_in: format_units(amount_in, decimals_in as usize)?,
+            amount_out: format_units(amount_out, decimals_out as usize)?,
+            price_impact_bps,
+        })
+    }
+
+    /// Sell every wallet holding tokens at any price, see `WalletService::panic_sell_all`
+    pub async fn panic_sell_all(&self) -> anyhow::Result<PanicSell> {
+        let gas_price = self.http_provider.get_gas_price().await?;
+        WalletService::new(self.env.clone(), self.http_provider.clone())
+            .panic_sell_all(Arc::new(RwLock::new(gas_price)))
+            .await
+    }
+
+    /// Sell the tokens of the `group` wallets one by one in the background, see `WalletService::dump_group`
+    pub async fn dump(
+        &self,
+        group: DumpGroup,
+        interval_min: u32,
+        interval_max: u32,
+    ) -> anyhow::Result<Dump> {
+        if interval_min > interval_max {
+            return Err(anyhow::anyhow!(
+                "[ApiService.dump] interval_min {} above interval_max {}",
+                interval_min,
+                interval_max
+            ));
+        }
+        let Some(dump_lock_guard) = DumpLockGuard::try_lock(&self.dump_lock) else {
+            return Err(anyhow::anyhow!(
+                "[ApiService.dump] a dump is already running"
+            ));
+        };
+        let gas_price = self.http_provider.get_gas_price().await?;
+        let wallet_service = WalletService::new(self.env.clone(), self.http_provider.clone());
+        let group_name = format!("{:?}", group);
+        tokio::spawn(async move {
+            let _dump_lock_guard = dump_lock_guard;
+            let dump_result = wallet_service
+                .dump_group(
+                    group,
+                    Arc::new(RwLock::new(gas_price)),
+                    interval_min,
+                    interval_max,
+                )
+                .await;
+            let message = match &dump_result {
+                Ok(()) => format!("Dump of {} wallets done", group_name),
+                Err(err) => {
+                    log::error!("[ApiService] dump of {} failed: {:?}", group_name, err);
+                    format!("Dump of {} wallets failed: {}", group_name, err)
+                }
+            };
+            MessageTransportService::new()
+                .send_outcome_message(message, dump_result.is_err())
+                .await;
+        });
+
+        Ok(Dump {
+            group: format!("{:?}", group),
+            interval_min,
+            interval_max,
+        })
+    }
+
+    /// one launch at a time, a token whose trading was activated is not launched again, see `guard_launch`
+    pub async fn launch_process(&self) -> LaunchStatus {
+        guard_launch(
+            &self.launch_lock,
+            &self.launch_state_path,
+            &self.env,
+            self.run_launch_process(),
+        )
+        .await
+    }
+
+    async fn run_launch_process(&self) -> LaunchStatus {
+        let mut status = pending_launch_status(self.env.dry_run);
         let http_provider = Arc::new(
             HttpProviders::get_healthy_provider(&self.env.listen_network, false)
                 .await
@@ -537,42 +934,234 @@ impl ApiService {
         let launching_process_service =
             LaunchingProcessService::new(self.env.clone(), http_provider);
 
-        match launching_process_service.active_trading_and_buy().await {
-            Ok(_) => status.active_trading = StepStatus::Activated,
+        let start_time = Instant::now();
+        let report = match launching_process_service.active_trading_and_buy().await {
+            Ok(report) => report,
             Err(error) => {
-                status.active_trading = StepStatus::Error(error.to_string());
+                status.active_trading = finished_step(Err(error), start_time.elapsed());
                 return status;
             }
+        };
+        status.active_trading = phase_step(&report.active_trading);
+        status.buyers_bot_launch = phase_step(&report.buy);
+        status.migrate_tokens_to_seller = phase_step(&report.migrate);
+        status.ordering = report.ordering;
+        status.tx_positions = report.tx_positions;
+        if !report.active_trading.errors.is_empty() {
+            return status;
         }
 
         tokio::time::sleep(Duration::from_secs(10)).await;
 
-        let (auto_sell_result, market_making_result) = tokio::join!(
-            launching_process_service.start_auto_sell(),
-            launching_process_service.start_market_making()
+        let (
+            (auto_sell_result, auto_sell_duration),
+            (market_making_result, market_making_duration),
+        ) = tokio::join!(
+            timed(launching_process_service.start_auto_sell()),
+            timed(launching_process_service.start_market_making())
         );
 
-        if auto_sell_result.is_ok() {
-            status.start_auto_sell = StepStatus::Activated;
-        } else {
-            status.start_auto_sell = StepStatus::Error(auto_sell_result.err().unwrap().to_string());
-            return status;
+        status.start_auto_sell = finished_step(auto_sell_result, auto_sell_duration);
+        status.market_making_launch = finished_step(market_making_result, market_making_duration);
+
+        status
+    }
+}
+
+/**
+ * `launch` unless another launch holds `launch_lock` or trading of the token was activated by a previous launch
+ * (kept in `launch_state_path`, ignored in DRY_RUN), the skipped step is reported on active_trading
+ */
+pub async fn guard_launch(
+    launch_lock: &AtomicBool,
+    launch_state_path: &str,
+    env: &Env,
+    launch: impl Future<Output = LaunchStatus>,
+) -> LaunchStatus {
+    if launch_lock
+        .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+        .is_err()
+    {
+        log::warn!("[ApiService.launch_process] a launch is already running");
+        return skipped_launch_status(env.dry_run, StepStatus::AlreadyRunning);
+    }
+    let _launch_lock_guard = LaunchLockGuard(launch_lock);
+
+    // checked under the lock, a launch finishing meanwhile has saved its state
+    if !env.dry_run {
+        if let Some(launched_at) = get_launched_at(launch_state_path, &env.token_address) {
+            log::warn!(
+                "[ApiService.launch_process] {:?} already launched at {}, remove it from {:?} to launch again",
+                env.token_address,
+                launched_at,
+                launch_state_path
+            );
+            return skipped_launch_status(env.dry_run, StepStatus::AlreadyLaunched(launched_at));
         }
+    }
 
-        if market_making_result.is_ok() {
-            status.market_making_launch = StepStatus::Activated;
-        } else {
-            status.market_making_launch =
-                StepStatus::Error(market_making_result.err().unwrap().to_string());
-            return status;
+    let status = launch.await;
+    if !env.dry_run && matches!(status.active_trading.status, StepStatus::Activated) {
+        if let Err(err) =
+            save_launched_at(launch_state_path, env.token_address, Utc::now().timestamp())
+        {
+            log::error!(
+                "[ApiService.launch_process] failed to save the launch state: {:?}",
+                err
+            );
         }
+    }
 
-        status
+    status
+}
+
+// releases the launch lock when the launch ends or its future is dropped
+struct LaunchLockGuard<'a>(&'a AtomicBool);
+
+impl Drop for LaunchLockGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+// released when the background dump ends, also on a panic
+struct DumpLockGuard(Arc<AtomicBool>);
+
+impl DumpLockGuard {
+    fn try_lock(dump_lock: &Arc<AtomicBool>) -> Option<Self> {
+        dump_lock
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()
+            .map(|_| Self(dump_lock.clone()))
+    }
+}
+
+impl Drop for DumpLockGuard {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::SeqCst);
+    }
+}
+
+fn pending_launch_status(dry_run: bool) -> LaunchStatus {
+    LaunchStatus {
+        active_trading: pending_step(),
+        buyers_bot_launch: pending_step(),
+        migrate_tokens_to_seller: pending_step(),
+        start_auto_sell: pending_step(),
+        market_making_launch: pending_step(),
+        ordering: LaunchOrdering::default(),
+        tx_positions: Vec::new(),
+        dry_run,
+    }
+}
+
+fn skipped_launch_status(dry_run: bool, step_status: StepStatus) -> LaunchStatus {
+    LaunchStatus {
+        active_trading: LaunchStep {
+            status: step_status,
+            duration_ms: None,
+        },
+        ..pending_launch_status(dry_run)
     }
 }
 
+async fn timed<T>(future: impl Future<Output = T>) -> (T, Duration) {
+    let start_time = Instant::now();
+    let result = future.await;
+    (result, start_time.elapsed())
+}
+
+fn pending_step() -> LaunchStep {
+    LaunchStep {
+        status: StepStatus::Pending,
+        duration_ms: None,
+    }
+}
+
+fn finished_step(result: anyhow::Result<()>, duration: Duration) -> LaunchStep {
+    LaunchStep {
+        status: match result {
+            Ok(_) => StepStatus::Activated,
+            Err(error) => StepStatus::Error(error.to_string()),
+        },
+        duration_ms: Some(duration.as_millis()),
+    }
+}
+
+fn phase_step(phase: &PhaseReport) -> LaunchStep {
+    let result = if phase.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(phase.errors.join("; ")))
+    };
+    finished_step(result, phase.duration)
+}
+
 impl Default for ApiService {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    #[tokio::test]
+    async fn concurrent_launches_activate_once() {
+        let launch_state_path = std::env::temp_dir()
+            .join(format!("launch_state_{:?}.json", Address::random()))
+            .to_string_lossy()
+            .to_string();
+        let env = Env {
+            token_address: Address::random(),
+            ..Default::default()
+        };
+        let launch_lock = AtomicBool::new(false);
+        let activations = AtomicUsize::new(0);
+        let launch = || async {
+            activations.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            LaunchStatus {
+                active_trading: finished_step(Ok(()), Duration::from_millis(100)),
+                ..pending_launch_status(false)
+            }
+        };
+
+        let (first, second) = tokio::join!(
+            guard_launch(&launch_lock, &launch_state_path, &env, launch()),
+            guard_launch(&launch_lock, &launch_state_path, &env, launch())
+        );
+        assert_eq!(activations.load(Ordering::SeqCst), 1);
+        assert!(matches!(first.active_trading.status, StepStatus::Activated));
+        assert!(matches!(
+            second.active_trading.status,
+            StepStatus::AlreadyRunning
+        ));
+        assert!(!launch_lock.load(Ordering::SeqCst));
+
+        // a restarted service has a fresh lock but reads the persisted launch
+        let restarted =
+            guard_launch(&AtomicBool::new(false), &launch_state_path, &env, launch()).await;
+        assert!(matches!(
+            restarted.active_trading.status,
+            StepStatus::AlreadyLaunched(_)
+        ));
+        assert_eq!(activations.load(Ordering::SeqCst), 1);
+
+        std::fs::remove_file(launch_state_path).unwrap();
+    }
+
+    #[test]
+    fn one_dump_at_a_time() {
+        let dump_lock = Arc::new(AtomicBool::new(false));
+
+        let running_dump = DumpLockGuard::try_lock(&dump_lock).unwrap();
+        assert!(DumpLockGuard::try_lock(&dump_lock).is_none());
+
+        // the next dump starts once the running one is done
+        drop(running_dump);
+        assert!(DumpLockGuard::try_lock(&dump_lock).is_some());
+    }
+}
@@ -8,8 +8,8 @@ use ethers::utils::hex;
 use ethers::{
     contract::parse_log,
     providers::{Http, Middleware, Provider},
-    signers::{LocalWallet, Signer, WalletError},
-    types::{Address, BlockNumber, Filter, H256, U256, U64},
+    signers::Signer,
+    types::{Address, BlockNumber, Filter, Log, Transaction, H256, U256, U64},
     utils::{format_ether, format_units, parse_ether},
 };
 use futures::{future::join_all, FutureExt};
@@ -24,17 +24,23 @@ use mm_token_utils::{
     abi::{IUniswapV2PairAbigenEvents, MemeTokenAbigen},
     constants::WRAPPED_NATIVE_TOKENS,
     env::get_env,
+    signer::{WalletSigner, WalletSignerError},
     utils::{compute_transaction_hash, load_mnemonic_wallet},
 };
-use provider_utils::{http_providers::HttpProviders, ws_providers::WsProviders};
-use rand::{seq::SliceRandom, Rng};
+use provider_utils::{
+    polling_mempool::subscribe_mempool, provider_pool::ProviderPool, ws_providers::WsProviders,
+};
+use rand::Rng;
 use std::{
     collections::HashMap,
     sync::{atomic::Ordering, Arc},
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio::{
-    sync::{Mutex, RwLock},
+    sync::{
+        broadcast::{self, error::RecvError},
+        Mutex, RwLock,
+    },
     time::timeout,
 };
 use tokio_stream::StreamExt;
@@ -42,19 +48,28 @@ use tokio_stream::StreamExt;
 use crate::routers::RouterService;
 use crate::utils::compute_all_system_wallets;
 use crate::{
-    constants::Env,
-    core::MessageTransportService,
-    types::TokenInfo,
-    utils::{compute_system_wallets, WalletContext},
+    constants::{EBuyDistribution, Env, TokenTaxMode},
+    core::{MessageTransportService, PrivateSendService},
+    types::{TokenInfo, WalletGroup},
+    utils::{
+        add_tax_bps, await_receipt_or_resync, cache_get_or_insert, compute_reactive_gas_price,
+        compute_system_wallets, distribute_buy_amount, fetch_token_info, format_buy_message,
+        get_native_symbol, get_reactive_gas_config, is_removed_log, is_within_reorg_depth,
+        prefetch_nonce_and_balance, resolve_token_tax, spawn_mempool_workers,
+        wait_for_pair_addresses, FloorPrice, NonceManager, OwnWallets, PnlLedger, PriceOracle,
+        ReorgWatcher, TokenGuard, WalletContext,
+    },
 };
 
 #[derive(Debug, Clone)]
 pub struct AutoBuyService {
     env: Env,
+    // ETH, BNB or FTM in the messages
+    native_symbol: &'static str,
     http_provider: Arc<Provider<Http>>,
     weth_address: Address,
     token_info: TokenInfo,
-    provider_index: Arc<RwLock<usize>>,
+    provider_pool: ProviderPool,
     auto_buyer_mnemonic: String,
     auto_buyer_wallets_count: u32,
     auto_buyer_surplus_balance: U256,
@@ -62,22 +77,44 @@ pub struct AutoBuyService {
     buyer_wallets_count: u32,
     seller_mnemonic: String,
     seller_wallets_count: u32,
-    floor_price: f64,
+    floor_price: FloorPrice,
+    price_oracle: PriceOracle,
     auto_buy_min_percent: u32,
     auto_buy_max_percent: u32,
+    buy_distribution: EBuyDistribution,
     sell_tax: f32,
+    tax_mode: TokenTaxMode,
+    reorg_depth: u64,
+    // resyncs the wallets of a buy reorged out within REORG_DEPTH blocks
+    reorg_watcher: ReorgWatcher,
+    mempool_workers: usize,
+    mempool_poll_interval: Duration,
+    ws_shared_subscription: bool,
+    pair_poll_interval: Duration,
+    pair_max_polls: u32,
+    // a buy not mined within BUY_TX_TIMEOUT_SECS is abandoned and the wallet resynced
+    buy_tx_timeout: Duration,
+    gas_price: Arc<RwLock<U256>>,
+    reactive_gas_config: Option<(u32, U256)>,
     router_service: RouterService,
+    nonce_manager: NonceManager,
+    // eth in / out of the trades, read by the /pnl command
+    pnl_ledger: PnlLedger,
+    private_send_service: PrivateSendService,
     auto_buyer_system_wallets: HashMap<Address, Arc<RwLock<WalletContext>>>,
     buyer_system_wallets: Vec<Address>,
     seller_system_wallets: Vec<Address>,
     market_maker_system_wallets: Vec<Address>,
+    own_wallets: OwnWallets,
+    token_guard: TokenGuard,
+    min_wallet_cooldown: Duration,
 }
 
 impl AutoBuyService {
     pub fn new(
         env: Env,
         gas_price: Arc<RwLock<U256>>,
-        provider_index: Arc<RwLock<usize>>,
+        provider_pool: ProviderPool,
         http_provider: Arc<Provider<Http>>,
     ) -> Self {
         let Some(weth) = WRAPPED_NATIVE_TOKENS.get(&env.listen_network) else {
@@ -87,49 +124,92 @@ impl AutoBuyService {
             );
         };
 
-        let sell_tax: f32 = get_env("TOKEN_SELL_TAX", None).parse().unwrap_or(0.0);
+        let sell_tax = env.token.sell_tax;
+        let reorg_depth: u64 = get_env("REORG_DEPTH", Some("0".to_string()))
+            .parse()
+            .unwrap();
+        let tax_mode: TokenTaxMode = get_env("TOKEN_TAX_MODE", Some("Env".to_string()))
+            .parse()
+            .unwrap();
+        // without a worker the mempool queue fills up and blocks the ingestion
+        let mempool_workers: usize = get_env("MEMPOOL_WORKERS", Some("4".to_string()))
+            .parse()
+            .unwrap();
+        if mempool_workers == 0 {
+            panic!("MEMPOOL_WORKERS must be at least 1");
+        }
 
         Self {
+            native_symbol: get_native_symbol(&env.listen_network),
             env: env.clone(),
             weth_address: weth.address,
             http_provider: http_provider.clone(),
             token_info: TokenInfo::default(),
-            provider_index,
+            provider_pool,
             auto_buyer_mnemonic: get_env("AUTO_BUYER_MNEMONIC", None),
             auto_buyer_wallets_count: get_env("AUTO_BUYER_WALLETS_COUNT", None).parse().unwrap(),
             buyer_mnemonic: get_env("BUYER_MNEMONIC", None),
             buyer_wallets_count: get_env("BUYER_WALLETS_COUNT", None).parse().unwrap(),
             seller_mnemonic: get_env("SELLER_MNEMONIC", None),
             seller_wallets_count: get_env("SELLER_WALLETS_COUNT", None).parse().unwrap(),
-            floor_price: get_env("FLOOR_PRICE", None).parse().unwrap(),
+            floor_price: FloorPrice::from_env(env.token.floor_price),
+            price_oracle: PriceOracle::from_env(&env.listen_network, http_provider.clone()),
             auto_buy_min_percent: get_env("AUTO_BUY_MIN_PERCENT", None).parse().unwrap(),
             auto_buy_max_percent: get_env("AUTO_BUY_MAX_PERCENT", None).parse().unwrap(),
             auto_buyer_surplus_balance: parse_ether(get_env("AUTO_BUYER_SURPLUS_BALANCE", None))
                 .unwrap(),
+            buy_distribution: get_env("AUTO_BUY_DISTRIBUTION", Some("Greedy".to_string()))
+                .parse()
+                .unwrap(),
             sell_tax,
-            router_service: RouterService::new(env, gas_price, http_provider),
+            tax_mode,
+            reorg_depth,
+            reorg_watcher: ReorgWatcher::new(reorg_depth),
+            mempool_workers,
+            mempool_poll_interval: Duration::from_millis(
+                get_env("MEMPOOL_POLL_INTERVAL_MS", Some("500".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
+            ws_shared_subscription: get_env("WS_SHARED_SUBSCRIPTION", Some("true".to_string()))
+                .parse()
+                .unwrap(),
+            pair_poll_interval: Duration::from_secs(
+                get_env("PAIR_POLL_INTERVAL_SECS", Some("10".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
+            pair_max_polls: get_env("PAIR_MAX_POLLS", Some("360".to_string()))
+                .parse()
+                .unwrap(),
+            buy_tx_timeout: Duration::from_secs(
+                get_env("BUY_TX_TIMEOUT_SECS", Some("10".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
+            gas_price: gas_price.clone(),
+            reactive_gas_config: get_reactive_gas_config(),
+            router_service: RouterService::new(env.clone(), gas_price, http_provider),
+            nonce_manager: NonceManager::new(),
+            pnl_ledger: PnlLedger::from_env(),
+            private_send_service: PrivateSendService::new(env),
             auto_buyer_system_wallets: HashMap::new(),
             buyer_system_wallets: Vec::<Address>::new(),
             seller_system_wallets: Vec::<Address>::new(),
             market_maker_system_wallets: Vec::<Address>::new(),
+            own_wallets: OwnWallets::default(),
+            token_guard: TokenGuard::from_env(),
+            min_wallet_cooldown: Duration::from_secs(
+                get_env("MIN_WALLET_COOLDOWN_SECS", Some("0".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
         }
     }
 
     pub async fn init(&mut self) -> anyhow::Result<()> {
-        let token_info_call =
-            MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
-        let symbol: String = token_info_call.symbol().call().await.unwrap();
-        let name: String = token_info_call.name().call().await.unwrap();
-        let decimals: u8 = token_info_call.decimals().call().await.unwrap();
-        let total_supply: U256 = token_info_call.total_supply().call().await.unwrap();
-
-        self.token_info = TokenInfo {
-            address: self.env.token_address,
-            symbol,
-            name,
-            decimals,
-            total_supply,
-        };
+        self.token_info =
+            fetch_token_info(self.env.token_address, self.http_provider.clone()).await?;
 
         (
             _,
@@ -143,48 +223,108 @@ impl AutoBuyService {
             self.buyer_wallets_count,
             &self.seller_mnemonic,
             self.seller_wallets_count,
+            self.env.listen_network,
         )
         .await?;
 
         self.auto_buyer_system_wallets = compute_system_wallets(
             &self.auto_buyer_mnemonic,
             self.auto_buyer_wallets_count,
+            self.env.listen_network,
             &self.env.token_address,
             self.http_provider.clone(),
         )
         .await?;
 
+        self.reorg_watcher.spawn(
+            "AutoBuyService",
+            self.env.token_address,
+            self.auto_buyer_system_wallets.clone(),
+            self.http_provider.clone(),
+            self.env.exit.clone(),
+        );
+
+        self.own_wallets = OwnWallets::from_env(
+            self.auto_buyer_system_wallets
+                .keys()
+                .chain(&self.buyer_system_wallets)
+                .chain(&self.seller_system_wallets)
+                .chain(&self.market_maker_system_wallets)
+                .copied(),
+        );
+
         Ok(())
     }
 
+    /// trades of the bots' own wallets must not trigger an auto buy (`SKIP_OWN_WALLET_TX`)
+    fn is_own_wallet(&self, address: &Address) -> bool {
+        self.own_wallets.contains(address)
+    }
+
     pub async fn start_event_mode(
         &self,
         tx_hashes_cache: Arc<Mutex<TimedCache<H256, bool>>>,
     ) -> anyhow::Result<()> {
         let message_transport_service = MessageTransportService::new();
         let message = "Auto buy event mode service have been launch".to_string();
-        message_transport_service.send_message(message).await?;
+        message_transport_service.send_message(message).await;
+
+        // the detectors start once the token-weth pair is created
+        let pair_addresses = wait_for_pair_addresses(
+            || {
+                self.router_service
+                    .get_all_pair_addresses(&self.env.token_address, &self.weth_address)
+            },
+            self.pair_poll_interval,
+            self.pair_max_polls,
+        )
+        .await?;
 
-        let pair_addresses = self
-            .router_service
-            .get_all_pair_addresses(&self.env.token_address, &self.weth_address)
-            .await?;
+        // one ws subscription for all pairs, each detector keeps the logs of its own pair
+        let shared_receiver = if self.ws_shared_subscription {
+            let pair_topics: Vec<H256> = pair_addresses
+                .iter()
+                .map(|pair_address| H256::from(*pair_address))
+                .collect();
+            Some(
+                WsProviders::subscribe_logs_stream_resilient(
+                    &self.env.listen_network,
+                    self.weth_transfer_filter(pair_topics),
+                    false,
+                )
+                .await?,
+            )
+        } else {
+            None
+        };
 
         let mut futures = Vec::new();
         for pair_address in pair_addresses {
             log::info!("initialized, token-weth pair is {:?}", pair_address);
             let auto_buy_service = self.clone();
             let tx_hashes_cache = tx_hashes_cache.clone();
+            let receiver = match &shared_receiver {
+                Some(shared_receiver) => shared_receiver.resubscribe(),
+                None => {
+                    WsProviders::subscribe_logs_stream_resilient(
+                        &self.env.listen_network,
+                        self.weth_transfer_filter(vec![H256::from(pair_address)]),
+                        false,
+                    )
+                    .await?
+                }
+            };
 
             futures.push(
                 tokio::spawn(async move {
                     let _ = auto_buy_service
-                        .detect_sell_tx(pair_address, tx_hashes_cache)
+                        .detect_sell_tx(pair_address, receiver, tx_hashes_cache)
                         .await;
                 })
                 .boxed(),
             )
         }
+        drop(shared_receiver);
         join_all(futures).await;
 
         Ok(())
@@ -196,13 +336,15 @@ impl AutoBuyService {
     ) -> anyhow::Result<()> {
         let message_transport_service = MessageTransportService::new();
         let message = "Auto buy mempool mode service have been launch".to_string();
-        message_transport_service.send_message(message).await?;
-
-        let get_ws_providers =
-            WsProviders::get_ws_providers(&self.env.listen_network, false).await?;
+        message_transport_service.send_message(message).await;
 
-        let stream_mempool = get_ws_providers[0].subscribe_pending_txs().await.unwrap();
-        let mut stream_mempool = stream_mempool.transactions_unordered(128).fuse();
+        let mut stream_mempool = subscribe_mempool(
+            &self.env.listen_network,
+            self.http_provider.clone(),
+            self.mempool_poll_interval,
+        )
+        .await?
+        .fuse();
 
         let Some(uniswapv2_router_address) = UNISWAP2_ROUTERS.get(&self.env.listen_network) else {
             panic!(
@@ -242,7 +384,31 @@ impl AutoBuyService {
                 self.env.listen_network
             );
         }
-        // assume that tx is success because there is an Transfer event
+
+        // decouple ingestion from processing, so a slow trigger doesn't block detection
+        // concurrent workers can pick the same system wallet, its write lock serializes the trades
+        let tx_sender = spawn_mempool_workers("AutoBuyService", self.mempool_workers, {
+            let service = self.clone();
+            let universal_router_address = *universal_router_address;
+            let uniswapv2_router_address = *uniswapv2_router_address;
+            let uniswapv3_router_address = *uniswapv3_router_address;
+            move |tx| {
+                let service = service.clone();
+                let tx_hashes_cache = tx_hashes_cache.clone();
+                async move {
+                    service
+                        .process_mempool_tx(
+                            tx,
+                            universal_router_address,
+                            uniswapv2_router_address,
+                            uniswapv3_router_address,
+                            &tx_hashes_cache,
+                        )
+                        .await
+                }
+            }
+        });
+
         loop {
             if self.env.exit.load(Ordering::Relaxed) {
                 return Err(anyhow!(
@@ -250,276 +416,286 @@ impl AutoBuyService {
                     self.env.exit
                 ));
             }
+            let Some(tx) = stream_mempool.next().await else {
+                // a dropped subscription must not pass for a clean exit
+                return Err(anyhow!(
+                    "[AutoBuyService.start_mempool_mode] mempool stream ended"
+                ));
+            };
 
-            let Some(result) = stream_mempool.next().await else {
-                break;
+            // only queue txs sent to the routers we decode
+            let Some(to) = tx.to else {
+                continue;
             };
-            let tx = result.unwrap_or_default();
-
-            // let tx_hash_test = H256::from_str(
-            //     "0x2a95de34baf8bc3c14aefbaab763e6cca10ffc063a95104d11fc2e04c11dad1c",
-            // )
-            // .unwrap();
-            // let tx = self
-            //     .http_provider
-            //     .get_transaction(tx_hash_test)
-            //     .await?
-            //     .unwrap();
-
-            let is_swap_tx_universal_router_matched =
-                tx.input.starts_with(&hex::decode("0x3593564c").unwrap()); // execute(bytes commands,bytes[] inputs,uint256 deadline) methodId
-            let is_sell_tx_uniswap_v2_matched =
-                tx.input.starts_with(&hex::decode("0x791ac947").unwrap()); // swapExactTokensForETHSupportingFeeOnTransferTokens methodId
-            let is_sell_tx_uniswap_v3_matched =
-                tx.input.starts_with(&hex::decode("0x04e45aaf").unwrap()); // exactInputSingle(ExactInputSingleParams memory params) methodId
-
-            let mut sell_token_amount = U256::zero(); // unit token
-            let mut sell_tx_value = U256::zero(); // unit WETH
-
-            let trigger_mempool_router: ERouter;
-            let pool_address: Address;
-            let mut pool_v3_fee_tier: u32 = 500;
-
-            // check universal router
-            if is_swap_tx_universal_router_matched && tx.to == Some(*universal_router_address) {
-                let sig = "function execute(bytes,bytes[],uint256) external payable";
-                let func = AbiParser::default().parse_function(sig)?;
-                let decoded_data = func.decode_input(&tx.input[4..])?;
-                let decode_command =
-                    Bytes::from_token(decoded_data.first().unwrap().clone()).unwrap();
-                let input_data = decoded_data.get(1).unwrap().clone().into_array().unwrap();
-
-                let mut is_sell_tx_universal_matched: bool = false;
-                for index in 0..decode_command.len() {
-                    let command = decode_command[index];
-                    let input = &input_data[index];
-                    let decode_input = Bytes::from_token(input.clone()).unwrap();
-
-                    let swap_info: SwapUniversalRouterInfo =
-                        universal_decode(command, decode_input);
-
-                    if !swap_info.path.is_empty() {
-                        let from_token = swap_info.path[0];
-                        let to_token = swap_info.path[1];
-                        if from_token == self.token_info.address && to_token == self.weth_address {
-                            log::info!("[AutoBuy] from universal router sell tx: {:#?}", tx.hash);
-                            is_sell_tx_universal_matched = true;
-                            sell_token_amount = swap_info.amount_in;
-                            sell_tx_value = swap_info.amount_out;
-                            log::info!("sell_token_amount: {:#?}", sell_token_amount);
-                            log::info!("sell_tx_value: {:#?}", sell_tx_value);
-                        }
-                    }
-                }
+            if ![
+                *universal_router_address,
+                *uniswapv2_router_address,
+                *uniswapv3_router_address,
+            ]
+            .contains(&to)
+            {
+                continue;
+            }
+            if tx_sender.send(tx).await.is_err() {
+                break;
+            }
+        }
 
-                if !is_sell_tx_universal_matched {
-                    continue;
+        Ok(())
+    }
+
+    async fn process_mempool_tx(
+        &self,
+        tx: Transaction,
+        universal_router_address: Address,
+        uniswapv2_router_address: Address,
+        uniswapv3_router_address: Address,
+        tx_hashes_cache: &Arc<Mutex<TimedCache<H256, bool>>>,
+    ) -> anyhow::Result<()> {
+        // let tx_hash_test = H256::from_str(
+        //     "0x2a95de34baf8bc3c14aefbaab763e6cca10ffc063a95104d11fc2e04c11dad1c",
+        // )
+        // .unwrap();
+        // let tx = self
+        //     .http_provider
+        //     .get_transaction(tx_hash_test)
+        //     .await?
+        //     .unwrap();
+
+        if self.is_own_wallet(&tx.from) {
+            log::warn!(
+                "tx {:?} from own system wallet {:?}, skip",
+                tx.hash,
+                tx.from
+            );
+            return Ok(());
+        }
+
+        let is_swap_tx_universal_router_matched =
+            tx.input.starts_with(&hex::decode("0x3593564c").unwrap()); // execute(bytes commands,bytes[] inputs,uint256 deadline) methodId
+        let is_sell_tx_uniswap_v2_matched =
+            tx.input.starts_with(&hex::decode("0x791ac947").unwrap()); // swapExactTokensForETHSupportingFeeOnTransferTokens methodId
+        let is_sell_tx_uniswap_v3_matched =
+            tx.input.starts_with(&hex::decode("0x04e45aaf").unwrap()); // exactInputSingle(ExactInputSingleParams memory params) methodId
+
+        let mut sell_token_amount = U256::zero(); // unit token
+        let mut sell_tx_value = U256::zero(); // unit WETH
+
+        let trigger_mempool_router: ERouter;
+        let pool_address: Address;
+        let mut pool_v3_fee_tier: u32 = 500;
+
+        // check universal router
+        if is_swap_tx_universal_router_matched && tx.to == Some(universal_router_address) {
+            let sig = "function execute(bytes,bytes[],uint256) external payable";
+            let func = AbiParser::default().parse_function(sig)?;
+            let decoded_data = func.decode_input(&tx.input[4..])?;
+            let decode_command = Bytes::from_token(decoded_data.first().unwrap().clone()).unwrap();
+            let input_data = decoded_data.get(1).unwrap().clone().into_array().unwrap();
+
+            let mut is_sell_tx_universal_matched: bool = false;
+            for index in 0..decode_command.len() {
+                let command = decode_command[index];
+                let input = &input_data[index];
+                let decode_input = Bytes::from_token(input.clone()).unwrap();
+
+                let swap_info: SwapUniversalRouterInfo = universal_decode(command, decode_input);
+
+                if !swap_info.path.is_empty() {
+                    let from_token = swap_info.path[0];
+                    let to_token = swap_info.path[1];
+                    if from_token == self.token_info.address && to_token == self.weth_address {
+                        log::info!("[AutoBuy] from universal router sell tx: {:#?}", tx.hash);
+                        is_sell_tx_universal_matched = true;
+                        sell_token_amount = swap_info.amount_in;
+                        sell_tx_value = swap_info.amount_out;
+                        log::info!("sell_token_amount: {:#?}", sell_token_amount);
+                        log::info!("sell_tx_value: {:#?}", sell_tx_value);
+                    }
                 }
+            }
 
-                trigger_mempool_router = ERouter::UniversalRouters;
+            if !is_sell_tx_universal_matched {
+                return Ok(());
             }
-            // check uniswapv2 router
-            else if is_sell_tx_uniswap_v2_matched && tx.to == Some(*uniswapv2_router_address) {
-                let sig = "function swapExactTokensForETHSupportingFeeOnTransferTokens(uint256,uint256,address[],address,uint256) external";
-                let func = AbiParser::default().parse_function(sig)?;
-                let decoded_data: Vec<_> = func.decode_input(&tx.input[4..])?;
-                let vec_token: Vec<Address> =
-                    Vec::from_token(decoded_data.get(2).unwrap().clone()).unwrap(); // [0]: token, [1]: WETH
-                let sell_token = vec_token[0];
-                if sell_token != self.token_info.address {
-                    continue;
-                }
-                sell_token_amount =
-                    U256::from_token(decoded_data.first().unwrap().clone()).unwrap();
 
-                trigger_mempool_router = ERouter::Uniswap2Routers;
+            trigger_mempool_router = ERouter::UniversalRouters;
+        }
+        // check uniswapv2 router
+        else if is_sell_tx_uniswap_v2_matched && tx.to == Some(uniswapv2_router_address) {
+            let sig = "function swapExactTokensForETHSupportingFeeOnTransferTokens(uint256,uint256,address[],address,uint256) external";
+            let func = AbiParser::default().parse_function(sig)?;
+            let decoded_data: Vec<_> = func.decode_input(&tx.input[4..])?;
+            let vec_token: Vec<Address> =
+                Vec::from_token(decoded_data.get(2).unwrap().clone()).unwrap(); // [0]: token, [1]: WETH
+            let sell_token = vec_token[0];
+            if sell_token != self.token_info.address {
+                return Ok(());
             }
-            // check uniswapv3 router
-            else if is_sell_tx_uniswap_v3_matched && tx.to == Some(*uniswapv3_router_address) {
-                let sig = "function exactInputSingle(address,address,uint24,address,uint256,uint256,uint160) external payable override";
-                let func = AbiParser::default().parse_function(sig)?;
-                let decoded_data: Vec<_> = func.decode_input(&tx.input[4..])?;
-
-                let sell_token =
-                    Address::from_token(decoded_data.first().unwrap().clone()).unwrap();
-                if sell_token != self.token_info.address {
-                    continue;
-                }
-                pool_v3_fee_tier = u32::from_token(decoded_data.get(2).unwrap().clone()).unwrap();
-                sell_token_amount = U256::from_token(decoded_data.get(4).unwrap().clone()).unwrap();
+            sell_token_amount = U256::from_token(decoded_data.first().unwrap().clone()).unwrap();
 
-                trigger_mempool_router = ERouter::Uniswap3Routers;
-            } else {
-                continue;
+            trigger_mempool_router = ERouter::Uniswap2Routers;
+        }
+        // check uniswapv3 router
+        else if is_sell_tx_uniswap_v3_matched && tx.to == Some(uniswapv3_router_address) {
+            let sig = "function exactInputSingle(address,address,uint24,address,uint256,uint256,uint160) external payable override";
+            let func = AbiParser::default().parse_function(sig)?;
+            let decoded_data: Vec<_> = func.decode_input(&tx.input[4..])?;
+
+            let sell_token = Address::from_token(decoded_data.first().unwrap().clone()).unwrap();
+            if sell_token != self.token_info.address {
+                return Ok(());
             }
+            pool_v3_fee_tier = u32::from_token(decoded_data.get(2).unwrap().clone()).unwrap();
+            sell_token_amount = U256::from_token(decoded_data.get(4).unwrap().clone()).unwrap();
 
-            let transaction_hash = tx.hash;
+            trigger_mempool_router = ERouter::Uniswap3Routers;
+        } else {
+            return Ok(());
+        }
 
-            let (transaction_value, token_price) = match trigger_mempool_router {
-                ERouter::Uniswap2Routers => {
-                    pool_address = self
-                        .router_service
-                        .get_pair_address_by_router(
-                            &self.env.token_address,
-                            &self.weth_address,
+        let transaction_hash = tx.hash;
+        let sell_tax = resolve_token_tax(
+            self.tax_mode,
+            self.sell_tax,
+            self.env.token_address,
+            self.http_provider.clone(),
+        )
+        .await;
+
+        let (transaction_value, token_price) = match trigger_mempool_router {
+            ERouter::Uniswap2Routers => {
+                pool_address = self
+                    .router_service
+                    .get_pair_address_by_router(
+                        &self.env.token_address,
+                        &self.weth_address,
+                        false,
+                        None,
+                        ERouter::Uniswap2Routers,
+                    )
+                    .await?
+                    .0;
+                (
+                    self.router_service
+                        .get_amount_out(
+                            ERouter::Uniswap2Routers,
+                            &pool_address,
                             false,
                             None,
-                            ERouter::Uniswap2Routers,
+                            None,
+                            None,
+                            sell_token_amount,
+                            add_tax_bps(0, sell_tax),
                         )
-                        .await?
-                        .0;
-                    (
-                        self.router_service
-                            .get_amount_out(
-                                ERouter::Uniswap2Routers,
-                                &pool_address,
-                                false,
-                                None,
-                                None,
-                                sell_token_amount,
-                                self.sell_tax,
-                            )
-                            .await?,
-                        self.router_service
-                            .get_token_native_price(ERouter::Uniswap2Routers, pool_address)
-                            .await?,
+                        .await?,
+                    self.router_service
+                        .get_token_native_price(ERouter::Uniswap2Routers, pool_address, false)
+                        .await?,
+                )
+            }
+            ERouter::Uniswap3Routers => {
+                pool_address = self
+                    .router_service
+                    .get_pair_address_by_router(
+                        &self.env.token_address,
+                        &self.weth_address,
+                        false,
+                        Some(pool_v3_fee_tier),
+                        ERouter::Uniswap3Routers,
                     )
-                }
-                ERouter::Uniswap3Routers => {
-                    pool_address = self
-                        .router_service
-                        .get_pair_address_by_router(
-                            &self.env.token_address,
-                            &self.weth_address,
-                            false,
-                            Some(pool_v3_fee_tier),
+                    .await?
+                    .0;
+                (
+                    self.router_service
+                        .get_amount_out(
                             ERouter::Uniswap3Routers,
-                        )
-                        .await?
-                        .0;
-                    (
-                        self.router_service
-                            .get_amount_out(
-                                ERouter::Uniswap3Routers,
-                                &pool_address,
-                                false,
-                                Some(&self.env.token_address),
-                                Some(&self.weth_address),
-                                sell_token_amount,
-                                self.sell_tax,
-                            )
-                            .await?,
-                        self.router_service
-                            .get_token_native_price(ERouter::Uniswap3Routers, pool_address)
-                            .await?,
-                    )
-                }
-                ERouter::UniversalRouters => {
-                    pool_address = self
-                        .router_service
-                        .get_pair_address_by_router(
-                            &self.env.token_address,
-                            &self.weth_address,
+                            &pool_address,
                             false,
+                            Some(&self.env.token_address),
+                            Some(&self.weth_address),
                             None,
-                            ERouter::UniversalRouters,
+                            sell_token_amount,
+                            add_tax_bps(0, sell_tax),
                         )
-                        .await?
-                        .0;
-                    (
-                        sell_tx_value,
-                        self.router_service
-                            .get_token_native_price(ERouter::UniversalRouters, pool_address)
-                            .await?,
+                        .await?,
+                    self.router_service
+                        .get_token_native_price(ERouter::Uniswap3Routers, pool_address, false)
+                        .await?,
+                )
+            }
+            ERouter::UniversalRouters => {
+                pool_address = self
+                    .router_service
+                    .get_pair_address_by_router(
+                        &self.env.token_address,
+                        &self.weth_address,
+                        false,
+                        None,
+                        ERouter::UniversalRouters,
                     )
-                }
-            };
-
-            if token_price > self.floor_price {
-                log::warn!(
-                    "token_price {:?} bigger than floor_price {:?}, skip",
-                    token_price,
-                    self.floor_price
-                );
-                continue;
+                    .await?
+                    .0;
+                (
+                    sell_tx_value,
+                    self.router_service
+                        .get_token_native_price(ERouter::UniversalRouters, pool_address, false)
+                        .await?,
+                )
             }
+            // aerodrome swaps are not decoded from the mempool, the event mode catches them
+            ERouter::Aerodrome => return Ok(()),
+        };
 
-            log::info!("transaction_value tx sell: {:#?}", transaction_value);
-
-            // if self.auto_buyer_system_wallets.contains_key(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from buyer system wallet {:?}, skip",
-            //         tx.hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-            // if self.buyer_system_wallets.contains(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from auto buyer system wallet {:?}, skip",
-            //         tx.hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-            // if self.seller_system_wallets.contains(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from seller system wallet {:?}, skip",
-            //         tx.hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-            // if self.market_maker_system_wallets.contains(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from market maker system wallet {:?}, skip",
-            //         tx.hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-
-            // set tx trigger to cache
-            let mut tx_hashes_cache = tx_hashes_cache.lock().await;
-            tx_hashes_cache.cache_set(tx.hash, true);
-            drop(tx_hashes_cache);
-
-            println!(
-                "token_price: {:#?}, transaction_value: {:#?}, pool_address: {:#?}",
-                token_price, transaction_value, pool_address
-            );
+        if self
+            .floor_price
+            .skips_trigger(token_price, &self.price_oracle)
+            .await
+        {
+            return Ok(());
+        }
 
-            self.process_trigger_buy(
-                &self.auto_buyer_system_wallets,
-                transaction_hash,
-                token_price,
-                transaction_value,
-                &pool_address,
-                true,
-            )
-            .await?;
+        log::info!("transaction_value tx sell: {:#?}", transaction_value);
+
+        // claimed here or by the event mode, whichever sees the tx first triggers
+        if cache_get_or_insert(tx_hashes_cache, tx.hash).await {
+            log::warn!("Meet this tx hash before from event mode: {:#?}", tx.hash);
+            return Ok(());
         }
 
+        log::info!(
+            "token_price: {:#?}, transaction_value: {:#?}, pool_address: {:#?}",
+            token_price,
+            transaction_value,
+            pool_address
+        );
+
+        self.process_trigger_buy(
+            &self.auto_buyer_system_wallets,
+            transaction_hash,
+            token_price,
+            transaction_value,
+            &pool_address,
+            true,
+            tx.gas_price,
+        )
+        .await?;
+
         Ok(())
     }
 
     async fn detect_sell_tx(
         mut self,
         pair_address: Address,
+        mut receiver: broadcast::Receiver<Log>,
         tx_hashes_cache: Arc<Mutex<TimedCache<H256, bool>>>,
     ) -> anyhow::Result<()> {
-        let erc20_transfer_filter = Filter::new()
-            .from_block(BlockNumber::Latest)
-            .event("Transfer(address,address,uint256)")
-            .topic1(H256::from(pair_address))
-            .address(self.weth_address);
+        // acquire the healthy provider once, refreshed only on provider errors
+        self.refresh_http_provider().await?;
+        let pair_topic = H256::from(pair_address);
 
-        let mut receiver = WsProviders::subscribe_logs_stream(
-            &self.env.listen_network,
-            erc20_transfer_filter,
-            false,
-        )
-        .await?;
+        // (trigger tx hash -> block number) of logs we already reacted on
+        let mut triggered_blocks: TimedCache<H256, U64> = TimedCache::with_lifespan(600);
 
         // assume that tx is success because there is an Transfer event
         loop {
@@ -533,91 +709,96 @@ impl AutoBuyService {
             let Ok(next_value) = timeout(Duration::from_millis(100), receiver.recv()).await else {
                 continue;
             };
-            let Ok(log) = next_value else {
-                break;
+            let log = match next_value {
+                Ok(log) => log,
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!(
+                        "[AutoBuyService] detector of pair {:?} lagged, skipped {:?} logs",
+                        pair_address,
+                        skipped
+                    );
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
             };
+            // the shared subscription delivers the logs of every pair
+            if log.topics.get(1) != Some(&pair_topic) {
+                continue;
+            }
 
-            // get healthy provider
-            self.http_provider = Arc::new(
-                HttpProviders::get_provider(
-                    &self.env.listen_network,
-                    false,
-                    self.provider_index.clone(),
+            // never trade on a log dropped by a reorg
+            if is_removed_log(&log) {
+                self.handle_removed_log(
+                    &mut triggered_blocks,
+                    log.transaction_hash.unwrap_or_default(),
                 )
-                .await?,
-            );
-
-            let transaction_hash = log.transaction_hash.unwrap_or_default();
-
-            tokio::time::sleep(Duration::from_secs(1)).await; // wait for mempool to cache first
-            let mut tx_hashes_cache = tx_hashes_cache.lock().await;
-            if tx_hashes_cache.cache_get(&transaction_hash).is_some() {
-                log::warn!(
-                    "[Auto buy] Meet this tx hash before from mempool mode: {:#?}",
-                    transaction_hash
-                );
-                drop(tx_hashes_cache);
+                .await?;
                 continue;
             }
 
+            let transaction_hash = log.transaction_hash.unwrap_or_default();
+
             let Ok(IUniswapV2PairAbigenEvents::TransferFilter(decoded)) = parse_log(log) else {
                 continue;
             };
-            let tx = self
+            let tx = match self
                 .http_provider
                 .get_transaction_receipt(transaction_hash)
-                .await?;
+                .await
+            {
+                Ok(tx) => tx,
+                Err(err) => {
+                    log::warn!(
+                        "[AutoBuyService] provider error, refresh provider: {:?}",
+                        err
+                    );
+                    self.refresh_http_provider().await?;
+                    self.http_provider
+                        .get_transaction_receipt(transaction_hash)
+                        .await?
+                }
+            };
             let Some(tx) = tx else {
                 log::warn!("cannot fetch tx {:?} from fullnode", transaction_hash);
                 continue;
             };
-            // if self.auto_buyer_system_wallets.contains_key(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from auto buyer system wallet {:?}, skip",
-            //         tx.transaction_hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-            // if self.buyer_system_wallets.contains(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from buyer system wallet {:?}, skip",
-            //         tx.transaction_hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-            // if self.seller_system_wallets.contains(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from seller system wallet {:?}, skip",
-            //         tx.transaction_hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-            // if self.market_maker_system_wallets.contains(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from market maker system wallet {:?}, skip",
-            //         tx.transaction_hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
+            if self.is_own_wallet(&tx.from) {
+                log::warn!(
+                    "tx {:?} from own system wallet {:?}, skip",
+                    tx.transaction_hash,
+                    tx.from
+                );
+                continue;
+            }
 
             let token_price = self
                 .router_service
-                .get_token_native_price(self.router_service.active_router, pair_address)
+                .get_token_native_price(self.router_service.active_router, pair_address, false)
                 .await?;
 
-            if token_price > self.floor_price {
+            if self
+                .floor_price
+                .skips_trigger(token_price, &self.price_oracle)
+                .await
+            {
+                continue;
+            }
+
+            // claimed here or by the mempool mode, whichever sees the tx first triggers
+            if cache_get_or_insert(&tx_hashes_cache, transaction_hash).await {
                 log::warn!(
-                    "token_price {:?} bigger than floor_price {:?}, skip",
-                    token_price,
-                    self.floor_price
+                    "[Auto buy] Meet this tx hash before from mempool mode: {:#?}",
+                    transaction_hash
                 );
                 continue;
             }
 
+            if self.reorg_depth > 0 {
+                if let Some(block_number) = tx.block_number {
+                    triggered_blocks.cache_set(transaction_hash, block_number);
+                }
+            }
+
             self.process_trigger_buy(
                 &self.auto_buyer_system_wallets,
                 transaction_hash,
@@ -625,6 +806,7 @@ impl AutoBuyService {
                 decoded.value,
                 &pair_address,
                 false,
+                None,
             )
             .await?;
         }
@@ -632,6 +814,55 @@ impl AutoBuyService {
         Ok(())
     }
 
+    fn weth_transfer_filter(&self, pair_topics: Vec<H256>) -> Filter {
+        Filter::new()
+            .from_block(BlockNumber::Latest)
+            .event("Transfer(address,address,uint256)")
+            .topic1(pair_topics)
+            .address(self.weth_address)
+    }
+
+    /// switch to the healthy provider
+    async fn refresh_http_provider(&mut self) -> anyhow::Result<()> {
+        self.http_provider = Arc::new(self.provider_pool.get_provider().await);
+        Ok(())
+    }
+
+    async fn handle_removed_log(
+        &self,
+        triggered_blocks: &mut TimedCache<H256, U64>,
+        transaction_hash: H256,
+    ) -> anyhow::Result<()> {
+        let Some(trigger_block) = triggered_blocks.cache_remove(&transaction_hash) else {
+            log::warn!(
+                "[AutoBuyService] ignore removed log of tx {:?}",
+                transaction_hash
+            );
+            return Ok(());
+        };
+
+        let current_block = self.http_provider.get_block_number().await?;
+        if !is_within_reorg_depth(trigger_block, current_block, self.reorg_depth) {
+            return Ok(());
+        }
+
+        log::warn!(
+            "[AutoBuyService] trigger sell tx {:?} at block {:?} was removed by a reorg, buy already executed",
+            transaction_hash,
+            trigger_block
+        );
+        let message = format!(
+            "[AutoBuyService] trigger sell tx {:?} at block {:?} was removed by a reorg, buy already executed",
+            transaction_hash, trigger_block
+        );
+        MessageTransportService::new()
+            .send_critical_message(message)
+            .await;
+
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
     async fn process_trigger_buy(
         &self,
         system_wallets: &HashMap<Address, Arc<RwLock<WalletContext>>>,
@@ -640,7 +871,21 @@ impl AutoBuyService {
         sell_value: U256,
         pair_address: &Address,
         is_from_mempool: bool,
+        trigger_gas_price: Option<U256>,
     ) -> anyhow::Result<()> {
+        // a misconfigured TOKEN_ADDRESS or a honeypot pair must not be bought
+        if !self.token_guard.is_allowed(&self.token_info.address)
+            || self.token_guard.is_blocked(pair_address)
+        {
+            log::warn!(
+                "[AutoBuyService] token {:?} pair {:?} rejected by TOKEN_ALLOWLIST / TOKEN_BLACKLIST, skip sell tx {:?}",
+                self.token_info.address,
+                pair_address,
+                tx_hash
+            );
+            return Ok(());
+        }
+
         let message_transport_service = MessageTransportService::new();
 
         if is_from_mempool {
@@ -652,7 +897,7 @@ impl AutoBuyService {
                 "[AutoAutoBuyService] trigger buy from mempool mode for sell tx {:?}",
                 tx_hash
             );
-            message_transport_service.send_message(message).await?;
+            message_transport_service.send_message(message).await;
         } else {
             log::info!(
                 "[AutoAutoBuyService] trigger auto buy from event mode for sell tx {:?}",
@@ -662,68 +907,54 @@ impl AutoBuyService {
                 "[AutoAutoBuyService] trigger buy from event mode for sell tx {:?}",
                 tx_hash
             );
-            message_transport_service.send_message(message).await?;
+            message_transport_service.send_message(message).await;
         }
 
         let auto_buy_min_percent = self.auto_buy_min_percent;
         let auto_buy_max_percent = self.auto_buy_max_percent;
         let buy_percent = rand::thread_rng().gen_range(auto_buy_min_percent..=auto_buy_max_percent);
-        let mut total_buy_amount = sell_value * U256::from(buy_percent) / U256::from(100);
+        let total_buy_amount = sell_value * U256::from(buy_percent) / U256::from(100);
 
         log::info!(
             "[AutoAutoBuyService] total buy amount to buy {:?}",
             total_buy_amount
         );
-        let mut wallet_configs: Vec<(Address, U256)> = Vec::new(); // (wallet_index, token_buy_amount)
-        let mut the_chosen_ones: Vec<Address> = Vec::new();
+        let mut wallet_balances: Vec<(Address, U256)> = Vec::new();
+        let now = Instant::now();
         for wallet in system_wallets.values() {
-            if total_buy_amount == U256::zero() {
-                break;
-            }
             // try write, if wallet is in used, skip it
             let Ok(wallet_context) = wallet.try_write() else {
                 continue;
             };
-            if wallet_context.eth_balance <= self.auto_buyer_surplus_balance {
+            // back to back buys from one address fingerprint it
+            if wallet_context.is_cooling_down(self.min_wallet_cooldown, now) {
                 continue;
             }
-            if wallet_context.eth_balance < self.auto_buyer_surplus_balance {
-                log::warn!(
-                    "auto buy wallet index {:#?} balance is lower than auto_buyer_surplus_balance",
-                    wallet_context.index
-                )
-            }
-            if wallet_context.eth_balance - self.auto_buyer_surplus_balance <= total_buy_amount {
-                wallet_configs.push((
-                    wallet_context.address,
-                    wallet_context.eth_balance - self.auto_buyer_surplus_balance,
-                ));
-                total_buy_amount += self.auto_buyer_surplus_balance;
-                total_buy_amount -= wallet_context.eth_balance;
-                continue;
-            }
-
-            the_chosen_ones.push(wallet_context.address);
+            wallet_balances.push((wallet_context.address, wallet_context.eth_balance));
         }
-
-        if total_buy_amount > U256::zero() {
-            let the_chosen_one = the_chosen_ones.choose(&mut rand::thread_rng());
-            if let Some(the_chosen_one) = the_chosen_one {
-                wallet_configs.push((*the_chosen_one, total_buy_amount));
-            } else {
-                log::warn!(
-                    "cannot find any wallet for total_buy_amount {:?}",
-                    total_buy_amount
-                );
-                let message = format!(
-                    "Cannot find any wallet for total_buy_amount {:#?} {:#?}",
-                    format_units(total_buy_amount, self.token_info.decimals as usize)?,
-                    self.token_info.symbol
-                );
-                message_transport_service.send_message(message).await?;
-            }
+        let (wallet_configs, uncovered_buy_amount) = distribute_buy_amount(
+            self.buy_distribution,
+            &wallet_balances,
+            self.auto_buyer_surplus_balance,
+            total_buy_amount,
+            &mut rand::thread_rng(),
+        ); // (wallet_address, eth_buy_amount)
+        if !uncovered_buy_amount.is_zero() {
+            log::warn!(
+                "cannot find any wallet for total_buy_amount {:?}",
+                uncovered_buy_amount
+            );
+            let message = format!(
+                "Cannot find any wallet for total_buy_amount {:#?} {:#?}",
+                format_units(uncovered_buy_amount, self.token_info.decimals as usize)?,
+                self.token_info.symbol
+            );
+            message_transport_service
+                .send_critical_message(message)
+                .await;
         }
 
+        let gas_price = self.reactive_gas_price(trigger_gas_price).await;
         for (wallet_address, buy_amount) in wallet_configs {
             let Some(wallet_context) = system_wallets.get(&wallet_address) else {
                 continue;
@@ -734,7 +965,13 @@ impl AutoBuyService {
 
             tokio::spawn(async move {
                 let _ = buy_service
-                    .try_buy(&wallet_context, buy_amount, token_price, &pair_address)
+                    .try_buy(
+                        &wallet_context,
+                        buy_amount,
+                        token_price,
+                        &pair_address,
+                        gas_price,
+                    )
                     .await;
             });
         }
@@ -742,15 +979,43 @@ impl AutoBuyService {
         Ok(())
     }
 
+    /// price the reactive trade relative to the trigger tx when `REACTIVE_GAS_PRICE_PERCENT` is set
+    async fn reactive_gas_price(&self, trigger_gas_price: Option<U256>) -> Option<U256> {
+        let (percent, max_gas_price) = self.reactive_gas_config?;
+        let trigger_gas_price = trigger_gas_price?;
+        let gas_price = *self.gas_price.read().await;
+        Some(compute_reactive_gas_price(
+            gas_price,
+            trigger_gas_price,
+            percent,
+            max_gas_price,
+        ))
+    }
+
     async fn try_buy(
         &self,
         wallet_context: &Arc<RwLock<WalletContext>>,
         buy_amount: U256,
         token_price: f64,
         pair_address: &Address,
+        gas_price: Option<U256>,
     ) -> Result<bool, Box<dyn std::error::Error>> {
+        if !self.router_service.check_min_liquidity(pair_address).await {
+            return Ok(false);
+        }
+        // no reactive buy while the gas is above MAX_GAS_PRICE_GWEI
+        if !self.router_service.is_gas_acceptable().await {
+            return Ok(false);
+        }
         let message_transport_service = MessageTransportService::new();
         let mut wallet_context_mut = wallet_context.write().await;
+        // a stuck buy may still be mined, a new one would double it
+        if wallet_context_mut
+            .is_stuck(self.env.token_address, self.http_provider.clone())
+            .await?
+        {
+            return Ok(false);
+        }
 
         let wallet = self.load_wallet(wallet_context_mut.index)?;
         log::info!(
@@ -771,6 +1036,7 @@ impl AutoBuyService {
                 buy_amount,
                 pair_address,
                 true,
+                gas_price,
             )
             .await
         {
@@ -784,34 +1050,92 @@ impl AutoBuyService {
         let buy_tx_hash = compute_transaction_hash(&signed_buy_tx);
 
         log::info!("[BuyService] constructed buy tx hash {:?}", buy_tx_hash);
-        let pending_tx = self.http_provider.send_raw_transaction(signed_buy_tx).await;
+        let pending_tx = self
+            .private_send_service
+            .send_raw_transaction(&self.http_provider, signed_buy_tx)
+            .await;
 
         match pending_tx {
             Ok(pending_tx) => {
-                let tx_receipt = timeout(Duration::from_secs(10), pending_tx)
+                // the cooldown starts on submit, whatever the receipt
+                wallet_context_mut.last_buy_at = Some(Instant::now());
+                // persisted before waiting, a restart meanwhile must not reuse the nonce
+                if let Err(err) = self
+                    .nonce_manager
+                    .record_submitted(wallet_context_mut.address, wallet_context_mut.nonce)
                     .await
-                    .map_err(|err| anyhow!("Timeout occurred: {}", err))??
-                    .ok_or_else(|| anyhow!("Cannot find tx_receipt"))?;
+                {
+                    log::warn!("[AutoBuyService] cannot persist nonce {:?}", err);
+                }
+                let tx_hash = pending_tx.tx_hash();
+                let Some(tx_receipt) = await_receipt_or_resync(
+                    pending_tx,
+                    tx_hash,
+                    self.buy_tx_timeout,
+                    &mut wallet_context_mut,
+                    self.env.token_address,
+                    self.http_provider.clone(),
+                )
+                .await?
+                else {
+                    return Ok(true);
+                };
+                if let Err(err) = self
+                    .private_send_service
+                    .inspect_sandwich(&self.http_provider, &tx_receipt)
+                    .await
+                {
+                    log::warn!("[AutoBuyService] inspect sandwich failed {:?}", err);
+                }
 
                 let message: String = if tx_receipt.status == Some(U64::zero()) {
-                    log::warn!("Buy transaction {:#?} failed", buy_tx_hash);
-                    format!(
-                        "Buy transaction {:#?} failed \nToken price: {:#?} ETH\nVolume: {:#?} ETH",
-                        buy_tx_hash,
+                    log::warn!(
+                        event = "buy_reverted",
+                        wallet_index = wallet_context_mut.index,
+                        tx_hash:? = buy_tx_hash,
+                        amount:% = format_ether(buy_amount),
+                        token_price = token_price;
+                        "Buy transaction {:#?} failed",
+                        buy_tx_hash
+                    );
+                    format_buy_message(
+                        &buy_tx_hash,
+                        false,
                         token_price,
-                        format_ether(buy_amount)
+                        buy_amount,
+                        self.native_symbol,
                     )
                 } else {
-                    log::info!("[AutoBuyService] tx success {:?}", buy_tx_hash);
+                    log::info!(
+                        event = "buy_sent",
+                        wallet_index = wallet_context_mut.index,
+                        tx_hash:? = buy_tx_hash,
+                        amount:% = format_ether(buy_amount),
+                        token_price = token_price;
+                        "[AutoBuyService] tx success {:?}",
+                        buy_tx_hash
+                    );
                     wallet_context_mut.eth_balance -= buy_amount;
-                    format!(
-                        "Buy transaction {:#?} success \nToken price: {:#?} ETH\nVolume: {:#?} ETH",
-                        buy_tx_hash,
+                    self.reorg_watcher
+                        .record(wallet_context_mut.address, &tx_receipt);
+                    if let Err(err) = self.pnl_ledger.record_buy(
+                        self.env.token_address,
+                        WalletGroup::AutoBuyer,
+                        buy_amount,
+                    ) {
+                        log::warn!("[AutoBuyService] cannot record pnl {:?}", err);
+                    }
+                    format_buy_message(
+                        &buy_tx_hash,
+                        true,
                         token_price,
-                        format_ether(buy_amount)
+                        buy_amount,
+                        self.native_symbol,
                     )
                 };
-                message_transport_service.send_message(message).await?;
+                message_transport_service
+                    .send_outcome_message(message, tx_receipt.status == Some(U64::zero()))
+                    .await;
                 wallet_context_mut.nonce += U256::one();
 
                 Ok(true)
@@ -821,16 +1145,12 @@ impl AutoBuyService {
                 let token_contract =
                     MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
                 let balance_of = token_contract.balance_of(wallet_context_mut.address);
-                let (token_balance, eth_balance, nonce) = tokio::join!(
+                let (token_balance, prefetched) = tokio::join!(
                     balance_of.call(),
-                    self.http_provider
-                        .get_balance(wallet_context_mut.address, None),
-                    self.http_provider
-                        .get_transaction_count(wallet_context_mut.address, None)
+                    prefetch_nonce_and_balance(&self.http_provider, wallet_context_mut.address)
                 );
                 let token_balance = token_balance?;
-                let eth_balance = eth_balance?;
-                let nonce = nonce?;
+                let (nonce, eth_balance) = prefetched?;
                 wallet_context_mut.token_balance = token_balance;
                 wallet_context_mut.eth_balance = eth_balance;
                 wallet_context_mut.nonce = nonce;
@@ -839,9 +1159,97 @@ impl AutoBuyService {
         }
     }
 
-    fn load_wallet(&self, index: u32) -> Result<LocalWallet, WalletError> {
-        let wallet = load_mnemonic_wallet(&self.auto_buyer_mnemonic, index)?;
+    fn load_wallet(&self, index: u32) -> Result<WalletSigner, WalletSignerError> {
+        let wallet = load_mnemonic_wallet(
+            &self.auto_buyer_mnemonic,
+            index,
+            self.env.listen_network,
+            None,
+        )?;
         let wallet = wallet.with_chain_id(self.env.chain_id.as_u64());
         Ok(wallet)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{abi::encode, utils::keccak256};
+    use provider_utils::enums::{ENetwork, EProviderStrategy};
+
+    const TEST_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+    #[tokio::test]
+    async fn removed_log_does_not_reach_the_detector() {
+        for (key, value) in [
+            ("AUTO_BUYER_MNEMONIC", TEST_MNEMONIC),
+            ("BUYER_MNEMONIC", TEST_MNEMONIC),
+            ("SELLER_MNEMONIC", TEST_MNEMONIC),
+            ("AUTO_BUYER_WALLETS_COUNT", "1"),
+            ("BUYER_WALLETS_COUNT", "1"),
+            ("SELLER_WALLETS_COUNT", "1"),
+            ("AUTO_BUY_MIN_PERCENT", "10"),
+            ("AUTO_BUY_MAX_PERCENT", "20"),
+            ("AUTO_BUYER_SURPLUS_BALANCE", "0.01"),
+            ("REORG_DEPTH", "3"),
+            (
+                "DEPLOYER_PRIVATE_KEY",
+                "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            ),
+            ("TRADING_SLIPPAGE", "1"),
+            ("ACTIVE_ROUTER", "UNISWAP2_ROUTERS"),
+        ] {
+            std::env::set_var(key, value);
+        }
+        let mut env = Env {
+            listen_network: ENetwork::BaseMainnet,
+            ..Default::default()
+        };
+        env.token.floor_price = Some(1.0);
+        // nothing listens, any receipt lookup of the detector fails
+        let http_provider = Provider::<Http>::try_from("http://127.0.0.1:1").unwrap();
+        let auto_buy_service = AutoBuyService::new(
+            env,
+            Arc::new(RwLock::new(U256::zero())),
+            ProviderPool::new(
+                vec![http_provider.clone()],
+                EProviderStrategy::default(),
+                Vec::new(),
+                Arc::new(RwLock::new(0)),
+            ),
+            Arc::new(http_provider),
+        );
+        let pair_address = Address::random();
+        let sell_log = |removed| Log {
+            topics: vec![
+                H256::from(keccak256("Transfer(address,address,uint256)")),
+                H256::from(pair_address),
+                H256::from(Address::random()),
+            ],
+            data: encode(&[U256::exp10(18).into_token()]).into(),
+            transaction_hash: Some(H256::random()),
+            block_number: Some(U64::from(100)),
+            removed: Some(removed),
+            ..Default::default()
+        };
+        let detect = |log| {
+            let auto_buy_service = auto_buy_service.clone();
+            async move {
+                let (log_sender, log_receiver) = broadcast::channel(16);
+                log_sender.send(log).unwrap();
+                drop(log_sender);
+                auto_buy_service
+                    .detect_sell_tx(
+                        pair_address,
+                        log_receiver,
+                        Arc::new(Mutex::new(TimedCache::with_lifespan(180))),
+                    )
+                    .await
+            }
+        };
+
+        // the removed log is skipped before its tx is fetched
+        assert!(detect(sell_log(true)).await.is_ok());
+        assert!(detect(sell_log(false)).await.is_err());
+    }
+}
@@ -2,13 +2,17 @@ use crate::{
     constants::Env,
     core::MessageTransportService,
     routers::RouterService,
-    types::TokenInfo,
-    utils::{compute_system_wallets, WalletContext},
+    types::{TokenInfo, WalletGroup},
+    utils::{
+        await_receipt_or_resync, compute_system_wallets, compute_tokens_received, fetch_token_info,
+        format_buy_message, get_native_symbol, prefetch_nonce_and_balance, GasJitter, NonceManager,
+        PnlLedger, ReorgWatcher, WalletContext,
+    },
 };
 use anyhow::anyhow;
 use ethers::{
-    providers::{Http, Middleware, Provider},
-    signers::{LocalWallet, Signer, WalletError},
+    providers::{Http, Provider},
+    signers::Signer,
     types::{Address, Bytes, U256, U64},
     utils::{format_ether, parse_ether},
 };
@@ -17,30 +21,51 @@ use mm_token_utils::{
     abi::MemeTokenAbigen,
     constants::WRAPPED_NATIVE_TOKENS,
     env::get_env,
+    signer::{WalletSigner, WalletSignerError},
     utils::{compute_transaction_hash, load_mnemonic_wallet},
 };
-use provider_utils::{constants::DESERIALIZATION_ERROR_MSG, http_providers::HttpProviders};
+use provider_utils::{
+    constants::{SEND_TX_BASE_DELAY_MS, SEND_TX_MAX_ATTEMPTS},
+    provider_pool::ProviderPool,
+    send_transaction::send_raw_transaction_with_retry,
+    tx_error::TxError,
+};
 use std::{collections::HashMap, sync::Arc, time::Duration};
-use tokio::{sync::RwLock, task, time::timeout};
+use tokio::{sync::RwLock, task};
+
+// delay before a retryable buy error is retried, doubled on each consecutive one
+const RETRY_BASE_BACKOFF: Duration = Duration::from_secs(1);
+const RETRY_MAX_BACKOFF: Duration = Duration::from_secs(30);
 
 #[derive(Debug, Clone)]
 pub struct BuyService {
     env: Env,
+    // ETH, BNB or FTM in the messages
+    native_symbol: &'static str,
     http_provider: Arc<Provider<Http>>,
     weth_address: Address,
     token_info: TokenInfo,
-    provider_index: Arc<RwLock<usize>>,
+    provider_pool: ProviderPool,
     buyer_mnemonic: String,
     buyer_surplus_balance: U256,
     buyer_wallets_count: u32,
+    gas_price: Arc<RwLock<U256>>,
+    gas_jitter: GasJitter,
+    // a buy not mined within BUY_TX_TIMEOUT_SECS is abandoned and the wallet resynced
+    buy_tx_timeout: Duration,
     router_service: RouterService,
+    nonce_manager: NonceManager,
+    // eth in / out of the trades, read by the /pnl command
+    pnl_ledger: PnlLedger,
+    // resyncs the wallets of a buy reorged out within REORG_DEPTH blocks
+    reorg_watcher: ReorgWatcher,
 }
 
 impl BuyService {
     pub fn new(
         env: Env,
         gas_price: Arc<RwLock<U256>>,
-        provider_index: Arc<RwLock<usize>>,
+        provider_pool: ProviderPool,
         http_provider: Arc<Provider<Http>>,
     ) -> Self {
         let Some(weth) = WRAPPED_NATIVE_TOKENS.get(&env.listen_network) else {
@@ -51,33 +76,44 @@ impl BuyService {
         };
 
         Self {
+            native_symbol: get_native_symbol(&env.listen_network),
             env: env.clone(),
             http_provider: http_provider.clone(),
             weth_address: weth.address,
             token_info: TokenInfo::default(),
-            provider_index,
+            provider_pool,
             buyer_mnemonic: get_env("BUYER_MNEMONIC", None),
             buyer_surplus_balance: parse_ether(get_env("BUYER_SURPLUS_BALANCE", None)).unwrap(),
             buyer_wallets_count: get_env("BUYER_WALLETS_COUNT", None).parse().unwrap(),
+            gas_price: gas_price.clone(),
+            gas_jitter: GasJitter::from_env(true),
+            buy_tx_timeout: Duration::from_secs(
+                get_env("BUY_TX_TIMEOUT_SECS", Some("10".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
             router_service: RouterService::new(env, gas_price, http_provider),
+            nonce_manager: NonceManager::new(),
+            pnl_ledger: PnlLedger::from_env(),
+            reorg_watcher: ReorgWatcher::new(
+                get_env("REORG_DEPTH", Some("0".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
         }
     }
 
+    /// `GAS_PRICE_JITTER_BUY_SELL` jitters the shared gas price of each buy
+    async fn jittered_gas_price(&self) -> Option<U256> {
+        let gas_price = *self.gas_price.read().await;
+        self.gas_jitter
+            .apply(gas_price, self.http_provider.clone())
+            .await
+    }
+
     pub async fn init(&mut self) -> anyhow::Result<()> {
-        let token_info_call =
-            MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
-        let symbol: String = token_info_call.symbol().call().await.unwrap();
-        let name: String = token_info_call.name().call().await.unwrap();
-        let decimals: u8 = token_info_call.decimals().call().await.unwrap();
-        let total_supply: U256 = token_info_call.total_supply().call().await.unwrap();
-
-        self.token_info = TokenInfo {
-            address: self.env.token_address,
-            symbol,
-            name,
-            decimals,
-            total_supply,
-        };
+        self.token_info =
+            fetch_token_info(self.env.token_address, self.http_provider.clone()).await?;
 
         Ok(())
     }
@@ -85,15 +121,17 @@ impl BuyService {
     pub async fn start_event_mode(&self) -> anyhow::Result<()> {
         let message_transport_service = MessageTransportService::new();
         let message = "Buy service have been launch".to_string();
-        message_transport_service.send_message(message).await?;
+        message_transport_service.send_message(message).await;
 
         let system_wallets = compute_system_wallets(
             &self.buyer_mnemonic,
             self.buyer_wallets_count,
+            self.env.listen_network,
             &self.env.token_address,
             self.http_provider.clone(),
         )
         .await?;
+        self.watch_reorgs(system_wallets.clone());
 
         let mut wallet_configs: Vec<(usize, Address)> = Vec::new(); // (wallet_index, wallet_address)
 
@@ -111,6 +149,17 @@ impl BuyService {
         Ok(())
     }
 
+    /// resync the wallets of `system_wallets` whose buy is reorged out, until exit
+    pub fn watch_reorgs(&self, system_wallets: HashMap<Address, Arc<RwLock<WalletContext>>>) {
+        self.reorg_watcher.spawn(
+            "BuyService",
+            self.env.token_address,
+            system_wallets,
+            self.http_provider.clone(),
+            self.env.exit.clone(),
+        );
+    }
+
     async fn listen(
         &self,
         system_wallets: &HashMap<Address, Arc<RwLock<WalletContext>>>,
@@ -140,26 +189,40 @@ impl BuyService {
         &mut self,
         wallet_context: Arc<RwLock<WalletContext>>,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut retry_backoff = RETRY_BASE_BACKOFF;
         loop {
             // get healthy provider
-            self.http_provider = Arc::new(
-                HttpProviders::get_provider(
-                    &self.env.listen_network,
-                    false,
-                    self.provider_index.clone(),
-                )
-                .await?,
-            );
+            self.http_provider = Arc::new(self.provider_pool.get_provider().await);
 
             let should_next = match self.try_buy(&wallet_context).await {
-                Ok(should_next) => should_next,
+                Ok(should_next) => Some(should_next),
                 Err(err) => {
-                    if err.to_string().contains(DESERIALIZATION_ERROR_MSG) {
-                        continue;
+                    if !TxError::classify(err.as_ref()).is_retryable() {
+                        return Err(err);
                     }
-                    return Err(err);
+                    log::warn!(
+                        "[BuyService] retry in {:?} after err={:?}",
+                        retry_backoff,
+                        err
+                    );
+                    None
+                }
+            };
+            let Some(should_next) = should_next else {
+                // a nonce too low or underpriced buy repeats until the wallet is read again from chain
+                if let Err(err) = wallet_context
+                    .write()
+                    .await
+                    .resync(self.env.token_address, self.http_provider.clone())
+                    .await
+                {
+                    log::warn!("[BuyService] resync fails err={:?}", err);
                 }
+                tokio::time::sleep(retry_backoff).await;
+                retry_backoff = (retry_backoff * 2).min(RETRY_MAX_BACKOFF);
+                continue;
             };
+            retry_backoff = RETRY_BASE_BACKOFF;
 
             if !should_next {
                 tokio::time::sleep(Duration::from_secs(2)).await;
@@ -171,7 +234,8 @@ impl BuyService {
         Ok(())
     }
 
-    async fn try_buy(
+    /// one buy of the wallet, false once it has nothing left to buy with
+    pub async fn try_buy(
         &self,
         wallet_context: &Arc<RwLock<WalletContext>>,
     ) -> Result<bool, Box<dyn std::error::Error>> {
@@ -180,8 +244,18 @@ impl BuyService {
 
         let wallet = self.load_wallet(wallet_context_mut.index)?;
 
+        if wallet_context_mut.suspected_honeypot {
+            return Ok(false);
+        }
+        // retried until the stuck buy is mined or dropped, a new buy would double it
+        if wallet_context_mut
+            .is_stuck(self.env.token_address, self.http_provider.clone())
+            .await?
+        {
+            return Ok(true);
+        }
         if wallet_context_mut.eth_balance <= self.buyer_surplus_balance {
-            println!(
+            log::info!(
                 "[BuyService] Wallet [{:?}] balance is less than threshold.",
                 wallet_context_mut.address,
             );
@@ -196,12 +270,20 @@ impl BuyService {
         {
             Ok(pair_address) => pair_address,
             Err(err) => {
-                println!("[BuyService] Error getting pair address: {:?}", err);
+                log::warn!("[BuyService] Error getting pair address: {:?}", err);
                 return Ok(true);
             }
         };
+        // retried until the pool is deep enough
+        if !self.router_service.check_min_liquidity(&pair_address).await {
+            return Ok(true);
+        }
+        // and until the gas falls back under MAX_GAS_PRICE_GWEI
+        if !self.router_service.is_gas_acceptable().await {
+            return Ok(true);
+        }
 
-        println!(
+        log::info!(
             "[BuyService] Trying to buy:
                 - Wallet Index: {:?} - Wallet Address: {:?}
                 - Token: {:?} - Amount: {:?} - Pair Address: {:?}",
@@ -212,9 +294,10 @@ impl BuyService {
             pair_address
         );
 
+        // every launch buy moves the price, a cached one would be a buy behind
         let token_price = self
             .router_service
-            .get_token_native_price(self.router_service.active_router, pair_address)
+            .get_token_native_price(self.router_service.active_router, pair_address, true)
             .await?;
 
         let signed_buy_tx = match self
@@ -225,66 +308,136 @@ impl BuyService {
                 buy_amount,
                 &pair_address,
                 true,
+                self.jittered_gas_price().await,
             )
             .await
         {
             Ok(signed_buy_tx) => signed_buy_tx,
             Err(err) => {
-                println!("[BuyService] try_buy {:?}", err);
+                log::warn!("[BuyService] try_buy {:?}", err);
                 return Ok(true);
             }
         };
 
         let buy_tx_hash = compute_transaction_hash(&signed_buy_tx);
 
-        let pending_tx = self.http_provider.send_raw_transaction(signed_buy_tx).await;
+        if self.env.dry_run {
+            if let Err(err) = self.router_service.simulate_tx(&signed_buy_tx).await {
+                log::warn!("[BuyService] dry run buy {:?} fails {:?}", buy_tx_hash, err);
+            }
+            // nothing was sent, the wallet nonce and balance stay as they are
+            return Ok(false);
+        }
+
+        let pending_tx = send_raw_transaction_with_retry(
+            self.http_provider.as_ref(),
+            signed_buy_tx,
+            SEND_TX_MAX_ATTEMPTS,
+            Duration::from_millis(SEND_TX_BASE_DELAY_MS),
+        )
+        .await;
 
         match pending_tx {
             Ok(pending_tx) => {
-                let tx_receipt = timeout(Duration::from_secs(3), pending_tx)
+                // persisted before waiting, a restart meanwhile must not reuse the nonce
+                if let Err(err) = self
+                    .nonce_manager
+                    .record_submitted(wallet_context_mut.address, wallet_context_mut.nonce)
                     .await
-                    .map_err(|err| anyhow!("Timeout occurred: {}", err))??
-                    .ok_or_else(|| anyhow!("Cannot find tx_receipt"))?;
+                {
+                    log::warn!("[BuyService] cannot persist nonce {:?}", err);
+                }
+                let tx_hash = pending_tx.tx_hash();
+                let Some(tx_receipt) = await_receipt_or_resync(
+                    pending_tx,
+                    tx_hash,
+                    self.buy_tx_timeout,
+                    &mut wallet_context_mut,
+                    self.env.token_address,
+                    self.http_provider.clone(),
+                )
+                .await?
+                else {
+                    return Ok(true);
+                };
 
                 let message: String = if tx_receipt.status == Some(U64::zero()) {
-                    println!("Buy transaction {:#?} failed", buy_tx_hash);
-                    format!(
-                        "Buy transaction {:#?} failed \nToken price: {:#?} ETH\nVolume: {:#?} ETH",
-                        buy_tx_hash,
+                    log::warn!("[BuyService] Buy transaction {:#?} failed", buy_tx_hash);
+                    format_buy_message(
+                        &buy_tx_hash,
+                        false,
                         token_price,
-                        format_ether(buy_amount)
+                        buy_amount,
+                        self.native_symbol,
                     )
                 } else {
-                    println!("[BuyService] tx success {:?}", buy_tx_hash);
+                    log::info!("[BuyService] tx success {:?}", buy_tx_hash);
                     wallet_context_mut.eth_balance -= buy_amount;
-                    format!(
-                        "Buy transaction {:#?} success \nToken price: {:#?} ETH\nVolume: {:#?} ETH",
-                        buy_tx_hash,
-                        token_price,
-                        format_ether(buy_amount)
-                    )
+                    self.reorg_watcher
+                        .record(wallet_context_mut.address, &tx_receipt);
+                    if let Err(err) = self.pnl_ledger.record_buy(
+                        self.env.token_address,
+                        WalletGroup::Buyer,
+                        buy_amount,
+                    ) {
+                        log::warn!("[BuyService] cannot record pnl {:?}", err);
+                    }
+                    // a successful buy must deliver tokens
+                    let tokens_received = compute_tokens_received(
+                        &tx_receipt,
+                        self.env.token_address,
+                        wallet_context_mut.address,
+                    );
+                    if tokens_received.is_zero() {
+                        wallet_context_mut.suspected_honeypot = true;
+                    }
+                    wallet_context_mut.token_balance += tokens_received;
+                    if wallet_context_mut.suspected_honeypot {
+                        log::error!(
+                            "[BuyService] buy {:?} succeeded but wallet {:?} received no token, suspected honeypot",
+                            buy_tx_hash,
+                            wallet_context_mut.address
+                        );
+                        format!(
+                            "🚨 Buy transaction {:#?} succeeded but delivered NO token, suspected honeypot!\nWallet: {:?} stops buying\nVolume: {:#?} {}",
+                            buy_tx_hash,
+                            wallet_context_mut.address,
+                            format_ether(buy_amount),
+                            self.native_symbol
+                        )
+                    } else {
+                        format_buy_message(
+                            &buy_tx_hash,
+                            true,
+                            token_price,
+                            buy_amount,
+                            self.native_symbol,
+                        )
+                    }
                 };
-                message_transport_service.send_message(message).await?;
+                message_transport_service
+                    .send_outcome_message(
+                        message,
+                        tx_receipt.status == Some(U64::zero())
+                            || wallet_context_mut.suspected_honeypot,
+                    )
+                    .await;
                 wallet_context_mut.nonce += U256::one();
 
-                Ok(true)
+                Ok(!wallet_context_mut.suspected_honeypot)
             }
             Err(err) => {
-                println!("reset wallet context because of {:?}", err);
+                log::warn!("[BuyService] reset wallet context because of {:?}", err);
 
                 let token_contract =
                     MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
                 let balance_of = token_contract.balance_of(wallet_context_mut.address);
-                let (token_balance, eth_balance, nonce) = tokio::join!(
+                let (token_balance, prefetched) = tokio::join!(
                     balance_of.call(),
-                    self.http_provider
-                        .get_balance(wallet_context_mut.address, None),
-                    self.http_provider
-                        .get_transaction_count(wallet_context_mut.address, None)
+                    prefetch_nonce_and_balance(&self.http_provider, wallet_context_mut.address)
                 );
                 let token_balance = token_balance?;
-                let eth_balance = eth_balance?;
-                let nonce = nonce?;
+                let (nonce, eth_balance) = prefetched?;
                 wallet_context_mut.token_balance = token_balance;
                 wallet_context_mut.eth_balance = eth_balance;
                 wallet_context_mut.nonce = nonce;
@@ -293,8 +446,9 @@ impl BuyService {
         }
     }
 
-    fn load_wallet(&self, index: u32) -> Result<LocalWallet, WalletError> {
-        let wallet = load_mnemonic_wallet(&self.buyer_mnemonic, index)?;
+    fn load_wallet(&self, index: u32) -> Result<WalletSigner, WalletSignerError> {
+        let wallet =
+            load_mnemonic_wallet(&self.buyer_mnemonic, index, self.env.listen_network, None)?;
         let wallet = wallet.with_chain_id(self.env.chain_id.as_u64());
         Ok(wallet)
     }
@@ -303,6 +457,7 @@ impl BuyService {
         let system_wallets = compute_system_wallets(
             &self.buyer_mnemonic,
             self.buyer_wallets_count,
+            self.env.listen_network,
             &self.env.token_address,
             self.http_provider.clone(),
         )
@@ -321,6 +476,12 @@ impl BuyService {
 
         let mut signed_txs: Vec<(Bytes, usize, U256)> = Vec::new();
 
+        if !self.router_service.is_gas_acceptable().await {
+            return Err(anyhow!(
+                "[BuyService.get_signed_buy_txs] gas price above MAX_GAS_PRICE_GWEI"
+            ));
+        }
+
         let (pair_address, _) = self
             .router_service
             .get_pair_address(&self.env.token_address, &self.weth_address, true)
@@ -350,6 +511,7 @@ impl BuyService {
                     buy_amount,
                     &pair_address,
                     false,
+                    self.jittered_gas_price().await,
                 )
                 .await?;
 
@@ -8,26 +8,31 @@ use std::{
 
 use anyhow::anyhow;
 use ethers::{
-    providers::{Middleware, StreamExt},
+    providers::{Http, Middleware, Provider, StreamExt},
     types::U256,
 };
-use provider_utils::{enums::ENetwork, http_providers::HttpProviders};
+use mm_token_utils::{constants::EGasMode, utils::get_gas_mode};
+use provider_utils::provider_pool::ProviderPool;
 use tokio::{
     sync::RwLock,
     time::{self, timeout},
 };
 use tokio_stream::wrappers::IntervalStream;
 
+use crate::utils::{fetch_eip1559_fees, GasJitter};
+
 pub struct GasPrice;
 
 impl GasPrice {
     pub async fn fetch_periodically(
         exit: Arc<AtomicBool>,
-        network: ENetwork,
-        provider_index: Arc<RwLock<usize>>,
+        provider_pool: ProviderPool,
         gas_price: Arc<RwLock<U256>>,
         duration: Duration,
     ) -> anyhow::Result<()> {
+        let gas_mode = get_gas_mode();
+        // the legacy gas price is jittered no lower than the base fee, refreshed along with it
+        let is_base_fee_cached = GasJitter::from_env(false).is_enabled();
         let mut stream = IntervalStream::new(time::interval(duration));
         loop {
             if exit.load(Ordering::Relaxed) {
@@ -38,11 +43,14 @@ impl GasPrice {
             };
 
             // get healthy provider
-            let http_provider = Arc::new(
-                HttpProviders::get_provider(&network, false, provider_index.clone()).await?,
-            );
+            let http_provider = Arc::new(provider_pool.get_provider().await);
 
-            let fetched_gas_price = match http_provider.get_gas_price().await {
+            if gas_mode == EGasMode::Legacy && is_base_fee_cached {
+                if let Err(err) = fetch_eip1559_fees(http_provider.clone()).await {
+                    log::warn!("[GasPrice] base fee unavailable: {:?}", err);
+                }
+            }
+            let fetched_gas_price = match Self::fetch_gas_price(gas_mode, http_provider).await {
                 Ok(gas_price) => gas_price,
                 Err(err) => {
                     if err
@@ -51,7 +59,7 @@ impl GasPrice {
                     {
                         continue;
                     }
-                    return Err(err.into());
+                    return Err(err);
                 }
             };
 
@@ -62,4 +70,23 @@ impl GasPrice {
             log::info!("[GasPrice] new gas price {:?}", fetched_gas_price);
         }
     }
+
+    /// in the EIP-1559 mode the shared gas price is the max fee, the fees are kept for `apply_gas_mode`
+    async fn fetch_gas_price(
+        gas_mode: EGasMode,
+        http_provider: Arc<Provider<Http>>,
+    ) -> anyhow::Result<U256> {
+        match gas_mode {
+            EGasMode::Legacy => Ok(http_provider.get_gas_price().await?),
+            EGasMode::Eip1559 => {
+                let fees = fetch_eip1559_fees(http_provider).await?;
+                log::info!(
+                    "[GasPrice] base fee {:?}, priority fee {:?}",
+                    fees.base_fee,
+                    fees.priority_fee
+                );
+                Ok(fees.max_fee())
+            }
+        }
+    }
 }
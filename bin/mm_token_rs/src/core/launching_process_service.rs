@@ -1,37 +1,94 @@
 use anyhow::anyhow;
-use cached::TimedCache;
 use ethers::{
     providers::{Http, Middleware, Provider},
+    signers::Signer,
     types::{Bytes, H256, U256},
+    utils::{keccak256, parse_ether},
 };
-use futures::{future::join_all, FutureExt};
-use mm_token_utils::env::get_env;
-use provider_utils::http_providers::HttpProviders;
+use futures::future::join_all;
+use mm_token_toolkit::bundler::{BloxrouteConfig, Bundler};
+use mm_token_utils::{env::get_env, utils::load_private_key_wallet};
+use provider_utils::{enums::ENetwork, http_providers::HttpProviders, provider_pool::ProviderPool};
 use std::{
     sync::{atomic::Ordering, Arc},
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio::{
-    sync::{Mutex, RwLock},
+    sync::RwLock,
     task::{self, JoinSet},
 };
 
-use crate::{constants::Env, routers::RouterService};
+use crate::{
+    constants::{Env, LaunchOrdering},
+    routers::RouterService,
+    types::LaunchTxPosition,
+    utils::{
+        check_bundle_positions, compute_bloxroute_tip_tx, get_bloxroute_tip_address,
+        get_bundle_positions, new_tx_hashes_cache,
+    },
+};
 
 use super::{BuyService, GasPrice, MarketMakerService, SellService, WalletService};
 
+#[derive(Debug, Clone, Default)]
+pub struct PhaseReport {
+    pub errors: Vec<String>,
+    pub duration: Duration,
+}
+
+/**
+ * active trading, buys and migrations run concurrently, each phase is reported separately
+ */
+#[derive(Debug, Clone, Default)]
+pub struct ActiveTradingReport {
+    pub active_trading: PhaseReport,
+    pub buy: PhaseReport,
+    pub migrate: PhaseReport,
+    pub ordering: LaunchOrdering,
+    // where the bundled txs landed, bundle ordering only
+    pub tx_positions: Vec<LaunchTxPosition>,
+}
+
+struct BuyAndMigrateResult {
+    buy: Result<(), String>,
+    buy_done_at: Instant,
+    // none when the buy failed
+    migrate: Option<(Result<(), String>, Instant)>,
+}
+
 #[derive(Debug, Clone)]
 pub struct LaunchingProcessService {
     env: Env,
     http_provider: Arc<Provider<Http>>,
+    ordering: LaunchOrdering,
+    bundle_max_blocks: u64,
 }
 
 impl LaunchingProcessService {
     pub fn new(env: Env, http_provider: Arc<Provider<Http>>) -> Self {
-        Self { env, http_provider }
+        Self {
+            env,
+            http_provider,
+            ordering: get_env("LAUNCH_ORDERING", Some("Concurrent".to_string()))
+                .parse()
+                .unwrap(),
+            bundle_max_blocks: get_env("LAUNCH_BUNDLE_MAX_BLOCKS", Some("5".to_string()))
+                .parse()
+                .unwrap(),
+        }
     }
 
-    pub async fn active_trading_and_buy(&self) -> anyhow::Result<()> {
+    pub async fn active_trading_and_buy(&self) -> anyhow::Result<ActiveTradingReport> {
+        // bundles are only available through bloxroute on bsc for now
+        if self.ordering == LaunchOrdering::Bundle
+            && ![ENetwork::BscMainnet, ENetwork::BscTestnet].contains(&self.env.listen_network)
+        {
+            return Err(anyhow!(
+                "[LaunchingProcessService.active_trading_and_buy] bundle ordering not support in {:?}",
+                self.env.listen_network
+            ));
+        }
+
         let mut futures = Vec::new();
 
         let fetched_gas_price = self.http_provider.get_gas_price().await?;
@@ -44,33 +101,61 @@ impl LaunchingProcessService {
         );
 
         let wallet_service = WalletService::new(self.env.clone(), self.http_provider.clone());
-        let provider_index: Arc<RwLock<usize>> = Arc::new(RwLock::new(
-            HttpProviders::init_provider_index(&self.env.listen_network, false).await?,
-        ));
+        let provider_pool = ProviderPool::init(&self.env.listen_network, false).await?;
 
         let buy_service = BuyService::new(
             self.env.clone(),
             gas_price,
-            provider_index.clone(),
+            provider_pool,
             self.http_provider.clone(),
         );
         let sign_txs = buy_service.get_signed_buy_txs().await?;
 
         let signed_active_trading_tx = router_service.get_active_trading_tx().await?;
+        if self.env.dry_run {
+            // buyer txs revert until trading is active on chain, only the activation is simulated
+            let start_time = Instant::now();
+            let mut report = ActiveTradingReport {
+                ordering: self.ordering,
+                ..Default::default()
+            };
+            if let Err(err) = router_service.simulate_tx(&signed_active_trading_tx).await {
+                report.active_trading.errors.push(err.to_string());
+            }
+            report.active_trading.duration = start_time.elapsed();
+            return Ok(report);
+        }
+        if self.ordering == LaunchOrdering::Bundle {
+            return self
+                .active_trading_and_buy_in_bundle(
+                    signed_active_trading_tx,
+                    sign_txs,
+                    wallet_service,
+                    fetched_gas_price,
+                )
+                .await;
+        }
+
         let http_provider = self.http_provider.clone();
+        let start_time = Instant::now();
         let active_trading_future = task::spawn(async move {
             match http_provider
                 .send_raw_transaction(signed_active_trading_tx)
                 .await
             {
-                Ok(response) => log::info!(
-                    "Active trading transaction sent successfully: {:?}",
-                    response
-                ),
-                Err(e) => log::error!("Failed to send active trading transaction: {:?}", e),
+                Ok(response) => {
+                    log::info!(
+                        "Active trading transaction sent successfully: {:?}",
+                        response
+                    );
+                    (Ok(()), Instant::now())
+                }
+                Err(e) => {
+                    log::error!("Failed to send active trading transaction: {:?}", e);
+                    (Err(e.to_string()), Instant::now())
+                }
             }
         });
-        futures.push(active_trading_future.boxed());
 
         for sign_tx in sign_txs {
             let http_clone = self.http_provider.clone();
@@ -78,30 +163,205 @@ impl LaunchingProcessService {
 
             // Spawn async task for each future
             let (sign_tx, wallet_index, buy_nonce) = sign_tx.clone();
-            let buy_and_migrate_future = task::spawn(async move {
-                match Self::buy_and_migrate_task(
-                    wallet_service_clone,
-                    sign_tx,
-                    http_clone,
-                    wallet_index,
-                    buy_nonce,
-                    fetched_gas_price,
-                )
-                .await
-                {
-                    Ok(response) => log::info!(
-                        "Buy and migrate task completed successfully: {:?}",
-                        response
-                    ),
-                    Err(e) => log::error!("Failed to complete buy and migrate task: {:?}", e),
+            let buy_and_migrate_future = task::spawn(Self::buy_and_migrate_task(
+                wallet_service_clone,
+                sign_tx,
+                http_clone,
+                wallet_index,
+                buy_nonce,
+                fetched_gas_price,
+            ));
+
+            futures.push(buy_and_migrate_future);
+        }
+
+        let (active_trading_result, buy_and_migrate_results) =
+            tokio::join!(active_trading_future, join_all(futures));
+
+        Ok(Self::concurrent_report(
+            start_time,
+            active_trading_result.map_err(|err| err.to_string()),
+            buy_and_migrate_results
+                .into_iter()
+                .map(|result| result.map_err(|err| err.to_string()))
+                .collect(),
+        ))
+    }
+
+    /// each phase is timed from the start until its own last tx, not until every task joined
+    fn concurrent_report(
+        start_time: Instant,
+        active_trading_result: Result<(Result<(), String>, Instant), String>,
+        buy_and_migrate_results: Vec<Result<BuyAndMigrateResult, String>>,
+    ) -> ActiveTradingReport {
+        let mut report = ActiveTradingReport::default();
+        match active_trading_result {
+            Ok((result, done_at)) => {
+                report.active_trading.duration = done_at.duration_since(start_time);
+                if let Err(err) = result {
+                    report.active_trading.errors.push(err);
                 }
-            });
+            }
+            Err(err) => {
+                report.active_trading.duration = start_time.elapsed();
+                report.active_trading.errors.push(err);
+            }
+        }
 
-            futures.push(buy_and_migrate_future.boxed());
+        let mut buy_done_at = start_time;
+        let mut migrate_done_at = start_time;
+        for result in buy_and_migrate_results {
+            let result = match result {
+                Ok(result) => result,
+                Err(err) => {
+                    report.buy.errors.push(err);
+                    continue;
+                }
+            };
+            buy_done_at = buy_done_at.max(result.buy_done_at);
+            if let Err(err) = result.buy {
+                report.buy.errors.push(err);
+            }
+            if let Some((migrate_result, done_at)) = result.migrate {
+                migrate_done_at = migrate_done_at.max(done_at);
+                if let Err(err) = migrate_result {
+                    report.migrate.errors.push(err);
+                }
+            }
         }
+        // migrations start as soon as their buy is sent, so the phases overlap
+        report.buy.duration = buy_done_at.duration_since(start_time);
+        report.migrate.duration = migrate_done_at.saturating_duration_since(buy_done_at);
 
-        join_all(futures).await;
-        Ok(())
+        report
+    }
+
+    /// sends tip, active trading then buys by wallet index as one bundle, resent each block until included
+    async fn active_trading_and_buy_in_bundle(
+        &self,
+        signed_active_trading_tx: Bytes,
+        mut sign_txs: Vec<(Bytes, usize, U256)>,
+        wallet_service: WalletService,
+        fetched_gas_price: U256,
+    ) -> anyhow::Result<ActiveTradingReport> {
+        let bundler = Bundler::new(
+            self.env.listen_network,
+            BloxrouteConfig {
+                relay_url: get_env("BLOXROUTE_RELAY_URL", None),
+                authorization_key: get_env("BLOXROUTE_AUTH_KEY", None),
+            },
+        );
+        let tip_wallet = load_private_key_wallet(&get_env("TIP_PK", None))?
+            .with_chain_id(self.env.chain_id.as_u64());
+        let tip_tx = compute_bloxroute_tip_tx(
+            &tip_wallet,
+            &self.env.listen_network,
+            get_bloxroute_tip_address(&self.env.listen_network),
+            fetched_gas_price,
+            parse_ether(get_env("TIP_ETH_AMOUNT", None))?,
+            sign_txs.len() as u32 + 2,
+            self.http_provider.clone(),
+        )
+        .await?;
+
+        sign_txs.sort_by_key(|(_, wallet_index, _)| *wallet_index);
+        let mut labelled_txs = vec![
+            ("tip".to_string(), tip_tx),
+            ("active_trading".to_string(), signed_active_trading_tx),
+        ];
+        for (sign_tx, wallet_index, _) in &sign_txs {
+            labelled_txs.push((format!("buyer {}", wallet_index), sign_tx.clone()));
+        }
+        let bundle_txs: Vec<(String, H256)> = labelled_txs
+            .iter()
+            .map(|(label, signed_tx)| (label.clone(), H256::from_slice(&keccak256(signed_tx))))
+            .collect();
+        let signed_txs: Vec<Bytes> = labelled_txs
+            .into_iter()
+            .map(|(_, signed_tx)| signed_tx)
+            .collect();
+
+        let mut report = ActiveTradingReport {
+            ordering: LaunchOrdering::Bundle,
+            ..Default::default()
+        };
+        let start_time = Instant::now();
+        let mut is_included = false;
+        for _ in 0..self.bundle_max_blocks {
+            let current_block = self.http_provider.get_block_number().await?;
+            let target_block = current_block + 1;
+            let bundle = bundler.to_bundle(&signed_txs, current_block, target_block);
+            let bundle_hashes = bundler.send_bundle(&bundle).await?;
+            log::info!(
+                "[LaunchingProcessService] sent launch bundle {:?} for block {:?}",
+                bundle_hashes,
+                target_block
+            );
+
+            while self.http_provider.get_block_number().await? <= target_block {
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+            if self
+                .http_provider
+                .get_transaction_receipt(bundle_txs[1].1)
+                .await?
+                .is_some()
+            {
+                is_included = true;
+                break;
+            }
+        }
+        // the bundle lands atomically, active trading and buys share the same duration
+        report.active_trading.duration = start_time.elapsed();
+        report.buy.duration = report.active_trading.duration;
+        if !is_included {
+            report.active_trading.errors.push(format!(
+                "launch bundle not included after {:?} blocks",
+                self.bundle_max_blocks
+            ));
+            return Ok(report);
+        }
+
+        report.tx_positions = get_bundle_positions(self.http_provider.clone(), &bundle_txs).await?;
+        for position in &report.tx_positions {
+            log::info!(
+                "[LaunchingProcessService] launch tx position {:?}",
+                position
+            );
+        }
+        report.buy.errors = check_bundle_positions(&report.tx_positions);
+
+        let migrate_start_time = Instant::now();
+        let mut futures = Vec::new();
+        // buys follow the tip and active trading txs in the bundle
+        for ((_, wallet_index, buy_nonce), position) in
+            sign_txs.into_iter().zip(&report.tx_positions[2..])
+        {
+            if position.block_number.is_none() {
+                continue;
+            }
+            let wallet_service = wallet_service.clone();
+            futures.push(task::spawn(async move {
+                wallet_service
+                    .migrate_token_to_seller_by_index(
+                        wallet_index as u32,
+                        buy_nonce,
+                        fetched_gas_price,
+                    )
+                    .await
+                    .map_err(|err| format!("wallet_index {:?}: {}", wallet_index, err))
+            }));
+        }
+        for result in join_all(futures).await {
+            match result {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => report.migrate.errors.push(err),
+                Err(err) => report.migrate.errors.push(err.to_string()),
+            }
+        }
+        report.migrate.duration = migrate_start_time.elapsed();
+
+        Ok(report)
     }
 
     async fn buy_and_migrate_task(
@@ -111,19 +371,35 @@ impl LaunchingProcessService {
         wallet_index: usize,
         buy_nonce: U256,
         fetched_gas_price: U256,
-    ) -> anyhow::Result<()> {
+    ) -> BuyAndMigrateResult {
         let pending_tx = http_provider.send_raw_transaction(sign_tx).await;
 
         match pending_tx {
             Ok(_pending_tx) => {
-                wallet_service
+                let buy_done_at = Instant::now();
+                let migrate_result = wallet_service
                     .migrate_token_to_seller_by_index(
                         wallet_index as u32,
                         buy_nonce,
                         fetched_gas_price,
                     )
-                    .await?;
-                Ok(())
+                    .await;
+                if let Err(err) = &migrate_result {
+                    log::error!(
+                        "Failed to migrate token wallet_index {:?} with err: {:?}",
+                        wallet_index,
+                        err
+                    );
+                }
+                BuyAndMigrateResult {
+                    buy: Ok(()),
+                    buy_done_at,
+                    migrate: Some((
+                        migrate_result
+                            .map_err(|err| format!("wallet_index {:?}: {}", wallet_index, err)),
+                        Instant::now(),
+                    )),
+                }
             }
             Err(err) => {
                 log::info!(
@@ -131,7 +407,11 @@ impl LaunchingProcessService {
                     wallet_index,
                     err
                 );
-                Ok(())
+                BuyAndMigrateResult {
+                    buy: Err(format!("wallet_index {:?}: {}", wallet_index, err)),
+                    buy_done_at: Instant::now(),
+                    migrate: None,
+                }
             }
         }
     }
@@ -147,19 +427,19 @@ impl LaunchingProcessService {
         let exit = self.env.exit.clone();
         let fetched_gas_price = self.http_provider.get_gas_price().await?;
         let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
-        let provider_index: Arc<RwLock<usize>> = Arc::new(RwLock::new(
-            HttpProviders::init_provider_index(&self.env.listen_network, false).await?,
-        ));
+        let provider_pool = ProviderPool::init(&self.env.listen_network, false).await?;
         set.spawn(GasPrice::fetch_periodically(
             exit.clone(),
-            self.env.listen_network,
-            provider_index.clone(),
+            provider_pool.clone(),
             gas_price.clone(),
             Duration::from_secs(3),
         ));
+        set.spawn(HttpProviders::fetch_periodically(
+            Some(exit.clone()),
+            provider_pool.clone(),
+        ));
 
-        let tx_hashes_cache: Arc<Mutex<TimedCache<H256, bool>>> =
-            Arc::new(Mutex::new(TimedCache::with_lifespan(120)));
+        let tx_hashes_cache = new_tx_hashes_cache();
 
         let auto_sell_event_listen_enabled: bool = get_env("AUTO_SELL_EVENT_LISTEN_ENABLED", None)
             .parse()
@@ -168,14 +448,14 @@ impl LaunchingProcessService {
             let env_clone = self.env.clone();
             let provider_clone = self.http_provider.clone();
             let gas_price_clone = gas_price.clone();
-            let provider_index_clone = provider_index.clone();
+            let provider_pool_clone = provider_pool.clone();
             let tx_hashes_cache_clone = tx_hashes_cache.clone();
 
             set.spawn(async move {
                 let mut sell_service = SellService::new(
                     env_clone,
                     gas_price_clone,
-                    provider_index_clone,
+                    provider_pool_clone,
                     provider_clone,
                 );
                 sell_service.init().await?;
@@ -184,7 +464,7 @@ impl LaunchingProcessService {
             });
         }
 
-        // NOTE: base/blast not support stream mempool
+        // base/blast have no pending tx subscription, their mempool is polled (MEMPOOL_POLL_INTERVAL_MS)
         let auto_sell_mempool_listen_enabled: bool =
             get_env("AUTO_SELL_MEMPOOL_LISTEN_ENABLED", None)
                 .parse()
@@ -192,7 +472,7 @@ impl LaunchingProcessService {
         if auto_sell_mempool_listen_enabled {
             let env = self.env.clone();
             let gas_price_clone = gas_price.clone();
-            let provider_index_clone = provider_index.clone();
+            let provider_pool_clone = provider_pool.clone();
             let http_provider_clone = self.http_provider.clone();
             let tx_hashes_cache_clone = tx_hashes_cache.clone();
 
@@ -200,7 +480,7 @@ impl LaunchingProcessService {
                 let mut sell_service = SellService::new(
                     env,
                     gas_price_clone,
-                    provider_index_clone,
+                    provider_pool_clone,
                     http_provider_clone,
                 );
                 sell_service.init().await?;
@@ -223,10 +503,49 @@ impl LaunchingProcessService {
     pub async fn start_market_making(&self) -> anyhow::Result<()> {
         let fetched_gas_price = self.http_provider.get_gas_price().await?;
         let gas_price: Arc<RwLock<U256>> = Arc::new(RwLock::new(fetched_gas_price));
-        let market_maker_service =
-            MarketMakerService::new(self.env.clone(), gas_price, self.http_provider.clone());
+        let provider_pool = ProviderPool::init(&self.env.listen_network, false).await?;
+        let market_maker_service = MarketMakerService::new(
+            self.env.clone(),
+            gas_price,
+            provider_pool,
+            self.http_provider.clone(),
+        );
 
         market_maker_service.market_make().await?;
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn active_trading_is_timed_on_its_own_send() {
+        let start_time = Instant::now();
+        let at = |millis: u64| start_time + Duration::from_millis(millis);
+        let buy_and_migrate_results = vec![
+            Ok(BuyAndMigrateResult {
+                buy: Ok(()),
+                buy_done_at: at(2_000),
+                migrate: Some((Ok(()), at(3_000))),
+            }),
+            Ok(BuyAndMigrateResult {
+                buy: Err("wallet_index 1: nonce too low".to_string()),
+                buy_done_at: at(1_500),
+                migrate: None,
+            }),
+        ];
+
+        let report = LaunchingProcessService::concurrent_report(
+            start_time,
+            Ok((Ok(()), at(50))),
+            buy_and_migrate_results,
+        );
+        assert_eq!(report.active_trading.duration, Duration::from_millis(50));
+        assert!(report.active_trading.errors.is_empty());
+        assert_eq!(report.buy.duration, Duration::from_millis(2_000));
+        assert_eq!(report.buy.errors, vec!["wallet_index 1: nonce too low"]);
+        assert_eq!(report.migrate.duration, Duration::from_millis(1_000));
+    }
+}
@@ -1,61 +1,235 @@
 use crate::{
     constants::Env,
-    core::{MessageTransportService, WalletService},
+    core::{GasPrice, MessageTransportService, WalletService},
     routers::RouterService,
-    utils::get_mm_config,
+    utils::{
+        acquire_mm_group_permit, await_confirmations, compute_native_received,
+        compute_retain_amount, count_token_holders, get_mm_config, get_mm_flow_state,
+        get_mm_resume_index, get_native_symbol, is_new_holder, load_system_wallet_address,
+        new_mm_group_semaphore, prefetch_nonce_and_balance, read_mm_config, save_mm_flow_state,
+        save_mm_resume_index, send_raw_transaction_with_gas_bump, CircuitBreaker, FlowPlanner,
+        GasJitter, GasLimits, MmAction, PnlLedger, ReorgWatcher, VolumeLedger,
+        GAS_CEILING_RECHECK_SECS,
+    },
 };
 use anyhow::anyhow;
+use chrono::Utc;
 use ethers::{
     middleware::SignerMiddleware,
     providers::{Http, Middleware, Provider},
-    signers::{LocalWallet, Signer, WalletError},
-    types::{Address, U256},
-    utils::parse_ether,
+    signers::Signer,
+    types::{Address, TransactionRequest, U256, U64},
+    utils::{format_ether, format_units, parse_ether},
 };
 use mm_token_utils::{
-    abi::MemeTokenAbigen, constants::WRAPPED_NATIVE_TOKENS, utils::load_mnemonic_wallet,
+    abi::MemeTokenAbigen,
+    constants::WRAPPED_NATIVE_TOKENS,
+    env::get_env,
+    signer::{WalletSigner, WalletSignerError},
+    utils::load_mnemonic_wallet,
+};
+use provider_utils::{
+    http_providers::HttpProviders, provider_pool::ProviderPool, tx_error::TxError,
 };
-use provider_utils::{constants::DESERIALIZATION_ERROR_MSG, http_providers::HttpProviders};
 use rand::Rng;
 use std::{
-    sync::{atomic::Ordering, Arc},
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
     time::Duration,
 };
-use tokio::{sync::RwLock, task::JoinSet};
+use tokio::{
+    sync::{RwLock, Semaphore},
+    task::JoinSet,
+};
 
-use crate::types::{MmConfig, MmSettings};
+use crate::types::{MmConfig, MmSettings, WalletGroup};
 
 #[derive(Debug, Clone)]
 pub struct MarketMakerService {
     env: Env,
+    // ETH, BNB or FTM in the messages
+    native_symbol: &'static str,
+    provider_pool: ProviderPool,
     http_provider: Arc<Provider<Http>>,
     weth_address: Address,
     router_service: RouterService,
+    preflight: bool,
+    preflight_eth_amount: U256,
+    // the preflight trades are broadcast instead of simulated
+    preflight_live: bool,
+    resume: bool,
+    target_holders: Option<u32>,
+    holder_min_token: u32,
+    holders_count: Arc<AtomicU32>,
+    gas_price: Arc<RwLock<U256>>,
+    circuit_breaker: CircuitBreaker,
+    confirmations: u64,
+    // a buy waited for longer than CONFIRMATIONS_TIMEOUT_SECS stops the group
+    confirmations_timeout: Duration,
+    gas_jitter: GasJitter,
+    // eth in / out of the trades, read by the /pnl command
+    pnl_ledger: PnlLedger,
+    // gas a token holder is funded with before its sell
+    gas_limits: GasLimits,
+    // wallet index reached by each group, read back with MM_RESUME
+    mm_state_path: String,
+    flow_state_path: String,
+    // a trade not mined within MM_TX_TIMEOUT_SECS is replaced with higher fees at the same nonce
+    tx_timeout: Duration,
+    // reports the buys / sells reorged out within REORG_DEPTH blocks
+    reorg_watcher: ReorgWatcher,
 }
 
 impl MarketMakerService {
-    pub fn new(env: Env, gas_price: Arc<RwLock<U256>>, http_provider: Arc<Provider<Http>>) -> Self {
+    pub fn new(
+        env: Env,
+        gas_price: Arc<RwLock<U256>>,
+        provider_pool: ProviderPool,
+        http_provider: Arc<Provider<Http>>,
+    ) -> Self {
         let Some(weth) = WRAPPED_NATIVE_TOKENS.get(&env.listen_network) else {
             panic!(
                 "WRAPPED_NATIVE_TOKENS not found in {:?}",
                 env.listen_network
             );
         };
+        let target_holders = get_env("MM_TARGET_HOLDERS", Some("".to_string()));
+        let target_holders = if target_holders.is_empty() {
+            None
+        } else {
+            Some(target_holders.parse().unwrap())
+        };
         Self {
+            native_symbol: get_native_symbol(&env.listen_network),
             env: env.clone(),
+            provider_pool,
             http_provider: http_provider.clone(),
             weth_address: weth.address,
-            router_service: RouterService::new(env, gas_price, http_provider),
+            router_service: RouterService::new(env, gas_price.clone(), http_provider),
+            preflight: get_env("MM_PREFLIGHT", Some("true".to_string()))
+                .parse()
+                .unwrap(),
+            preflight_eth_amount: parse_ether(get_env(
+                "MM_PREFLIGHT_ETH_AMOUNT",
+                Some("0.0001".to_string()),
+            ))
+            .unwrap(),
+            preflight_live: get_env("MM_PREFLIGHT_LIVE", Some("false".to_string()))
+                .parse()
+                .unwrap(),
+            resume: get_env("MM_RESUME", Some("false".to_string()))
+                .parse()
+                .unwrap(),
+            target_holders,
+            holder_min_token: get_env("MM_HOLDER_MIN_TOKEN", Some("1".to_string()))
+                .parse()
+                .unwrap(),
+            holders_count: Arc::new(AtomicU32::new(0)),
+            gas_price,
+            circuit_breaker: CircuitBreaker::from_env("MarketMakerService"),
+            confirmations: get_env("CONFIRMATIONS", Some("1".to_string()))
+                .parse()
+                .unwrap(),
+            confirmations_timeout: Duration::from_secs(
+                get_env("CONFIRMATIONS_TIMEOUT_SECS", Some("300".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
+            gas_jitter: GasJitter::from_env(false),
+            pnl_ledger: PnlLedger::from_env(),
+            gas_limits: GasLimits::from_env(700_000),
+            mm_state_path: get_env("MM_STATE_PATH", Some("mm_state.json".to_string())),
+            flow_state_path: get_env("MM_FLOW_STATE_PATH", Some("mm_flow_state.json".to_string())),
+            tx_timeout: Duration::from_secs(
+                get_env("MM_TX_TIMEOUT_SECS", Some("30".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
+            reorg_watcher: ReorgWatcher::new(
+                get_env("REORG_DEPTH", Some("0".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
         }
     }
 
+    /// shared gas price with a random `GAS_PRICE_JITTER_PERCENT` per tx, none when the jitter is disabled
+    async fn jittered_gas_price(&self) -> Option<U256> {
+        let gas_price = *self.gas_price.read().await;
+        self.gas_jitter
+            .apply(gas_price, self.http_provider.clone())
+            .await
+    }
+
     /// Market make
-    /// Increase volume and makers of a token
+    /// Increase volume and makers of every configured token (`TOKENS`, TOKEN_ADDRESS otherwise),
+    /// the tokens share the provider pool and the gas price
     pub async fn market_make(&self) -> anyhow::Result<()> {
         let mut set = JoinSet::new();
         let exit = self.env.exit.clone();
 
-        let mm_config: MmConfig = get_mm_config();
+        let message_transport_service = MessageTransportService::new();
+        let message = "Market maker have been launch".to_string();
+        message_transport_service.send_message(message).await;
+
+        set.spawn(HttpProviders::fetch_periodically(
+            Some(exit.clone()),
+            self.provider_pool.clone(),
+        ));
+        // the gas price stays the one fetched at startup unless MAX_GAS_PRICE_GWEI has to follow it
+        if self.router_service.gas_ceiling.is_enabled() {
+            set.spawn(GasPrice::fetch_periodically(
+                exit.clone(),
+                self.provider_pool.clone(),
+                self.gas_price.clone(),
+                Duration::from_secs(3),
+            ));
+        }
+        for env in self.env.token_envs() {
+            let market_maker_service = Self::new(
+                env,
+                self.gas_price.clone(),
+                self.provider_pool.clone(),
+                self.http_provider.clone(),
+            );
+            set.spawn(market_maker_service.market_make_token());
+        }
+
+        while let Some(res) = set.join_next().await {
+            match res {
+                Ok(Ok(())) => {
+                    log::info!("Program exited gracefully.");
+                }
+                Ok(Err(err)) => {
+                    log::error!("Error occurred: {:?}", err);
+                    exit.store(true, Ordering::Relaxed);
+                }
+                Err(err) => {
+                    log::error!("Error occurred: {:?}", err);
+                    exit.store(true, Ordering::Relaxed);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// market make groups of `mm_config.json` (or the token's `mmConfigPath`) for the token of `self.env`
+    async fn market_make_token(self) -> anyhow::Result<()> {
+        let mut set = JoinSet::new();
+        let exit = self.env.exit.clone();
+        log::info!(
+            "[MarketMakerService] market make token {:?}",
+            self.env.token_address
+        );
+
+        let mm_config: MmConfig = match &self.env.token.mm_config_path {
+            Some(mm_config_path) => read_mm_config(mm_config_path),
+            None => get_mm_config(),
+        };
         let default_settings = mm_config.default_settings.clone();
         let mm_settings_list: Vec<MmSettings> = mm_config
             .groups
@@ -98,28 +272,59 @@ impl MarketMakerService {
                         .max_retain_token
                         .unwrap_or(default_settings.max_retain_token),
                 ),
+                min_retain_percent: settings
+                    .min_retain_percent
+                    .or(default_settings.min_retain_percent),
+                max_retain_percent: settings
+                    .max_retain_percent
+                    .or(default_settings.max_retain_percent),
+                max_daily_volume_eth: settings
+                    .max_daily_volume_eth
+                    .or(default_settings.max_daily_volume_eth),
+                max_per_wallet_volume_eth: settings
+                    .max_per_wallet_volume_eth
+                    .or(default_settings.max_per_wallet_volume_eth),
+                buy_sell_ratio: settings.buy_sell_ratio.or(default_settings.buy_sell_ratio),
+                action_randomness: settings
+                    .action_randomness
+                    .or(default_settings.action_randomness),
+                slippage_bps: settings.slippage_bps.or(default_settings.slippage_bps),
+                start_index: settings.start_index,
             })
             .collect();
 
-        let provider_index: Arc<RwLock<usize>> = Arc::new(RwLock::new(
-            HttpProviders::init_provider_index(&self.env.listen_network, false).await?,
-        ));
+        let group_semaphore = new_mm_group_semaphore(default_settings.max_concurrent_groups);
         let message_transport_service = MessageTransportService::new();
-        let message = "Market maker have been launch".to_string();
-        message_transport_service.send_message(message).await?;
+        // mm wallets read their nonce and balances from chain before each trade, nothing to resync
+        self.reorg_watcher.spawn(
+            "MarketMakerService",
+            self.env.token_address,
+            HashMap::new(),
+            self.http_provider.clone(),
+            exit.clone(),
+        );
+
+        if let Some(target_holders) = self.target_holders {
+            let holders_count = self.count_holders(&mm_settings_list).await?;
+            self.holders_count.store(holders_count, Ordering::Relaxed);
+            log::info!(
+                "[MarketMakerService] holders {:?} / target {:?}",
+                holders_count,
+                target_holders
+            );
+            let message = format!(
+                "Market maker status \nHolders: {:#?} / {:#?}",
+                holders_count, target_holders
+            );
+            message_transport_service.send_message(message).await;
+        }
 
-        set.spawn(HttpProviders::fetch_periodically(
-            self.env.listen_network,
-            false,
-            Some(exit.clone()),
-            provider_index.clone(),
-        ));
         for (mm_index, mm_settings) in mm_settings_list.iter().enumerate() {
             set.spawn(Self::market_make_by_config(
                 self.clone(),
                 mm_index,
                 mm_settings.to_owned(),
-                provider_index.clone(),
+                group_semaphore.clone(),
             ));
         }
 
@@ -146,18 +351,57 @@ impl MarketMakerService {
         mut self,
         mm_index: usize,
         mm_settings: MmSettings,
-        provider_index: Arc<RwLock<usize>>,
+        group_semaphore: Option<Arc<Semaphore>>,
     ) -> anyhow::Result<()> {
         log::info!("MM Settings: {:#?}", mm_settings);
 
-        let mm_mnemonic = mm_settings.mnemonic;
+        let mm_mnemonic = mm_settings.mnemonic.clone();
         let mm_wallets_size: u32 = mm_settings.max_wallets_count.unwrap();
+        // a cap below the largest buy would pause the group forever
+        let max_buy_volume = mm_settings.max_buy_volume.unwrap();
+        for cap in [
+            mm_settings.max_daily_volume_eth,
+            mm_settings.max_per_wallet_volume_eth,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if cap < max_buy_volume {
+                return Err(anyhow!(
+                    "group {:?} volume cap {:?} ETH below maxBuyVolume {:?}",
+                    mm_index,
+                    cap,
+                    max_buy_volume
+                ));
+            }
+        }
+        // the group trades with its own slippage, the other groups keep theirs
+        if let Some(slippage_bps) = mm_settings.slippage_bps {
+            self.router_service.set_slippage_bps(slippage_bps)?;
+        }
+        let mut volume_ledger = VolumeLedger::from_settings(&mm_settings)?;
+        let mut is_volume_paused = false;
         let gas_price =
             self.http_provider.get_gas_price().await? * U256::from(101) / U256::from(100);
         let transfer_gas_cost = gas_price * U256::from(21_000);
 
+        let group_address = self.load_mnemonic_wallet(&mm_mnemonic, 0)?.address();
+        // the volumes and token holders of an earlier run keep converging to the ratio
+        let mut flow_planner = FlowPlanner::from_settings(&mm_settings)?.map(|flow_planner| {
+            match get_mm_flow_state(&self.flow_state_path, &group_address) {
+                Some(flow_state) => flow_planner.with_state(flow_state),
+                None => flow_planner,
+            }
+        });
+        let mut index: u32 = mm_settings.start_index.unwrap_or(0);
+        if self.resume {
+            if let Some(resume_index) = get_mm_resume_index(&self.mm_state_path, &group_address) {
+                log::info!("group {:?} resume from index {:?}", mm_index, resume_index);
+                index = resume_index;
+            }
+        }
+
         // find wallet with enough balance
-        let mut index: u32 = 0;
         loop {
             if index >= mm_wallets_size {
                 log::error!("cannot find wallet with positive balance, exited");
@@ -179,52 +423,42 @@ impl MarketMakerService {
             index += 1;
         }
 
+        let message_transport_service = MessageTransportService::new();
+        // a tiny buy + sell before forwarding eth through the whole group
+        if self.preflight && index < mm_wallets_size {
+            let wallet = self.load_mnemonic_wallet(&mm_mnemonic, index)?;
+            let _group_permit = acquire_mm_group_permit(&group_semaphore).await?;
+            if let Err(err) = self.preflight(&wallet).await {
+                log::error!("preflight failed, group {:?} aborted: {:?}", mm_index, err);
+                let message = format!(
+                    "Market maker status \nMarket index: {:#?} \nPreflight failed, group aborted: {}",
+                    mm_index, err
+                );
+                message_transport_service
+                    .send_critical_message(message)
+                    .await;
+                return Ok(());
+            }
+        }
+
         // market make
         let mut is_entire_eth_err = false;
-        let message_transport_service = MessageTransportService::new();
         loop {
             // update healthy provider
-            self.http_provider = Arc::new(
-                HttpProviders::get_provider(
-                    &self.env.listen_network,
-                    false,
-                    provider_index.clone(),
-                )
-                .await?,
-            );
+            self.http_provider = Arc::new(self.provider_pool.get_provider().await);
 
             // check out of bound and refund ETH to first wallet
             if index >= mm_wallets_size {
                 log::error!("index outbound, exited");
-                let final_wallet = self.load_mnemonic_wallet(&mm_mnemonic, index)?;
-                let first_wallet = self.load_mnemonic_wallet(&mm_mnemonic, 0)?;
-                log::info!(
-                    "start refund the remaining ETH to first wallet: {:#?}",
-                    first_wallet.address()
-                );
-
-                let final_signer =
-                    SignerMiddleware::new(self.http_provider.clone(), final_wallet.clone());
-
-                if let Err(err) = WalletService::send_entire_eth_balance(
-                    &final_signer,
-                    final_wallet.address(),
-                    first_wallet.address(),
-                )
-                .await
+                if let Err(err) = self
+                    .refund_to_first_wallet(&mm_mnemonic, mm_index, index, group_address)
+                    .await
                 {
                     log::warn!("rerun because resend overshot failed err={:?}", err);
                     is_entire_eth_err = true;
                     continue;
-                } else {
-                    let message = format!(
-                        "Market maker status \nMarket index: {:#?} \nRefund the remaining ETH to first wallet: {:#?}",
-                        mm_index,
-                        first_wallet.address(),
-                    );
-                    message_transport_service.send_message(message).await?;
-                    break Ok(());
                 }
+                break Ok(());
             }
 
             let wallet = self.load_mnemonic_wallet(&mm_mnemonic, index)?;
@@ -252,9 +486,32 @@ impl MarketMakerService {
 
                 is_entire_eth_err = false;
                 index += 1;
+                self.save_resume_index(group_address, index);
                 continue;
             }
 
+            // shutdown requested: no new buy, the eth of the current wallet goes back to the first wallet
+            if self.env.exit.load(Ordering::Relaxed) {
+                log::warn!(
+                    "[MarketMakerService] group {:?} stopped at index {:?} by exit",
+                    mm_index,
+                    index
+                );
+                if index > 0 {
+                    if let Err(err) = self
+                        .refund_to_first_wallet(&mm_mnemonic, mm_index, index, group_address)
+                        .await
+                    {
+                        log::warn!("rerun because resend overshot failed err={:?}", err);
+                        continue;
+                    }
+                }
+                break Ok(());
+            }
+
+            // held until the sell is mined, the eth forwarding to the next wallet is not limited
+            let group_permit = acquire_mm_group_permit(&group_semaphore).await?;
+
             let token_contract =
                 MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
 
@@ -263,122 +520,446 @@ impl MarketMakerService {
 
             let num = rand::thread_rng().gen_range(min_buy_volume..=max_buy_volume);
             let eth_amount = parse_ether(num.to_string()).unwrap();
-            log::info!("buying token with eth_amount {:?}", num);
 
-            let (pair_address, _) = self
-                .router_service
-                .get_pair_address(&self.env.token_address, &self.weth_address, true)
-                .await?;
+            let min_delay_time = mm_settings.min_delay_time.unwrap();
+            let max_delay_time = mm_settings.max_delay_time.unwrap();
+            // buy then sell unless the group targets a buy/sell ratio
+            let mut action = flow_planner
+                .as_ref()
+                .map_or(MmAction::BuyAndSell, |flow_planner| {
+                    flow_planner.next_action(num.into(), &mut rand::thread_rng())
+                });
+            // a dry run only simulates buys
+            if action == MmAction::Sell && self.env.dry_run {
+                action = MmAction::BuyAndSell;
+            }
+            // the current wallet is fresh, a sell only iteration sells the tokens a buy only wallet kept
+            let token_holder_index = flow_planner
+                .as_ref()
+                .filter(|_| action == MmAction::Sell)
+                .and_then(FlowPlanner::next_token_holder);
+            log::info!(
+                "[MarketMakerService] group {:?} action {:?}, realized buy ratio {:?}",
+                mm_index,
+                action,
+                flow_planner
+                    .as_ref()
+                    .and_then(FlowPlanner::realized_buy_ratio)
+            );
 
-            let signed_buy_tx = self
-                .router_service
-                .construct_buy_token_tx(&wallet, None, eth_amount, &pair_address, true)
-                .await?;
+            // a wallet buying again while it already holds is not a new holder
+            let mut token_balance_before_buy = U256::zero();
+            if action.is_buy() {
+                // volume caps reached, wait for the 24h window to roll over
+                let now = Utc::now().timestamp();
+                if let Some(resume_at) = volume_ledger.resume_at(from_address, eth_amount, now)? {
+                    drop(group_permit);
+                    if !is_volume_paused {
+                        is_volume_paused = true;
+                        log::warn!(
+                            event = "volume_cap_reached",
+                            wallet_index = index;
+                            "[MarketMakerService] group {:?} paused by the volume cap for {:?}s, 24h volume {} ETH",
+                            mm_index,
+                            resume_at - now,
+                            format_ether(volume_ledger.volume(now))
+                        );
+                        let message = format!(
+                            "Market maker status \nMarket index: {:#?} \nVolume cap reached ({} {} in 24h), paused for {}s",
+                            mm_index,
+                            format_ether(volume_ledger.volume(now)),
+                            self.native_symbol,
+                            resume_at - now
+                        );
+                        message_transport_service.send_message(message).await;
+                    }
+                    // short sleeps, an exit request is still noticed
+                    tokio::time::sleep(Duration::from_secs((resume_at - now).clamp(1, 60) as u64))
+                        .await;
+                    continue;
+                }
+                if is_volume_paused {
+                    is_volume_paused = false;
+                    log::info!("[MarketMakerService] group {:?} resumed", mm_index);
+                }
+                // gas above MAX_GAS_PRICE_GWEI, no new buy until it falls back under
+                if !self.router_service.is_gas_acceptable().await {
+                    drop(group_permit);
+                    tokio::time::sleep(Duration::from_secs(GAS_CEILING_RECHECK_SECS)).await;
+                    continue;
+                }
+                log::info!("buying token with eth_amount {:?}", num);
 
-            let buy_pending_tx = self
-                .http_provider
-                .send_raw_transaction(signed_buy_tx)
-                .await?;
-            let buy_tx_receipt = match buy_pending_tx.await {
-                Ok(result) => result,
-                Err(err) => {
-                    if err.to_string().contains(DESERIALIZATION_ERROR_MSG) {
-                        continue;
+                let balance_of = token_contract.balance_of(from_address);
+                let (pair_address, prefetched, token_balance) = tokio::join!(
+                    self.router_service.get_pair_address(
+                        &self.env.token_address,
+                        &self.weth_address,
+                        true
+                    ),
+                    prefetch_nonce_and_balance(&self.http_provider, from_address),
+                    balance_of.call()
+                );
+                let (pair_address, _) = pair_address?;
+                let (nonce, eth_balance) = prefetched?;
+                token_balance_before_buy = token_balance?;
+                if eth_balance < eth_amount {
+                    return Err(anyhow!(
+                        "eth_balance {:?} lower than buy eth_amount {:?}",
+                        eth_balance,
+                        eth_amount
+                    ));
+                }
+
+                let signed_buy_tx = self
+                    .router_service
+                    .construct_buy_token_tx(
+                        &wallet,
+                        Some(nonce),
+                        eth_amount,
+                        &pair_address,
+                        true,
+                        self.jittered_gas_price().await,
+                    )
+                    .await?;
+
+                if self.env.dry_run {
+                    if let Err(err) = self.router_service.simulate_tx(&signed_buy_tx).await {
+                        log::warn!("[MarketMakerService] dry run buy fails {:?}", err);
                     }
-                    return Err(err.into());
+                    volume_ledger.record(from_address, eth_amount, Utc::now().timestamp());
+                    drop(group_permit);
+                    // the sell needs the bought tokens, simulate the next buy from the same wallet
+                    let sleep_duration = Duration::from_secs(rand::thread_rng().gen_range(
+                        mm_settings.min_delay_time.unwrap()..=mm_settings.max_delay_time.unwrap(),
+                    ));
+                    tokio::time::sleep(sleep_duration).await;
+                    continue;
                 }
-            };
 
-            let min_delay_time = mm_settings.min_delay_time.unwrap();
-            let max_delay_time = mm_settings.max_delay_time.unwrap();
-            let sleep_duration =
-                Duration::from_secs(rand::thread_rng().gen_range(min_delay_time..=max_delay_time));
-            log::info!(
-                "token bought tx_hash={:?}, sleep={:?}",
-                buy_tx_receipt.map(|x| x.transaction_hash),
-                sleep_duration
-            );
-            tokio::time::sleep(sleep_duration).await;
+                let buy_tx_receipt = match send_raw_transaction_with_gas_bump(
+                    self.http_provider.as_ref(),
+                    &wallet,
+                    signed_buy_tx,
+                    self.tx_timeout,
+                )
+                .await
+                {
+                    Ok(result) => result,
+                    Err(err) => {
+                        // send and receipt errors alike, the next iteration reads the nonce again
+                        if TxError::classify(err.as_ref()).is_retryable() {
+                            log::warn!("[MarketMakerService] retry after err={:?}", err);
+                            drop(group_permit);
+                            tokio::time::sleep(Duration::from_secs(1)).await;
+                            continue;
+                        }
+                        return Err(err);
+                    }
+                };
+
+                // no token bought, retry the buy from the same wallet
+                if let Some(receipt) = buy_tx_receipt
+                    .as_ref()
+                    .filter(|receipt| receipt.status == Some(U64::zero()))
+                {
+                    log::warn!(
+                        event = "buy_reverted",
+                        wallet_index = index,
+                        tx_hash:? = receipt.transaction_hash;
+                        "[MarketMakerService] buy tx {:?} reverted",
+                        receipt.transaction_hash
+                    );
+                    drop(group_permit);
+                    self.circuit_breaker
+                        .record_failure(&format!("buy tx {:?} reverted", receipt.transaction_hash))
+                        .await;
+                    self.circuit_breaker.wait_until_resumed().await;
+                    let sleep_duration = Duration::from_secs(
+                        rand::thread_rng().gen_range(min_delay_time..=max_delay_time),
+                    );
+                    tokio::time::sleep(sleep_duration).await;
+                    continue;
+                }
+                // the sell spends the bought tokens, a reorged buy would revert it
+                let buy_tx_receipt = match buy_tx_receipt {
+                    Some(receipt) => Some(
+                        await_confirmations(
+                            self.http_provider.as_ref(),
+                            receipt.transaction_hash,
+                            self.confirmations,
+                            self.confirmations_timeout,
+                        )
+                        .await?,
+                    ),
+                    None => None,
+                };
+                self.circuit_breaker.record_success();
+                if let Some(receipt) = &buy_tx_receipt {
+                    self.reorg_watcher.record(from_address, receipt);
+                }
+                volume_ledger.record(from_address, eth_amount, Utc::now().timestamp());
+                if let Err(err) = self.pnl_ledger.record_buy(
+                    self.env.token_address,
+                    WalletGroup::MarketMaker,
+                    eth_amount,
+                ) {
+                    log::warn!("[MarketMakerService] cannot record pnl {:?}", err);
+                }
+                if let Some(flow_planner) = flow_planner.as_mut() {
+                    flow_planner.record_buy(num.into());
+                    if action == MmAction::Buy {
+                        flow_planner.record_token_holder(index);
+                    }
+                }
+                let sleep_duration = Duration::from_secs(
+                    rand::thread_rng().gen_range(min_delay_time..=max_delay_time),
+                );
+                let buy_tx_hash = buy_tx_receipt.map(|x| x.transaction_hash);
+                log::info!(
+                    event = "buy_sent",
+                    wallet_index = index,
+                    tx_hash:? = buy_tx_hash.unwrap_or_default(),
+                    amount:% = format_ether(eth_amount);
+                    "token bought tx_hash={:?}, sleep={:?}",
+                    buy_tx_hash,
+                    sleep_duration
+                );
+                tokio::time::sleep(sleep_duration).await;
+            }
 
-            let router_address = self.router_service.get_router_address()?;
+            // the bought tokens are sold once the gas falls back under MAX_GAS_PRICE_GWEI
+            while !self.router_service.is_gas_acceptable().await {
+                if self.env.exit.load(Ordering::Relaxed) {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_secs(GAS_CEILING_RECHECK_SECS)).await;
+            }
 
-            let balance_of = token_contract.balance_of(from_address);
-            let allowance = token_contract.allowance(from_address, router_address);
+            let (seller, seller_signer) = match token_holder_index {
+                Some(token_holder_index) => {
+                    let token_holder =
+                        self.load_mnemonic_wallet(&mm_mnemonic, token_holder_index)?;
+                    let token_holder_signer =
+                        SignerMiddleware::new(self.http_provider.clone(), token_holder.clone());
+                    (token_holder, token_holder_signer)
+                }
+                None => (wallet.clone(), signer.clone()),
+            };
+            let seller_address = seller.address();
+            let balance_of = token_contract.balance_of(seller_address);
             let token_decimals = token_contract.decimals();
-            let (token_balance, allowance, token_decimals) =
-                tokio::join!(balance_of.call(), allowance.call(), token_decimals.call());
+            let (token_balance, allowance, token_decimals, nonce) = tokio::join!(
+                balance_of.call(),
+                self.router_service.get_sell_allowance(seller_address),
+                token_decimals.call(),
+                self.http_provider
+                    .get_transaction_count(seller_address, None)
+            );
             let token_balance = token_balance?;
             let allowance = allowance?;
             let token_decimals = token_decimals?;
+            let mut nonce = nonce?;
 
-            if allowance < token_balance {
-                log::info!("approving token");
+            // keep retain token for holder volume, pure volume once the holders target is reached
+            let retain_amount = if !action.is_sell() {
+                // a buy only iteration keeps every token
+                token_balance
+            } else if self.is_target_holders_reached() {
+                U256::zero()
+            } else if action.is_buy() {
+                compute_retain_amount(
+                    &mm_settings,
+                    token_balance,
+                    token_decimals,
+                    &mut rand::thread_rng(),
+                )?
+            } else {
+                // a sell only wallet holding less than a retain keeps it
+                compute_retain_amount(
+                    &mm_settings,
+                    token_balance,
+                    token_decimals,
+                    &mut rand::thread_rng(),
+                )
+                .unwrap_or(token_balance)
+                .min(token_balance)
+            };
+            log::info!("retain_amount: {:#?}", retain_amount);
+            let token_amount_in: U256 = token_balance - retain_amount;
 
-                let token_contract =
-                    MemeTokenAbigen::new(self.env.token_address, Arc::new(signer.clone()));
-                match token_contract
-                    .approve(router_address, U256::MAX)
-                    .send()
+            if token_amount_in.is_zero() {
+                drop(group_permit);
+                log::info!("nothing to sell, keep {:?} tokens", token_balance);
+                // the holder has nothing left to sell
+                if let (Some(flow_planner), Some(_)) = (flow_planner.as_mut(), token_holder_index) {
+                    flow_planner.release_token_holder();
+                }
+            } else {
+                if token_holder_index.is_some() {
+                    if let Err(err) = self.fund_sell_gas(&signer, seller_address).await {
+                        log::warn!(
+                            "[MarketMakerService] cannot fund the token holder gas {:?}",
+                            err
+                        );
+                        continue;
+                    }
+                }
+                nonce = match self
+                    .router_service
+                    .approve_sell_if_under_approved(
+                        allowance,
+                        token_balance,
+                        nonce,
+                        Arc::new(seller_signer.clone()),
+                    )
                     .await
                 {
-                    Ok(result) => result.await?,
+                    Ok(nonce) => nonce,
                     Err(err) => {
-                        if err.to_string().contains(DESERIALIZATION_ERROR_MSG) {
+                        if TxError::classify(err.as_ref()).is_retryable() {
+                            log::warn!("[MarketMakerService] retry after err={:?}", err);
+                            drop(group_permit);
+                            tokio::time::sleep(Duration::from_secs(1)).await;
                             continue;
                         }
-                        return Err(err.into());
+                        return Err(err);
                     }
                 };
-            }
 
-            log::info!("selling token");
-            let min_retain_token = mm_settings.min_retain_token.unwrap();
-            let max_retain_token = mm_settings.max_retain_token.unwrap();
-            // keep retain token for holder volume
-            let retain_token = rand::thread_rng().gen_range(min_retain_token..=max_retain_token);
-            log::info!("retain_token: {:#?}", retain_token);
-            let retain_token_with_decimals =
-                U256::from(retain_token) * U256::exp10(token_decimals as usize);
-
-            if token_balance < retain_token_with_decimals {
-                return Err(anyhow!("token_balance must be greater than retain_token"));
-            }
-            let token_amount_in: U256 = token_balance - retain_token_with_decimals;
+                log::info!("selling token");
+                let (pair_address, _) = self
+                    .router_service
+                    .get_pair_address(&self.env.token_address, &self.weth_address, false)
+                    .await?;
 
-            let (pair_address, _) = self
-                .router_service
-                .get_pair_address(&self.env.token_address, &self.weth_address, false)
-                .await?;
+                let signed_sell_tx = self
+                    .router_service
+                    .construct_sell_token_tx(
+                        &seller,
+                        Some(nonce),
+                        token_amount_in,
+                        &pair_address,
+                        true,
+                        self.jittered_gas_price().await,
+                    )
+                    .await?;
 
-            let signed_sell_tx = self
-                .router_service
-                .construct_sell_token_tx(&wallet, None, token_amount_in, &pair_address, true)
-                .await?;
+                let sell_tx_receipt = match send_raw_transaction_with_gas_bump(
+                    self.http_provider.as_ref(),
+                    &seller,
+                    signed_sell_tx,
+                    self.tx_timeout,
+                )
+                .await
+                {
+                    Ok(result) => result,
+                    Err(err) => {
+                        // send and receipt errors alike, the next iteration reads the nonce again
+                        if TxError::classify(err.as_ref()).is_retryable() {
+                            log::warn!("[MarketMakerService] retry after err={:?}", err);
+                            drop(group_permit);
+                            tokio::time::sleep(Duration::from_secs(1)).await;
+                            continue;
+                        }
+                        return Err(err);
+                    }
+                };
 
-            let sell_pending_tx = self
-                .http_provider
-                .send_raw_transaction(signed_sell_tx)
-                .await?;
-            let sell_tx_receipt = match sell_pending_tx.await {
-                Ok(result) => result,
-                Err(err) => {
-                    if err.to_string().contains(DESERIALIZATION_ERROR_MSG) {
-                        continue;
+                drop(group_permit);
+
+                match sell_tx_receipt
+                    .as_ref()
+                    .filter(|receipt| receipt.status == Some(U64::zero()))
+                {
+                    Some(receipt) => {
+                        log::warn!(
+                            event = "sell_reverted",
+                            wallet_index = index,
+                            tx_hash:? = receipt.transaction_hash;
+                            "[MarketMakerService] sell tx {:?} reverted",
+                            receipt.transaction_hash
+                        );
+                        self.circuit_breaker
+                            .record_failure(&format!(
+                                "sell tx {:?} reverted",
+                                receipt.transaction_hash
+                            ))
+                            .await;
+                        self.circuit_breaker.wait_until_resumed().await;
+                    }
+                    None => {
+                        self.circuit_breaker.record_success();
+                        if let (Some(flow_planner), Some(_)) =
+                            (flow_planner.as_mut(), token_holder_index)
+                        {
+                            flow_planner.release_token_holder();
+                        }
+                        if let Some(receipt) = &sell_tx_receipt {
+                            self.reorg_watcher.record(seller_address, receipt);
+                            let native_received =
+                                compute_native_received(receipt, self.weth_address);
+                            // the sell volume is the eth received, not the size drawn for the buy
+                            if let Some(flow_planner) = flow_planner.as_mut() {
+                                flow_planner.record_sell(format_ether(native_received).parse()?);
+                            }
+                            if let Err(err) = self.pnl_ledger.record_sell(
+                                self.env.token_address,
+                                WalletGroup::MarketMaker,
+                                native_received,
+                            ) {
+                                log::warn!("[MarketMakerService] cannot record pnl {:?}", err);
+                            }
+                        }
                     }
-                    return Err(err.into());
                 }
-            };
 
-            let sleep_duration =
-                Duration::from_secs(rand::thread_rng().gen_range(min_delay_time..=max_delay_time));
-            log::info!(
-                "token sold tx_hash={:?}, sleep={:?}",
-                sell_tx_receipt.map(|x| x.transaction_hash),
-                sleep_duration
-            );
-            tokio::time::sleep(sleep_duration).await;
+                let sleep_duration = Duration::from_secs(
+                    rand::thread_rng().gen_range(min_delay_time..=max_delay_time),
+                );
+                let sell_tx_hash = sell_tx_receipt.map(|x| x.transaction_hash);
+                log::info!(
+                    event = "sell_sent",
+                    wallet_index = index,
+                    tx_hash:? = sell_tx_hash.unwrap_or_default(),
+                    amount:% = format_units(token_amount_in, token_decimals as usize)?;
+                    "token sold tx_hash={:?}, sleep={:?}",
+                    sell_tx_hash,
+                    sleep_duration
+                );
+                tokio::time::sleep(sleep_duration).await;
+            }
+
+            // the eth of the sell and the unused gas move on with the current wallet
+            if token_holder_index.is_some() {
+                if let Err(err) = WalletService::send_entire_eth_balance(
+                    &seller_signer,
+                    seller_address,
+                    from_address,
+                )
+                .await
+                {
+                    log::warn!(
+                        "[MarketMakerService] cannot return the token holder eth {:?}",
+                        err
+                    );
+                }
+            }
+
+            // a sell only wallet was already counted as a holder
+            if action.is_buy()
+                && is_new_holder(
+                    token_balance_before_buy,
+                    retain_amount,
+                    U256::from(self.holder_min_token.max(1)) * U256::exp10(token_decimals as usize),
+                )
+            {
+                self.add_holder(mm_index).await?;
+            }
 
             log::info!(
+                event = "migration",
+                wallet_index = index + 1;
                 "migrate to next_wallet={:?}, next_index={:?}",
                 next_wallet.address(),
                 index + 1
@@ -398,18 +979,282 @@ impl MarketMakerService {
                 next_wallet.address(),
                 index + 1
             );
-            message_transport_service.send_message(message).await?;
+            message_transport_service.send_message(message).await;
 
             index += 1;
+            self.save_resume_index(group_address, index);
+            if let Some(flow_planner) = &flow_planner {
+                self.save_flow_state(group_address, flow_planner);
+            }
+        }
+    }
+
+    /// tops the token holder up to the gas of an approve and a sell, from the current wallet
+    async fn fund_sell_gas(
+        &self,
+        signer: &SignerMiddleware<Arc<Provider<Http>>, WalletSigner>,
+        token_holder_address: Address,
+    ) -> anyhow::Result<()> {
+        let gas_price = *self.gas_price.read().await;
+        let sell_gas_cost = gas_price * (self.gas_limits.approve + self.gas_limits.sell);
+        let eth_balance = self
+            .http_provider
+            .get_balance(token_holder_address, None)
+            .await?;
+        if eth_balance >= sell_gas_cost {
+            return Ok(());
         }
+
+        let tx = TransactionRequest::new()
+            .to(token_holder_address)
+            .value(sell_gas_cost - eth_balance)
+            .gas(21_000);
+        let receipt = signer
+            .send_transaction(tx, None)
+            .await?
+            .await?
+            .ok_or(anyhow!("Cannot find tx_receipt"))?;
+        if receipt.status == Some(U64::zero()) {
+            return Err(anyhow!(
+                "gas funding tx {:?} reverted",
+                receipt.transaction_hash
+            ));
+        }
+        Ok(())
+    }
+
+    /// Send the remaining ETH of the wallet at `index` back to the first wallet of the group
+    async fn refund_to_first_wallet(
+        &self,
+        mm_mnemonic: &str,
+        mm_index: usize,
+        index: u32,
+        group_address: Address,
+    ) -> anyhow::Result<()> {
+        let final_wallet = self.load_mnemonic_wallet(mm_mnemonic, index)?;
+        let first_wallet = self.load_mnemonic_wallet(mm_mnemonic, 0)?;
+        log::info!(
+            "start refund the remaining ETH to first wallet: {:#?}",
+            first_wallet.address()
+        );
+
+        let final_signer = SignerMiddleware::new(self.http_provider.clone(), final_wallet.clone());
+        WalletService::send_entire_eth_balance(
+            &final_signer,
+            final_wallet.address(),
+            first_wallet.address(),
+        )
+        .await?;
+
+        let message = format!(
+            "Market maker status \nMarket index: {:#?} \nRefund the remaining {} to first wallet: {:#?}",
+            mm_index,
+            self.native_symbol,
+            first_wallet.address(),
+        );
+        MessageTransportService::new().send_message(message).await;
+        // group is done, next run starts over
+        self.save_resume_index(group_address, 0);
+        Ok(())
+    }
+
+    /// Count mm wallets of all groups holding at least `MM_HOLDER_MIN_TOKEN`
+    async fn count_holders(&self, mm_settings_list: &[MmSettings]) -> anyhow::Result<u32> {
+        let token_contract =
+            MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
+        let token_decimals = token_contract.decimals().call().await?;
+        let min_balance = U256::from(self.holder_min_token) * U256::exp10(token_decimals as usize);
+
+        let mut holders_count = 0;
+        for mm_settings in mm_settings_list {
+            let wallets = load_system_wallet_address(
+                &mm_settings.mnemonic,
+                mm_settings.max_wallets_count.unwrap(),
+                self.env.listen_network,
+            )?;
+            holders_count += count_token_holders(
+                self.env.token_address,
+                &wallets,
+                min_balance,
+                self.http_provider.clone(),
+            )
+            .await?;
+        }
+
+        Ok(holders_count)
+    }
+
+    fn is_target_holders_reached(&self) -> bool {
+        self.target_holders.is_some_and(|target_holders| {
+            self.holders_count.load(Ordering::Relaxed) >= target_holders
+        })
+    }
+
+    /// Track a wallet that retained tokens, notify once the holders target is reached
+    async fn add_holder(&self, mm_index: usize) -> anyhow::Result<()> {
+        let Some(target_holders) = self.target_holders else {
+            return Ok(());
+        };
+        let holders_count = self.holders_count.fetch_add(1, Ordering::Relaxed) + 1;
+        log::info!(
+            "[MarketMakerService] holders {:?} / target {:?}",
+            holders_count,
+            target_holders
+        );
+        if holders_count == target_holders {
+            let message = format!(
+                "Market maker status \nMarket index: {:#?} \nHolders target {:#?} reached, switch to pure volume",
+                mm_index, target_holders
+            );
+            MessageTransportService::new().send_message(message).await;
+        }
+
+        Ok(())
+    }
+
+    fn save_resume_index(&self, group_address: Address, index: u32) {
+        if let Err(err) = save_mm_resume_index(&self.mm_state_path, group_address, index) {
+            log::warn!("cannot save mm resume index {:?}: {:?}", index, err);
+        }
+    }
+
+    fn save_flow_state(&self, group_address: Address, flow_planner: &FlowPlanner) {
+        if let Err(err) =
+            save_mm_flow_state(&self.flow_state_path, group_address, flow_planner.state())
+        {
+            log::warn!("cannot save mm flow state {:?}: {:?}", group_address, err);
+        }
+    }
+
+    /// Buy `MM_PREFLIGHT_ETH_AMOUNT` then sell the bought tokens from a wallet, simulated in eth_call
+    /// unless `MM_PREFLIGHT_LIVE`. Fail if the token cannot be bought or sold
+    async fn preflight(&self, wallet: &WalletSigner) -> anyhow::Result<()> {
+        let wallet_address = wallet.address();
+        let token_contract =
+            MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
+        let (pair_address, _) = self
+            .router_service
+            .get_pair_address(&self.env.token_address, &self.weth_address, true)
+            .await?;
+        let balance_of = token_contract.balance_of(wallet_address);
+        let (token_balance_before, prefetched) = tokio::join!(
+            balance_of.call(),
+            prefetch_nonce_and_balance(&self.http_provider, wallet_address)
+        );
+        let token_balance_before = token_balance_before?;
+        let (mut nonce, eth_balance) = prefetched?;
+        if eth_balance < self.preflight_eth_amount {
+            return Err(anyhow!(
+                "eth_balance {:?} lower than MM_PREFLIGHT_ETH_AMOUNT",
+                eth_balance
+            ));
+        }
+
+        log::info!("preflight buy from {:?}", wallet_address);
+        let signed_buy_tx = self
+            .router_service
+            .construct_buy_token_tx(
+                wallet,
+                Some(nonce),
+                self.preflight_eth_amount,
+                &pair_address,
+                true,
+                self.jittered_gas_price().await,
+            )
+            .await?;
+        if self.env.dry_run || !self.preflight_live {
+            self.router_service.simulate_tx(&signed_buy_tx).await?;
+            match self.router_service.simulate_round_trip().await? {
+                Some(weth_received) => {
+                    log::info!(
+                        "preflight sell simulated, {:?} weth received",
+                        weth_received
+                    )
+                }
+                None => log::info!(
+                    "preflight sell not simulated, no v2 route on {:?}",
+                    self.router_service.active_router
+                ),
+            }
+            return Ok(());
+        }
+        let buy_tx_receipt = self
+            .http_provider
+            .send_raw_transaction(signed_buy_tx)
+            .await?
+            .await?
+            .ok_or(anyhow!("Cannot find tx_receipt"))?;
+        if buy_tx_receipt.status != Some(U64::one()) {
+            return Err(anyhow!(
+                "preflight buy {:?} reverted",
+                buy_tx_receipt.transaction_hash
+            ));
+        }
+        nonce += U256::one();
+
+        let balance_of = token_contract.balance_of(wallet_address);
+        let (token_balance, allowance) = tokio::join!(
+            balance_of.call(),
+            self.router_service.get_sell_allowance(wallet_address)
+        );
+        let bought_amount = token_balance?.saturating_sub(token_balance_before);
+        if bought_amount.is_zero() {
+            return Err(anyhow!(
+                "preflight buy {:?} received no token",
+                buy_tx_receipt.transaction_hash
+            ));
+        }
+        let nonce = self
+            .router_service
+            .approve_sell_if_under_approved(
+                allowance?,
+                bought_amount,
+                nonce,
+                Arc::new(SignerMiddleware::new(
+                    self.http_provider.clone(),
+                    wallet.clone(),
+                )),
+            )
+            .await?;
+
+        log::info!("preflight sell from {:?}", wallet_address);
+        let (pair_address, _) = self
+            .router_service
+            .get_pair_address(&self.env.token_address, &self.weth_address, false)
+            .await?;
+        let signed_sell_tx = self
+            .router_service
+            .construct_sell_token_tx(
+                wallet,
+                Some(nonce),
+                bought_amount,
+                &pair_address,
+                true,
+                self.jittered_gas_price().await,
+            )
+            .await?;
+        let sell_tx_receipt = self
+            .http_provider
+            .send_raw_transaction(signed_sell_tx)
+            .await?
+            .await?
+            .ok_or(anyhow!("Cannot find tx_receipt"))?;
+        if sell_tx_receipt.status != Some(U64::one()) {
+            return Err(anyhow!(
+                "preflight sell {:?} reverted, token may be unsellable",
+                sell_tx_receipt.transaction_hash
+            ));
+        }
+
+        Ok(())
     }
 
     pub fn load_mnemonic_wallet(
         &self,
         mnemonic: &str,
         index: u32,
-    ) -> Result<LocalWallet, WalletError> {
-        let wallet = load_mnemonic_wallet(mnemonic, index)?;
+    ) -> Result<WalletSigner, WalletSignerError> {
+        let wallet = load_mnemonic_wallet(mnemonic, index, self.env.listen_network, None)?;
         let wallet = wallet.with_chain_id(self.env.chain_id.as_u64());
         Ok(wallet)
     }
@@ -1,24 +1,128 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use futures::{future::join_all, FutureExt};
 use mm_token_utils::env::get_env;
+use serde_json::json;
 use teloxide::prelude::*;
+use tokio::sync::mpsc;
+
+use crate::{core::RateLimitedTransport, types::TelegramConfig};
+
+// notifications waiting to be sent by the background sender, see NOTIFICATION_QUEUE_SIZE
+static NOTIFICATION_QUEUE: OnceLock<mpsc::Sender<String>> = OnceLock::new();
+// digests of the messages of each MESSAGE_BATCH_SECS window, shared by every service
+static RATE_LIMITED_TRANSPORT: OnceLock<RateLimitedTransport> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ETransport {
+    Telegram,
+    Discord,
+    Slack,
+}
 
-use crate::types::TelegramConfig;
+impl FromStr for ETransport {
+    type Err = anyhow::Error;
+
+    fn from_str(transport: &str) -> Result<Self, Self::Err> {
+        match transport {
+            "Telegram" => Ok(ETransport::Telegram),
+            "Discord" => Ok(ETransport::Discord),
+            "Slack" => Ok(ETransport::Slack),
+            _ => Err(anyhow::anyhow!(
+                "invalid MESSAGE_TRANSPORTS {:?}",
+                transport
+            )),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
-pub struct MessageTransportService {
-    telegram_enabled: bool,
-    telegram_config: Option<TelegramConfig>,
-    telegram_bot: Option<Bot>,
-    // email_enabled: bool,
-    // email_sender_address: Option<String>,
-    // email_sender_password: Option<String>,
+enum TransportSender {
+    Telegram(Bot, TelegramConfig),
+    // incoming webhook urls
+    Discord(String),
+    Slack(String),
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub enum TransportPlatform {
-    TELEGRAM,
-    EMAIL,
-    SLACK,
-    DISCORD,
+impl TransportSender {
+    fn new(transport: ETransport) -> Self {
+        match transport {
+            ETransport::Telegram => {
+                let telegram_bot_token = get_env("TELEGRAM_BOT_TOKEN", None);
+                TransportSender::Telegram(
+                    Bot::new(telegram_bot_token.clone()),
+                    TelegramConfig {
+                        telegram_bot_token,
+                        telegram_channel_id: get_env("TELEGRAM_CHANNEL_ID", None),
+                    },
+                )
+            }
+            ETransport::Discord => TransportSender::Discord(get_env("DISCORD_WEBHOOK_URL", None)),
+            ETransport::Slack => TransportSender::Slack(get_env("SLACK_WEBHOOK_URL", None)),
+        }
+    }
+
+    fn transport(&self) -> ETransport {
+        match self {
+            TransportSender::Telegram(..) => ETransport::Telegram,
+            TransportSender::Discord(_) => ETransport::Discord,
+            TransportSender::Slack(_) => ETransport::Slack,
+        }
+    }
+}
+
+/// Escape what each backend would otherwise render, telegram messages are sent without parse mode
+pub fn escape_message(transport: ETransport, message: &str) -> String {
+    match transport {
+        ETransport::Telegram => message.to_string(),
+        ETransport::Discord => {
+            let mut escaped = String::with_capacity(message.len());
+            for char in message.chars() {
+                if matches!(char, '\\' | '*' | '_' | '~' | '`' | '|' | '>') {
+                    escaped.push('\\');
+                }
+                escaped.push(char);
+            }
+            escaped
+        }
+        ETransport::Slack => message
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;"),
+    }
+}
+
+/// transports of MESSAGE_TRANSPORTS in order, each once, telegram is added by TELEGRAM_ENABLED
+fn parse_transports(message_transports: &str, telegram_enabled: bool) -> Vec<ETransport> {
+    let mut transports: Vec<ETransport> = Vec::new();
+    let configured = message_transports
+        .split(',')
+        .map(str::trim)
+        .filter(|transport| !transport.is_empty())
+        .map(|transport| transport.parse().unwrap());
+    // TELEGRAM_ENABLED predates MESSAGE_TRANSPORTS
+    let enabled = telegram_enabled.then_some(ETransport::Telegram);
+    for transport in configured.chain(enabled) {
+        if !transports.contains(&transport) {
+            transports.push(transport);
+        }
+    }
+    transports
+}
+
+#[derive(Debug, Clone)]
+pub struct MessageTransportService {
+    senders: Vec<TransportSender>,
+    notification_timeout: Duration,
+    notification_queue_size: usize,
+    // 0 sends every message on its own
+    message_batch_secs: u64,
+    message_max_per_minute: usize,
+    http_client: reqwest::Client,
 }
 
 impl Default for MessageTransportService {
@@ -29,91 +133,205 @@ impl Default for MessageTransportService {
 
 impl MessageTransportService {
     pub fn new() -> Self {
-        let telegram_enabled: bool = get_env("TELEGRAM_ENABLED", None).parse().unwrap();
-        let telegram_bot_token = get_env("TELEGRAM_BOT_TOKEN", None);
-        let telegram_channel_id = get_env("TELEGRAM_CHANNEL_ID", None);
+        let telegram_enabled: bool = get_env("TELEGRAM_ENABLED", Some("false".to_string()))
+            .parse()
+            .unwrap();
+        let transports = parse_transports(
+            &get_env("MESSAGE_TRANSPORTS", Some("".to_string())),
+            telegram_enabled,
+        );
 
-        // let email_enabled: bool = get_env("EMAIL_ENABLED", None).parse().unwrap();
         Self {
-            telegram_enabled,
-            telegram_config: if telegram_enabled {
-                Some(TelegramConfig {
-                    telegram_bot_token: telegram_bot_token.clone(),
-                    telegram_channel_id,
-                })
-            } else {
-                None
-            },
-            telegram_bot: if telegram_enabled {
-                Some(Bot::new(telegram_bot_token))
-            } else {
-                None
-            },
-            // email_enabled,
-            // email_sender_address: if email_enabled {
-            //     Some(get_env("EMAIL_SENDER_ADDRESS", None))
-            // } else {
-            //     None
-            // },
-            // email_sender_password: if email_enabled {
-            //     Some(get_env("EMAIL_SENDER_PASSWORD", None))
-            // } else {
-            //     None
-            // },
+            senders: transports.into_iter().map(TransportSender::new).collect(),
+            notification_timeout: Duration::from_secs(
+                get_env("NOTIFICATION_TIMEOUT", Some("5".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
+            notification_queue_size: get_env("NOTIFICATION_QUEUE_SIZE", Some("0".to_string()))
+                .parse()
+                .unwrap(),
+            message_batch_secs: get_env("MESSAGE_BATCH_SECS", Some("0".to_string()))
+                .parse()
+                .unwrap(),
+            message_max_per_minute: get_env("MESSAGE_MAX_PER_MINUTE", Some("20".to_string()))
+                .parse()
+                .unwrap(),
+            http_client: reqwest::Client::new(),
         }
     }
 
-    pub async fn send_message(&self, message: String) -> anyhow::Result<()> {
-        if self.telegram_enabled {
-            Self::handle_send_telegram(self, self.telegram_bot.clone().unwrap(), message.clone())
-                .await?;
+    /// Notifications never fail the caller, errors are logged and the message dropped
+    pub async fn send_message(&self, message: String) {
+        if self.senders.is_empty() {
+            return;
+        }
+        if self.message_batch_secs > 0 {
+            self.rate_limited_transport().send(message);
+            return;
         }
+        self.dispatch_message(message).await;
+    }
 
-        // if self.email_enabled {
-        //     Self::handle_send_email(self, message.clone()).await?;
-        // }
-        Ok(())
-        // TransportPlatform::DISCORD => Ok(()),
-        // TransportPlatform::SLACK => Ok(()),
+    /// errors and alerts, sent right away even when messages are batched
+    pub async fn send_critical_message(&self, message: String) {
+        if self.senders.is_empty() {
+            return;
+        }
+        if self.message_batch_secs > 0 {
+            self.rate_limited_transport().send_critical(message).await;
+            return;
+        }
+        self.dispatch_message(message).await;
+    }
+
+    /// a failure goes out right away like `send_critical_message`, a success is batched
+    pub async fn send_outcome_message(&self, message: String, is_failure: bool) {
+        if is_failure {
+            self.send_critical_message(message).await;
+        } else {
+            self.send_message(message).await;
+        }
     }
 
-    async fn handle_send_telegram(&self, telegram_bot: Bot, message: String) -> anyhow::Result<()> {
-        log::info!("Sending message to telegram bot...");
-        telegram_bot
-            .send_message(
-                self.telegram_config.clone().unwrap().telegram_channel_id,
-                message,
+    /// the messages still batched are sent, called before the process exits
+    pub async fn flush() {
+        if let Some(rate_limited_transport) = RATE_LIMITED_TRANSPORT.get() {
+            rate_limited_transport.flush().await;
+        }
+    }
+
+    fn rate_limited_transport(&self) -> &'static RateLimitedTransport {
+        RATE_LIMITED_TRANSPORT.get_or_init(|| {
+            let message_transport_service = self.clone();
+            RateLimitedTransport::new(
+                Duration::from_secs(self.message_batch_secs),
+                self.message_max_per_minute,
+                Arc::new(move |message| {
+                    let message_transport_service = message_transport_service.clone();
+                    async move { message_transport_service.dispatch_message(message).await }.boxed()
+                }),
             )
-            .await
-            .unwrap();
+        })
+    }
+
+    /// inline or through the notification queue, see NOTIFICATION_QUEUE_SIZE
+    async fn dispatch_message(&self, message: String) {
+        if self.notification_queue_size == 0 {
+            self.deliver_message(message).await;
+            return;
+        }
+
+        let notification_queue = NOTIFICATION_QUEUE.get_or_init(|| {
+            let (sender, mut receiver) = mpsc::channel::<String>(self.notification_queue_size);
+            let message_transport_service = self.clone();
+            tokio::spawn(async move {
+                while let Some(message) = receiver.recv().await {
+                    message_transport_service.deliver_message(message).await;
+                }
+            });
+            sender
+        });
+        if let Err(err) = notification_queue.try_send(message) {
+            log::warn!(
+                "[MessageTransportService] notification queue full, drop message: {:?}",
+                err
+            );
+        }
+    }
+
+    /// fan out to every transport, a failing transport does not hold back the others
+    async fn deliver_message(&self, message: String) {
+        let deliveries = self.senders.iter().map(|sender| {
+            let message = message.clone();
+            async move {
+                let transport = sender.transport();
+                match tokio::time::timeout(
+                    self.notification_timeout,
+                    self.handle_send(sender, escape_message(transport, &message)),
+                )
+                .await
+                {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => {
+                        log::warn!(
+                            "[MessageTransportService] send {:?} failed {:?}, message: {:?}",
+                            transport,
+                            err,
+                            message
+                        );
+                    }
+                    Err(_) => {
+                        log::warn!(
+                            "[MessageTransportService] send {:?} timed out, message: {:?}",
+                            transport,
+                            message
+                        );
+                    }
+                }
+            }
+        });
+        join_all(deliveries).await;
+    }
+
+    async fn handle_send(&self, sender: &TransportSender, message: String) -> anyhow::Result<()> {
+        log::info!("Sending message to {:?}...", sender.transport());
+        match sender {
+            TransportSender::Telegram(telegram_bot, telegram_config) => {
+                telegram_bot
+                    .send_message(telegram_config.telegram_channel_id.clone(), message)
+                    .await?;
+            }
+            TransportSender::Discord(webhook_url) => {
+                self.http_client
+                    .post(webhook_url)
+                    .json(&json!({ "content": message }))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            TransportSender::Slack(webhook_url) => {
+                self.http_client
+                    .post(webhook_url)
+                    .json(&json!({ "text": message }))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+        }
 
         Ok(())
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // async fn handle_send_email(&self, message: String) -> anyhow::Result<()> {
-    //     log::info!("Sending message to list emails...");
-
-    //     let email = Message::builder()
-    //         .from(self.email_sender_address.clone().unwrap().parse().unwrap())
-    //         .to("receipt email".parse().unwrap())
-    //         .subject("Monitor Token Launch")
-    //         .header(ContentType::TEXT_PLAIN)
-    //         .body(message)
-    //         .unwrap();
-
-    //     let creds = Credentials::new("smtp_username".to_owned(), "smtp_password".to_owned());
-
-    //     // Open a remote connection to gmail
-    //     let mailer = SmtpTransport::relay("smtp.gmail.com")
-    //         .unwrap()
-    //         .credentials(creds)
-    //         .build();
-
-    //     // Send the email
-    //     match mailer.send(&email) {
-    //         Ok(_) => log::info!("Email sent successfully!"),
-    //         Err(e) => panic!("Could not send email: {e:?}"),
-    //     }
-    //     Ok(())
-    // }
+    #[test]
+    fn every_transport_is_sent_to_once() {
+        assert_eq!(
+            parse_transports("Slack, Telegram,Slack", true),
+            vec![ETransport::Slack, ETransport::Telegram]
+        );
+        assert_eq!(
+            parse_transports("Discord", true),
+            vec![ETransport::Discord, ETransport::Telegram]
+        );
+        assert!(parse_transports("", false).is_empty());
+    }
+
+    #[test]
+    fn markdown_escaped_per_transport() {
+        let message = "Buy *0x12_34* <ok> & `done`";
+        assert_eq!(escape_message(ETransport::Telegram, message), message);
+        assert_eq!(
+            escape_message(ETransport::Discord, message),
+            "Buy \\*0x12\\_34\\* <ok\\> & \\`done\\`"
+        );
+        assert_eq!(
+            escape_message(ETransport::Slack, message),
+            "Buy *0x12_34* &lt;ok&gt; &amp; `done`"
+        );
+    }
 }
@@ -1,31 +1,46 @@
 use std::sync::Arc;
 use std::{str::FromStr, time::Duration};
 
-use chrono::Utc;
-use ethers::types::{H256, U64};
+use ethers::types::{Block, H256, U64};
 use ethers::utils::keccak256;
 use ethers::{
     providers::{Http, Middleware, Provider},
-    signers::{LocalWallet, Signer, WalletError},
+    signers::Signer,
     types::{transaction::eip2718::TypedTransaction, Address, Bytes, TransactionRequest, U256},
-    utils::parse_ether,
+    utils::{format_ether, parse_ether},
+};
+use mm_token_toolkit::bundler::{
+    get_flashbots_relay_url, BloxrouteConfig, Bundler, BundlerBackend, FlashbotsConfig,
 };
-use mm_token_toolkit::bundler::{BloxrouteConfig, Bundler};
 use mm_token_utils::{
     abi::UniswapV2Router02Abigen,
     constants::{UNISWAP2_ROUTERS, WRAPPED_NATIVE_TOKENS},
     env::get_env,
-    utils::{load_mnemonic_wallet, to_signed_tx},
+    signer::{WalletSigner, WalletSignerError},
+    utils::{load_mnemonic_wallet, load_private_key_wallet, to_signed_tx},
 };
-use provider_utils::constants::DESERIALIZATION_ERROR_MSG;
-use provider_utils::http_providers::HttpProviders;
+use provider_utils::provider_pool::ProviderPool;
+use provider_utils::tx_error::TxError;
+use provider_utils::ws_providers::WsProviders;
 use rand::Rng;
 use tokio::time::timeout;
-use tokio::{sync::RwLock, time};
+use tokio::{
+    sync::{broadcast, RwLock},
+    time,
+};
 use tokio_stream::wrappers::IntervalStream;
 use tokio_stream::StreamExt;
 
-use crate::{constants::Env, utils::get_bloxroute_tip_fee};
+use crate::{
+    constants::Env,
+    utils::{
+        check_bundle_positions, compute_bloxroute_tip_tx, compute_deadline,
+        get_bloxroute_tip_address, get_bloxroute_tip_fee, get_bundle_positions, GasLimits,
+    },
+};
+
+// gas limit of the activate tx when the node cannot estimate it
+const ACTIVATE_GAS_LIMIT: u64 = 500_000;
 
 pub struct MevBuyService {
     env: Env,
@@ -34,23 +49,30 @@ pub struct MevBuyService {
     buyer_surplus_balance: U256,
     tip_pk: String,
     tip_eth_amount: U256,
+    // bloxroute tip wallet of the network, unused by flashbots
+    tip_address: Address,
     activate_pk: String,
+    wallets_top_up: bool,
+    funding_pk: String,
     open_trading_address: Address,
     open_trading_method: String,
     http_provider: Arc<Provider<Http>>,
     gas_price: Arc<RwLock<U256>>,
-    provider_index: Arc<RwLock<usize>>,
+    provider_pool: ProviderPool,
     bundler: Bundler,
     weth_address: Address,
     uniswapv2_router_address: Address,
-    bloxroute_tip_address: Address,
+    // bundles may land a few blocks later, longer than SWAP_DEADLINE_SECS
+    swap_deadline_secs: u64,
+    // buy txs per bundle, 0 sends every buy in one bundle
+    bundle_max_txs: usize,
 }
 
 impl MevBuyService {
     pub fn new(
         env: Env,
         gas_price: Arc<RwLock<U256>>,
-        provider_index: Arc<RwLock<usize>>,
+        provider_pool: ProviderPool,
         http_provider: Arc<Provider<Http>>,
     ) -> Self {
         let Some(uniswapv2_router_address) = UNISWAP2_ROUTERS.get(&env.listen_network) else {
@@ -62,20 +84,50 @@ impl MevBuyService {
                 env.listen_network
             );
         };
-        let bundler = Bundler::new(
-            env.listen_network,
-            BloxrouteConfig {
-                relay_url: get_env("BLOXROUTE_RELAY_URL", None),
-                authorization_key: get_env("BLOXROUTE_AUTH_KEY", None),
-            },
-        );
+        let bundle_relay: BundlerBackend =
+            get_env("BUNDLE_RELAY", Some(BundlerBackend::Bloxroute.to_string()))
+                .parse()
+                .unwrap();
+        let bundler = match bundle_relay {
+            BundlerBackend::Bloxroute => Bundler::new(
+                env.listen_network,
+                BloxrouteConfig {
+                    relay_url: get_env("BLOXROUTE_RELAY_URL", None),
+                    authorization_key: get_env("BLOXROUTE_AUTH_KEY", None),
+                },
+            ),
+            BundlerBackend::Flashbots => Bundler::new_flashbots(
+                env.listen_network,
+                FlashbotsConfig {
+                    relay_url: get_env(
+                        "FLASHBOTS_RELAY_URL",
+                        get_flashbots_relay_url(&env.listen_network).map(str::to_string),
+                    ),
+                    searcher_key: get_env("FLASHBOTS_SEARCHER_KEY", None),
+                },
+            ),
+        };
+        // flashbots builders are paid by the gas price of the bundle txs, no tip tx
+        let (tip_pk, tip_eth_amount, tip_address) = match bundle_relay {
+            BundlerBackend::Bloxroute => (
+                get_env("TIP_PK", None),
+                parse_ether(get_env("TIP_ETH_AMOUNT", None)).unwrap(),
+                get_bloxroute_tip_address(&env.listen_network),
+            ),
+            BundlerBackend::Flashbots => (Default::default(), U256::zero(), Address::zero()),
+        };
         Self {
             buyer_mnemonic: get_env("BUYER_MNEMONIC", None),
             buyer_surplus_balance: parse_ether(get_env("BUYER_SURPLUS_BALANCE", None)).unwrap(),
             buyer_wallets_count: get_env("BUYER_WALLETS_COUNT", None).parse().unwrap(),
-            tip_pk: get_env("TIP_PK", None),
-            tip_eth_amount: parse_ether(get_env("TIP_ETH_AMOUNT", None)).unwrap(),
+            tip_pk,
+            tip_eth_amount,
+            tip_address,
             activate_pk: get_env("ACTIVATE_PK", None),
+            wallets_top_up: get_env("MEV_WALLETS_TOP_UP", Some("false".to_string()))
+                .parse()
+                .unwrap(),
+            funding_pk: get_env("MEV_FUNDING_PK", Some("".to_string())),
             open_trading_address: Address::from_str(&get_env("OPEN_TRADING_ADDRESS", None))
                 .unwrap(),
             open_trading_method: get_env("OPEN_TRADING_METHOD", None),
@@ -83,62 +135,68 @@ impl MevBuyService {
             uniswapv2_router_address: *uniswapv2_router_address,
             env,
             gas_price,
-            provider_index,
+            provider_pool,
             weth_address: weth.address,
             bundler,
-            bloxroute_tip_address: Address::from_str("0x965Df5Ff6116C395187E288e5C87fb96CfB8141c")
+            swap_deadline_secs: get_env("MEV_SWAP_DEADLINE_SECS", Some("120".to_string()))
+                .parse()
+                .unwrap(),
+            bundle_max_txs: get_env("MEV_BUNDLE_MAX_TXS", Some("0".to_string()))
+                .parse()
                 .unwrap(),
         }
     }
 
     pub async fn start(mut self) -> anyhow::Result<()> {
         let mut latest_block = self.http_provider.get_block_number().await?;
-        let mut first_tx_hash_in_batch: Option<H256> = None;
-        let mut stream = IntervalStream::new(time::interval(Duration::from_millis(500)));
+        // labelled tx hashes of the last sent bundle, the tip tx goes first
+        let mut last_bundle_txs: Option<Vec<(String, H256)>> = None;
+        let mut new_heads = match WsProviders::subscribe_blocks(&self.env.listen_network, false)
+            .await
+        {
+            Ok(block_receiver) => NewHeads::Ws(block_receiver),
+            Err(err) => {
+                log::warn!(
+                    "[MevBuyService] new heads subscription unavailable, poll the block number: {:?}",
+                    err
+                );
+                NewHeads::polling()
+            }
+        };
+        // once, a resent bundle spends the tip and activate eth only when it lands
+        self.precheck_wallets(self.buyer_wallets_count + 2).await?;
         loop {
             if self.env.exit.load(std::sync::atomic::Ordering::Relaxed) {
                 break;
             }
-            let Ok(_) = timeout(Duration::from_millis(100), stream.next()).await else {
+
+            let Some(current_block) = new_heads
+                .next_block(self.http_provider.as_ref(), latest_block)
+                .await?
+            else {
                 continue;
             };
 
             // get healthy provider
-            self.http_provider = Arc::new(
-                HttpProviders::get_provider(
-                    &self.env.listen_network,
-                    false,
-                    self.provider_index.clone(),
-                )
-                .await?,
-            );
-
-            let current_block = match self.http_provider.get_block_number().await {
-                Ok(current_block) => current_block,
-                Err(err) => {
-                    if err.to_string().contains(DESERIALIZATION_ERROR_MSG) {
-                        continue;
-                    }
-                    return Err(err.into());
-                }
-            };
+            self.http_provider = Arc::new(self.provider_pool.get_provider().await);
 
-            if current_block == latest_block {
-                continue;
-            }
-
-            if let Some(tx_hash) = first_tx_hash_in_batch {
-                match self.http_provider.get_transaction_receipt(tx_hash).await {
+            if let Some(bundle_txs) = &last_bundle_txs {
+                match self
+                    .http_provider
+                    .get_transaction_receipt(bundle_txs[0].1)
+                    .await
+                {
                     Ok(tx_receipt) => {
                         if let Some(tx_receipt) = tx_receipt {
                             if tx_receipt.status == Some(U64::one()) {
                                 log::info!("Bundle success: {:?}, exiting", tx_receipt);
+                                self.log_bundle_positions(bundle_txs).await;
                                 break;
                             }
                         }
                     }
                     Err(err) => {
-                        if err.to_string().contains(DESERIALIZATION_ERROR_MSG) {
+                        if TxError::classify(&err).is_retryable() {
                             continue;
                         }
                         return Err(err.into());
@@ -146,32 +204,43 @@ impl MevBuyService {
                 }
             }
 
-            let tx_hash = match self.mev_snipe(current_block).await {
-                Ok(tx_hash) => tx_hash,
+            let bundle_txs = match self.mev_snipe(current_block).await {
+                Ok(bundle_txs) => bundle_txs,
                 Err(err) => {
-                    if err.to_string().contains(DESERIALIZATION_ERROR_MSG) {
+                    if TxError::classify(err.as_ref()).is_retryable() {
                         continue;
                     }
                     return Err(err);
                 }
             };
-            log::info!("First tx hash in batch: {:?}", tx_hash);
-            first_tx_hash_in_batch = Some(tx_hash);
+            log::info!("First tx hash in batch: {:?}", bundle_txs[0].1);
+            last_bundle_txs = Some(bundle_txs);
             latest_block = current_block;
         }
 
         Ok(())
     }
 
-    pub async fn mev_snipe(&self, current_block: U64) -> anyhow::Result<H256> {
+    /// sends tip, activate then buys by wallet index, in bundles of `MEV_BUNDLE_MAX_TXS` buys all
+    /// targeting the next block, returns the labelled tx hashes in bundle order
+    pub async fn mev_snipe(&self, current_block: U64) -> anyhow::Result<Vec<(String, H256)>> {
         log::info!("Mev sniping block: {:?}", current_block);
-        let (tip_tx, activate_tx) = tokio::join!(
-            self.compute_tip_tx(self.buyer_wallets_count + 2),
-            self.compute_activate_tx()
-        );
-        let (tip_tx, activate_tx) = (tip_tx?, activate_tx?);
-        let first_tx_hash = H256::from_slice(&keccak256(&tip_tx));
-        let mut signed_txs = vec![tip_tx, activate_tx];
+        let (signed_txs, mut labels) = if self.is_tip_tx_required() {
+            let (tip_tx, activate_tx) = tokio::join!(
+                self.compute_tip_tx(self.buyer_wallets_count + 2),
+                self.compute_activate_tx()
+            );
+            let (tip_tx, activate_tx) = (tip_tx?, activate_tx?);
+            (
+                vec![tip_tx, activate_tx],
+                vec!["tip".to_string(), "active_trading".to_string()],
+            )
+        } else {
+            (
+                vec![self.compute_activate_tx().await?],
+                vec!["active_trading".to_string()],
+            )
+        };
 
         let mut jobs = Vec::new();
         for i in 0..self.buyer_wallets_count {
@@ -181,43 +250,175 @@ impl MevBuyService {
         let signed_buy_txs = signed_buy_txs
             .into_iter()
             .collect::<Result<Vec<Bytes>, _>>()?;
-        signed_txs.extend(signed_buy_txs);
+        labels.extend((0..self.buyer_wallets_count).map(|index| format!("buyer {}", index)));
+        let bundle_txs = labels
+            .into_iter()
+            .zip(signed_txs.iter().chain(&signed_buy_txs))
+            .map(|(label, signed_tx)| (label, H256::from_slice(&keccak256(signed_tx))))
+            .collect();
 
-        let bundle = self
-            .bundler
-            .to_bundle(&signed_txs, current_block, current_block + U64::one());
-        log::info!("Sending bundle {:?}", bundle);
-        let bundle_hashes = self.bundler.send_bundle(&bundle).await?;
-        log::info!("Bundle hashes: {:?}", bundle_hashes);
+        // relays reject oversized bundles, the tip and activate txs only lead the first one
+        let bundles: Vec<_> = chunk_bundle_txs(signed_txs, signed_buy_txs, self.bundle_max_txs)
+            .iter()
+            .map(|chunk| {
+                self.bundler
+                    .to_bundle(chunk, current_block, current_block + U64::one())
+            })
+            .collect();
+        let bundles_count = bundles.len();
+        let results = futures::future::join_all(bundles.iter().enumerate().map(
+            |(index, bundle)| async move {
+                log::info!(
+                    "Sending bundle {}/{} {:?}",
+                    index + 1,
+                    bundles_count,
+                    bundle
+                );
+                let bundle_hashes = self.bundler.send_bundle(bundle).await?;
+                log::info!(
+                    "Bundle {}/{} hashes: {:?}",
+                    index + 1,
+                    bundles_count,
+                    bundle_hashes
+                );
+                anyhow::Ok(bundle_hashes)
+            },
+        ))
+        .await;
+        results.into_iter().collect::<anyhow::Result<Vec<_>>>()?;
 
-        Ok(first_tx_hash)
+        Ok(bundle_txs)
     }
 
-    async fn compute_tip_tx(&self, number_of_txs: u32) -> anyhow::Result<Bytes> {
-        let wallet = self.load_tip_wallet()?;
+    async fn log_bundle_positions(&self, bundle_txs: &[(String, H256)]) {
+        let positions = match get_bundle_positions(self.http_provider.clone(), bundle_txs).await {
+            Ok(positions) => positions,
+            Err(err) => {
+                log::warn!("Failed to fetch bundle positions: {:?}", err);
+                return;
+            }
+        };
+        for position in &positions {
+            log::info!("Bundle position: {:?}", position);
+        }
+        let errors = check_bundle_positions(&positions);
+        if !errors.is_empty() {
+            log::warn!("Bundle positions mismatch: {:?}", errors);
+        }
+    }
+
+    /// the whole bundle fails if the tip or activate wallet cannot pay, check them (and top up) before sending
+    async fn precheck_wallets(&self, number_of_txs: u32) -> anyhow::Result<()> {
+        let gas_price = *self.gas_price.read().await;
+        let tip_gas = self.tip_eth_amount / U256::from(21000);
+        let tip_required = get_bloxroute_tip_fee(&self.env.listen_network, number_of_txs)
+            + (gas_price + tip_gas) * U256::from(21000);
+        let (activate_wallet, activate_tx) = self.build_activate_tx().await?;
+        let activate_required = gas_price * activate_tx.gas().copied().unwrap_or_default();
+
+        let mut required_balances =
+            vec![("activate", activate_wallet.address(), activate_required)];
+        if self.is_tip_tx_required() {
+            required_balances.push(("tip", self.load_tip_wallet()?.address(), tip_required));
+        }
+        let required_balances = sum_required_balances(required_balances);
+        for (name, wallet_address, required) in required_balances {
+            let balance = self.http_provider.get_balance(wallet_address, None).await?;
+            if balance >= required {
+                continue;
+            }
+            if !self.wallets_top_up {
+                return Err(anyhow::anyhow!(
+                    "[MevBuyService.precheck_wallets] {} wallet {:?} underfunded, balance {} ETH, required {} ETH",
+                    name,
+                    wallet_address,
+                    format_ether(balance),
+                    format_ether(required)
+                ));
+            }
+            // twice the requirement, the bundle may be resent for a few blocks before landing
+            self.top_up_wallet(name, wallet_address, required * 2 - balance)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn top_up_wallet(
+        &self,
+        name: &str,
+        wallet_address: Address,
+        value: U256,
+    ) -> anyhow::Result<()> {
+        let funding_wallet =
+            load_private_key_wallet(&self.funding_pk)?.with_chain_id(self.env.chain_id.as_u64());
         let gas_price = *self.gas_price.read().await;
-        let tip_value = get_bloxroute_tip_fee(&self.env.listen_network, number_of_txs);
         let nonce = self
             .http_provider
-            .get_transaction_count(wallet.address(), None)
+            .get_transaction_count(funding_wallet.address(), None)
             .await?;
-        let tip_gas = self.tip_eth_amount / U256::from(21000);
 
         let tx = TransactionRequest::new()
-            .from(wallet.address())
+            .from(funding_wallet.address())
             .nonce(nonce)
-            .to(self.bloxroute_tip_address)
-            .value(tip_value)
-            .gas_price(gas_price + tip_gas)
+            .to(wallet_address)
+            .value(value)
+            .gas_price(gas_price)
             .gas(21000);
-        let mut tip_tx = TypedTransaction::Legacy(tx);
-        tip_tx.set_chain_id(self.env.chain_id);
-        let signed_tx = to_signed_tx(&wallet, &tip_tx).await?;
+        let mut top_up_tx = TypedTransaction::Legacy(tx);
+        top_up_tx.set_chain_id(self.env.chain_id);
+        let signed_tx = to_signed_tx(&funding_wallet, &top_up_tx).await?;
+        log::info!(
+            "Topping up {} wallet {:?} with {} ETH",
+            name,
+            wallet_address,
+            format_ether(value)
+        );
+        let tx_receipt = self
+            .http_provider
+            .send_raw_transaction(signed_tx)
+            .await?
+            .await?;
+        if tx_receipt.and_then(|tx_receipt| tx_receipt.status) != Some(U64::one()) {
+            return Err(anyhow::anyhow!(
+                "[MevBuyService.top_up_wallet] failed to top up {} wallet {:?}",
+                name,
+                wallet_address
+            ));
+        }
 
-        Ok(signed_tx)
+        Ok(())
+    }
+
+    fn is_tip_tx_required(&self) -> bool {
+        self.bundler.backend == BundlerBackend::Bloxroute
+    }
+
+    async fn compute_tip_tx(&self, number_of_txs: u32) -> anyhow::Result<Bytes> {
+        let wallet = self.load_tip_wallet()?;
+        let gas_price = *self.gas_price.read().await;
+
+        compute_bloxroute_tip_tx(
+            &wallet,
+            &self.env.listen_network,
+            self.tip_address,
+            gas_price,
+            self.tip_eth_amount,
+            number_of_txs,
+            self.http_provider.clone(),
+        )
+        .await
     }
 
     async fn compute_activate_tx(&self) -> anyhow::Result<Bytes> {
+        let (wallet, activate_tx) = self.build_activate_tx().await?;
+        let signed_tx = to_signed_tx(&wallet, &activate_tx).await?;
+
+        Ok(signed_tx)
+    }
+
+    /// unsigned activate tx, its gas limit is the node estimate or `ACTIVATE_GAS_LIMIT`
+    async fn build_activate_tx(&self) -> anyhow::Result<(WalletSigner, TypedTransaction)> {
         let wallet = self.load_activate_wallet()?;
         let gas_price = *self.gas_price.read().await;
         let nonce = self
@@ -231,13 +432,18 @@ impl MevBuyService {
             .nonce(nonce)
             .to(self.open_trading_address)
             .data(Bytes::from(method_id.to_vec()))
-            .gas_price(gas_price)
-            .gas(500_000);
+            .gas_price(gas_price);
         let mut activate_tx = TypedTransaction::Legacy(tx);
         activate_tx.set_chain_id(self.env.chain_id);
-        let signed_tx = to_signed_tx(&wallet, &activate_tx).await?;
+        let gas_limit = GasLimits::estimate_or_default(
+            self.http_provider.as_ref(),
+            &activate_tx,
+            ACTIVATE_GAS_LIMIT.into(),
+        )
+        .await;
+        activate_tx.set_gas(gas_limit);
 
-        Ok(signed_tx)
+        Ok((wallet, activate_tx))
     }
 
     async fn compute_signed_buy_tx(&self, wallet_index: u32) -> anyhow::Result<Bytes> {
@@ -262,7 +468,7 @@ impl MevBuyService {
         }
         let random_gas_limit = rand::thread_rng().gen_range(500_000..=550_000); // fixed gas limit
 
-        let deadline = U256::from(Utc::now().timestamp()) + U256::from(120);
+        let deadline = compute_deadline(self.swap_deadline_secs);
         let mut buy_tx: TypedTransaction = uniswapv2_router
             .swap_exact_eth_for_tokens_supporting_fee_on_transfer_tokens(
                 U256::one(),
@@ -283,18 +489,205 @@ impl MevBuyService {
         Ok(signed_tx)
     }
 
-    fn load_tip_wallet(&self) -> Result<LocalWallet, WalletError> {
-        let wallet: LocalWallet = self.tip_pk.parse()?;
+    fn load_tip_wallet(&self) -> Result<WalletSigner, WalletSignerError> {
+        let wallet = load_private_key_wallet(&self.tip_pk)?;
         Ok(wallet.with_chain_id(self.env.chain_id.as_u64()))
     }
 
-    fn load_activate_wallet(&self) -> Result<LocalWallet, WalletError> {
-        let wallet: LocalWallet = self.activate_pk.parse()?;
+    fn load_activate_wallet(&self) -> Result<WalletSigner, WalletSignerError> {
+        let wallet = load_private_key_wallet(&self.activate_pk)?;
         Ok(wallet.with_chain_id(self.env.chain_id.as_u64()))
     }
 
-    fn load_mev_buy_wallet(&self, index: u32) -> Result<LocalWallet, WalletError> {
-        let wallet = load_mnemonic_wallet(&self.buyer_mnemonic, index)?;
+    fn load_mev_buy_wallet(&self, index: u32) -> Result<WalletSigner, WalletSignerError> {
+        let wallet =
+            load_mnemonic_wallet(&self.buyer_mnemonic, index, self.env.listen_network, None)?;
         Ok(wallet.with_chain_id(self.env.chain_id.as_u64()))
     }
 }
+
+/// where the snipe loop learns about new blocks
+enum NewHeads {
+    Ws(broadcast::Receiver<Block<H256>>),
+    // fallback without ws, lags a new block by up to the interval
+    Polling(IntervalStream),
+}
+
+impl NewHeads {
+    fn polling() -> Self {
+        NewHeads::Polling(IntervalStream::new(time::interval(Duration::from_millis(
+            500,
+        ))))
+    }
+
+    /// a block number above `latest_block`, none when nothing new arrived yet. The ws
+    /// subscription falls back to polling once it closed
+    async fn next_block<M: Middleware>(
+        &mut self,
+        http_provider: &M,
+        latest_block: U64,
+    ) -> anyhow::Result<Option<U64>>
+    where
+        M::Error: 'static,
+    {
+        let current_block = match self {
+            NewHeads::Ws(block_receiver) => {
+                let Ok(next_block) =
+                    timeout(Duration::from_millis(100), block_receiver.recv()).await
+                else {
+                    return Ok(None);
+                };
+                match next_block {
+                    Ok(block) => block.number,
+                    Err(broadcast::error::RecvError::Lagged(_)) => return Ok(None),
+                    Err(broadcast::error::RecvError::Closed) => {
+                        log::warn!(
+                            "[MevBuyService] new heads subscription closed, poll the block number"
+                        );
+                        *self = NewHeads::polling();
+                        return Ok(None);
+                    }
+                }
+            }
+            NewHeads::Polling(stream) => {
+                let Ok(_) = timeout(Duration::from_millis(100), stream.next()).await else {
+                    return Ok(None);
+                };
+                match http_provider.get_block_number().await {
+                    Ok(current_block) => Some(current_block),
+                    Err(err) => {
+                        if TxError::classify(&err).is_retryable() {
+                            return Ok(None);
+                        }
+                        return Err(err.into());
+                    }
+                }
+            }
+        };
+
+        Ok(current_block.filter(|current_block| *current_block > latest_block))
+    }
+}
+
+/// one requirement per wallet, a wallet both tipping and activating (TIP_PK == ACTIVATE_PK) pays both
+fn sum_required_balances(
+    required_balances: Vec<(&'static str, Address, U256)>,
+) -> Vec<(&'static str, Address, U256)> {
+    let mut summed: Vec<(&'static str, Address, U256)> = Vec::new();
+    for (name, wallet_address, required) in required_balances {
+        match summed
+            .iter_mut()
+            .find(|(_, summed_address, _)| *summed_address == wallet_address)
+        {
+            Some((summed_name, _, summed_required)) => {
+                *summed_name = "tip and activate";
+                *summed_required += required;
+            }
+            None => summed.push((name, wallet_address, required)),
+        }
+    }
+    summed
+}
+
+/// the buy txs in chunks of `max_buy_txs` (0 = one chunk), the first chunk leads with `head_txs`
+fn chunk_bundle_txs<T>(head_txs: Vec<T>, buy_txs: Vec<T>, max_buy_txs: usize) -> Vec<Vec<T>> {
+    let chunk_size = if max_buy_txs == 0 {
+        buy_txs.len().max(1)
+    } else {
+        max_buy_txs
+    };
+    let mut buy_txs = buy_txs.into_iter().peekable();
+    let mut chunks = vec![head_txs
+        .into_iter()
+        .chain(buy_txs.by_ref().take(chunk_size))
+        .collect::<Vec<_>>()];
+    while buy_txs.peek().is_some() {
+        chunks.push(buy_txs.by_ref().take(chunk_size).collect());
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use provider_utils::mock_rpc::MockRpc;
+
+    #[tokio::test]
+    async fn new_head_triggers_one_snipe() {
+        let (block_sender, block_receiver) = broadcast::channel(16);
+        let mut new_heads = NewHeads::Ws(block_receiver);
+        let http_provider = MockRpc::new().provider();
+        let head = |number: u64| Block::<H256> {
+            number: Some(U64::from(number)),
+            ..Default::default()
+        };
+
+        // a new head, then a stale one
+        block_sender.send(head(101)).unwrap();
+        block_sender.send(head(100)).unwrap();
+        let mut latest_block = U64::from(100);
+        let mut snipes = Vec::new();
+        for _ in 0..3 {
+            if let Some(current_block) = new_heads
+                .next_block(&http_provider, latest_block)
+                .await
+                .unwrap()
+            {
+                snipes.push(current_block);
+                latest_block = current_block;
+            }
+        }
+        assert_eq!(snipes, vec![U64::from(101)]);
+
+        // the subscription ended, the block number is polled
+        drop(block_sender);
+        assert_eq!(
+            new_heads
+                .next_block(&http_provider, latest_block)
+                .await
+                .unwrap(),
+            None
+        );
+        assert!(matches!(new_heads, NewHeads::Polling(_)));
+    }
+
+    #[test]
+    fn buys_are_chunked_behind_the_tip_and_activate() {
+        let buy_txs: Vec<String> = (0..40).map(|index| format!("buyer {}", index)).collect();
+        let head_txs = || vec!["tip".to_string(), "active_trading".to_string()];
+
+        let chunks = chunk_bundle_txs(head_txs(), buy_txs.clone(), 10);
+        assert_eq!(
+            chunks.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![12, 10, 10, 10]
+        );
+        assert_eq!(chunks[0][..3], ["tip", "active_trading", "buyer 0"]);
+        assert_eq!(chunks[1][0], "buyer 10");
+        assert_eq!(chunks[3][9], "buyer 39");
+
+        // no limit keeps a single bundle
+        assert_eq!(chunk_bundle_txs(head_txs(), buy_txs, 0).len(), 1);
+    }
+
+    #[test]
+    fn shared_tip_and_activate_wallet_needs_both_amounts() {
+        let (activate_wallet, tip_wallet) = (Address::random(), Address::random());
+        assert_eq!(
+            sum_required_balances(vec![
+                ("activate", activate_wallet, U256::from(500)),
+                ("tip", tip_wallet, U256::from(300)),
+            ]),
+            vec![
+                ("activate", activate_wallet, U256::from(500)),
+                ("tip", tip_wallet, U256::from(300)),
+            ]
+        );
+        assert_eq!(
+            sum_required_balances(vec![
+                ("activate", activate_wallet, U256::from(500)),
+                ("tip", activate_wallet, U256::from(300)),
+            ]),
+            vec![("tip and activate", activate_wallet, U256::from(800))]
+        );
+    }
+}
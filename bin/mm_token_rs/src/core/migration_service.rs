@@ -4,10 +4,15 @@ use ethers::{
     abi::Address,
     middleware::SignerMiddleware,
     providers::{Http, Provider},
-    signers::{LocalWallet, Signer, WalletError},
+    signers::Signer,
     types::{TransactionReceipt, U256},
 };
-use mm_token_utils::{abi::IUniswapV2PairAbigen, env::get_env, utils::load_mnemonic_wallet};
+use mm_token_utils::{
+    abi::IUniswapV2PairAbigen,
+    env::get_env,
+    signer::{WalletSigner, WalletSignerError},
+    utils::load_mnemonic_wallet,
+};
 use provider_utils::http_providers::HttpProviders;
 
 use crate::{constants::Env, core::WalletService};
@@ -66,9 +71,14 @@ impl MigrationService {
                     .send()
                     .await?
                     .await?;
+                let tx_hash = tx_receipt.map(|x| x.transaction_hash);
                 log::info!(
+                    event = "migration",
+                    wallet_index = index,
+                    tx_hash:? = tx_hash.unwrap_or_default(),
+                    amount:% = token_balance;
                     "sent token tx_hash={:?}",
-                    tx_receipt.map(|x| x.transaction_hash)
+                    tx_hash
                 );
             } else {
                 log::warn!("skip because of zero token balance");
@@ -113,8 +123,13 @@ impl MigrationService {
         Ok(())
     }
 
-    fn load_migration_wallet(&self, index: u32) -> Result<LocalWallet, WalletError> {
-        let wallet = load_mnemonic_wallet(&self.migration_source_mnemonic, index)?;
+    fn load_migration_wallet(&self, index: u32) -> Result<WalletSigner, WalletSignerError> {
+        let wallet = load_mnemonic_wallet(
+            &self.migration_source_mnemonic,
+            index,
+            self.env.listen_network,
+            None,
+        )?;
         Ok(wallet.with_chain_id(self.env.chain_id.as_u64()))
     }
 }
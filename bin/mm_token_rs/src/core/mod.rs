@@ -7,6 +7,9 @@ mod market_maker_service;
 mod message_transport_service;
 mod mev_buy_service;
 mod migration_service;
+mod monitor_service;
+mod private_send_service;
+mod rate_limited_transport;
 mod sell_service;
 mod snipe_service;
 mod wallet_service;
@@ -20,6 +23,9 @@ pub use market_maker_service::*;
 pub use message_transport_service::*;
 pub use mev_buy_service::*;
 pub use migration_service::*;
+pub use monitor_service::*;
+pub use private_send_service::*;
+pub use rate_limited_transport::*;
 pub use sell_service::*;
 pub use snipe_service::*;
 pub use wallet_service::*;
@@ -0,0 +1,187 @@
+use std::{
+    collections::VecDeque,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+
+use anyhow::anyhow;
+use ethers::{
+    contract::parse_log,
+    providers::{Http, Provider},
+    types::{Address, BlockNumber, Filter, U256},
+    utils::{format_ether, parse_ether},
+};
+use mm_token_utils::{
+    abi::IUniswapV2PairAbigenEvents, constants::WRAPPED_NATIVE_TOKENS, env::get_env,
+};
+use provider_utils::ws_providers::WsProviders;
+use tokio::{sync::RwLock, time::timeout};
+
+use crate::{
+    constants::Env,
+    routers::Uniswap2Service,
+    utils::{is_crossed_above, is_crossed_below, is_removed_log},
+};
+
+use super::MessageTransportService;
+
+/**
+ * watch-only: follows the uniswapv2 pair swaps and alerts on price levels and volume spikes, never trades
+ */
+#[derive(Debug, Clone)]
+pub struct MonitorService {
+    env: Env,
+    uniswap2_service: Uniswap2Service,
+    weth_address: Address,
+    price_above: Option<f64>,
+    price_below: Option<f64>,
+    volume_spike: Option<U256>,
+    volume_window: Duration,
+}
+
+impl MonitorService {
+    pub fn new(env: Env, http_provider: Arc<Provider<Http>>) -> Self {
+        let Some(weth) = WRAPPED_NATIVE_TOKENS.get(&env.listen_network) else {
+            panic!(
+                "WRAPPED_NATIVE_TOKENS not found in {:?}",
+                env.listen_network
+            );
+        };
+        let price_above = get_env("MONITOR_PRICE_ABOVE", Some("".to_string()));
+        let price_below = get_env("MONITOR_PRICE_BELOW", Some("".to_string()));
+        let volume_spike = get_env("MONITOR_VOLUME_SPIKE", Some("".to_string()));
+
+        Self {
+            // no tx is sent, the gas price is never read
+            uniswap2_service: Uniswap2Service::new(
+                env.clone(),
+                Arc::new(RwLock::new(U256::zero())),
+                http_provider,
+            ),
+            env,
+            weth_address: weth.address,
+            price_above: (!price_above.is_empty()).then(|| price_above.parse().unwrap()),
+            price_below: (!price_below.is_empty()).then(|| price_below.parse().unwrap()),
+            volume_spike: (!volume_spike.is_empty()).then(|| parse_ether(volume_spike).unwrap()),
+            volume_window: Duration::from_secs(
+                get_env("MONITOR_VOLUME_WINDOW", Some("300".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
+        }
+    }
+
+    pub async fn start(&self) -> anyhow::Result<()> {
+        let (pair_address, is_token0) = self
+            .uniswap2_service
+            .compute_pair_address(&self.env.token_address, &self.weth_address)
+            .await?;
+        let swap_filter = Filter::new()
+            .from_block(BlockNumber::Latest)
+            .event("Swap(address,uint256,uint256,uint256,uint256,address)")
+            .address(pair_address);
+        let mut receiver =
+            WsProviders::subscribe_logs_stream(&self.env.listen_network, swap_filter, false)
+                .await?;
+
+        let message_transport_service = MessageTransportService::new();
+        let message = format!(
+            "[MonitorService] watching pair {:?}, price above {:?}, price below {:?}, volume spike {:?} ETH in {:?}",
+            pair_address,
+            self.price_above,
+            self.price_below,
+            self.volume_spike.map(format_ether),
+            self.volume_window
+        );
+        log::info!("{}", message);
+        message_transport_service.send_message(message).await;
+
+        // (swap time, weth volume) within the volume window
+        let mut volumes: VecDeque<(Instant, U256)> = VecDeque::new();
+        let mut last_price: Option<f64> = None;
+        let mut is_volume_spiking = false;
+        loop {
+            if self.env.exit.load(Ordering::Relaxed) {
+                return Err(anyhow!("[MonitorService.start] exit={:?}", self.env.exit));
+            }
+            let Ok(next_value) = timeout(Duration::from_millis(100), receiver.recv()).await else {
+                continue;
+            };
+            let Ok(log) = next_value else {
+                break;
+            };
+            if is_removed_log(&log) {
+                continue;
+            }
+            let Ok(IUniswapV2PairAbigenEvents::SwapFilter(swap)) = parse_log(log) else {
+                continue;
+            };
+
+            let weth_volume = if is_token0 {
+                swap.amount_1_in + swap.amount_1_out
+            } else {
+                swap.amount_0_in + swap.amount_0_out
+            };
+            let now = Instant::now();
+            volumes.push_back((now, weth_volume));
+            while let Some((swap_time, _)) = volumes.front() {
+                if now.duration_since(*swap_time) <= self.volume_window {
+                    break;
+                }
+                volumes.pop_front();
+            }
+            let window_volume = volumes
+                .iter()
+                .fold(U256::zero(), |total, (_, volume)| total + volume);
+
+            let mut alerts = Vec::new();
+            if let Some(volume_spike) = self.volume_spike {
+                if window_volume >= volume_spike && !is_volume_spiking {
+                    alerts.push(format!(
+                        "volume {} ETH in {:?} reached spike {} ETH",
+                        format_ether(window_volume),
+                        self.volume_window,
+                        format_ether(volume_spike)
+                    ));
+                }
+                is_volume_spiking = window_volume >= volume_spike;
+            }
+
+            let price = match self.uniswap2_service.get_token_native_price().await {
+                Ok((price, _, _)) => Some(price),
+                Err(err) => {
+                    log::warn!("[MonitorService] failed to get token price: {:?}", err);
+                    None
+                }
+            };
+            if let Some(price) = price {
+                if let Some(level) = self.price_above {
+                    if is_crossed_above(last_price, price, level) {
+                        alerts.push(format!("price {} crossed above {}", price, level));
+                    }
+                }
+                if let Some(level) = self.price_below {
+                    if is_crossed_below(last_price, price, level) {
+                        alerts.push(format!("price {} crossed below {}", price, level));
+                    }
+                }
+                last_price = Some(price);
+            }
+
+            log::info!(
+                "[MonitorService] swap {:?} weth volume {} ETH, window volume {} ETH, price {:?}",
+                swap.to,
+                format_ether(weth_volume),
+                format_ether(window_volume),
+                price
+            );
+            for alert in alerts {
+                let message = format!("[MonitorService] {}", alert);
+                log::warn!("{}", message);
+                message_transport_service.send_message(message).await;
+            }
+        }
+
+        Err(anyhow!("[MonitorService.start] swap stream is closed"))
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ethers::{
+    providers::{Http, Middleware, PendingTransaction, Provider, ProviderError},
+    types::{Bytes, Transaction, TransactionReceipt, H256},
+    utils::keccak256,
+};
+use mm_token_toolkit::bundler::{BloxrouteConfig, Bundler};
+use mm_token_utils::env::get_env;
+use provider_utils::{
+    constants::{SEND_TX_BASE_DELAY_MS, SEND_TX_MAX_ATTEMPTS},
+    enums::ENetwork,
+    send_transaction::{send_raw_transaction_with_retry, SendTxError},
+};
+use tokio::sync::RwLock;
+
+use crate::{constants::Env, core::MessageTransportService};
+
+#[derive(Debug, Default)]
+struct SandwichState {
+    sandwiched_count: u32,
+    private_until: Option<Instant>,
+}
+
+/**
+ * send txs publicly, escalate to the private relay for a cooldown after being sandwiched repeatedly
+ */
+#[derive(Debug, Clone)]
+pub struct PrivateSendService {
+    adaptive_private_send: bool,
+    sandwich_threshold: u32,
+    private_send_cooldown: Duration,
+    // blocks the bundle is resent for before falling back to a public send
+    private_send_blocks: u64,
+    bundler: Option<Arc<Bundler>>,
+    state: Arc<RwLock<SandwichState>>,
+}
+
+impl PrivateSendService {
+    pub fn new(env: Env) -> Self {
+        let adaptive_private_send: bool =
+            get_env("ADAPTIVE_PRIVATE_SEND", Some("false".to_string()))
+                .parse()
+                .unwrap();
+
+        // private relay is only available through bloxroute on bsc for now
+        let is_private_relay_supported =
+            [ENetwork::BscMainnet, ENetwork::BscTestnet].contains(&env.listen_network);
+        if adaptive_private_send && !is_private_relay_supported {
+            log::warn!(
+                "[PrivateSendService] private relay not support in {:?}, always send publicly",
+                env.listen_network
+            );
+        }
+        let bundler = if adaptive_private_send && is_private_relay_supported {
+            Some(Arc::new(Bundler::new(
+                env.listen_network,
+                BloxrouteConfig {
+                    relay_url: get_env("BLOXROUTE_RELAY_URL", None),
+                    authorization_key: get_env("BLOXROUTE_AUTH_KEY", None),
+                },
+            )))
+        } else {
+            None
+        };
+
+        let private_send_blocks: u64 = get_env("PRIVATE_SEND_BLOCKS", Some("3".to_string()))
+            .parse()
+            .unwrap();
+        if private_send_blocks == 0 {
+            panic!("PRIVATE_SEND_BLOCKS must be at least 1");
+        }
+
+        Self {
+            adaptive_private_send,
+            sandwich_threshold: get_env("SANDWICH_ESCALATION_THRESHOLD", Some("2".to_string()))
+                .parse()
+                .unwrap(),
+            private_send_cooldown: Duration::from_secs(
+                get_env("PRIVATE_SEND_COOLDOWN", Some("300".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
+            private_send_blocks,
+            bundler,
+            state: Arc::new(RwLock::new(SandwichState::default())),
+        }
+    }
+
+    pub async fn is_private_send_active(&self) -> bool {
+        let state = self.state.read().await;
+        matches!(state.private_until, Some(private_until) if Instant::now() < private_until)
+    }
+
+    /// drop-in for `Provider::send_raw_transaction`, routed through the private relay during cooldown
+    pub async fn send_raw_transaction<'a>(
+        &self,
+        http_provider: &'a Provider<Http>,
+        signed_tx: Bytes,
+    ) -> Result<PendingTransaction<'a, Http>, SendTxError> {
+        let Some(bundler) = &self.bundler else {
+            return send_raw_transaction_with_retry(
+                http_provider,
+                signed_tx,
+                SEND_TX_MAX_ATTEMPTS,
+                Duration::from_millis(SEND_TX_BASE_DELAY_MS),
+            )
+            .await;
+        };
+        if !self.is_private_send_active().await {
+            return send_raw_transaction_with_retry(
+                http_provider,
+                signed_tx,
+                SEND_TX_MAX_ATTEMPTS,
+                Duration::from_millis(SEND_TX_BASE_DELAY_MS),
+            )
+            .await;
+        }
+
+        let tx_hash = H256::from_slice(&keccak256(&signed_tx));
+        let mut current_block = http_provider.get_block_number().await?;
+        for _ in 0..self.private_send_blocks {
+            let target_block = current_block + 1;
+            let bundle = bundler.to_bundle(&vec![signed_tx.clone()], current_block, target_block);
+            let bundle_hashes = bundler
+                .send_bundle(&bundle)
+                .await
+                .map_err(|err| ProviderError::CustomError(err.to_string()))?;
+            log::info!(
+                "[PrivateSendService] sent tx {:?} privately for block {:?}, bundle hashes {:?}",
+                tx_hash,
+                target_block,
+                bundle_hashes
+            );
+
+            // wait until the target block is mined, then check the bundle landed in it
+            loop {
+                current_block = http_provider.get_block_number().await?;
+                if current_block > target_block {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(500)).await;
+            }
+            if http_provider
+                .get_transaction_receipt(tx_hash)
+                .await?
+                .is_some()
+            {
+                return Ok(PendingTransaction::new(tx_hash, http_provider));
+            }
+        }
+
+        log::warn!(
+            "[PrivateSendService] tx {:?} not included privately within {:?} blocks, send publicly",
+            tx_hash,
+            self.private_send_blocks
+        );
+        send_raw_transaction_with_retry(
+            http_provider,
+            signed_tx,
+            SEND_TX_MAX_ATTEMPTS,
+            Duration::from_millis(SEND_TX_BASE_DELAY_MS),
+        )
+        .await
+    }
+
+    /// inspect the block of a confirmed tx, escalate to private send if sandwiched too often
+    pub async fn inspect_sandwich(
+        &self,
+        http_provider: &Provider<Http>,
+        tx_receipt: &TransactionReceipt,
+    ) -> anyhow::Result<()> {
+        if !self.adaptive_private_send {
+            return Ok(());
+        }
+        let Some(block_number) = tx_receipt.block_number else {
+            return Ok(());
+        };
+        let Some(block) = http_provider.get_block_with_txs(block_number).await? else {
+            return Ok(());
+        };
+        if !is_sandwiched(&block.transactions, tx_receipt.transaction_index.as_usize()) {
+            return Ok(());
+        }
+
+        let mut state = self.state.write().await;
+        state.sandwiched_count += 1;
+        log::warn!(
+            "[PrivateSendService] tx {:?} was sandwiched ({:?}/{:?})",
+            tx_receipt.transaction_hash,
+            state.sandwiched_count,
+            self.sandwich_threshold
+        );
+        if state.sandwiched_count < self.sandwich_threshold {
+            return Ok(());
+        }
+
+        state.sandwiched_count = 0;
+        state.private_until = Some(Instant::now() + self.private_send_cooldown);
+        drop(state);
+
+        let message = format!(
+            "[PrivateSendService] sandwiched {:?} times, send privately for the next {:?}",
+            self.sandwich_threshold, self.private_send_cooldown
+        );
+        log::warn!("{}", message);
+        MessageTransportService::new().send_message(message).await;
+
+        Ok(())
+    }
+}
+
+/**
+ * a tx is sandwiched when its neighbours in the block come from the same sender to the same contract
+ */
+pub fn is_sandwiched(transactions: &[Transaction], index: usize) -> bool {
+    if index == 0 || index + 1 >= transactions.len() {
+        return false;
+    }
+    let (front_tx, tx, back_tx) = (
+        &transactions[index - 1],
+        &transactions[index],
+        &transactions[index + 1],
+    );
+
+    front_tx.from == back_tx.from && front_tx.from != tx.from && front_tx.to == back_tx.to
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::Address;
+
+    fn tx(from: Address, to: Address) -> Transaction {
+        Transaction {
+            from,
+            to: Some(to),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_is_sandwiched() {
+        let (bot, us, other) = (Address::random(), Address::random(), Address::random());
+        let (router, bot_contract) = (Address::random(), Address::random());
+
+        // front-run and back-run from the same sender around our tx
+        let block = vec![
+            tx(other, router),
+            tx(bot, bot_contract),
+            tx(us, router),
+            tx(bot, bot_contract),
+        ];
+        assert!(is_sandwiched(&block, 2));
+
+        // a same-sender pair elsewhere in the block does not wrap our tx
+        let block = vec![
+            tx(bot, bot_contract),
+            tx(other, router),
+            tx(bot, bot_contract),
+            tx(us, router),
+            tx(other, router),
+        ];
+        assert!(!is_sandwiched(&block, 3));
+        // the same sender around us calling different contracts
+        let block = vec![tx(bot, bot_contract), tx(us, router), tx(bot, router)];
+        assert!(!is_sandwiched(&block, 1));
+        // our own txs around our tx
+        let block = vec![tx(us, router), tx(us, router), tx(us, router)];
+        assert!(!is_sandwiched(&block, 1));
+
+        // first or last tx of the block has no neighbour on one side
+        let block = vec![tx(us, router), tx(bot, bot_contract), tx(bot, bot_contract)];
+        assert!(!is_sandwiched(&block, 0));
+        let block = vec![tx(bot, bot_contract), tx(bot, bot_contract), tx(us, router)];
+        assert!(!is_sandwiched(&block, 2));
+        assert!(!is_sandwiched(&[tx(us, router)], 0));
+    }
+}
@@ -0,0 +1,228 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use futures::future::BoxFuture;
+use tokio::sync::{mpsc, oneshot};
+
+// the rate limit counts the sends of the last minute
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+/// sends one message to the transports
+pub type DeliverMessage = Arc<dyn Fn(String) -> BoxFuture<'static, ()> + Send + Sync>;
+
+enum BatchCommand {
+    Message(String),
+    // send the queued messages now, acknowledged once delivered
+    Flush(oneshot::Sender<()>),
+}
+
+/**
+ * Coalesce the messages of each `batch_window` into one digest and send at most `max_per_minute`
+ * digests, the overflow waits in the queue and joins the next digest. Critical messages skip both,
+ * `flush` sends the queued ones before the process exits
+ */
+#[derive(Clone)]
+pub struct RateLimitedTransport {
+    sender: mpsc::UnboundedSender<BatchCommand>,
+    sent_at: Arc<Mutex<VecDeque<Instant>>>,
+    deliver: DeliverMessage,
+}
+
+impl std::fmt::Debug for RateLimitedTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RateLimitedTransport")
+            .finish_non_exhaustive()
+    }
+}
+
+impl RateLimitedTransport {
+    /// spawns the batching task, it stops with the last clone
+    pub fn new(batch_window: Duration, max_per_minute: usize, deliver: DeliverMessage) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let sent_at = Arc::new(Mutex::new(VecDeque::new()));
+        tokio::spawn(run_batches(
+            receiver,
+            sent_at.clone(),
+            deliver.clone(),
+            batch_window,
+            max_per_minute.max(1),
+        ));
+
+        Self {
+            sender,
+            sent_at,
+            deliver,
+        }
+    }
+
+    pub fn send(&self, message: String) {
+        if self.sender.send(BatchCommand::Message(message)).is_err() {
+            log::warn!("[RateLimitedTransport] batching stopped, drop message");
+        }
+    }
+
+    /// the queued messages are sent in one digest without waiting for the window or the rate limit
+    pub async fn flush(&self) {
+        let (ack_sender, ack_receiver) = oneshot::channel();
+        if self.sender.send(BatchCommand::Flush(ack_sender)).is_ok() {
+            let _ = ack_receiver.await;
+        }
+    }
+
+    /// sent right away, still counted by the rate limit
+    pub async fn send_critical(&self, message: String) {
+        self.sent_at.lock().unwrap().push_back(Instant::now());
+        (self.deliver)(message).await;
+    }
+}
+
+async fn run_batches(
+    mut receiver: mpsc::UnboundedReceiver<BatchCommand>,
+    sent_at: Arc<Mutex<VecDeque<Instant>>>,
+    deliver: DeliverMessage,
+    batch_window: Duration,
+    max_per_minute: usize,
+) {
+    while let Some(command) = receiver.recv().await {
+        let mut messages = Vec::new();
+        let mut flush_acks = Vec::new();
+        match command {
+            BatchCommand::Message(message) => messages.push(message),
+            BatchCommand::Flush(ack) => {
+                let _ = ack.send(());
+                continue;
+            }
+        }
+        // the window and the rate limit are cut short by a flush
+        let wait = async {
+            tokio::time::sleep(batch_window).await;
+            while let Some(wait) = rate_limit_wait(&sent_at, max_per_minute) {
+                tokio::time::sleep(wait).await;
+            }
+        };
+        tokio::pin!(wait);
+        loop {
+            tokio::select! {
+                _ = &mut wait => break,
+                command = receiver.recv() => match command {
+                    Some(BatchCommand::Message(message)) => messages.push(message),
+                    Some(BatchCommand::Flush(ack)) => {
+                        flush_acks.push(ack);
+                        break;
+                    }
+                    None => break,
+                },
+            }
+        }
+        while let Ok(command) = receiver.try_recv() {
+            match command {
+                BatchCommand::Message(message) => messages.push(message),
+                BatchCommand::Flush(ack) => flush_acks.push(ack),
+            }
+        }
+
+        sent_at.lock().unwrap().push_back(Instant::now());
+        deliver(digest(messages)).await;
+        for ack in flush_acks {
+            let _ = ack.send(());
+        }
+    }
+}
+
+/// time until a send slot frees up, none when one is free
+fn rate_limit_wait(sent_at: &Mutex<VecDeque<Instant>>, max_per_minute: usize) -> Option<Duration> {
+    let mut sent_at = sent_at.lock().unwrap();
+    while sent_at
+        .front()
+        .is_some_and(|sent_at| sent_at.elapsed() >= RATE_LIMIT_WINDOW)
+    {
+        sent_at.pop_front();
+    }
+    if sent_at.len() < max_per_minute {
+        return None;
+    }
+    sent_at
+        .front()
+        .map(|oldest| RATE_LIMIT_WINDOW.saturating_sub(oldest.elapsed()))
+}
+
+/// a lone message is sent as is
+fn digest(messages: Vec<String>) -> String {
+    if messages.len() == 1 {
+        return messages.into_iter().next().unwrap();
+    }
+    format!("{} messages\n\n{}", messages.len(), messages.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    fn recording_transport(
+        batch_window: Duration,
+        max_per_minute: usize,
+    ) -> (RateLimitedTransport, Arc<Mutex<Vec<String>>>) {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let deliver: DeliverMessage = {
+            let sent = sent.clone();
+            Arc::new(move |message| {
+                sent.lock().unwrap().push(message);
+                async {}.boxed()
+            })
+        };
+        (
+            RateLimitedTransport::new(batch_window, max_per_minute, deliver),
+            sent,
+        )
+    }
+
+    #[tokio::test]
+    async fn rapid_messages_collapse_into_one_digest() {
+        let (transport, sent) = recording_transport(Duration::from_millis(200), 20);
+        for index in 0..5 {
+            transport.send(format!("trigger sell {}", index));
+        }
+        tokio::time::sleep(Duration::from_millis(400)).await;
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 1);
+        assert!(sent[0].starts_with("5 messages"), "{}", sent[0]);
+        assert!(sent[0].contains("trigger sell 0") && sent[0].contains("trigger sell 4"));
+    }
+
+    #[tokio::test]
+    async fn critical_message_skips_the_batch_and_the_rate_limit() {
+        let (transport, sent) = recording_transport(Duration::from_millis(200), 1);
+        transport.send("trigger sell".to_string());
+        transport
+            .send_critical("circuit breaker tripped".to_string())
+            .await;
+        assert_eq!(*sent.lock().unwrap(), vec!["circuit breaker tripped"]);
+
+        // the critical send used the only slot of the minute, the digest is queued
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn flush_sends_the_queued_digest_before_exit() {
+        let (transport, sent) = recording_transport(Duration::from_secs(60), 20);
+        transport.send("sell 1".to_string());
+        transport.send("sell 2".to_string());
+        tokio::time::timeout(Duration::from_secs(1), transport.flush())
+            .await
+            .unwrap();
+        assert_eq!(
+            *sent.lock().unwrap(),
+            vec!["2 messages\n\nsell 1\n\nsell 2"]
+        );
+
+        // nothing queued, nothing sent
+        transport.flush().await;
+        assert_eq!(sent.lock().unwrap().len(), 1);
+    }
+}
@@ -7,9 +7,10 @@ use ethers::abi::Tokenizable;
 use ethers::utils::hex;
 use ethers::{
     contract::parse_log,
+    middleware::SignerMiddleware,
     providers::{Http, Middleware, Provider},
-    signers::{LocalWallet, Signer, WalletError},
-    types::{Address, BlockNumber, Filter, H256, U256, U64},
+    signers::Signer,
+    types::{Address, BlockNumber, Filter, Log, Transaction, TransactionReceipt, H256, U256, U64},
     utils::{format_units, parse_ether},
 };
 use futures::future::join_all;
@@ -23,9 +24,12 @@ use mm_token_utils::{
     abi::{IUniswapV2PairAbigenEvents, MemeTokenAbigen},
     constants::{UNISWAP2_ROUTERS, WRAPPED_NATIVE_TOKENS, ZERO_ADDRESS},
     env::get_env,
+    signer::{WalletSigner, WalletSignerError},
     utils::{compute_transaction_hash, load_mnemonic_wallet},
 };
-use provider_utils::{http_providers::HttpProviders, ws_providers::WsProviders};
+use provider_utils::{
+    polling_mempool::subscribe_mempool, provider_pool::ProviderPool, ws_providers::WsProviders,
+};
 use rand::{seq::SliceRandom, Rng};
 use std::{
     collections::HashMap,
@@ -33,7 +37,10 @@ use std::{
     time::Duration,
 };
 use tokio::{
-    sync::{Mutex, RwLock},
+    sync::{
+        broadcast::{self, error::RecvError},
+        Mutex, RwLock,
+    },
     time::timeout,
 };
 use tokio_stream::StreamExt;
@@ -41,19 +48,29 @@ use tokio_stream::StreamExt;
 use crate::routers::RouterService;
 use crate::utils::compute_all_system_wallets;
 use crate::{
-    constants::Env,
-    core::MessageTransportService,
-    types::TokenInfo,
-    utils::{compute_system_wallets, WalletContext},
+    constants::{Env, TokenTaxMode},
+    core::{MessageTransportService, PrivateSendService},
+    types::{SellApproval, TokenInfo, WalletGroup},
+    utils::{
+        add_tax_bps, await_receipt_or_resync, cache_get_or_insert, compute_native_received,
+        compute_reactive_gas_price, compute_system_wallets, fetch_revert_reason, fetch_token_info,
+        format_sell_message, get_native_symbol, get_reactive_gas_config, is_removed_log,
+        is_under_approved, is_within_reorg_depth, percent_to_bps, plan_sell_approval,
+        prefetch_nonce_and_balance, resolve_token_tax, spawn_mempool_workers,
+        wait_for_pair_addresses, CircuitBreaker, GasJitter, NonceManager, OwnWallets, PnlLedger,
+        ReorgWatcher, RevertKind, WalletContext,
+    },
 };
 
 #[derive(Debug, Clone)]
 pub struct SellService {
     env: Env,
+    // ETH, BNB or FTM in the messages
+    native_symbol: &'static str,
     http_provider: Arc<Provider<Http>>,
     weth_address: Address,
     token_info: TokenInfo,
-    provider_index: Arc<RwLock<usize>>,
+    provider_pool: ProviderPool,
     seller_mnemonic: String,
     seller_wallets_count: u32,
     buyer_mnemonic: String,
@@ -64,18 +81,43 @@ pub struct SellService {
     auto_sell_min_percent: u32,
     auto_sell_max_percent: u32,
     buy_tax: f32,
+    tax_mode: TokenTaxMode,
+    reorg_depth: u64,
+    // resyncs the wallets of a buy / sell reorged out within REORG_DEPTH blocks
+    reorg_watcher: ReorgWatcher,
+    mempool_workers: usize,
+    mempool_poll_interval: Duration,
+    ws_shared_subscription: bool,
+    pair_poll_interval: Duration,
+    pair_max_polls: u32,
+    // a sell or its approve not mined within SELL_TX_TIMEOUT_SECS is abandoned and the wallet resynced
+    sell_tx_timeout: Duration,
+    gas_price: Arc<RwLock<U256>>,
+    reactive_gas_config: Option<(u32, U256)>,
+    gas_jitter: GasJitter,
     router_service: RouterService,
+    nonce_manager: NonceManager,
+    // eth in / out of the trades, read by the /pnl command
+    pnl_ledger: PnlLedger,
+    private_send_service: PrivateSendService,
     auto_buyer_system_wallets: Vec<Address>,
     buyer_system_wallets: Vec<Address>,
     seller_system_wallets: HashMap<Address, Arc<RwLock<WalletContext>>>,
     market_maker_system_wallets: Vec<Address>,
+    own_wallets: OwnWallets,
+    circuit_breaker: CircuitBreaker,
+    sell_retry_on_revert: bool,
+    max_slippage_bps: u32,
+    auto_approve_on_sell: bool,
+    // allowance of each seller for the router, lowered by its sells
+    sell_allowances: Arc<Mutex<HashMap<Address, U256>>>,
 }
 
 impl SellService {
     pub fn new(
         env: Env,
         gas_price: Arc<RwLock<U256>>,
-        provider_index: Arc<RwLock<usize>>,
+        provider_pool: ProviderPool,
         http_provider: Arc<Provider<Http>>,
     ) -> Self {
         let Some(weth) = WRAPPED_NATIVE_TOKENS.get(&env.listen_network) else {
@@ -85,13 +127,27 @@ impl SellService {
             );
         };
 
-        let buy_tax: f32 = get_env("TOKEN_BUY_TAX", None).parse().unwrap();
+        let buy_tax = env.token.buy_tax;
+        let reorg_depth: u64 = get_env("REORG_DEPTH", Some("0".to_string()))
+            .parse()
+            .unwrap();
+        let tax_mode: TokenTaxMode = get_env("TOKEN_TAX_MODE", Some("Env".to_string()))
+            .parse()
+            .unwrap();
+        // without a worker the mempool queue fills up and blocks the ingestion
+        let mempool_workers: usize = get_env("MEMPOOL_WORKERS", Some("4".to_string()))
+            .parse()
+            .unwrap();
+        if mempool_workers == 0 {
+            panic!("MEMPOOL_WORKERS must be at least 1");
+        }
         Self {
+            native_symbol: get_native_symbol(&env.listen_network),
             env: env.clone(),
             weth_address: weth.address,
             http_provider: http_provider.clone(),
             token_info: TokenInfo::default(),
-            provider_index,
+            provider_pool,
             seller_mnemonic: get_env("SELLER_MNEMONIC", None),
             seller_wallets_count: get_env("SELLER_WALLETS_COUNT", None).parse().unwrap(),
             buyer_mnemonic: get_env("BUYER_MNEMONIC", None),
@@ -101,36 +157,71 @@ impl SellService {
             auto_sell_volume_threshold: parse_ether(get_env("AUTO_SELL_VOLUME_THRESHOLD", None))
                 .unwrap(),
             buy_tax,
+            tax_mode,
             auto_sell_min_percent: get_env("AUTO_SELL_MIN_PERCENT", None).parse().unwrap(),
             auto_sell_max_percent: get_env("AUTO_SELL_MAX_PERCENT", None).parse().unwrap(),
-            router_service: RouterService::new(env, gas_price, http_provider),
+            reorg_depth,
+            reorg_watcher: ReorgWatcher::new(reorg_depth),
+            mempool_workers,
+            mempool_poll_interval: Duration::from_millis(
+                get_env("MEMPOOL_POLL_INTERVAL_MS", Some("500".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
+            ws_shared_subscription: get_env("WS_SHARED_SUBSCRIPTION", Some("true".to_string()))
+                .parse()
+                .unwrap(),
+            pair_poll_interval: Duration::from_secs(
+                get_env("PAIR_POLL_INTERVAL_SECS", Some("10".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
+            pair_max_polls: get_env("PAIR_MAX_POLLS", Some("360".to_string()))
+                .parse()
+                .unwrap(),
+            sell_tx_timeout: Duration::from_secs(
+                get_env("SELL_TX_TIMEOUT_SECS", Some("30".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
+            gas_price: gas_price.clone(),
+            reactive_gas_config: get_reactive_gas_config(),
+            gas_jitter: GasJitter::from_env(true),
+            router_service: RouterService::new(env.clone(), gas_price, http_provider),
+            nonce_manager: NonceManager::new(),
+            pnl_ledger: PnlLedger::from_env(),
+            private_send_service: PrivateSendService::new(env),
             auto_buyer_system_wallets: Vec::new(),
             buyer_system_wallets: Vec::new(),
             seller_system_wallets: HashMap::new(),
             market_maker_system_wallets: Vec::new(),
+            own_wallets: OwnWallets::default(),
+            circuit_breaker: CircuitBreaker::from_env("SellService"),
+            sell_retry_on_revert: get_env("SELL_RETRY_ON_REVERT", Some("false".to_string()))
+                .parse()
+                .unwrap(),
+            max_slippage_bps: percent_to_bps(
+                get_env("MAX_SLIPPAGE", Some("20".to_string()))
+                    .parse()
+                    .unwrap(),
+            )
+            .unwrap(),
+            auto_approve_on_sell: get_env("AUTO_APPROVE_ON_SELL", Some("true".to_string()))
+                .parse()
+                .unwrap(),
+            sell_allowances: Arc::new(Mutex::new(HashMap::new())),
         }
     }
 
     pub async fn init(&mut self) -> anyhow::Result<()> {
-        let token_info_call =
-            MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
-        let symbol: String = token_info_call.symbol().call().await.unwrap();
-        let name: String = token_info_call.name().call().await.unwrap();
-        let decimals: u8 = token_info_call.decimals().call().await.unwrap();
-        let total_supply: U256 = token_info_call.total_supply().call().await.unwrap();
-
-        self.token_info = TokenInfo {
-            address: self.env.token_address,
-            symbol,
-            name,
-            decimals,
-            total_supply,
-        };
+        self.token_info =
+            fetch_token_info(self.env.token_address, self.http_provider.clone()).await?;
 
+        let seller_wallets: Vec<Address>;
         (
             self.auto_buyer_system_wallets,
             self.buyer_system_wallets,
-            _,
+            seller_wallets,
             self.market_maker_system_wallets,
         ) = compute_all_system_wallets(
             &self.auto_buyer_mnemonic,
@@ -139,48 +230,119 @@ impl SellService {
             self.buyer_wallets_count,
             &self.seller_mnemonic,
             self.seller_wallets_count,
+            self.env.listen_network,
         )
         .await?;
 
         self.seller_system_wallets = compute_system_wallets(
             &self.auto_buyer_mnemonic,
             self.auto_buyer_wallets_count,
+            self.env.listen_network,
             &self.env.token_address,
             self.http_provider.clone(),
         )
         .await?;
 
+        self.reorg_watcher.spawn(
+            "SellService",
+            self.env.token_address,
+            self.seller_system_wallets.clone(),
+            self.http_provider.clone(),
+            self.env.exit.clone(),
+        );
+
+        self.own_wallets = OwnWallets::from_env(
+            self.auto_buyer_system_wallets
+                .iter()
+                .chain(&self.buyer_system_wallets)
+                .chain(&seller_wallets)
+                .chain(self.seller_system_wallets.keys())
+                .chain(&self.market_maker_system_wallets)
+                .copied(),
+        );
+
         Ok(())
     }
 
+    fn record_sell_pnl(&self, tx_receipt: &TransactionReceipt) {
+        if let Err(err) = self.pnl_ledger.record_sell(
+            self.env.token_address,
+            WalletGroup::Seller,
+            compute_native_received(tx_receipt, self.weth_address),
+        ) {
+            log::warn!("[SellService] cannot record pnl {:?}", err);
+        }
+    }
+
+    /// trades of the bots' own wallets must not trigger an auto sell (`SKIP_OWN_WALLET_TX`)
+    fn is_own_wallet(&self, address: &Address) -> bool {
+        self.own_wallets.contains(address)
+    }
+
     pub async fn start_event_mode(
         &self,
         tx_hashes_cache: Arc<Mutex<TimedCache<H256, bool>>>,
     ) -> anyhow::Result<()> {
         let message_transport_service = MessageTransportService::new();
         let message = "Sell service event mode have been launch".to_string();
-        message_transport_service.send_message(message).await?;
+        message_transport_service.send_message(message).await;
+
+        // the detectors start once the token-weth pair is created
+        let pair_addresses = wait_for_pair_addresses(
+            || {
+                self.router_service
+                    .get_all_pair_addresses(&self.env.token_address, &self.weth_address)
+            },
+            self.pair_poll_interval,
+            self.pair_max_polls,
+        )
+        .await?;
 
-        let pair_addresses = self
-            .router_service
-            .get_all_pair_addresses(&self.env.token_address, &self.weth_address)
-            .await?;
+        // one ws subscription for all pairs, each detector keeps the logs of its own pair
+        let shared_receiver = if self.ws_shared_subscription {
+            let pair_topics: Vec<H256> = pair_addresses
+                .iter()
+                .map(|pair_address| H256::from(*pair_address))
+                .collect();
+            Some(
+                WsProviders::subscribe_logs_stream_resilient(
+                    &self.env.listen_network,
+                    self.weth_transfer_filter(pair_topics),
+                    false,
+                )
+                .await?,
+            )
+        } else {
+            None
+        };
 
         let mut futures = Vec::new();
         for pair_address in pair_addresses {
             log::info!("initialized, token-weth pair is {:?}", pair_address);
             let auto_buy_service = self.clone();
             let tx_hashes_cache = tx_hashes_cache.clone();
+            let receiver = match &shared_receiver {
+                Some(shared_receiver) => shared_receiver.resubscribe(),
+                None => {
+                    WsProviders::subscribe_logs_stream_resilient(
+                        &self.env.listen_network,
+                        self.weth_transfer_filter(vec![H256::from(pair_address)]),
+                        false,
+                    )
+                    .await?
+                }
+            };
 
             futures.push(
                 tokio::spawn(async move {
                     let _ = auto_buy_service
-                        .detect_buy_tx(pair_address, tx_hashes_cache)
+                        .detect_buy_tx(pair_address, receiver, tx_hashes_cache)
                         .await;
                 })
                 .boxed(),
             )
         }
+        drop(shared_receiver);
         join_all(futures).await;
 
         Ok(())
@@ -192,13 +354,15 @@ impl SellService {
     ) -> anyhow::Result<()> {
         let message_transport_service = MessageTransportService::new();
         let message = "Sell service mempool mode have been launch".to_string();
-        message_transport_service.send_message(message).await?;
+        message_transport_service.send_message(message).await;
 
-        let get_ws_providers =
-            WsProviders::get_ws_providers(&self.env.listen_network, false).await?;
-
-        let stream_mempool = get_ws_providers[0].subscribe_pending_txs().await.unwrap();
-        let mut stream_mempool = stream_mempool.transactions_unordered(128).fuse();
+        let mut stream_mempool = subscribe_mempool(
+            &self.env.listen_network,
+            self.http_provider.clone(),
+            self.mempool_poll_interval,
+        )
+        .await?
+        .fuse();
 
         let Some(universal_router_address) = UNIVERSAL_ROUTERS.get(&self.env.listen_network) else {
             panic!(
@@ -239,6 +403,30 @@ impl SellService {
             );
         }
 
+        // decouple ingestion from processing, so a slow trigger doesn't block detection
+        // concurrent workers can pick the same system wallet, its write lock serializes the trades
+        let tx_sender = spawn_mempool_workers("SellService", self.mempool_workers, {
+            let service = self.clone();
+            let universal_router_address = *universal_router_address;
+            let uniswapv2_router_address = *uniswapv2_router_address;
+            let uniswapv3_router_address = *uniswapv3_router_address;
+            move |tx| {
+                let service = service.clone();
+                let tx_hashes_cache = tx_hashes_cache.clone();
+                async move {
+                    service
+                        .process_mempool_tx(
+                            tx,
+                            universal_router_address,
+                            uniswapv2_router_address,
+                            uniswapv3_router_address,
+                            &tx_hashes_cache,
+                        )
+                        .await
+                }
+            }
+        });
+
         loop {
             if self.env.exit.load(Ordering::Relaxed) {
                 return Err(anyhow!(
@@ -246,233 +434,234 @@ impl SellService {
                     self.env.exit
                 ));
             }
-            let Some(result) = stream_mempool.next().await else {
-                break;
+            let Some(tx) = stream_mempool.next().await else {
+                // a dropped subscription must not pass for a clean exit
+                return Err(anyhow!(
+                    "[SellService.start_mempool_mode] mempool stream ended"
+                ));
             };
-            let tx = result.unwrap_or_default();
-
-            // let tx_hash_test = H256::from_str(
-            //     "0x0cb237845aa015136cbb0ece26c4a1aadf0e95b7d27ffb1d911f2afb4dec463c",
-            // )
-            // .unwrap();
-            // let tx = self
-            //     .http_provider
-            //     .get_transaction(tx_hash_test)
-            //     .await?
-            //     .unwrap();
-
-            let is_swap_tx_universal_router_matched =
-                tx.input.starts_with(&hex::decode("0x3593564c").unwrap()); // execute(bytes commands,bytes[] inputs,uint256 deadline) methodId
-            let is_buy_tx_uniswap_v2_matched =
-                tx.input.starts_with(&hex::decode("0xb6f9de95").unwrap()); // swapExactETHForTokensSupportingFeeOnTransferTokens methodId
-            let is_buy_tx_uniswap_v3_matched =
-                tx.input.starts_with(&hex::decode("0x04e45aaf").unwrap()); // exactInputSingle(ExactInputSingleParams memory params) methodId
-
-            let trigger_mempool_router: ERouter;
-            let pool_address: Address;
-            let pool_v3_fee_tier: u32;
-            let token_price: f64;
-
-            // check universal router
-            if is_swap_tx_universal_router_matched && tx.to == Some(*universal_router_address) {
-                let sig = "function execute(bytes,bytes[],uint256) external payable";
-                let func = AbiParser::default().parse_function(sig)?;
-                let decoded_data = func.decode_input(&tx.input[4..])?;
-                let decode_command =
-                    Bytes::from_token(decoded_data.first().unwrap().clone()).unwrap();
-                let input_data = decoded_data.get(1).unwrap().clone().into_array().unwrap();
-
-                let mut is_buy_tx_universal_matched: bool = false;
-                for index in 0..decode_command.len() {
-                    let command = decode_command[index];
-                    let input = &input_data[index];
-                    let decode_input = Bytes::from_token(input.clone()).unwrap();
-
-                    let swap_info: SwapUniversalRouterInfo =
-                        universal_decode(command, decode_input);
-
-                    if !swap_info.path.is_empty() {
-                        let from_token = swap_info.path[0];
-                        let to_token = swap_info.path[1];
-                        if from_token == self.weth_address && to_token == self.token_info.address {
-                            log::info!("[AutoSell] from universal router buy tx: {:#?}", tx.hash);
-                            is_buy_tx_universal_matched = true;
-                        }
-                    }
-                }
 
-                if !is_buy_tx_universal_matched {
-                    continue;
-                }
-
-                pool_address = self
-                    .router_service
-                    .get_pair_address_by_router(
-                        &self.env.token_address,
-                        &self.weth_address,
-                        true,
-                        None,
-                        ERouter::UniversalRouters,
-                    )
-                    .await?
-                    .0;
-                token_price = self
-                    .router_service
-                    .get_token_native_price(ERouter::UniversalRouters, pool_address)
-                    .await?;
-                trigger_mempool_router = ERouter::UniversalRouters;
+            // only queue txs sent to the routers we decode
+            let Some(to) = tx.to else {
+                continue;
+            };
+            if ![
+                *universal_router_address,
+                *uniswapv2_router_address,
+                *uniswapv3_router_address,
+            ]
+            .contains(&to)
+            {
+                continue;
             }
-            // check uniswapv2 router
-            else if is_buy_tx_uniswap_v2_matched && tx.to == Some(*uniswapv2_router_address) {
-                let sig = "function swapExactETHForTokensSupportingFeeOnTransferTokens(uint256,address[],address,uint256) external payable";
-                let func = AbiParser::default().parse_function(sig)?;
-                let decoded_data: Vec<_> = func.decode_input(&tx.input[4..])?;
-                let vec_token: Vec<Address> =
-                    Vec::from_token(decoded_data.get(1).unwrap().clone()).unwrap(); // [0]: WETH, [1]: token
-                let buy_token = vec_token[1];
-
-                if buy_token != self.token_info.address {
-                    log::info!("self.token_info.address: {:#?}", self.token_info.address);
-                    log::info!("vec_token: {:#?}", vec_token);
-                    log::warn!("tx is not from token");
-                    continue;
+            if tx_sender.send(tx).await.is_err() {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn process_mempool_tx(
+        &self,
+        tx: Transaction,
+        universal_router_address: Address,
+        uniswapv2_router_address: Address,
+        uniswapv3_router_address: Address,
+        tx_hashes_cache: &Arc<Mutex<TimedCache<H256, bool>>>,
+    ) -> anyhow::Result<()> {
+        // let tx_hash_test = H256::from_str(
+        //     "0x0cb237845aa015136cbb0ece26c4a1aadf0e95b7d27ffb1d911f2afb4dec463c",
+        // )
+        // .unwrap();
+        // let tx = self
+        //     .http_provider
+        //     .get_transaction(tx_hash_test)
+        //     .await?
+        //     .unwrap();
+
+        if self.is_own_wallet(&tx.from) {
+            log::warn!(
+                "tx {:?} from own system wallet {:?}, skip",
+                tx.hash,
+                tx.from
+            );
+            return Ok(());
+        }
+
+        let is_swap_tx_universal_router_matched =
+            tx.input.starts_with(&hex::decode("0x3593564c").unwrap()); // execute(bytes commands,bytes[] inputs,uint256 deadline) methodId
+        let is_buy_tx_uniswap_v2_matched =
+            tx.input.starts_with(&hex::decode("0xb6f9de95").unwrap()); // swapExactETHForTokensSupportingFeeOnTransferTokens methodId
+        let is_buy_tx_uniswap_v3_matched =
+            tx.input.starts_with(&hex::decode("0x04e45aaf").unwrap()); // exactInputSingle(ExactInputSingleParams memory params) methodId
+
+        let trigger_mempool_router: ERouter;
+        let pool_address: Address;
+        let pool_v3_fee_tier: u32;
+        let token_price: f64;
+
+        // check universal router
+        if is_swap_tx_universal_router_matched && tx.to == Some(universal_router_address) {
+            let sig = "function execute(bytes,bytes[],uint256) external payable";
+            let func = AbiParser::default().parse_function(sig)?;
+            let decoded_data = func.decode_input(&tx.input[4..])?;
+            let decode_command = Bytes::from_token(decoded_data.first().unwrap().clone()).unwrap();
+            let input_data = decoded_data.get(1).unwrap().clone().into_array().unwrap();
+
+            let mut is_buy_tx_universal_matched: bool = false;
+            for index in 0..decode_command.len() {
+                let command = decode_command[index];
+                let input = &input_data[index];
+                let decode_input = Bytes::from_token(input.clone()).unwrap();
+
+                let swap_info: SwapUniversalRouterInfo = universal_decode(command, decode_input);
+
+                if !swap_info.path.is_empty() {
+                    let from_token = swap_info.path[0];
+                    let to_token = swap_info.path[1];
+                    if from_token == self.weth_address && to_token == self.token_info.address {
+                        log::info!("[AutoSell] from universal router buy tx: {:#?}", tx.hash);
+                        is_buy_tx_universal_matched = true;
+                    }
                 }
+            }
 
-                pool_address = self
-                    .router_service
-                    .get_pair_address_by_router(
-                        &self.env.token_address,
-                        &self.weth_address,
-                        true,
-                        None,
-                        ERouter::Uniswap2Routers,
-                    )
-                    .await?
-                    .0;
-                token_price = self
-                    .router_service
-                    .get_token_native_price(ERouter::Uniswap2Routers, pool_address)
-                    .await?;
-                trigger_mempool_router = ERouter::Uniswap2Routers;
+            if !is_buy_tx_universal_matched {
+                return Ok(());
             }
-            // check uniswapv3 router
-            else if is_buy_tx_uniswap_v3_matched && tx.to == Some(*uniswapv3_router_address) {
-                let sig = "function exactInputSingle(address,address,uint24,address,uint256,uint256,uint160) external payable override";
-                let func = AbiParser::default().parse_function(sig)?;
-                let decoded_data: Vec<_> = func.decode_input(&tx.input[4..])?;
-
-                let buy_token = Address::from_token(decoded_data.get(1).unwrap().clone()).unwrap();
-                if buy_token != self.token_info.address {
-                    continue;
-                }
 
-                pool_v3_fee_tier = u32::from_token(decoded_data.get(2).unwrap().clone()).unwrap();
+            pool_address = self
+                .router_service
+                .get_pair_address_by_router(
+                    &self.env.token_address,
+                    &self.weth_address,
+                    true,
+                    None,
+                    ERouter::UniversalRouters,
+                )
+                .await?
+                .0;
+            token_price = self
+                .router_service
+                .get_token_native_price(ERouter::UniversalRouters, pool_address, false)
+                .await?;
+            trigger_mempool_router = ERouter::UniversalRouters;
+        }
+        // check uniswapv2 router
+        else if is_buy_tx_uniswap_v2_matched && tx.to == Some(uniswapv2_router_address) {
+            let sig = "function swapExactETHForTokensSupportingFeeOnTransferTokens(uint256,address[],address,uint256) external payable";
+            let func = AbiParser::default().parse_function(sig)?;
+            let decoded_data: Vec<_> = func.decode_input(&tx.input[4..])?;
+            let vec_token: Vec<Address> =
+                Vec::from_token(decoded_data.get(1).unwrap().clone()).unwrap(); // [0]: WETH, [1]: token
+            let buy_token = vec_token[1];
+
+            if buy_token != self.token_info.address {
+                log::info!("self.token_info.address: {:#?}", self.token_info.address);
+                log::info!("vec_token: {:#?}", vec_token);
+                log::warn!("tx is not from token");
+                return Ok(());
+            }
 
-                pool_address = self
-                    .router_service
-                    .get_pair_address_by_router(
-                        &self.env.token_address,
-                        &self.weth_address,
-                        true,
-                        Some(pool_v3_fee_tier),
-                        ERouter::Uniswap3Routers,
-                    )
-                    .await?
-                    .0;
-                token_price = self
-                    .router_service
-                    .get_token_native_price(ERouter::Uniswap3Routers, pool_address)
-                    .await?;
-                trigger_mempool_router = ERouter::Uniswap3Routers;
-            } else {
-                // not match with all of routers
-                continue;
+            pool_address = self
+                .router_service
+                .get_pair_address_by_router(
+                    &self.env.token_address,
+                    &self.weth_address,
+                    true,
+                    None,
+                    ERouter::Uniswap2Routers,
+                )
+                .await?
+                .0;
+            token_price = self
+                .router_service
+                .get_token_native_price(ERouter::Uniswap2Routers, pool_address, false)
+                .await?;
+            trigger_mempool_router = ERouter::Uniswap2Routers;
+        }
+        // check uniswapv3 router
+        else if is_buy_tx_uniswap_v3_matched && tx.to == Some(uniswapv3_router_address) {
+            let sig = "function exactInputSingle(address,address,uint24,address,uint256,uint256,uint160) external payable override";
+            let func = AbiParser::default().parse_function(sig)?;
+            let decoded_data: Vec<_> = func.decode_input(&tx.input[4..])?;
+
+            let buy_token = Address::from_token(decoded_data.get(1).unwrap().clone()).unwrap();
+            if buy_token != self.token_info.address {
+                return Ok(());
             }
 
-            let transaction_hash = tx.hash;
-            let transaction_value = tx.value;
+            pool_v3_fee_tier = u32::from_token(decoded_data.get(2).unwrap().clone()).unwrap();
 
-            if transaction_value < self.auto_sell_volume_threshold {
-                continue;
-            }
+            pool_address = self
+                .router_service
+                .get_pair_address_by_router(
+                    &self.env.token_address,
+                    &self.weth_address,
+                    true,
+                    Some(pool_v3_fee_tier),
+                    ERouter::Uniswap3Routers,
+                )
+                .await?
+                .0;
+            token_price = self
+                .router_service
+                .get_token_native_price(ERouter::Uniswap3Routers, pool_address, false)
+                .await?;
+            trigger_mempool_router = ERouter::Uniswap3Routers;
+        } else {
+            // not match with all of routers
+            return Ok(());
+        }
 
-            // if self.buyer_system_wallets.contains(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from buyer system wallet {:?}, skip",
-            //         tx.hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-            // if self.auto_buyer_system_wallets.contains(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from auto buyer system wallet {:?}, skip",
-            //         tx.hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-            // if self.seller_system_wallets.contains_key(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from seller system wallet {:?}, skip",
-            //         tx.hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-            // if self.market_maker_system_wallets.contains(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from market maker system wallet {:?}, skip",
-            //         tx.hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-
-            // set tx trigger to cache
-            let mut tx_hashes_cache = tx_hashes_cache.lock().await;
-            tx_hashes_cache.cache_set(tx.hash, true);
-            drop(tx_hashes_cache);
-
-            println!(
-                "token_price: {:#?},transaction_value: {:#?}, pool_address: {:#?}",
-                token_price, transaction_value, pool_address
-            );
+        let transaction_hash = tx.hash;
+        let transaction_value = tx.value;
 
-            self.process_trigger_sell(
-                &self.seller_system_wallets,
-                transaction_value,
-                transaction_hash,
-                true,
-                &pool_address,
-                trigger_mempool_router,
-                token_price,
-            )
-            .await?;
+        if transaction_value < self.auto_sell_volume_threshold {
+            return Ok(());
         }
 
+        // claimed here or by the event mode, whichever sees the tx first triggers
+        if cache_get_or_insert(tx_hashes_cache, tx.hash).await {
+            log::warn!("Meet this tx hash before from event mode: {:#?}", tx.hash);
+            return Ok(());
+        }
+
+        log::info!(
+            "token_price: {:#?},transaction_value: {:#?}, pool_address: {:#?}",
+            token_price,
+            transaction_value,
+            pool_address
+        );
+
+        self.process_trigger_sell(
+            &self.seller_system_wallets,
+            transaction_value,
+            transaction_hash,
+            true,
+            &pool_address,
+            trigger_mempool_router,
+            token_price,
+            tx.gas_price,
+        )
+        .await?;
+
         Ok(())
     }
 
     async fn detect_buy_tx(
         mut self,
         pair_address: Address,
+        mut receiver: broadcast::Receiver<Log>,
         tx_hashes_cache: Arc<Mutex<TimedCache<H256, bool>>>,
     ) -> anyhow::Result<()> {
-        // detect weth transfer from user to pair (user buy token from pair)
-        // conflict with add_liquidity, but we accept it
-        let erc20_transfer_filter = Filter::new()
-            .from_block(BlockNumber::Latest)
-            .event("Transfer(address,address,uint256)")
-            .topic2(H256::from(pair_address))
-            .address(self.weth_address);
+        // acquire the healthy provider once, refreshed only on provider errors
+        self.refresh_http_provider().await?;
+        let pair_topic = H256::from(pair_address);
 
-        let mut receiver = WsProviders::subscribe_logs_stream(
-            &self.env.listen_network,
-            erc20_transfer_filter,
-            false,
-        )
-        .await?;
+        // (trigger tx hash -> block number) of logs we already reacted on
+        let mut triggered_blocks: TimedCache<H256, U64> = TimedCache::with_lifespan(600);
 
         // assume that tx is success because there is an Transfer event
         loop {
@@ -486,82 +675,79 @@ impl SellService {
             let Ok(next_value) = timeout(Duration::from_millis(100), receiver.recv()).await else {
                 continue;
             };
-            let Ok(log) = next_value else {
-                break;
+            let log = match next_value {
+                Ok(log) => log,
+                Err(RecvError::Lagged(skipped)) => {
+                    log::warn!(
+                        "[SellService] detector of pair {:?} lagged, skipped {:?} logs",
+                        pair_address,
+                        skipped
+                    );
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
             };
+            // the shared subscription delivers the logs of every pair
+            if log.topics.get(2) != Some(&pair_topic) {
+                continue;
+            }
 
             let transaction_hash = log.transaction_hash.unwrap_or_default();
 
-            tokio::time::sleep(Duration::from_secs(1)).await; // wait for mempool to cache first
-            let mut tx_hashes_cache = tx_hashes_cache.lock().await;
-            if tx_hashes_cache.cache_get(&transaction_hash).is_some() {
-                log::warn!(
-                    "Meet this tx hash before from mempool mode: {:#?}",
-                    transaction_hash
-                );
-                drop(tx_hashes_cache);
+            // never trade on a log dropped by a reorg
+            if is_removed_log(&log) {
+                self.handle_removed_log(&mut triggered_blocks, transaction_hash)
+                    .await?;
                 continue;
             }
 
-            // get healthy provider
-            self.http_provider = Arc::new(
-                HttpProviders::get_provider(
-                    &self.env.listen_network,
-                    false,
-                    self.provider_index.clone(),
-                )
-                .await?,
-            );
-
             let Ok(IUniswapV2PairAbigenEvents::TransferFilter(decoded)) = parse_log(log) else {
                 continue;
             };
             if decoded.value < self.auto_sell_volume_threshold {
                 continue;
             }
-            let tx = self.http_provider.get_transaction(transaction_hash).await?;
+            let tx = match self.http_provider.get_transaction(transaction_hash).await {
+                Ok(tx) => tx,
+                Err(err) => {
+                    log::warn!("[SellService] provider error, refresh provider: {:?}", err);
+                    self.refresh_http_provider().await?;
+                    self.http_provider.get_transaction(transaction_hash).await?
+                }
+            };
             let Some(tx) = tx else {
                 log::warn!("cannot fetch tx {:?} from fullnode", transaction_hash);
                 continue;
             };
-            // if self.buyer_system_wallets.contains(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from buyer system wallet {:?}, skip",
-            //         tx.hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-            // if self.auto_buyer_system_wallets.contains(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from auto buyer system wallet {:?}, skip",
-            //         tx.hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-            // if self.seller_system_wallets.contains_key(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from seller system wallet {:?}, skip",
-            //         tx.hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
-            // if self.market_maker_system_wallets.contains(&tx.from) {
-            //     log::warn!(
-            //         "tx {:?} from market maker system wallet {:?}, skip",
-            //         tx.hash,
-            //         tx.from
-            //     );
-            //     continue;
-            // }
+            if self.is_own_wallet(&tx.from) {
+                log::warn!(
+                    "tx {:?} from own system wallet {:?}, skip",
+                    tx.hash,
+                    tx.from
+                );
+                continue;
+            }
 
             let token_price = self
                 .router_service
-                .get_token_native_price(self.router_service.active_router, pair_address)
+                .get_token_native_price(self.router_service.active_router, pair_address, false)
                 .await?;
 
+            // claimed here or by the mempool mode, whichever sees the tx first triggers
+            if cache_get_or_insert(&tx_hashes_cache, transaction_hash).await {
+                log::warn!(
+                    "Meet this tx hash before from mempool mode: {:#?}",
+                    transaction_hash
+                );
+                continue;
+            }
+
+            if self.reorg_depth > 0 {
+                if let Some(block_number) = tx.block_number {
+                    triggered_blocks.cache_set(transaction_hash, block_number);
+                }
+            }
+
             self.process_trigger_sell(
                 &self.seller_system_wallets,
                 decoded.value,
@@ -570,6 +756,7 @@ impl SellService {
                 &pair_address,
                 self.router_service.active_router,
                 token_price,
+                None,
             )
             .await?;
         }
@@ -577,6 +764,56 @@ impl SellService {
         Ok(())
     }
 
+    fn weth_transfer_filter(&self, pair_topics: Vec<H256>) -> Filter {
+        // detect weth transfer from user to pair (user buy token from pair)
+        // conflict with add_liquidity, but we accept it
+        Filter::new()
+            .from_block(BlockNumber::Latest)
+            .event("Transfer(address,address,uint256)")
+            .topic2(pair_topics)
+            .address(self.weth_address)
+    }
+
+    /// switch to the healthy provider
+    async fn refresh_http_provider(&mut self) -> anyhow::Result<()> {
+        self.http_provider = Arc::new(self.provider_pool.get_provider().await);
+        Ok(())
+    }
+
+    async fn handle_removed_log(
+        &self,
+        triggered_blocks: &mut TimedCache<H256, U64>,
+        transaction_hash: H256,
+    ) -> anyhow::Result<()> {
+        let Some(trigger_block) = triggered_blocks.cache_remove(&transaction_hash) else {
+            log::warn!(
+                "[SellService] ignore removed log of tx {:?}",
+                transaction_hash
+            );
+            return Ok(());
+        };
+
+        let current_block = self.http_provider.get_block_number().await?;
+        if !is_within_reorg_depth(trigger_block, current_block, self.reorg_depth) {
+            return Ok(());
+        }
+
+        log::warn!(
+            "[SellService] trigger buy tx {:?} at block {:?} was removed by a reorg, sell already executed",
+            transaction_hash,
+            trigger_block
+        );
+        let message = format!(
+            "[SellService] trigger buy tx {:?} at block {:?} was removed by a reorg, sell already executed",
+            transaction_hash, trigger_block
+        );
+        MessageTransportService::new()
+            .send_critical_message(message)
+            .await;
+
+        Ok(())
+    }
+
     #[allow(clippy::too_many_arguments)]
     async fn process_trigger_sell(
         &self,
@@ -587,7 +824,10 @@ impl SellService {
         pair_address: &Address,
         active_router: ERouter,
         token_price: f64,
+        trigger_gas_price: Option<U256>,
     ) -> anyhow::Result<()> {
+        // triggers received while paused are sold once the cooldown elapsed
+        self.circuit_breaker.wait_until_resumed().await;
         let message_transport_service = MessageTransportService::new();
 
         if is_from_mempool {
@@ -599,7 +839,7 @@ impl SellService {
                 "[SellService] trigger sell from mempool mode for buy tx {:?}",
                 trigger_tx_hash
             );
-            message_transport_service.send_message(message).await?;
+            message_transport_service.send_message(message).await;
         } else {
             log::info!(
                 "[SellService] trigger sell from event mode for buy tx {:?}",
@@ -609,12 +849,20 @@ impl SellService {
                 "[SellService] trigger sell from event mode for buy tx {:?}",
                 trigger_tx_hash
             );
-            message_transport_service.send_message(message).await?;
+            message_transport_service.send_message(message).await;
         }
 
         let mut wallet_configs: Vec<(Address, U256)> = Vec::new(); // (wallet_index, token_sell_amount)
         let mut the_chosen_ones: Vec<Address> = Vec::new();
-        let tx_sell_amount = self
+        let buy_tax = resolve_token_tax(
+            self.tax_mode,
+            self.buy_tax,
+            self.env.token_address,
+            self.http_provider.clone(),
+        )
+        .await;
+        // a failed quote must not stop the detector, skip this trigger only
+        let tx_sell_amount = match self
             .router_service
             .get_amount_out(
                 active_router,
@@ -622,10 +870,22 @@ impl SellService {
                 true,
                 Some(&self.weth_address),
                 Some(&self.env.token_address),
+                None,
                 tx_value,
-                self.buy_tax,
+                add_tax_bps(0, buy_tax),
             )
-            .await?;
+            .await
+        {
+            Ok(tx_sell_amount) => tx_sell_amount,
+            Err(err) => {
+                log::warn!(
+                    "[SellService] cannot quote buy tx {:?}, skip: {:?}",
+                    trigger_tx_hash,
+                    err
+                );
+                return Ok(());
+            }
+        };
         let random_sell_percent =
             rand::thread_rng().gen_range(self.auto_sell_min_percent..=self.auto_sell_max_percent);
         let mut total_sell_amount =
@@ -665,10 +925,13 @@ impl SellService {
                     format_units(total_sell_amount, self.token_info.decimals as usize)?,
                     self.token_info.symbol
                 );
-                message_transport_service.send_message(message).await?;
+                message_transport_service
+                    .send_critical_message(message)
+                    .await;
             }
         }
 
+        let gas_price = self.reactive_gas_price(trigger_gas_price).await;
         for (wallet_address, sell_amount) in wallet_configs {
             let Some(wallet_context) = system_wallets.get(&wallet_address) else {
                 continue;
@@ -684,6 +947,7 @@ impl SellService {
                         sell_amount,
                         token_price,
                         &pair_address_clone,
+                        gas_price,
                     )
                     .await;
             });
@@ -692,6 +956,31 @@ impl SellService {
         Ok(())
     }
 
+    /// price the reactive trade relative to the trigger tx when `REACTIVE_GAS_PRICE_PERCENT` is set
+    async fn reactive_gas_price(&self, trigger_gas_price: Option<U256>) -> Option<U256> {
+        let (percent, max_gas_price) = self.reactive_gas_config?;
+        let trigger_gas_price = trigger_gas_price?;
+        let gas_price = *self.gas_price.read().await;
+        Some(compute_reactive_gas_price(
+            gas_price,
+            trigger_gas_price,
+            percent,
+            max_gas_price,
+        ))
+    }
+
+    /// `GAS_PRICE_JITTER_BUY_SELL` jitters the reactive or shared gas price of each sell
+    async fn jittered_gas_price(&self, gas_price: Option<U256>) -> Option<U256> {
+        let base_gas_price = match gas_price {
+            Some(gas_price) => gas_price,
+            None => *self.gas_price.read().await,
+        };
+        self.gas_jitter
+            .apply(base_gas_price, self.http_provider.clone())
+            .await
+            .or(gas_price)
+    }
+
     async fn sell(
         &self,
         trigger_tx_hash: H256,
@@ -699,12 +988,38 @@ impl SellService {
         sell_amount: U256,
         token_price: f64,
         pair_address: &Address,
+        gas_price: Option<U256>,
     ) -> Result<bool, Box<dyn std::error::Error>> {
+        // no reactive sell while the gas is above MAX_GAS_PRICE_GWEI
+        if !self.router_service.is_gas_acceptable().await {
+            return Ok(false);
+        }
         let message_transport_service = MessageTransportService::new();
+        let gas_price = self.jittered_gas_price(gas_price).await;
 
         let mut wallet_context_mut = wallet_context.write().await;
+        // a stuck sell may still be mined, a new one would sell twice
+        if wallet_context_mut
+            .is_stuck(self.env.token_address, self.http_provider.clone())
+            .await?
+        {
+            return Ok(false);
+        }
         let wallet = self.load_wallet(wallet_context_mut.index)?;
 
+        // a fresh seller wallet has never approved the router, its sell would revert
+        if !self
+            .approve_router_if_needed(
+                &mut wallet_context_mut,
+                &wallet,
+                sell_amount,
+                trigger_tx_hash,
+            )
+            .await?
+        {
+            return Ok(false);
+        }
+
         let signed_sell_tx = match self
             .router_service
             .construct_sell_token_tx(
@@ -713,6 +1028,7 @@ impl SellService {
                 sell_amount,
                 pair_address,
                 true,
+                gas_price,
             )
             .await
         {
@@ -732,9 +1048,21 @@ impl SellService {
             trigger_tx_hash,
         );
 
+        if self.env.dry_run {
+            if let Err(err) = self.router_service.simulate_tx(&signed_sell_tx).await {
+                log::warn!(
+                    "[SellService] dry run sell {:?} fails {:?}",
+                    sell_tx_hash,
+                    err
+                );
+            }
+            // nothing was sent, the wallet nonce and balance stay as they are
+            return Ok(true);
+        }
+
         let pending_tx = self
-            .http_provider
-            .send_raw_transaction(signed_sell_tx)
+            .private_send_service
+            .send_raw_transaction(&self.http_provider, signed_sell_tx.clone())
             .await;
         log::info!(
             "[SellService] pending tx sell from wallet index {:#?} result {:?}",
@@ -743,46 +1071,127 @@ impl SellService {
         );
         match pending_tx {
             Ok(pending_tx) => {
-                let tx_receipt = pending_tx.await?.ok_or(anyhow!("Cannot find tx_receipt"))?;
+                // persisted before waiting, a restart meanwhile must not reuse the nonce
+                if let Err(err) = self
+                    .nonce_manager
+                    .record_submitted(wallet_context_mut.address, wallet_context_mut.nonce)
+                    .await
+                {
+                    log::warn!("[SellService] cannot persist nonce {:?}", err);
+                }
+                if let Some(allowance) = self
+                    .sell_allowances
+                    .lock()
+                    .await
+                    .get_mut(&wallet_context_mut.address)
+                {
+                    *allowance = allowance.saturating_sub(sell_amount);
+                }
+                let tx_hash = pending_tx.tx_hash();
+                let Some(tx_receipt) = await_receipt_or_resync(
+                    pending_tx,
+                    tx_hash,
+                    self.sell_tx_timeout,
+                    &mut wallet_context_mut,
+                    self.env.token_address,
+                    self.http_provider.clone(),
+                )
+                .await?
+                else {
+                    return Ok(true);
+                };
+                if let Err(err) = self
+                    .private_send_service
+                    .inspect_sandwich(&self.http_provider, &tx_receipt)
+                    .await
+                {
+                    log::warn!("[SellService] inspect sandwich failed {:?}", err);
+                }
                 let message = if tx_receipt.status == Some(U64::zero()) {
-                    log::warn!("Sell transaction {:#?} failed", tx_receipt.transaction_hash);
-                    format!(
-                        "Sell transaction {:#?} failed \nToken price: {:#?} ETH\nVolume: {:#?} {:#?}",
+                    log::warn!(
+                        event = "sell_reverted",
+                        wallet_index = wallet_context_mut.index,
+                        tx_hash:? = tx_receipt.transaction_hash,
+                        amount:% = format_units(sell_amount, self.token_info.decimals as usize)?,
+                        token_price = token_price;
+                        "Sell transaction {:#?} failed",
+                        tx_receipt.transaction_hash
+                    );
+                    self.circuit_breaker
+                        .record_failure(&format!(
+                            "sell tx {:?} reverted",
+                            tx_receipt.transaction_hash
+                        ))
+                        .await;
+                    format_sell_message(
                         tx_receipt.transaction_hash,
+                        false,
                         token_price,
-                        format_units(sell_amount, self.token_info.decimals as usize)?,
-                        self.token_info.symbol
+                        &format_units(sell_amount, self.token_info.decimals as usize)?,
+                        &self.token_info.symbol,
+                        self.native_symbol,
                     )
                 } else {
-                    log::info!("[SellService] tx success {:?}", tx_receipt.transaction_hash);
+                    log::info!(
+                        event = "sell_sent",
+                        wallet_index = wallet_context_mut.index,
+                        tx_hash:? = tx_receipt.transaction_hash,
+                        amount:% = format_units(sell_amount, self.token_info.decimals as usize)?,
+                        token_price = token_price;
+                        "[SellService] tx success {:?}",
+                        tx_receipt.transaction_hash
+                    );
+                    self.circuit_breaker.record_success();
                     wallet_context_mut.token_balance -= sell_amount;
-                    format!(
-                        "Sell transaction {:#?} success \nToken price: {:#?} ETH\nVolume: {:#?} {:#?}",
+                    self.reorg_watcher
+                        .record(wallet_context_mut.address, &tx_receipt);
+                    self.record_sell_pnl(&tx_receipt);
+                    format_sell_message(
                         tx_receipt.transaction_hash,
+                        true,
                         token_price,
-                        format_units(sell_amount, self.token_info.decimals as usize)?,
-                        self.token_info.symbol
+                        &format_units(sell_amount, self.token_info.decimals as usize)?,
+                        &self.token_info.symbol,
+                        self.native_symbol,
                     )
                 };
-                message_transport_service.send_message(message).await?;
+                message_transport_service
+                    .send_outcome_message(message, tx_receipt.status == Some(U64::zero()))
+                    .await;
                 wallet_context_mut.nonce += U256::one();
+
+                if tx_receipt.status == Some(U64::zero()) && self.sell_retry_on_revert {
+                    if let Err(err) = self
+                        .retry_reverted_sell(
+                            &wallet,
+                            &mut wallet_context_mut,
+                            &signed_sell_tx,
+                            &tx_receipt,
+                            sell_amount,
+                            pair_address,
+                            gas_price,
+                        )
+                        .await
+                    {
+                        log::warn!("[SellService] sell retry failed {:?}", err);
+                    }
+                }
                 Ok(true)
             }
             Err(err) => {
                 log::warn!("reset wallet context because of {:?}", err);
+                self.circuit_breaker
+                    .record_failure(&format!("send sell tx failed {:?}", err))
+                    .await;
                 let token_contract =
                     MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
                 let balance_of = token_contract.balance_of(wallet_context_mut.address);
-                let (token_balance, eth_balance, nonce) = tokio::join!(
+                let (token_balance, prefetched) = tokio::join!(
                     balance_of.call(),
-                    self.http_provider
-                        .get_balance(wallet_context_mut.address, None),
-                    self.http_provider
-                        .get_transaction_count(wallet_context_mut.address, None)
+                    prefetch_nonce_and_balance(&self.http_provider, wallet_context_mut.address)
                 );
                 let token_balance = token_balance?;
-                let eth_balance = eth_balance?;
-                let nonce = nonce?;
+                let (nonce, eth_balance) = prefetched?;
                 wallet_context_mut.token_balance = token_balance;
                 wallet_context_mut.eth_balance = eth_balance;
                 wallet_context_mut.nonce = nonce;
@@ -791,8 +1200,226 @@ impl SellService {
         }
     }
 
-    fn load_wallet(&self, index: u32) -> Result<LocalWallet, WalletError> {
-        let wallet = load_mnemonic_wallet(&self.seller_mnemonic, index)?;
+    /// `SELL_RETRY_ON_REVERT`: resubmit once with a widened slippage (capped at `MAX_SLIPPAGE`) and the next nonce,
+    /// only when the revert reason is slippage
+    #[allow(clippy::too_many_arguments)]
+    async fn retry_reverted_sell(
+        &self,
+        wallet: &WalletSigner,
+        wallet_context: &mut WalletContext,
+        reverted_sell_tx: &ethers::types::Bytes,
+        reverted_tx_receipt: &TransactionReceipt,
+        sell_amount: U256,
+        pair_address: &Address,
+        gas_price: Option<U256>,
+    ) -> anyhow::Result<()> {
+        let message_transport_service = MessageTransportService::new();
+        let revert_reason = fetch_revert_reason(
+            self.http_provider.as_ref(),
+            reverted_sell_tx,
+            reverted_tx_receipt.block_number,
+        )
+        .await;
+        let revert_kind = RevertKind::from_reason(revert_reason.as_deref());
+        if !revert_kind.is_retryable() {
+            log::info!(
+                "[SellService] sell {:?} reverted by {:?} ({:?}), not retried",
+                reverted_tx_receipt.transaction_hash,
+                revert_kind,
+                revert_reason
+            );
+            return Ok(());
+        }
+
+        let signed_sell_tx = self
+            .router_service
+            .construct_widened_sell_token_tx(
+                wallet,
+                Some(wallet_context.nonce),
+                sell_amount,
+                pair_address,
+                gas_price,
+                self.max_slippage_bps,
+            )
+            .await?;
+        let pending_tx = self
+            .private_send_service
+            .send_raw_transaction(&self.http_provider, signed_sell_tx)
+            .await?;
+        if let Err(err) = self
+            .nonce_manager
+            .record_submitted(wallet_context.address, wallet_context.nonce)
+            .await
+        {
+            log::warn!("[SellService] cannot persist nonce {:?}", err);
+        }
+        let tx_hash = pending_tx.tx_hash();
+        let Some(tx_receipt) = await_receipt_or_resync(
+            pending_tx,
+            tx_hash,
+            self.sell_tx_timeout,
+            wallet_context,
+            self.env.token_address,
+            self.http_provider.clone(),
+        )
+        .await?
+        else {
+            return Ok(());
+        };
+        wallet_context.nonce += U256::one();
+
+        let message = if tx_receipt.status == Some(U64::zero()) {
+            log::warn!(
+                "[SellService] sell retry {:?} failed",
+                tx_receipt.transaction_hash
+            );
+            self.circuit_breaker
+                .record_failure(&format!(
+                    "sell retry {:?} reverted",
+                    tx_receipt.transaction_hash
+                ))
+                .await;
+            format!(
+                "Sell retry {:#?} of {:#?} failed",
+                tx_receipt.transaction_hash, reverted_tx_receipt.transaction_hash
+            )
+        } else {
+            log::info!(
+                "[SellService] sell retry {:?} success",
+                tx_receipt.transaction_hash
+            );
+            self.circuit_breaker.record_success();
+            wallet_context.token_balance -= sell_amount;
+            self.reorg_watcher
+                .record(wallet_context.address, &tx_receipt);
+            self.record_sell_pnl(&tx_receipt);
+            format!(
+                "Sell retry {:#?} of {:#?} success with slippage up to {:?}% \nVolume: {:#?} {:#?}",
+                tx_receipt.transaction_hash,
+                reverted_tx_receipt.transaction_hash,
+                self.max_slippage_bps as f64 / 100.0,
+                format_units(sell_amount, self.token_info.decimals as usize)?,
+                self.token_info.symbol
+            )
+        };
+        message_transport_service
+            .send_outcome_message(message, tx_receipt.status == Some(U64::zero()))
+            .await;
+
+        Ok(())
+    }
+
+    /// allowance of the seller for the router, read from chain only once the cached one no longer covers the sell
+    async fn get_sell_allowance(&self, owner: Address, sell_amount: U256) -> anyhow::Result<U256> {
+        if let Some(allowance) = self.sell_allowances.lock().await.get(&owner).copied() {
+            if !is_under_approved(allowance, sell_amount) {
+                return Ok(allowance);
+            }
+        }
+        let allowance = self.router_service.get_sell_allowance(owner).await?;
+        self.sell_allowances.lock().await.insert(owner, allowance);
+        Ok(allowance)
+    }
+
+    /// false when the sell must not be sent: the seller has not approved the router and
+    /// `AUTO_APPROVE_ON_SELL=false`, or its approve failed or was not mined within `SELL_TX_TIMEOUT_SECS`
+    pub async fn approve_router_if_needed(
+        &self,
+        wallet_context: &mut WalletContext,
+        wallet: &WalletSigner,
+        sell_amount: U256,
+        trigger_tx_hash: H256,
+    ) -> anyhow::Result<bool> {
+        let allowance = self
+            .get_sell_allowance(wallet_context.address, sell_amount)
+            .await?;
+        match plan_sell_approval(allowance, sell_amount, self.auto_approve_on_sell) {
+            SellApproval::Approved => {}
+            SellApproval::ApproveFirst if self.env.dry_run => {
+                log::info!(
+                    "[SellService] dry run, wallet index {:?} would approve the router first",
+                    wallet_context.index
+                );
+                return Ok(false);
+            }
+            SellApproval::ApproveFirst => {
+                log::info!(
+                    "[SellService] wallet index {:?} approving the router before its sell",
+                    wallet_context.index
+                );
+                let nonce = wallet_context.nonce;
+                let approve = self.router_service.approve_sell_if_under_approved(
+                    allowance,
+                    sell_amount,
+                    nonce,
+                    Arc::new(SignerMiddleware::new(
+                        self.http_provider.clone(),
+                        wallet.clone(),
+                    )),
+                );
+                match tokio::time::timeout(self.sell_tx_timeout, approve).await {
+                    Ok(Ok(next_nonce)) => {
+                        if let Err(err) = self
+                            .nonce_manager
+                            .record_submitted(wallet_context.address, nonce)
+                            .await
+                        {
+                            log::warn!("[SellService] cannot persist nonce {:?}", err);
+                        }
+                        wallet_context.nonce = next_nonce;
+                        self.sell_allowances
+                            .lock()
+                            .await
+                            .insert(wallet_context.address, U256::MAX);
+                    }
+                    Ok(Err(err)) => {
+                        log::warn!("[SellService] approve before sell failed {:?}", err);
+                        wallet_context.nonce = self
+                            .http_provider
+                            .get_transaction_count(wallet_context.address, None)
+                            .await?;
+                        return Ok(false);
+                    }
+                    // the approve may still be mined, the next tx of the wallet goes after it
+                    Err(_) => {
+                        log::warn!(
+                            "[SellService] wallet index {:?} approve not mined within {:?}",
+                            wallet_context.index,
+                            self.sell_tx_timeout
+                        );
+                        wallet_context.nonce = self
+                            .http_provider
+                            .get_transaction_count(
+                                wallet_context.address,
+                                Some(BlockNumber::Pending.into()),
+                            )
+                            .await?;
+                        return Ok(false);
+                    }
+                }
+            }
+            SellApproval::Skip => {
+                log::warn!(
+                    "[SellService] wallet index {:?} address {:?} has not approved the router, sell skipped",
+                    wallet_context.index,
+                    wallet_context.address
+                );
+                MessageTransportService::new()
+                    .send_critical_message(format!(
+                        "⚠️ Seller wallet {:?} has not approved the router, sell for buy tx {:#?} skipped\nApprove it or set AUTO_APPROVE_ON_SELL=true",
+                        wallet_context.address, trigger_tx_hash
+                    ))
+                    .await;
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    fn load_wallet(&self, index: u32) -> Result<WalletSigner, WalletSignerError> {
+        let wallet =
+            load_mnemonic_wallet(&self.seller_mnemonic, index, self.env.listen_network, None)?;
         let wallet = wallet.with_chain_id(self.env.chain_id.as_u64());
         Ok(wallet)
     }
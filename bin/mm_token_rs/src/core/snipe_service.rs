@@ -4,11 +4,10 @@ use std::{
 };
 
 use anyhow::anyhow;
-use chrono::Utc;
 use ethers::{
     contract::parse_log,
     providers::{Http, Middleware, Provider},
-    signers::{LocalWallet, Signer, WalletError},
+    signers::Signer,
     types::{
         transaction::eip2718::TypedTransaction, Address, BlockNumber, Filter, Log,
         TransactionReceipt, U256, U64,
@@ -21,12 +20,22 @@ use mm_token_utils::{
     },
     constants::{UNISWAP2_ROUTERS, WRAPPED_NATIVE_TOKENS, ZERO_ADDRESS},
     env::get_env,
-    utils::{compute_transaction_hash, load_mnemonic_wallet, to_legacy_tx, to_signed_tx},
+    signer::{WalletSigner, WalletSignerError},
+    utils::{compute_transaction_hash, load_mnemonic_wallet, to_signed_tx},
+};
+use provider_utils::{
+    constants::{SEND_TX_BASE_DELAY_MS, SEND_TX_MAX_ATTEMPTS},
+    provider_pool::ProviderPool,
+    send_transaction::send_raw_transaction_with_retry,
+    ws_providers::WsProviders,
 };
-use provider_utils::{http_providers::HttpProviders, ws_providers::WsProviders};
 use tokio::{sync::RwLock, time::timeout};
 
-use crate::constants::Env;
+use crate::{
+    constants::{Env, SwapMethodMode},
+    core::MessageTransportService,
+    utils::{apply_gas_mode, compute_deadline, resolve_fee_on_transfer, GasLimits, HoneypotCheck},
+};
 
 #[derive(Debug, Clone)]
 pub struct SnipeService {
@@ -36,8 +45,12 @@ pub struct SnipeService {
     uniswapv2_factory_address: Address,
     weth_address: Address,
     gas_price: Arc<RwLock<U256>>,
-    provider_index: Arc<RwLock<usize>>,
+    provider_pool: ProviderPool,
     snipe_mnemonic: String,
+    swap_method_mode: SwapMethodMode,
+    swap_deadline_secs: u64,
+    gas_limits: GasLimits,
+    is_honeypot_check: bool,
     nonce: Arc<RwLock<U256>>,
 }
 
@@ -45,7 +58,7 @@ impl SnipeService {
     pub fn new(
         env: Env,
         gas_price: Arc<RwLock<U256>>,
-        provider_index: Arc<RwLock<usize>>,
+        provider_pool: ProviderPool,
         http_provider: Arc<Provider<Http>>,
     ) -> Self {
         let Some(uniswapv2_router_address) = UNISWAP2_ROUTERS.get(&env.listen_network) else {
@@ -66,8 +79,18 @@ impl SnipeService {
             uniswapv2_factory_address: *ZERO_ADDRESS,
             weth_address: weth.address,
             gas_price,
-            provider_index,
+            provider_pool,
             snipe_mnemonic,
+            swap_method_mode: get_env("SWAP_METHOD_MODE", Some("Auto".to_string()))
+                .parse()
+                .unwrap(),
+            swap_deadline_secs: get_env("SWAP_DEADLINE_SECS", Some("60".to_string()))
+                .parse()
+                .unwrap(),
+            gas_limits: GasLimits::from_env(500_000),
+            is_honeypot_check: get_env("SNIPE_HONEYPOT_CHECK", Some("false".to_string()))
+                .parse()
+                .unwrap(),
             nonce: Default::default(),
         }
     }
@@ -116,14 +139,7 @@ impl SnipeService {
             };
 
             // get healthy provider
-            self.http_provider = Arc::new(
-                HttpProviders::get_provider(
-                    &self.env.listen_network,
-                    false,
-                    self.provider_index.clone(),
-                )
-                .await?,
-            );
+            self.http_provider = Arc::new(self.provider_pool.get_provider().await);
 
             let snipe_service = self.clone();
             tokio::spawn(async move {
@@ -165,6 +181,24 @@ impl SnipeService {
             );
             return Ok(());
         }
+        if self.is_honeypot_check {
+            let honeypot_check = HoneypotCheck::new(
+                self.http_provider.clone(),
+                self.uniswapv2_router_address,
+                self.weth_address,
+            );
+            if let Err(err) = honeypot_check.check(token).await {
+                log::warn!("[SnipeService] skip honeypot {:?}: {:?}", token, err);
+                let message = format!(
+                    "[SnipeService] skip snipe of {:?}, cannot sell it back: {}",
+                    token, err
+                );
+                MessageTransportService::new()
+                    .send_critical_message(message)
+                    .await;
+                return Ok(());
+            }
+        }
 
         let wallet = self.load_snipe_wallet()?;
         let Some(tx_receipt) = self.snipe(&wallet, token).await? else {
@@ -199,7 +233,7 @@ impl SnipeService {
         Ok(())
     }
 
-    async fn auto_sell(&self, wallet: &LocalWallet, token_address: Address) -> anyhow::Result<()> {
+    async fn auto_sell(&self, wallet: &WalletSigner, token_address: Address) -> anyhow::Result<()> {
         let wallet_address = wallet.address();
         let token_contract =
             MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
@@ -226,32 +260,47 @@ impl SnipeService {
 
     async fn sell(
         &self,
-        wallet: &LocalWallet,
+        wallet: &WalletSigner,
         token_address: Address,
         sell_amount: U256,
     ) -> anyhow::Result<()> {
+        let is_fee_on_transfer = self.is_fee_on_transfer(token_address).await;
         let mut nonce_mut = self.nonce.write().await;
         let gas_price = *self.gas_price.read().await;
         let uniswapv2_router =
             UniswapV2Router02Abigen::new(self.uniswapv2_router_address, self.http_provider.clone());
 
-        let deadline = U256::from(Utc::now().timestamp()) + U256::from(60);
-        let mut tx = uniswapv2_router
-            .swap_exact_tokens_for_eth_supporting_fee_on_transfer_tokens(
-                sell_amount,
-                U256::one(),
-                vec![token_address, self.weth_address],
-                wallet.address(),
-                deadline,
-            )
-            .tx;
+        let deadline = compute_deadline(self.swap_deadline_secs);
+        let path = vec![token_address, self.weth_address];
+        let mut tx = if is_fee_on_transfer {
+            uniswapv2_router
+                .swap_exact_tokens_for_eth_supporting_fee_on_transfer_tokens(
+                    sell_amount,
+                    U256::one(),
+                    path,
+                    wallet.address(),
+                    deadline,
+                )
+                .tx
+        } else {
+            uniswapv2_router
+                .swap_exact_tokens_for_eth(
+                    sell_amount,
+                    U256::one(),
+                    path,
+                    wallet.address(),
+                    deadline,
+                )
+                .tx
+        };
         tx.set_chain_id(self.env.chain_id);
         tx.set_from(wallet.address());
         tx.set_nonce(*nonce_mut);
-        tx.set_gas(U256::from(500_000));
-        tx.set_gas_price(gas_price);
-
-        let snipe_tx = to_legacy_tx(tx);
+        tx.set_gas(
+            GasLimits::estimate_or_default(self.http_provider.as_ref(), &tx, self.gas_limits.sell)
+                .await,
+        );
+        let snipe_tx = apply_gas_mode(tx, gas_price, self.http_provider.clone()).await?;
         let signed_tx = to_signed_tx(wallet, &snipe_tx).await?;
         let tx_hash = compute_transaction_hash(&signed_tx);
         log::info!(
@@ -260,11 +309,14 @@ impl SnipeService {
             wallet.address(),
             tx_hash,
         );
-        let tx_receipt = self
-            .http_provider
-            .send_raw_transaction(signed_tx)
-            .await?
-            .await?;
+        let tx_receipt = send_raw_transaction_with_retry(
+            self.http_provider.as_ref(),
+            signed_tx,
+            SEND_TX_MAX_ATTEMPTS,
+            Duration::from_millis(SEND_TX_BASE_DELAY_MS),
+        )
+        .await?
+        .await?;
         let Some(tx_receipt) = tx_receipt else {
             log::warn!("auto_sell failed");
             return Ok(());
@@ -280,7 +332,7 @@ impl SnipeService {
         Ok(())
     }
 
-    async fn approve(&self, wallet: &LocalWallet, token_address: Address) -> anyhow::Result<()> {
+    async fn approve(&self, wallet: &WalletSigner, token_address: Address) -> anyhow::Result<()> {
         let mut nonce_mut = self.nonce.write().await;
         let token = IUniswapV2PairAbigen::new(token_address, self.http_provider.clone());
         let gas_price = *self.gas_price.read().await;
@@ -289,10 +341,15 @@ impl SnipeService {
         tx.set_chain_id(self.env.chain_id);
         tx.set_from(wallet.address());
         tx.set_nonce(*nonce_mut);
-        tx.set_gas(U256::from(500_000));
-        tx.set_gas_price(gas_price);
-
-        let snipe_tx = to_legacy_tx(tx);
+        tx.set_gas(
+            GasLimits::estimate_or_default(
+                self.http_provider.as_ref(),
+                &tx,
+                self.gas_limits.approve,
+            )
+            .await,
+        );
+        let snipe_tx = apply_gas_mode(tx, gas_price, self.http_provider.clone()).await?;
         let signed_tx = to_signed_tx(wallet, &snipe_tx).await?;
         let tx_hash = compute_transaction_hash(&signed_tx);
         log::info!(
@@ -301,11 +358,14 @@ impl SnipeService {
             wallet.address(),
             tx_hash,
         );
-        let tx_receipt = self
-            .http_provider
-            .send_raw_transaction(signed_tx)
-            .await?
-            .await?;
+        let tx_receipt = send_raw_transaction_with_retry(
+            self.http_provider.as_ref(),
+            signed_tx,
+            SEND_TX_MAX_ATTEMPTS,
+            Duration::from_millis(SEND_TX_BASE_DELAY_MS),
+        )
+        .await?
+        .await?;
         let Some(tx_receipt) = tx_receipt else {
             log::warn!("approve failed");
             return Ok(());
@@ -323,7 +383,7 @@ impl SnipeService {
 
     async fn snipe(
         &self,
-        wallet: &LocalWallet,
+        wallet: &WalletSigner,
         token: Address,
     ) -> anyhow::Result<Option<TransactionReceipt>> {
         let uniswapv2_router =
@@ -332,23 +392,31 @@ impl SnipeService {
         let snipe_eth_amount =
             parse_ether(get_env("SNIPE_ETH_AMOUNT", Some("0".to_string()))).unwrap();
 
+        let is_fee_on_transfer = self.is_fee_on_transfer(token).await;
         let mut nonce_mut = self.nonce.write().await;
-        let deadline = U256::from(Utc::now().timestamp()) + U256::from(60);
-        let mut snipe_tx: TypedTransaction = uniswapv2_router
-            .swap_exact_eth_for_tokens_supporting_fee_on_transfer_tokens(
-                U256::one(),
-                vec![self.weth_address, token],
-                wallet.address(),
-                deadline,
-            )
-            .tx;
+        let deadline = compute_deadline(self.swap_deadline_secs);
+        let path = vec![self.weth_address, token];
+        let mut snipe_tx: TypedTransaction = if is_fee_on_transfer {
+            uniswapv2_router
+                .swap_exact_eth_for_tokens_supporting_fee_on_transfer_tokens(
+                    U256::one(),
+                    path,
+                    wallet.address(),
+                    deadline,
+                )
+                .tx
+        } else {
+            uniswapv2_router
+                .swap_exact_eth_for_tokens(U256::one(), path, wallet.address(), deadline)
+                .tx
+        };
         snipe_tx.set_chain_id(self.env.chain_id);
         snipe_tx.set_from(wallet.address());
         snipe_tx.set_nonce(*nonce_mut);
         snipe_tx.set_value(snipe_eth_amount);
-        snipe_tx.set_gas(U256::from(500_000));
-        snipe_tx.set_gas_price(gas_price);
-        let snipe_tx = to_legacy_tx(snipe_tx);
+        // no estimate, the snipe races the liquidity block
+        snipe_tx.set_gas(self.gas_limits.buy);
+        let snipe_tx = apply_gas_mode(snipe_tx, gas_price, self.http_provider.clone()).await?;
         let signed_snipe_tx = to_signed_tx(wallet, &snipe_tx).await?;
         let snipe_tx_hash = compute_transaction_hash(&signed_snipe_tx);
         log::info!(
@@ -357,11 +425,14 @@ impl SnipeService {
             wallet.address(),
             snipe_tx_hash,
         );
-        let tx_receipt = self
-            .http_provider
-            .send_raw_transaction(signed_snipe_tx)
-            .await?
-            .await?;
+        let tx_receipt = send_raw_transaction_with_retry(
+            self.http_provider.as_ref(),
+            signed_snipe_tx,
+            SEND_TX_MAX_ATTEMPTS,
+            Duration::from_millis(SEND_TX_BASE_DELAY_MS),
+        )
+        .await?
+        .await?;
         let Some(tx_receipt) = tx_receipt else {
             log::warn!("snipe failed");
             return Ok(None);
@@ -377,12 +448,27 @@ impl SnipeService {
         Ok(Some(tx_receipt))
     }
 
-    fn load_snipe_wallet(&self) -> Result<LocalWallet, WalletError> {
+    async fn is_fee_on_transfer(&self, token_address: Address) -> bool {
+        resolve_fee_on_transfer(
+            self.swap_method_mode,
+            token_address,
+            self.weth_address,
+            self.uniswapv2_router_address,
+            self.http_provider.clone(),
+        )
+        .await
+    }
+
+    fn load_snipe_wallet(&self) -> Result<WalletSigner, WalletSignerError> {
         self.load_mnemonic_wallet(&self.snipe_mnemonic, 0)
     }
 
-    fn load_mnemonic_wallet(&self, mnemonic: &str, index: u32) -> Result<LocalWallet, WalletError> {
-        let wallet = load_mnemonic_wallet(mnemonic, index)?;
+    fn load_mnemonic_wallet(
+        &self,
+        mnemonic: &str,
+        index: u32,
+    ) -> Result<WalletSigner, WalletSignerError> {
+        let wallet = load_mnemonic_wallet(mnemonic, index, self.env.listen_network, None)?;
         let wallet = wallet.with_chain_id(self.env.chain_id.as_u64());
         Ok(wallet)
     }
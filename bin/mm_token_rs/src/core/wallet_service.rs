@@ -1,33 +1,61 @@
 use crate::{
-    constants::Env, core::MessageTransportService, routers::RouterService, utils::format_bmk,
+    constants::Env,
+    core::MessageTransportService,
+    routers::RouterService,
+    types::{
+        BatchApprove, DisperseEthAmount, MmConfig, PanicSell, PanicSellError, PanicSellPlan,
+        PanicSellWallet, WalletApproval, WalletApprovalError,
+    },
+    utils::{
+        await_confirmations, find_under_approved_wallets, format_bmk, plan_disperse_eth_values,
+        plan_panic_sells, read_balances_and_allowances, read_json_file, GasCeiling, GasLimits,
+        GAS_BUMP_PERCENT,
+    },
 };
 use anyhow::anyhow;
 use ethers::{
+    abi::Detokenize,
+    contract::ContractCall,
     middleware::SignerMiddleware,
     providers::{Http, Middleware, Provider},
-    signers::{LocalWallet, Signer, WalletError},
+    signers::Signer,
     types::{
         transaction::eip2718::TypedTransaction, Address, TransactionReceipt, TransactionRequest,
-        U256, U64,
+        H256, U256, U64,
     },
     utils::{format_ether, format_units, parse_ether},
 };
 use futures::future::join_all;
 use mm_token_utils::{
     abi::{DisperseAbigen, IUniswapV2PairAbigen, MemeTokenAbigen},
-    constants::WRAPPED_NATIVE_TOKENS,
+    constants::{DISPERSE_ROUTERS, WRAPPED_NATIVE_TOKENS, ZERO_ADDRESS},
     env::get_env,
-    utils::{load_mnemonic_wallet, to_legacy_tx, to_signed_tx},
+    signer::{WalletSigner, WalletSignerError},
+    utils::{load_mnemonic_wallet, load_private_key_wallet, to_legacy_tx, to_signed_tx},
 };
-use provider_utils::constants::DESERIALIZATION_ERROR_MSG;
+use provider_utils::tx_error::TxError;
 use rand::Rng;
 use regex::Regex;
 use std::{
     str::FromStr,
-    sync::Arc,
+    sync::{atomic::Ordering, Arc, LazyLock},
     time::{Duration, Instant},
 };
-use tokio::{sync::RwLock, task};
+use tokio::{
+    sync::{RwLock, Semaphore},
+    task,
+};
+
+// resends with the shortfall of the node error before `send_entire_eth_balance` falls back
+const MAX_OVERSHOT_RESENDS: u32 = 3;
+// a transfer not mined within it is replaced at the same nonce with a higher gas price
+const ETH_TRANSFER_TIMEOUT: Duration = Duration::from_secs(60);
+const MAX_TRANSFER_GAS_BUMPS: u32 = 3;
+// shortfall of a rejected transfer, "overshot N" or the geth "have X want Y"
+static OVERSHOT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"overshot (?P<overshot>\d+)").unwrap());
+static HAVE_WANT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"have (?P<have>\d+) want (?P<want>\d+)").unwrap());
 
 #[derive(Debug, Clone)]
 pub struct WalletService {
@@ -35,6 +63,60 @@ pub struct WalletService {
     http_provider: Arc<Provider<Http>>,
     token_address: Address,
     weth_address: Address,
+    gas_limits: GasLimits,
+    confirmations: u64,
+    // migrations not CONFIRMATIONS deep within CONFIRMATIONS_TIMEOUT_SECS are not swept
+    confirmations_timeout: Duration,
+}
+
+/// wallets of the buyer, auto buyer or seller mnemonic, `MarketMaker` is a group of the token mm config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WalletRole {
+    Buyer,
+    AutoBuyer,
+    Seller,
+    MarketMaker(usize),
+}
+
+impl FromStr for WalletRole {
+    type Err = anyhow::Error;
+
+    /// `market_maker_<n>` is the mm group at index n
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "buyer" => Ok(WalletRole::Buyer),
+            "auto_buyer" => Ok(WalletRole::AutoBuyer),
+            "seller" => Ok(WalletRole::Seller),
+            _ => role
+                .strip_prefix("market_maker_")
+                .and_then(|mm_index| mm_index.parse().ok())
+                .map(WalletRole::MarketMaker)
+                .ok_or(anyhow!("invalid wallet role {:?}", role)),
+        }
+    }
+}
+
+/// wallets sold by `dump_group`, `MarketMaker` is every group of the token mm config
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DumpGroup {
+    Buyer,
+    AutoBuyer,
+    Seller,
+    MarketMaker,
+}
+
+impl FromStr for DumpGroup {
+    type Err = anyhow::Error;
+
+    fn from_str(group: &str) -> Result<Self, Self::Err> {
+        match group {
+            "buyer" => Ok(DumpGroup::Buyer),
+            "auto_buyer" => Ok(DumpGroup::AutoBuyer),
+            "seller" => Ok(DumpGroup::Seller),
+            "market_maker" => Ok(DumpGroup::MarketMaker),
+            _ => Err(anyhow!("invalid dump group {:?}", group)),
+        }
+    }
 }
 
 impl WalletService {
@@ -46,10 +128,19 @@ impl WalletService {
             );
         };
         Self {
+            token_address: env.token_address,
             env,
             http_provider,
-            token_address: Address::from_str(&get_env("TOKEN_ADDRESS", None)).unwrap(),
             weth_address: weth.address,
+            gas_limits: GasLimits::from_env(500_000),
+            confirmations: get_env("CONFIRMATIONS", Some("1".to_string()))
+                .parse()
+                .unwrap(),
+            confirmations_timeout: Duration::from_secs(
+                get_env("CONFIRMATIONS_TIMEOUT_SECS", Some("300".to_string()))
+                    .parse()
+                    .unwrap(),
+            ),
         }
     }
 
@@ -104,31 +195,23 @@ impl WalletService {
         let mut total_token_balance = U256::zero();
         let mut total_eth_balance = U256::zero();
 
-        let router_address = RouterService::new(
+        let router_service = RouterService::new(
             self.env.clone(),
             Arc::new(RwLock::new(U256::zero())),
             self.http_provider.clone(),
-        )
-        .get_router_address()?;
+        );
         log::info!(
-            "checking wallets' balances, uniswapv2_router={:?}",
-            router_address
+            "checking wallets' balances, spenders={:?}",
+            router_service.get_spender_addresses()?
         );
         let buyer_wallets_count: u32 = get_env("BUYER_WALLETS_COUNT", None).parse().unwrap();
         for index in 0..buyer_wallets_count {
             let wallet = self.load_buyer_wallets(index)?;
             let wallet_address = wallet.address();
 
-            let balance_of = token_contract.balance_of(wallet_address);
-            let allowance = token_contract.allowance(wallet_address, router_address);
-            let (balance, allowance, eth_balance) = tokio::join!(
-                balance_of.call(),
-                allowance.call(),
-                self.http_provider.get_balance(wallet_address, None)
-            );
-            let balance: U256 = balance?;
-            let allowance = allowance?;
-            let eth_balance = eth_balance?;
+            let (balance, allowance, eth_balance) = self
+                .get_wallet_balances(&token_contract, wallet_address, &router_service)
+                .await?;
 
             log::info!(
                 "wallet_index {:?}, address {:?}, token_balance {:?}, eth_balance {:?}, allowance {:?}",
@@ -171,6 +254,446 @@ impl WalletService {
         Ok(())
     }
 
+    /// Repair wallets flagged by `check_buyer_balance`
+    /// Top up eth from the disperse wallet, then approve max token to the router
+    /// Wallets already consistent are skipped, so it is safe to rerun
+    pub async fn repair_wallets(
+        &self,
+        role: WalletRole,
+        gas_price: Arc<RwLock<U256>>,
+    ) -> anyhow::Result<()> {
+        let token_contract =
+            MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
+        let router_service = RouterService::new(
+            self.env.clone(),
+            gas_price.clone(),
+            self.http_provider.clone(),
+        );
+        let router_address = router_service.get_router_address()?;
+        let min_eth_balance =
+            parse_ether(get_env("REPAIR_MIN_ETH_BALANCE", Some("0.005".to_string()))).unwrap();
+        let top_up_eth_balance = parse_ether(get_env(
+            "REPAIR_TOP_UP_ETH_BALANCE",
+            Some("0.01".to_string()),
+        ))
+        .unwrap();
+        if top_up_eth_balance < min_eth_balance {
+            return Err(anyhow!(
+                "[WalletService.repair_wallets] REPAIR_TOP_UP_ETH_BALANCE lower than REPAIR_MIN_ETH_BALANCE"
+            ));
+        }
+
+        let (mnemonic, wallets_count) = self.get_role_wallets(role)?;
+        let wallets = (0..wallets_count)
+            .map(|index| self.load_mnemonic_wallet(&mnemonic, index))
+            .collect::<Result<Vec<WalletSigner>, _>>()?;
+        log::info!(
+            "repairing {:?} {:?} wallets, router={:?}",
+            wallets_count,
+            role,
+            router_address
+        );
+
+        let balances = join_all(wallets.iter().map(|wallet| {
+            self.get_wallet_balances(&token_contract, wallet.address(), &router_service)
+        }))
+        .await
+        .into_iter()
+        .collect::<anyhow::Result<Vec<(U256, U256, U256)>>>()?;
+
+        let mut top_up_recipients = Vec::new();
+        let mut top_up_values = Vec::new();
+        let mut approve_wallets = Vec::new();
+        for (wallet, (balance, allowance, eth_balance)) in wallets.iter().zip(balances) {
+            if eth_balance < min_eth_balance {
+                top_up_recipients.push(wallet.address());
+                top_up_values.push(top_up_eth_balance - eth_balance);
+            }
+            if allowance < balance {
+                approve_wallets.push(wallet.clone());
+            }
+        }
+
+        let mut reports = Vec::new();
+        if !top_up_recipients.is_empty() {
+            let disperse_router = *DISPERSE_ROUTERS.get(&self.env.listen_network).unwrap();
+            if disperse_router == *ZERO_ADDRESS {
+                return Err(anyhow!(
+                    "[WalletService.repair_wallets] disperse router not config for {:?}",
+                    self.env.listen_network
+                ));
+            }
+            let disperse_wallet =
+                load_private_key_wallet(&get_env("DISPERSE_ETH_PRIVATE_KEY", None))?
+                    .with_chain_id(self.env.chain_id.as_u64());
+            let tx_receipt = self
+                .send_disperse_eth(
+                    disperse_wallet,
+                    disperse_router,
+                    top_up_recipients.clone(),
+                    top_up_values.clone(),
+                    &gas_price,
+                )
+                .await?;
+            for (recipient, value) in top_up_recipients.iter().zip(top_up_values) {
+                reports.push(format!(
+                    "{:?} topped up {} eth, tx_hash={:?}",
+                    recipient,
+                    format_ether(value),
+                    tx_receipt.transaction_hash
+                ));
+            }
+        }
+
+        let mut futures = Vec::new();
+        for wallet in approve_wallets {
+            let wallet_service_clone = self.clone();
+            let router_service_clone = router_service.clone();
+            let approve_future = task::spawn(async move {
+                let wallet_address = wallet.address();
+                let signer =
+                    SignerMiddleware::new(wallet_service_clone.http_provider.clone(), wallet);
+                match router_service_clone.approve_sell(Arc::new(signer)).await {
+                    Ok(tx_hash) => {
+                        format!("{:?} approved max, tx_hash={:?}", wallet_address, tx_hash)
+                    }
+                    Err(err) => format!("{:?} approve failed: {:?}", wallet_address, err),
+                }
+            });
+            futures.push(approve_future);
+        }
+        for report in join_all(futures).await {
+            reports.push(report?);
+        }
+
+        if reports.is_empty() {
+            log::info!("all {:?} wallets are consistent, nothing to repair", role);
+            return Ok(());
+        }
+        for report in reports.iter() {
+            log::info!("{}", report);
+        }
+        let message = format!("Repair {:?} wallets\n{}", role, reports.join("\n"));
+        MessageTransportService::new().send_message(message).await;
+
+        Ok(())
+    }
+
+    /// Approve max token to the router of `router_service` from wallets `index_from..=index_to` of `mnemonic`
+    /// Wallets whose allowance already covers their balance are skipped, the others are approved
+    /// concurrently (at most `BATCH_APPROVE_CONCURRENCY` at once) and their receipts awaited
+    pub async fn batch_approve(
+        &self,
+        mnemonic: &str,
+        index_from: u32,
+        index_to: u32,
+        router_service: &RouterService,
+    ) -> anyhow::Result<BatchApprove> {
+        if index_from > index_to {
+            return Err(anyhow!(
+                "[WalletService.batch_approve] index_from {} greater than index_to {}",
+                index_from,
+                index_to
+            ));
+        }
+        let concurrency: usize = get_env("BATCH_APPROVE_CONCURRENCY", Some("10".to_string()))
+            .parse()
+            .unwrap();
+        let wallets = (index_from..=index_to)
+            .map(|index| self.load_mnemonic_wallet(mnemonic, index))
+            .collect::<Result<Vec<WalletSigner>, _>>()?;
+        let wallet_addresses: Vec<Address> =
+            wallets.iter().map(|wallet| wallet.address()).collect();
+        let router = router_service.get_router_address()?;
+        let under_approved_wallets = find_under_approved_wallets(
+            self.env.token_address,
+            &wallet_addresses,
+            &router_service.get_spender_addresses()?,
+            self.http_provider.clone(),
+        )
+        .await?;
+
+        let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+        let mut futures = Vec::new();
+        let mut skipped = Vec::new();
+        for wallet in wallets {
+            let wallet_address = wallet.address();
+            if !under_approved_wallets.contains(&wallet_address) {
+                skipped.push(wallet_address);
+                continue;
+            }
+            let semaphore = semaphore.clone();
+            let wallet_service_clone = self.clone();
+            let router_service_clone = router_service.clone();
+            let approve_future = task::spawn(async move {
+                let _permit = semaphore.acquire_owned().await?;
+                let signer =
+                    SignerMiddleware::new(wallet_service_clone.http_provider.clone(), wallet);
+                router_service_clone.approve_sell(Arc::new(signer)).await
+            });
+            futures.push((wallet_address, approve_future));
+        }
+
+        let mut approved = Vec::new();
+        let mut failed = Vec::new();
+        for (address, approve_future) in futures {
+            match approve_future.await? {
+                Ok(tx_hash) => approved.push(WalletApproval { address, tx_hash }),
+                Err(err) => failed.push(WalletApprovalError {
+                    address,
+                    error: err.to_string(),
+                }),
+            }
+        }
+        let batch_approve = BatchApprove {
+            router,
+            skipped,
+            approved,
+            failed,
+        };
+        log::info!("[WalletService] {}", batch_approve.summary());
+
+        Ok(batch_approve)
+    }
+
+    /// Sell the whole token balance of every buyer, seller, auto buyer and mm group wallet at any price
+    /// Wallets are sold concurrently (at most `PANIC_SELL_CONCURRENCY` at once), under approved ones approve first
+    pub async fn panic_sell_all(&self, gas_price: Arc<RwLock<U256>>) -> anyhow::Result<PanicSell> {
+        let router_service =
+            RouterService::new(self.env.clone(), gas_price, self.http_provider.clone());
+        let spender_addresses = router_service.get_spender_addresses()?;
+        let (pair_address, _) = router_service
+            .get_pair_address(&self.env.token_address, &self.weth_address, false)
+            .await?;
+        let token_contract =
+            MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
+        let decimals: u8 = token_contract.decimals().call().await?;
+        let concurrency: usize = get_env("PANIC_SELL_CONCURRENCY", Some("20".to_string()))
+            .parse()
+            .unwrap();
+        let semaphore = Arc::new(Semaphore::new(concurrency.max(1)));
+
+        let mut futures = Vec::new();
+        let mut failed = Vec::new();
+        for (group, mnemonic, wallets_count) in self.get_panic_sell_groups() {
+            let wallets = (0..wallets_count)
+                .map(|index| self.load_mnemonic_wallet(&mnemonic, index))
+                .collect::<Result<Vec<WalletSigner>, _>>()?;
+            let wallet_addresses: Vec<Address> =
+                wallets.iter().map(|wallet| wallet.address()).collect();
+            let balances_and_allowances = match read_balances_and_allowances(
+                self.env.token_address,
+                &wallet_addresses,
+                &spender_addresses,
+                self.http_provider.clone(),
+            )
+            .await
+            {
+                Ok(balances_and_allowances) => balances_and_allowances,
+                Err(err) => {
+                    log::error!("[WalletService] {} balances not read: {:?}", group, err);
+                    failed.extend(wallet_addresses.iter().map(|address| PanicSellError {
+                        group: group.clone(),
+                        address: *address,
+                        error: format!("balance not read: {}", err),
+                    }));
+                    continue;
+                }
+            };
+
+            // wallets without tokens are skipped
+            for panic_sell_plan in plan_panic_sells(&wallet_addresses, &balances_and_allowances) {
+                let Some(wallet) = wallets
+                    .iter()
+                    .find(|wallet| wallet.address() == panic_sell_plan.address)
+                    .cloned()
+                else {
+                    continue;
+                };
+                let semaphore = semaphore.clone();
+                let wallet_service_clone = self.clone();
+                let router_service_clone = router_service.clone();
+                let panic_sell_plan_clone = panic_sell_plan.clone();
+                let sell_future = task::spawn(async move {
+                    let _permit = semaphore.acquire_owned().await?;
+                    wallet_service_clone
+                        .panic_sell(
+                            &router_service_clone,
+                            wallet,
+                            &panic_sell_plan_clone,
+                            pair_address,
+                        )
+                        .await
+                });
+                futures.push((group.clone(), panic_sell_plan, sell_future));
+            }
+        }
+
+        let mut sold = Vec::new();
+        let mut total_sold = U256::zero();
+        for (group, panic_sell_plan, sell_future) in futures {
+            match sell_future.await? {
+                Ok(tx_hash) => {
+                    total_sold += panic_sell_plan.sell_amount;
+                    sold.push(PanicSellWallet {
+                        group,
+                        address: panic_sell_plan.address,
+                        amount: format_units(panic_sell_plan.sell_amount, decimals as usize)?,
+                        tx_hash,
+                    });
+                }
+                Err(err) => failed.push(PanicSellError {
+                    group,
+                    address: panic_sell_plan.address,
+                    error: err.to_string(),
+                }),
+            }
+        }
+        let panic_sell = PanicSell {
+            sold,
+            failed,
+            total_sold: format_units(total_sold, decimals as usize)?,
+            dry_run: self.env.dry_run,
+        };
+        log::info!("[WalletService] {}", panic_sell.summary());
+        MessageTransportService::new()
+            .send_outcome_message(panic_sell.summary(), !panic_sell.failed.is_empty())
+            .await;
+
+        Ok(panic_sell)
+    }
+
+    /// buyer, seller and auto buyer wallets, then every group of the token mm config when it can be read
+    fn get_panic_sell_groups(&self) -> Vec<(String, String, u32)> {
+        let mut groups: Vec<(String, String, u32)> =
+            [WalletRole::Buyer, WalletRole::Seller, WalletRole::AutoBuyer]
+                .into_iter()
+                .map(|role| {
+                    let (mnemonic, wallets_count) = self.get_role_wallets(role).unwrap();
+                    (format!("{:?}", role), mnemonic, wallets_count)
+                })
+                .collect();
+
+        match self.get_mm_wallet_groups() {
+            Ok(mm_groups) => groups.extend(mm_groups),
+            Err(err) => log::warn!("[WalletService] mm groups are not sold: {:?}", err),
+        }
+
+        groups
+    }
+
+    /// (mnemonic, wallets count) of the role, from the env or the token mm config
+    pub fn get_role_wallets(&self, role: WalletRole) -> anyhow::Result<(String, u32)> {
+        let (mnemonic_env_key, wallets_count_env_key) = match role {
+            WalletRole::Buyer => ("BUYER_MNEMONIC", "BUYER_WALLETS_COUNT"),
+            WalletRole::AutoBuyer => ("AUTO_BUYER_MNEMONIC", "AUTO_BUYER_WALLETS_COUNT"),
+            WalletRole::Seller => ("SELLER_MNEMONIC", "SELLER_WALLETS_COUNT"),
+            WalletRole::MarketMaker(mm_index) => {
+                let (_, mnemonic, wallets_count) = self
+                    .get_mm_wallet_groups()?
+                    .into_iter()
+                    .nth(mm_index)
+                    .ok_or(anyhow!("mm group {} not in the mm config", mm_index))?;
+                return Ok((mnemonic, wallets_count));
+            }
+        };
+
+        Ok((
+            get_env(mnemonic_env_key, None),
+            get_env(wallets_count_env_key, None).parse()?,
+        ))
+    }
+
+    /// (name, mnemonic, wallets count) of every group of the token mm config
+    fn get_mm_wallet_groups(&self) -> anyhow::Result<Vec<(String, String, u32)>> {
+        let mm_config_path = self
+            .env
+            .token
+            .mm_config_path
+            .clone()
+            .unwrap_or("mm_config.json".to_string());
+        let mm_config = read_json_file(&mm_config_path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| Ok(serde_json::from_str::<MmConfig>(&content)?))
+            .map_err(|err| anyhow!("mm config {:?} not read: {:?}", mm_config_path, err))?;
+
+        Ok(mm_config
+            .groups
+            .into_iter()
+            .enumerate()
+            .map(|(mm_index, mm_settings)| {
+                (
+                    format!("MmGroup{}", mm_index),
+                    mm_settings.mnemonic,
+                    mm_settings
+                        .max_wallets_count
+                        .unwrap_or(mm_config.default_settings.max_wallets_count),
+                )
+            })
+            .collect())
+    }
+
+    /// Approve the router if needed, then sell the whole balance with no minimum amount out
+    /// Returns the sell tx hash, none in dry run
+    async fn panic_sell(
+        &self,
+        router_service: &RouterService,
+        wallet: WalletSigner,
+        panic_sell_plan: &PanicSellPlan,
+        pair_address: Address,
+    ) -> anyhow::Result<Option<H256>> {
+        if panic_sell_plan.is_approve_needed && !self.env.dry_run {
+            let signer = SignerMiddleware::new(self.http_provider.clone(), wallet.clone());
+            router_service.approve_sell(Arc::new(signer)).await?;
+        }
+
+        let signed_sell_tx = router_service
+            .construct_sell_token_tx(
+                &wallet,
+                None,
+                panic_sell_plan.sell_amount,
+                &pair_address,
+                false,
+                None,
+            )
+            .await?;
+        if self.env.dry_run {
+            router_service.simulate_tx(&signed_sell_tx).await?;
+            return Ok(None);
+        }
+        let sell_tx_receipt = self
+            .http_provider
+            .send_raw_transaction(signed_sell_tx)
+            .await?
+            .await?
+            .ok_or(anyhow!("Cannot find sell tx_receipt"))?;
+        if sell_tx_receipt.status != Some(U64::one()) {
+            return Err(anyhow!(
+                "sell {:?} reverted",
+                sell_tx_receipt.transaction_hash
+            ));
+        }
+
+        Ok(Some(sell_tx_receipt.transaction_hash))
+    }
+
+    /// Read token balance, allowance to the sell spenders and eth balance of a wallet
+    async fn get_wallet_balances(
+        &self,
+        token_contract: &MemeTokenAbigen<Provider<Http>>,
+        wallet_address: Address,
+        router_service: &RouterService,
+    ) -> anyhow::Result<(U256, U256, U256)> {
+        let balance_of = token_contract.balance_of(wallet_address);
+        let (balance, allowance, eth_balance) = tokio::join!(
+            balance_of.call(),
+            router_service.get_sell_allowance(wallet_address),
+            self.http_provider.get_balance(wallet_address, None)
+        );
+
+        Ok((balance?, allowance?, eth_balance?))
+    }
+
     /// Migrate all buyer wallets' token to seller wallets
     pub async fn migrate_token_buyer_to_seller(&self) -> anyhow::Result<()> {
         let message_transport_service = MessageTransportService::new();
@@ -191,7 +714,7 @@ impl WalletService {
                 "Migrate token \nIndex {:?} from_wallet buyer {:?} to_wallet seller {:?} processing",
                 index, from_wallet_address, to_wallet_address
             );
-            message_transport_service.send_message(message).await?;
+            message_transport_service.send_message(message).await;
 
             let signer = SignerMiddleware::new(self.http_provider.clone(), wallet);
             let token = IUniswapV2PairAbigen::new(self.env.token_address, Arc::new(signer.clone()));
@@ -217,6 +740,7 @@ impl WalletService {
     }
 
     /// Migrate all buyer wallets' eth to seller wallets
+    /// After a shutdown request no new migration starts, in-flight ones are retried until sent
     pub async fn migrate_eth_buyer_to_seller(&self) -> anyhow::Result<()> {
         // let message_transport_service = MessageTransportService::new();
         let mut index = 0;
@@ -236,6 +760,12 @@ impl WalletService {
                     to_wallet_address
                 );
 
+                // shutdown requested: wallets not started yet keep their eth, started ones finish
+                if wallet_service_clone.env.exit.load(Ordering::Relaxed) {
+                    log::warn!("migrate eth index {:?} skipped by exit", index);
+                    return;
+                }
+
                 let signer =
                     SignerMiddleware::new(wallet_service_clone.http_provider.clone(), wallet);
                 // handle transfer surplus balance from buyer to seller wallet
@@ -307,7 +837,14 @@ impl WalletService {
         transfer_tx.set_chain_id(self.env.chain_id);
         transfer_tx.set_from(wallet.address());
         transfer_tx.set_nonce(migrate_nonce);
-        transfer_tx.set_gas(U256::from(500_000)); // fixed gas
+        transfer_tx.set_gas(
+            GasLimits::estimate_or_default(
+                self.http_provider.as_ref(),
+                &transfer_tx,
+                self.gas_limits.transfer,
+            )
+            .await,
+        );
         transfer_tx.set_gas_price(fetched_gas_price);
         let transfer_tx = to_legacy_tx(transfer_tx);
         let signed_transfer_tx = to_signed_tx(&wallet, &transfer_tx).await?;
@@ -322,6 +859,14 @@ impl WalletService {
                 let tx_receipt = _pending_tx
                     .await?
                     .ok_or(anyhow!("Cannot find tx_receipt"))?;
+                // a reorg could drop the migration after the eth sweep left no gas to resend it
+                let tx_receipt = await_confirmations(
+                    self.http_provider.as_ref(),
+                    tx_receipt.transaction_hash,
+                    self.confirmations,
+                    self.confirmations_timeout,
+                )
+                .await?;
                 if tx_receipt.status == Some(U64::zero()) {
                     log::warn!("Transaction {} failed", tx_receipt.transaction_hash);
                 } else {
@@ -364,7 +909,7 @@ impl WalletService {
 
     /// Send entire eth balance to another address
     pub async fn send_entire_eth_balance(
-        signer: &SignerMiddleware<Arc<Provider<Http>>, LocalWallet>,
+        signer: &SignerMiddleware<Arc<Provider<Http>>, WalletSigner>,
         from_address: Address,
         to_address: Address,
     ) -> anyhow::Result<()> {
@@ -373,79 +918,119 @@ impl WalletService {
             log::warn!("skip because of zero eth balance");
             return Ok(());
         }
-        let gas_price = signer.get_gas_price().await? * U256::from(101) / U256::from(100);
+        let mut gas_price = signer.get_gas_price().await? * U256::from(101) / U256::from(100);
         let gas_limit = 21_000;
-        let gas_cost_wei = gas_price * gas_limit;
+        let mut gas_cost_wei = gas_price * gas_limit;
         if gas_cost_wei >= balance {
             log::warn!("skip because of approximately zero eth balance");
             return Ok(());
         }
+        // every resend replaces the previous transfer, only one of them can be mined
+        let nonce = signer.get_transaction_count(from_address, None).await?;
         let mut total_wei_to_send = balance - gas_cost_wei;
-        let tx = TransactionRequest::new()
-            .to(to_address)
-            .value(total_wei_to_send)
-            .gas(gas_limit)
-            .gas_price(gas_price);
-        let pending_tx = signer.send_transaction(tx, None).await;
-        let Err(err) = pending_tx else {
-            let tx_receipt = pending_tx?
-                .await?
-                .ok_or(anyhow!("Cannot find tx_receipt"))?;
-            if tx_receipt.status == Some(U64::zero()) {
-                log::warn!(
-                    "sent eth fail from {:#?} to {:#?}, tx_hash={:#?}",
-                    from_address,
-                    to_address,
-                    tx_receipt.transaction_hash
-                );
-            } else {
-                log::info!(
-                    "sent eth success from {:#?} to {:#?}, tx_hash={:#?}",
-                    from_address,
-                    to_address,
-                    tx_receipt.transaction_hash
-                )
+        let mut resend_attempt = 0;
+        let mut gas_bumps = 0;
+        loop {
+            let tx = TransactionRequest::new()
+                .to(to_address)
+                .value(total_wei_to_send)
+                .gas(gas_limit)
+                .gas_price(gas_price)
+                .nonce(nonce);
+            let err = match signer.send_transaction(tx, None).await {
+                Ok(pending_tx) => {
+                    let tx_hash = pending_tx.tx_hash();
+                    let tx_receipt = match tokio::time::timeout(ETH_TRANSFER_TIMEOUT, pending_tx)
+                        .await
+                    {
+                        Ok(tx_receipt) => tx_receipt?.ok_or(anyhow!("Cannot find tx_receipt"))?,
+                        Err(_) if gas_bumps < MAX_TRANSFER_GAS_BUMPS => {
+                            gas_price =
+                                gas_price * U256::from(100 + GAS_BUMP_PERCENT) / U256::from(100);
+                            gas_cost_wei = gas_price * gas_limit;
+                            if gas_cost_wei >= balance {
+                                return Err(anyhow!(
+                                    "transfer {:?} not mined, the eth balance cannot pay a replacement",
+                                    tx_hash
+                                ));
+                            }
+                            total_wei_to_send = balance - gas_cost_wei;
+                            gas_bumps += 1;
+                            log::warn!(
+                                "transfer {:?} not mined within {:?}, replace it with gas price {:?}",
+                                tx_hash,
+                                ETH_TRANSFER_TIMEOUT,
+                                gas_price
+                            );
+                            continue;
+                        }
+                        Err(_) => {
+                            return Err(anyhow!(
+                                "transfer {:?} not mined after {} gas bumps",
+                                tx_hash,
+                                MAX_TRANSFER_GAS_BUMPS
+                            ))
+                        }
+                    };
+                    if tx_receipt.status == Some(U64::zero()) {
+                        log::warn!(
+                            "sent eth fail from {:#?} to {:#?}, tx_hash={:#?}",
+                            from_address,
+                            to_address,
+                            tx_receipt.transaction_hash
+                        );
+                    } else {
+                        log::info!(
+                            "sent eth success from {:#?} to {:#?}, tx_hash={:#?}",
+                            from_address,
+                            to_address,
+                            tx_receipt.transaction_hash
+                        )
+                    };
+                    return Ok(());
+                }
+                Err(err) => err,
             };
 
-            return Ok(());
-        };
-
-        let re: Regex = Regex::new(r"overshot (?P<overshot>\d+)").unwrap();
-        let err_str = err.to_string();
-        let Some(captures) = re.captures(&err_str) else {
-            return Err(err.into());
-        };
-        let overshot = U256::from_dec_str(&captures["overshot"])?;
-        log::warn!("resend overshot={:?}", overshot);
-        total_wei_to_send -= overshot;
-        let tx_receipt = signer
-            .send_transaction(
-                TransactionRequest::new()
-                    .to(to_address)
-                    .value(total_wei_to_send)
-                    .gas(gas_limit)
-                    .gas_price(gas_price),
-                None,
-            )
-            .await?
-            .await?;
-        let Some(tx_receipt) = tx_receipt else {
-            return Err(anyhow::anyhow!("overshot failed"));
-        };
-        log::info!("sent eth tx_hash={:?}", tx_receipt.transaction_hash);
-
-        Ok(())
+            let Some(reduced_wei_to_send) = reduced_eth_to_send(
+                &err.to_string(),
+                total_wei_to_send,
+                balance,
+                gas_cost_wei,
+                resend_attempt,
+            ) else {
+                return Err(err.into());
+            };
+            if reduced_wei_to_send.is_zero() {
+                log::warn!(
+                    "skip because the gas cost exceeds the eth balance, err={:?}",
+                    err
+                );
+                return Ok(());
+            }
+            log::warn!(
+                "resend eth {:?} instead of {:?}, attempt {:?}, err={:?}",
+                reduced_wei_to_send,
+                total_wei_to_send,
+                resend_attempt,
+                err
+            );
+            total_wei_to_send = reduced_wei_to_send;
+            resend_attempt += 1;
+        }
     }
 
     /// disperse eth to another address
+    #[allow(clippy::too_many_arguments)]
     pub async fn disperse_eth(
         &self,
         disperse_eth_private_key: &str,
         disperse_eth_mnemonic: &str,
-        disperse_eth_amount: U256,
+        disperse_eth_amount: DisperseEthAmount,
         disperse_router: Address,
         wallet_index_from: u32,
         wallet_index_to: u32,
+        gas_price: Arc<RwLock<U256>>,
     ) -> anyhow::Result<()> {
         if wallet_index_from > wallet_index_to {
             log::error!("invalid index");
@@ -453,43 +1038,138 @@ impl WalletService {
         }
         let wallet_size = wallet_index_to - wallet_index_from + 1;
 
-        let total_disperse_value = disperse_eth_amount * U256::from(wallet_size);
-        let disperse_wallet = disperse_eth_private_key
-            .parse::<LocalWallet>()
+        let disperse_wallet = load_private_key_wallet(disperse_eth_private_key)
             .unwrap()
             .with_chain_id(self.env.clone().chain_id.as_u64());
         let disperse_wallet_balance = self
             .http_provider
             .get_balance(disperse_wallet.address(), None)
             .await?;
-        if disperse_wallet_balance < total_disperse_value {
-            log::error!("disperse_wallet balance not enough for disperse");
-            return Ok(());
-        }
+        let transfer_values = match plan_disperse_eth_values(
+            disperse_eth_amount,
+            wallet_size as usize,
+            disperse_wallet_balance,
+            &mut rand::thread_rng(),
+        ) {
+            Ok(transfer_values) => transfer_values,
+            Err(err) => {
+                log::error!("disperse_wallet balance not enough for disperse: {:?}", err);
+                return Ok(());
+            }
+        };
 
         let mut recipients = Vec::new();
-        let transfer_values: Vec<U256> = vec![disperse_eth_amount; wallet_size as usize];
         for index in wallet_index_from..wallet_index_to + 1 {
-            let wallet = load_mnemonic_wallet(disperse_eth_mnemonic, index)?;
+            let wallet =
+                load_mnemonic_wallet(disperse_eth_mnemonic, index, self.env.listen_network, None)?;
             recipients.push(wallet.address());
         }
 
+        let tx_receipt = self
+            .send_disperse_eth(
+                disperse_wallet,
+                disperse_router,
+                recipients,
+                transfer_values,
+                &gas_price,
+            )
+            .await?;
+
+        log::info!(
+            "Disperse ETH for buyer wallets at tx: {:#?}",
+            tx_receipt.transaction_hash
+        );
+        Ok(())
+    }
+
+    /// disperse eth from a wallet, each recipient gets its own value
+    async fn send_disperse_eth(
+        &self,
+        disperse_wallet: WalletSigner,
+        disperse_router: Address,
+        recipients: Vec<Address>,
+        transfer_values: Vec<U256>,
+        gas_price: &Arc<RwLock<U256>>,
+    ) -> anyhow::Result<TransactionReceipt> {
+        let total_disperse_value = transfer_values
+            .iter()
+            .fold(U256::zero(), |total, value| total + value);
         let signer = Arc::new(SignerMiddleware::new(
             self.http_provider.clone(),
             disperse_wallet,
         ));
 
+        let recipients_count = recipients.len();
         let disperse = DisperseAbigen::new(disperse_router, signer);
         let disperse_fn = disperse
             .disperse_ether(recipients, transfer_values)
             .value(total_disperse_value);
-        let disperse_tx = disperse_fn.send().await?;
+        let disperse_fn = self
+            .with_disperse_gas(disperse_fn, recipients_count, gas_price)
+            .await?;
+        let tx_receipt = disperse_fn
+            .send()
+            .await?
+            .await?
+            .ok_or(anyhow!("Cannot find tx_receipt"))?;
+        if tx_receipt.status == Some(U64::zero()) {
+            return Err(anyhow!(
+                "[WalletService.send_disperse_eth] tx {:?} failed",
+                tx_receipt.transaction_hash
+            ));
+        }
+
+        Ok(tx_receipt)
+    }
 
+    /// Set explicit legacy gas on a disperse call, priced from the shared gas price and refused above `MAX_GAS_PRICE_GWEI`
+    /// Gas limit scales with recipients, raised to the estimation (+20%) when it is higher
+    async fn with_disperse_gas<M: Middleware, D: Detokenize>(
+        &self,
+        disperse_fn: ContractCall<M, D>,
+        recipients_count: usize,
+        gas_price: &Arc<RwLock<U256>>,
+    ) -> anyhow::Result<ContractCall<M, D>> {
+        let gas_price = *gas_price.read().await;
+        if !GasCeiling::from_env().is_acceptable(gas_price).await {
+            return Err(anyhow!(
+                "[WalletService.with_disperse_gas] gas price {:?} above MAX_GAS_PRICE_GWEI",
+                gas_price
+            ));
+        }
+        let base_gas: u64 = get_env("DISPERSE_BASE_GAS", Some("50000".to_string()))
+            .parse()
+            .unwrap();
+        let gas_per_recipient: u64 =
+            get_env("DISPERSE_GAS_PER_RECIPIENT", Some("40000".to_string()))
+                .parse()
+                .unwrap();
+        let gas_price_buffer_percent: u64 =
+            get_env("DISPERSE_GAS_PRICE_BUFFER_PERCENT", Some("1".to_string()))
+                .parse()
+                .unwrap();
+
+        let scaled_gas = U256::from(base_gas + gas_per_recipient * recipients_count as u64);
+        let gas_price = gas_price * U256::from(100 + gas_price_buffer_percent) / U256::from(100);
+        let gas = match disperse_fn.estimate_gas().await {
+            Ok(estimated_gas) => scaled_gas.max(estimated_gas * U256::from(120) / U256::from(100)),
+            Err(err) => {
+                log::warn!(
+                    "estimate disperse gas failed, use {:?}: {:?}",
+                    scaled_gas,
+                    err
+                );
+                scaled_gas
+            }
+        };
         log::info!(
-            "Disperse ETH for buyer wallets at tx: {:#?}",
-            disperse_tx.tx_hash()
+            "disperse to {:?} recipients, gas {:?}, gas_price {:?}",
+            recipients_count,
+            gas,
+            gas_price
         );
-        Ok(())
+
+        Ok(disperse_fn.legacy().gas(gas).gas_price(gas_price))
     }
 
     /// disperse token to another address
@@ -503,6 +1183,7 @@ impl WalletService {
         wallet_index_to: u32,
         disperse_token_amount_min: u128,
         disperse_token_amount_max: u128,
+        gas_price: Arc<RwLock<U256>>,
     ) -> anyhow::Result<()> {
         // vec: wallet address and token amount
         let mut target_wallets_address = Vec::<Address>::new();
@@ -516,8 +1197,7 @@ impl WalletService {
             target_wallets_token_amount.push(random_token_amount);
         }
 
-        let disperse_wallet = disperse_token_private_key
-            .parse::<LocalWallet>()
+        let disperse_wallet = load_private_key_wallet(disperse_token_private_key)
             .unwrap()
             .with_chain_id(self.env.clone().chain_id.as_u64());
 
@@ -572,11 +1252,15 @@ impl WalletService {
             .iter()
             .map(|&x| U256::from(x) * U256::exp10(token_decimals as usize))
             .collect();
+        let recipients_count = target_wallets_address.len();
         let disperse_fn = disperse.disperse_token(
             self.token_address,
             target_wallets_address,
             target_wallets_token_amount,
         );
+        let disperse_fn = self
+            .with_disperse_gas(disperse_fn, recipients_count, &gas_price)
+            .await?;
         let disperse_tx = disperse_fn.send().await?;
 
         log::info!(
@@ -587,8 +1271,12 @@ impl WalletService {
         Ok(())
     }
 
+    /// Sell the whole token balance of `mnemonic` wallets `0..wallets_count` one by one,
+    /// `dump_interval_min..=dump_interval_max` seconds apart
     pub async fn dump_all(
         &self,
+        mnemonic: &str,
+        wallets_count: u32,
         gas_price: Arc<RwLock<U256>>,
         dump_interval_min: u32,
         dump_interval_max: u32,
@@ -598,66 +1286,47 @@ impl WalletService {
         let token_contract =
             MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
 
-        let router_address = router_service.get_router_address()?;
-
-        // update flex for any mnemonic later
-        let buyer_wallets_count: u32 = get_env("BUYER_WALLETS_COUNT", None).parse().unwrap();
-
         let mut index: u32 = 0;
         loop {
-            if index >= buyer_wallets_count {
+            if index >= wallets_count {
                 break;
             }
-            let buyer_wallet = self.load_buyer_wallets(index).unwrap();
+            let wallet = self.load_mnemonic_wallet(mnemonic, index)?;
 
-            let balance_of = token_contract.balance_of(buyer_wallet.address());
-            let allowance = token_contract.allowance(buyer_wallet.address(), router_address);
-            let (token_balance, allowance) = tokio::join!(balance_of.call(), allowance.call());
+            let balance_of = token_contract.balance_of(wallet.address());
+            let (token_balance, allowance) = tokio::join!(
+                balance_of.call(),
+                router_service.get_sell_allowance(wallet.address())
+            );
             let token_balance = token_balance?;
             let allowance = allowance?;
 
             if token_balance.is_zero() {
-                log::info!(
-                    "Buyer wallet {:#?} don't have token, skip",
-                    buyer_wallet.address()
-                );
+                log::info!("Wallet {:#?} don't have token, skip", wallet.address());
                 index += 1;
                 continue;
             }
 
-            let signer = SignerMiddleware::new(self.http_provider.clone(), buyer_wallet.clone());
+            let signer = SignerMiddleware::new(self.http_provider.clone(), wallet.clone());
             if allowance < token_balance {
-                log::info!("approving token wallet {:#?}", buyer_wallet.address());
+                log::info!("approving token wallet {:#?}", wallet.address());
 
-                let token_contract =
-                    MemeTokenAbigen::new(self.env.token_address, Arc::new(signer.clone()));
-
-                match token_contract
-                    .approve(router_address, U256::MAX)
-                    .send()
-                    .await
-                {
-                    Ok(result) => result.await?,
-                    Err(err) => {
-                        if err.to_string().contains(DESERIALIZATION_ERROR_MSG) {
-                            continue;
-                        }
-                        return Err(err.into());
+                if let Err(err) = router_service.approve_sell(Arc::new(signer.clone())).await {
+                    if matches!(TxError::classify(err.as_ref()), TxError::Deserialization(_)) {
+                        continue;
                     }
-                };
+                    return Err(err);
+                }
             }
 
-            log::info!(
-                "Selling all tokens in buyer wallet {:#?}",
-                buyer_wallet.address()
-            );
+            log::info!("Selling all tokens in wallet {:#?}", wallet.address());
 
             let (pair_address, _) = router_service
                 .get_pair_address(&self.env.token_address, &self.weth_address, false)
                 .await?;
 
             let signed_sell_tx = router_service
-                .construct_sell_token_tx(&buyer_wallet, None, token_balance, &pair_address, true)
+                .construct_sell_token_tx(&wallet, None, token_balance, &pair_address, true, None)
                 .await?;
 
             let sell_pending_tx = self
@@ -667,7 +1336,7 @@ impl WalletService {
             let sell_tx_receipt = match sell_pending_tx.await {
                 Ok(result) => result,
                 Err(err) => {
-                    if err.to_string().contains(DESERIALIZATION_ERROR_MSG) {
+                    if matches!(TxError::classify(&err), TxError::Deserialization(_)) {
                         continue;
                     }
                     return Err(err.into());
@@ -689,12 +1358,124 @@ impl WalletService {
         Ok(())
     }
 
-    pub fn load_buyer_wallets(&self, index: u32) -> Result<LocalWallet, WalletError> {
+    pub async fn dump_buyers(
+        &self,
+        gas_price: Arc<RwLock<U256>>,
+        dump_interval_min: u32,
+        dump_interval_max: u32,
+    ) -> anyhow::Result<()> {
+        self.dump_role(
+            WalletRole::Buyer,
+            gas_price,
+            dump_interval_min,
+            dump_interval_max,
+        )
+        .await
+    }
+
+    pub async fn dump_auto_buyers(
+        &self,
+        gas_price: Arc<RwLock<U256>>,
+        dump_interval_min: u32,
+        dump_interval_max: u32,
+    ) -> anyhow::Result<()> {
+        self.dump_role(
+            WalletRole::AutoBuyer,
+            gas_price,
+            dump_interval_min,
+            dump_interval_max,
+        )
+        .await
+    }
+
+    pub async fn dump_sellers(
+        &self,
+        gas_price: Arc<RwLock<U256>>,
+        dump_interval_min: u32,
+        dump_interval_max: u32,
+    ) -> anyhow::Result<()> {
+        self.dump_role(
+            WalletRole::Seller,
+            gas_price,
+            dump_interval_min,
+            dump_interval_max,
+        )
+        .await
+    }
+
+    /// every group of the token mm config, one after the other
+    pub async fn dump_market_makers(
+        &self,
+        gas_price: Arc<RwLock<U256>>,
+        dump_interval_min: u32,
+        dump_interval_max: u32,
+    ) -> anyhow::Result<()> {
+        for (group_name, mnemonic, wallets_count) in self.get_mm_wallet_groups()? {
+            log::info!("[WalletService] dumping {}", group_name);
+            self.dump_all(
+                &mnemonic,
+                wallets_count,
+                gas_price.clone(),
+                dump_interval_min,
+                dump_interval_max,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn dump_group(
+        &self,
+        group: DumpGroup,
+        gas_price: Arc<RwLock<U256>>,
+        dump_interval_min: u32,
+        dump_interval_max: u32,
+    ) -> anyhow::Result<()> {
+        match group {
+            DumpGroup::Buyer => {
+                self.dump_buyers(gas_price, dump_interval_min, dump_interval_max)
+                    .await
+            }
+            DumpGroup::AutoBuyer => {
+                self.dump_auto_buyers(gas_price, dump_interval_min, dump_interval_max)
+                    .await
+            }
+            DumpGroup::Seller => {
+                self.dump_sellers(gas_price, dump_interval_min, dump_interval_max)
+                    .await
+            }
+            DumpGroup::MarketMaker => {
+                self.dump_market_makers(gas_price, dump_interval_min, dump_interval_max)
+                    .await
+            }
+        }
+    }
+
+    async fn dump_role(
+        &self,
+        role: WalletRole,
+        gas_price: Arc<RwLock<U256>>,
+        dump_interval_min: u32,
+        dump_interval_max: u32,
+    ) -> anyhow::Result<()> {
+        let (mnemonic, wallets_count) = self.get_role_wallets(role)?;
+        self.dump_all(
+            &mnemonic,
+            wallets_count,
+            gas_price,
+            dump_interval_min,
+            dump_interval_max,
+        )
+        .await
+    }
+
+    pub fn load_buyer_wallets(&self, index: u32) -> Result<WalletSigner, WalletSignerError> {
         let buyer_mnemonic: String = get_env("BUYER_MNEMONIC", None);
         self.load_mnemonic_wallet(&buyer_mnemonic, index)
     }
 
-    pub fn load_seller_wallets(&self, index: u32) -> Result<LocalWallet, WalletError> {
+    pub fn load_seller_wallets(&self, index: u32) -> Result<WalletSigner, WalletSignerError> {
         let seller_mnemonic: String = get_env("SELLER_MNEMONIC", None);
         self.load_mnemonic_wallet(&seller_mnemonic, index)
     }
@@ -703,9 +1484,143 @@ impl WalletService {
         &self,
         mnemonic: &str,
         index: u32,
-    ) -> Result<LocalWallet, WalletError> {
-        let wallet = load_mnemonic_wallet(mnemonic, index)?;
+    ) -> Result<WalletSigner, WalletSignerError> {
+        let wallet = load_mnemonic_wallet(mnemonic, index, self.env.listen_network, None)?;
         let wallet = wallet.with_chain_id(self.env.chain_id.as_u64());
         Ok(wallet)
     }
 }
+
+/**
+ * amount resent by `send_entire_eth_balance` after the node rejected `sent_wei` for lack of funds, the shortfall is
+ * read from the "overshot N" or the geth "have X want Y" error. After `MAX_OVERSHOT_RESENDS` resends it falls back
+ * to `balance - gas_cost * 2` once, None when the error is not a shortfall or the fallback was rejected too. An
+ * early fallback (a shortfall the amount cannot cover) is recognized by `sent_wei` and not resent either
+ */
+fn reduced_eth_to_send(
+    err: &str,
+    sent_wei: U256,
+    balance: U256,
+    gas_cost_wei: U256,
+    resend_attempt: u32,
+) -> Option<U256> {
+    let shortfall = if let Some(captures) = OVERSHOT_RE.captures(err) {
+        U256::from_dec_str(&captures["overshot"]).ok()?
+    } else if let Some(captures) = HAVE_WANT_RE.captures(err) {
+        let have = U256::from_dec_str(&captures["have"]).ok()?;
+        let want = U256::from_dec_str(&captures["want"]).ok()?;
+        want.saturating_sub(have)
+    } else {
+        return None;
+    };
+    let fallback_wei = balance.saturating_sub(gas_cost_wei * 2);
+    if sent_wei == fallback_wei {
+        return None;
+    }
+    match resend_attempt.cmp(&MAX_OVERSHOT_RESENDS) {
+        std::cmp::Ordering::Less if !shortfall.is_zero() && shortfall < sent_wei => {
+            Some(sent_wei - shortfall)
+        }
+        std::cmp::Ordering::Less | std::cmp::Ordering::Equal => Some(fallback_wei),
+        std::cmp::Ordering::Greater => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mm_groups_parse_as_wallet_roles() {
+        assert_eq!("seller".parse::<WalletRole>().unwrap(), WalletRole::Seller);
+        assert_eq!(
+            "market_maker_2".parse::<WalletRole>().unwrap(),
+            WalletRole::MarketMaker(2)
+        );
+        assert!("market_maker".parse::<WalletRole>().is_err());
+        assert!("market_maker_x".parse::<WalletRole>().is_err());
+    }
+
+    #[test]
+    fn eth_resend_follows_the_node_shortfall() {
+        let (balance, gas_cost_wei) = (U256::from(1_000_000), U256::from(21_000));
+        let sent_wei = balance - gas_cost_wei;
+
+        // current node wording
+        assert_eq!(
+            reduced_eth_to_send(
+                "(code: -32000, message: insufficient funds for transfer, overshot 1500, data: None)",
+                sent_wei,
+                balance,
+                gas_cost_wei,
+                0
+            ),
+            Some(sent_wei - 1500)
+        );
+        // geth wording
+        assert_eq!(
+            reduced_eth_to_send(
+                "(code: -32000, message: insufficient funds for gas * price + value: address 0x0000000000000000000000000000000000000001 have 1000000 want 1002000, data: None)",
+                sent_wei,
+                balance,
+                gas_cost_wei,
+                2
+            ),
+            Some(sent_wei - 2000)
+        );
+        assert_eq!(
+            reduced_eth_to_send("nonce too low", sent_wei, balance, gas_cost_wei, 0),
+            None
+        );
+    }
+
+    #[test]
+    fn eth_resend_falls_back_after_the_retries() {
+        let (balance, gas_cost_wei) = (U256::from(1_000_000), U256::from(21_000));
+        let fallback_wei = balance - gas_cost_wei * 2;
+        let err = "insufficient funds for transfer, overshot 10";
+
+        assert_eq!(
+            reduced_eth_to_send(
+                err,
+                U256::from(500),
+                balance,
+                gas_cost_wei,
+                MAX_OVERSHOT_RESENDS
+            ),
+            Some(fallback_wei)
+        );
+        // a shortfall the amount cannot cover falls back right away
+        assert_eq!(
+            reduced_eth_to_send(err, U256::from(10), balance, gas_cost_wei, 0),
+            Some(fallback_wei)
+        );
+        // the fallback was rejected too, the caller gets the error
+        assert_eq!(
+            reduced_eth_to_send(
+                err,
+                fallback_wei,
+                balance,
+                gas_cost_wei,
+                MAX_OVERSHOT_RESENDS + 1
+            ),
+            None
+        );
+        // an early fallback rejected before the retries ran out is not resent either
+        assert_eq!(
+            reduced_eth_to_send(err, fallback_wei, balance, gas_cost_wei, 1),
+            None
+        );
+        // the gas cost exceeds half the balance: nothing left to send
+        assert_eq!(
+            reduced_eth_to_send(
+                err,
+                U256::from(500),
+                U256::from(30_000),
+                gas_cost_wei,
+                MAX_OVERSHOT_RESENDS
+            ),
+            Some(U256::zero())
+        );
+    }
+}
@@ -1,3 +1,5 @@
+pub mod api_server;
+pub mod cli;
 pub mod constants;
 pub mod core;
 pub mod routers;
@@ -0,0 +1,14 @@
+use clap::Parser;
+use mm_token_rs::{cli::Cli, core::MessageTransportService};
+use mm_token_utils::{env::load_env, log::setup_logger};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // the env of the profile are the defaults of the flags
+    load_env();
+    setup_logger(None)?;
+    let result = Cli::parse().command.run().await;
+    // digests still batched would be lost with the process
+    MessageTransportService::flush().await;
+    result
+}
@@ -0,0 +1,605 @@
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{transaction::eip2718::TypedTransaction, Address, U256},
+    utils::{format_units, parse_ether},
+};
+use mm_token_utils::{
+    abi::{
+        AerodromePoolAbigen, AerodromePoolFactoryAbigen, AerodromeRouterAbigen, MemeTokenAbigen,
+        Route,
+    },
+    constants::{AERODROME_ROUTERS, WRAPPED_NATIVE_TOKENS, ZERO_ADDRESS},
+    env::get_env,
+};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{
+    constants::{Env, SwapMethodMode},
+    utils::{
+        add_tax_bps, apply_gas_mode, apply_slippage_bps, apply_slippage_floor, compute_deadline,
+        compute_reserve_price, percent_to_bps, validate_slippage_bps, widen_slippage, GasLimits,
+        PairOverride, TokenTax,
+    },
+};
+
+/// Aerodrome keeps a stable and a volatile pool per pair, the route selects one by its flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AerodromePool {
+    pub address: Address,
+    pub stable: bool,
+    pub factory: Address,
+}
+
+/// single hop route of the Aerodrome router, `address[]` paths do not exist there
+pub fn construct_route(from: Address, to: Address, stable: bool, factory: Address) -> Route {
+    Route {
+        from,
+        to,
+        stable,
+        factory,
+    }
+}
+
+/// pool with the larger amount out, pools that cannot quote are skipped
+pub fn select_best_pool(
+    quotes: Vec<(AerodromePool, anyhow::Result<U256>)>,
+) -> Option<(AerodromePool, U256)> {
+    quotes
+        .into_iter()
+        .filter_map(|(pool, quote)| match quote {
+            Ok(amount_out) if !amount_out.is_zero() => Some((pool, amount_out)),
+            Ok(_) => None,
+            Err(err) => {
+                log::warn!(
+                    "[AerodromeService] skip pool {:?} stable={:?}: {:?}",
+                    pool.address,
+                    pool.stable,
+                    err
+                );
+                None
+            }
+        })
+        .max_by_key(|(_, amount_out)| *amount_out)
+}
+
+/// marginal price of `x3y + y3x = k` in weth per token, the curve runs on the reserves in whole tokens,
+/// the price is per raw amount like `compute_reserve_price`
+pub fn compute_stable_pool_price(
+    mm_token_reserve: U256,
+    weth_reserve: U256,
+    token_decimals: u8,
+    weth_decimals: u8,
+) -> anyhow::Result<f64> {
+    if mm_token_reserve.is_zero() {
+        return Err(anyhow::anyhow!("pool without token reserve"));
+    }
+    let x: f64 = format_units(mm_token_reserve, token_decimals as usize)?.parse()?;
+    let y: f64 = format_units(weth_reserve, weth_decimals as usize)?.parse()?;
+    let price = (3.0 * x * x * y + y * y * y) / (x * x * x + 3.0 * x * y * y);
+    Ok(price * 10f64.powi(weth_decimals as i32 - token_decimals as i32))
+}
+
+#[derive(Debug, Clone)]
+pub struct AerodromeService {
+    env: Env,
+    http_provider: Arc<Provider<Http>>,
+    gas_price: Arc<RwLock<U256>>,
+    aerodrome_router_address: Address,
+    weth_address: Address,
+    weth_decimals: u8,
+    trading_slippage_bps: u32,
+    min_slippage_floor_bps: u32,
+    token_tax: TokenTax,
+    pair_override: Option<PairOverride>,
+    swap_method_mode: SwapMethodMode,
+    swap_deadline_secs: u64,
+    gas_limits: GasLimits,
+}
+
+impl AerodromeService {
+    pub fn new(env: Env, gas_price: Arc<RwLock<U256>>, http_provider: Arc<Provider<Http>>) -> Self {
+        // only base has aerodrome, get_router_address fails elsewhere
+        let aerodrome_router_address = AERODROME_ROUTERS
+            .get(&env.listen_network)
+            .copied()
+            .unwrap_or(*ZERO_ADDRESS);
+
+        let Some(weth) = WRAPPED_NATIVE_TOKENS.get(&env.listen_network) else {
+            panic!(
+                "WRAPPED_NATIVE_TOKENS not found in {:?}",
+                env.listen_network
+            );
+        };
+
+        let trading_slippage: f32 = get_env("TRADING_SLIPPAGE", None).parse().unwrap_or(0.0);
+        let min_slippage_floor: f32 = get_env("MIN_SLIPPAGE_FLOOR", Some("0.1".to_string()))
+            .parse()
+            .unwrap();
+        let token_tax = TokenTax::from_env(&env, http_provider.clone());
+        let pair_override = PairOverride::from_env(env.token_address, weth.address);
+
+        Self {
+            env,
+            http_provider,
+            gas_price,
+            aerodrome_router_address,
+            weth_address: weth.address,
+            weth_decimals: weth.decimals as u8,
+            trading_slippage_bps: percent_to_bps(trading_slippage).unwrap(),
+            min_slippage_floor_bps: percent_to_bps(min_slippage_floor).unwrap(),
+            token_tax,
+            pair_override,
+            swap_method_mode: get_env("SWAP_METHOD_MODE", Some("Auto".to_string()))
+                .parse()
+                .unwrap(),
+            swap_deadline_secs: get_env("SWAP_DEADLINE_SECS", Some("60".to_string()))
+                .parse()
+                .unwrap(),
+            gas_limits: GasLimits::from_env(500_000),
+        }
+    }
+
+    /// trading slippage in basis points, rejected beyond 100%
+    pub fn set_slippage_bps(&mut self, slippage_bps: u32) -> anyhow::Result<()> {
+        self.trading_slippage_bps = validate_slippage_bps(slippage_bps)?;
+        Ok(())
+    }
+
+    /// fee on transfer detection simulates on a v2 router, only `Standard` opts out of the supporting methods here
+    fn is_fee_on_transfer(&self) -> bool {
+        self.swap_method_mode != SwapMethodMode::Standard
+    }
+
+    async fn get_factory_address(&self) -> anyhow::Result<Address> {
+        let aerodrome_router =
+            AerodromeRouterAbigen::new(self.get_router_address()?, self.http_provider.clone());
+        Ok(aerodrome_router.default_factory().call().await?)
+    }
+
+    /// deployed stable and volatile pools of the pair
+    pub async fn get_pools(
+        &self,
+        first_token: &Address,
+        second_token: &Address,
+    ) -> anyhow::Result<Vec<AerodromePool>> {
+        let factory_address = self.get_factory_address().await?;
+        let aerodrome_factory =
+            AerodromePoolFactoryAbigen::new(factory_address, self.http_provider.clone());
+        let stable_pool = aerodrome_factory.get_pool(*first_token, *second_token, true);
+        let volatile_pool = aerodrome_factory.get_pool(*first_token, *second_token, false);
+        let (stable_pool, volatile_pool) = tokio::join!(stable_pool.call(), volatile_pool.call());
+
+        let pools: Vec<AerodromePool> = [(stable_pool?, true), (volatile_pool?, false)]
+            .into_iter()
+            .filter(|(address, _)| *address != *ZERO_ADDRESS)
+            .map(|(address, stable)| AerodromePool {
+                address,
+                stable,
+                factory: factory_address,
+            })
+            .collect();
+
+        // TOKEN_WETH_PAIR_OVERRIDE pins one of the two pools
+        let Some(pair_override) = &self.pair_override else {
+            return Ok(pools);
+        };
+        let Some((pair_address, _)) = pair_override
+            .resolve(first_token, second_token, self.http_provider.clone())
+            .await?
+        else {
+            return Ok(pools);
+        };
+        let pools: Vec<AerodromePool> = pools
+            .into_iter()
+            .filter(|pool| pool.address == pair_address)
+            .collect();
+        if pools.is_empty() {
+            return Err(anyhow::anyhow!(
+                "TOKEN_WETH_PAIR_OVERRIDE {:?} is not a pool of the aerodrome factory {:?}",
+                pair_address,
+                factory_address
+            ));
+        }
+
+        Ok(pools)
+    }
+
+    /// Quote `amount_in` on the stable and the volatile pool, returns the pool with the better quote
+    pub async fn quote_best_pool(
+        &self,
+        token_in: &Address,
+        token_out: &Address,
+        amount_in: U256,
+    ) -> anyhow::Result<(AerodromePool, U256)> {
+        let aerodrome_router =
+            AerodromeRouterAbigen::new(self.get_router_address()?, self.http_provider.clone());
+        let pools = self.get_pools(token_in, token_out).await?;
+        let quotes = futures::future::join_all(pools.iter().map(|pool| {
+            let routes = vec![construct_route(
+                *token_in,
+                *token_out,
+                pool.stable,
+                pool.factory,
+            )];
+            let aerodrome_router = aerodrome_router.clone();
+            async move {
+                let amounts_out = aerodrome_router
+                    .get_amounts_out(amount_in, routes)
+                    .call()
+                    .await?;
+                amounts_out
+                    .last()
+                    .copied()
+                    .ok_or_else(|| anyhow::anyhow!("empty amounts out"))
+            }
+        }))
+        .await;
+
+        select_best_pool(pools.into_iter().zip(quotes).collect()).ok_or_else(|| {
+            anyhow::anyhow!(
+                "[AerodromeService.quote_best_pool] no pool of {:?} -> {:?} can quote {}",
+                token_in,
+                token_out,
+                amount_in
+            )
+        })
+    }
+
+    /// amount out of the better pool minus `total_slippage_bps` basis points, with the route to trade it
+    pub async fn get_amount_out(
+        &self,
+        is_buy: bool,
+        amount_in: U256,
+        total_slippage_bps: u32,
+    ) -> anyhow::Result<(Route, U256)> {
+        let (token_in, token_out) = if is_buy {
+            (self.weth_address, self.env.token_address)
+        } else {
+            (self.env.token_address, self.weth_address)
+        };
+        let (pool, amount_out) = self
+            .quote_best_pool(&token_in, &token_out, amount_in)
+            .await?;
+        let route = construct_route(token_in, token_out, pool.stable, pool.factory);
+
+        let amount_out_min = apply_slippage_bps(amount_out, total_slippage_bps);
+
+        Ok((route, amount_out_min))
+    }
+
+    async fn get_nonce(
+        &self,
+        wallet_address: &Address,
+        nonce: Option<U256>,
+    ) -> anyhow::Result<U256> {
+        match nonce {
+            Some(nonce) => Ok(nonce),
+            None => Ok(self
+                .http_provider
+                .get_transaction_count(*wallet_address, None)
+                .await?),
+        }
+    }
+
+    /// the pool is picked by the quote of `buy_amount`, not by the pair address of the caller
+    pub async fn buy_token(
+        &self,
+        wallet_address: &Address,
+        nonce: Option<U256>,
+        buy_amount: U256,
+        is_apply_slippage: bool,
+    ) -> anyhow::Result<TypedTransaction> {
+        let gas_price = *self.gas_price.read().await;
+        let deadline = compute_deadline(self.swap_deadline_secs);
+
+        let total_slippage_bps = apply_slippage_floor(
+            add_tax_bps(
+                self.trading_slippage_bps,
+                self.token_tax.resolve(true).await,
+            ),
+            self.min_slippage_floor_bps,
+        );
+        let (route, amount_out_min) = self
+            .get_amount_out(true, buy_amount, total_slippage_bps)
+            .await?;
+        let amount_out_min = if is_apply_slippage {
+            amount_out_min
+        } else {
+            U256::one()
+        };
+        let nonce = self.get_nonce(wallet_address, nonce).await?;
+
+        let aerodrome_router =
+            AerodromeRouterAbigen::new(self.get_router_address()?, self.http_provider.clone());
+        let routes = vec![route];
+        let mut buy_tx = if self.is_fee_on_transfer() {
+            aerodrome_router
+                .swap_exact_eth_for_tokens_supporting_fee_on_transfer_tokens(
+                    amount_out_min,
+                    routes,
+                    *wallet_address,
+                    deadline,
+                )
+                .tx
+        } else {
+            aerodrome_router
+                .swap_exact_eth_for_tokens(amount_out_min, routes, *wallet_address, deadline)
+                .tx
+        };
+
+        buy_tx.set_chain_id(self.env.chain_id);
+        buy_tx.set_from(*wallet_address);
+        buy_tx.set_nonce(nonce);
+        buy_tx.set_value(buy_amount);
+        buy_tx.set_gas(
+            GasLimits::estimate_or_default(
+                self.http_provider.as_ref(),
+                &buy_tx,
+                self.gas_limits.buy,
+            )
+            .await,
+        );
+        let buy_tx = apply_gas_mode(buy_tx, gas_price, self.http_provider.clone()).await?;
+
+        Ok(buy_tx)
+    }
+
+    /// `max_slippage_bps` widens the slippage up to it, for the retry of a reverted sell
+    pub async fn sell_token(
+        &self,
+        wallet_address: &Address,
+        nonce: Option<U256>,
+        sell_amount: U256,
+        is_apply_slippage: bool,
+        max_slippage_bps: Option<u32>,
+    ) -> anyhow::Result<TypedTransaction> {
+        let gas_price = *self.gas_price.read().await;
+        let deadline = compute_deadline(self.swap_deadline_secs);
+
+        let total_slippage_bps = apply_slippage_floor(
+            add_tax_bps(
+                self.trading_slippage_bps,
+                self.token_tax.resolve(false).await,
+            ),
+            self.min_slippage_floor_bps,
+        );
+        // retry of a reverted sell
+        let total_slippage_bps = max_slippage_bps.map_or(total_slippage_bps, |max_slippage_bps| {
+            widen_slippage(total_slippage_bps, max_slippage_bps)
+        });
+        let (route, amount_out_min) = self
+            .get_amount_out(false, sell_amount, total_slippage_bps)
+            .await?;
+        let amount_out_min = if is_apply_slippage {
+            amount_out_min
+        } else {
+            U256::one()
+        };
+        let nonce = self.get_nonce(wallet_address, nonce).await?;
+
+        let aerodrome_router =
+            AerodromeRouterAbigen::new(self.get_router_address()?, self.http_provider.clone());
+        let routes = vec![route];
+        let mut sell_tx = if self.is_fee_on_transfer() {
+            aerodrome_router
+                .swap_exact_tokens_for_eth_supporting_fee_on_transfer_tokens(
+                    sell_amount,
+                    amount_out_min,
+                    routes,
+                    *wallet_address,
+                    deadline,
+                )
+                .tx
+        } else {
+            aerodrome_router
+                .swap_exact_tokens_for_eth(
+                    sell_amount,
+                    amount_out_min,
+                    routes,
+                    *wallet_address,
+                    deadline,
+                )
+                .tx
+        };
+
+        sell_tx.set_chain_id(self.env.chain_id);
+        sell_tx.set_from(*wallet_address);
+        sell_tx.set_nonce(nonce);
+        sell_tx.set_gas(
+            GasLimits::estimate_or_default(
+                self.http_provider.as_ref(),
+                &sell_tx,
+                self.gas_limits.sell,
+            )
+            .await,
+        );
+        let sell_tx = apply_gas_mode(sell_tx, gas_price, self.http_provider.clone()).await?;
+
+        Ok(sell_tx)
+    }
+
+    /// pool of the better quote for 0.01 of `first_token`, the second boolean is whether first_token is the token0
+    pub async fn compute_pair_address(
+        &self,
+        first_token: &Address,
+        second_token: &Address,
+    ) -> anyhow::Result<(Address, bool)> {
+        let (pool, _) = self
+            .quote_best_pool(first_token, second_token, parse_ether("0.01")?)
+            .await?;
+
+        let aerodrome_pool = AerodromePoolAbigen::new(pool.address, self.http_provider.clone());
+        let token0_address = aerodrome_pool.token_0().call().await?;
+
+        Ok((pool.address, *first_token == token0_address))
+    }
+
+    pub async fn get_all_pair_addresses(
+        &self,
+        first_token: &Address,
+        second_token: &Address,
+    ) -> anyhow::Result<Vec<Address>> {
+        Ok(self
+            .get_pools(first_token, second_token)
+            .await?
+            .into_iter()
+            .map(|pool| pool.address)
+            .collect())
+    }
+
+    pub async fn get_token_native_price(&self, pool_address: Address) -> anyhow::Result<f64> {
+        let aerodrome_pool = AerodromePoolAbigen::new(pool_address, self.http_provider.clone());
+        let token0 = aerodrome_pool.token_0();
+        let stable = aerodrome_pool.stable();
+        let reserves = aerodrome_pool.get_reserves();
+        let (token0_address, stable, reserves) =
+            tokio::join!(token0.call(), stable.call(), reserves.call());
+        let (reserve0, reserve1, _) = reserves?;
+        let (mm_token_reserve, weth_reserve) = if token0_address? == self.env.token_address {
+            (reserve0, reserve1)
+        } else {
+            (reserve1, reserve0)
+        };
+
+        if stable? {
+            let token_contract =
+                MemeTokenAbigen::new(self.env.token_address, self.http_provider.clone());
+            let token_decimals = token_contract.decimals().call().await?;
+            compute_stable_pool_price(
+                mm_token_reserve,
+                weth_reserve,
+                token_decimals,
+                self.weth_decimals,
+            )
+        } else {
+            // the reserves of a pool are uint256, unlike the uint112 of a v2 pair
+            let to_u128 = |reserve: U256| {
+                u128::try_from(reserve).map_err(|_| {
+                    anyhow::anyhow!("reserve {} of {:?} above u128", reserve, pool_address)
+                })
+            };
+            compute_reserve_price(to_u128(mm_token_reserve)?, to_u128(weth_reserve)?)
+        }
+    }
+
+    pub fn get_router_address(&self) -> anyhow::Result<Address> {
+        if self.aerodrome_router_address == *ZERO_ADDRESS {
+            return Err(anyhow::anyhow!(
+                "AERODROME_ROUTERS not found in {:?}",
+                self.env.listen_network
+            ));
+        }
+        Ok(self.aerodrome_router_address)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        abi::{AbiDecode, Token},
+        utils::id,
+    };
+    use mm_token_utils::abi::aerodrome_router_abigen::SwapExactETHForTokensCall;
+
+    #[test]
+    fn buy_tx_encodes_the_aerodrome_route() {
+        let provider = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+        let aerodrome_router = AerodromeRouterAbigen::new(Address::random(), provider);
+        let (weth_address, token_address, factory_address, receiver) = (
+            Address::random(),
+            Address::random(),
+            Address::random(),
+            Address::random(),
+        );
+        let route = construct_route(weth_address, token_address, false, factory_address);
+
+        let buy_tx: TypedTransaction = aerodrome_router
+            .swap_exact_eth_for_tokens(U256::one(), vec![route.clone()], receiver, U256::from(60))
+            .tx;
+        let data = buy_tx.data().unwrap();
+        assert_eq!(
+            data[..4],
+            id("swapExactETHForTokens(uint256,(address,address,bool,address)[],address,uint256)")
+        );
+        let call = SwapExactETHForTokensCall::decode(data).unwrap();
+        assert_eq!(call.routes, vec![route]);
+        assert_eq!(call.to, receiver);
+        assert_eq!(
+            ethers::abi::Tokenizable::into_token(call.routes[0].clone()),
+            Token::Tuple(vec![
+                Token::Address(weth_address),
+                Token::Address(token_address),
+                Token::Bool(false),
+                Token::Address(factory_address),
+            ])
+        );
+    }
+
+    #[test]
+    fn best_quote_picks_stable_or_volatile_pool() {
+        let factory = Address::random();
+        let stable_pool = AerodromePool {
+            address: Address::random(),
+            stable: true,
+            factory,
+        };
+        let volatile_pool = AerodromePool {
+            address: Address::random(),
+            stable: false,
+            factory,
+        };
+
+        let best_pool = select_best_pool(vec![
+            (stable_pool, Ok(U256::from(900))),
+            (volatile_pool, Ok(U256::from(1_000))),
+        ]);
+        assert_eq!(best_pool, Some((volatile_pool, U256::from(1_000))));
+
+        let best_pool = select_best_pool(vec![
+            (stable_pool, Ok(U256::from(1_100))),
+            (volatile_pool, Ok(U256::from(1_000))),
+        ]);
+        assert_eq!(best_pool, Some((stable_pool, U256::from(1_100))));
+
+        // a pool that reverts or quotes nothing is left out
+        let best_pool = select_best_pool(vec![
+            (stable_pool, Err(anyhow::anyhow!("execution reverted"))),
+            (volatile_pool, Ok(U256::from(1_000))),
+        ]);
+        assert_eq!(best_pool, Some((volatile_pool, U256::from(1_000))));
+        let best_pool = select_best_pool(vec![
+            (stable_pool, Ok(U256::zero())),
+            (volatile_pool, Err(anyhow::anyhow!("execution reverted"))),
+        ]);
+        assert_eq!(best_pool, None);
+    }
+
+    #[test]
+    fn stable_pool_price_reads_the_reserves_in_whole_tokens() {
+        // balanced 18 decimals pool: 1 weth per token
+        let price = compute_stable_pool_price(
+            parse_ether(1_000).unwrap(),
+            parse_ether(1_000).unwrap(),
+            18,
+            18,
+        )
+        .unwrap();
+        assert!((price - 1.0).abs() < 1e-12);
+
+        // the same pool with a 6 decimals token, 1 weth per whole token is 1e12 wei per raw unit
+        let price = compute_stable_pool_price(
+            U256::from(1_000_000_000u64),
+            parse_ether(1_000).unwrap(),
+            6,
+            18,
+        )
+        .unwrap();
+        assert!((price / 1e12 - 1.0).abs() < 1e-9);
+
+        // reserves above u128 do not panic
+        assert!(compute_stable_pool_price(U256::MAX, U256::MAX, 18, 18).is_ok());
+        assert!(compute_stable_pool_price(U256::zero(), U256::one(), 18, 18).is_err());
+    }
+}
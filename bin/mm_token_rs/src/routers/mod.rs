@@ -1,7 +1,11 @@
+mod aerodrome_service;
 mod router_service;
 mod uniswap2_service;
 mod uniswap3_service;
+mod universal_router_service;
 
+pub use aerodrome_service::*;
 pub use router_service::*;
 pub use uniswap2_service::*;
 pub use uniswap3_service::*;
+pub use universal_router_service::*;
@@ -1,19 +1,54 @@
-use super::{Uniswap2Service, Uniswap3Service};
-use crate::constants::Env;
+use super::{AerodromeService, Uniswap2Service, Uniswap3Service, UniversalRouterService};
+use crate::{
+    constants::Env,
+    utils::{
+        apply_slippage_bps, approve_if_under_approved, approve_permit2_spender,
+        compute_price_impact_bps, compute_reserve_price, is_under_approved, override_gas_price,
+        simulate_raw_tx, GasCeiling, HoneypotCheck, NonceManager,
+    },
+};
+use cached::{Cached, TimedCache};
 use ethers::{
-    providers::{Http, Provider},
-    signers::{LocalWallet, Signer},
-    types::{transaction::eip2718::TypedTransaction, Address, Bytes, U256},
+    providers::{Http, Middleware, Provider},
+    signers::Signer,
+    types::{Address, BlockNumber, Bytes, H256, U256, U64},
+    utils::{format_ether, parse_ether},
+};
+use futures::future::try_join_all;
+use mm_token_utils::{
+    abi::{IUniswapV2PairAbigen, MemeTokenAbigen, Weth9Abigen},
+    constants::{ERouter, PERMIT2_ADDRESS, WRAPPED_NATIVE_TOKENS},
+    env::get_env,
+    signer::WalletSigner,
+    utils::{compute_transaction_hash, to_signed_tx},
 };
-use mm_token_utils::{constants::ERouter, env::get_env, utils::to_signed_tx};
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::{future::Future, sync::Arc};
+use tokio::sync::{Mutex, RwLock};
+
+// (token, is_buy, amount bucket) -> (router, pool)
+type RouteCache = TimedCache<(Address, bool, U256), (ERouter, Address)>;
+// pool -> token price in native
+type PriceCache = TimedCache<Address, f64>;
 
 #[derive(Debug, Clone)]
 pub struct RouterService {
     pub active_router: ERouter,
+    /// ACTIVE_ROUTER=AUTO, every trade goes to the venue with the better quote
+    pub auto_route: bool,
     uniswap2_service: Uniswap2Service,
     uniswap3_service: Uniswap3Service,
+    universal_router_service: UniversalRouterService,
+    aerodrome_service: AerodromeService,
+    token_address: Address,
+    weth_address: Address,
+    route_cache: Arc<Mutex<RouteCache>>,
+    price_cache: Arc<Mutex<PriceCache>>,
+    min_pool_liquidity: U256,
+    gas_price: Arc<RwLock<U256>>,
+    pub gas_ceiling: GasCeiling,
+    // nonces of the approvals sent by `approve_sell`
+    nonce_manager: NonceManager,
+    http_provider: Arc<Provider<Http>>,
 }
 
 impl RouterService {
@@ -22,25 +57,186 @@ impl RouterService {
             Uniswap2Service::new(env.clone(), gas_price.clone(), http_provider.clone());
         let uniswap3_service =
             Uniswap3Service::new(env.clone(), gas_price.clone(), http_provider.clone());
-        let active_router: ERouter = get_env("ACTIVE_ROUTER", None).parse().unwrap();
+        let Some(weth) = WRAPPED_NATIVE_TOKENS.get(&env.listen_network) else {
+            panic!(
+                "WRAPPED_NATIVE_TOKENS not found in {:?}",
+                env.listen_network
+            );
+        };
+        let aerodrome_service =
+            AerodromeService::new(env.clone(), gas_price.clone(), http_provider.clone());
+        let universal_router_service = UniversalRouterService::new(
+            env.clone(),
+            gas_price.clone(),
+            http_provider.clone(),
+            uniswap2_service.clone(),
+            uniswap3_service.clone(),
+        );
+        let active_router = env
+            .token
+            .router
+            .clone()
+            .unwrap_or_else(|| get_env("ACTIVE_ROUTER", None));
+        let auto_route = active_router.eq_ignore_ascii_case("AUTO");
+        // auto mode keeps v2 for pricing and pair lookups, sells approve both routers
+        let active_router: ERouter = if auto_route {
+            ERouter::Uniswap2Routers
+        } else {
+            active_router.parse().unwrap()
+        };
+        if active_router == ERouter::Aerodrome {
+            if let Err(err) = aerodrome_service.get_router_address() {
+                panic!("{:?}", err);
+            }
+        }
+        let route_cache_seconds: u64 = get_env("ROUTE_CACHE_SECONDS", Some("3".to_string()))
+            .parse()
+            .unwrap();
+        let price_cache_seconds: u64 = get_env("PRICE_CACHE_SECONDS", Some("2".to_string()))
+            .parse()
+            .unwrap();
 
         Self {
             active_router,
+            auto_route,
             uniswap2_service,
             uniswap3_service,
+            universal_router_service,
+            aerodrome_service,
+            token_address: env.token_address,
+            weth_address: weth.address,
+            route_cache: Arc::new(Mutex::new(TimedCache::with_lifespan(route_cache_seconds))),
+            price_cache: Arc::new(Mutex::new(TimedCache::with_lifespan(price_cache_seconds))),
+            min_pool_liquidity: parse_ether(get_env(
+                "MIN_POOL_LIQUIDITY_ETH",
+                Some("0".to_string()),
+            ))
+            .unwrap(),
+            gas_price,
+            gas_ceiling: GasCeiling::from_env(),
+            nonce_manager: NonceManager::new(),
+            http_provider,
         }
     }
 
+    /// trading slippage of every venue in basis points, rejected beyond 100%
+    pub fn set_slippage_bps(&mut self, slippage_bps: u32) -> anyhow::Result<()> {
+        self.uniswap2_service.set_slippage_bps(slippage_bps)?;
+        self.uniswap3_service.set_slippage_bps(slippage_bps)?;
+        self.universal_router_service
+            .set_slippage_bps(slippage_bps)?;
+        self.aerodrome_service.set_slippage_bps(slippage_bps)
+    }
+
+    /// false while the shared gas price is above `MAX_GAS_PRICE_GWEI`, consulted before constructing a buy or sell tx
+    pub async fn is_gas_acceptable(&self) -> bool {
+        let gas_price = *self.gas_price.read().await;
+        self.gas_ceiling.is_acceptable(gas_price).await
+    }
+
+    /// Quote `amount_in` on the v2 pair and the best v3 pool, returns the venue with the larger output and its pool.
+    /// The route is reused for `ROUTE_CACHE_SECONDS` by the trades of the same side and amount bucket
+    pub async fn best_route(
+        &self,
+        token: &Address,
+        weth: &Address,
+        amount_in: U256,
+        is_buy: bool,
+    ) -> anyhow::Result<(ERouter, Address)> {
+        let route_key = (*token, is_buy, route_amount_bucket(amount_in));
+        if let Some(route) = self.route_cache.lock().await.cache_get(&route_key) {
+            return Ok(*route);
+        }
+
+        let (token_in, token_out) = if is_buy { (weth, token) } else { (token, weth) };
+        let (v2_quote, v3_quote) = tokio::join!(
+            async {
+                let (pair_address, _) = self
+                    .uniswap2_service
+                    .compute_pair_address(token, weth)
+                    .await?;
+                let amount_out = self
+                    .uniswap2_service
+                    .get_amount_out_min(pair_address, is_buy, amount_in, 0)
+                    .await?;
+                anyhow::Ok((ERouter::Uniswap2Routers, pair_address, amount_out))
+            },
+            async {
+                let (pool_address, _) = self
+                    .uniswap3_service
+                    .compute_pair_address(token, weth, is_buy, None)
+                    .await?;
+                if pool_address == Address::zero() {
+                    return Err(anyhow::anyhow!("no v3 pool"));
+                }
+                let amount_out = self
+                    .uniswap3_service
+                    .get_amount_out_by_slippage(&pool_address, token_in, token_out, amount_in, 0)
+                    .await?;
+                anyhow::Ok((ERouter::Uniswap3Routers, pool_address, amount_out))
+            }
+        );
+
+        // a venue that cannot quote is left out
+        let mut quotes = Vec::new();
+        for quote in [v2_quote, v3_quote] {
+            match quote {
+                Ok(quote) => quotes.push(quote),
+                Err(err) => log::warn!("[RouterService.best_route] skip venue: {:?}", err),
+            }
+        }
+        let Some((router, pool_address, amount_out)) = quotes
+            .into_iter()
+            .max_by_key(|(_, _, amount_out)| *amount_out)
+        else {
+            return Err(anyhow::anyhow!(
+                "[RouterService.best_route] no venue can quote {} {}",
+                if is_buy { "buy" } else { "sell" },
+                amount_in
+            ));
+        };
+        log::info!(
+            "[RouterService.best_route] {} {} via {:?} pool {:?}, amount out {}",
+            if is_buy { "buy" } else { "sell" },
+            amount_in,
+            router,
+            pool_address,
+            amount_out
+        );
+
+        self.route_cache
+            .lock()
+            .await
+            .cache_set(route_key, (router, pool_address));
+        Ok((router, pool_address))
+    }
+
+    async fn resolve_route(
+        &self,
+        amount_in: U256,
+        pair_address: &Address,
+        is_buy: bool,
+    ) -> anyhow::Result<(ERouter, Address)> {
+        if !self.auto_route {
+            return Ok((self.active_router, *pair_address));
+        }
+        self.best_route(&self.token_address, &self.weth_address, amount_in, is_buy)
+            .await
+    }
+
     // buy
     pub async fn construct_buy_token_tx(
         &self,
-        wallet: &LocalWallet,
+        wallet: &WalletSigner,
         nonce: Option<U256>,
         buy_amount: U256,
         pair_address: &Address,
         is_apply_slippage: bool,
+        gas_price: Option<U256>,
     ) -> anyhow::Result<Bytes> {
-        let buy_tx = match self.active_router {
+        let (router, pair_address) = self.resolve_route(buy_amount, pair_address, true).await?;
+        let pair_address = &pair_address;
+        let mut buy_tx = match router {
             ERouter::Uniswap2Routers => {
                 self.uniswap2_service
                     .buy_token(
@@ -53,6 +249,15 @@ impl RouterService {
                     .await?
             }
             ERouter::Uniswap3Routers => {
+                let is_approved = self
+                    .uniswap3_service
+                    .approve_weth_if_needed(wallet, nonce, buy_amount)
+                    .await?;
+                let nonce = if is_approved {
+                    nonce.map(|nonce| nonce + U256::one())
+                } else {
+                    nonce
+                };
                 self.uniswap3_service
                     .buy_token(
                         pair_address,
@@ -63,8 +268,27 @@ impl RouterService {
                     )
                     .await?
             }
-            ERouter::UniversalRouters => TypedTransaction::default(),
+            ERouter::UniversalRouters => {
+                self.universal_router_service
+                    .buy_token(
+                        pair_address,
+                        &wallet.address(),
+                        nonce,
+                        buy_amount,
+                        is_apply_slippage,
+                    )
+                    .await?
+            }
+            ERouter::Aerodrome => {
+                self.aerodrome_service
+                    .buy_token(&wallet.address(), nonce, buy_amount, is_apply_slippage)
+                    .await?
+            }
         };
+        // reactive trades override the shared gas price
+        if let Some(gas_price) = gas_price {
+            override_gas_price(&mut buy_tx, gas_price);
+        }
         let signed_buy_tx = to_signed_tx(wallet, &buy_tx).await?;
 
         Ok(signed_buy_tx)
@@ -73,13 +297,61 @@ impl RouterService {
     // sell
     pub async fn construct_sell_token_tx(
         &self,
-        wallet: &LocalWallet,
+        wallet: &WalletSigner,
         nonce: Option<U256>,
         sell_amount: U256,
         pair_address: &Address,
         is_apply_slippage: bool,
+        gas_price: Option<U256>,
     ) -> anyhow::Result<Bytes> {
-        let sell_tx = match self.active_router {
+        self.construct_sell_tx(
+            wallet,
+            nonce,
+            sell_amount,
+            pair_address,
+            is_apply_slippage,
+            gas_price,
+            None,
+        )
+        .await
+    }
+
+    /// Resubmit of a sell reverted by slippage, the slippage is widened up to `max_slippage_bps`
+    pub async fn construct_widened_sell_token_tx(
+        &self,
+        wallet: &WalletSigner,
+        nonce: Option<U256>,
+        sell_amount: U256,
+        pair_address: &Address,
+        gas_price: Option<U256>,
+        max_slippage_bps: u32,
+    ) -> anyhow::Result<Bytes> {
+        self.construct_sell_tx(
+            wallet,
+            nonce,
+            sell_amount,
+            pair_address,
+            true,
+            gas_price,
+            Some(max_slippage_bps),
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn construct_sell_tx(
+        &self,
+        wallet: &WalletSigner,
+        nonce: Option<U256>,
+        sell_amount: U256,
+        pair_address: &Address,
+        is_apply_slippage: bool,
+        gas_price: Option<U256>,
+        max_slippage_bps: Option<u32>,
+    ) -> anyhow::Result<Bytes> {
+        let (router, pair_address) = self.resolve_route(sell_amount, pair_address, false).await?;
+        let pair_address = &pair_address;
+        let mut sell_tx = match router {
             ERouter::Uniswap2Routers => {
                 self.uniswap2_service
                     .sell_token(
@@ -88,6 +360,7 @@ impl RouterService {
                         nonce,
                         sell_amount,
                         is_apply_slippage,
+                        max_slippage_bps,
                     )
                     .await?
             }
@@ -99,36 +372,119 @@ impl RouterService {
                         nonce,
                         sell_amount,
                         is_apply_slippage,
+                        max_slippage_bps,
+                    )
+                    .await?
+            }
+            ERouter::UniversalRouters => {
+                self.universal_router_service
+                    .sell_token(
+                        pair_address,
+                        &wallet.address(),
+                        nonce,
+                        sell_amount,
+                        is_apply_slippage,
+                        max_slippage_bps,
+                    )
+                    .await?
+            }
+            ERouter::Aerodrome => {
+                self.aerodrome_service
+                    .sell_token(
+                        &wallet.address(),
+                        nonce,
+                        sell_amount,
+                        is_apply_slippage,
+                        max_slippage_bps,
                     )
                     .await?
             }
-            ERouter::UniversalRouters => TypedTransaction::default(),
         };
+        if let Some(gas_price) = gas_price {
+            override_gas_price(&mut sell_tx, gas_price);
+        }
         let signed_sell_tx = to_signed_tx(wallet, &sell_tx).await?;
 
         Ok(signed_sell_tx)
     }
 
+    /// Quote `amount_in` on the pool a trade would use without sending a tx,
+    /// returns (amount out, price impact in bps, pool, router)
+    pub async fn quote(
+        &self,
+        amount_in: U256,
+        is_buy: bool,
+    ) -> anyhow::Result<(U256, i64, Address, ERouter)> {
+        let (pair_address, _) = self
+            .get_pair_address(&self.token_address, &self.weth_address, is_buy)
+            .await?;
+        let (router, pool_address) = self.resolve_route(amount_in, &pair_address, is_buy).await?;
+        let (token_in, token_out) = if is_buy {
+            (self.weth_address, self.token_address)
+        } else {
+            (self.token_address, self.weth_address)
+        };
+        let (amount_out, price_impact_bps) = compute_quote(
+            amount_in,
+            is_buy,
+            self.get_amount_out(
+                router,
+                &pool_address,
+                is_buy,
+                Some(&token_in),
+                Some(&token_out),
+                None,
+                amount_in,
+                0,
+            ),
+            self.get_token_native_price(router, pool_address, true),
+        )
+        .await?;
+
+        Ok((amount_out, price_impact_bps, pool_address, router))
+    }
+
+    /// false when the WETH side of the pool is below `MIN_POOL_LIQUIDITY_ETH`, buys there are skipped
+    pub async fn check_min_liquidity(&self, pair_address: &Address) -> bool {
+        check_min_liquidity(
+            self.active_router,
+            *pair_address,
+            self.token_address,
+            self.weth_address,
+            self.min_pool_liquidity,
+            self.http_provider.clone(),
+        )
+        .await
+    }
+
+    /// Token price of the pool, reused for `PRICE_CACHE_SECONDS` unless `force_refresh`
     pub async fn get_token_native_price(
         &self,
         active_router: ERouter,
         pair_address: Address,
+        force_refresh: bool,
     ) -> anyhow::Result<f64> {
-        match active_router {
-            ERouter::Uniswap2Routers => {
-                let (price, _, _) = self.uniswap2_service.get_token_native_price().await?;
-                Ok(price)
-            }
-            ERouter::Uniswap3Routers => Ok(self
-                .uniswap3_service
-                .get_token_native_price(pair_address)
-                .await?),
-            // TODO: update universal ver later
-            ERouter::UniversalRouters => {
-                let (price, _, _) = self.uniswap2_service.get_token_native_price().await?;
-                Ok(price)
+        get_cached_price(&self.price_cache, pair_address, force_refresh, async {
+            match active_router {
+                ERouter::Uniswap2Routers => {
+                    let (price, _, _) = self.uniswap2_service.get_token_native_price().await?;
+                    Ok(price)
+                }
+                ERouter::Uniswap3Routers => Ok(self
+                    .uniswap3_service
+                    .get_token_native_price(pair_address)
+                    .await?),
+                ERouter::UniversalRouters => Ok(self
+                    .universal_router_service
+                    .get_token_native_price(pair_address)
+                    .await?),
+                ERouter::Aerodrome => Ok(self
+                    .aerodrome_service
+                    .get_token_native_price(pair_address)
+                    .await?),
             }
-        }
+        })
+        .await
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -139,15 +495,25 @@ impl RouterService {
         is_buy: bool,
         token_in: Option<&Address>,
         token_out: Option<&Address>,
+        path: Option<&[Address]>,
         amount_in: U256,
-        total_slippage: f32,
+        total_slippage_bps: u32,
     ) -> anyhow::Result<U256> {
         let amount_out: U256 = match active_router {
-            ERouter::Uniswap2Routers => {
-                self.uniswap2_service
-                    .get_amount_out_min(*pool_address, is_buy, amount_in, total_slippage)
-                    .await?
-            }
+            // reserve math only prices the WETH <-> token pair, other paths are quoted by the router
+            ERouter::Uniswap2Routers => match path {
+                Some(path) if !self.is_weth_token_hop(path) => apply_slippage_bps(
+                    self.uniswap2_service
+                        .get_amounts_out(path.to_vec(), amount_in)
+                        .await?,
+                    total_slippage_bps,
+                ),
+                _ => {
+                    self.uniswap2_service
+                        .get_amount_out_min(*pool_address, is_buy, amount_in, total_slippage_bps)
+                        .await?
+                }
+            },
             ERouter::Uniswap3Routers => {
                 self.uniswap3_service
                     .get_amount_out_by_slippage(
@@ -155,21 +521,33 @@ impl RouterService {
                         token_in.unwrap(),
                         token_out.unwrap(),
                         amount_in,
-                        total_slippage,
+                        total_slippage_bps,
                     )
                     .await?
             }
-            // TODO: update universal ver later
             ERouter::UniversalRouters => {
-                self.uniswap2_service
-                    .get_amount_out_min(*pool_address, is_buy, amount_in, total_slippage)
+                self.universal_router_service
+                    .get_amount_out(pool_address, is_buy, amount_in, total_slippage_bps)
                     .await?
             }
+            // quoted on the better of the stable and volatile pools
+            ERouter::Aerodrome => {
+                let (_, amount_out) = self
+                    .aerodrome_service
+                    .get_amount_out(is_buy, amount_in, total_slippage_bps)
+                    .await?;
+                amount_out
+            }
         };
 
         Ok(amount_out)
     }
 
+    fn is_weth_token_hop(&self, path: &[Address]) -> bool {
+        path == [self.weth_address, self.token_address]
+            || path == [self.token_address, self.weth_address]
+    }
+
     pub async fn get_pair_address(
         &self,
         first_token: &Address,
@@ -187,9 +565,13 @@ impl RouterService {
                     .compute_pair_address(first_token, second_token, is_buy, None)
                     .await?
             }
-            // TODP: update later
             ERouter::UniversalRouters => {
-                self.uniswap2_service
+                self.universal_router_service
+                    .compute_pair_address(first_token, second_token, is_buy, None)
+                    .await?
+            }
+            ERouter::Aerodrome => {
+                self.aerodrome_service
                     .compute_pair_address(first_token, second_token)
                     .await?
             }
@@ -217,9 +599,13 @@ impl RouterService {
                     .compute_pair_address(first_token, second_token, is_buy, fee_tier_v3)
                     .await?
             }
-            // TODP: update later
             ERouter::UniversalRouters => {
-                self.uniswap2_service
+                self.universal_router_service
+                    .compute_pair_address(first_token, second_token, is_buy, fee_tier_v3)
+                    .await?
+            }
+            ERouter::Aerodrome => {
+                self.aerodrome_service
                     .compute_pair_address(first_token, second_token)
                     .await?
             }
@@ -244,29 +630,538 @@ impl RouterService {
                     .get_all_pair_addresses(first_token, second_token)
                     .await?
             }
-            ERouter::UniversalRouters => todo!(),
+            ERouter::UniversalRouters => {
+                self.universal_router_service
+                    .get_all_pair_addresses(first_token, second_token)
+                    .await?
+            }
+            ERouter::Aerodrome => {
+                self.aerodrome_service
+                    .get_all_pair_addresses(first_token, second_token)
+                    .await?
+            }
         };
 
         Ok(sell_receivers)
     }
 
+    /// DRY_RUN replacement of `send_raw_transaction`, logs what the signed tx would do without broadcasting it
+    pub async fn simulate_tx(&self, signed_tx: &Bytes) -> anyhow::Result<(Option<U256>, U256)> {
+        let tx_hash = compute_transaction_hash(signed_tx);
+        let (amount_out, gas_estimate) =
+            simulate_raw_tx(self.http_provider.as_ref(), signed_tx).await?;
+        log::info!(
+            "[RouterService] DRY RUN tx {:?} not broadcast, amount out {:?}, gas estimate {:?}",
+            tx_hash,
+            amount_out,
+            gas_estimate
+        );
+
+        Ok((amount_out, gas_estimate))
+    }
+
+    /// WETH returned by a simulated buy then sell of the token through the v2 router,
+    /// none when the v2 router is not a venue of the active router
+    pub async fn simulate_round_trip(&self) -> anyhow::Result<Option<U256>> {
+        if self.active_router != ERouter::Uniswap2Routers && !self.auto_route {
+            return Ok(None);
+        }
+        let honeypot_check = HoneypotCheck::new(
+            self.http_provider.clone(),
+            self.uniswap2_service.get_router_address()?,
+            self.weth_address,
+        );
+
+        Ok(Some(honeypot_check.check(self.token_address).await?))
+    }
+
     pub fn get_router_address(&self) -> anyhow::Result<Address> {
         let address = match self.active_router {
             ERouter::Uniswap2Routers => self.uniswap2_service.get_router_address()?,
             ERouter::Uniswap3Routers => self.uniswap3_service.get_router_address()?,
-            ERouter::UniversalRouters => todo!(),
+            ERouter::UniversalRouters => self.universal_router_service.get_router_address()?,
+            ERouter::Aerodrome => self.aerodrome_service.get_router_address()?,
         };
 
         Ok(address)
     }
 
+    /// spenders of the sold token: Permit2 for the universal router which pulls the token through it,
+    /// both the v2 and v3 routers with ACTIVE_ROUTER=AUTO as a sell can take either
+    pub fn get_spender_addresses(&self) -> anyhow::Result<Vec<Address>> {
+        if self.auto_route {
+            return Ok(vec![
+                self.uniswap2_service.get_router_address()?,
+                self.uniswap3_service.get_router_address()?,
+            ]);
+        }
+        let router_address = self.get_router_address()?;
+        match self.active_router {
+            ERouter::UniversalRouters => Ok(vec![*PERMIT2_ADDRESS]),
+            _ => Ok(vec![router_address]),
+        }
+    }
+
+    /// lowest token allowance of `owner` to the spenders of its sells
+    pub async fn get_sell_allowance(&self, owner: Address) -> anyhow::Result<U256> {
+        let token_contract = MemeTokenAbigen::new(self.token_address, self.http_provider.clone());
+        let allowances = try_join_all(self.get_spender_addresses()?.into_iter().map(|spender| {
+            let allowance = token_contract.allowance(owner, spender);
+            async move { allowance.call().await }
+        }))
+        .await?;
+
+        Ok(allowances.into_iter().min().unwrap_or_default())
+    }
+
+    /// Approve the spenders for the max from the signer with nonce when `allowance` does not cover `amount`,
+    /// the universal router is then approved on Permit2, returns the nonce of the next tx
+    pub async fn approve_sell_if_under_approved<M: Middleware + 'static>(
+        &self,
+        allowance: U256,
+        amount: U256,
+        nonce: U256,
+        signer: Arc<M>,
+    ) -> anyhow::Result<U256> {
+        if !is_under_approved(allowance, amount) {
+            return Ok(nonce);
+        }
+        let owner = signer
+            .default_sender()
+            .ok_or(anyhow::anyhow!("approve signer has no address"))?;
+        let spender_addresses = self.get_spender_addresses()?;
+        let mut nonce = nonce;
+        for spender_address in spender_addresses.iter() {
+            // `allowance` is the lowest of the spenders, the others may already be approved
+            let spender_allowance = if spender_addresses.len() == 1 {
+                allowance
+            } else {
+                MemeTokenAbigen::new(self.token_address, self.http_provider.clone())
+                    .allowance(owner, *spender_address)
+                    .call()
+                    .await?
+            };
+            nonce = approve_if_under_approved(
+                self.token_address,
+                *spender_address,
+                spender_allowance,
+                amount,
+                nonce,
+                signer.clone(),
+            )
+            .await?;
+        }
+        match self.active_router {
+            ERouter::UniversalRouters => {
+                approve_permit2_spender(
+                    self.token_address,
+                    self.get_router_address()?,
+                    nonce,
+                    signer,
+                )
+                .await
+            }
+            _ => Ok(nonce),
+        }
+    }
+
+    /// Approve the spenders for the max from the signer and wait for them, the universal router is then
+    /// approved on Permit2, returns the hash of the last token approve.
+    /// The nonces go through the nonce manager like the trades of the wallet
+    pub async fn approve_sell<M: Middleware + 'static>(
+        &self,
+        signer: Arc<M>,
+    ) -> anyhow::Result<H256> {
+        let owner = signer
+            .default_sender()
+            .ok_or(anyhow::anyhow!("approve signer has no address"))?;
+        let pending_nonce = signer
+            .get_transaction_count(owner, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|err| anyhow::anyhow!("approve nonce of {:?} not read: {:?}", owner, err))?;
+        let mined_nonce = signer
+            .get_transaction_count(owner, Some(BlockNumber::Latest.into()))
+            .await
+            .map_err(|err| anyhow::anyhow!("approve nonce of {:?} not read: {:?}", owner, err))?;
+        let mut nonce = self
+            .nonce_manager
+            .reconcile(&owner, pending_nonce, mined_nonce)
+            .await;
+        let token_contract = MemeTokenAbigen::new(self.token_address, signer.clone());
+        let mut approve_tx_hash = H256::zero();
+        for spender_address in self.get_spender_addresses()? {
+            let approve = token_contract
+                .approve(spender_address, U256::MAX)
+                .nonce(nonce);
+            let pending_tx = approve.send().await?;
+            // persisted before waiting, a restart meanwhile must not reuse the nonce
+            if let Err(err) = self.nonce_manager.record_submitted(owner, nonce).await {
+                log::warn!("[RouterService] cannot persist nonce {:?}", err);
+            }
+            let approve_tx_receipt = pending_tx
+                .await?
+                .ok_or(anyhow::anyhow!("Cannot find approve tx_receipt"))?;
+            if approve_tx_receipt.status != Some(U64::one()) {
+                return Err(anyhow::anyhow!(
+                    "approve {:?} reverted",
+                    approve_tx_receipt.transaction_hash
+                ));
+            }
+            approve_tx_hash = approve_tx_receipt.transaction_hash;
+            nonce += U256::one();
+        }
+        if self.active_router == ERouter::UniversalRouters {
+            approve_permit2_spender(
+                self.token_address,
+                self.get_router_address()?,
+                nonce,
+                signer,
+            )
+            .await?;
+            if let Err(err) = self.nonce_manager.record_submitted(owner, nonce).await {
+                log::warn!("[RouterService] cannot persist nonce {:?}", err);
+            }
+        }
+
+        Ok(approve_tx_hash)
+    }
+
     pub async fn get_active_trading_tx(&self) -> anyhow::Result<Bytes> {
         let future = match self.active_router {
             ERouter::Uniswap2Routers => self.uniswap2_service.get_active_trading_tx().await?,
             ERouter::Uniswap3Routers => self.uniswap3_service.get_active_trading_tx().await?,
-            ERouter::UniversalRouters => todo!(),
+            // activate_trading is a token call, the router does not matter
+            ERouter::UniversalRouters | ERouter::Aerodrome => {
+                self.uniswap2_service.get_active_trading_tx().await?
+            }
         };
 
         Ok(future)
     }
 }
+
+/**
+ * (token reserve, weth reserve) of the pool, the reserves of v2 style pairs, the pool balances otherwise
+ */
+pub async fn fetch_pool_reserves<M: Middleware + 'static>(
+    router: ERouter,
+    pool_address: Address,
+    token_address: Address,
+    weth_address: Address,
+    provider: Arc<M>,
+) -> anyhow::Result<(U256, U256)> {
+    match router {
+        ERouter::Uniswap2Routers | ERouter::Aerodrome => {
+            let pair = IUniswapV2PairAbigen::new(pool_address, provider);
+            let token0_address = pair.token_0().call().await?;
+            let (reserve0, reserve1, _) = pair.get_reserves().call().await?;
+            if token0_address == weth_address {
+                return Ok((U256::from(reserve1), U256::from(reserve0)));
+            }
+            Ok((U256::from(reserve0), U256::from(reserve1)))
+        }
+        ERouter::Uniswap3Routers | ERouter::UniversalRouters => {
+            let token = MemeTokenAbigen::new(token_address, provider.clone());
+            let weth = Weth9Abigen::new(weth_address, provider);
+            let token_balance = token.balance_of(pool_address);
+            let weth_balance = weth.balance_of(pool_address);
+            let (token_balance, weth_balance) =
+                tokio::join!(token_balance.call(), weth_balance.call());
+            Ok((token_balance?, weth_balance?))
+        }
+    }
+}
+
+/**
+ * reserves of the pool and the token price in native, v2 pairs are priced by their reserves,
+ * the other pools by `pool_price` as their balances do not give the price
+ */
+pub async fn fetch_pool_state<M: Middleware + 'static>(
+    router: ERouter,
+    pool_address: Address,
+    token_address: Address,
+    weth_address: Address,
+    provider: Arc<M>,
+    pool_price: impl Future<Output = anyhow::Result<f64>>,
+) -> anyhow::Result<(U256, U256, f64)> {
+    let (token_reserve, weth_reserve) =
+        fetch_pool_reserves(router, pool_address, token_address, weth_address, provider).await?;
+    let price_native = match router {
+        ERouter::Uniswap2Routers => {
+            compute_reserve_price(token_reserve.as_u128(), weth_reserve.as_u128())?
+        }
+        _ => pool_price.await?,
+    };
+
+    Ok((token_reserve, weth_reserve, price_native))
+}
+
+/**
+ * whether the pool holds at least `min_liquidity` WETH, a pool that cannot be read counts as empty
+ */
+pub async fn check_min_liquidity<M: Middleware + 'static>(
+    router: ERouter,
+    pool_address: Address,
+    token_address: Address,
+    weth_address: Address,
+    min_liquidity: U256,
+    provider: Arc<M>,
+) -> bool {
+    if min_liquidity.is_zero() {
+        return true;
+    }
+    let weth_liquidity =
+        fetch_pool_reserves(router, pool_address, token_address, weth_address, provider).await;
+    match weth_liquidity.map(|(_, weth_reserve)| weth_reserve) {
+        Ok(weth_liquidity) if weth_liquidity >= min_liquidity => true,
+        Ok(weth_liquidity) => {
+            log::warn!(
+                "[RouterService] pool {:?} holds {} WETH, below MIN_POOL_LIQUIDITY_ETH {}, skip buy",
+                pool_address,
+                format_ether(weth_liquidity),
+                format_ether(min_liquidity)
+            );
+            false
+        }
+        Err(err) => {
+            log::warn!(
+                "[RouterService] cannot read the liquidity of pool {:?}, skip buy: {:?}",
+                pool_address,
+                err
+            );
+            false
+        }
+    }
+}
+
+/**
+ * `amount_in` rounded down to its two leading digits, the amounts of a bucket share their route
+ */
+fn route_amount_bucket(amount_in: U256) -> U256 {
+    let digits = amount_in.to_string().len();
+    let scale = U256::exp10(digits.saturating_sub(2));
+    amount_in / scale * scale
+}
+
+/**
+ * price of the pool from the cache, else awaits fetch_price and caches it.
+ * The lock is not held while fetching, concurrent misses may both hit the chain
+ */
+async fn get_cached_price(
+    price_cache: &Mutex<PriceCache>,
+    pool_address: Address,
+    force_refresh: bool,
+    fetch_price: impl Future<Output = anyhow::Result<f64>>,
+) -> anyhow::Result<f64> {
+    if !force_refresh {
+        if let Some(price) = price_cache.lock().await.cache_get(&pool_address) {
+            return Ok(*price);
+        }
+    }
+    let price = fetch_price.await?;
+    price_cache.lock().await.cache_set(pool_address, price);
+    Ok(price)
+}
+
+/**
+ * amount out of the quoter without slippage and its price impact against the mid price
+ */
+async fn compute_quote(
+    amount_in: U256,
+    is_buy: bool,
+    amount_out: impl Future<Output = anyhow::Result<U256>>,
+    mid_price: impl Future<Output = anyhow::Result<f64>>,
+) -> anyhow::Result<(U256, i64)> {
+    let (amount_out, mid_price) = tokio::join!(amount_out, mid_price);
+    let amount_out = amount_out?;
+    let price_impact_bps = compute_price_impact_bps(mid_price?, amount_in, amount_out, is_buy)?;
+    Ok((amount_out, price_impact_bps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::Token;
+    use provider_utils::mock_rpc::MockRpc;
+    use std::{
+        sync::atomic::{AtomicU32, Ordering},
+        time::Duration,
+    };
+
+    #[tokio::test]
+    async fn token_price_is_cached_within_lifespan() {
+        let price_cache = Mutex::new(PriceCache::with_lifespan(1));
+        let pool_address = Address::random();
+        let fetches = AtomicU32::new(0);
+        let get_price = |force_refresh| {
+            get_cached_price(&price_cache, pool_address, force_refresh, async {
+                Ok(f64::from(fetches.fetch_add(1, Ordering::SeqCst) + 1))
+            })
+        };
+
+        assert_eq!(get_price(false).await.unwrap(), 1.0);
+        // second call within the lifespan hits the cache
+        assert_eq!(get_price(false).await.unwrap(), 1.0);
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+        // another pool is fetched on its own
+        let other_price =
+            get_cached_price(&price_cache, Address::random(), false, async { Ok(0.5) })
+                .await
+                .unwrap();
+        assert_eq!(other_price, 0.5);
+
+        assert_eq!(get_price(true).await.unwrap(), 2.0);
+        assert_eq!(get_price(false).await.unwrap(), 2.0);
+
+        // re-fetched after expiry
+        tokio::time::sleep(Duration::from_millis(1_100)).await;
+        assert_eq!(get_price(false).await.unwrap(), 3.0);
+        assert_eq!(fetches.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn buy_is_skipped_below_min_liquidity() {
+        let (token_address, weth_address, pool_address) =
+            (Address::random(), Address::random(), Address::random());
+        let min_liquidity = parse_ether(1).unwrap();
+        let v2_pool = |weth_reserve: U256| {
+            let mock_rpc = MockRpc::new()
+                .on_call(
+                    pool_address,
+                    "token0()",
+                    &[],
+                    &[Token::Address(Address::random())],
+                )
+                .on_call(
+                    pool_address,
+                    "getReserves()",
+                    &[],
+                    &[
+                        Token::Uint(parse_ether(1_000).unwrap()),
+                        Token::Uint(weth_reserve),
+                        Token::Uint(U256::zero()),
+                    ],
+                );
+            Arc::new(mock_rpc.provider())
+        };
+
+        let provider = v2_pool(parse_ether("0.5").unwrap());
+        assert!(
+            !check_min_liquidity(
+                ERouter::Uniswap2Routers,
+                pool_address,
+                token_address,
+                weth_address,
+                min_liquidity,
+                provider
+            )
+            .await
+        );
+        let provider = v2_pool(parse_ether(2).unwrap());
+        assert!(
+            check_min_liquidity(
+                ERouter::Uniswap2Routers,
+                pool_address,
+                token_address,
+                weth_address,
+                min_liquidity,
+                provider
+            )
+            .await
+        );
+
+        // v3 pools are measured by their weth balance
+        for (weth_balance, proceeds) in [("0.5", false), ("1", true)] {
+            let mock_rpc = MockRpc::new()
+                .on_call(
+                    token_address,
+                    "balanceOf(address)",
+                    &[Token::Address(pool_address)],
+                    &[Token::Uint(parse_ether(1_000).unwrap())],
+                )
+                .on_call(
+                    weth_address,
+                    "balanceOf(address)",
+                    &[Token::Address(pool_address)],
+                    &[Token::Uint(parse_ether(weth_balance).unwrap())],
+                );
+            let is_liquid = check_min_liquidity(
+                ERouter::Uniswap3Routers,
+                pool_address,
+                token_address,
+                weth_address,
+                min_liquidity,
+                Arc::new(mock_rpc.provider()),
+            )
+            .await;
+            assert_eq!(is_liquid, proceeds);
+        }
+
+        // disabled by default, no rpc call
+        let mock_rpc = MockRpc::new();
+        assert!(
+            check_min_liquidity(
+                ERouter::Uniswap2Routers,
+                pool_address,
+                token_address,
+                weth_address,
+                U256::zero(),
+                Arc::new(mock_rpc.provider())
+            )
+            .await
+        );
+        assert!(mock_rpc.requests("eth_call").is_empty());
+    }
+
+    #[tokio::test]
+    async fn quote_without_output_cannot_be_priced() {
+        assert!(compute_quote(
+            parse_ether(1).unwrap(),
+            true,
+            async { Ok(U256::zero()) },
+            async { Ok(0.0001) }
+        )
+        .await
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn v2_snapshot_is_priced_by_the_reserves() {
+        let (token_address, weth_address) = (Address::random(), Address::random());
+        let (token_reserve, weth_reserve) =
+            (parse_ether(1_000_000).unwrap(), parse_ether(20).unwrap());
+        let pool_address = Address::random();
+        let mock_rpc = MockRpc::new()
+            .on_call(
+                pool_address,
+                "token0()",
+                &[],
+                &[Token::Address(weth_address)],
+            )
+            .on_call(
+                pool_address,
+                "getReserves()",
+                &[],
+                &[
+                    Token::Uint(weth_reserve),
+                    Token::Uint(token_reserve),
+                    Token::Uint(U256::zero()),
+                ],
+            );
+
+        let (snapshot_token_reserve, snapshot_weth_reserve, price_native) = fetch_pool_state(
+            ERouter::Uniswap2Routers,
+            pool_address,
+            token_address,
+            weth_address,
+            Arc::new(mock_rpc.provider()),
+            async { Err(anyhow::anyhow!("v2 pairs are not priced by the pool")) },
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(snapshot_token_reserve, token_reserve);
+        assert_eq!(snapshot_weth_reserve, weth_reserve);
+        assert_eq!(price_native, 20.0 / 1_000_000.0);
+    }
+}
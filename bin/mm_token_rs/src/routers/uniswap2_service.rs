@@ -1,20 +1,25 @@
-use bigdecimal::BigDecimal;
-use chrono::Utc;
 use ethers::{
     providers::{Http, Middleware, Provider},
-    signers::{LocalWallet, Signer},
+    signers::Signer,
     types::{transaction::eip2718::TypedTransaction, Address, Bytes, U256},
 };
 use mm_token_utils::{
     abi::{IUniswapV2PairAbigen, MemeTokenAbigen, UniswapV2FactoryAbigen, UniswapV2Router02Abigen},
     constants::{UNISWAP2_ROUTERS, WRAPPED_NATIVE_TOKENS},
     env::get_env,
-    utils::{to_legacy_tx, to_signed_tx},
+    utils::{load_private_key_wallet, to_legacy_tx, to_signed_tx},
 };
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::constants::Env;
+use crate::{
+    constants::{Env, SwapMethodMode},
+    utils::{
+        add_tax_bps, apply_gas_mode, apply_slippage_bps, apply_slippage_floor, compute_deadline,
+        compute_reserve_price, percent_to_bps, resolve_fee_on_transfer, validate_slippage_bps,
+        widen_slippage, GasLimits, PairOverride, TokenTax,
+    },
+};
 
 #[derive(Debug, Clone)]
 pub struct Uniswap2Service {
@@ -23,9 +28,13 @@ pub struct Uniswap2Service {
     gas_price: Arc<RwLock<U256>>,
     uniswapv2_router_address: Address,
     weth_address: Address,
-    trading_slippage: f32,
-    sell_tax: f32,
-    buy_tax: f32,
+    trading_slippage_bps: u32,
+    min_slippage_floor_bps: u32,
+    token_tax: TokenTax,
+    pair_override: Option<PairOverride>,
+    swap_method_mode: SwapMethodMode,
+    swap_deadline_secs: u64,
+    gas_limits: GasLimits,
     deployer_private_key: String,
 }
 
@@ -44,8 +53,22 @@ impl Uniswap2Service {
 
         let deployer_private_key = get_env("DEPLOYER_PRIVATE_KEY", None).parse().unwrap();
         let trading_slippage: f32 = get_env("TRADING_SLIPPAGE", None).parse().unwrap_or(0.0);
-        let sell_tax: f32 = get_env("TOKEN_SELL_TAX", None).parse().unwrap_or(0.0);
-        let buy_tax: f32 = get_env("TOKEN_BUY_TAX", None).parse().unwrap_or(0.0);
+        let sell_tax = env.token.sell_tax;
+        let buy_tax = env.token.buy_tax;
+        let min_slippage_floor: f32 = get_env("MIN_SLIPPAGE_FLOOR", Some("0.1".to_string()))
+            .parse()
+            .unwrap();
+        if trading_slippage + buy_tax.min(sell_tax) < min_slippage_floor {
+            log::warn!(
+                "[Uniswap2Service] TRADING_SLIPPAGE {:?} plus tax is below MIN_SLIPPAGE_FLOOR, use {:?} instead",
+                trading_slippage,
+                min_slippage_floor
+            );
+        }
+
+        let token_tax = TokenTax::from_env(&env, http_provider.clone());
+
+        let pair_override = PairOverride::from_env(env.token_address, weth.address);
 
         Self {
             env,
@@ -53,24 +76,41 @@ impl Uniswap2Service {
             gas_price,
             uniswapv2_router_address: *uniswapv2_router_address,
             weth_address: weth.address,
-            trading_slippage,
-            sell_tax,
-            buy_tax,
+            trading_slippage_bps: percent_to_bps(trading_slippage).unwrap(),
+            min_slippage_floor_bps: percent_to_bps(min_slippage_floor).unwrap(),
+            token_tax,
+            pair_override,
+            swap_method_mode: get_env("SWAP_METHOD_MODE", Some("Auto".to_string()))
+                .parse()
+                .unwrap(),
+            swap_deadline_secs: get_env("SWAP_DEADLINE_SECS", Some("60".to_string()))
+                .parse()
+                .unwrap(),
+            gas_limits: GasLimits::from_env(500_000),
             deployer_private_key,
         }
     }
 
+    /// trading slippage in basis points, rejected beyond 100%
+    pub fn set_slippage_bps(&mut self, slippage_bps: u32) -> anyhow::Result<()> {
+        self.trading_slippage_bps = validate_slippage_bps(slippage_bps)?;
+        Ok(())
+    }
+
     pub async fn approve_token(
         &self,
         wallet_address: Address,
         nonce: Option<U256>,
         token_address: Address,
     ) -> anyhow::Result<TypedTransaction> {
-        let nonce = nonce.unwrap_or(
-            self.http_provider
-                .get_transaction_count(wallet_address, None)
-                .await?,
-        );
+        let nonce = match nonce {
+            Some(nonce) => nonce,
+            None => {
+                self.http_provider
+                    .get_transaction_count(wallet_address, None)
+                    .await?
+            }
+        };
         let token = IUniswapV2PairAbigen::new(token_address, self.http_provider.clone());
         let gas_price = *self.gas_price.read().await;
 
@@ -78,10 +118,15 @@ impl Uniswap2Service {
         tx.set_chain_id(self.env.chain_id);
         tx.set_from(wallet_address);
         tx.set_nonce(nonce);
-        tx.set_gas(U256::from(500_000));
-        tx.set_gas_price(gas_price);
-
-        let approve_tx = to_legacy_tx(tx);
+        tx.set_gas(
+            GasLimits::estimate_or_default(
+                self.http_provider.as_ref(),
+                &tx,
+                self.gas_limits.approve,
+            )
+            .await,
+        );
+        let approve_tx = apply_gas_mode(tx, gas_price, self.http_provider.clone()).await?;
 
         Ok(approve_tx)
     }
@@ -95,50 +140,73 @@ impl Uniswap2Service {
         is_apply_slippage: bool,
     ) -> anyhow::Result<TypedTransaction> {
         let gas_price = *self.gas_price.read().await;
-        let deadline = U256::from(Utc::now().timestamp()) + U256::from(60);
+        let deadline = compute_deadline(self.swap_deadline_secs);
 
         let uniswapv2_router =
             UniswapV2Router02Abigen::new(self.uniswapv2_router_address, self.http_provider.clone());
 
         let amount_out_min = if is_apply_slippage {
-            let total_slippage = self.trading_slippage + self.buy_tax;
+            let total_slippage_bps = apply_slippage_floor(
+                add_tax_bps(
+                    self.trading_slippage_bps,
+                    self.token_tax.resolve(true).await,
+                ),
+                self.min_slippage_floor_bps,
+            );
             self.get_amount_out_min(
                 *mm_token_weth_pair_address,
                 true,
                 buy_amount,
-                total_slippage,
+                total_slippage_bps,
             )
             .await?
         } else {
             U256::one()
         };
 
-        let nonce = nonce.unwrap_or(
-            self.http_provider
-                .get_transaction_count(*wallet_address, None)
-                .await?,
-        );
+        let nonce = match nonce {
+            Some(nonce) => nonce,
+            None => {
+                self.http_provider
+                    .get_transaction_count(*wallet_address, None)
+                    .await?
+            }
+        };
 
-        let mut buy_tx = uniswapv2_router
-            .swap_exact_eth_for_tokens_supporting_fee_on_transfer_tokens(
-                amount_out_min,
-                vec![self.weth_address, self.env.token_address],
-                *wallet_address,
-                deadline,
-            )
-            .tx;
+        let path = vec![self.weth_address, self.env.token_address];
+        let mut buy_tx = if self.is_fee_on_transfer().await {
+            uniswapv2_router
+                .swap_exact_eth_for_tokens_supporting_fee_on_transfer_tokens(
+                    amount_out_min,
+                    path,
+                    *wallet_address,
+                    deadline,
+                )
+                .tx
+        } else {
+            uniswapv2_router
+                .swap_exact_eth_for_tokens(amount_out_min, path, *wallet_address, deadline)
+                .tx
+        };
 
         buy_tx.set_chain_id(self.env.chain_id);
         buy_tx.set_from(*wallet_address);
         buy_tx.set_nonce(nonce);
-        buy_tx.set_gas(U256::from(500_000)); // fixed gas
-        buy_tx.set_gas_price(gas_price);
         buy_tx.set_value(buy_amount);
-        let buy_tx = to_legacy_tx(buy_tx);
+        buy_tx.set_gas(
+            GasLimits::estimate_or_default(
+                self.http_provider.as_ref(),
+                &buy_tx,
+                self.gas_limits.buy,
+            )
+            .await,
+        );
+        let buy_tx = apply_gas_mode(buy_tx, gas_price, self.http_provider.clone()).await?;
 
         Ok(buy_tx)
     }
 
+    /// `max_slippage_bps` widens the slippage up to it, for the retry of a reverted sell
     pub async fn sell_token(
         &self,
         mm_token_weth_pair_address: &Address,
@@ -146,57 +214,111 @@ impl Uniswap2Service {
         nonce: Option<U256>,
         sell_amount: U256,
         is_apply_slippage: bool,
+        max_slippage_bps: Option<u32>,
     ) -> anyhow::Result<TypedTransaction> {
         let gas_price = *self.gas_price.read().await;
-        let deadline = U256::from(Utc::now().timestamp()) + U256::from(60);
+        let deadline = compute_deadline(self.swap_deadline_secs);
 
         let uniswapv2_router =
             UniswapV2Router02Abigen::new(self.uniswapv2_router_address, self.http_provider.clone());
 
         let amount_out_min = if is_apply_slippage {
-            let total_slippage = self.trading_slippage + self.sell_tax;
+            let total_slippage_bps = apply_slippage_floor(
+                add_tax_bps(
+                    self.trading_slippage_bps,
+                    self.token_tax.resolve(false).await,
+                ),
+                self.min_slippage_floor_bps,
+            );
+            // retry of a reverted sell
+            let total_slippage_bps = max_slippage_bps
+                .map_or(total_slippage_bps, |max_slippage_bps| {
+                    widen_slippage(total_slippage_bps, max_slippage_bps)
+                });
             self.get_amount_out_min(
                 *mm_token_weth_pair_address,
                 false,
                 sell_amount,
-                total_slippage,
+                total_slippage_bps,
             )
             .await?
         } else {
             U256::one()
         };
 
-        let nonce = nonce.unwrap_or(
-            self.http_provider
-                .get_transaction_count(*wallet_address, None)
-                .await?,
-        );
+        let nonce = match nonce {
+            Some(nonce) => nonce,
+            None => {
+                self.http_provider
+                    .get_transaction_count(*wallet_address, None)
+                    .await?
+            }
+        };
 
-        let mut sell_tx = uniswapv2_router
-            .swap_exact_tokens_for_eth_supporting_fee_on_transfer_tokens(
-                sell_amount,
-                amount_out_min,
-                vec![self.env.token_address, self.weth_address],
-                *wallet_address,
-                deadline,
-            )
-            .tx;
+        let path = vec![self.env.token_address, self.weth_address];
+        let mut sell_tx = if self.is_fee_on_transfer().await {
+            uniswapv2_router
+                .swap_exact_tokens_for_eth_supporting_fee_on_transfer_tokens(
+                    sell_amount,
+                    amount_out_min,
+                    path,
+                    *wallet_address,
+                    deadline,
+                )
+                .tx
+        } else {
+            uniswapv2_router
+                .swap_exact_tokens_for_eth(
+                    sell_amount,
+                    amount_out_min,
+                    path,
+                    *wallet_address,
+                    deadline,
+                )
+                .tx
+        };
 
         sell_tx.set_chain_id(self.env.chain_id);
         sell_tx.set_from(*wallet_address);
         sell_tx.set_nonce(nonce);
-        sell_tx.set_gas(U256::from(500_000)); // fixed gas
-        sell_tx.set_gas_price(gas_price);
-        let sell_tx = to_legacy_tx(sell_tx);
+        sell_tx.set_gas(
+            GasLimits::estimate_or_default(
+                self.http_provider.as_ref(),
+                &sell_tx,
+                self.gas_limits.sell,
+            )
+            .await,
+        );
+        let sell_tx = apply_gas_mode(sell_tx, gas_price, self.http_provider.clone()).await?;
 
         Ok(sell_tx)
     }
 
+    async fn is_fee_on_transfer(&self) -> bool {
+        resolve_fee_on_transfer(
+            self.swap_method_mode,
+            self.env.token_address,
+            self.weth_address,
+            self.uniswapv2_router_address,
+            self.http_provider.clone(),
+        )
+        .await
+    }
+
     pub async fn compute_pair_address(
         &self,
         first_token: &Address,
         second_token: &Address,
     ) -> anyhow::Result<(Address, bool)> {
+        if let Some(pair_override) = &self.pair_override {
+            if let Some(pair) = pair_override
+                .resolve(first_token, second_token, self.http_provider.clone())
+                .await?
+            {
+                return Ok(pair);
+            }
+        }
+
         let uniswapv2_router =
             UniswapV2Router02Abigen::new(self.uniswapv2_router_address, self.http_provider.clone());
         let factory_address: Address = uniswapv2_router.factory().call().await?;
@@ -234,7 +356,7 @@ impl Uniswap2Service {
         mm_token_weth_pair_address: Address,
         is_buy: bool, // false for sell
         amount_in: U256,
-        total_slippage: f32,
+        total_slippage_bps: u32,
     ) -> anyhow::Result<U256> {
         let uniswapv2_pair =
             IUniswapV2PairAbigen::new(mm_token_weth_pair_address, self.http_provider.clone());
@@ -264,13 +386,30 @@ impl Uniswap2Service {
                 .await?
         };
 
-        let total_slippage_u256 = U256::from((total_slippage * 1000_f32).trunc() as u32);
-
-        let amount_out_min = amount_out - amount_out * total_slippage_u256 / U256::from(100_000);
+        let amount_out_min = apply_slippage_bps(amount_out, total_slippage_bps);
 
         Ok(amount_out_min)
     }
 
+    /// router `getAmountsOut` along `path`, the amount received at the last token.
+    /// Covers multi-hop paths and the pair fee of forks, unlike the single hop reserve math
+    pub async fn get_amounts_out(
+        &self,
+        path: Vec<Address>,
+        amount_in: U256,
+    ) -> anyhow::Result<U256> {
+        let uniswapv2_router =
+            UniswapV2Router02Abigen::new(self.uniswapv2_router_address, self.http_provider.clone());
+        let amounts_out = uniswapv2_router
+            .get_amounts_out(amount_in, path)
+            .call()
+            .await?;
+
+        amounts_out.last().copied().ok_or(anyhow::anyhow!(
+            "[Uniswap2Service.get_amounts_out] empty amounts out"
+        ))
+    }
+
     pub async fn get_token_native_price(&self) -> anyhow::Result<(f64, u128, u128)> {
         let (pair, is_token0) = self
             .compute_pair_address(&self.env.token_address, &self.weth_address)
@@ -289,17 +428,11 @@ impl Uniswap2Service {
             (reserve1, reserve0)
         };
 
-        Ok(
-            // token price
-            (
-                (BigDecimal::from(weth_reserve) / BigDecimal::from(mm_token_reserve))
-                    .round(18)
-                    .to_string()
-                    .parse::<f64>()?,
-                mm_token_reserve,
-                weth_reserve,
-            ),
-        )
+        Ok((
+            compute_reserve_price(mm_token_reserve, weth_reserve)?,
+            mm_token_reserve,
+            weth_reserve,
+        ))
     }
 
     pub fn get_router_address(&self) -> anyhow::Result<Address> {
@@ -307,9 +440,7 @@ impl Uniswap2Service {
     }
 
     pub async fn get_active_trading_tx(&self) -> anyhow::Result<Bytes> {
-        let deployer_wallet = self
-            .deployer_private_key
-            .parse::<LocalWallet>()
+        let deployer_wallet = load_private_key_wallet(&self.deployer_private_key)
             .unwrap()
             .with_chain_id(self.env.chain_id.as_u64());
 
@@ -2,43 +2,241 @@ use anyhow::anyhow;
 use bigdecimal::BigDecimal;
 use ethers::{
     providers::{Http, Middleware, Provider},
-    signers::{LocalWallet, Signer},
-    types::{transaction::eip2718::TypedTransaction, Address, Bytes, U256},
+    signers::Signer,
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, U256, U512, U64},
     utils::parse_ether,
 };
 use mm_token_utils::{
     abi::{
-        ExactInputSingleParams, MemeTokenAbigen, QuoteExactInputSingleParams, QuoterV2Abigen,
-        UniswapV3FactoryAbigen, UniswapV3PoolAbigen, UniswapV3Router02Abigen,
+        ExactInputParams, ExactInputSingleParams, MemeTokenAbigen, QuoteExactInputSingleParams,
+        QuoterV2Abigen, UniswapV3FactoryAbigen, UniswapV3PoolAbigen, UniswapV3Router02Abigen,
+        Weth9Abigen,
+    },
+    constants::{
+        UNISWAP3_FACTORIES, UNISWAP3_QUOTER_V2, UNISWAP3_ROUTERS, WRAPPED_NATIVE_TOKENS,
+        ZERO_ADDRESS,
     },
-    constants::{UNISWAP3_QUOTER_V2, UNISWAP3_ROUTERS, WRAPPED_NATIVE_TOKENS, ZERO_ADDRESS},
     env::get_env,
-    utils::{to_legacy_tx, to_signed_tx},
+    signer::WalletSigner,
+    utils::{encode_v3_multi_hop_path, load_private_key_wallet, to_legacy_tx, to_signed_tx},
+};
+use provider_utils::enums::ENetwork;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
 };
-use std::str::FromStr;
-use std::sync::Arc;
+use std::{future::Future, str::FromStr};
 use tokio::sync::RwLock;
 
-use crate::constants::Env;
+use crate::{
+    constants::{Env, V3PoolSelection},
+    utils::{
+        add_tax_bps, apply_gas_mode, apply_slippage_bps, apply_slippage_floor, percent_to_bps,
+        validate_slippage_bps, widen_slippage, GasLimits, PairOverride, TokenTax,
+    },
+};
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub enum UniswapV3FeeTier {
+    Tier100 = 100,
     Tier500 = 500,
+    Tier2500 = 2500,
     Tier3000 = 3000,
     Tier10000 = 10000,
 }
 
+impl UniswapV3FeeTier {
+    /// pancakeswap v3 on BSC uses 100/500/2500/10000 instead of the uniswap tiers
+    pub fn get_fee_tiers(network: &ENetwork) -> &'static [UniswapV3FeeTier] {
+        match network {
+            ENetwork::BscMainnet | ENetwork::BscTestnet => &[
+                UniswapV3FeeTier::Tier100,
+                UniswapV3FeeTier::Tier500,
+                UniswapV3FeeTier::Tier2500,
+                UniswapV3FeeTier::Tier10000,
+            ],
+            _ => &[
+                UniswapV3FeeTier::Tier500,
+                UniswapV3FeeTier::Tier3000,
+                UniswapV3FeeTier::Tier10000,
+            ],
+        }
+    }
+}
+
+/**
+ * fee tiers of `V3_FEE_TIER_PREFERENCE`, comma separated fees (e.g. `3000,500`) in the order they are tried,
+ * the tiers of the network when empty
+ */
+pub fn parse_fee_tier_preference(
+    preference: &str,
+    network: &ENetwork,
+) -> anyhow::Result<Vec<UniswapV3FeeTier>> {
+    let network_fee_tiers = UniswapV3FeeTier::get_fee_tiers(network);
+    let mut fee_tiers: Vec<UniswapV3FeeTier> = Vec::new();
+    for fee in preference
+        .split(',')
+        .map(str::trim)
+        .filter(|fee| !fee.is_empty())
+    {
+        let fee: u32 = fee
+            .parse()
+            .map_err(|_| anyhow!("invalid V3_FEE_TIER_PREFERENCE fee {:?}", fee))?;
+        let Some(fee_tier) = network_fee_tiers
+            .iter()
+            .find(|fee_tier| Into::<u32>::into(**fee_tier) == fee)
+        else {
+            return Err(anyhow!(
+                "V3_FEE_TIER_PREFERENCE fee {:?} not a fee tier of {:?}",
+                fee,
+                network
+            ));
+        };
+        if !fee_tiers.contains(fee_tier) {
+            fee_tiers.push(*fee_tier);
+        }
+    }
+    if fee_tiers.is_empty() {
+        return Ok(network_fee_tiers.to_vec());
+    }
+
+    Ok(fee_tiers)
+}
+
+/// an existing pool of a fee tier, `amount_out` is the quote of the simulate amount
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct V3PoolProbe {
+    pub pool_address: Address,
+    pub weth_liquidity: U256,
+    pub amount_out: U256,
+}
+
+/**
+ * pool picked among `fee_tiers` by `selection`, pools below `min_weth_liquidity` are skipped.
+ * `probe` returns none when the tier has no pool, an error (e.g. a reverting quote) skips the tier
+ */
+pub async fn select_v3_pool<F, Fut>(
+    fee_tiers: &[UniswapV3FeeTier],
+    selection: V3PoolSelection,
+    min_weth_liquidity: U256,
+    probe: F,
+) -> anyhow::Result<Option<(UniswapV3FeeTier, V3PoolProbe)>>
+where
+    F: Fn(UniswapV3FeeTier) -> Fut,
+    Fut: Future<Output = anyhow::Result<Option<V3PoolProbe>>>,
+{
+    let mut best_pool: Option<(UniswapV3FeeTier, V3PoolProbe)> = None;
+    for fee_tier in fee_tiers {
+        let pool_probe = match probe(*fee_tier).await {
+            Ok(Some(pool_probe)) => pool_probe,
+            Ok(None) => continue,
+            Err(err) => {
+                log::warn!(
+                    "[Uniswap3Service.select_v3_pool] skip fee tier {:?}: {:?}",
+                    fee_tier,
+                    err
+                );
+                continue;
+            }
+        };
+        if pool_probe.weth_liquidity < min_weth_liquidity {
+            log::info!(
+                "[Uniswap3Service.select_v3_pool] skip pool {:?}, WETH liquidity {:?} below {:?}",
+                pool_probe.pool_address,
+                pool_probe.weth_liquidity,
+                min_weth_liquidity
+            );
+            continue;
+        }
+
+        match selection {
+            V3PoolSelection::Preference => return Ok(Some((*fee_tier, pool_probe))),
+            V3PoolSelection::BestQuote => {
+                if pool_probe.amount_out.is_zero() {
+                    continue;
+                }
+                if best_pool
+                    .is_none_or(|(_, best_probe)| pool_probe.amount_out > best_probe.amount_out)
+                {
+                    best_pool = Some((*fee_tier, pool_probe));
+                }
+            }
+        }
+    }
+
+    Ok(best_pool)
+}
+
 #[allow(clippy::from_over_into)]
 impl Into<u32> for UniswapV3FeeTier {
     fn into(self) -> u32 {
         match self {
+            UniswapV3FeeTier::Tier100 => 100,
             UniswapV3FeeTier::Tier500 => 500,
+            UniswapV3FeeTier::Tier2500 => 2500,
             UniswapV3FeeTier::Tier3000 => 3000,
             UniswapV3FeeTier::Tier10000 => 10000,
         }
     }
 }
 
+/// when token_in is WETH and `native_value` is attached, the router wraps the ETH itself
+pub fn construct_exact_input_single_tx<M: Middleware>(
+    uniswapv3_router: &UniswapV3Router02Abigen<M>,
+    params: ExactInputSingleParams,
+    native_value: Option<U256>,
+) -> TypedTransaction {
+    let mut tx: TypedTransaction = uniswapv3_router.exact_input_single(params).tx;
+    if let Some(native_value) = native_value {
+        tx.set_value(native_value);
+    }
+    tx
+}
+
+/// multi-hop variant of `construct_exact_input_single_tx`
+pub fn construct_exact_input_tx<M: Middleware>(
+    uniswapv3_router: &UniswapV3Router02Abigen<M>,
+    params: ExactInputParams,
+    native_value: Option<U256>,
+) -> TypedTransaction {
+    let mut tx: TypedTransaction = uniswapv3_router.exact_input(params).tx;
+    if let Some(native_value) = native_value {
+        tx.set_value(native_value);
+    }
+    tx
+}
+
+/// two-hop route WETH -> intermediary -> token, for tokens without a direct WETH pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct V3MultiHop {
+    pub intermediary: Address,
+    pub weth_fee: u32,
+    pub token_fee: u32,
+}
+
+impl V3MultiHop {
+    pub fn path(&self, weth_address: Address, token_address: Address, is_buy: bool) -> Bytes {
+        let path = if is_buy {
+            encode_v3_multi_hop_path(
+                &[
+                    (weth_address, self.weth_fee),
+                    (self.intermediary, self.token_fee),
+                ],
+                token_address,
+            )
+        } else {
+            encode_v3_multi_hop_path(
+                &[
+                    (token_address, self.token_fee),
+                    (self.intermediary, self.weth_fee),
+                ],
+                weth_address,
+            )
+        };
+        path.into()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Uniswap3Service {
     env: Env,
@@ -47,9 +245,25 @@ pub struct Uniswap3Service {
     weth_address: Address,
     uniswap_v3_router_address: Address,
     uniswap_v3_quoter_v2_address: Address,
-    trading_slippage: f32,
-    sell_tax: f32,
-    buy_tax: f32,
+    trading_slippage_bps: u32,
+    min_slippage_floor_bps: u32,
+    token_tax: TokenTax,
+    pair_override: Option<PairOverride>,
+    quoter_revert_accept_any_output: bool,
+    auto_approve_weth: bool,
+    buy_with_native: bool,
+    // `V3_INTERMEDIARY_TOKENS`, tried when the token has no direct WETH pool
+    intermediary_tokens: Vec<Address>,
+    // `V3_FEE_TIER_PREFERENCE`, the order fee tiers are tried by `compute_pair_address`
+    fee_tier_preference: Vec<UniswapV3FeeTier>,
+    pool_selection: V3PoolSelection,
+    // `V3_SIMULATE_AMOUNT` of token_in quoted on every candidate pool
+    simulate_amount: U256,
+    // `V3_MIN_POOL_LIQUIDITY`, WETH tradable around the current price of a candidate pool
+    min_pool_liquidity: U256,
+    // token side pool -> route, filled by `compute_pair_address`
+    multi_hops: Arc<Mutex<BTreeMap<Address, V3MultiHop>>>,
+    gas_limits: GasLimits,
     deployer_private_key: String,
 }
 
@@ -72,8 +286,56 @@ impl Uniswap3Service {
 
         let deployer_private_key = get_env("DEPLOYER_PRIVATE_KEY", None).parse().unwrap();
         let trading_slippage: f32 = get_env("TRADING_SLIPPAGE", None).parse().unwrap_or(0.0);
-        let sell_tax: f32 = get_env("TOKEN_SELL_TAX", None).parse().unwrap_or(0.0);
-        let buy_tax: f32 = get_env("TOKEN_BUY_TAX", None).parse().unwrap_or(0.0);
+        let sell_tax = env.token.sell_tax;
+        let buy_tax = env.token.buy_tax;
+        let min_slippage_floor: f32 = get_env("MIN_SLIPPAGE_FLOOR", Some("0.1".to_string()))
+            .parse()
+            .unwrap();
+        if trading_slippage + buy_tax.min(sell_tax) < min_slippage_floor {
+            log::warn!(
+                "[Uniswap3Service] TRADING_SLIPPAGE {:?} plus tax is below MIN_SLIPPAGE_FLOOR, use {:?} instead",
+                trading_slippage,
+                min_slippage_floor
+            );
+        }
+        // trading without quote means amount_out_minimum = 0, unprotected against any slippage
+        let quoter_revert_accept_any_output: bool = get_env(
+            "V3_QUOTER_REVERT_ACCEPT_ANY_OUTPUT",
+            Some("false".to_string()),
+        )
+        .parse()
+        .unwrap();
+        let auto_approve_weth: bool = get_env("V3_AUTO_APPROVE_WETH", Some("true".to_string()))
+            .parse()
+            .unwrap();
+        let buy_with_native: bool = get_env("V3_BUY_WITH_NATIVE", Some("true".to_string()))
+            .parse()
+            .unwrap();
+
+        let intermediary_tokens: Vec<Address> =
+            get_env("V3_INTERMEDIARY_TOKENS", Some("".to_string()))
+                .split(',')
+                .filter(|address| !address.trim().is_empty())
+                .map(|address| Address::from_str(address.trim()).unwrap())
+                .collect();
+
+        let fee_tier_preference = parse_fee_tier_preference(
+            &get_env("V3_FEE_TIER_PREFERENCE", Some("".to_string())),
+            &env.listen_network,
+        )
+        .unwrap();
+        let pool_selection: V3PoolSelection =
+            get_env("V3_POOL_SELECTION", Some("Preference".to_string()))
+                .parse()
+                .unwrap();
+        let simulate_amount =
+            parse_ether(get_env("V3_SIMULATE_AMOUNT", Some("0.01".to_string()))).unwrap();
+        let min_pool_liquidity =
+            parse_ether(get_env("V3_MIN_POOL_LIQUIDITY", Some("0".to_string()))).unwrap();
+
+        let token_tax = TokenTax::from_env(&env, http_provider.clone());
+
+        let pair_override = PairOverride::from_env(env.token_address, weth.address);
 
         Self {
             env,
@@ -82,13 +344,30 @@ impl Uniswap3Service {
             weth_address: weth.address,
             uniswap_v3_router_address: *uniswap_v3_router_address,
             uniswap_v3_quoter_v2_address: *uniswap_v3_quoter_v2_address,
-            trading_slippage,
-            sell_tax,
-            buy_tax,
+            trading_slippage_bps: percent_to_bps(trading_slippage).unwrap(),
+            min_slippage_floor_bps: percent_to_bps(min_slippage_floor).unwrap(),
+            token_tax,
+            pair_override,
+            quoter_revert_accept_any_output,
+            auto_approve_weth,
+            buy_with_native,
+            intermediary_tokens,
+            fee_tier_preference,
+            pool_selection,
+            simulate_amount,
+            min_pool_liquidity,
+            multi_hops: Default::default(),
+            gas_limits: GasLimits::from_env(700_000),
             deployer_private_key,
         }
     }
 
+    /// trading slippage in basis points, rejected beyond 100%
+    pub fn set_slippage_bps(&mut self, slippage_bps: u32) -> anyhow::Result<()> {
+        self.trading_slippage_bps = validate_slippage_bps(slippage_bps)?;
+        Ok(())
+    }
+
     pub async fn buy_token(
         &self,
         pool_address: &Address,
@@ -116,47 +395,149 @@ impl Uniswap3Service {
         );
 
         let amount_out_minimum = if is_apply_slippage {
-            let total_slippage = self.trading_slippage + self.buy_tax;
+            let total_slippage_bps = apply_slippage_floor(
+                add_tax_bps(
+                    self.trading_slippage_bps,
+                    self.token_tax.resolve(true).await,
+                ),
+                self.min_slippage_floor_bps,
+            );
             self.get_amount_out_by_slippage(
                 pool_address,
                 &self.weth_address,
                 &self.env.token_address,
                 amount_in,
-                total_slippage,
+                total_slippage_bps,
             )
             .await?
         } else {
             U256::zero()
         };
 
-        let recipient_nonce = recipient_nonce.unwrap_or(
-            self.http_provider
-                .get_transaction_count(*recipient, None)
-                .await?,
-        );
+        let recipient_nonce = match recipient_nonce {
+            Some(recipient_nonce) => recipient_nonce,
+            None => {
+                self.http_provider
+                    .get_transaction_count(*recipient, None)
+                    .await?
+            }
+        };
 
-        let mut buy_tx: TypedTransaction = uniswapv3_router
-            .exact_input_single(ExactInputSingleParams {
-                token_in: self.weth_address,
-                token_out: self.env.token_address,
-                fee: pool_fee,
-                recipient: *recipient,
-                amount_in,
-                amount_out_minimum,
-                sqrt_price_limit_x96: U256::zero(),
-            })
-            .tx;
+        let native_value = if self.buy_with_native {
+            Some(amount_in)
+        } else {
+            None
+        };
+        let mut buy_tx = match self.get_multi_hop(pool_address) {
+            Some(multi_hop) => construct_exact_input_tx(
+                &uniswapv3_router,
+                ExactInputParams {
+                    path: multi_hop.path(self.weth_address, self.env.token_address, true),
+                    recipient: *recipient,
+                    amount_in,
+                    amount_out_minimum,
+                },
+                native_value,
+            ),
+            None => construct_exact_input_single_tx(
+                &uniswapv3_router,
+                ExactInputSingleParams {
+                    token_in: self.weth_address,
+                    token_out: self.env.token_address,
+                    fee: pool_fee,
+                    recipient: *recipient,
+                    amount_in,
+                    amount_out_minimum,
+                    sqrt_price_limit_x96: U256::zero(),
+                },
+                native_value,
+            ),
+        };
         buy_tx.set_chain_id(self.env.chain_id);
         buy_tx.set_from(*recipient);
         buy_tx.set_nonce(recipient_nonce);
-        buy_tx.set_gas(U256::from(700_000)); // fixed gas
-        buy_tx.set_gas_price(gas_price);
-
-        let buy_tx = to_legacy_tx(buy_tx);
+        buy_tx.set_gas(
+            GasLimits::estimate_or_default(
+                self.http_provider.as_ref(),
+                &buy_tx,
+                self.gas_limits.buy,
+            )
+            .await,
+        );
+        let buy_tx = apply_gas_mode(buy_tx, gas_price, self.http_provider.clone()).await?;
 
         Ok(buy_tx)
     }
 
+    /// buy spends WETH through the router, approve it once if allowance is insufficient
+    /// return true if an approve tx was sent (nonce is consumed)
+    pub async fn approve_weth_if_needed(
+        &self,
+        wallet: &WalletSigner,
+        nonce: Option<U256>,
+        amount_in: U256,
+    ) -> anyhow::Result<bool> {
+        if !self.auto_approve_weth || self.buy_with_native {
+            return Ok(false);
+        }
+
+        let weth = Weth9Abigen::new(self.weth_address, self.http_provider.clone());
+        let allowance: U256 = weth
+            .allowance(wallet.address(), self.uniswap_v3_router_address)
+            .call()
+            .await?;
+        if allowance >= amount_in {
+            return Ok(false);
+        }
+
+        let nonce = match nonce {
+            Some(nonce) => nonce,
+            None => {
+                self.http_provider
+                    .get_transaction_count(wallet.address(), None)
+                    .await?
+            }
+        };
+        let gas_price = *self.gas_price.read().await;
+
+        let mut approve_tx: TypedTransaction =
+            weth.approve(self.uniswap_v3_router_address, U256::MAX).tx;
+        approve_tx.set_chain_id(self.env.chain_id);
+        approve_tx.set_from(wallet.address());
+        approve_tx.set_nonce(nonce);
+        approve_tx.set_gas(
+            GasLimits::estimate_or_default(
+                self.http_provider.as_ref(),
+                &approve_tx,
+                self.gas_limits.approve,
+            )
+            .await,
+        );
+        let approve_tx = apply_gas_mode(approve_tx, gas_price, self.http_provider.clone()).await?;
+        let signed_approve_tx = to_signed_tx(wallet, &approve_tx).await?;
+
+        let tx_receipt = self
+            .http_provider
+            .send_raw_transaction(signed_approve_tx)
+            .await?
+            .await?
+            .ok_or(anyhow!("Cannot find tx_receipt"))?;
+        if tx_receipt.status == Some(U64::zero()) {
+            return Err(anyhow!(
+                "[Uniswap3Service.approve_weth_if_needed] approve tx {:?} failed",
+                tx_receipt.transaction_hash
+            ));
+        }
+        log::info!(
+            "[Uniswap3Service] wallet {:?} approved WETH to router at tx {:?}",
+            wallet.address(),
+            tx_receipt.transaction_hash
+        );
+
+        Ok(true)
+    }
+
+    /// `max_slippage_bps` widens the slippage up to it, for the retry of a reverted sell
     pub async fn sell_token(
         &self,
         pool_address: &Address,
@@ -164,6 +545,7 @@ impl Uniswap3Service {
         recipient_nonce: Option<U256>,
         amount_in: U256,
         is_apply_slippage: bool,
+        max_slippage_bps: Option<u32>,
     ) -> anyhow::Result<TypedTransaction> {
         let uniswapv3_pool = UniswapV3PoolAbigen::new(*pool_address, self.http_provider.clone());
 
@@ -183,45 +565,77 @@ impl Uniswap3Service {
             self.http_provider.clone(),
         );
 
-        let total_slippage = self.trading_slippage + self.sell_tax;
+        let total_slippage_bps = apply_slippage_floor(
+            add_tax_bps(
+                self.trading_slippage_bps,
+                self.token_tax.resolve(false).await,
+            ),
+            self.min_slippage_floor_bps,
+        );
+        // retry of a reverted sell
+        let total_slippage_bps = max_slippage_bps.map_or(total_slippage_bps, |max_slippage_bps| {
+            widen_slippage(total_slippage_bps, max_slippage_bps)
+        });
         let amount_out_minimum = if is_apply_slippage {
             self.get_amount_out_by_slippage(
                 pool_address,
                 &self.env.token_address,
                 &self.weth_address,
                 amount_in,
-                total_slippage,
+                total_slippage_bps,
             )
             .await?
         } else {
             U256::zero()
         };
 
-        let recipient_nonce = recipient_nonce.unwrap_or(
-            self.http_provider
-                .get_transaction_count(*recipient, None)
-                .await?,
-        );
+        let recipient_nonce = match recipient_nonce {
+            Some(recipient_nonce) => recipient_nonce,
+            None => {
+                self.http_provider
+                    .get_transaction_count(*recipient, None)
+                    .await?
+            }
+        };
 
-        let mut sell_tx: TypedTransaction = uniswapv3_router
-            .exact_input_single(ExactInputSingleParams {
-                token_in: self.env.token_address,
-                token_out: self.weth_address,
-                fee: pool_fee,
-                recipient: *recipient,
-                amount_in,
-                amount_out_minimum,
-                sqrt_price_limit_x96: U256::zero(),
-            })
-            .tx;
+        let mut sell_tx = match self.get_multi_hop(pool_address) {
+            Some(multi_hop) => construct_exact_input_tx(
+                &uniswapv3_router,
+                ExactInputParams {
+                    path: multi_hop.path(self.weth_address, self.env.token_address, false),
+                    recipient: *recipient,
+                    amount_in,
+                    amount_out_minimum,
+                },
+                None,
+            ),
+            None => construct_exact_input_single_tx(
+                &uniswapv3_router,
+                ExactInputSingleParams {
+                    token_in: self.env.token_address,
+                    token_out: self.weth_address,
+                    fee: pool_fee,
+                    recipient: *recipient,
+                    amount_in,
+                    amount_out_minimum,
+                    sqrt_price_limit_x96: U256::zero(),
+                },
+                None,
+            ),
+        };
 
         sell_tx.set_chain_id(self.env.chain_id);
         sell_tx.set_from(*recipient);
         sell_tx.set_nonce(recipient_nonce);
-        sell_tx.set_gas(U256::from(700_000)); // fixed gas
-        sell_tx.set_gas_price(gas_price);
-
-        let sell_tx = to_legacy_tx(sell_tx);
+        sell_tx.set_gas(
+            GasLimits::estimate_or_default(
+                self.http_provider.as_ref(),
+                &sell_tx,
+                self.gas_limits.sell,
+            )
+            .await,
+        );
+        let sell_tx = apply_gas_mode(sell_tx, gas_price, self.http_provider.clone()).await?;
         Ok(sell_tx)
     }
 
@@ -231,37 +645,58 @@ impl Uniswap3Service {
         token_in: &Address,
         token_out: &Address,
         amount_in: U256,
-        total_slippage: f32,
+        total_slippage_bps: u32,
     ) -> anyhow::Result<U256> {
-        let uniswapv3_pool = UniswapV3PoolAbigen::new(*pool_address, self.http_provider.clone());
-        let pool_fee: u32 = uniswapv3_pool.fee().call().await?;
-
         let quoter_v2 = QuoterV2Abigen::new(
             self.uniswap_v3_quoter_v2_address,
             self.http_provider.clone(),
         );
-        let (amount_out, _, _, _) = match quoter_v2
-            .quote_exact_input_single(QuoteExactInputSingleParams {
-                token_in: *token_in,
-                token_out: *token_out,
-                amount_in,
-                fee: pool_fee,
-                sqrt_price_limit_x96: U256::zero(),
-            })
-            .call()
-            .await
-        {
-            Ok(result) => result,
+        let quote = match self.get_multi_hop(pool_address) {
+            Some(multi_hop) => {
+                let is_buy = *token_in == self.weth_address;
+                quoter_v2
+                    .quote_exact_input(
+                        multi_hop.path(self.weth_address, self.env.token_address, is_buy),
+                        amount_in,
+                    )
+                    .call()
+                    .await
+                    .map(|(amount_out, _, _, _)| amount_out)
+            }
+            None => {
+                let uniswapv3_pool =
+                    UniswapV3PoolAbigen::new(*pool_address, self.http_provider.clone());
+                let pool_fee: u32 = uniswapv3_pool.fee().call().await?;
+                quoter_v2
+                    .quote_exact_input_single(QuoteExactInputSingleParams {
+                        token_in: *token_in,
+                        token_out: *token_out,
+                        amount_in,
+                        fee: pool_fee,
+                        sqrt_price_limit_x96: U256::zero(),
+                    })
+                    .call()
+                    .await
+                    .map(|(amount_out, _, _, _)| amount_out)
+            }
+        };
+        let amount_out = match quote {
+            Ok(amount_out) => amount_out,
             Err(err) => {
                 let revert_data = err.to_string();
                 log::warn!("[quote_exact_input_single] with error: {:?}", revert_data);
-                return Ok(U256::zero());
+                if self.quoter_revert_accept_any_output {
+                    return Ok(U256::zero());
+                }
+                return Err(anyhow!(
+                    "[Uniswap3Service.get_amount_out_by_slippage] quoter reverted for pool {:?}: {}",
+                    pool_address,
+                    revert_data
+                ));
             }
         };
 
-        let total_slippage_u256 = U256::from((total_slippage * 1000_f32).trunc() as u32);
-
-        let amount_out_min = amount_out - amount_out * total_slippage_u256 / U256::from(100_000);
+        let amount_out_min = apply_slippage_bps(amount_out, total_slippage_bps);
 
         Ok(amount_out_min)
     }
@@ -273,99 +708,176 @@ impl Uniswap3Service {
         is_buy: bool,
         fee_tier_v3: Option<u32>,
     ) -> anyhow::Result<(Address, bool)> {
-        let uniswapv3_router = UniswapV3Router02Abigen::new(
-            self.uniswap_v3_router_address,
-            self.http_provider.clone(),
-        );
-        let factory_address: Address = uniswapv3_router.factory().call().await?;
+        // an explicit fee tier asks for that pool, the override only replaces the best pool lookup
+        if let (Some(pair_override), None) = (&self.pair_override, fee_tier_v3) {
+            if let Some(pair) = pair_override
+                .resolve(first_token, second_token, self.http_provider.clone())
+                .await?
+            {
+                return Ok(pair);
+            }
+        }
+
+        let factory_address = self.get_factory_address().await?;
 
         let uniswapv3_factory =
             UniswapV3FactoryAbigen::new(factory_address, self.http_provider.clone());
-        let mut max_amount_out = U256::zero();
-        let mut max_pair_address = *ZERO_ADDRESS;
-        let mut is_first_token_0 = false;
 
-        if fee_tier_v3.is_some() {
+        if let Some(fee_tier_v3) = fee_tier_v3 {
             let pool_address: Address = uniswapv3_factory
-                .get_pool(*first_token, *second_token, fee_tier_v3.unwrap())
+                .get_pool(*first_token, *second_token, fee_tier_v3)
                 .await?;
 
             return Ok((pool_address, false));
         }
 
-        for fee_tier in &[
-            UniswapV3FeeTier::Tier500,
-            UniswapV3FeeTier::Tier3000,
-            UniswapV3FeeTier::Tier10000,
-        ] {
-            let pair_address: Address = uniswapv3_factory
-                .get_pool(*first_token, *second_token, (*fee_tier).into())
-                .await?;
-
-            if pair_address.eq(&ZERO_ADDRESS) {
-                continue;
-            }
+        let is_first_token_weth = *first_token == self.weth_address;
+        let (token_in, token_out) = if is_buy == is_first_token_weth {
+            (first_token, second_token)
+        } else {
+            (second_token, first_token)
+        };
+        let selected_pool = select_v3_pool(
+            &self.fee_tier_preference,
+            self.pool_selection,
+            self.min_pool_liquidity,
+            |fee_tier| {
+                let uniswapv3_factory = &uniswapv3_factory;
+                async move {
+                    let pool_address: Address = uniswapv3_factory
+                        .get_pool(*first_token, *second_token, fee_tier.into())
+                        .await?;
+                    if pool_address == *ZERO_ADDRESS {
+                        return Ok(None);
+                    }
+                    let (_, weth_liquidity) = self.get_pool_liquidity(pool_address).await?;
+                    let amount_out = self
+                        .get_amount_out_by_slippage(
+                            &pool_address,
+                            token_in,
+                            token_out,
+                            self.simulate_amount,
+                            0,
+                        )
+                        .await?;
+                    Ok(Some(V3PoolProbe {
+                        pool_address,
+                        weth_liquidity,
+                        amount_out,
+                    }))
+                }
+            },
+        )
+        .await?;
+
+        if let Some((fee_tier, pool_probe)) = selected_pool {
+            log::info!(
+                "[Uniswap3Service.compute_pair_address] {:?} pool {:?} fee tier {:?}",
+                self.pool_selection,
+                pool_probe.pool_address,
+                fee_tier
+            );
+            let uniswap_v3_pool =
+                UniswapV3PoolAbigen::new(pool_probe.pool_address, self.http_provider.clone());
+            let token0_address: Address = uniswap_v3_pool.token_0().call().await?;
+            return Ok((pool_probe.pool_address, *first_token == token0_address));
+        }
 
-            let is_first_token_weth = *first_token == self.weth_address;
-
-            let mut amount_out_min = U256::zero();
-            if is_buy && is_first_token_weth {
-                amount_out_min = self
-                    .get_amount_out_by_slippage(
-                        &pair_address,
-                        first_token,
-                        second_token,
-                        U256::from(100), // simulate number
-                        0.0,
-                    )
-                    .await?;
-            };
-            if is_buy && !is_first_token_weth {
-                amount_out_min = self
-                    .get_amount_out_by_slippage(
-                        &pair_address,
-                        second_token,
-                        first_token,
-                        U256::from(100), // simulate number
-                        0.0,
-                    )
-                    .await?;
-            };
-            if !is_buy && is_first_token_weth {
-                amount_out_min = self
-                    .get_amount_out_by_slippage(
-                        &pair_address,
-                        second_token,
-                        first_token,
-                        U256::from(100), // simulate number
-                        0.0,
-                    )
-                    .await?;
-            };
-            if !is_buy && !is_first_token_weth {
-                amount_out_min = self
-                    .get_amount_out_by_slippage(
-                        &pair_address,
-                        first_token,
-                        second_token,
-                        U256::from(100), // simulate number
-                        0.0,
-                    )
-                    .await?;
+        if !self.intermediary_tokens.is_empty() {
+            let token_address = if *first_token == self.weth_address {
+                second_token
+            } else {
+                first_token
             };
+            return self
+                .compute_multi_hop_pair_address(&uniswapv3_factory, first_token, token_address)
+                .await;
+        }
 
-            if amount_out_min > max_amount_out {
-                max_amount_out = amount_out_min;
-                max_pair_address = pair_address;
+        Ok((*ZERO_ADDRESS, false))
+    }
 
-                let uniswap_v3_pool =
-                    UniswapV3PoolAbigen::new(pair_address, self.http_provider.clone());
-                let token0_address: Address = uniswap_v3_pool.token_0().call().await?;
-                is_first_token_0 = *first_token == token0_address;
+    /// best two-hop route through `V3_INTERMEDIARY_TOKENS`, returns the token side pool
+    async fn compute_multi_hop_pair_address(
+        &self,
+        uniswapv3_factory: &UniswapV3FactoryAbigen<Provider<Http>>,
+        first_token: &Address,
+        token_address: &Address,
+    ) -> anyhow::Result<(Address, bool)> {
+        let fee_tiers = UniswapV3FeeTier::get_fee_tiers(&self.env.listen_network);
+        let quoter_v2 = QuoterV2Abigen::new(
+            self.uniswap_v3_quoter_v2_address,
+            self.http_provider.clone(),
+        );
+        let probe_amount = parse_ether("0.0001").unwrap();
+        let mut best_route: Option<(U256, Address, V3MultiHop)> = None;
+
+        for intermediary in &self.intermediary_tokens {
+            for weth_fee_tier in fee_tiers {
+                let weth_pool: Address = uniswapv3_factory
+                    .get_pool(self.weth_address, *intermediary, (*weth_fee_tier).into())
+                    .await?;
+                if weth_pool == *ZERO_ADDRESS {
+                    continue;
+                }
+                for token_fee_tier in fee_tiers {
+                    let token_pool: Address = uniswapv3_factory
+                        .get_pool(*intermediary, *token_address, (*token_fee_tier).into())
+                        .await?;
+                    if token_pool == *ZERO_ADDRESS {
+                        continue;
+                    }
+                    let multi_hop = V3MultiHop {
+                        intermediary: *intermediary,
+                        weth_fee: (*weth_fee_tier).into(),
+                        token_fee: (*token_fee_tier).into(),
+                    };
+                    let amount_out = match quoter_v2
+                        .quote_exact_input(
+                            multi_hop.path(self.weth_address, *token_address, true),
+                            probe_amount,
+                        )
+                        .call()
+                        .await
+                    {
+                        Ok((amount_out, _, _, _)) => amount_out,
+                        Err(err) => {
+                            log::warn!(
+                                "[Uniswap3Service.compute_multi_hop_pair_address] skip route {:?}: {:?}",
+                                multi_hop,
+                                err
+                            );
+                            continue;
+                        }
+                    };
+                    if best_route.is_none_or(|(max_amount_out, _, _)| amount_out > max_amount_out) {
+                        best_route = Some((amount_out, token_pool, multi_hop));
+                    }
+                }
             }
         }
 
-        Ok((max_pair_address, is_first_token_0))
+        let Some((_, token_pool, multi_hop)) = best_route else {
+            return Ok((*ZERO_ADDRESS, false));
+        };
+        log::info!(
+            "[Uniswap3Service] no direct WETH pool, route {:?} through pool {:?}",
+            multi_hop,
+            token_pool
+        );
+        self.multi_hops
+            .lock()
+            .unwrap()
+            .insert(token_pool, multi_hop);
+
+        let uniswap_v3_pool = UniswapV3PoolAbigen::new(token_pool, self.http_provider.clone());
+        let token0_address: Address = uniswap_v3_pool.token_0().call().await?;
+
+        Ok((token_pool, *first_token == token0_address))
+    }
+
+    fn get_multi_hop(&self, pool_address: &Address) -> Option<V3MultiHop> {
+        self.multi_hops.lock().unwrap().get(pool_address).copied()
     }
 
     pub async fn get_all_pair_addresses(
@@ -373,22 +885,14 @@ impl Uniswap3Service {
         first_token: &Address,
         second_token: &Address,
     ) -> anyhow::Result<Vec<Address>> {
-        let uniswapv3_router = UniswapV3Router02Abigen::new(
-            self.uniswap_v3_router_address,
-            self.http_provider.clone(),
-        );
-        let factory_address: Address = uniswapv3_router.factory().call().await?;
+        let factory_address = self.get_factory_address().await?;
 
         let uniswapv2_factory =
             UniswapV3FactoryAbigen::new(factory_address, self.http_provider.clone());
 
         let mut pair_addresses: Vec<Address> = Vec::new();
 
-        for fee_tier in &[
-            UniswapV3FeeTier::Tier500,
-            UniswapV3FeeTier::Tier3000,
-            UniswapV3FeeTier::Tier10000,
-        ] {
+        for fee_tier in UniswapV3FeeTier::get_fee_tiers(&self.env.listen_network) {
             let pair_address: Address = uniswapv2_factory
                 .get_pool(*first_token, *second_token, (*fee_tier).into())
                 .await?;
@@ -403,7 +907,36 @@ impl Uniswap3Service {
         Ok(pair_addresses)
     }
 
+    /// price in WETH of the non WETH token of the pool, through the intermediary WETH pool for multi-hop routes
     pub async fn get_token_native_price(&self, pool_address: Address) -> anyhow::Result<f64> {
+        let Some(multi_hop) = self.get_multi_hop(&pool_address) else {
+            return self.get_pool_price(pool_address, self.weth_address).await;
+        };
+
+        let token_intermediary_price = self
+            .get_pool_price(pool_address, multi_hop.intermediary)
+            .await?;
+        let factory_address = self.get_factory_address().await?;
+        let uniswapv3_factory =
+            UniswapV3FactoryAbigen::new(factory_address, self.http_provider.clone());
+        let weth_pool: Address = uniswapv3_factory
+            .get_pool(
+                self.weth_address,
+                multi_hop.intermediary,
+                multi_hop.weth_fee,
+            )
+            .await?;
+        let intermediary_weth_price = self.get_pool_price(weth_pool, self.weth_address).await?;
+
+        Ok(token_intermediary_price * intermediary_weth_price)
+    }
+
+    /// price of the other token of the pool in `quote_token`, from the pool sqrt price
+    async fn get_pool_price(
+        &self,
+        pool_address: Address,
+        quote_token: Address,
+    ) -> anyhow::Result<f64> {
         let uniswapv3_pool = UniswapV3PoolAbigen::new(pool_address, self.http_provider.clone());
         let (sqrt_price_x96, _, _, _, _, _, _): (U256, i32, u16, u16, u16, u8, bool) =
             uniswapv3_pool.slot_0().call().await?;
@@ -423,21 +956,35 @@ impl Uniswap3Service {
             .to_string()
             .parse::<f64>()?;
 
-        if token0.eq(&self.weth_address) {
+        if token0.eq(&quote_token) {
             return Ok(1_f64 / token0_token1_ratio);
         }
 
         Ok(token0_token1_ratio)
     }
 
+    /// (token amount, weth amount) tradable around the current price of the pool
+    pub async fn get_pool_liquidity(&self, pool_address: Address) -> anyhow::Result<(U256, U256)> {
+        fetch_v3_pool_liquidity(pool_address, self.weth_address, self.http_provider.clone()).await
+    }
+
     pub fn get_router_address(&self) -> anyhow::Result<Address> {
         Ok(self.uniswap_v3_router_address)
     }
 
+    async fn get_factory_address(&self) -> anyhow::Result<Address> {
+        if let Some(factory_address) = UNISWAP3_FACTORIES.get(&self.env.listen_network) {
+            return Ok(*factory_address);
+        }
+        let uniswapv3_router = UniswapV3Router02Abigen::new(
+            self.uniswap_v3_router_address,
+            self.http_provider.clone(),
+        );
+        Ok(uniswapv3_router.factory().call().await?)
+    }
+
     pub async fn get_active_trading_tx(&self) -> anyhow::Result<Bytes> {
-        let deployer_wallet = self
-            .deployer_private_key
-            .parse::<LocalWallet>()
+        let deployer_wallet = load_private_key_wallet(&self.deployer_private_key)
             .unwrap()
             .with_chain_id(self.env.chain_id.as_u64());
 
@@ -466,3 +1013,228 @@ impl Uniswap3Service {
         Ok(signed_active_trading_tx)
     }
 }
+
+/**
+ * virtual reserves (amount0, amount1) of the in range liquidity at sqrt_price_x96:
+ * amount0 = L * 2^96 / sqrtP, amount1 = L * sqrtP / 2^96
+ */
+pub fn compute_v3_virtual_reserves(liquidity: u128, sqrt_price_x96: U256) -> (U256, U256) {
+    if sqrt_price_x96.is_zero() {
+        return (U256::zero(), U256::zero());
+    }
+    let q96 = U256::one() << 96;
+    let liquidity = U256::from(liquidity);
+    let amount0 = liquidity.full_mul(q96) / U512::from(sqrt_price_x96);
+    let amount1 = liquidity.full_mul(sqrt_price_x96) / U512::from(q96);
+
+    (
+        U256::try_from(amount0).unwrap_or(U256::MAX),
+        U256::try_from(amount1).unwrap_or(U256::MAX),
+    )
+}
+
+/**
+ * (token amount, weth amount) of a v3 pool from `liquidity()` and `slot0`,
+ * capped by the pool balances since a narrow position has more virtual than real reserves
+ */
+pub async fn fetch_v3_pool_liquidity<M: Middleware + 'static>(
+    pool_address: Address,
+    weth_address: Address,
+    provider: Arc<M>,
+) -> anyhow::Result<(U256, U256)> {
+    let uniswapv3_pool = UniswapV3PoolAbigen::new(pool_address, provider.clone());
+    let liquidity: u128 = uniswapv3_pool.liquidity().call().await?;
+    let (sqrt_price_x96, _, _, _, _, _, _): (U256, i32, u16, u16, u16, u8, bool) =
+        uniswapv3_pool.slot_0().call().await?;
+    let token0: Address = uniswapv3_pool.token_0().call().await?;
+    let token1: Address = uniswapv3_pool.token_1().call().await?;
+    let balance0 = MemeTokenAbigen::new(token0, provider.clone())
+        .balance_of(pool_address)
+        .call()
+        .await?;
+    let balance1 = MemeTokenAbigen::new(token1, provider)
+        .balance_of(pool_address)
+        .call()
+        .await?;
+
+    let (amount0, amount1) = compute_v3_virtual_reserves(liquidity, sqrt_price_x96);
+    let (amount0, amount1) = (amount0.min(balance0), amount1.min(balance1));
+    if token0 == weth_address {
+        return Ok((amount1, amount0));
+    }
+
+    Ok((amount0, amount1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::Token;
+    use provider_utils::mock_rpc::MockRpc;
+
+    #[test]
+    fn buy_tx_carries_native_value() {
+        let provider = Arc::new(Provider::<Http>::try_from("http://localhost:8545").unwrap());
+        let uniswapv3_router = UniswapV3Router02Abigen::new(Address::random(), provider);
+        let weth_address = Address::random();
+        let amount_in = parse_ether("0.1").unwrap();
+        let params = ExactInputSingleParams {
+            token_in: weth_address,
+            token_out: Address::random(),
+            fee: 3000,
+            recipient: Address::random(),
+            amount_in,
+            amount_out_minimum: U256::one(),
+            sqrt_price_limit_x96: U256::zero(),
+        };
+
+        let buy_tx =
+            construct_exact_input_single_tx(&uniswapv3_router, params.clone(), Some(amount_in));
+        assert_eq!(buy_tx.value(), Some(&amount_in));
+        let data = buy_tx.data().unwrap();
+        assert!(data.starts_with(&[0x04, 0xe4, 0x5a, 0xaf])); // exactInputSingle methodId
+        assert_eq!(&data[16..36], weth_address.as_bytes()); // token_in
+
+        let weth_buy_tx = construct_exact_input_single_tx(&uniswapv3_router, params, None);
+        assert_eq!(weth_buy_tx.value(), None);
+    }
+
+    #[tokio::test]
+    async fn v3_pool_reports_liquidity_depth() {
+        let (pool_address, token_address, weth_address) =
+            (Address::random(), Address::random(), Address::random());
+        let liquidity = parse_ether(1).unwrap();
+        // price 4 weth per token: sqrtPriceX96 = 2 * 2^96
+        let sqrt_price_x96 = U256::from(2) << 96;
+        let pool_balance = |token: Address, balance: U256| {
+            (
+                token,
+                [Token::Address(pool_address)],
+                [Token::Uint(balance)],
+            )
+        };
+        let mut mock_rpc = MockRpc::new()
+            .on_call(pool_address, "liquidity()", &[], &[Token::Uint(liquidity)])
+            .on_call(
+                pool_address,
+                "slot0()",
+                &[],
+                &[
+                    Token::Uint(sqrt_price_x96),
+                    Token::Int(U256::from(13863)),
+                    Token::Uint(U256::zero()),
+                    Token::Uint(U256::one()),
+                    Token::Uint(U256::one()),
+                    Token::Uint(U256::zero()),
+                    Token::Bool(true),
+                ],
+            )
+            .on_call(
+                pool_address,
+                "token0()",
+                &[],
+                &[Token::Address(token_address)],
+            )
+            .on_call(
+                pool_address,
+                "token1()",
+                &[],
+                &[Token::Address(weth_address)],
+            );
+        for (token, args, output) in [
+            pool_balance(token_address, parse_ether(10).unwrap()),
+            // below the 2 weth of virtual reserve
+            pool_balance(weth_address, parse_ether(1).unwrap()),
+        ] {
+            mock_rpc = mock_rpc.on_call(token, "balanceOf(address)", &args, &output);
+        }
+
+        let (token_amount, weth_amount) =
+            fetch_v3_pool_liquidity(pool_address, weth_address, Arc::new(mock_rpc.provider()))
+                .await
+                .unwrap();
+        assert_eq!(token_amount, parse_ether("0.5").unwrap());
+        assert_eq!(weth_amount, parse_ether(1).unwrap());
+
+        assert_eq!(
+            compute_v3_virtual_reserves(0, sqrt_price_x96),
+            (U256::zero(), U256::zero())
+        );
+    }
+
+    fn pool_probe(weth_liquidity: &str, amount_out: u64) -> V3PoolProbe {
+        V3PoolProbe {
+            pool_address: Address::random(),
+            weth_liquidity: parse_ether(weth_liquidity).unwrap(),
+            amount_out: U256::from(amount_out),
+        }
+    }
+
+    #[tokio::test]
+    async fn fee_tiers_are_tried_in_preference_order() {
+        let fee_tiers = parse_fee_tier_preference("3000, 500", &ENetwork::BaseMainnet).unwrap();
+        assert_eq!(
+            fee_tiers,
+            vec![UniswapV3FeeTier::Tier3000, UniswapV3FeeTier::Tier500]
+        );
+        assert_eq!(
+            parse_fee_tier_preference("", &ENetwork::BscMainnet).unwrap(),
+            UniswapV3FeeTier::get_fee_tiers(&ENetwork::BscMainnet)
+        );
+        assert!(parse_fee_tier_preference("3000", &ENetwork::BscMainnet).is_err());
+
+        // the 500 pool quotes better on the dust amount, the preferred 3000 pool is still picked
+        let pools = BTreeMap::from([(3000, pool_probe("10", 90)), (500, pool_probe("10", 100))]);
+        let probe = |fee_tier: UniswapV3FeeTier| {
+            let pool_probe = pools.get(&Into::<u32>::into(fee_tier)).copied();
+            async move { Ok(pool_probe) }
+        };
+        let selected = select_v3_pool(&fee_tiers, V3PoolSelection::Preference, U256::zero(), probe)
+            .await
+            .unwrap();
+        assert_eq!(selected, Some((UniswapV3FeeTier::Tier3000, pools[&3000])));
+
+        let selected = select_v3_pool(&fee_tiers, V3PoolSelection::BestQuote, U256::zero(), probe)
+            .await
+            .unwrap();
+        assert_eq!(selected, Some((UniswapV3FeeTier::Tier500, pools[&500])));
+    }
+
+    #[tokio::test]
+    async fn thin_pools_are_skipped() {
+        let fee_tiers = UniswapV3FeeTier::get_fee_tiers(&ENetwork::BaseMainnet);
+        // no 500 pool, the 3000 pool is thin and the 10000 pool quote reverts
+        let probe = |fee_tier: UniswapV3FeeTier| async move {
+            match fee_tier {
+                UniswapV3FeeTier::Tier3000 => Ok(Some(pool_probe("0.5", 1_000))),
+                UniswapV3FeeTier::Tier10000 => Err(anyhow!("quote reverted")),
+                _ => Ok(None),
+            }
+        };
+        let min_weth_liquidity = parse_ether(1).unwrap();
+        for selection in [V3PoolSelection::Preference, V3PoolSelection::BestQuote] {
+            let selected = select_v3_pool(fee_tiers, selection, min_weth_liquidity, probe)
+                .await
+                .unwrap();
+            assert_eq!(selected, None);
+        }
+
+        let deep_pool = pool_probe("25", 10);
+        let probe = |fee_tier: UniswapV3FeeTier| async move {
+            match fee_tier {
+                UniswapV3FeeTier::Tier500 => Ok(Some(pool_probe("0.5", 1_000))),
+                UniswapV3FeeTier::Tier3000 => Ok(Some(deep_pool)),
+                _ => Ok(None),
+            }
+        };
+        let selected = select_v3_pool(
+            fee_tiers,
+            V3PoolSelection::Preference,
+            min_weth_liquidity,
+            probe,
+        )
+        .await
+        .unwrap();
+        assert_eq!(selected, Some((UniswapV3FeeTier::Tier3000, deep_pool)));
+    }
+}
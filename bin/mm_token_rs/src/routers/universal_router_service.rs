@@ -0,0 +1,375 @@
+use anyhow::anyhow;
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{transaction::eip2718::TypedTransaction, Address, Bytes, TransactionRequest, U256},
+};
+use mm_token_utils::{
+    abi::UniswapV3PoolAbigen,
+    constants::{
+        UNIVERSAL_ADDRESS_THIS, UNIVERSAL_MSG_SENDER, UNIVERSAL_ROUTERS, UNWRAP_WETH,
+        V2_SWAP_EXACT_IN, V3_SWAP_EXACT_IN, WRAPPED_NATIVE_TOKENS, WRAP_ETH, ZERO_ADDRESS,
+    },
+    env::get_env,
+    utils::{
+        encode_universal_execute, encode_v2_swap_exact_in, encode_v3_path, encode_v3_swap_exact_in,
+        encode_wrap_unwrap,
+    },
+};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use super::{Uniswap2Service, Uniswap3Service};
+use crate::{
+    constants::{Env, UniversalSwapVersion},
+    utils::{
+        add_tax_bps, apply_gas_mode, apply_slippage_floor, compute_deadline, percent_to_bps,
+        validate_slippage_bps, widen_slippage, TokenTax,
+    },
+};
+
+/// Swaps through the universal router `execute`, pools are quoted with the v2/v3 services.
+/// Sells pull the token through Permit2, the token must be approved to Permit2 and Permit2 to the router
+#[derive(Debug, Clone)]
+pub struct UniversalRouterService {
+    env: Env,
+    http_provider: Arc<Provider<Http>>,
+    gas_price: Arc<RwLock<U256>>,
+    universal_router_address: Address,
+    weth_address: Address,
+    swap_version: UniversalSwapVersion,
+    trading_slippage_bps: u32,
+    min_slippage_floor_bps: u32,
+    token_tax: TokenTax,
+    swap_deadline_secs: u64,
+    uniswap2_service: Uniswap2Service,
+    uniswap3_service: Uniswap3Service,
+}
+
+impl UniversalRouterService {
+    pub fn new(
+        env: Env,
+        gas_price: Arc<RwLock<U256>>,
+        http_provider: Arc<Provider<Http>>,
+        uniswap2_service: Uniswap2Service,
+        uniswap3_service: Uniswap3Service,
+    ) -> Self {
+        // unsupported networks fail on the first universal trade, not at startup
+        let universal_router_address = UNIVERSAL_ROUTERS
+            .get(&env.listen_network)
+            .copied()
+            .unwrap_or(*ZERO_ADDRESS);
+
+        let Some(weth) = WRAPPED_NATIVE_TOKENS.get(&env.listen_network) else {
+            panic!(
+                "WRAPPED_NATIVE_TOKENS not found in {:?}",
+                env.listen_network
+            );
+        };
+
+        let swap_version: UniversalSwapVersion =
+            get_env("UNIVERSAL_SWAP_VERSION", Some("V2".to_string()))
+                .parse()
+                .unwrap();
+        let trading_slippage: f32 = get_env("TRADING_SLIPPAGE", None).parse().unwrap_or(0.0);
+        let min_slippage_floor: f32 = get_env("MIN_SLIPPAGE_FLOOR", Some("0.1".to_string()))
+            .parse()
+            .unwrap();
+
+        let token_tax = TokenTax::from_env(&env, http_provider.clone());
+
+        Self {
+            env,
+            http_provider,
+            gas_price,
+            universal_router_address,
+            weth_address: weth.address,
+            swap_version,
+            trading_slippage_bps: percent_to_bps(trading_slippage).unwrap(),
+            min_slippage_floor_bps: percent_to_bps(min_slippage_floor).unwrap(),
+            token_tax,
+            swap_deadline_secs: get_env("SWAP_DEADLINE_SECS", Some("60".to_string()))
+                .parse()
+                .unwrap(),
+            uniswap2_service,
+            uniswap3_service,
+        }
+    }
+
+    /// trading slippage in basis points, rejected beyond 100%, the v2/v3 quoting services follow
+    pub fn set_slippage_bps(&mut self, slippage_bps: u32) -> anyhow::Result<()> {
+        self.trading_slippage_bps = validate_slippage_bps(slippage_bps)?;
+        self.uniswap2_service.set_slippage_bps(slippage_bps)?;
+        self.uniswap3_service.set_slippage_bps(slippage_bps)
+    }
+
+    /// WRAP_ETH into the router, then swap the WETH to the recipient, the tx value is `amount_in`
+    pub async fn buy_token(
+        &self,
+        pool_address: &Address,
+        recipient: &Address,
+        recipient_nonce: Option<U256>,
+        amount_in: U256,
+        is_apply_slippage: bool,
+    ) -> anyhow::Result<TypedTransaction> {
+        let amount_out_min = if is_apply_slippage {
+            let total_slippage_bps = self.get_total_slippage(true).await;
+            self.get_amount_out(pool_address, true, amount_in, total_slippage_bps)
+                .await?
+        } else {
+            U256::zero()
+        };
+
+        let swap_input = self
+            .encode_swap_input(
+                pool_address,
+                *recipient,
+                self.weth_address,
+                self.env.token_address,
+                amount_in,
+                amount_out_min,
+                false, // paid with the wrapped ETH held by the router
+            )
+            .await?;
+        let calldata = encode_universal_execute(
+            vec![WRAP_ETH, self.swap_command()],
+            vec![
+                encode_wrap_unwrap(*UNIVERSAL_ADDRESS_THIS, amount_in),
+                swap_input,
+            ],
+            compute_deadline(self.swap_deadline_secs),
+        );
+
+        let mut buy_tx = self
+            .construct_execute_tx(recipient, recipient_nonce, calldata)
+            .await?;
+        buy_tx.set_value(amount_in);
+
+        Ok(buy_tx)
+    }
+
+    /// swap the token into WETH held by the router, then UNWRAP_WETH to the sender,
+    /// `max_slippage_bps` widens the slippage up to it, for the retry of a reverted sell
+    pub async fn sell_token(
+        &self,
+        pool_address: &Address,
+        recipient: &Address,
+        recipient_nonce: Option<U256>,
+        amount_in: U256,
+        is_apply_slippage: bool,
+        max_slippage_bps: Option<u32>,
+    ) -> anyhow::Result<TypedTransaction> {
+        let amount_out_min = if is_apply_slippage {
+            let total_slippage_bps = self.get_total_slippage(false).await;
+            // retry of a reverted sell
+            let total_slippage_bps = max_slippage_bps
+                .map_or(total_slippage_bps, |max_slippage_bps| {
+                    widen_slippage(total_slippage_bps, max_slippage_bps)
+                });
+            self.get_amount_out(pool_address, false, amount_in, total_slippage_bps)
+                .await?
+        } else {
+            U256::zero()
+        };
+
+        let swap_input = self
+            .encode_swap_input(
+                pool_address,
+                *UNIVERSAL_ADDRESS_THIS,
+                self.env.token_address,
+                self.weth_address,
+                amount_in,
+                amount_out_min,
+                true, // pulled from the seller through Permit2
+            )
+            .await?;
+        let calldata = encode_universal_execute(
+            vec![self.swap_command(), UNWRAP_WETH],
+            vec![
+                swap_input,
+                encode_wrap_unwrap(*UNIVERSAL_MSG_SENDER, amount_out_min),
+            ],
+            compute_deadline(self.swap_deadline_secs),
+        );
+
+        self.construct_execute_tx(recipient, recipient_nonce, calldata)
+            .await
+    }
+
+    pub async fn get_amount_out(
+        &self,
+        pool_address: &Address,
+        is_buy: bool,
+        amount_in: U256,
+        total_slippage_bps: u32,
+    ) -> anyhow::Result<U256> {
+        match self.swap_version {
+            UniversalSwapVersion::V2 => {
+                self.uniswap2_service
+                    .get_amount_out_min(*pool_address, is_buy, amount_in, total_slippage_bps)
+                    .await
+            }
+            UniversalSwapVersion::V3 => {
+                let (token_in, token_out) = if is_buy {
+                    (self.weth_address, self.env.token_address)
+                } else {
+                    (self.env.token_address, self.weth_address)
+                };
+                self.uniswap3_service
+                    .get_amount_out_by_slippage(
+                        pool_address,
+                        &token_in,
+                        &token_out,
+                        amount_in,
+                        total_slippage_bps,
+                    )
+                    .await
+            }
+        }
+    }
+
+    pub async fn get_token_native_price(&self, pool_address: Address) -> anyhow::Result<f64> {
+        match self.swap_version {
+            UniversalSwapVersion::V2 => {
+                let (price, _, _) = self.uniswap2_service.get_token_native_price().await?;
+                Ok(price)
+            }
+            UniversalSwapVersion::V3 => {
+                self.uniswap3_service
+                    .get_token_native_price(pool_address)
+                    .await
+            }
+        }
+    }
+
+    pub async fn compute_pair_address(
+        &self,
+        first_token: &Address,
+        second_token: &Address,
+        is_buy: bool,
+        fee_tier_v3: Option<u32>,
+    ) -> anyhow::Result<(Address, bool)> {
+        match self.swap_version {
+            UniversalSwapVersion::V2 => {
+                self.uniswap2_service
+                    .compute_pair_address(first_token, second_token)
+                    .await
+            }
+            UniversalSwapVersion::V3 => {
+                self.uniswap3_service
+                    .compute_pair_address(first_token, second_token, is_buy, fee_tier_v3)
+                    .await
+            }
+        }
+    }
+
+    pub async fn get_all_pair_addresses(
+        &self,
+        first_token: &Address,
+        second_token: &Address,
+    ) -> anyhow::Result<Vec<Address>> {
+        match self.swap_version {
+            UniversalSwapVersion::V2 => {
+                self.uniswap2_service
+                    .get_all_pair_addresses(first_token, second_token)
+                    .await
+            }
+            UniversalSwapVersion::V3 => {
+                self.uniswap3_service
+                    .get_all_pair_addresses(first_token, second_token)
+                    .await
+            }
+        }
+    }
+
+    pub fn get_router_address(&self) -> anyhow::Result<Address> {
+        if self.universal_router_address == *ZERO_ADDRESS {
+            return Err(anyhow!(
+                "[UniversalRouterService] UNIVERSAL_ROUTERS not found in {:?}",
+                self.env.listen_network
+            ));
+        }
+        Ok(self.universal_router_address)
+    }
+
+    fn swap_command(&self) -> u8 {
+        match self.swap_version {
+            UniversalSwapVersion::V2 => V2_SWAP_EXACT_IN,
+            UniversalSwapVersion::V3 => V3_SWAP_EXACT_IN,
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn encode_swap_input(
+        &self,
+        pool_address: &Address,
+        recipient: Address,
+        token_in: Address,
+        token_out: Address,
+        amount_in: U256,
+        amount_out_min: U256,
+        payer_is_user: bool,
+    ) -> anyhow::Result<Vec<u8>> {
+        let input = match self.swap_version {
+            UniversalSwapVersion::V2 => encode_v2_swap_exact_in(
+                recipient,
+                amount_in,
+                amount_out_min,
+                vec![token_in, token_out],
+                payer_is_user,
+            ),
+            UniversalSwapVersion::V3 => {
+                let uniswapv3_pool =
+                    UniswapV3PoolAbigen::new(*pool_address, self.http_provider.clone());
+                let pool_fee: u32 = uniswapv3_pool.fee().call().await?;
+                encode_v3_swap_exact_in(
+                    recipient,
+                    amount_in,
+                    amount_out_min,
+                    encode_v3_path(token_in, pool_fee, token_out),
+                    payer_is_user,
+                )
+            }
+        };
+
+        Ok(input)
+    }
+
+    async fn construct_execute_tx(
+        &self,
+        recipient: &Address,
+        recipient_nonce: Option<U256>,
+        calldata: Bytes,
+    ) -> anyhow::Result<TypedTransaction> {
+        let universal_router_address = self.get_router_address()?;
+        let gas_price = *self.gas_price.read().await;
+        let recipient_nonce = match recipient_nonce {
+            Some(recipient_nonce) => recipient_nonce,
+            None => {
+                self.http_provider
+                    .get_transaction_count(*recipient, None)
+                    .await?
+            }
+        };
+
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(universal_router_address)
+            .data(calldata)
+            .chain_id(self.env.chain_id)
+            .from(*recipient)
+            .nonce(recipient_nonce)
+            .gas(U256::from(700_000)) // fixed gas
+            .into();
+
+        apply_gas_mode(tx, gas_price, self.http_provider.clone()).await
+    }
+
+    async fn get_total_slippage(&self, is_buy: bool) -> u32 {
+        apply_slippage_floor(
+            add_tax_bps(
+                self.trading_slippage_bps,
+                self.token_tax.resolve(is_buy).await,
+            ),
+            self.min_slippage_floor_bps,
+        )
+    }
+}
@@ -0,0 +1,42 @@
+use ethers::types::{Address, H256};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchApprove {
+    pub router: Address,
+    // allowance already covers the balance, no tx sent
+    pub skipped: Vec<Address>,
+    pub approved: Vec<WalletApproval>,
+    pub failed: Vec<WalletApprovalError>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletApproval {
+    pub address: Address,
+    pub tx_hash: H256,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WalletApprovalError {
+    pub address: Address,
+    pub error: String,
+}
+
+impl BatchApprove {
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!(
+            "Batch approve to {:?}: {} approved, {} failed, {} skipped",
+            self.router,
+            self.approved.len(),
+            self.failed.len(),
+            self.skipped.len()
+        )];
+        for failure in self.failed.iter() {
+            lines.push(format!("{:?} failed: {}", failure.address, failure.error));
+        }
+        lines.join("\n")
+    }
+}
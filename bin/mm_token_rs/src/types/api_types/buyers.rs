@@ -1,4 +1,4 @@
-use ethers::types::Address;
+use ethers::types::{Address, U256};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,6 +20,11 @@ pub struct BuyersSettings {
 pub struct BuyersStatus {
     pub total_balance: String,
     pub total_token_balance: String,
+    // unrounded totals the telegram summary values, not part of the api
+    #[serde(skip)]
+    pub raw_total_balance: U256,
+    #[serde(skip)]
+    pub raw_total_token_balance: U256,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
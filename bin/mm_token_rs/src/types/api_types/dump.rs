@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Dump {
+    // Buyer, AutoBuyer, Seller or MarketMaker
+    pub group: String,
+    // seconds between two sells
+    pub interval_min: u32,
+    pub interval_max: u32,
+}
+
+impl Dump {
+    pub fn summary(&self) -> String {
+        format!(
+            "Dump of {} wallets started, {}-{}s between sells, a message follows when it ends",
+            self.group, self.interval_min, self.interval_max
+        )
+    }
+}
@@ -1,18 +1,54 @@
+use ethers::types::{H256, U64};
 use serde::{Deserialize, Serialize};
 
+use crate::constants::LaunchOrdering;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum StepStatus {
     Pending,
     Activated,
     Error(String),
+    // another launch_process call is in progress, this one did nothing
+    AlreadyRunning,
+    // trading was activated by a previous launch at this unix timestamp
+    AlreadyLaunched(i64),
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchStep {
+    pub status: StepStatus,
+    pub duration_ms: Option<u128>,
+}
+
+/**
+ * where a launch tx landed, compared to the position it was bundled at
+ */
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchTxPosition {
+    // tip, active trading or buyer wallet index
+    pub label: String,
+    pub tx_hash: H256,
+    pub expected_position: usize,
+    pub block_number: Option<U64>,
+    pub transaction_index: Option<U64>,
 }
 
+/**
+ * steps in the order launch_process runs them
+ */
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct LaunchStatus {
-    pub active_trading: StepStatus,
-    pub buyers_bot_launch: StepStatus,
-    pub migrate_tokens_to_seller: StepStatus,
-    pub start_auto_sell: StepStatus,
-    pub market_making_launch: StepStatus,
+    pub active_trading: LaunchStep,
+    pub buyers_bot_launch: LaunchStep,
+    pub migrate_tokens_to_seller: LaunchStep,
+    pub start_auto_sell: LaunchStep,
+    pub market_making_launch: LaunchStep,
+    pub ordering: LaunchOrdering,
+    // only filled in bundle ordering
+    pub tx_positions: Vec<LaunchTxPosition>,
+    // DRY_RUN, trades were simulated and nothing was broadcast
+    pub dry_run: bool,
 }
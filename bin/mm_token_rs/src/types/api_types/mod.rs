@@ -1,17 +1,29 @@
+mod batch_approve;
 mod buyers;
 mod deployer;
 mod deployment_checklist;
+mod dump;
 mod launch_process_body;
 mod launch_process_status;
 mod market_makers;
 mod network_status;
+mod panic_sell;
+mod pnl_report;
+mod pool_snapshot;
+mod quote;
 mod sellers;
 
+pub use batch_approve::*;
 pub use buyers::*;
 pub use deployer::*;
 pub use deployment_checklist::*;
+pub use dump::*;
 pub use launch_process_body::*;
 pub use launch_process_status::*;
 pub use market_makers::*;
 pub use network_status::*;
+pub use panic_sell::*;
+pub use pnl_report::*;
+pub use pool_snapshot::*;
+pub use quote::*;
 pub use sellers::*;
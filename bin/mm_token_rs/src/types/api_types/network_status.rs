@@ -29,6 +29,9 @@ pub struct NetworkStatusTokenInfo {
     pub token_template: TokenTemplate,
     pub router_contract: Address,
     pub pair_contract: Address,
+    /// fee of the v3 pool, none for v2 pairs
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pair_fee_tier: Option<u32>,
     pub weth: Address,
 }
 
@@ -45,3 +48,42 @@ pub enum TokenTemplate {
 pub struct NetworkStatusRouterInfo {
     pub avabot: Address,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_info_serializes_pair() {
+        let pair_contract: Address = "0x1111111111111111111111111111111111111111"
+            .parse()
+            .unwrap();
+        let token_info = NetworkStatusTokenInfo {
+            address: Address::random(),
+            is_deployed: true,
+            symbol: "MM".to_string(),
+            name: "MM".to_string(),
+            decimals: 18,
+            total_supply: 1_000_000_000,
+            token_template: TokenTemplate::BaseMemeTokenV1,
+            router_contract: Address::random(),
+            pair_contract,
+            pair_fee_tier: Some(3000),
+            weth: Address::random(),
+        };
+
+        let json = serde_json::to_value(&token_info).unwrap();
+        assert_eq!(
+            json["pairContract"],
+            "0x1111111111111111111111111111111111111111"
+        );
+        assert_eq!(json["pairFeeTier"], 3000);
+
+        let v2_token_info = NetworkStatusTokenInfo {
+            pair_fee_tier: None,
+            ..token_info
+        };
+        let json = serde_json::to_value(&v2_token_info).unwrap();
+        assert!(json.get("pairFeeTier").is_none());
+    }
+}
@@ -0,0 +1,51 @@
+use ethers::types::{Address, H256};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PanicSell {
+    pub sold: Vec<PanicSellWallet>,
+    pub failed: Vec<PanicSellError>,
+    // formatted with the token decimals
+    pub total_sold: String,
+    // DRY_RUN, sells were simulated and nothing was broadcast
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PanicSellWallet {
+    // buyer, seller, auto_buyer or mm group index
+    pub group: String,
+    pub address: Address,
+    pub amount: String,
+    // none in dry run
+    pub tx_hash: Option<H256>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PanicSellError {
+    pub group: String,
+    pub address: Address,
+    pub error: String,
+}
+
+impl PanicSell {
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!(
+            "{}Panic sell: {} wallets sold {} tokens, {} failed",
+            if self.dry_run { "DRY RUN " } else { "" },
+            self.sold.len(),
+            self.total_sold,
+            self.failed.len()
+        )];
+        for failure in self.failed.iter() {
+            lines.push(format!(
+                "{} {:?} failed: {}",
+                failure.group, failure.address, failure.error
+            ));
+        }
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,91 @@
+use ethers::{types::U256, utils::format_units};
+use serde::{Deserialize, Serialize};
+
+use crate::types::WalletGroup;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PnlReport {
+    // ETH, BNB or FTM
+    pub native_symbol: String,
+    // weth per token, values the holdings
+    pub token_price: f64,
+    pub groups: Vec<PnlGroupReport>,
+}
+
+/// native amounts of a wallet group, `pnl` counts the holdings at the current price
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct PnlGroupReport {
+    pub group: WalletGroup,
+    pub eth_spent: f64,
+    pub eth_received: f64,
+    pub token_holdings: f64,
+    pub holdings_value: f64,
+    pub pnl: f64,
+}
+
+impl PnlGroupReport {
+    pub fn new(
+        group: WalletGroup,
+        eth_spent: U256,
+        eth_received: U256,
+        token_holdings: U256,
+        token_decimals: u8,
+        token_price: f64,
+    ) -> anyhow::Result<Self> {
+        let eth_spent: f64 = format_units(eth_spent, "ether")?.parse()?;
+        let eth_received: f64 = format_units(eth_received, "ether")?.parse()?;
+        let token_holdings: f64 = format_units(token_holdings, token_decimals as usize)?.parse()?;
+        let holdings_value = token_holdings * token_price;
+        Ok(Self {
+            group,
+            eth_spent,
+            eth_received,
+            token_holdings,
+            holdings_value,
+            pnl: eth_received + holdings_value - eth_spent,
+        })
+    }
+}
+
+impl PnlReport {
+    pub fn total_pnl(&self) -> f64 {
+        self.groups.iter().map(|group| group.pnl).sum()
+    }
+
+    /// native figures with their USD value at `native_usd_price`
+    pub fn summary(&self, native_usd_price: f64) -> String {
+        let symbol = &self.native_symbol;
+        let mut summary = format!(
+            "📈 PnL 📈\n- Token price: {:#?} {} ~ ${:#?}\n",
+            self.token_price,
+            symbol,
+            self.token_price * native_usd_price
+        );
+        for group in &self.groups {
+            summary.push_str(&format!(
+                "\n{:?}\n- In: {:.4} {} / Out: {:.4} {}\n- Holdings: {:.2} tokens ~ {:.4} {}\n- PnL: {:.4} {} ~ ${:.2}\n",
+                group.group,
+                group.eth_spent,
+                symbol,
+                group.eth_received,
+                symbol,
+                group.token_holdings,
+                group.holdings_value,
+                symbol,
+                group.pnl,
+                symbol,
+                group.pnl * native_usd_price
+            ));
+        }
+        let total_pnl = self.total_pnl();
+        summary.push_str(&format!(
+            "\nTotal PnL: {:.4} {} ~ ${:.2}",
+            total_pnl,
+            symbol,
+            total_pnl * native_usd_price
+        ));
+        summary
+    }
+}
@@ -0,0 +1,16 @@
+use ethers::types::Address;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct PoolSnapshot {
+    pub pool_address: Address,
+    pub token_reserve: String,
+    pub weth_reserve: String,
+    // weth per token
+    pub price_native: f64,
+    pub price_usd: f64,
+    // price_usd times the total supply
+    pub fdv: f64,
+    pub block_number: u64,
+}
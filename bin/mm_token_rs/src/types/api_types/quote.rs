@@ -0,0 +1,36 @@
+use ethers::types::Address;
+use mm_token_utils::constants::ERouter;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct Quote {
+    pub is_buy: bool,
+    pub router: ERouter,
+    pub pool_address: Address,
+    // formatted with the decimals of the token in and out
+    pub amount_in: String,
+    pub amount_out: String,
+    // execution price against the mid price, positive when paying above it
+    pub price_impact_bps: i64,
+}
+
+impl Quote {
+    pub fn summary(&self) -> String {
+        let (amount_in_symbol, amount_out_symbol) = if self.is_buy {
+            ("ETH", "token")
+        } else {
+            ("token", "ETH")
+        };
+        format!(
+            "Quote {} {} -> {} {} via {:?} pool {:?}\nPrice impact: {:.2}%",
+            self.amount_in,
+            amount_in_symbol,
+            self.amount_out,
+            amount_out_symbol,
+            self.router,
+            self.pool_address,
+            self.price_impact_bps as f64 / 100.0
+        )
+    }
+}
@@ -1,4 +1,4 @@
-use ethers::types::Address;
+use ethers::types::{Address, U256};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -22,6 +22,11 @@ pub struct SellersSettings {
 pub struct SellersStatus {
     pub total_balance: String,
     pub total_token_balance: String,
+    // unrounded totals the telegram summary values, not part of the api
+    #[serde(skip)]
+    pub raw_total_balance: U256,
+    #[serde(skip)]
+    pub raw_total_token_balance: U256,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
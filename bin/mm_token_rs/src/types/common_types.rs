@@ -1,4 +1,10 @@
-use ethers::types::{Address, H160, U256};
+use std::str::FromStr;
+
+use anyhow::anyhow;
+use ethers::{
+    types::{Address, H160, U256},
+    utils::parse_ether,
+};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -18,3 +24,70 @@ pub struct CheckMnemonicWalletInfo {
     pub address: Address,
     pub private_key: String,
 }
+
+/**
+ * sell of the whole token balance of a wallet by the panic sell
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PanicSellPlan {
+    pub address: Address,
+    pub sell_amount: U256,
+    pub is_approve_needed: bool,
+}
+
+/**
+ * what a sell does about the router allowance of its wallet
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SellApproval {
+    // the allowance covers the sell
+    Approved,
+    // approve the router and wait for it before the sell
+    ApproveFirst,
+    // under approved with `AUTO_APPROVE_ON_SELL` off
+    Skip,
+}
+
+/**
+ * wallets whose trades are tracked together by the pnl ledger, `MarketMaker` is every group of the mm config
+ */
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum WalletGroup {
+    Buyer,
+    AutoBuyer,
+    Seller,
+    MarketMaker,
+}
+
+/**
+ * eth sent to each recipient of a disperse, `0.003` for the same amount or `0.002..0.004` for a random amount
+ * within the range
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisperseEthAmount {
+    Uniform(U256),
+    Random { min: U256, max: U256 },
+}
+
+impl FromStr for DisperseEthAmount {
+    type Err = anyhow::Error;
+
+    fn from_str(amount: &str) -> Result<Self, Self::Err> {
+        let parse = |amount: &str| {
+            parse_ether(amount.trim())
+                .map_err(|err| anyhow!("invalid eth amount {:?}: {}", amount, err))
+        };
+        let Some((min, max)) = amount.split_once("..") else {
+            return Ok(DisperseEthAmount::Uniform(parse(amount)?));
+        };
+        let (min, max) = (parse(min)?, parse(max)?);
+        if min > max {
+            return Err(anyhow!(
+                "invalid eth amount range {:?}, min is greater than max",
+                amount
+            ));
+        }
+        Ok(DisperseEthAmount::Random { min, max })
+    }
+}
@@ -17,6 +17,30 @@ pub struct DefaultMmSettings {
     pub max_delay_time: u64,
     pub min_retain_token: u32,
     pub max_retain_token: u32,
+    // percent of the wallet token balance retained instead of the absolute retain token when both are set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_retain_percent: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retain_percent: Option<f32>,
+    // groups in their buy/sell phase at once, unlimited when missing or 0
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_groups: Option<u32>,
+    // ETH a group buys over a rolling 24h, the group pauses at the cap, unlimited when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_daily_volume_eth: Option<f32>,
+    // ETH a single wallet buys over a rolling 24h, unlimited when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_wallet_volume_eth: Option<f32>,
+    // share of the group volume bought (0.6 = 60% buys), each iteration only buys, only sells or both
+    // to converge to it, every iteration buys then sells when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buy_sell_ratio: Option<f32>,
+    // 0..=1 chance an iteration draws its action at random instead of correcting the ratio, default 0.5
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action_randomness: Option<f32>,
+    // trading slippage of the group trades in basis points (0..=10000), TRADING_SLIPPAGE when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slippage_bps: Option<u32>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -30,4 +54,19 @@ pub struct MmSettings {
     pub max_delay_time: Option<u64>,
     pub min_retain_token: Option<u32>,
     pub max_retain_token: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_retain_percent: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_retain_percent: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_daily_volume_eth: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_wallet_volume_eth: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub buy_sell_ratio: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub action_randomness: Option<f32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub slippage_bps: Option<u32>,
+    pub start_index: Option<u32>,
 }
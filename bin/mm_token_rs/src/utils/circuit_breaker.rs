@@ -0,0 +1,147 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use mm_token_utils::env::get_env;
+
+use crate::core::MessageTransportService;
+
+#[derive(Debug, Default)]
+struct CircuitBreakerState {
+    consecutive_failures: u32,
+    paused_until: Option<Instant>,
+}
+
+/**
+ * pauses a service after `CIRCUIT_BREAKER_THRESHOLD` consecutive failed txs for `CIRCUIT_BREAKER_COOLDOWN_SECS`,
+ * clones share the state so concurrent trades of the same service trip it together
+ */
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    service_name: String,
+    threshold: u32,
+    cooldown: Duration,
+    state: Arc<Mutex<CircuitBreakerState>>,
+    message_transport_service: MessageTransportService,
+}
+
+impl CircuitBreaker {
+    /// `threshold` 0 disables the circuit breaker
+    pub fn new(service_name: &str, threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            service_name: service_name.to_string(),
+            threshold,
+            cooldown,
+            state: Arc::new(Mutex::new(CircuitBreakerState::default())),
+            message_transport_service: MessageTransportService::new(),
+        }
+    }
+
+    pub fn from_env(service_name: &str) -> Self {
+        let threshold: u32 = get_env("CIRCUIT_BREAKER_THRESHOLD", Some("5".to_string()))
+            .parse()
+            .unwrap();
+        let cooldown_secs: u64 = get_env("CIRCUIT_BREAKER_COOLDOWN_SECS", Some("600".to_string()))
+            .parse()
+            .unwrap();
+        Self::new(service_name, threshold, Duration::from_secs(cooldown_secs))
+    }
+
+    pub fn record_success(&self) {
+        self.state.lock().unwrap().consecutive_failures = 0;
+    }
+
+    /// returns true when this failure trips the breaker, the alert is sent once per trip
+    pub async fn record_failure(&self, reason: &str) -> bool {
+        if self.threshold == 0 {
+            return false;
+        }
+        let consecutive_failures = {
+            let mut state = self.state.lock().unwrap();
+            if state.paused_until.is_some() {
+                return false;
+            }
+            state.consecutive_failures += 1;
+            if state.consecutive_failures < self.threshold {
+                return false;
+            }
+            state.paused_until = Some(Instant::now() + self.cooldown);
+            state.consecutive_failures
+        };
+
+        log::error!(
+            "[{}] circuit breaker tripped after {:?} consecutive failed txs, pause {:?}: {}",
+            self.service_name,
+            consecutive_failures,
+            self.cooldown,
+            reason
+        );
+        let message = format!(
+            "[{}] Circuit breaker tripped \nConsecutive failed txs: {:?} \nTrading paused for {:?} \nLast failure: {}",
+            self.service_name, consecutive_failures, self.cooldown, reason
+        );
+        self.message_transport_service
+            .send_critical_message(message)
+            .await;
+        true
+    }
+
+    /// false once the cooldown elapsed, the failure counter starts over
+    pub fn is_paused(&self) -> bool {
+        self.remaining_pause().is_some()
+    }
+
+    /// sleep until the cooldown elapsed, returns immediately when not paused
+    pub async fn wait_until_resumed(&self) {
+        while let Some(remaining_pause) = self.remaining_pause() {
+            log::warn!(
+                "[{}] paused by circuit breaker, resume in {:?}",
+                self.service_name,
+                remaining_pause
+            );
+            tokio::time::sleep(remaining_pause).await;
+        }
+    }
+
+    fn remaining_pause(&self) -> Option<Duration> {
+        let mut state = self.state.lock().unwrap();
+        let paused_until = state.paused_until?;
+        let now = Instant::now();
+        if now >= paused_until {
+            log::info!("[{}] circuit breaker reset", self.service_name);
+            *state = CircuitBreakerState::default();
+            return None;
+        }
+        Some(paused_until - now)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn circuit_breaker_trips_after_threshold_and_resets_after_cooldown() {
+        let circuit_breaker = CircuitBreaker::new("Test", 3, Duration::from_millis(50));
+
+        assert!(!circuit_breaker.record_failure("reverted").await);
+        // a success in between starts the count over
+        circuit_breaker.record_success();
+        assert!(!circuit_breaker.record_failure("reverted").await);
+        assert!(!circuit_breaker.record_failure("reverted").await);
+        assert!(!circuit_breaker.is_paused());
+        assert!(circuit_breaker.record_failure("reverted").await);
+        assert!(circuit_breaker.is_paused());
+        // failures while paused do not trip it again
+        assert!(!circuit_breaker.clone().record_failure("reverted").await);
+
+        circuit_breaker.wait_until_resumed().await;
+        assert!(!circuit_breaker.is_paused());
+        assert!(!circuit_breaker.record_failure("reverted").await);
+
+        let disabled = CircuitBreaker::new("Test", 0, Duration::from_millis(50));
+        assert!(!disabled.record_failure("reverted").await);
+        assert!(!disabled.is_paused());
+    }
+}
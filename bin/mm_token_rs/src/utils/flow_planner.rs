@@ -0,0 +1,234 @@
+use std::collections::VecDeque;
+
+use anyhow::anyhow;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::types::MmSettings;
+
+// half random, half correcting the realized ratio when `actionRandomness` is missing
+const DEFAULT_ACTION_RANDOMNESS: f64 = 0.5;
+
+/// what a market maker wallet does in one iteration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MmAction {
+    Buy,
+    Sell,
+    BuyAndSell,
+}
+
+impl MmAction {
+    pub fn is_buy(&self) -> bool {
+        matches!(self, MmAction::Buy | MmAction::BuyAndSell)
+    }
+
+    pub fn is_sell(&self) -> bool {
+        matches!(self, MmAction::Sell | MmAction::BuyAndSell)
+    }
+}
+
+/**
+ * volumes and token holders of a group so far, persisted so a restart keeps converging
+ */
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlowState {
+    pub bought: f64,
+    pub sold: f64,
+    // group wallet indexes still holding the tokens of a buy only iteration, oldest first
+    pub token_holders: VecDeque<u32>,
+}
+
+/**
+ * picks the action of each market maker iteration so the buy share of the group volume converges
+ * to `buySellRatio`, buys are counted in the eth size drawn for each iteration, sells in the eth
+ * they received. A sell only iteration sells the tokens of an earlier buy only wallet
+ */
+#[derive(Debug, Clone)]
+pub struct FlowPlanner {
+    buy_ratio: f64,
+    action_randomness: f64,
+    state: FlowState,
+}
+
+impl FlowPlanner {
+    pub fn new(buy_ratio: f64, action_randomness: f64) -> anyhow::Result<Self> {
+        if !(0.0..=1.0).contains(&buy_ratio) {
+            return Err(anyhow!("buySellRatio {:?} not within 0..=1", buy_ratio));
+        }
+        if !(0.0..=1.0).contains(&action_randomness) {
+            return Err(anyhow!(
+                "actionRandomness {:?} not within 0..=1",
+                action_randomness
+            ));
+        }
+
+        Ok(Self {
+            buy_ratio,
+            action_randomness,
+            state: FlowState::default(),
+        })
+    }
+
+    /// continues from the state saved by an earlier run
+    pub fn with_state(mut self, state: FlowState) -> Self {
+        self.state = state;
+        self
+    }
+
+    pub fn state(&self) -> &FlowState {
+        &self.state
+    }
+
+    /// none keeps every iteration a buy then a sell
+    pub fn from_settings(mm_settings: &MmSettings) -> anyhow::Result<Option<Self>> {
+        let Some(buy_ratio) = mm_settings.buy_sell_ratio else {
+            return Ok(None);
+        };
+        let action_randomness = mm_settings
+            .action_randomness
+            .map_or(DEFAULT_ACTION_RANDOMNESS, f64::from);
+
+        Self::new(buy_ratio.into(), action_randomness).map(Some)
+    }
+
+    /// buy share of the volume so far, none before the first trade
+    pub fn realized_buy_ratio(&self) -> Option<f64> {
+        let volume = self.state.bought + self.state.sold;
+        (volume > 0.0).then(|| self.state.bought / volume)
+    }
+
+    /// a weighted draw with probability `action_randomness`, otherwise the action of `volume`
+    /// bringing the realized ratio closest to the target. A sell only action needs a token holder,
+    /// it turns into a buy and sell without one
+    pub fn next_action<R: Rng>(&self, volume: f64, rng: &mut R) -> MmAction {
+        let action = self.draw_action(volume, rng);
+        if action == MmAction::Sell && self.state.token_holders.is_empty() {
+            return MmAction::BuyAndSell;
+        }
+        action
+    }
+
+    fn draw_action<R: Rng>(&self, volume: f64, rng: &mut R) -> MmAction {
+        if rng.gen_bool(self.action_randomness) {
+            return self.random_action(rng);
+        }
+
+        let distance = |action: MmAction| {
+            let bought = self.state.bought + if action.is_buy() { volume } else { 0.0 };
+            let sold = self.state.sold + if action.is_sell() { volume } else { 0.0 };
+            (bought / (bought + sold) - self.buy_ratio).abs()
+        };
+        // ties keep the lockstep
+        [MmAction::BuyAndSell, MmAction::Buy, MmAction::Sell]
+            .into_iter()
+            .min_by(|a, b| distance(*a).total_cmp(&distance(*b)))
+            .unwrap()
+    }
+
+    pub fn record_buy(&mut self, volume: f64) {
+        self.state.bought += volume;
+    }
+
+    pub fn record_sell(&mut self, volume: f64) {
+        self.state.sold += volume;
+    }
+
+    /// the wallet at `wallet_index` kept the tokens of a buy only iteration
+    pub fn record_token_holder(&mut self, wallet_index: u32) {
+        self.state.token_holders.push_back(wallet_index);
+    }
+
+    /// oldest wallet holding tokens to sell
+    pub fn next_token_holder(&self) -> Option<u32> {
+        self.state.token_holders.front().copied()
+    }
+
+    /// the tokens of the oldest holder are sold
+    pub fn release_token_holder(&mut self) {
+        self.state.token_holders.pop_front();
+    }
+
+    /// buys and sells drawn independently, the likelier side always happens
+    fn random_action<R: Rng>(&self, rng: &mut R) -> MmAction {
+        let scale = self.buy_ratio.max(1.0 - self.buy_ratio);
+        let is_buy = rng.gen_bool(self.buy_ratio / scale);
+        let is_sell = rng.gen_bool((1.0 - self.buy_ratio) / scale);
+        match (is_buy, is_sell) {
+            (true, false) => MmAction::Buy,
+            (false, true) => MmAction::Sell,
+            _ => MmAction::BuyAndSell,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn realized_buy_ratio_converges_to_the_target() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(7);
+        for (buy_ratio, action_randomness) in [(0.6, 0.0), (0.6, 0.5), (0.6, 1.0), (0.8, 0.5)] {
+            let mut flow_planner = FlowPlanner::new(buy_ratio, action_randomness).unwrap();
+            let mut actions = std::collections::HashSet::new();
+            for _ in 0..5_000 {
+                let volume = rng.gen_range(0.01..=0.05);
+                let action = flow_planner.next_action(volume, &mut rng);
+                if action.is_buy() {
+                    flow_planner.record_buy(volume);
+                }
+                if action.is_sell() {
+                    flow_planner.record_sell(volume);
+                }
+                match action {
+                    MmAction::Buy => flow_planner.record_token_holder(0),
+                    MmAction::Sell => flow_planner.release_token_holder(),
+                    MmAction::BuyAndSell => {}
+                }
+                actions.insert(action);
+            }
+
+            let realized = flow_planner.realized_buy_ratio().unwrap();
+            assert!(
+                (realized - buy_ratio).abs() < 0.02,
+                "{} {} realized {}",
+                buy_ratio,
+                action_randomness,
+                realized
+            );
+            // not a plain buy + sell lockstep
+            assert!(actions.len() > 1);
+        }
+
+        assert!(FlowPlanner::new(1.5, 0.5).is_err());
+        assert!(FlowPlanner::new(0.6, -0.1).is_err());
+    }
+
+    #[test]
+    fn sells_need_a_token_holder() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let flow_planner = FlowPlanner::new(0.3, 0.0).unwrap().with_state(FlowState {
+            bought: 1.0,
+            ..Default::default()
+        });
+        assert_eq!(
+            flow_planner.next_action(0.1, &mut rng),
+            MmAction::BuyAndSell
+        );
+
+        let mut flow_planner = flow_planner.with_state(FlowState {
+            bought: 1.0,
+            sold: 0.0,
+            token_holders: [3, 5].into(),
+        });
+        assert_eq!(flow_planner.next_action(0.1, &mut rng), MmAction::Sell);
+        assert_eq!(flow_planner.next_token_holder(), Some(3));
+        flow_planner.release_token_holder();
+        assert_eq!(flow_planner.next_token_holder(), Some(5));
+    }
+}
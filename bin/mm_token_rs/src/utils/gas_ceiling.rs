@@ -0,0 +1,133 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use ethers::{
+    types::U256,
+    utils::{format_units, parse_units},
+};
+use mm_token_utils::env::get_env;
+
+use crate::core::MessageTransportService;
+
+// skipped trades wait this long before checking the gas price again
+pub const GAS_CEILING_RECHECK_SECS: u64 = 10;
+
+/**
+ * `MAX_GAS_PRICE_GWEI` ceiling on the shared gas price, new buys and sells are skipped above it,
+ * clones share the paused state so a spike is logged and notified once
+ */
+#[derive(Debug, Clone)]
+pub struct GasCeiling {
+    max_gas_price: Option<U256>,
+    is_paused: Arc<AtomicBool>,
+    message_transport_service: MessageTransportService,
+}
+
+impl GasCeiling {
+    /// none disables the ceiling
+    pub fn new(max_gas_price: Option<U256>) -> Self {
+        Self {
+            max_gas_price,
+            is_paused: Arc::new(AtomicBool::new(false)),
+            message_transport_service: MessageTransportService::new(),
+        }
+    }
+
+    /// `MAX_GAS_PRICE_GWEI` (default empty, no ceiling)
+    pub fn from_env() -> Self {
+        let max_gas_price_gwei = get_env("MAX_GAS_PRICE_GWEI", Some("".to_string()));
+        if max_gas_price_gwei.is_empty() {
+            return Self::new(None);
+        }
+        let Ok(max_gas_price) = parse_units(&max_gas_price_gwei, "gwei") else {
+            panic!("MAX_GAS_PRICE_GWEI {:?} invalid", max_gas_price_gwei);
+        };
+        Self::new(Some(max_gas_price.into()))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.max_gas_price.is_some()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused.load(Ordering::Relaxed)
+    }
+
+    /// false above the ceiling, crossing it either way is logged and notified once
+    pub async fn is_acceptable(&self, gas_price: U256) -> bool {
+        let Some(max_gas_price) = self.max_gas_price else {
+            return true;
+        };
+        let is_acceptable = gas_price <= max_gas_price;
+        if self.is_paused.swap(!is_acceptable, Ordering::Relaxed) == is_acceptable {
+            let gas_price_gwei = format_units(gas_price, "gwei").unwrap_or_default();
+            let max_gas_price_gwei = format_units(max_gas_price, "gwei").unwrap_or_default();
+            let message = if is_acceptable {
+                log::info!(
+                    "[GasCeiling] gas price {} gwei back under {} gwei, trading resumed",
+                    gas_price_gwei,
+                    max_gas_price_gwei
+                );
+                format!(
+                    "Gas price {} gwei back under MAX_GAS_PRICE_GWEI {}, trading resumed",
+                    gas_price_gwei, max_gas_price_gwei
+                )
+            } else {
+                log::warn!(
+                    "[GasCeiling] gas price {} gwei above {} gwei, new buys and sells skipped",
+                    gas_price_gwei,
+                    max_gas_price_gwei
+                );
+                format!(
+                    "Gas price {} gwei above MAX_GAS_PRICE_GWEI {}, new buys and sells skipped",
+                    gas_price_gwei, max_gas_price_gwei
+                )
+            };
+            self.message_transport_service
+                .send_outcome_message(message, !is_acceptable)
+                .await;
+        }
+
+        is_acceptable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn gas_ceiling_skips_trades_above_max_gas_price() {
+        let gas_ceiling = GasCeiling::new(Some(parse_units(5, "gwei").unwrap().into()));
+
+        assert!(
+            gas_ceiling
+                .is_acceptable(parse_units(5, "gwei").unwrap().into())
+                .await
+        );
+        assert!(
+            !gas_ceiling
+                .is_acceptable(parse_units(12, "gwei").unwrap().into())
+                .await
+        );
+        assert!(gas_ceiling.is_paused());
+        // clones share the pause
+        assert!(gas_ceiling.clone().is_paused());
+        assert!(
+            gas_ceiling
+                .is_acceptable(parse_units(4, "gwei").unwrap().into())
+                .await
+        );
+        assert!(!gas_ceiling.is_paused());
+
+        let disabled = GasCeiling::new(None);
+        assert!(
+            disabled
+                .is_acceptable(parse_units(1_000, "gwei").unwrap().into())
+                .await
+        );
+        assert!(!disabled.is_enabled());
+    }
+}
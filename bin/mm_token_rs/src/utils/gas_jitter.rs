@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use ethers::{
+    providers::{Http, Provider},
+    types::U256,
+};
+use mm_token_utils::{
+    constants::EGasMode,
+    env::get_env,
+    utils::{get_gas_mode, Eip1559Fees},
+};
+use rand::Rng;
+
+use super::{cached_eip1559_fees, fetch_eip1559_fees};
+
+/**
+ * random ±`GAS_PRICE_JITTER_PERCENT` on the gas price of each tx, the system wallets do not all
+ * broadcast at the shared gas price, never below the base fee of the latest block.
+ * In the EIP-1559 mode the priority fee is jittered
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasJitter {
+    percent: u32,
+}
+
+impl GasJitter {
+    /// `percent` 0 disables the jitter
+    pub fn new(percent: u32) -> Self {
+        if percent > 100 {
+            panic!("GAS_PRICE_JITTER_PERCENT {:?} must be at most 100", percent);
+        }
+        Self { percent }
+    }
+
+    /// market maker txs are jittered by `GAS_PRICE_JITTER_PERCENT` (default 0),
+    /// buy and sell bot txs only with `GAS_PRICE_JITTER_BUY_SELL=true`
+    pub fn from_env(is_buy_sell: bool) -> Self {
+        let percent: u32 = get_env("GAS_PRICE_JITTER_PERCENT", Some("0".to_string()))
+            .parse()
+            .unwrap();
+        let is_enabled = !is_buy_sell
+            || get_env("GAS_PRICE_JITTER_BUY_SELL", Some("false".to_string()))
+                .parse::<bool>()
+                .unwrap();
+        Self::new(if is_enabled { percent } else { 0 })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.percent > 0
+    }
+
+    pub fn jitter(&self, gas_price: U256, min_gas_price: U256, rng: &mut impl Rng) -> U256 {
+        if !self.is_enabled() {
+            return gas_price;
+        }
+        let band_bps = i64::from(self.percent) * 100;
+        let delta_bps = rng.gen_range(-band_bps..=band_bps);
+        let jittered = gas_price * U256::from((10_000 + delta_bps) as u64) / U256::from(10_000);
+        jittered.max(min_gas_price)
+    }
+
+    /// none when disabled or the fees cannot be read, the tx keeps its gas price.
+    /// The base fee is the one cached by `GasPrice`, the fee history is only read before its first fetch
+    pub async fn apply(&self, gas_price: U256, http_provider: Arc<Provider<Http>>) -> Option<U256> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let fees = match cached_eip1559_fees() {
+            Some(fees) => fees,
+            None => match fetch_eip1559_fees(http_provider).await {
+                Ok(fees) => fees,
+                Err(err) => {
+                    log::warn!("[GasJitter] base fee unavailable, no jitter: {:?}", err);
+                    return None;
+                }
+            },
+        };
+        Some(self.jitter_by_gas_mode(get_gas_mode(), gas_price, fees, &mut rand::thread_rng()))
+    }
+
+    /// an EIP-1559 `gas_price` is a max fee, only its tip is jittered and the base fee headroom is kept
+    fn jitter_by_gas_mode(
+        &self,
+        gas_mode: EGasMode,
+        gas_price: U256,
+        fees: Eip1559Fees,
+        rng: &mut impl Rng,
+    ) -> U256 {
+        match gas_mode {
+            EGasMode::Legacy => self.jitter(gas_price, fees.base_fee, rng),
+            EGasMode::Eip1559 => {
+                let base_fee_headroom = fees.base_fee * 2;
+                let priority_fee = gas_price.saturating_sub(base_fee_headroom);
+                base_fee_headroom + self.jitter(priority_fee, U256::zero(), rng)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn jittered_gas_prices_stay_within_the_band() {
+        let gas_price = U256::from(1_000_000_000u64);
+        let mut rng = rand::thread_rng();
+        let gas_jitter = GasJitter::new(10);
+
+        let jittered: Vec<U256> = (0..1_000)
+            .map(|_| gas_jitter.jitter(gas_price, U256::zero(), &mut rng))
+            .collect();
+        assert!(jittered.iter().all(|price| (U256::from(900_000_000u64)
+            ..=U256::from(1_100_000_000u64))
+            .contains(price)));
+        let distinct: HashSet<U256> = jittered.iter().copied().collect();
+        assert!(distinct.len() > 100, "{}", distinct.len());
+
+        // never below the base fee
+        let base_fee = U256::from(990_000_000u64);
+        assert!((0..1_000).all(|_| gas_jitter.jitter(gas_price, base_fee, &mut rng) >= base_fee));
+
+        // disabled keeps the shared gas price
+        assert_eq!(
+            GasJitter::new(0).jitter(gas_price, U256::zero(), &mut rng),
+            gas_price
+        );
+    }
+
+    #[test]
+    fn eip1559_jitter_moves_the_priority_fee_only() {
+        let mut rng = rand::thread_rng();
+        let gas_jitter = GasJitter::new(10);
+        let fees = Eip1559Fees {
+            base_fee: U256::from(1_000_000_000u64),
+            priority_fee: U256::from(100_000_000u64),
+        };
+
+        let jittered: Vec<U256> = (0..1_000)
+            .map(|_| {
+                gas_jitter.jitter_by_gas_mode(EGasMode::Eip1559, fees.max_fee(), fees, &mut rng)
+            })
+            .collect();
+        // the max fee keeps twice the base fee, the tip stays within ±10%
+        assert!(jittered.iter().all(|max_fee| (U256::from(2_090_000_000u64)
+            ..=U256::from(2_110_000_000u64))
+            .contains(max_fee)));
+        let distinct: HashSet<U256> = jittered.iter().copied().collect();
+        assert!(distinct.len() > 100, "{}", distinct.len());
+
+        // a legacy gas price is jittered as a whole, floored at the base fee
+        assert!((0..1_000).all(|_| gas_jitter.jitter_by_gas_mode(
+            EGasMode::Legacy,
+            fees.base_fee,
+            fees,
+            &mut rng
+        ) >= fees.base_fee));
+    }
+}
@@ -0,0 +1,89 @@
+use ethers::{
+    providers::Middleware,
+    types::{transaction::eip2718::TypedTransaction, U256},
+};
+use mm_token_utils::env::get_env;
+
+// estimates miss state changes between estimation and inclusion (tax swap back, price moves)
+const GAS_ESTIMATE_BUFFER_PERCENT: u64 = 120;
+
+/**
+ * gas limit per operation, `GAS_LIMIT_BUY` / `GAS_LIMIT_SELL` / `GAS_LIMIT_APPROVE` / `GAS_LIMIT_TRANSFER`,
+ * used as is when the node cannot estimate the tx
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GasLimits {
+    pub buy: U256,
+    pub sell: U256,
+    pub approve: U256,
+    pub transfer: U256,
+}
+
+impl GasLimits {
+    /// `swap_gas_limit` is the default of buy and sell, 500k for v2 routers and 700k for v3 routers
+    pub fn from_env(swap_gas_limit: u64) -> Self {
+        let read = |name: &str, default: u64| -> U256 {
+            let gas_limit = get_env(name, Some("".to_string()));
+            if gas_limit.is_empty() {
+                return default.into();
+            }
+            gas_limit.parse::<u64>().unwrap().into()
+        };
+        Self {
+            buy: read("GAS_LIMIT_BUY", swap_gas_limit),
+            sell: read("GAS_LIMIT_SELL", swap_gas_limit),
+            approve: read("GAS_LIMIT_APPROVE", 500_000),
+            transfer: read("GAS_LIMIT_TRANSFER", 500_000),
+        }
+    }
+
+    /// `eth_estimateGas` plus a 20% buffer, `default_gas_limit` when the estimate fails
+    /// (e.g. txs prepared before trading is enabled)
+    pub async fn estimate_or_default<M: Middleware>(
+        provider: &M,
+        tx: &TypedTransaction,
+        default_gas_limit: U256,
+    ) -> U256 {
+        match provider.estimate_gas(tx, None).await {
+            Ok(gas_estimate) => gas_estimate * GAS_ESTIMATE_BUFFER_PERCENT / 100,
+            Err(err) => {
+                log::debug!(
+                    "[GasLimits] estimate failed, use default gas limit {:?}: {:?}",
+                    default_gas_limit,
+                    err
+                );
+                default_gas_limit
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::types::{Address, TransactionRequest};
+    use provider_utils::mock_rpc::MockRpc;
+
+    #[tokio::test]
+    async fn gas_limit_falls_back_to_default_on_failed_estimate() {
+        let mock_rpc = MockRpc::new();
+        let provider = mock_rpc.provider();
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::random())
+            .value(U256::from(1000))
+            .into();
+        let default_gas_limit = U256::from(500_000);
+
+        // the estimate reverts
+        assert_eq!(
+            GasLimits::estimate_or_default(&provider, &tx, default_gas_limit).await,
+            default_gas_limit
+        );
+
+        mock_rpc.push_responses("eth_estimateGas", [U256::from(100_000)]);
+        assert_eq!(
+            GasLimits::estimate_or_default(&provider, &tx, default_gas_limit).await,
+            U256::from(120_000)
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use std::sync::Arc;
+
+use ethers::{
+    abi::AbiDecode,
+    contract::{
+        multicall_contract::{Call3Value, Multicall3},
+        MULTICALL_ADDRESS,
+    },
+    providers::Middleware,
+    types::{Address, Bytes, U256},
+    utils::parse_ether,
+};
+use mm_token_utils::abi::{MemeTokenAbigen, UniswapV2Router02Abigen};
+
+use super::compute_deadline;
+
+/**
+ * sellability of a freshly listed token, a tiny v2 buy then sell of what was bought simulated in eth_call,
+ * a token whose sell reverts or returns no WETH is a honeypot
+ */
+#[derive(Debug, Clone)]
+pub struct HoneypotCheck<M> {
+    provider: Arc<M>,
+    uniswapv2_router_address: Address,
+    weth_address: Address,
+}
+
+impl<M: Middleware + 'static> HoneypotCheck<M> {
+    pub fn new(provider: Arc<M>, uniswapv2_router_address: Address, weth_address: Address) -> Self {
+        Self {
+            provider,
+            uniswapv2_router_address,
+            weth_address,
+        }
+    }
+
+    /// WETH received by the simulated sell, an error when the token cannot be bought or sold back
+    pub async fn check(&self, token_address: Address) -> anyhow::Result<U256> {
+        let probe_amount = parse_ether("0.0001").unwrap();
+        let buy_path = vec![self.weth_address, token_address];
+        let sell_path = vec![token_address, self.weth_address];
+        let router =
+            UniswapV2Router02Abigen::new(self.uniswapv2_router_address, self.provider.clone());
+        let token = MemeTokenAbigen::new(token_address, self.provider.clone());
+        let weth = MemeTokenAbigen::new(self.weth_address, self.provider.clone());
+        let deadline = compute_deadline(60);
+
+        let buy_call = Call3Value {
+            target: self.uniswapv2_router_address,
+            allow_failure: false,
+            value: probe_amount,
+            call_data: router
+                .swap_exact_eth_for_tokens_supporting_fee_on_transfer_tokens(
+                    U256::zero(),
+                    buy_path,
+                    MULTICALL_ADDRESS,
+                    deadline,
+                )
+                .calldata()
+                .unwrap(),
+        };
+        let view_call = |target: Address, call_data| Call3Value {
+            target,
+            allow_failure: false,
+            value: U256::zero(),
+            call_data,
+        };
+
+        let results = self
+            .aggregate(
+                vec![
+                    buy_call.clone(),
+                    view_call(
+                        token_address,
+                        token.balance_of(MULTICALL_ADDRESS).calldata().unwrap(),
+                    ),
+                ],
+                probe_amount,
+            )
+            .await
+            .map_err(|err| anyhow::anyhow!("buy simulation reverted: {}", err))?;
+        let bought = U256::decode(&results[1])?;
+        if bought.is_zero() {
+            return Err(anyhow::anyhow!("buy simulation returned no token"));
+        }
+
+        // state is not kept between eth_calls, buy again then sell what was bought
+        let results = self
+            .aggregate(
+                vec![
+                    buy_call,
+                    view_call(
+                        token_address,
+                        token
+                            .approve(self.uniswapv2_router_address, U256::MAX)
+                            .calldata()
+                            .unwrap(),
+                    ),
+                    view_call(
+                        self.weth_address,
+                        weth.balance_of(MULTICALL_ADDRESS).calldata().unwrap(),
+                    ),
+                    view_call(
+                        self.uniswapv2_router_address,
+                        router
+                            .swap_exact_tokens_for_tokens_supporting_fee_on_transfer_tokens(
+                                bought,
+                                U256::zero(),
+                                sell_path,
+                                MULTICALL_ADDRESS,
+                                deadline,
+                            )
+                            .calldata()
+                            .unwrap(),
+                    ),
+                    view_call(
+                        self.weth_address,
+                        weth.balance_of(MULTICALL_ADDRESS).calldata().unwrap(),
+                    ),
+                ],
+                probe_amount,
+            )
+            .await
+            .map_err(|err| anyhow::anyhow!("sell simulation reverted: {}", err))?;
+        let sold = U256::decode(&results[4])?.saturating_sub(U256::decode(&results[2])?);
+        if sold.is_zero() {
+            return Err(anyhow::anyhow!("sell simulation returned no WETH"));
+        }
+
+        Ok(sold)
+    }
+
+    async fn aggregate(&self, calls: Vec<Call3Value>, value: U256) -> anyhow::Result<Vec<Bytes>> {
+        let multicall = Multicall3::new(MULTICALL_ADDRESS, self.provider.clone());
+        // weth holds enough eth to fund the simulated buy
+        let results = multicall
+            .aggregate_3_value(calls)
+            .from(self.weth_address)
+            .value(value)
+            .call()
+            .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| result.return_data)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::abi::Token;
+    use provider_utils::mock_rpc::MockRpc;
+
+    #[tokio::test]
+    async fn honeypot_check_blocks_unsellable_token() {
+        let encode_results = |return_data: Vec<Vec<u8>>| {
+            let results = return_data
+                .into_iter()
+                .map(|data| Token::Tuple(vec![Token::Bool(true), Token::Bytes(data)]))
+                .collect();
+            Bytes::from(ethers::abi::encode(&[Token::Array(results)]))
+        };
+        let amount = |amount: u64| ethers::abi::encode(&[Token::Uint(U256::from(amount))]);
+        let bought = encode_results(vec![Vec::new(), amount(1_000)]);
+        let check = |mock_rpc: &MockRpc| {
+            HoneypotCheck::new(
+                Arc::new(mock_rpc.provider()),
+                Address::random(),
+                Address::random(),
+            )
+        };
+
+        // buy, then buy + approve + sell
+        let mock_rpc = MockRpc::new();
+        mock_rpc.push_responses(
+            "eth_call",
+            [
+                bought.clone(),
+                encode_results(vec![
+                    Vec::new(),
+                    ethers::abi::encode(&[Token::Bool(true)]),
+                    amount(5),
+                    Vec::new(),
+                    amount(95),
+                ]),
+            ],
+        );
+        assert_eq!(
+            check(&mock_rpc).check(Address::random()).await.unwrap(),
+            U256::from(90)
+        );
+
+        // the token reverts on sell
+        let mock_rpc = MockRpc::new();
+        mock_rpc.push_responses("eth_call", [bought]);
+        mock_rpc.push_error(
+            "eth_call",
+            3,
+            "execution reverted: TransferHelper: TRANSFER_FROM_FAILED",
+        );
+        let err = check(&mock_rpc).check(Address::random()).await.unwrap_err();
+        assert!(
+            err.to_string().contains("sell simulation reverted"),
+            "{}",
+            err
+        );
+    }
+}
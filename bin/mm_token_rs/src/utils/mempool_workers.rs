@@ -0,0 +1,139 @@
+use std::{future::Future, sync::Arc};
+
+use ethers::types::Transaction;
+use tokio::sync::{mpsc, Mutex};
+
+use crate::constants::MEMPOOL_QUEUE_SIZE;
+
+/**
+ * spawn `workers` tasks draining one bounded queue of mempool txs, each queued tx is processed once.
+ * A slow tx only holds its own worker, the others keep taking the next txs
+ */
+pub fn spawn_mempool_workers<F, Fut>(
+    service_name: &'static str,
+    workers: usize,
+    process_tx: F,
+) -> mpsc::Sender<Transaction>
+where
+    F: Fn(Transaction) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let (tx_sender, tx_receiver) = mpsc::channel::<Transaction>(MEMPOOL_QUEUE_SIZE);
+    let tx_receiver = Arc::new(Mutex::new(tx_receiver));
+    for worker_index in 0..workers {
+        let tx_receiver = tx_receiver.clone();
+        let process_tx = process_tx.clone();
+        tokio::spawn(async move {
+            loop {
+                // the lock is released before processing, only the recv is serialized
+                let Some(tx) = tx_receiver.lock().await.recv().await else {
+                    break;
+                };
+                let tx_hash = tx.hash;
+                log::debug!(
+                    "[{}] mempool worker {:?} picked tx {:?}",
+                    service_name,
+                    worker_index,
+                    tx_hash
+                );
+                if let Err(err) = process_tx(tx).await {
+                    log::warn!(
+                        "[{}] mempool worker {:?} failed on tx {:?}: {:?}",
+                        service_name,
+                        worker_index,
+                        tx_hash,
+                        err
+                    );
+                }
+            }
+        });
+    }
+    tx_sender
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use ethers::types::H256;
+    use tokio::sync::Barrier;
+
+    #[tokio::test]
+    async fn slow_txs_are_processed_concurrently_and_once() {
+        let workers = 3;
+        // each tx waits until every worker holds one, a serial pool would never get past the first
+        let barrier = Arc::new(Barrier::new(workers));
+        let processed = Arc::new(Mutex::new(Vec::new()));
+        let tx_sender = spawn_mempool_workers("Test", workers, {
+            let barrier = barrier.clone();
+            let processed = processed.clone();
+            move |tx: Transaction| {
+                let barrier = barrier.clone();
+                let processed = processed.clone();
+                async move {
+                    barrier.wait().await;
+                    processed.lock().await.push(tx.hash);
+                    Ok(())
+                }
+            }
+        });
+
+        let tx_hashes: Vec<H256> = (0..workers * 2).map(|_| H256::random()).collect();
+        for tx_hash in &tx_hashes {
+            let tx = Transaction {
+                hash: *tx_hash,
+                ..Default::default()
+            };
+            tx_sender.send(tx).await.unwrap();
+        }
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while processed.lock().await.len() < tx_hashes.len() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("the workers did not process the txs concurrently");
+
+        let mut processed = processed.lock().await.clone();
+        processed.sort();
+        let mut tx_hashes = tx_hashes;
+        tx_hashes.sort();
+        assert_eq!(processed, tx_hashes);
+    }
+
+    #[tokio::test]
+    async fn a_failed_tx_does_not_stop_its_worker() {
+        let processed = Arc::new(Mutex::new(0));
+        let tx_sender = spawn_mempool_workers("Test", 1, {
+            let processed = processed.clone();
+            move |tx: Transaction| {
+                let processed = processed.clone();
+                async move {
+                    *processed.lock().await += 1;
+                    if tx.nonce.is_zero() {
+                        anyhow::bail!("pair not found");
+                    }
+                    Ok(())
+                }
+            }
+        });
+
+        for nonce in 0..2u64 {
+            let tx = Transaction {
+                nonce: nonce.into(),
+                ..Default::default()
+            };
+            tx_sender.send(tx).await.unwrap();
+        }
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while *processed.lock().await < 2 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("the worker stopped after a failed tx");
+    }
+}
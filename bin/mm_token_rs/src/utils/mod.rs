@@ -0,0 +1,2150 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::Read,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, LazyLock, Mutex},
+    time::Duration,
+};
+
+use cached::{Cached, TimedCache};
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    signers::Signer,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Bytes, Log,
+        TransactionReceipt, TransactionRequest, H256, U256, U64,
+    },
+    utils::{format_ether, format_units, keccak256, parse_ether, parse_units, rlp::Rlp},
+};
+use futures::future::{join_all, try_join_all};
+use mm_token_utils::{
+    abi::{MemeTokenAbigen, Permit2Abigen},
+    constants::{EGasMode, BLOXROUTE_TIP_ADDRESSES, NATIVE_SYMBOLS, PERMIT2_ADDRESS},
+    env::get_env,
+    signer::WalletSigner,
+    utils::{
+        compute_eip1559_fees, get_gas_mode, load_mnemonic_wallet, load_private_key_wallet,
+        to_eip1559_tx, to_legacy_tx, to_signed_tx, Eip1559Fees,
+    },
+};
+use provider_utils::enums::ENetwork;
+use rand::{seq::SliceRandom, Rng};
+use rust_decimal::Decimal;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::{
+    constants::{EBuyDistribution, TokenTaxMode},
+    types::{
+        DisperseEthAmount, LaunchTxPosition, MmConfig, MmSettings, PanicSellPlan, SellApproval,
+        TokenInfo,
+    },
+};
+
+mod circuit_breaker;
+mod flow_planner;
+mod gas_ceiling;
+mod gas_jitter;
+mod gas_limits;
+mod honeypot_check;
+mod mempool_workers;
+mod nonce_manager;
+mod pair_override;
+mod pnl_ledger;
+mod price_oracle;
+mod reorg_watcher;
+mod revert_reason;
+mod shutdown;
+mod tax_probe;
+mod tx_dedup;
+mod tx_receipts;
+mod v2_swap;
+mod volume_ledger;
+mod wallet_context;
+
+pub use circuit_breaker::*;
+pub use flow_planner::*;
+pub use gas_ceiling::*;
+pub use gas_jitter::*;
+pub use gas_limits::*;
+pub use honeypot_check::*;
+pub use mempool_workers::*;
+pub use nonce_manager::*;
+pub use pair_override::*;
+pub use pnl_ledger::*;
+pub use price_oracle::*;
+pub use reorg_watcher::*;
+pub use revert_reason::*;
+pub use shutdown::*;
+pub use tax_probe::*;
+pub use tx_dedup::*;
+pub use tx_receipts::*;
+pub use v2_swap::*;
+pub use volume_ledger::*;
+pub use wallet_context::*;
+
+pub fn load_system_wallet_address(
+    mnemonic: &str,
+    wallets_size: u32,
+    network: ENetwork,
+) -> anyhow::Result<Vec<Address>> {
+    let mut addresses = Vec::new();
+    for index in 0..wallets_size {
+        let wallet = load_mnemonic_wallet(mnemonic, index, network, None)?;
+        let wallet_address = wallet.address();
+        addresses.push(wallet_address)
+    }
+
+    Ok(addresses)
+}
+
+pub async fn compute_all_system_wallets(
+    auto_buyer_mnemonic: &str,
+    auto_buyer_wallets_count: u32,
+    buyer_mnemonic: &str,
+    buyer_wallets_count: u32,
+    seller_mnemonic: &str,
+    seller_wallets_count: u32,
+    network: ENetwork,
+) -> anyhow::Result<(Vec<Address>, Vec<Address>, Vec<Address>, Vec<Address>)> {
+    let auto_buyer_system_wallets =
+        load_system_wallet_address(auto_buyer_mnemonic, auto_buyer_wallets_count, network)?;
+    let buyer_system_wallets =
+        load_system_wallet_address(buyer_mnemonic, buyer_wallets_count, network)?;
+    let seller_system_wallets =
+        load_system_wallet_address(seller_mnemonic, seller_wallets_count, network)?;
+
+    let mm_config = get_mm_config();
+    let default_settings = mm_config.default_settings.clone();
+    // get mnemonic and number of wallet market maker
+    let mm_wallet_settings_list: Vec<(String, u32)> = mm_config
+        .groups
+        .clone()
+        .iter()
+        .map(|settings| {
+            (
+                settings.mnemonic.clone(),
+                settings
+                    .max_wallets_count
+                    .unwrap_or(default_settings.max_wallets_count),
+            )
+        })
+        .collect();
+
+    let mut market_maker_system_wallets = Vec::new();
+    for (mm_mnemonic, wallet_count) in mm_wallet_settings_list {
+        let mm_mnemonic_wallets = load_system_wallet_address(&mm_mnemonic, wallet_count, network)?;
+        market_maker_system_wallets.extend(mm_mnemonic_wallets);
+    }
+
+    Ok((
+        auto_buyer_system_wallets,
+        buyer_system_wallets,
+        seller_system_wallets,
+        market_maker_system_wallets,
+    ))
+}
+
+pub fn format_bmk(number: &str, dp: u32) -> Result<String, rust_decimal::Error> {
+    let decimal_number = Decimal::from_str(number)?;
+    if decimal_number >= Decimal::from(1_000_000_000) {
+        let x = (decimal_number / Decimal::from(1_000_000_000)).round_dp(dp);
+        return Ok(format!("{}B", x));
+    }
+    if decimal_number >= Decimal::from(1_000_000) {
+        let x = (decimal_number / Decimal::from(1_000_000)).round_dp(dp);
+        return Ok(format!("{}M", x));
+    }
+    if decimal_number >= Decimal::from(1_000) {
+        let x = (decimal_number / Decimal::from(1_000)).round_dp(dp);
+        return Ok(format!("{}K", x));
+    }
+
+    Ok(decimal_number.round_dp(dp).to_string())
+}
+
+// SI suffixes of token amounts, largest first
+const TOKEN_AMOUNT_SUFFIXES: [(usize, &str); 4] = [(12, "T"), (9, "B"), (6, "M"), (3, "K")];
+
+/**
+ * token amount with the K/M/B/T suffix of its magnitude and at most 3 decimals, e.g. `1.5M`,
+ * max allowances stay readable as a (huge) number of T
+ */
+pub fn format_token_amount(amount: U256, decimals: u8) -> String {
+    let (exponent, suffix) = TOKEN_AMOUNT_SUFFIXES
+        .into_iter()
+        .find(|(exponent, _)| amount >= U256::exp10(decimals as usize + exponent))
+        .unwrap_or((0, ""));
+    let value: f64 = format_units(amount, decimals as usize + exponent)
+        .unwrap()
+        .parse()
+        .unwrap();
+    let value = format!("{:.3}", value);
+    let value = value.trim_end_matches('0').trim_end_matches('.');
+
+    format!("{}{}", value, suffix)
+}
+
+pub fn read_json_file(file_path: &str) -> std::io::Result<String> {
+    let path = Path::new(file_path);
+    let mut file = File::open(path)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    Ok(content)
+}
+
+pub fn get_mm_config() -> MmConfig {
+    read_mm_config("mm_config.json")
+}
+
+pub fn read_mm_config(file_path: &str) -> MmConfig {
+    let json_content = read_json_file(file_path).expect("Failed to read JSON file");
+    let mm_config: MmConfig = serde_json::from_str(&json_content).expect("Failed to parse JSON");
+    mm_config
+}
+
+// mm groups run concurrently and share the state file
+static MM_STATE_LOCK: Mutex<()> = Mutex::new(());
+
+/**
+ * wallet index each mm group has reached, keyed by the group's first wallet address
+ */
+pub fn get_mm_resume_index(mm_state_path: &str, group_address: &Address) -> Option<u32> {
+    let _lock = MM_STATE_LOCK.lock().unwrap();
+    let json_content = read_json_file(mm_state_path).ok()?;
+    let mm_state: HashMap<Address, u32> = serde_json::from_str(&json_content).ok()?;
+    mm_state.get(group_address).copied()
+}
+
+pub fn save_mm_resume_index(
+    mm_state_path: &str,
+    group_address: Address,
+    index: u32,
+) -> anyhow::Result<()> {
+    let _lock = MM_STATE_LOCK.lock().unwrap();
+    let mut mm_state: HashMap<Address, u32> = match read_json_file(mm_state_path) {
+        Ok(json_content) => serde_json::from_str(&json_content)?,
+        Err(_) => HashMap::new(),
+    };
+    mm_state.insert(group_address, index);
+    std::fs::write(mm_state_path, serde_json::to_string_pretty(&mm_state)?)?;
+    Ok(())
+}
+
+// mm groups run concurrently and share the flow state file
+static MM_FLOW_STATE_LOCK: Mutex<()> = Mutex::new(());
+
+/**
+ * flow state of each mm group with a `buySellRatio`, keyed by the group's first wallet address
+ */
+pub fn get_mm_flow_state(flow_state_path: &str, group_address: &Address) -> Option<FlowState> {
+    let _lock = MM_FLOW_STATE_LOCK.lock().unwrap();
+    let json_content = read_json_file(flow_state_path).ok()?;
+    let mut flow_states: HashMap<Address, FlowState> = serde_json::from_str(&json_content).ok()?;
+    flow_states.remove(group_address)
+}
+
+pub fn save_mm_flow_state(
+    flow_state_path: &str,
+    group_address: Address,
+    flow_state: &FlowState,
+) -> anyhow::Result<()> {
+    let _lock = MM_FLOW_STATE_LOCK.lock().unwrap();
+    let mut flow_states: HashMap<Address, FlowState> = match read_json_file(flow_state_path) {
+        Ok(json_content) => serde_json::from_str(&json_content)?,
+        Err(_) => HashMap::new(),
+    };
+    flow_states.insert(group_address, flow_state.clone());
+    std::fs::write(flow_state_path, serde_json::to_string_pretty(&flow_states)?)?;
+    Ok(())
+}
+
+// ApiService instances share the launch state file
+static LAUNCH_STATE_LOCK: Mutex<()> = Mutex::new(());
+
+/**
+ * unix timestamp trading of the token was activated by `launch_process`, kept in `launch_state_path`
+ * so a restart does not activate it again
+ */
+pub fn get_launched_at(launch_state_path: &str, token_address: &Address) -> Option<i64> {
+    let _lock = LAUNCH_STATE_LOCK.lock().unwrap();
+    let json_content = read_json_file(launch_state_path).ok()?;
+    let launch_state: HashMap<Address, i64> = serde_json::from_str(&json_content).ok()?;
+    launch_state.get(token_address).copied()
+}
+
+pub fn save_launched_at(
+    launch_state_path: &str,
+    token_address: Address,
+    launched_at: i64,
+) -> anyhow::Result<()> {
+    let _lock = LAUNCH_STATE_LOCK.lock().unwrap();
+    let mut launch_state: HashMap<Address, i64> = match read_json_file(launch_state_path) {
+        Ok(json_content) => serde_json::from_str(&json_content)?,
+        Err(_) => HashMap::new(),
+    };
+    launch_state.insert(token_address, launched_at);
+    std::fs::write(
+        launch_state_path,
+        serde_json::to_string_pretty(&launch_state)?,
+    )?;
+    Ok(())
+}
+
+/**
+ * shared limit of mm groups buying/selling at once, `None` when `max_concurrent_groups` is missing or 0
+ */
+pub fn new_mm_group_semaphore(max_concurrent_groups: Option<u32>) -> Option<Arc<Semaphore>> {
+    match max_concurrent_groups {
+        Some(max_concurrent_groups) if max_concurrent_groups > 0 => {
+            Some(Arc::new(Semaphore::new(max_concurrent_groups as usize)))
+        }
+        _ => None,
+    }
+}
+
+/**
+ * wait for a free group slot, the slot is released when the permit is dropped
+ */
+pub async fn acquire_mm_group_permit(
+    group_semaphore: &Option<Arc<Semaphore>>,
+) -> anyhow::Result<Option<OwnedSemaphorePermit>> {
+    match group_semaphore {
+        Some(group_semaphore) => Ok(Some(group_semaphore.clone().acquire_owned().await?)),
+        None => Ok(None),
+    }
+}
+
+/**
+ * wallets run by the bots (auto buyer, buyer, seller, market maker) and the deployer,
+ * with `SKIP_OWN_WALLET_TX=true` their trades do not trigger the bots, avoiding feedback loops
+ */
+#[derive(Debug, Default, Clone)]
+pub struct OwnWallets {
+    enabled: bool,
+    addresses: HashSet<Address>,
+}
+
+impl OwnWallets {
+    pub fn new(enabled: bool, addresses: impl IntoIterator<Item = Address>) -> Self {
+        Self {
+            enabled,
+            addresses: addresses.into_iter().collect(),
+        }
+    }
+
+    /// the deployer of `DEPLOYER_PRIVATE_KEY` is added to `system_wallets`
+    pub fn from_env(system_wallets: impl IntoIterator<Item = Address>) -> Self {
+        let enabled: bool = get_env("SKIP_OWN_WALLET_TX", Some("false".to_string()))
+            .parse()
+            .unwrap();
+        let deployer_private_key = get_env("DEPLOYER_PRIVATE_KEY", Some("".to_string()));
+        let deployer_address = if deployer_private_key.is_empty() {
+            None
+        } else {
+            load_private_key_wallet(&deployer_private_key)
+                .ok()
+                .map(|wallet| wallet.address())
+        };
+
+        Self::new(enabled, system_wallets.into_iter().chain(deployer_address))
+    }
+
+    pub fn contains(&self, address: &Address) -> bool {
+        self.enabled && self.addresses.contains(address)
+    }
+}
+
+/**
+ * tokens the auto buyer may buy, an empty `TOKEN_ALLOWLIST` allows every token not in `TOKEN_BLACKLIST`,
+ * the blacklist may also list pair addresses (e.g. a honeypot pair returned by a compromised factory)
+ */
+#[derive(Debug, Default, Clone)]
+pub struct TokenGuard {
+    allowlist: HashSet<Address>,
+    blacklist: HashSet<Address>,
+}
+
+impl TokenGuard {
+    pub fn new(
+        allowlist: impl IntoIterator<Item = Address>,
+        blacklist: impl IntoIterator<Item = Address>,
+    ) -> Self {
+        Self {
+            allowlist: allowlist.into_iter().collect(),
+            blacklist: blacklist.into_iter().collect(),
+        }
+    }
+
+    /// comma separated addresses of `TOKEN_ALLOWLIST` and `TOKEN_BLACKLIST`
+    pub fn from_env() -> Self {
+        let parse_addresses = |key: &str| -> Vec<Address> {
+            get_env(key, Some("".to_string()))
+                .split(',')
+                .filter(|address| !address.trim().is_empty())
+                .map(|address| {
+                    Address::from_str(address.trim())
+                        .unwrap_or_else(|_| panic!("{} address {:?} invalid", key, address))
+                })
+                .collect()
+        };
+
+        Self::new(
+            parse_addresses("TOKEN_ALLOWLIST"),
+            parse_addresses("TOKEN_BLACKLIST"),
+        )
+    }
+
+    pub fn is_allowed(&self, token_address: &Address) -> bool {
+        !self.is_blocked(token_address)
+            && (self.allowlist.is_empty() || self.allowlist.contains(token_address))
+    }
+
+    pub fn is_blocked(&self, address: &Address) -> bool {
+        self.blacklist.contains(address)
+    }
+}
+
+pub fn get_bloxroute_tip_fee(network: &ENetwork, number_of_txs: u32) -> U256 {
+    if ![ENetwork::BscMainnet, ENetwork::BscTestnet].contains(network) {
+        return U256::zero();
+    }
+
+    match number_of_txs {
+        0..=2 => parse_ether("0.0004").unwrap(),
+        3..=5 => parse_ether("0.004").unwrap(),
+        6..=10 => parse_ether("0.008").unwrap(),
+        11..=15 => parse_ether("0.012").unwrap(),
+        _ => parse_ether("0.012").unwrap(),
+    }
+}
+
+// latest fees fetched by `GasPrice` in the EIP-1559 gas mode
+static EIP1559_FEES: Mutex<Option<Eip1559Fees>> = Mutex::new(None);
+
+/**
+ * base fee of the next block and median tip of the last blocks
+ */
+pub async fn fetch_eip1559_fees(http_provider: Arc<Provider<Http>>) -> anyhow::Result<Eip1559Fees> {
+    let fee_history = http_provider
+        .fee_history(10u64, BlockNumber::Latest, &[50.0])
+        .await?;
+    let fees = compute_eip1559_fees(&fee_history.base_fee_per_gas, &fee_history.reward);
+    *EIP1559_FEES.lock().unwrap() = Some(fees);
+
+    Ok(fees)
+}
+
+/// fees of the last `fetch_eip1559_fees`, none before the first fetch
+pub fn cached_eip1559_fees() -> Option<Eip1559Fees> {
+    *EIP1559_FEES.lock().unwrap()
+}
+
+/**
+ * price the tx by `GAS_MODE`, the shared gas price for legacy txs or the latest fee history for EIP-1559
+ */
+pub async fn apply_gas_mode(
+    mut tx: TypedTransaction,
+    gas_price: U256,
+    http_provider: Arc<Provider<Http>>,
+) -> anyhow::Result<TypedTransaction> {
+    match get_gas_mode() {
+        EGasMode::Legacy => {
+            tx.set_gas_price(gas_price);
+            Ok(to_legacy_tx(tx))
+        }
+        EGasMode::Eip1559 => {
+            let fees = match cached_eip1559_fees() {
+                Some(fees) => fees,
+                None => fetch_eip1559_fees(http_provider).await?,
+            };
+            Ok(to_eip1559_tx(tx, fees.max_fee(), fees.priority_fee))
+        }
+    }
+}
+
+/**
+ * gas price of a reactive or jittered trade over the one of `apply_gas_mode`. An EIP-1559 `gas_price`
+ * is a max fee, its tip is what is left above twice the cached base fee
+ */
+pub fn override_gas_price(tx: &mut TypedTransaction, gas_price: U256) {
+    match tx {
+        TypedTransaction::Eip1559(tx) => {
+            let base_fee = cached_eip1559_fees()
+                .map(|fees| fees.base_fee)
+                .unwrap_or_default();
+            tx.max_fee_per_gas = Some(gas_price);
+            tx.max_priority_fee_per_gas = Some(gas_price.saturating_sub(base_fee * 2));
+        }
+        _ => {
+            tx.set_gas_price(gas_price);
+        }
+    }
+}
+
+/**
+ * symbol of the gas token of the network (ETH, BNB, FTM), the amounts of the messages are labelled with it
+ */
+pub fn get_native_symbol(network: &ENetwork) -> &'static str {
+    let Some(native_symbol) = NATIVE_SYMBOLS.get(network) else {
+        panic!("NATIVE_SYMBOLS not found in {:?}", network);
+    };
+    native_symbol
+}
+
+/**
+ * notification of a buy tx, price and volume in the native token
+ */
+pub fn format_buy_message(
+    buy_tx_hash: &str,
+    is_success: bool,
+    token_price: f64,
+    buy_amount: U256,
+    native_symbol: &str,
+) -> String {
+    format!(
+        "Buy transaction {:#?} {} \nToken price: {:#?} {}\nVolume: {:#?} {}",
+        buy_tx_hash,
+        if is_success { "success" } else { "failed" },
+        token_price,
+        native_symbol,
+        format_ether(buy_amount),
+        native_symbol
+    )
+}
+
+/**
+ * notification of a sell tx, price in the native token and volume in the token
+ */
+pub fn format_sell_message(
+    sell_tx_hash: H256,
+    is_success: bool,
+    token_price: f64,
+    sell_volume: &str,
+    token_symbol: &str,
+    native_symbol: &str,
+) -> String {
+    format!(
+        "Sell transaction {:#?} {} \nToken price: {:#?} {}\nVolume: {:#?} {:#?}",
+        sell_tx_hash,
+        if is_success { "success" } else { "failed" },
+        token_price,
+        native_symbol,
+        sell_volume,
+        token_symbol
+    )
+}
+
+/**
+ * bloxroute tip wallet of the network, the tip of a bundle sent to another chain's wallet is lost
+ */
+pub fn get_bloxroute_tip_address(network: &ENetwork) -> Address {
+    let Some(tip_address) = BLOXROUTE_TIP_ADDRESSES.get(network) else {
+        panic!("BLOXROUTE_TIP_ADDRESSES not found in {:?}", network);
+    };
+    *tip_address
+}
+
+/**
+ * sign the bloxroute tip transfer, sent as the first tx of a bundle
+ */
+pub async fn compute_bloxroute_tip_tx(
+    tip_wallet: &WalletSigner,
+    network: &ENetwork,
+    tip_address: Address,
+    gas_price: U256,
+    tip_eth_amount: U256,
+    number_of_txs: u32,
+    http_provider: Arc<Provider<Http>>,
+) -> anyhow::Result<Bytes> {
+    let tip_value = get_bloxroute_tip_fee(network, number_of_txs);
+    let nonce = http_provider
+        .get_transaction_count(tip_wallet.address(), None)
+        .await?;
+    let tip_gas = tip_eth_amount / U256::from(21000);
+
+    let tx = TransactionRequest::new()
+        .from(tip_wallet.address())
+        .nonce(nonce)
+        .to(tip_address)
+        .value(tip_value)
+        .gas_price(gas_price + tip_gas)
+        .gas(21000);
+    let mut tip_tx = TypedTransaction::Legacy(tx);
+    tip_tx.set_chain_id(tip_wallet.chain_id());
+    let signed_tx = to_signed_tx(tip_wallet, &tip_tx).await?;
+
+    Ok(signed_tx)
+}
+
+/**
+ * fetch where each bundled tx landed, the expected position is its index in the bundle
+ */
+pub async fn get_bundle_positions(
+    http_provider: Arc<Provider<Http>>,
+    bundle_txs: &[(String, H256)],
+) -> anyhow::Result<Vec<LaunchTxPosition>> {
+    let receipts = join_all(
+        bundle_txs
+            .iter()
+            .map(|(_, tx_hash)| http_provider.get_transaction_receipt(*tx_hash)),
+    )
+    .await;
+
+    let mut positions = Vec::new();
+    for (expected_position, ((label, tx_hash), receipt)) in
+        bundle_txs.iter().zip(receipts).enumerate()
+    {
+        let receipt = receipt?;
+        positions.push(LaunchTxPosition {
+            label: label.clone(),
+            tx_hash: *tx_hash,
+            expected_position,
+            block_number: receipt.as_ref().and_then(|receipt| receipt.block_number),
+            transaction_index: receipt.map(|receipt| receipt.transaction_index),
+        });
+    }
+
+    Ok(positions)
+}
+
+/**
+ * bundled txs must land in the same block, at consecutive indexes in the bundle order
+ */
+pub fn check_bundle_positions(positions: &[LaunchTxPosition]) -> Vec<String> {
+    let Some(first) = positions.first() else {
+        return Vec::new();
+    };
+    let (Some(block_number), Some(first_index)) = (first.block_number, first.transaction_index)
+    else {
+        return vec![format!("{} not included", first.label)];
+    };
+
+    let mut errors = Vec::new();
+    for position in positions {
+        let (Some(tx_block_number), Some(tx_index)) =
+            (position.block_number, position.transaction_index)
+        else {
+            errors.push(format!("{} not included", position.label));
+            continue;
+        };
+        if tx_block_number != block_number {
+            errors.push(format!(
+                "{} landed in block {} instead of {}",
+                position.label, tx_block_number, block_number
+            ));
+            continue;
+        }
+        let expected_index = first_index + position.expected_position;
+        if tx_index != expected_index {
+            errors.push(format!(
+                "{} landed at index {} instead of {}",
+                position.label, tx_index, expected_index
+            ));
+        }
+    }
+
+    errors
+}
+
+/**
+ * fetch nonce and eth balance of a wallet in parallel, so tx builders don't await the nonce inline
+ */
+pub async fn prefetch_nonce_and_balance(
+    http_provider: &Provider<Http>,
+    wallet_address: Address,
+) -> anyhow::Result<(U256, U256)> {
+    let (nonce, eth_balance) = tokio::join!(
+        http_provider.get_transaction_count(wallet_address, None),
+        http_provider.get_balance(wallet_address, None)
+    );
+
+    Ok((nonce?, eth_balance?))
+}
+
+/**
+ * run a signed tx with eth_call and estimate_gas instead of broadcasting it,
+ * returns the last word of the call output, the amount out of swaps, and the gas estimate
+ */
+pub async fn simulate_raw_tx<M: Middleware>(
+    provider: &M,
+    raw_tx: &Bytes,
+) -> anyhow::Result<(Option<U256>, U256)> {
+    let (mut tx, signature) = TypedTransaction::decode_signed(&Rlp::new(raw_tx))?;
+    tx.set_from(signature.recover(tx.sighash())?);
+
+    let output = provider
+        .call(&tx, None)
+        .await
+        .map_err(|err| anyhow::anyhow!("eth_call reverted: {}", err))?;
+    let gas_estimate = provider
+        .estimate_gas(&tx, None)
+        .await
+        .map_err(|err| anyhow::anyhow!("estimate_gas failed: {}", err))?;
+
+    Ok((decode_amount_out(&output), gas_estimate))
+}
+
+/**
+ * pairs of the token, polled every `poll_interval` while none is created (empty or failing),
+ * fails after `max_polls` polls so a detect loop never runs without a pair
+ */
+pub async fn wait_for_pair_addresses<F, Fut>(
+    mut fetch_pair_addresses: F,
+    poll_interval: Duration,
+    max_polls: u32,
+) -> anyhow::Result<Vec<Address>>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<Vec<Address>>>,
+{
+    let mut poll = 1;
+    loop {
+        let err = match fetch_pair_addresses().await {
+            Ok(pair_addresses) if !pair_addresses.is_empty() => return Ok(pair_addresses),
+            Ok(_) => anyhow::anyhow!("no pair created"),
+            Err(err) => err,
+        };
+        if poll >= max_polls {
+            return Err(err.context(format!("no pair after {} polls", max_polls)));
+        }
+        log::info!(
+            "waiting for pair, poll {}/{} in {:?}: {}",
+            poll,
+            max_polls,
+            poll_interval,
+            err
+        );
+        poll += 1;
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+/**
+ * read symbol, name, decimals and total supply of a token,
+ * provider failures are retried `TOKEN_METADATA_RETRIES` times, a wrong token address fails at once
+ */
+pub async fn fetch_token_info(
+    token_address: Address,
+    http_provider: Arc<Provider<Http>>,
+) -> anyhow::Result<TokenInfo> {
+    let retries: u32 = get_env("TOKEN_METADATA_RETRIES", Some("3".to_string()))
+        .parse()
+        .unwrap();
+    let token_contract = MemeTokenAbigen::new(token_address, http_provider);
+
+    let mut attempt = 0;
+    loop {
+        let symbol = token_contract.symbol();
+        let name = token_contract.name();
+        let decimals = token_contract.decimals();
+        let total_supply = token_contract.total_supply();
+        let (symbol, name, decimals, total_supply) = tokio::join!(
+            symbol.call(),
+            name.call(),
+            decimals.call(),
+            total_supply.call()
+        );
+        let err = match (symbol, name, decimals, total_supply) {
+            (Ok(symbol), Ok(name), Ok(decimals), Ok(total_supply)) => {
+                return Ok(TokenInfo {
+                    address: token_address,
+                    symbol,
+                    name,
+                    decimals,
+                    total_supply,
+                });
+            }
+            (Err(err), _, _, _) => ("symbol", err),
+            (_, Err(err), _, _) => ("name", err),
+            (_, _, Err(err), _) => ("decimals", err),
+            (_, _, _, Err(err)) => ("total_supply", err),
+        };
+
+        let (call, err) = err;
+        let is_transient = err.is_middleware_error() || err.is_provider_error();
+        if !is_transient || attempt >= retries {
+            return Err(anyhow::anyhow!(
+                "failed to read token metadata at address {:?}: {}() {}",
+                token_address,
+                call,
+                err
+            ));
+        }
+        attempt += 1;
+        log::warn!(
+            "[fetch_token_info] {}() of {:?} failed, retry {}/{}: {}",
+            call,
+            token_address,
+            attempt,
+            retries,
+            err
+        );
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/**
+ * removed logs are re-emitted by the node when their block is dropped by a chain reorg
+ */
+pub fn is_removed_log(log: &Log) -> bool {
+    log.removed.unwrap_or(false)
+}
+
+pub fn is_within_reorg_depth(trigger_block: U64, current_block: U64, reorg_depth: u64) -> bool {
+    current_block.saturating_sub(trigger_block) <= U64::from(reorg_depth)
+}
+
+/**
+ * a level alerts once when the price crosses it, the first observed price counts as a cross
+ */
+pub fn is_crossed_above(last_price: Option<f64>, price: f64, level: f64) -> bool {
+    price > level && last_price.is_none_or(|last_price| last_price <= level)
+}
+
+pub fn is_crossed_below(last_price: Option<f64>, price: f64, level: f64) -> bool {
+    price < level && last_price.is_none_or(|last_price| last_price >= level)
+}
+
+// 100% slippage, any amount out is accepted
+pub const MAX_SLIPPAGE_BPS: u32 = 10_000;
+
+/**
+ * a slippage in basis points must be within 0..=100%
+ */
+pub fn validate_slippage_bps(slippage_bps: u32) -> anyhow::Result<u32> {
+    if slippage_bps > MAX_SLIPPAGE_BPS {
+        return Err(anyhow::anyhow!(
+            "slippage {} bps not within 0..={}",
+            slippage_bps,
+            MAX_SLIPPAGE_BPS
+        ));
+    }
+    Ok(slippage_bps)
+}
+
+/**
+ * a percent of the env (`TRADING_SLIPPAGE=0.25`, `MAX_SLIPPAGE`, a token tax) in basis points,
+ * rounded so a fractional percent keeps its precision
+ */
+pub fn percent_to_bps(percent: f32) -> anyhow::Result<u32> {
+    let slippage_bps = (f64::from(percent) * 100.0).round();
+    if !(0.0..=f64::from(MAX_SLIPPAGE_BPS)).contains(&slippage_bps) {
+        return Err(anyhow::anyhow!("slippage {}% not within 0..=100%", percent));
+    }
+    Ok(slippage_bps as u32)
+}
+
+/**
+ * trading slippage plus the tax (percent) of the trade, capped at 100%
+ */
+pub fn add_tax_bps(trading_slippage_bps: u32, tax: f32) -> u32 {
+    let tax_bps = percent_to_bps(tax.clamp(0.0, 100.0)).unwrap_or(MAX_SLIPPAGE_BPS);
+    (trading_slippage_bps + tax_bps).min(MAX_SLIPPAGE_BPS)
+}
+
+/**
+ * minimum amount out of a trade quoted `amount_out`
+ */
+pub fn apply_slippage_bps(amount_out: U256, slippage_bps: u32) -> U256 {
+    let slippage_bps = slippage_bps.min(MAX_SLIPPAGE_BPS);
+    amount_out - amount_out * U256::from(slippage_bps) / U256::from(MAX_SLIPPAGE_BPS)
+}
+
+/**
+ * a zero total slippage makes amount_out_min equal to the quote, so any price move reverts the trade
+ */
+pub fn apply_slippage_floor(total_slippage_bps: u32, min_slippage_floor_bps: u32) -> u32 {
+    total_slippage_bps.max(min_slippage_floor_bps)
+}
+
+/**
+ * slippage of the resubmit of a sell reverted by slippage, doubled and capped at `max_slippage_bps` (`MAX_SLIPPAGE`)
+ */
+pub fn widen_slippage(total_slippage_bps: u32, max_slippage_bps: u32) -> u32 {
+    (total_slippage_bps * 2)
+        .min(max_slippage_bps)
+        .max(total_slippage_bps)
+}
+
+/**
+ * contract tax is a fraction of `tax_denominator`, router services expect percent.
+ * None for a zero denominator or a value beyond u128, the callers fall back to the env tax
+ */
+pub fn tax_to_percent(tax: U256, tax_denominator: U256) -> Option<f32> {
+    if tax_denominator.is_zero() {
+        return None;
+    }
+    let tax = u128::try_from(tax).ok()?;
+    let tax_denominator = u128::try_from(tax_denominator).ok()?;
+    Some((tax as f64 * 100.0 / tax_denominator as f64) as f32)
+}
+
+// seconds a tax read from the contract is reused, the tax of a launch decays slowly
+const TOKEN_TAX_CACHE_SECONDS: u64 = 30;
+
+// token address => tax percent, only successful reads are cached
+static TOKEN_TAX_CACHE: LazyLock<Mutex<TimedCache<Address, f32>>> =
+    LazyLock::new(|| Mutex::new(TimedCache::with_lifespan(TOKEN_TAX_CACHE_SECONDS)));
+
+/**
+ * read the current tax from the MemeToken contract when `TOKEN_TAX_MODE=Contract`, fall back to the env tax
+ */
+pub async fn resolve_token_tax(
+    tax_mode: TokenTaxMode,
+    env_tax: f32,
+    token_address: Address,
+    http_provider: Arc<Provider<Http>>,
+) -> f32 {
+    if tax_mode == TokenTaxMode::Env {
+        return env_tax;
+    }
+    if let Some(tax) = TOKEN_TAX_CACHE.lock().unwrap().cache_get(&token_address) {
+        return *tax;
+    }
+    let token_contract = MemeTokenAbigen::new(token_address, http_provider);
+    let current_tax = token_contract.get_current_tax();
+    let tax_denominator = token_contract.tax_denominator();
+    let (current_tax, tax_denominator) = tokio::join!(current_tax.call(), tax_denominator.call());
+    match (current_tax, tax_denominator) {
+        (Ok(current_tax), Ok(tax_denominator)) => {
+            match tax_to_percent(current_tax, tax_denominator) {
+                Some(tax) => {
+                    TOKEN_TAX_CACHE
+                        .lock()
+                        .unwrap()
+                        .cache_set(token_address, tax);
+                    tax
+                }
+                None => {
+                    log::warn!(
+                        "[resolve_token_tax] invalid tax {:?} / {:?}, fall back to env tax",
+                        current_tax,
+                        tax_denominator
+                    );
+                    env_tax
+                }
+            }
+        }
+        (current_tax, tax_denominator) => {
+            log::warn!(
+                "[resolve_token_tax] cannot read tax from {:?}, fall back to env tax: {:?} {:?}",
+                token_address,
+                current_tax.err(),
+                tax_denominator.err()
+            );
+            env_tax
+        }
+    }
+}
+
+/**
+ * `REACTIVE_GAS_PRICE_PERCENT` prices reactive trades relative to the trigger tx, capped by `MAX_GAS_PRICE_GWEI`
+ * returns (percent, max_gas_price)
+ */
+pub fn get_reactive_gas_config() -> Option<(u32, U256)> {
+    let reactive_gas_price_percent = get_env("REACTIVE_GAS_PRICE_PERCENT", Some("".to_string()));
+    if reactive_gas_price_percent.is_empty() {
+        return None;
+    }
+    let max_gas_price_gwei = get_env("MAX_GAS_PRICE_GWEI", Some("".to_string()));
+    if max_gas_price_gwei.is_empty() {
+        panic!("MAX_GAS_PRICE_GWEI is required when REACTIVE_GAS_PRICE_PERCENT is set");
+    }
+    Some((
+        reactive_gas_price_percent.parse().unwrap(),
+        parse_units(max_gas_price_gwei, "gwei").unwrap().into(),
+    ))
+}
+
+/**
+ * trigger gas price scaled by percent and capped by max_gas_price, never below the shared gas price
+ */
+pub fn compute_reactive_gas_price(
+    gas_price: U256,
+    trigger_gas_price: U256,
+    percent: u32,
+    max_gas_price: U256,
+) -> U256 {
+    let reactive_gas_price = trigger_gas_price * U256::from(percent) / U256::from(100);
+    gas_price.max(reactive_gas_price.min(max_gas_price))
+}
+
+/**
+ * tokens a market maker wallet keeps after its sell, a random percent of the balance with
+ * `minRetainPercent`/`maxRetainPercent`, a random absolute token count otherwise
+ */
+pub fn compute_retain_amount<R: Rng>(
+    mm_settings: &MmSettings,
+    token_balance: U256,
+    token_decimals: u8,
+    rng: &mut R,
+) -> anyhow::Result<U256> {
+    if let (Some(min_retain_percent), Some(max_retain_percent)) = (
+        mm_settings.min_retain_percent,
+        mm_settings.max_retain_percent,
+    ) {
+        if !(0.0..=100.0).contains(&min_retain_percent)
+            || !(min_retain_percent..=100.0).contains(&max_retain_percent)
+        {
+            return Err(anyhow::anyhow!(
+                "invalid retain percent range {}..={}",
+                min_retain_percent,
+                max_retain_percent
+            ));
+        }
+        let retain_percent = rng.gen_range(min_retain_percent..=max_retain_percent);
+        let retain_bps = U256::from((retain_percent * 100.0).round() as u64);
+        return Ok(token_balance * retain_bps / U256::from(10_000));
+    }
+
+    let min_retain_token = mm_settings.min_retain_token.unwrap();
+    let max_retain_token = mm_settings.max_retain_token.unwrap();
+    let retain_token = rng.gen_range(min_retain_token..=max_retain_token);
+    let retain_amount = U256::from(retain_token) * U256::exp10(token_decimals as usize);
+    if token_balance < retain_amount {
+        return Err(anyhow::anyhow!(
+            "token_balance must be greater than retain_token"
+        ));
+    }
+    Ok(retain_amount)
+}
+
+/**
+ * number of balances holding at least min_balance, dust does not count as a holder
+ */
+pub fn count_holders(balances: &[U256], min_balance: U256) -> u32 {
+    balances
+        .iter()
+        .filter(|balance| !balance.is_zero() && **balance >= min_balance)
+        .count() as u32
+}
+
+/**
+ * a wallet becomes a holder when it goes from no counted balance to one, a wallet buying again
+ * while it already holds was counted before
+ */
+pub fn is_new_holder(balance_before: U256, balance_after: U256, min_balance: U256) -> bool {
+    count_holders(&[balance_before], min_balance) == 0
+        && count_holders(&[balance_after], min_balance) == 1
+}
+
+/**
+ * count the wallets currently holding a non-dust token balance
+ */
+pub async fn count_token_holders(
+    token_address: Address,
+    wallets: &[Address],
+    min_balance: U256,
+    http_provider: Arc<Provider<Http>>,
+) -> anyhow::Result<u32> {
+    let token_contract = MemeTokenAbigen::new(token_address, http_provider);
+    let balance_calls: Vec<_> = wallets
+        .iter()
+        .map(|wallet| token_contract.balance_of(*wallet))
+        .collect();
+    let balances = join_all(balance_calls.iter().map(|balance_of| balance_of.call()))
+        .await
+        .into_iter()
+        .collect::<Result<Vec<U256>, _>>()?;
+
+    Ok(count_holders(&balances, min_balance))
+}
+
+/**
+ * tokens of `token_address` transferred to `wallet` in a buy receipt, zero when a successful buy
+ * delivered nothing (honeypot, 100% transfer tax)
+ */
+pub fn compute_tokens_received(
+    tx_receipt: &TransactionReceipt,
+    token_address: Address,
+    wallet: Address,
+) -> U256 {
+    let transfer_topic = H256::from(keccak256("Transfer(address,address,uint256)"));
+    tx_receipt
+        .logs
+        .iter()
+        .filter(|log| {
+            log.address == token_address
+                && log.topics.first() == Some(&transfer_topic)
+                && log.topics.get(2) == Some(&H256::from(wallet))
+        })
+        .fold(U256::zero(), |received, log| {
+            received + U256::from_big_endian(&log.data)
+        })
+}
+
+/**
+ * a wallet needs an approve when its allowance does not cover its balance,
+ * a zero allowance always does so wallets are approved ahead of their first token
+ */
+pub fn is_under_approved(allowance: U256, balance: U256) -> bool {
+    allowance.is_zero() || allowance < balance
+}
+
+/**
+ * a sell the allowance does not cover approves first with `auto_approve`, is skipped otherwise
+ */
+pub fn plan_sell_approval(allowance: U256, sell_amount: U256, auto_approve: bool) -> SellApproval {
+    if !is_under_approved(allowance, sell_amount) {
+        SellApproval::Approved
+    } else if auto_approve {
+        SellApproval::ApproveFirst
+    } else {
+        SellApproval::Skip
+    }
+}
+
+/**
+ * approve spender for the max from the signer with nonce and wait for it when the allowance does
+ * not cover amount, returns the nonce of the next tx
+ */
+pub async fn approve_if_under_approved<M: Middleware + 'static>(
+    token_address: Address,
+    spender: Address,
+    allowance: U256,
+    amount: U256,
+    nonce: U256,
+    signer: Arc<M>,
+) -> anyhow::Result<U256> {
+    if !is_under_approved(allowance, amount) {
+        return Ok(nonce);
+    }
+
+    let token_contract = MemeTokenAbigen::new(token_address, signer);
+    let approve_tx_receipt = token_contract
+        .approve(spender, U256::MAX)
+        .nonce(nonce)
+        .send()
+        .await?
+        .await?
+        .ok_or(anyhow::anyhow!("Cannot find approve tx_receipt"))?;
+    if approve_tx_receipt.status != Some(U64::one()) {
+        return Err(anyhow::anyhow!(
+            "approve {:?} reverted",
+            approve_tx_receipt.transaction_hash
+        ));
+    }
+
+    Ok(nonce + U256::one())
+}
+
+/**
+ * let Permit2 move the max of the token approved to it for spender, without expiration, from the signer
+ * with nonce and wait for it, returns the nonce of the next tx
+ */
+pub async fn approve_permit2_spender<M: Middleware + 'static>(
+    token_address: Address,
+    spender: Address,
+    nonce: U256,
+    signer: Arc<M>,
+) -> anyhow::Result<U256> {
+    let permit2 = Permit2Abigen::new(*PERMIT2_ADDRESS, signer);
+    // uint160 amount, uint48 expiration
+    let max_amount = (U256::one() << 160) - 1;
+    let max_expiration = (1u64 << 48) - 1;
+    let approve_tx_receipt = permit2
+        .approve(token_address, spender, max_amount, max_expiration)
+        .nonce(nonce)
+        .send()
+        .await?
+        .await?
+        .ok_or(anyhow::anyhow!("Cannot find permit2 approve tx_receipt"))?;
+    if approve_tx_receipt.status != Some(U64::one()) {
+        return Err(anyhow::anyhow!(
+            "permit2 approve {:?} reverted",
+            approve_tx_receipt.transaction_hash
+        ));
+    }
+
+    Ok(nonce + U256::one())
+}
+
+/**
+ * token balance and lowest allowance to spenders of each wallet, read concurrently
+ */
+pub async fn read_balances_and_allowances<M: Middleware + 'static>(
+    token_address: Address,
+    wallets: &[Address],
+    spenders: &[Address],
+    provider: Arc<M>,
+) -> anyhow::Result<Vec<(U256, U256)>> {
+    let token_contract = MemeTokenAbigen::new(token_address, provider);
+    let calls: Vec<_> = wallets
+        .iter()
+        .map(|wallet| {
+            (
+                token_contract.balance_of(*wallet),
+                spenders
+                    .iter()
+                    .map(|spender| token_contract.allowance(*wallet, *spender))
+                    .collect::<Vec<_>>(),
+            )
+        })
+        .collect();
+    let results = join_all(calls.iter().map(|(balance_of, allowances)| async move {
+        tokio::join!(
+            balance_of.call(),
+            try_join_all(allowances.iter().map(|allowance| allowance.call()))
+        )
+    }))
+    .await;
+
+    results
+        .into_iter()
+        .map(|(balance, allowances)| {
+            Ok((balance?, allowances?.into_iter().min().unwrap_or_default()))
+        })
+        .collect()
+}
+
+/**
+ * wallets whose token allowance to any of spenders is under approved
+ */
+pub async fn find_under_approved_wallets<M: Middleware + 'static>(
+    token_address: Address,
+    wallets: &[Address],
+    spenders: &[Address],
+    provider: Arc<M>,
+) -> anyhow::Result<Vec<Address>> {
+    let balances_and_allowances =
+        read_balances_and_allowances(token_address, wallets, spenders, provider).await?;
+
+    Ok(wallets
+        .iter()
+        .zip(balances_and_allowances)
+        .filter(|(_, (balance, allowance))| is_under_approved(*allowance, *balance))
+        .map(|(wallet, _)| *wallet)
+        .collect())
+}
+
+/**
+ * one sell of the whole balance per wallet holding tokens, approved first when under approved
+ */
+pub fn plan_panic_sells(
+    wallets: &[Address],
+    balances_and_allowances: &[(U256, U256)],
+) -> Vec<PanicSellPlan> {
+    wallets
+        .iter()
+        .zip(balances_and_allowances)
+        .filter(|(_, (balance, _))| !balance.is_zero())
+        .map(|(wallet, (balance, allowance))| PanicSellPlan {
+            address: *wallet,
+            sell_amount: *balance,
+            is_approve_needed: is_under_approved(*allowance, *balance),
+        })
+        .collect()
+}
+
+/**
+ * split buy_amount over wallets spending at most balance - surplus_balance each,
+ * wallets fitting the remaining amount are drained, the rest goes to a random wallet able to cover it
+ * returns the allocations and the amount no wallet could cover
+ */
+pub fn allocate_buy_amount<R: Rng>(
+    wallet_balances: &[(Address, U256)],
+    surplus_balance: U256,
+    buy_amount: U256,
+    rng: &mut R,
+) -> (Vec<(Address, U256)>, U256) {
+    let mut allocations = Vec::new();
+    let mut candidates = Vec::new();
+    let mut remaining = buy_amount;
+    for (wallet_address, eth_balance) in wallet_balances {
+        if remaining.is_zero() {
+            break;
+        }
+        let spendable = eth_balance.saturating_sub(surplus_balance);
+        if spendable.is_zero() {
+            continue;
+        }
+        match remaining.checked_sub(spendable) {
+            Some(rest) => {
+                allocations.push((*wallet_address, spendable));
+                remaining = rest;
+            }
+            None => candidates.push(*wallet_address),
+        }
+    }
+    if !remaining.is_zero() {
+        if let Some(wallet_address) = candidates.choose(rng) {
+            allocations.push((*wallet_address, remaining));
+            remaining = U256::zero();
+        }
+    }
+
+    (allocations, remaining)
+}
+
+/**
+ * split buy_amount by the distribution, every wallet spends at most balance - surplus_balance
+ * returns the allocations and the amount no wallet could cover
+ */
+pub fn distribute_buy_amount<R: Rng>(
+    distribution: EBuyDistribution,
+    wallet_balances: &[(Address, U256)],
+    surplus_balance: U256,
+    buy_amount: U256,
+    rng: &mut R,
+) -> (Vec<(Address, U256)>, U256) {
+    let spendables: Vec<(Address, U256)> = wallet_balances
+        .iter()
+        .map(|(wallet_address, eth_balance)| {
+            (*wallet_address, eth_balance.saturating_sub(surplus_balance))
+        })
+        .filter(|(_, spendable)| !spendable.is_zero())
+        .collect();
+    let weights: Vec<U256> = match distribution {
+        EBuyDistribution::Greedy => {
+            return allocate_buy_amount(wallet_balances, surplus_balance, buy_amount, rng)
+        }
+        EBuyDistribution::EvenSplit => vec![U256::one(); spendables.len()],
+        EBuyDistribution::RandomWeighted => spendables
+            .iter()
+            .map(|_| U256::from(rng.gen_range(1..=100u64)))
+            .collect(),
+    };
+
+    let mut amounts = vec![U256::zero(); spendables.len()];
+    let mut open_indexes: Vec<usize> = (0..spendables.len()).collect();
+    let mut remaining = buy_amount;
+    // wallets whose share exceeds their spendable balance are drained, the rest is shared again
+    while !remaining.is_zero() && !open_indexes.is_empty() {
+        let total_weight = open_indexes
+            .iter()
+            .fold(U256::zero(), |total, index| total + weights[*index]);
+        let share = |index: usize| remaining * weights[index] / total_weight;
+        let (drained_indexes, shared_indexes): (Vec<usize>, Vec<usize>) = open_indexes
+            .iter()
+            .partition(|index| share(**index) >= spendables[**index].1);
+        if drained_indexes.is_empty() {
+            let shares: Vec<U256> = shared_indexes.iter().map(|index| share(*index)).collect();
+            for (index, share) in shared_indexes.iter().zip(shares) {
+                amounts[*index] = share;
+                remaining -= share;
+            }
+            // rounding dust
+            for index in shared_indexes {
+                let dust = remaining.min(spendables[index].1 - amounts[index]);
+                amounts[index] += dust;
+                remaining -= dust;
+            }
+            break;
+        }
+        for index in drained_indexes.iter() {
+            amounts[*index] = spendables[*index].1;
+            remaining -= spendables[*index].1;
+        }
+        open_indexes = shared_indexes;
+    }
+
+    let allocations = spendables
+        .iter()
+        .zip(amounts)
+        .filter(|(_, amount)| !amount.is_zero())
+        .map(|((wallet_address, _), amount)| (*wallet_address, amount))
+        .collect();
+
+    (allocations, remaining)
+}
+
+/**
+ * value of each recipient of a disperse, the largest possible total must fit the available balance
+ * of the disperse wallet, checked before any value is drawn
+ */
+pub fn plan_disperse_eth_values<R: Rng>(
+    amount: DisperseEthAmount,
+    recipients_count: usize,
+    available_balance: U256,
+    rng: &mut R,
+) -> anyhow::Result<Vec<U256>> {
+    let max_value = match amount {
+        DisperseEthAmount::Uniform(amount) => amount,
+        DisperseEthAmount::Random { max, .. } => max,
+    };
+    let max_total_value = max_value * U256::from(recipients_count);
+    if max_total_value > available_balance {
+        return Err(anyhow::anyhow!(
+            "[plan_disperse_eth_values] up to {} for {} recipients exceeds the balance {}",
+            format_ether(max_total_value),
+            recipients_count,
+            format_ether(available_balance)
+        ));
+    }
+
+    Ok(match amount {
+        DisperseEthAmount::Uniform(amount) => vec![amount; recipients_count],
+        DisperseEthAmount::Random { min, max } => (0..recipients_count)
+            .map(|_| U256::from(rng.gen_range(min.as_u128()..=max.as_u128())))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use provider_utils::mock_rpc::MockRpc;
+
+    #[test]
+    fn removed_log_does_not_trigger() {
+        let log = Log {
+            block_number: Some(U64::from(100)),
+            removed: Some(true),
+            ..Default::default()
+        };
+        assert!(is_removed_log(&log));
+        assert!(!is_removed_log(&Log::default()));
+    }
+
+    #[test]
+    fn within_reorg_depth() {
+        assert!(is_within_reorg_depth(U64::from(100), U64::from(103), 3));
+        assert!(!is_within_reorg_depth(U64::from(100), U64::from(104), 3));
+        assert!(is_within_reorg_depth(U64::from(100), U64::from(99), 0));
+    }
+
+    #[test]
+    fn contract_tax_to_percent() {
+        assert_eq!(
+            tax_to_percent(U256::from(500), U256::from(10_000)),
+            Some(5.0)
+        );
+        assert_eq!(tax_to_percent(U256::from(3), U256::from(100)), Some(3.0));
+        assert_eq!(tax_to_percent(U256::from(3), U256::zero()), None);
+        assert_eq!(tax_to_percent(U256::MAX, U256::from(100)), None);
+    }
+
+    #[test]
+    fn price_crosses_level() {
+        assert!(is_crossed_above(None, 2.0, 1.0));
+        assert!(is_crossed_above(Some(0.5), 2.0, 1.0));
+        assert!(!is_crossed_above(Some(1.5), 2.0, 1.0));
+        assert!(is_crossed_below(Some(1.5), 0.5, 1.0));
+        assert!(!is_crossed_below(Some(0.8), 0.5, 1.0));
+    }
+
+    #[test]
+    fn bundle_positions() {
+        let position = |expected_position: usize, block_number: u64, transaction_index: u64| {
+            LaunchTxPosition {
+                label: format!("tx {}", expected_position),
+                tx_hash: H256::zero(),
+                expected_position,
+                block_number: Some(U64::from(block_number)),
+                transaction_index: Some(U64::from(transaction_index)),
+            }
+        };
+        assert!(check_bundle_positions(&[position(0, 100, 4), position(1, 100, 5)]).is_empty());
+        assert_eq!(
+            check_bundle_positions(&[position(0, 100, 4), position(1, 100, 6)]),
+            vec!["tx 1 landed at index 6 instead of 5"]
+        );
+        assert_eq!(
+            check_bundle_positions(&[position(0, 100, 4), position(1, 101, 0)]),
+            vec!["tx 1 landed in block 101 instead of 100"]
+        );
+    }
+
+    #[test]
+    fn reactive_gas_price_capped() {
+        let gwei = |v: u64| U256::from(v) * U256::exp10(9);
+        assert_eq!(
+            compute_reactive_gas_price(gwei(3), gwei(5), 110, gwei(10)),
+            U256::from(5_500_000_000u64)
+        );
+        assert_eq!(
+            compute_reactive_gas_price(gwei(3), gwei(20), 100, gwei(10)),
+            gwei(10)
+        );
+        assert_eq!(
+            compute_reactive_gas_price(gwei(3), gwei(1), 100, gwei(10)),
+            gwei(3)
+        );
+    }
+
+    #[test]
+    fn holders_ignore_dust() {
+        let balances = [
+            U256::zero(),
+            U256::from(5),
+            U256::from(100),
+            U256::from(1_000),
+        ];
+        assert_eq!(count_holders(&balances, U256::from(100)), 2);
+        assert_eq!(count_holders(&balances, U256::zero()), 3);
+    }
+
+    #[test]
+    fn only_a_first_holding_counts_as_a_new_holder() {
+        let min_balance = U256::from(100);
+        assert!(is_new_holder(U256::zero(), U256::from(100), min_balance));
+        // dust below min_balance was not counted yet
+        assert!(is_new_holder(U256::from(5), U256::from(500), min_balance));
+        assert!(!is_new_holder(
+            U256::from(100),
+            U256::from(500),
+            min_balance
+        ));
+        assert!(!is_new_holder(U256::zero(), U256::from(5), min_balance));
+    }
+
+    #[test]
+    fn buy_allocation_never_exceeds_buy_amount() {
+        let mut rng = rand::thread_rng();
+        let surplus_balance = U256::from(1_000);
+        for _ in 0..1_000 {
+            let wallet_balances: Vec<(Address, U256)> = (0..rng.gen_range(0..8))
+                .map(|_| (Address::random(), U256::from(rng.gen_range(0..5_000u64))))
+                .collect();
+            let buy_amount = U256::from(rng.gen_range(0..20_000u64));
+            let (allocations, remaining) =
+                allocate_buy_amount(&wallet_balances, surplus_balance, buy_amount, &mut rng);
+
+            let allocated = allocations
+                .iter()
+                .fold(U256::zero(), |total, (_, amount)| total + amount);
+            assert_eq!(allocated + remaining, buy_amount);
+            for (wallet_address, amount) in allocations {
+                let (_, eth_balance) = wallet_balances
+                    .iter()
+                    .find(|(address, _)| *address == wallet_address)
+                    .unwrap();
+                assert!(!amount.is_zero());
+                assert!(amount <= eth_balance.saturating_sub(surplus_balance));
+            }
+        }
+    }
+
+    #[test]
+    fn buy_allocation_boundaries() {
+        let mut rng = rand::thread_rng();
+        let surplus_balance = U256::from(1_000);
+        let (surplus_wallet, small_wallet, large_wallet) =
+            (Address::random(), Address::random(), Address::random());
+
+        // a wallet holding exactly the surplus has nothing to spend
+        let wallet_balances = [(surplus_wallet, surplus_balance)];
+        for distribution in [
+            EBuyDistribution::Greedy,
+            EBuyDistribution::EvenSplit,
+            EBuyDistribution::RandomWeighted,
+        ] {
+            let (allocations, remaining) = distribute_buy_amount(
+                distribution,
+                &wallet_balances,
+                surplus_balance,
+                U256::from(500),
+                &mut rng,
+            );
+            assert!(allocations.is_empty(), "{:?}", distribution);
+            assert_eq!(remaining, U256::from(500), "{:?}", distribution);
+        }
+
+        // the remaining amount exactly drains the small wallet, the large one is left alone
+        let wallet_balances = [
+            (surplus_wallet, surplus_balance),
+            (large_wallet, U256::from(3_000)),
+            (small_wallet, U256::from(1_500)),
+        ];
+        let (allocations, remaining) =
+            allocate_buy_amount(&wallet_balances, surplus_balance, U256::from(500), &mut rng);
+        assert_eq!(allocations, vec![(small_wallet, U256::from(500))]);
+        assert!(remaining.is_zero());
+
+        // past the small wallet, the rest goes to the large one
+        let (allocations, remaining) =
+            allocate_buy_amount(&wallet_balances, surplus_balance, U256::from(600), &mut rng);
+        assert_eq!(
+            allocations,
+            vec![
+                (small_wallet, U256::from(500)),
+                (large_wallet, U256::from(100))
+            ]
+        );
+        assert!(remaining.is_zero());
+    }
+
+    #[tokio::test]
+    async fn simulate_raw_tx_calls_and_estimates_the_signed_tx() {
+        let mock_rpc = MockRpc::new();
+        let wallet =
+            ethers::signers::LocalWallet::new(&mut rand::thread_rng()).with_chain_id(1_u64);
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::random())
+            .value(U256::from(1000))
+            .nonce(7)
+            .gas(21000)
+            .gas_price(1)
+            .chain_id(1)
+            .into();
+        let signature = wallet.sign_transaction(&tx).await.unwrap();
+        let raw_tx = tx.rlp_signed(&signature);
+
+        mock_rpc.push_call_outputs([vec![ethers::abi::Token::Uint(U256::from(42))]]);
+        mock_rpc.push_responses("eth_estimateGas", [U256::from(21000)]);
+
+        let (amount_out, gas_estimate) = simulate_raw_tx(&mock_rpc.provider(), &raw_tx)
+            .await
+            .unwrap();
+        assert_eq!(amount_out, Some(U256::from(42)));
+        assert_eq!(gas_estimate, U256::from(21000));
+
+        let mut expected_tx = tx.clone();
+        expected_tx.set_from(wallet.address());
+        assert_eq!(
+            mock_rpc.requests("eth_call"),
+            vec![serde_json::to_value((&expected_tx, "latest")).unwrap()]
+        );
+        assert_eq!(
+            mock_rpc.requests("eth_estimateGas"),
+            vec![serde_json::to_value([&expected_tx]).unwrap()]
+        );
+    }
+
+    #[tokio::test]
+    async fn mm_group_semaphore_caps_concurrent_groups() {
+        let group_semaphore = new_mm_group_semaphore(Some(2));
+        let buying = Arc::new(std::sync::atomic::AtomicU32::new(0));
+        let max_buying = Arc::new(std::sync::atomic::AtomicU32::new(0));
+
+        let mut set = tokio::task::JoinSet::new();
+        for _ in 0..5 {
+            let group_semaphore = group_semaphore.clone();
+            let buying = buying.clone();
+            let max_buying = max_buying.clone();
+            set.spawn(async move {
+                let _permit = acquire_mm_group_permit(&group_semaphore).await.unwrap();
+                let now_buying = buying.fetch_add(1, std::sync::atomic::Ordering::SeqCst) + 1;
+                max_buying.fetch_max(now_buying, std::sync::atomic::Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                buying.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+            });
+        }
+        while set.join_next().await.is_some() {}
+
+        assert_eq!(max_buying.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert!(new_mm_group_semaphore(Some(0)).is_none());
+        assert!(new_mm_group_semaphore(None).is_none());
+    }
+
+    #[test]
+    fn own_wallet_tx_is_skipped() {
+        let buyer = Address::random();
+        let market_maker = Address::random();
+        let external = Address::random();
+        let own_wallets = OwnWallets::new(true, [buyer, market_maker]);
+
+        assert!(own_wallets.contains(&buyer));
+        assert!(own_wallets.contains(&market_maker));
+        assert!(!own_wallets.contains(&external));
+
+        // SKIP_OWN_WALLET_TX=false, every trade triggers
+        assert!(!OwnWallets::new(false, [buyer]).contains(&buyer));
+    }
+
+    #[test]
+    fn two_token_config_runs_one_market_maker_per_token() {
+        use crate::constants::{Env, TokenConfig};
+
+        let tokens = TokenConfig::parse_tokens(
+            r#"[
+                {"address": "0x0000000000000000000000000000000000000001", "buyTax": 3, "sellTax": 5, "router": "UNISWAP3_ROUTERS"},
+                {"address": "0x0000000000000000000000000000000000000002", "floorPrice": 0.0001, "mmConfigPath": "mm_config_b.json"}
+            ]"#,
+        )
+        .unwrap();
+        let env = Env {
+            token_address: tokens[0].address,
+            token: tokens[0].clone(),
+            tokens,
+            ..Default::default()
+        };
+
+        // market_make spawns one market make loop per token env
+        let token_envs = env.token_envs();
+        assert_eq!(token_envs.len(), 2);
+        assert_eq!(token_envs[0].token_address, Address::from_low_u64_be(1));
+        assert_eq!(token_envs[0].token.buy_tax, 3.0);
+        assert_eq!(token_envs[0].token.sell_tax, 5.0);
+        assert_eq!(
+            token_envs[0].token.router.as_deref(),
+            Some("UNISWAP3_ROUTERS")
+        );
+        assert_eq!(token_envs[1].token_address, Address::from_low_u64_be(2));
+        assert_eq!(token_envs[1].token.buy_tax, 0.0);
+        assert_eq!(token_envs[1].token.floor_price, Some(0.0001));
+        assert_eq!(
+            token_envs[1].token.mm_config_path.as_deref(),
+            Some("mm_config_b.json")
+        );
+        // the loops share the exit flag
+        assert!(Arc::ptr_eq(&token_envs[0].exit, &token_envs[1].exit));
+
+        assert!(TokenConfig::parse_tokens("[]").is_err());
+    }
+
+    #[tokio::test]
+    async fn only_under_approved_wallets_are_approved() {
+        let mock_rpc = MockRpc::new();
+        let wallets = [Address::random(), Address::random(), Address::random()];
+        // (balance, allowance) of each wallet: approved, allowance below balance, no allowance yet
+        let balances_and_allowances = [
+            (U256::from(1000), U256::MAX),
+            (U256::from(1000), U256::from(999)),
+            (U256::zero(), U256::zero()),
+        ];
+        // reads go balance then allowance per wallet
+        mock_rpc.push_call_outputs(balances_and_allowances.iter().flat_map(
+            |(balance, allowance)| {
+                [
+                    vec![ethers::abi::Token::Uint(*balance)],
+                    vec![ethers::abi::Token::Uint(*allowance)],
+                ]
+            },
+        ));
+
+        let under_approved_wallets = find_under_approved_wallets(
+            Address::random(),
+            &wallets,
+            &[Address::random()],
+            Arc::new(mock_rpc.provider()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(under_approved_wallets, vec![wallets[1], wallets[2]]);
+        assert!(!is_under_approved(U256::from(1000), U256::from(1000)));
+    }
+
+    #[test]
+    fn panic_sells_skip_wallets_without_tokens() {
+        let wallets = [Address::random(), Address::random(), Address::random()];
+        let balances_and_allowances = [
+            (U256::zero(), U256::zero()),
+            (U256::from(1000), U256::MAX),
+            (U256::from(500), U256::zero()),
+        ];
+
+        let panic_sells = plan_panic_sells(&wallets, &balances_and_allowances);
+        assert_eq!(
+            panic_sells,
+            vec![
+                PanicSellPlan {
+                    address: wallets[1],
+                    sell_amount: U256::from(1000),
+                    is_approve_needed: false,
+                },
+                PanicSellPlan {
+                    address: wallets[2],
+                    sell_amount: U256::from(500),
+                    is_approve_needed: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn token_guard_checks_allowlist_and_blacklist() {
+        let (token, other_token, honeypot_pair) =
+            (Address::random(), Address::random(), Address::random());
+
+        // allowed
+        let token_guard = TokenGuard::new([token], [honeypot_pair]);
+        assert!(token_guard.is_allowed(&token));
+        assert!(!token_guard.is_blocked(&token));
+        // not in the allowlist or blacklisted
+        assert!(!token_guard.is_allowed(&other_token));
+        assert!(token_guard.is_blocked(&honeypot_pair));
+        assert!(!TokenGuard::new([token], [token]).is_allowed(&token));
+
+        // empty lists allow every token
+        let token_guard = TokenGuard::default();
+        assert!(token_guard.is_allowed(&token));
+        assert!(token_guard.is_allowed(&other_token));
+        assert!(!token_guard.is_blocked(&honeypot_pair));
+        assert!(!TokenGuard::new([], [other_token]).is_allowed(&other_token));
+    }
+
+    #[test]
+    fn buy_distributions_cover_the_amount_within_spendable_balances() {
+        let mut rng = rand::thread_rng();
+        let surplus_balance = U256::from(1_000);
+        for distribution in [
+            EBuyDistribution::Greedy,
+            EBuyDistribution::EvenSplit,
+            EBuyDistribution::RandomWeighted,
+        ] {
+            for _ in 0..1_000 {
+                let wallet_balances: Vec<(Address, U256)> = (0..rng.gen_range(0..8))
+                    .map(|_| (Address::random(), U256::from(rng.gen_range(0..5_000u64))))
+                    .collect();
+                let total_spendable = wallet_balances
+                    .iter()
+                    .fold(U256::zero(), |total, (_, balance)| {
+                        total + balance.saturating_sub(surplus_balance)
+                    });
+                let buy_amount = U256::from(rng.gen_range(0..20_000u64));
+                let (allocations, remaining) = distribute_buy_amount(
+                    distribution,
+                    &wallet_balances,
+                    surplus_balance,
+                    buy_amount,
+                    &mut rng,
+                );
+
+                let allocated = allocations
+                    .iter()
+                    .fold(U256::zero(), |total, (_, amount)| total + amount);
+                assert_eq!(allocated + remaining, buy_amount, "{:?}", distribution);
+                assert_eq!(
+                    allocated,
+                    buy_amount.min(total_spendable),
+                    "{:?}",
+                    distribution
+                );
+                for (wallet_address, amount) in allocations {
+                    let (_, eth_balance) = wallet_balances
+                        .iter()
+                        .find(|(address, _)| *address == wallet_address)
+                        .unwrap();
+                    assert!(!amount.is_zero());
+                    assert!(amount <= eth_balance.saturating_sub(surplus_balance));
+                }
+            }
+        }
+
+        // an even split spreads the amount over every wallet able to pay it
+        let wallet_balances: Vec<(Address, U256)> = [1_500u64, 5_000, 5_000]
+            .iter()
+            .map(|balance| (Address::random(), U256::from(*balance)))
+            .collect();
+        let (allocations, remaining) = distribute_buy_amount(
+            EBuyDistribution::EvenSplit,
+            &wallet_balances,
+            surplus_balance,
+            U256::from(3_500),
+            &mut rng,
+        );
+        assert!(remaining.is_zero());
+        let amounts: Vec<U256> = allocations.iter().map(|(_, amount)| *amount).collect();
+        assert_eq!(
+            amounts,
+            vec![U256::from(500), U256::from(1_500), U256::from(1_500)]
+        );
+    }
+
+    #[test]
+    fn retain_percent_scales_with_the_balance() {
+        let mut rng = rand::thread_rng();
+        let mm_settings = MmSettings {
+            mnemonic: String::new(),
+            max_wallets_count: None,
+            min_buy_volume: None,
+            max_buy_volume: None,
+            min_delay_time: None,
+            max_delay_time: None,
+            min_retain_token: Some(100),
+            max_retain_token: Some(200),
+            min_retain_percent: Some(5.0),
+            max_retain_percent: Some(10.0),
+            max_daily_volume_eth: None,
+            max_per_wallet_volume_eth: None,
+            buy_sell_ratio: None,
+            action_randomness: None,
+            slippage_bps: None,
+            start_index: None,
+        };
+
+        for token_balance in [
+            parse_ether(50_000_000).unwrap(),
+            U256::from(7),
+            U256::zero(),
+        ] {
+            for _ in 0..100 {
+                let retain_amount =
+                    compute_retain_amount(&mm_settings, token_balance, 18, &mut rng).unwrap();
+                assert!(retain_amount >= token_balance * 5 / 100);
+                assert!(retain_amount <= token_balance * 10 / 100);
+                // the sell amount never underflows
+                assert!(token_balance.checked_sub(retain_amount).is_some());
+            }
+        }
+
+        // the absolute retain token does not fit a tiny balance
+        let absolute_settings = MmSettings {
+            min_retain_percent: None,
+            max_retain_percent: None,
+            ..mm_settings.clone()
+        };
+        assert!(compute_retain_amount(&absolute_settings, U256::from(7), 18, &mut rng).is_err());
+        let retain_amount = compute_retain_amount(
+            &absolute_settings,
+            parse_ether(1_000).unwrap(),
+            18,
+            &mut rng,
+        )
+        .unwrap();
+        assert!((parse_ether(100).unwrap()..=parse_ether(200).unwrap()).contains(&retain_amount));
+
+        let invalid_settings = MmSettings {
+            max_retain_percent: Some(150.0),
+            ..mm_settings
+        };
+        assert!(compute_retain_amount(&invalid_settings, U256::from(7), 18, &mut rng).is_err());
+    }
+
+    #[test]
+    fn tokens_received_are_read_from_the_buy_receipt() {
+        let (token_address, wallet) = (Address::random(), Address::random());
+        let transfer = |token: Address, to: Address, amount: u64| Log {
+            address: token,
+            topics: vec![
+                H256::from(keccak256("Transfer(address,address,uint256)")),
+                H256::from(Address::random()),
+                H256::from(to),
+            ],
+            data: Bytes::from(ethers::abi::encode(&[ethers::abi::Token::Uint(
+                amount.into(),
+            )])),
+            ..Default::default()
+        };
+
+        // the token swallowed the bought amount, only the weth moved
+        let receipt = TransactionReceipt {
+            status: Some(U64::one()),
+            logs: vec![transfer(Address::random(), wallet, 1_000)],
+            ..Default::default()
+        };
+        assert!(compute_tokens_received(&receipt, token_address, wallet).is_zero());
+
+        // the tax transfer to another address is not received
+        let receipt = TransactionReceipt {
+            status: Some(U64::one()),
+            logs: vec![
+                transfer(token_address, Address::random(), 50),
+                transfer(token_address, wallet, 450),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            compute_tokens_received(&receipt, token_address, wallet),
+            U256::from(450)
+        );
+    }
+
+    #[test]
+    fn bloxroute_tip_address_follows_the_network() {
+        assert_eq!(
+            get_bloxroute_tip_address(&ENetwork::EthMainnet),
+            Address::from_str("0x965Df5Ff6116C395187E288e5C87fb96CfB8141c").unwrap()
+        );
+        assert_eq!(
+            get_bloxroute_tip_address(&ENetwork::BscMainnet),
+            Address::from_str("0x74c5F8C6ffe41AD4789602BDB9a48E6Cad623520").unwrap()
+        );
+    }
+
+    #[test]
+    fn token_amounts_get_the_suffix_of_their_magnitude() {
+        let amount =
+            |tokens: u64, decimals: u8| U256::from(tokens) * U256::exp10(decimals as usize);
+        for decimals in [6, 18] {
+            assert_eq!(format_token_amount(amount(999, decimals), decimals), "999");
+            assert_eq!(
+                format_token_amount(amount(1_500, decimals), decimals),
+                "1.5K"
+            );
+            assert_eq!(
+                format_token_amount(amount(2_000_000, decimals), decimals),
+                "2M"
+            );
+            assert_eq!(
+                format_token_amount(amount(123_456_789_000, decimals), decimals),
+                "123.457B"
+            );
+            assert_eq!(
+                format_token_amount(amount(7_000_000_000_000, decimals), decimals),
+                "7T"
+            );
+        }
+        assert_eq!(format_token_amount(U256::from(1_234_567), 6), "1.235");
+        assert_eq!(format_token_amount(U256::zero(), 18), "0");
+        assert!(format_token_amount(U256::MAX, 18).ends_with('T'));
+    }
+
+    #[tokio::test]
+    async fn sell_approves_the_router_only_when_under_approved() {
+        let sell_amount = U256::from(1000);
+        assert_eq!(
+            plan_sell_approval(U256::MAX, sell_amount, false),
+            SellApproval::Approved
+        );
+        assert_eq!(
+            plan_sell_approval(U256::zero(), sell_amount, true),
+            SellApproval::ApproveFirst
+        );
+        assert_eq!(
+            plan_sell_approval(U256::from(999), sell_amount, false),
+            SellApproval::Skip
+        );
+
+        // an approved wallet sells with its nonce, no approve is sent
+        let mock_rpc = MockRpc::new();
+        let nonce = approve_if_under_approved(
+            Address::random(),
+            Address::random(),
+            U256::MAX,
+            sell_amount,
+            U256::from(7),
+            Arc::new(mock_rpc.provider()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(nonce, U256::from(7));
+        assert!(mock_rpc.sent_txs().is_empty());
+    }
+
+    #[tokio::test]
+    async fn pair_created_on_the_third_poll_is_waited_for() {
+        let pair_address = Address::random();
+        let polls = Mutex::new(0);
+        let fetch_pair_addresses = || async {
+            let mut polls = polls.lock().unwrap();
+            *polls += 1;
+            match *polls {
+                1 => Err(anyhow::anyhow!(
+                    "Pair address not found for the given tokens"
+                )),
+                2 => Ok(Vec::new()),
+                _ => Ok(vec![pair_address]),
+            }
+        };
+
+        let pair_addresses =
+            wait_for_pair_addresses(fetch_pair_addresses, Duration::from_millis(10), 5)
+                .await
+                .unwrap();
+        assert_eq!(pair_addresses, vec![pair_address]);
+        assert_eq!(*polls.lock().unwrap(), 3);
+
+        let never_created =
+            wait_for_pair_addresses(|| async { Ok(Vec::new()) }, Duration::from_millis(10), 2)
+                .await;
+        assert!(never_created.is_err());
+    }
+
+    #[test]
+    fn bsc_messages_are_labelled_in_bnb() {
+        let native_symbol = get_native_symbol(&ENetwork::BscMainnet);
+        assert_eq!(native_symbol, "BNB");
+
+        let buy_message = format_buy_message(
+            "0xbuy",
+            true,
+            0.0001,
+            parse_ether("0.5").unwrap(),
+            native_symbol,
+        );
+        assert!(buy_message.contains("success"), "{}", buy_message);
+        assert!(
+            buy_message.ends_with("Token price: 0.0001 BNB\nVolume: \"0.500000000000000000\" BNB"),
+            "{}",
+            buy_message
+        );
+        assert!(!buy_message.contains("ETH"));
+
+        let sell_message = format_sell_message(H256::zero(), false, 0.0001, "1000", "MM", "ETH");
+        assert!(sell_message.contains("failed \nToken price: 0.0001 ETH\nVolume: \"1000\" \"MM\""));
+        assert_eq!(get_native_symbol(&ENetwork::FtmMainnet), "FTM");
+    }
+
+    #[test]
+    fn slippage_keeps_fractional_percents_in_bps() {
+        assert_eq!(percent_to_bps(0.25).unwrap(), 25);
+        assert_eq!(percent_to_bps(0.05).unwrap(), 5);
+        assert_eq!(percent_to_bps(12.34).unwrap(), 1234);
+        assert!(percent_to_bps(-0.5).is_err());
+        assert!(percent_to_bps(100.5).is_err());
+        assert_eq!(add_tax_bps(25, 5.5), 575);
+
+        let amount_out = U256::from(1_000_000);
+        assert_eq!(apply_slippage_bps(amount_out, 25), U256::from(997_500));
+        assert_eq!(apply_slippage_bps(amount_out, 1), U256::from(999_900));
+
+        // 100% accepts any amount out, beyond is rejected or capped
+        assert_eq!(percent_to_bps(100.0).unwrap(), MAX_SLIPPAGE_BPS);
+        assert_eq!(validate_slippage_bps(MAX_SLIPPAGE_BPS).unwrap(), 10_000);
+        assert!(validate_slippage_bps(10_001).is_err());
+        assert_eq!(
+            apply_slippage_bps(amount_out, MAX_SLIPPAGE_BPS),
+            U256::zero()
+        );
+        assert_eq!(add_tax_bps(9_000, 20.0), MAX_SLIPPAGE_BPS);
+        assert_eq!(apply_slippage_bps(amount_out, 20_000), U256::zero());
+    }
+
+    #[test]
+    fn random_disperse_values_fit_the_balance() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let (min, max) = (parse_ether("0.002").unwrap(), parse_ether("0.004").unwrap());
+        let available_balance = parse_ether("0.05").unwrap();
+        let transfer_values = plan_disperse_eth_values(
+            DisperseEthAmount::Random { min, max },
+            12,
+            available_balance,
+            &mut rng,
+        )
+        .unwrap();
+        assert_eq!(transfer_values.len(), 12);
+        assert!(transfer_values
+            .iter()
+            .all(|value| *value >= min && *value <= max));
+        assert!(transfer_values
+            .iter()
+            .any(|value| *value != transfer_values[0]));
+        let total_value = transfer_values
+            .iter()
+            .fold(U256::zero(), |total, value| total + value);
+        assert!(total_value <= available_balance);
+
+        // uniform mode sends the same amount, over the balance is refused
+        assert_eq!(
+            plan_disperse_eth_values(
+                DisperseEthAmount::Uniform(min),
+                3,
+                available_balance,
+                &mut rng
+            )
+            .unwrap(),
+            vec![min; 3]
+        );
+        // 13 draws may fit 0.05 but 13 * 0.004 does not, refused whatever the draws
+        assert!(plan_disperse_eth_values(
+            DisperseEthAmount::Random { min, max },
+            13,
+            available_balance,
+            &mut rng
+        )
+        .is_err());
+        assert!(plan_disperse_eth_values(
+            DisperseEthAmount::Uniform(max),
+            13,
+            available_balance,
+            &mut rng
+        )
+        .is_err());
+
+        assert_eq!(
+            "0.002..0.004".parse::<DisperseEthAmount>().unwrap(),
+            DisperseEthAmount::Random { min, max }
+        );
+        assert_eq!(
+            "0.002".parse::<DisperseEthAmount>().unwrap(),
+            DisperseEthAmount::Uniform(min)
+        );
+        assert!("0.004..0.002".parse::<DisperseEthAmount>().is_err());
+    }
+}
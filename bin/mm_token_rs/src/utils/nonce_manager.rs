@@ -0,0 +1,203 @@
+use std::{
+    collections::BTreeMap,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use ethers::types::{Address, U256};
+use mm_token_utils::env::get_env;
+
+// services of the same process share the store file
+static NONCE_STORE_LOCK: Mutex<()> = Mutex::new(());
+
+/**
+ * last submitted nonce per address, persisted in `NONCE_STORE_PATH`
+ * so a restart does not reuse the nonces of txs still in the mempool
+ */
+#[derive(Debug, Clone, Default)]
+pub struct NonceManager {
+    store_path: Option<PathBuf>,
+    nonces: Arc<Mutex<BTreeMap<Address, U256>>>,
+}
+
+impl NonceManager {
+    /// empty `NONCE_STORE_PATH` keeps nonces in memory only
+    pub fn new() -> Self {
+        let store_path = get_env("NONCE_STORE_PATH", Some("".to_string()));
+        if store_path.is_empty() {
+            return Self::default();
+        }
+        Self::load(PathBuf::from(store_path))
+    }
+
+    pub fn load(store_path: PathBuf) -> Self {
+        let nonces = Self::read_store(&store_path);
+        Self {
+            store_path: Some(store_path),
+            nonces: Arc::new(Mutex::new(nonces)),
+        }
+    }
+
+    /// the next nonce is after the stored nonces while they may still be in the mempool,
+    /// a node behind the one that took them reports a lower pending count.
+    /// The entry is dropped once the mined count is past them
+    pub async fn reconcile(
+        &self,
+        address: &Address,
+        pending_nonce: U256,
+        mined_nonce: U256,
+    ) -> U256 {
+        let last_nonce = {
+            let mut nonces = self.nonces.lock().unwrap();
+            let Some(last_nonce) = nonces.get(address).copied() else {
+                return pending_nonce;
+            };
+            if last_nonce < mined_nonce {
+                // a mined entry would only grow the store
+                nonces.remove(address);
+                None
+            } else {
+                Some(last_nonce)
+            }
+        };
+        let Some(last_nonce) = last_nonce else {
+            if let Err(err) = self.forget_stored(*address).await {
+                log::warn!(
+                    "[NonceManager] cannot remove {:?} from the store: {:?}",
+                    address,
+                    err
+                );
+            }
+            return pending_nonce;
+        };
+
+        let next_nonce = pending_nonce.max(last_nonce + U256::one());
+        if next_nonce > pending_nonce {
+            log::warn!(
+                "[NonceManager] {:?} submitted nonce {:?} is not mined yet, resume from {:?} instead of the pending {:?}",
+                address,
+                last_nonce,
+                next_nonce,
+                pending_nonce
+            );
+        }
+        next_nonce
+    }
+
+    /// record a submitted nonce, merged into the store written by other services
+    pub async fn record_submitted(&self, address: Address, nonce: U256) -> anyhow::Result<()> {
+        let nonces = {
+            let mut nonces = self.nonces.lock().unwrap();
+            let last_nonce = nonces.entry(address).or_default();
+            *last_nonce = (*last_nonce).max(nonce);
+            nonces.clone()
+        };
+
+        let Some(store_path) = self.store_path.clone() else {
+            return Ok(());
+        };
+        tokio::task::spawn_blocking(move || {
+            let _store_lock = NONCE_STORE_LOCK.lock().unwrap();
+            let mut stored_nonces = Self::read_store(&store_path);
+            for (address, nonce) in nonces {
+                let stored_nonce = stored_nonces.entry(address).or_default();
+                *stored_nonce = (*stored_nonce).max(nonce);
+            }
+            std::fs::write(&store_path, serde_json::to_string_pretty(&stored_nonces)?)?;
+            Ok(())
+        })
+        .await?
+    }
+
+    async fn forget_stored(&self, address: Address) -> anyhow::Result<()> {
+        let Some(store_path) = self.store_path.clone() else {
+            return Ok(());
+        };
+        tokio::task::spawn_blocking(move || {
+            let _store_lock = NONCE_STORE_LOCK.lock().unwrap();
+            let mut stored_nonces = Self::read_store(&store_path);
+            if stored_nonces.remove(&address).is_some() {
+                std::fs::write(&store_path, serde_json::to_string_pretty(&stored_nonces)?)?;
+            }
+            Ok(())
+        })
+        .await?
+    }
+
+    fn read_store(store_path: &PathBuf) -> BTreeMap<Address, U256> {
+        let Ok(content) = std::fs::read_to_string(store_path) else {
+            return BTreeMap::new();
+        };
+        match serde_json::from_str(&content) {
+            Ok(nonces) => nonces,
+            Err(err) => {
+                log::warn!(
+                    "[NonceManager] ignore invalid store {:?}: {:?}",
+                    store_path,
+                    err
+                );
+                BTreeMap::new()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn nonce_store_survives_restart() {
+        let store_path = std::env::temp_dir().join(format!("nonces-{}.json", Address::random()));
+        let address = Address::random();
+
+        let nonce_manager = NonceManager::load(store_path.clone());
+        nonce_manager
+            .record_submitted(address, U256::from(7))
+            .await
+            .unwrap();
+        nonce_manager
+            .record_submitted(address, U256::from(8))
+            .await
+            .unwrap();
+        // crash before the pending txs are mined
+        drop(nonce_manager);
+
+        // the node still reports 7 as pending, nonces 7 and 8 may be in the mempool
+        let nonce_manager = NonceManager::load(store_path.clone());
+        assert_eq!(
+            nonce_manager
+                .reconcile(&address, U256::from(7), U256::from(7))
+                .await,
+            U256::from(9)
+        );
+        assert_eq!(
+            NonceManager::read_store(&store_path)[&address],
+            U256::from(8)
+        );
+        // a pending count past the store wins
+        assert_eq!(
+            nonce_manager
+                .reconcile(&address, U256::from(12), U256::from(8))
+                .await,
+            U256::from(12)
+        );
+        assert_eq!(
+            nonce_manager
+                .reconcile(&Address::random(), U256::from(3), U256::from(3))
+                .await,
+            U256::from(3)
+        );
+
+        // once nonce 8 is mined the entry is stale
+        assert_eq!(
+            nonce_manager
+                .reconcile(&address, U256::from(9), U256::from(9))
+                .await,
+            U256::from(9)
+        );
+        assert!(!NonceManager::read_store(&store_path).contains_key(&address));
+
+        std::fs::remove_file(store_path).unwrap();
+    }
+}
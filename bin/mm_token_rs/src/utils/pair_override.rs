@@ -0,0 +1,132 @@
+use std::{str::FromStr, sync::Arc};
+
+use ethers::{
+    providers::{Http, Provider},
+    types::Address,
+};
+use mm_token_utils::{abi::IUniswapV2PairAbigen, env::get_env};
+use tokio::sync::OnceCell;
+
+/**
+ * `TOKEN_WETH_PAIR_OVERRIDE` pins the token/weth pair when the factory lookup is wrong.
+ * The pair tokens are read and checked on the first lookup, clones share the result
+ */
+#[derive(Debug, Clone)]
+pub struct PairOverride {
+    // a malformed TOKEN_WETH_PAIR_OVERRIDE fails every token/weth lookup instead of the startup
+    pair_address: Result<Address, String>,
+    token_address: Address,
+    weth_address: Address,
+    // token0 of the pair once it is checked to hold the token and weth
+    token0_address: Arc<OnceCell<Address>>,
+}
+
+impl PairOverride {
+    pub fn new(pair_address: Address, token_address: Address, weth_address: Address) -> Self {
+        Self {
+            pair_address: Ok(pair_address),
+            token_address,
+            weth_address,
+            token0_address: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// none without `TOKEN_WETH_PAIR_OVERRIDE`
+    pub fn from_env(token_address: Address, weth_address: Address) -> Option<Self> {
+        Self::parse(
+            &get_env("TOKEN_WETH_PAIR_OVERRIDE", Some("".to_string())),
+            token_address,
+            weth_address,
+        )
+    }
+
+    fn parse(pair_override: &str, token_address: Address, weth_address: Address) -> Option<Self> {
+        if pair_override.is_empty() {
+            return None;
+        }
+        let pair_address = Address::from_str(pair_override).map_err(|err| {
+            log::error!(
+                "[PairOverride] invalid TOKEN_WETH_PAIR_OVERRIDE {:?}: {:?}",
+                pair_override,
+                err
+            );
+            format!(
+                "invalid TOKEN_WETH_PAIR_OVERRIDE {:?}: {}",
+                pair_override, err
+            )
+        });
+
+        Some(Self {
+            pair_address,
+            token_address,
+            weth_address,
+            token0_address: Arc::new(OnceCell::new()),
+        })
+    }
+
+    /// the pair address and whether first_token is the token0, none unless the requested pair is token/weth
+    pub async fn resolve(
+        &self,
+        first_token: &Address,
+        second_token: &Address,
+        http_provider: Arc<Provider<Http>>,
+    ) -> anyhow::Result<Option<(Address, bool)>> {
+        let mut requested_tokens = [*first_token, *second_token];
+        let mut expected_tokens = [self.token_address, self.weth_address];
+        requested_tokens.sort();
+        expected_tokens.sort();
+        if requested_tokens != expected_tokens {
+            return Ok(None);
+        }
+        let pair_address = self.pair_address.clone().map_err(anyhow::Error::msg)?;
+
+        let token0_address = self
+            .token0_address
+            .get_or_try_init(|| async {
+                let pair = IUniswapV2PairAbigen::new(pair_address, http_provider);
+                let token_0 = pair.token_0();
+                let token_1 = pair.token_1();
+                let (token0_address, token1_address) = tokio::join!(token_0.call(), token_1.call());
+                let mut pair_tokens = [token0_address?, token1_address?];
+                let token0_address = pair_tokens[0];
+                pair_tokens.sort();
+                if pair_tokens != expected_tokens {
+                    return Err(anyhow::anyhow!(
+                        "TOKEN_WETH_PAIR_OVERRIDE {:?} is not a token/weth pair, tokens {:?}",
+                        pair_address,
+                        pair_tokens
+                    ));
+                }
+                anyhow::Ok(token0_address)
+            })
+            .await?;
+
+        Ok(Some((pair_address, first_token == token0_address)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn malformed_override_fails_the_token_weth_lookup() {
+        let (token_address, weth_address) = (Address::random(), Address::random());
+        // nothing listens, the malformed override fails before any rpc call
+        let http_provider = Arc::new(Provider::<Http>::try_from("http://127.0.0.1:1").unwrap());
+        let pair_override = PairOverride::parse("0x1234", token_address, weth_address).unwrap();
+
+        let err = pair_override
+            .resolve(&token_address, &weth_address, http_provider.clone())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid TOKEN_WETH_PAIR_OVERRIDE"));
+        // other pairs are not overridden
+        assert!(pair_override
+            .resolve(&token_address, &Address::random(), http_provider)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(PairOverride::parse("", token_address, weth_address).is_none());
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    collections::BTreeMap,
+    fs::OpenOptions,
+    io::{ErrorKind, Write},
+    path::PathBuf,
+};
+
+use ethers::{
+    types::{Address, TransactionReceipt, H256, U256},
+    utils::keccak256,
+};
+use mm_token_utils::env::get_env;
+use serde::{Deserialize, Serialize};
+
+use crate::types::WalletGroup;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PnlTotals {
+    // eth in of the buys
+    pub eth_spent: U256,
+    // eth out of the sells
+    pub eth_received: U256,
+}
+
+// one line of the ledger file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PnlEntry {
+    token_address: Address,
+    group: WalletGroup,
+    #[serde(flatten)]
+    totals: PnlTotals,
+}
+
+/**
+ * eth spent by the buys and received by the sells of each wallet group per token, appended as json lines
+ * to `PNL_LEDGER_PATH` so the trading processes never rewrite each other's records and the telegram bot
+ * sums what they recorded
+ */
+#[derive(Debug, Clone, Default)]
+pub struct PnlLedger {
+    ledger_path: Option<PathBuf>,
+}
+
+impl PnlLedger {
+    /// empty `PNL_LEDGER_PATH` disables the ledger
+    pub fn from_env() -> Self {
+        let ledger_path = get_env("PNL_LEDGER_PATH", Some("pnl_ledger.jsonl".to_string()));
+        if ledger_path.is_empty() {
+            return Self::default();
+        }
+        Self::new(PathBuf::from(ledger_path))
+    }
+
+    pub fn new(ledger_path: PathBuf) -> Self {
+        Self {
+            ledger_path: Some(ledger_path),
+        }
+    }
+
+    pub fn record_buy(
+        &self,
+        token_address: Address,
+        group: WalletGroup,
+        eth_amount: U256,
+    ) -> anyhow::Result<()> {
+        self.record(
+            token_address,
+            group,
+            PnlTotals {
+                eth_spent: eth_amount,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn record_sell(
+        &self,
+        token_address: Address,
+        group: WalletGroup,
+        eth_amount: U256,
+    ) -> anyhow::Result<()> {
+        self.record(
+            token_address,
+            group,
+            PnlTotals {
+                eth_received: eth_amount,
+                ..Default::default()
+            },
+        )
+    }
+
+    /// totals of every group which traded `token_address`, an invalid record fails instead of being left out
+    pub fn totals(
+        &self,
+        token_address: &Address,
+    ) -> anyhow::Result<BTreeMap<WalletGroup, PnlTotals>> {
+        let mut totals = BTreeMap::<WalletGroup, PnlTotals>::new();
+        let Some(ledger_path) = &self.ledger_path else {
+            return Ok(totals);
+        };
+        let content = match std::fs::read_to_string(ledger_path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(totals),
+            Err(err) => return Err(err.into()),
+        };
+        // a last line without its newline is still being appended
+        let complete_len = content.rfind('\n').map_or(0, |index| index + 1);
+        for (line_index, line) in content[..complete_len].lines().enumerate() {
+            let entry: PnlEntry = serde_json::from_str(line).map_err(|err| {
+                anyhow::anyhow!(
+                    "[PnlLedger] invalid record {} of {:?}: {}",
+                    line_index + 1,
+                    ledger_path,
+                    err
+                )
+            })?;
+            if entry.token_address != *token_address {
+                continue;
+            }
+            let group_totals = totals.entry(entry.group).or_default();
+            group_totals.eth_spent += entry.totals.eth_spent;
+            group_totals.eth_received += entry.totals.eth_received;
+        }
+
+        Ok(totals)
+    }
+
+    fn record(
+        &self,
+        token_address: Address,
+        group: WalletGroup,
+        totals: PnlTotals,
+    ) -> anyhow::Result<()> {
+        let Some(ledger_path) = &self.ledger_path else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_string(&PnlEntry {
+            token_address,
+            group,
+            totals,
+        })?;
+        line.push('\n');
+        // a single append write, records of concurrent processes do not interleave
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(ledger_path)?
+            .write_all(line.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+/**
+ * eth unwrapped from `weth_address` in a sell receipt, the routers withdraw the weth out before sending it.
+ * Zero when the sell was paid in weth
+ */
+pub fn compute_native_received(tx_receipt: &TransactionReceipt, weth_address: Address) -> U256 {
+    let withdrawal_topic = H256::from(keccak256("Withdrawal(address,uint256)"));
+    tx_receipt
+        .logs
+        .iter()
+        .filter(|log| log.address == weth_address && log.topics.first() == Some(&withdrawal_topic))
+        .fold(U256::zero(), |received, log| {
+            received + U256::from_big_endian(&log.data)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{PnlGroupReport, PnlReport};
+    use ethers::{
+        types::{Bytes, Log},
+        utils::{parse_ether, parse_units},
+    };
+
+    #[test]
+    fn pnl_from_a_seeded_ledger() {
+        let ledger_path = std::env::temp_dir().join(format!("pnl-{}.jsonl", Address::random()));
+        let (token_address, weth_address) = (Address::random(), Address::random());
+        let pnl_ledger = PnlLedger::new(ledger_path.clone());
+        pnl_ledger
+            .record_buy(token_address, WalletGroup::Buyer, parse_ether(2).unwrap())
+            .unwrap();
+        pnl_ledger
+            .record_buy(token_address, WalletGroup::Buyer, parse_ether(1).unwrap())
+            .unwrap();
+        // the sell eth is read from the weth withdrawal of the receipt
+        let sell_receipt = TransactionReceipt {
+            logs: vec![Log {
+                address: weth_address,
+                topics: vec![
+                    H256::from(ethers::utils::keccak256("Withdrawal(address,uint256)")),
+                    H256::random(),
+                ],
+                data: Bytes::from(ethers::abi::encode(&[ethers::abi::Token::Uint(
+                    parse_ether("1.5").unwrap(),
+                )])),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        pnl_ledger
+            .record_sell(
+                token_address,
+                WalletGroup::Buyer,
+                compute_native_received(&sell_receipt, weth_address),
+            )
+            .unwrap();
+        // another token is not mixed in
+        pnl_ledger
+            .record_buy(
+                Address::random(),
+                WalletGroup::Buyer,
+                parse_ether(5).unwrap(),
+            )
+            .unwrap();
+
+        let totals = PnlLedger::new(ledger_path.clone())
+            .totals(&token_address)
+            .unwrap();
+        let buyer_totals = totals[&WalletGroup::Buyer];
+        assert_eq!(buyer_totals.eth_spent, parse_ether(3).unwrap());
+        assert_eq!(buyer_totals.eth_received, parse_ether("1.5").unwrap());
+        assert!(!totals.contains_key(&WalletGroup::Seller));
+
+        // 1M tokens left at 0.000001 eth
+        let group_report = PnlGroupReport::new(
+            WalletGroup::Buyer,
+            buyer_totals.eth_spent,
+            buyer_totals.eth_received,
+            parse_units(1_000_000, 9).unwrap().into(),
+            9,
+            0.000001,
+        )
+        .unwrap();
+        assert!((group_report.holdings_value - 1.0).abs() < 1e-9);
+        assert!((group_report.pnl + 0.5).abs() < 1e-9);
+        let pnl_report = PnlReport {
+            native_symbol: "ETH".to_string(),
+            token_price: 0.000001,
+            groups: vec![group_report],
+        };
+        assert!(pnl_report
+            .summary(3_000.0)
+            .ends_with("Total PnL: -0.5000 ETH ~ $-1500.00"));
+        std::fs::remove_file(ledger_path).unwrap();
+    }
+
+    #[test]
+    fn invalid_ledger_fails_instead_of_being_overwritten() {
+        let ledger_path = std::env::temp_dir().join(format!("pnl-{}.jsonl", Address::random()));
+        let token_address = Address::random();
+        let pnl_ledger = PnlLedger::new(ledger_path.clone());
+        pnl_ledger
+            .record_buy(token_address, WalletGroup::Seller, parse_ether(1).unwrap())
+            .unwrap();
+        // a record still being appended by another process is not read yet
+        let mut ledger_file = OpenOptions::new().append(true).open(&ledger_path).unwrap();
+        ledger_file.write_all(b"{\"tokenAddress\":").unwrap();
+        assert_eq!(
+            pnl_ledger.totals(&token_address).unwrap()[&WalletGroup::Seller].eth_spent,
+            parse_ether(1).unwrap()
+        );
+
+        ledger_file.write_all(b"\n").unwrap();
+        assert!(pnl_ledger.totals(&token_address).is_err());
+        pnl_ledger
+            .record_buy(token_address, WalletGroup::Seller, parse_ether(1).unwrap())
+            .unwrap();
+        let content = std::fs::read_to_string(&ledger_path).unwrap();
+        assert_eq!(content.lines().count(), 3);
+        std::fs::remove_file(ledger_path).unwrap();
+    }
+}
@@ -0,0 +1,427 @@
+use std::sync::Arc;
+
+use cached::{Cached, TimedCache};
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    types::{Address, I256},
+    utils::format_units,
+};
+use mm_token_utils::{
+    abi::ChainlinkAggregatorAbigen,
+    constants::{CHAINLINK_NATIVE_USD_FEEDS, COINGECKO_NATIVE_IDS},
+    env::get_env,
+};
+use provider_utils::enums::ENetwork;
+use tokio::sync::Mutex;
+
+const COINGECKO_SIMPLE_PRICE_URL: &str = "https://api.coingecko.com/api/v3/simple/price";
+
+/**
+ * native token (ETH, BNB, FTM) price in USD from CoinGecko, or from the Chainlink native/USD feed of the
+ * network when CoinGecko fails (e.g. rate limited), reused for `ETH_USD_CACHE_SECONDS` (default 60),
+ * clones share the cache
+ */
+#[derive(Debug, Clone)]
+pub struct PriceOracle<M = Provider<Http>> {
+    http_client: reqwest::Client,
+    coingecko_id: String,
+    coingecko_url: String,
+    coingecko_api_key: Option<String>,
+    chainlink_feed: Option<(Address, Arc<M>)>,
+    eth_usd_cache: Arc<Mutex<TimedCache<(), f64>>>,
+}
+
+impl PriceOracle {
+    /// `COINGECKO_API_KEY` (optional), the coin and the Chainlink feed of `network` read through `provider`
+    pub fn from_env(network: &ENetwork, provider: Arc<Provider<Http>>) -> Self {
+        let cache_seconds: u64 = get_env("ETH_USD_CACHE_SECONDS", Some("60".to_string()))
+            .parse()
+            .unwrap();
+        let coingecko_api_key = std::env::var("COINGECKO_API_KEY")
+            .ok()
+            .filter(|api_key| !api_key.is_empty());
+        let Some(coingecko_id) = COINGECKO_NATIVE_IDS.get(network) else {
+            panic!("COINGECKO_NATIVE_IDS not found in {:?}", network);
+        };
+        let chainlink_feed = CHAINLINK_NATIVE_USD_FEEDS
+            .get(network)
+            .map(|feed_address| (*feed_address, provider));
+        if chainlink_feed.is_none() {
+            log::warn!(
+                "[PriceOracle] no Chainlink native/USD feed in {:?}, CoinGecko only",
+                network
+            );
+        }
+
+        Self::new(
+            cache_seconds,
+            coingecko_id.to_string(),
+            format!(
+                "{}?ids={}&vs_currencies=usd",
+                COINGECKO_SIMPLE_PRICE_URL, coingecko_id
+            ),
+            coingecko_api_key,
+            chainlink_feed,
+        )
+    }
+}
+
+impl<M: Middleware + 'static> PriceOracle<M> {
+    pub fn new(
+        cache_seconds: u64,
+        coingecko_id: String,
+        coingecko_url: String,
+        coingecko_api_key: Option<String>,
+        chainlink_feed: Option<(Address, Arc<M>)>,
+    ) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            coingecko_id,
+            coingecko_url,
+            coingecko_api_key,
+            chainlink_feed,
+            eth_usd_cache: Arc::new(Mutex::new(TimedCache::with_lifespan(cache_seconds))),
+        }
+    }
+
+    /// an error when both CoinGecko and the Chainlink feed fail, never a zero price
+    pub async fn get_native_usd_price(&self) -> anyhow::Result<f64> {
+        if let Some(price) = self.eth_usd_cache.lock().await.cache_get(&()) {
+            return Ok(*price);
+        }
+
+        let price = match self.get_coingecko_price().await {
+            Ok(price) => {
+                log::info!(
+                    "[PriceOracle] current {} price: ${}",
+                    self.coingecko_id,
+                    price
+                );
+                price
+            }
+            Err(coingecko_err) => {
+                log::warn!("{}, fallback to Chainlink", coingecko_err);
+                let price = self.get_chainlink_price().await.map_err(|chainlink_err| {
+                    anyhow::anyhow!(
+                        "[PriceOracle] native price unavailable: {}; {}",
+                        coingecko_err,
+                        chainlink_err
+                    )
+                })?;
+                log::info!(
+                    "[PriceOracle] current {} price (Chainlink): ${}",
+                    self.coingecko_id,
+                    price
+                );
+                price
+            }
+        };
+
+        self.eth_usd_cache.lock().await.cache_set((), price);
+        Ok(price)
+    }
+
+    async fn get_coingecko_price(&self) -> anyhow::Result<f64> {
+        let mut request = self.http_client.get(&self.coingecko_url);
+        if let Some(api_key) = &self.coingecko_api_key {
+            request = request.header("x-cg-demo-api-key", api_key);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "[PriceOracle] failed to get {} price, status code {}",
+                self.coingecko_id,
+                response.status()
+            ));
+        }
+        parse_native_usd_price(&response.text().await?, &self.coingecko_id)
+    }
+
+    async fn get_chainlink_price(&self) -> anyhow::Result<f64> {
+        let Some((feed_address, provider)) = &self.chainlink_feed else {
+            return Err(anyhow::anyhow!(
+                "[PriceOracle] no Chainlink native/USD feed"
+            ));
+        };
+        let feed = ChainlinkAggregatorAbigen::new(*feed_address, provider.clone());
+        let (_, answer, _, _, _) = feed.latest_round_data().call().await?;
+        let decimals = feed.decimals().call().await?;
+        if answer <= I256::zero() {
+            return Err(anyhow::anyhow!(
+                "[PriceOracle] invalid Chainlink answer {}",
+                answer
+            ));
+        }
+
+        Ok(format_units(answer.into_raw(), decimals as u32)?.parse()?)
+    }
+}
+
+/**
+ * auto buys trigger while the token price is under the floor, `FLOOR_PRICE_USD` keeps the floor
+ * dollar stable as ETH moves, the native `FLOOR_PRICE` of the token otherwise
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FloorPrice {
+    Native(f64),
+    Usd(f64),
+}
+
+impl FloorPrice {
+    pub fn from_env(native_floor_price: Option<f64>) -> Self {
+        let usd_floor_price = get_env("FLOOR_PRICE_USD", Some("".to_string()));
+        if !usd_floor_price.is_empty() {
+            return Self::Usd(usd_floor_price.parse().unwrap());
+        }
+        Self::Native(native_floor_price.expect("expect env FLOOR_PRICE or FLOOR_PRICE_USD"))
+    }
+
+    /// `token_price` is in native, converted by `price_oracle` for a USD floor
+    pub async fn is_exceeded_by<M: Middleware + 'static>(
+        &self,
+        token_price: f64,
+        price_oracle: &PriceOracle<M>,
+    ) -> anyhow::Result<bool> {
+        Ok(match self {
+            Self::Native(floor_price) => token_price > *floor_price,
+            Self::Usd(floor_price) => {
+                token_price * price_oracle.get_native_usd_price().await? > *floor_price
+            }
+        })
+    }
+
+    /// a trigger at `token_price` is skipped above the floor, or when a USD floor cannot be priced,
+    /// e.g. CoinGecko down on a network without a Chainlink feed, instead of failing the detector
+    pub async fn skips_trigger<M: Middleware + 'static>(
+        &self,
+        token_price: f64,
+        price_oracle: &PriceOracle<M>,
+    ) -> bool {
+        match self.is_exceeded_by(token_price, price_oracle).await {
+            Ok(false) => false,
+            Ok(true) => {
+                log::warn!(
+                    "token_price {:?} bigger than floor_price {:?}, skip",
+                    token_price,
+                    self
+                );
+                true
+            }
+            Err(err) => {
+                log::warn!(
+                    "[FloorPrice] {:?} unknown, skip token_price {:?}: {:?}",
+                    self,
+                    token_price,
+                    err
+                );
+                true
+            }
+        }
+    }
+}
+
+/**
+ * USD price of `coingecko_id` in a CoinGecko `simple/price` response
+ */
+pub fn parse_native_usd_price(body: &str, coingecko_id: &str) -> anyhow::Result<f64> {
+    let json: serde_json::Value = serde_json::from_str(body)?;
+    json[coingecko_id]["usd"].as_f64().ok_or(anyhow::anyhow!(
+        "[PriceOracle] price data not found in {}",
+        body
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{abi::Token, types::U256};
+    use provider_utils::mock_rpc::MockRpc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// serves `status` and `body` to every request, the requests received are counted and kept
+    async fn serve_coingecko(
+        status: &'static str,
+        body: &'static str,
+    ) -> (String, Arc<AtomicUsize>, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/simple/price", listener.local_addr().unwrap());
+        let requests_count = Arc::new(AtomicUsize::new(0));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let (count, received) = (requests_count.clone(), requests.clone());
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut buffer = [0u8; 4096];
+                let read = stream.read(&mut buffer).await.unwrap();
+                count.fetch_add(1, Ordering::SeqCst);
+                received
+                    .lock()
+                    .await
+                    .push(String::from_utf8_lossy(&buffer[..read]).to_lowercase());
+                let response = format!(
+                    "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests_count, requests)
+    }
+
+    fn chainlink_feed(answer: i64, decimals: u8) -> (Address, Arc<Provider<MockRpc>>) {
+        let feed_address = Address::random();
+        let mock_rpc = MockRpc::new()
+            .on_call(
+                feed_address,
+                "latestRoundData()",
+                &[],
+                &[
+                    Token::Uint(U256::one()),
+                    Token::Int(I256::from(answer).into_raw()),
+                    Token::Uint(U256::zero()),
+                    Token::Uint(U256::zero()),
+                    Token::Uint(U256::one()),
+                ],
+            )
+            .on_call(
+                feed_address,
+                "decimals()",
+                &[],
+                &[Token::Uint(U256::from(decimals))],
+            );
+        (feed_address, Arc::new(mock_rpc.provider()))
+    }
+
+    #[tokio::test]
+    async fn cache_hit_skips_coingecko() {
+        let (url, requests_count, requests) =
+            serve_coingecko("200 OK", r#"{"ethereum":{"usd":3200.5}}"#).await;
+        let oracle = PriceOracle::<Provider<MockRpc>>::new(
+            60,
+            "ethereum".to_string(),
+            url,
+            Some("cg-key".to_string()),
+            None,
+        );
+
+        assert_eq!(oracle.get_native_usd_price().await.unwrap(), 3200.5);
+        assert_eq!(oracle.clone().get_native_usd_price().await.unwrap(), 3200.5);
+        assert_eq!(requests_count.load(Ordering::SeqCst), 1);
+        assert!(requests.lock().await[0].contains("x-cg-demo-api-key: cg-key"));
+    }
+
+    #[tokio::test]
+    async fn rate_limited_coingecko_falls_back_to_chainlink() {
+        let (url, requests_count, _) =
+            serve_coingecko("429 Too Many Requests", r#"{"status":{"error_code":429}}"#).await;
+        let oracle = PriceOracle::new(
+            60,
+            "ethereum".to_string(),
+            url,
+            None,
+            Some(chainlink_feed(320_012_345_678, 8)),
+        );
+
+        assert_eq!(oracle.get_native_usd_price().await.unwrap(), 3200.12345678);
+        // the fallback price is cached too
+        assert_eq!(oracle.get_native_usd_price().await.unwrap(), 3200.12345678);
+        assert_eq!(requests_count.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn unreachable_coingecko_uses_the_onchain_feed() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/simple/price", listener.local_addr().unwrap());
+        drop(listener);
+
+        let oracle = PriceOracle::new(
+            60,
+            "ethereum".to_string(),
+            url.clone(),
+            None,
+            Some(chainlink_feed(2_500, 0)),
+        );
+        assert_eq!(oracle.get_native_usd_price().await.unwrap(), 2500.0);
+
+        // no zero price when every source fails
+        let oracle = PriceOracle::new(
+            60,
+            "ethereum".to_string(),
+            url.clone(),
+            None,
+            Some(chainlink_feed(0, 8)),
+        );
+        assert!(oracle.get_native_usd_price().await.is_err());
+        let oracle =
+            PriceOracle::<Provider<MockRpc>>::new(60, "ethereum".to_string(), url, None, None);
+        assert!(oracle.get_native_usd_price().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn usd_floor_follows_the_eth_price() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/simple/price", listener.local_addr().unwrap());
+        drop(listener);
+        let token_price = 0.000_001;
+
+        // the native floor ignores the ETH price
+        let oracle = PriceOracle::new(
+            60,
+            "ethereum".to_string(),
+            url.clone(),
+            None,
+            Some(chainlink_feed(4_000, 0)),
+        );
+        let native_floor = FloorPrice::Native(0.000_002);
+        assert!(!native_floor
+            .is_exceeded_by(token_price, &oracle)
+            .await
+            .unwrap());
+        assert!(native_floor
+            .is_exceeded_by(0.000_003, &oracle)
+            .await
+            .unwrap());
+
+        // $0.003 floor: the token is worth $0.002 at ETH $2000, $0.004 once ETH doubles
+        let usd_floor = FloorPrice::Usd(0.003);
+        let oracle = PriceOracle::new(
+            60,
+            "ethereum".to_string(),
+            url.clone(),
+            None,
+            Some(chainlink_feed(2_000, 0)),
+        );
+        assert!(!usd_floor
+            .is_exceeded_by(token_price, &oracle)
+            .await
+            .unwrap());
+        let oracle = PriceOracle::new(
+            60,
+            "ethereum".to_string(),
+            url.clone(),
+            None,
+            Some(chainlink_feed(4_000, 0)),
+        );
+        assert!(usd_floor
+            .is_exceeded_by(token_price, &oracle)
+            .await
+            .unwrap());
+
+        // no ETH price, no USD comparison
+        let oracle =
+            PriceOracle::<Provider<MockRpc>>::new(60, "ethereum".to_string(), url, None, None);
+        assert!(usd_floor
+            .is_exceeded_by(token_price, &oracle)
+            .await
+            .is_err());
+        // the trigger is skipped, the detector keeps running
+        assert!(usd_floor.skips_trigger(token_price, &oracle).await);
+        assert!(!native_floor.skips_trigger(token_price, &oracle).await);
+    }
+}
@@ -0,0 +1,299 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::Duration,
+};
+
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, TransactionReceipt, H256, U64},
+};
+use tokio::sync::RwLock;
+
+use super::WalletContext;
+
+// heads are polled, a chain with 1s blocks is followed block by block
+const REORG_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Default)]
+struct ReorgWatcherState {
+    // (number, hash) of the last head seen
+    last_head: Option<(U64, H256)>,
+    // block number => (block hash, wallet) of the confirmed txs that mutated a wallet context
+    confirmed_txs: BTreeMap<U64, Vec<(H256, Address)>>,
+}
+
+/**
+ * follows the heads by parent hash, on a mismatch the blocks of the confirmed buys / sells are checked
+ * against the canonical chain and the wallets of a reorged tx are resynced from chain.
+ * Txs are kept `REORG_DEPTH` blocks, clones share the state
+ */
+#[derive(Debug, Clone)]
+pub struct ReorgWatcher {
+    reorg_depth: u64,
+    state: Arc<Mutex<ReorgWatcherState>>,
+}
+
+impl ReorgWatcher {
+    /// `reorg_depth` 0 disables the watcher
+    pub fn new(reorg_depth: u64) -> Self {
+        Self {
+            reorg_depth,
+            state: Default::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.reorg_depth > 0
+    }
+
+    /// remember the block of a status 1 receipt whose wallet context was updated optimistically
+    pub fn record(&self, wallet: Address, tx_receipt: &TransactionReceipt) {
+        if !self.is_enabled() {
+            return;
+        }
+        let (Some(block_number), Some(block_hash)) =
+            (tx_receipt.block_number, tx_receipt.block_hash)
+        else {
+            return;
+        };
+        self.state
+            .lock()
+            .unwrap()
+            .confirmed_txs
+            .entry(block_number)
+            .or_default()
+            .push((block_hash, wallet));
+    }
+
+    /// true when the new head does not extend the last one, a head skipped by the polling is checked too
+    pub fn observe_head(&self, number: U64, hash: H256, parent_hash: H256) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let is_reorg = match state.last_head {
+            None => false,
+            Some((last_number, last_hash)) if last_number == number => last_hash != hash,
+            Some((last_number, last_hash)) if last_number + 1 == number => last_hash != parent_hash,
+            Some(_) => true,
+        };
+        state.last_head = Some((number, hash));
+        // older txs are final
+        let min_block = number.saturating_sub(U64::from(self.reorg_depth));
+        state.confirmed_txs = state.confirmed_txs.split_off(&min_block);
+        is_reorg
+    }
+
+    /// wallets of the confirmed txs whose block is not canonical anymore, they are not tracked anymore
+    pub async fn take_reorged_wallets<M: Middleware>(
+        &self,
+        provider: &M,
+    ) -> anyhow::Result<Vec<Address>> {
+        let block_numbers: Vec<U64> = self
+            .state
+            .lock()
+            .unwrap()
+            .confirmed_txs
+            .keys()
+            .copied()
+            .collect();
+        let mut reorged_wallets = Vec::new();
+        for block_number in block_numbers {
+            let canonical_hash = provider
+                .get_block(block_number)
+                .await
+                .map_err(|err| anyhow::anyhow!("{:?}", err))?
+                .and_then(|block| block.hash);
+            let mut state = self.state.lock().unwrap();
+            let Some(txs) = state.confirmed_txs.get_mut(&block_number) else {
+                continue;
+            };
+            txs.retain(|(block_hash, wallet)| {
+                if Some(*block_hash) == canonical_hash {
+                    return true;
+                }
+                if !reorged_wallets.contains(wallet) {
+                    reorged_wallets.push(*wallet);
+                }
+                false
+            });
+            if txs.is_empty() {
+                state.confirmed_txs.remove(&block_number);
+            }
+        }
+        Ok(reorged_wallets)
+    }
+
+    /// poll the heads until `exit`, every reorged wallet is reported and those of `system_wallets` are resynced
+    pub fn spawn<M: Middleware + 'static>(
+        &self,
+        service_name: &'static str,
+        token_address: Address,
+        system_wallets: HashMap<Address, Arc<RwLock<WalletContext>>>,
+        provider: Arc<M>,
+        exit: Arc<std::sync::atomic::AtomicBool>,
+    ) {
+        if !self.is_enabled() {
+            return;
+        }
+        let reorg_watcher = self.clone();
+        tokio::spawn(async move {
+            while !exit.load(Ordering::Relaxed) {
+                tokio::time::sleep(REORG_POLL_INTERVAL).await;
+                let head = match provider.get_block(BlockNumber::Latest).await {
+                    Ok(Some(head)) => head,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        log::warn!("[{}] cannot get the latest block {:?}", service_name, err);
+                        continue;
+                    }
+                };
+                let (Some(number), Some(hash)) = (head.number, head.hash) else {
+                    continue;
+                };
+                if !reorg_watcher.observe_head(number, hash, head.parent_hash) {
+                    continue;
+                }
+                let reorged_wallets = match reorg_watcher.take_reorged_wallets(&*provider).await {
+                    Ok(reorged_wallets) => reorged_wallets,
+                    Err(err) => {
+                        log::warn!("[{}] cannot check the reorged txs {:?}", service_name, err);
+                        continue;
+                    }
+                };
+                for wallet in reorged_wallets {
+                    log::warn!(
+                        "[{}] tx of wallet {:?} reorged out before block {:?}",
+                        service_name,
+                        wallet,
+                        number
+                    );
+                    // a wallet without context reads its balances from chain before each trade
+                    let Some(wallet_context) = system_wallets.get(&wallet) else {
+                        continue;
+                    };
+                    if let Err(err) = wallet_context
+                        .write()
+                        .await
+                        .resync(token_address, provider.clone())
+                        .await
+                    {
+                        log::warn!(
+                            "[{}] cannot resync wallet {:?} {:?}",
+                            service_name,
+                            wallet,
+                            err
+                        );
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        abi::Token,
+        types::{Block, U256},
+        utils::parse_ether,
+    };
+    use provider_utils::mock_rpc::MockRpc;
+
+    #[tokio::test]
+    async fn reorged_buy_is_rolled_back() {
+        let (token_address, wallet) = (Address::random(), Address::random());
+        let reorg_watcher = ReorgWatcher::new(3);
+        let (buy_block, replaced_block) = (H256::random(), H256::random());
+        let buy_amount = parse_ether("0.1").unwrap();
+        let mut wallet_context = WalletContext {
+            address: wallet,
+            nonce: U256::from(5),
+            eth_balance: parse_ether(1).unwrap(),
+            ..Default::default()
+        };
+
+        // the buy is confirmed in block 100 and applied optimistically
+        reorg_watcher.record(
+            wallet,
+            &TransactionReceipt {
+                block_number: Some(U64::from(100)),
+                block_hash: Some(buy_block),
+                status: Some(U64::one()),
+                ..Default::default()
+            },
+        );
+        wallet_context.eth_balance -= buy_amount;
+        wallet_context.nonce += U256::one();
+        assert!(!reorg_watcher.observe_head(U64::from(100), buy_block, H256::random()));
+        let block_101 = H256::random();
+        assert!(!reorg_watcher.observe_head(U64::from(101), block_101, buy_block));
+
+        // block 101 is replaced by a head built on another block 100
+        assert!(reorg_watcher.observe_head(U64::from(102), H256::random(), H256::random()));
+        let mock_rpc = MockRpc::new()
+            .with_eth_balance(parse_ether(1).unwrap())
+            .on_call(
+                token_address,
+                "balanceOf(address)",
+                &[Token::Address(wallet)],
+                &[Token::Uint(U256::zero())],
+            );
+        let provider = mock_rpc.provider();
+        mock_rpc.push_responses(
+            "eth_getBlockByNumber",
+            [Block::<H256> {
+                number: Some(U64::from(100)),
+                hash: Some(replaced_block),
+                ..Default::default()
+            }],
+        );
+        assert_eq!(
+            reorg_watcher.take_reorged_wallets(&provider).await.unwrap(),
+            vec![wallet]
+        );
+
+        mock_rpc.push_responses("eth_getTransactionCount", [U256::from(5)]);
+        wallet_context
+            .resync(token_address, Arc::new(provider))
+            .await
+            .unwrap();
+        assert_eq!(wallet_context.eth_balance, parse_ether(1).unwrap());
+        assert_eq!(wallet_context.nonce, U256::from(5));
+
+        // the tx is not checked again
+        let mock_rpc = MockRpc::new();
+        assert!(reorg_watcher
+            .take_reorged_wallets(&mock_rpc.provider())
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(mock_rpc.requests("eth_getBlockByNumber").is_empty());
+    }
+
+    #[test]
+    fn final_blocks_are_not_tracked() {
+        let reorg_watcher = ReorgWatcher::new(2);
+        reorg_watcher.record(
+            Address::random(),
+            &TransactionReceipt {
+                block_number: Some(U64::from(10)),
+                block_hash: Some(H256::random()),
+                ..Default::default()
+            },
+        );
+        reorg_watcher.observe_head(U64::from(13), H256::random(), H256::random());
+        assert!(reorg_watcher.state.lock().unwrap().confirmed_txs.is_empty());
+
+        // disabled by default
+        let reorg_watcher = ReorgWatcher::new(0);
+        reorg_watcher.record(
+            Address::random(),
+            &TransactionReceipt {
+                block_number: Some(U64::from(10)),
+                block_hash: Some(H256::random()),
+                ..Default::default()
+            },
+        );
+        assert!(reorg_watcher.state.lock().unwrap().confirmed_txs.is_empty());
+    }
+}
@@ -0,0 +1,188 @@
+use ethers::{
+    abi::AbiDecode,
+    providers::{Middleware, MiddlewareError},
+    types::{transaction::eip2718::TypedTransaction, BlockId, BlockNumber, Bytes, U64},
+    utils::{hex, id, rlp::Rlp},
+};
+
+// Error(string)
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+// v2 router, v3 router and universal router (custom errors, matched by selector)
+const SLIPPAGE_REVERT_REASONS: [&str; 4] = [
+    "INSUFFICIENT_OUTPUT_AMOUNT",
+    "Too little received",
+    "V2TooLittleReceived()",
+    "V3TooLittleReceived()",
+];
+// the wallet no longer holds the tokens (or the allowance), a resubmit reverts again
+const BALANCE_REVERT_REASONS: [&str; 5] = [
+    "TRANSFER_FROM_FAILED",
+    "exceeds balance",
+    "insufficient balance",
+    "exceeds allowance",
+    "STF",
+];
+
+/**
+ * cause of a swap revert, read from the revert reason
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevertKind {
+    // amount out below amount_out_min, the price moved between quote and inclusion
+    Slippage,
+    InsufficientBalance,
+    Unknown,
+}
+
+impl RevertKind {
+    pub fn from_reason(reason: Option<&str>) -> Self {
+        let Some(reason) = reason else {
+            return RevertKind::Unknown;
+        };
+        let reason = reason.to_lowercase();
+        let matches = |patterns: &[&str]| {
+            patterns.iter().any(|pattern| {
+                let selector = format!("0x{}", hex::encode(id(pattern)));
+                // custom errors only show up as their selector
+                pattern.ends_with("()") && reason.contains(&selector)
+                    || reason.contains(&pattern.to_lowercase())
+            })
+        };
+        if matches(&BALANCE_REVERT_REASONS) {
+            RevertKind::InsufficientBalance
+        } else if matches(&SLIPPAGE_REVERT_REASONS) {
+            RevertKind::Slippage
+        } else {
+            RevertKind::Unknown
+        }
+    }
+
+    /// only a slippage revert may pass with a wider slippage, others revert again and burn gas
+    pub fn is_retryable(&self) -> bool {
+        *self == RevertKind::Slippage
+    }
+}
+
+/**
+ * replay a mined signed tx with eth_call at its block, returns the node revert message
+ * with the decoded Error(string) or the custom error data, none when the replay passes
+ */
+pub async fn fetch_revert_reason<M: Middleware>(
+    provider: &M,
+    raw_tx: &Bytes,
+    block_number: Option<U64>,
+) -> Option<String> {
+    let (mut tx, signature) = TypedTransaction::decode_signed(&Rlp::new(raw_tx)).ok()?;
+    tx.set_from(signature.recover(tx.sighash()).ok()?);
+    let block = block_number.map(|block_number| BlockId::Number(BlockNumber::Number(block_number)));
+
+    let err = provider.call(&tx, block).await.err()?;
+    let Some(err) = err.as_error_response() else {
+        return Some(err.to_string());
+    };
+    let revert_data = err.as_revert_data().unwrap_or_default();
+    if revert_data.starts_with(&ERROR_STRING_SELECTOR) {
+        if let Ok(reason) = String::decode(&revert_data[4..]) {
+            if err.message.contains(&reason) {
+                return Some(err.message.clone());
+            }
+            return Some(format!("{}: {}", err.message, reason));
+        }
+    }
+    if revert_data.is_empty() {
+        return Some(err.message.clone());
+    }
+    Some(format!("{}: {}", err.message, revert_data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::widen_slippage;
+    use ethers::{
+        providers::JsonRpcError,
+        signers::Signer,
+        types::{Address, TransactionRequest},
+    };
+    use provider_utils::mock_rpc::MockRpc;
+
+    async fn signed_sell_tx() -> Bytes {
+        let wallet =
+            ethers::signers::LocalWallet::new(&mut rand::thread_rng()).with_chain_id(1_u64);
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::random())
+            .nonce(3)
+            .gas(300_000)
+            .gas_price(1)
+            .chain_id(1)
+            .into();
+        let signature = wallet.sign_transaction(&tx).await.unwrap();
+        tx.rlp_signed(&signature)
+    }
+
+    #[tokio::test]
+    async fn slippage_revert_is_retried_with_widened_slippage() {
+        let mock_rpc = MockRpc::new();
+        mock_rpc.push_error(
+            "eth_call",
+            3,
+            "execution reverted: UniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT",
+        );
+
+        let revert_reason = fetch_revert_reason(
+            &mock_rpc.provider(),
+            &signed_sell_tx().await,
+            Some(U64::from(100)),
+        )
+        .await;
+        let revert_kind = RevertKind::from_reason(revert_reason.as_deref());
+        assert_eq!(revert_kind, RevertKind::Slippage);
+        assert!(revert_kind.is_retryable());
+
+        // the resubmit accepts twice the slippage, up to MAX_SLIPPAGE
+        assert_eq!(widen_slippage(150, 2000), 300);
+        assert_eq!(widen_slippage(1500, 2000), 2000);
+        assert_eq!(widen_slippage(2500, 2000), 2500);
+        // universal router custom error, only its selector is returned
+        assert_eq!(
+            RevertKind::from_reason(Some("execution reverted: 0x39d35496")),
+            RevertKind::Slippage
+        );
+    }
+
+    #[tokio::test]
+    async fn balance_revert_is_not_retried() {
+        let mock_rpc = MockRpc::new();
+        let revert_data = [
+            vec![0x08, 0xc3, 0x79, 0xa0],
+            ethers::abi::encode(&[ethers::abi::Token::String(
+                "ERC20: transfer amount exceeds balance".to_string(),
+            )]),
+        ]
+        .concat();
+        mock_rpc.push_json_rpc_error(
+            "eth_call",
+            JsonRpcError {
+                code: 3,
+                message: "execution reverted".to_string(),
+                data: Some(serde_json::to_value(Bytes::from(revert_data)).unwrap()),
+            },
+        );
+
+        let revert_reason = fetch_revert_reason(
+            &mock_rpc.provider(),
+            &signed_sell_tx().await,
+            Some(U64::from(100)),
+        )
+        .await;
+        assert_eq!(
+            revert_reason.as_deref(),
+            Some("execution reverted: ERC20: transfer amount exceeds balance")
+        );
+        let revert_kind = RevertKind::from_reason(revert_reason.as_deref());
+        assert_eq!(revert_kind, RevertKind::InsufficientBalance);
+        assert!(!revert_kind.is_retryable());
+        assert!(!RevertKind::from_reason(None).is_retryable());
+    }
+}
@@ -0,0 +1,46 @@
+use std::{
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::constants::Env;
+
+/**
+ * Ctrl-C / SIGTERM set `env.exit` instead of killing the process,
+ * services stop starting new buys and finish the refund of the current wallet, a second signal kills the process
+ */
+pub fn register_shutdown(env: &Env) {
+    let exit = env.exit.clone();
+    tokio::spawn(async move {
+        shutdown_on(exit, wait_shutdown_signal()).await;
+        wait_shutdown_signal().await;
+        log::warn!("[Shutdown] second signal received, exit now");
+        std::process::exit(130);
+    });
+}
+
+/// set `exit` once `signal` resolves
+pub async fn shutdown_on(exit: Arc<AtomicBool>, signal: impl Future<Output = ()>) {
+    signal.await;
+    log::warn!("[Shutdown] signal received, finishing in-flight wallets before exit");
+    exit.store(true, Ordering::Relaxed);
+}
+
+async fn wait_shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("cannot install SIGTERM handler");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = sigterm.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
@@ -0,0 +1,340 @@
+use std::sync::{Arc, Mutex};
+
+use cached::{Cached, TimedCache};
+use ethers::{
+    abi::AbiDecode,
+    contract::{
+        multicall_contract::{Call3Value, Multicall3},
+        MULTICALL_ADDRESS,
+    },
+    providers::{Http, Provider},
+    types::{Address, U256},
+    utils::parse_ether,
+};
+use mm_token_utils::{
+    abi::{MemeTokenAbigen, UniswapV2Router02Abigen},
+    constants::{UNISWAP2_ROUTERS, WRAPPED_NATIVE_TOKENS},
+    env::get_env,
+};
+
+use super::{compute_deadline, resolve_token_tax};
+use crate::constants::{Env, TokenTaxMode};
+
+// token -> (buy tax, sell tax) in percent
+static TAX_PROBE_CACHE: Mutex<Option<TimedCache<Address, (f32, f32)>>> = Mutex::new(None);
+const TAX_PROBE_CACHE_SECONDS: u64 = 300;
+
+/**
+ * effective buy and sell tax of a token, simulated with a tiny v2 buy then sell in eth_call,
+ * the tax is the share of the router quote that never reaches the receiver
+ */
+#[derive(Debug, Clone)]
+pub struct TaxProbe {
+    http_provider: Arc<Provider<Http>>,
+    uniswapv2_router_address: Address,
+    weth_address: Address,
+}
+
+impl TaxProbe {
+    /// `AUTO_TAX_DETECT=true` replaces TOKEN_BUY_TAX / TOKEN_SELL_TAX by the simulated tax
+    pub fn from_env(env: &Env, http_provider: Arc<Provider<Http>>) -> Option<Self> {
+        let auto_tax_detect: bool = get_env("AUTO_TAX_DETECT", Some("false".to_string()))
+            .parse()
+            .unwrap();
+        if !auto_tax_detect {
+            return None;
+        }
+        let Some(uniswapv2_router_address) = UNISWAP2_ROUTERS.get(&env.listen_network) else {
+            panic!("UNISWAP2_ROUTERS not found in {:?}", env.listen_network);
+        };
+        let Some(weth) = WRAPPED_NATIVE_TOKENS.get(&env.listen_network) else {
+            panic!(
+                "WRAPPED_NATIVE_TOKENS not found in {:?}",
+                env.listen_network
+            );
+        };
+
+        Some(Self {
+            http_provider,
+            uniswapv2_router_address: *uniswapv2_router_address,
+            weth_address: weth.address,
+        })
+    }
+
+    /// simulated tax in percent, cached for 5 minutes, `env_tax` when the simulation fails
+    pub async fn resolve_tax(&self, token_address: Address, is_buy: bool, env_tax: f32) -> f32 {
+        let cached_taxes = TAX_PROBE_CACHE
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| TimedCache::with_lifespan(TAX_PROBE_CACHE_SECONDS))
+            .cache_get(&token_address)
+            .copied();
+        let (buy_tax, sell_tax) = match cached_taxes {
+            Some(taxes) => taxes,
+            None => match self.probe(token_address).await {
+                Ok(taxes) => {
+                    log::info!(
+                        "[TaxProbe] token {:?} buy tax {:?}% sell tax {:?}%",
+                        token_address,
+                        taxes.0,
+                        taxes.1
+                    );
+                    TAX_PROBE_CACHE
+                        .lock()
+                        .unwrap()
+                        .get_or_insert_with(|| TimedCache::with_lifespan(TAX_PROBE_CACHE_SECONDS))
+                        .cache_set(token_address, taxes);
+                    taxes
+                }
+                Err(err) => {
+                    log::warn!(
+                        "[TaxProbe] cannot simulate tax of {:?}, fall back to env tax: {:?}",
+                        token_address,
+                        err
+                    );
+                    return env_tax;
+                }
+            },
+        };
+
+        if is_buy {
+            buy_tax
+        } else {
+            sell_tax
+        }
+    }
+
+    /// (buy tax, sell tax) in percent
+    pub async fn probe(&self, token_address: Address) -> anyhow::Result<(f32, f32)> {
+        let probe_amount = parse_ether("0.0001").unwrap();
+        let buy_path = vec![self.weth_address, token_address];
+        let sell_path = vec![token_address, self.weth_address];
+        let router =
+            UniswapV2Router02Abigen::new(self.uniswapv2_router_address, self.http_provider.clone());
+        let token = MemeTokenAbigen::new(token_address, self.http_provider.clone());
+        let weth = MemeTokenAbigen::new(self.weth_address, self.http_provider.clone());
+        let deadline = compute_deadline(60);
+
+        let buy_call = Call3Value {
+            target: self.uniswapv2_router_address,
+            allow_failure: false,
+            value: probe_amount,
+            call_data: router
+                .swap_exact_eth_for_tokens_supporting_fee_on_transfer_tokens(
+                    U256::zero(),
+                    buy_path.clone(),
+                    MULTICALL_ADDRESS,
+                    deadline,
+                )
+                .calldata()
+                .unwrap(),
+        };
+        let view_call = |target: Address, call_data| Call3Value {
+            target,
+            allow_failure: false,
+            value: U256::zero(),
+            call_data,
+        };
+
+        // buy: the quote against the token received by the multicall contract
+        let results = self
+            .aggregate(
+                vec![
+                    view_call(
+                        token_address,
+                        token.balance_of(MULTICALL_ADDRESS).calldata().unwrap(),
+                    ),
+                    view_call(
+                        self.uniswapv2_router_address,
+                        router
+                            .get_amounts_out(probe_amount, buy_path)
+                            .calldata()
+                            .unwrap(),
+                    ),
+                    buy_call.clone(),
+                    view_call(
+                        token_address,
+                        token.balance_of(MULTICALL_ADDRESS).calldata().unwrap(),
+                    ),
+                ],
+                probe_amount,
+            )
+            .await?;
+        let buy_quote = Vec::<U256>::decode(&results[1])?
+            .last()
+            .copied()
+            .unwrap_or_default();
+        let bought = U256::decode(&results[3])?.saturating_sub(U256::decode(&results[0])?);
+        let buy_tax = compute_tax_percent(buy_quote, bought);
+
+        // sell: state is not kept between eth_calls, buy again then sell what was bought
+        let results = self
+            .aggregate(
+                vec![
+                    buy_call,
+                    view_call(
+                        token_address,
+                        token
+                            .approve(self.uniswapv2_router_address, U256::MAX)
+                            .calldata()
+                            .unwrap(),
+                    ),
+                    view_call(
+                        self.weth_address,
+                        weth.balance_of(MULTICALL_ADDRESS).calldata().unwrap(),
+                    ),
+                    view_call(
+                        self.uniswapv2_router_address,
+                        router
+                            .get_amounts_out(bought, sell_path.clone())
+                            .calldata()
+                            .unwrap(),
+                    ),
+                    view_call(
+                        self.uniswapv2_router_address,
+                        router
+                            .swap_exact_tokens_for_tokens_supporting_fee_on_transfer_tokens(
+                                bought,
+                                U256::zero(),
+                                sell_path,
+                                MULTICALL_ADDRESS,
+                                deadline,
+                            )
+                            .calldata()
+                            .unwrap(),
+                    ),
+                    view_call(
+                        self.weth_address,
+                        weth.balance_of(MULTICALL_ADDRESS).calldata().unwrap(),
+                    ),
+                ],
+                probe_amount,
+            )
+            .await?;
+        // the sell quote already includes the buy price impact
+        let sell_quote = Vec::<U256>::decode(&results[3])?
+            .last()
+            .copied()
+            .unwrap_or_default();
+        let sold = U256::decode(&results[5])?.saturating_sub(U256::decode(&results[2])?);
+        let sell_tax = compute_tax_percent(sell_quote, sold);
+
+        Ok((buy_tax, sell_tax))
+    }
+
+    async fn aggregate(
+        &self,
+        calls: Vec<Call3Value>,
+        value: U256,
+    ) -> anyhow::Result<Vec<ethers::types::Bytes>> {
+        let multicall = Multicall3::new(MULTICALL_ADDRESS, self.http_provider.clone());
+        // weth holds enough eth to fund the simulated buy
+        let results = multicall
+            .aggregate_3_value(calls)
+            .from(self.weth_address)
+            .value(value)
+            .call()
+            .await?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| result.return_data)
+            .collect())
+    }
+}
+
+/**
+ * buy and sell tax of the traded token the routers add to their slippage:
+ * simulated with AUTO_TAX_DETECT, otherwise TOKEN_BUY_TAX / TOKEN_SELL_TAX read as TOKEN_TAX_MODE
+ */
+#[derive(Debug, Clone)]
+pub struct TokenTax {
+    http_provider: Arc<Provider<Http>>,
+    token_address: Address,
+    buy_tax: f32,
+    sell_tax: f32,
+    tax_mode: TokenTaxMode,
+    tax_probe: Option<TaxProbe>,
+}
+
+impl TokenTax {
+    pub fn from_env(env: &Env, http_provider: Arc<Provider<Http>>) -> Self {
+        Self {
+            token_address: env.token_address,
+            buy_tax: env.token.buy_tax,
+            sell_tax: env.token.sell_tax,
+            tax_mode: get_env("TOKEN_TAX_MODE", Some("Env".to_string()))
+                .parse()
+                .unwrap(),
+            tax_probe: TaxProbe::from_env(env, http_provider.clone()),
+            http_provider,
+        }
+    }
+
+    /// tax of a buy or a sell in percent
+    pub async fn resolve(&self, is_buy: bool) -> f32 {
+        let env_tax = if is_buy { self.buy_tax } else { self.sell_tax };
+        match &self.tax_probe {
+            Some(tax_probe) => {
+                tax_probe
+                    .resolve_tax(self.token_address, is_buy, env_tax)
+                    .await
+            }
+            None => {
+                resolve_token_tax(
+                    self.tax_mode,
+                    env_tax,
+                    self.token_address,
+                    self.http_provider.clone(),
+                )
+                .await
+            }
+        }
+    }
+}
+
+/**
+ * share of `quoted` missing from `received`, in percent
+ */
+pub fn compute_tax_percent(quoted: U256, received: U256) -> f32 {
+    if quoted.is_zero() || received >= quoted {
+        return 0.0;
+    }
+    // basis points keep 2 decimals
+    let tax_bps = (quoted - received) * U256::from(10_000) / quoted;
+    tax_bps.as_u32() as f32 / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tax_from_simulated_amounts() {
+        let quoted = parse_ether("1000").unwrap();
+        assert_eq!(compute_tax_percent(quoted, quoted), 0.0);
+        assert_eq!(
+            compute_tax_percent(quoted, parse_ether("900").unwrap()),
+            10.0
+        );
+        // more than quoted is not a negative tax
+        assert_eq!(
+            compute_tax_percent(quoted, parse_ether("1001").unwrap()),
+            0.0
+        );
+        assert_eq!(compute_tax_percent(U256::zero(), U256::zero()), 0.0);
+    }
+
+    #[tokio::test]
+    async fn env_taxes_resolve_without_any_rpc() {
+        let mut env = Env::default();
+        env.token.buy_tax = 3.0;
+        env.token.sell_tax = 5.0;
+        // nothing listens, the default TOKEN_TAX_MODE=Env never reads the token
+        let http_provider = Arc::new(Provider::<Http>::try_from("http://127.0.0.1:1").unwrap());
+        let token_tax = TokenTax::from_env(&env, http_provider);
+
+        assert_eq!(token_tax.resolve(true).await, 3.0);
+        assert_eq!(token_tax.resolve(false).await, 5.0);
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use cached::{Cached, TimedCache};
+use ethers::types::H256;
+use mm_token_utils::env::get_env;
+use tokio::sync::Mutex;
+
+/**
+ * trigger tx hashes shared by the mempool and the event mode, kept `TX_DEDUP_LIFESPAN_SECS` (default 120)
+ */
+pub fn new_tx_hashes_cache() -> Arc<Mutex<TimedCache<H256, bool>>> {
+    let lifespan_secs: u64 = get_env("TX_DEDUP_LIFESPAN_SECS", Some("120".to_string()))
+        .parse()
+        .unwrap();
+    Arc::new(Mutex::new(TimedCache::with_lifespan(lifespan_secs)))
+}
+
+/**
+ * claim `tx_hash` under one lock, true when the other mode saw it first.
+ * A hash stays claimed when its log is removed by a reorg, the re-included tx does not trigger twice
+ */
+pub async fn cache_get_or_insert(
+    tx_hashes_cache: &Mutex<TimedCache<H256, bool>>,
+    tx_hash: H256,
+) -> bool {
+    let mut tx_hashes_cache = tx_hashes_cache.lock().await;
+    if tx_hashes_cache.cache_get(&tx_hash).is_some() {
+        return true;
+    }
+    tx_hashes_cache.cache_set(tx_hash, true);
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::future::join_all;
+
+    #[tokio::test]
+    async fn mempool_and_event_mode_trigger_a_tx_once() {
+        let tx_hashes_cache = new_tx_hashes_cache();
+        let tx_hash = H256::random();
+        let triggers = Arc::new(std::sync::atomic::AtomicU32::new(0));
+
+        // both modes see the hash at the same time
+        let detectors = (0..2).map(|_| {
+            let tx_hashes_cache = tx_hashes_cache.clone();
+            let triggers = triggers.clone();
+            tokio::spawn(async move {
+                if !cache_get_or_insert(&tx_hashes_cache, tx_hash).await {
+                    triggers.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                }
+            })
+        });
+        for detector in join_all(detectors).await {
+            detector.unwrap();
+        }
+
+        assert_eq!(triggers.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(!cache_get_or_insert(&tx_hashes_cache, H256::random()).await);
+    }
+}
@@ -0,0 +1,374 @@
+use std::{sync::Arc, time::Duration};
+
+use ethers::{
+    providers::Middleware,
+    signers::Signer,
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Bytes, TransactionReceipt, H256, U256,
+    },
+    utils::rlp::Rlp,
+};
+
+use super::WalletContext;
+
+/**
+ * wait until the tx is `confirmations` blocks deep, 1 is the block including it,
+ * a receipt dropped by a reorg is waited for again. An error when the tx is not confirmed within `timeout`,
+ * e.g. dropped and never mined again
+ */
+pub async fn await_confirmations<M: Middleware>(
+    provider: &M,
+    tx_hash: H256,
+    confirmations: u64,
+    timeout: Duration,
+) -> anyhow::Result<TransactionReceipt> {
+    let confirmed = async {
+        loop {
+            let receipt = provider
+                .get_transaction_receipt(tx_hash)
+                .await
+                .map_err(|err| {
+                    anyhow::anyhow!("[await_confirmations] receipt of {:?}: {}", tx_hash, err)
+                })?;
+            if let Some(receipt) = receipt {
+                if confirmations <= 1 {
+                    return Ok(receipt);
+                }
+                if let Some(receipt_block) = receipt.block_number {
+                    let current_block = provider.get_block_number().await.map_err(|err| {
+                        anyhow::anyhow!("[await_confirmations] block number: {}", err)
+                    })?;
+                    if current_block.saturating_sub(receipt_block).as_u64() + 1 >= confirmations {
+                        return Ok(receipt);
+                    }
+                }
+            }
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+    };
+    tokio::time::timeout(timeout, confirmed)
+        .await
+        .map_err(|_| {
+            anyhow::anyhow!(
+                "[await_confirmations] {:?} not {} blocks deep within {:?}",
+                tx_hash,
+                confirmations,
+                timeout
+            )
+        })?
+}
+
+/**
+ * the receipt of a sent tx, none when it is not mined within `tx_timeout` or dropped from the mempool.
+ * The wallet is resynced from chain then. A tx not mined in time may still be mined later,
+ * the wallet is kept stuck on it until then (see `WalletContext::is_stuck`)
+ */
+pub async fn await_receipt_or_resync<F, E, M>(
+    pending_tx: F,
+    tx_hash: H256,
+    tx_timeout: Duration,
+    wallet_context: &mut WalletContext,
+    token_address: Address,
+    provider: Arc<M>,
+) -> anyhow::Result<Option<TransactionReceipt>>
+where
+    F: std::future::Future<Output = Result<Option<TransactionReceipt>, E>>,
+    E: std::error::Error + Send + Sync + 'static,
+    M: Middleware + 'static,
+{
+    let tx_receipt = match tokio::time::timeout(tx_timeout, pending_tx).await {
+        Ok(tx_receipt) => tx_receipt?,
+        Err(_) => {
+            wallet_context.stuck_tx = Some(tx_hash);
+            None
+        }
+    };
+    if tx_receipt.is_none() {
+        log::warn!(
+            "[await_receipt_or_resync] no receipt within {:?}, resync wallet {:?}",
+            tx_timeout,
+            wallet_context.address
+        );
+        wallet_context.resync(token_address, provider).await?;
+    }
+    Ok(tx_receipt)
+}
+
+// fee increase of a replacement tx, nodes reject one below 10%
+pub const GAS_BUMP_PERCENT: u64 = 25;
+const MAX_GAS_BUMPS: u32 = 3;
+
+/**
+ * sends a signed tx and waits `tx_timeout` for its receipt. A tx not mined by then is replaced at the same nonce
+ * with its fees raised by `GAS_BUMP_PERCENT`, at most `MAX_GAS_BUMPS` times, so only one version can be mined.
+ * None when the tx is dropped, an error when no version is mined after the last bump
+ */
+pub async fn send_raw_transaction_with_gas_bump<M, S>(
+    provider: &M,
+    signer: &S,
+    signed_tx: Bytes,
+    tx_timeout: Duration,
+) -> anyhow::Result<Option<TransactionReceipt>>
+where
+    M: Middleware,
+    M::Error: 'static,
+    S: Signer,
+{
+    let (mut tx, _) = TypedTransaction::decode_signed(&Rlp::new(&signed_tx))?;
+    let mut raw_tx = signed_tx;
+    let mut tx_hashes = Vec::new();
+    loop {
+        let pending_tx = match provider.send_raw_transaction(raw_tx).await {
+            Ok(pending_tx) => pending_tx,
+            // an earlier version was mined meanwhile and used the nonce
+            Err(err) if !tx_hashes.is_empty() => {
+                log::warn!(
+                    "[send_raw_transaction_with_gas_bump] replacement rejected: {:?}",
+                    err
+                );
+                break;
+            }
+            Err(err) => return Err(err.into()),
+        };
+        tx_hashes.push(pending_tx.tx_hash());
+        match tokio::time::timeout(tx_timeout, pending_tx).await {
+            Ok(Ok(Some(tx_receipt))) => return Ok(Some(tx_receipt)),
+            // dropped, possibly because an earlier version was mined
+            Ok(Ok(None)) => break,
+            Ok(Err(err)) => return Err(err.into()),
+            Err(_) if tx_hashes.len() as u32 > MAX_GAS_BUMPS => break,
+            Err(_) => {}
+        }
+        bump_tx_fees(&mut tx, GAS_BUMP_PERCENT);
+        let signature = signer
+            .sign_transaction(&tx)
+            .await
+            .map_err(|err| anyhow::anyhow!("[send_raw_transaction_with_gas_bump] {:?}", err))?;
+        raw_tx = tx.rlp_signed(&signature);
+        log::warn!(
+            "[send_raw_transaction_with_gas_bump] tx {:?} not mined within {:?}, replace it with fees +{}%",
+            tx_hashes.last(),
+            tx_timeout,
+            GAS_BUMP_PERCENT
+        );
+    }
+
+    for tx_hash in &tx_hashes {
+        if let Some(tx_receipt) = provider.get_transaction_receipt(*tx_hash).await? {
+            return Ok(Some(tx_receipt));
+        }
+    }
+    if tx_hashes.len() as u32 > MAX_GAS_BUMPS {
+        return Err(anyhow::anyhow!(
+            "[send_raw_transaction_with_gas_bump] none of {:?} mined after {} gas bumps",
+            tx_hashes,
+            MAX_GAS_BUMPS
+        ));
+    }
+    Ok(None)
+}
+
+fn bump_tx_fees(tx: &mut TypedTransaction, percent: u64) {
+    let bump = |fee: U256| fee * U256::from(100 + percent) / U256::from(100);
+    match tx {
+        TypedTransaction::Eip1559(tx) => {
+            tx.max_fee_per_gas = tx.max_fee_per_gas.map(bump);
+            tx.max_priority_fee_per_gas = tx.max_priority_fee_per_gas.map(bump);
+        }
+        TypedTransaction::Legacy(tx) => tx.gas_price = tx.gas_price.map(bump),
+        TypedTransaction::Eip2930(tx) => tx.tx.gas_price = tx.tx.gas_price.map(bump),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::{
+        types::{TransactionRequest, U64},
+        utils::{parse_ether, parse_units},
+    };
+    use provider_utils::mock_rpc::MockRpc;
+
+    #[tokio::test]
+    async fn await_confirmations_waits_for_depth() {
+        let tx_hash = H256::random();
+        let receipt = TransactionReceipt {
+            transaction_hash: tx_hash,
+            block_number: Some(U64::from(10)),
+            status: Some(U64::one()),
+            ..Default::default()
+        };
+
+        // not mined, 1 then 2 confirmations
+        let mock_rpc = MockRpc::new();
+        mock_rpc.push_responses(
+            "eth_getTransactionReceipt",
+            [None, Some(receipt.clone()), Some(receipt.clone())],
+        );
+        mock_rpc.push_responses("eth_blockNumber", [U64::from(10), U64::from(11)]);
+
+        let confirmed =
+            await_confirmations(&mock_rpc.provider(), tx_hash, 2, Duration::from_secs(10))
+                .await
+                .unwrap();
+        assert_eq!(confirmed.transaction_hash, tx_hash);
+        // the sweep waits for the second confirmation
+        assert_eq!(mock_rpc.requests("eth_blockNumber").len(), 2);
+
+        // a single confirmation returns the receipt without reading the block number
+        let mock_rpc = MockRpc::new();
+        mock_rpc.push_responses("eth_getTransactionReceipt", [receipt]);
+        await_confirmations(&mock_rpc.provider(), tx_hash, 1, Duration::from_secs(10))
+            .await
+            .unwrap();
+        assert!(mock_rpc.requests("eth_blockNumber").is_empty());
+
+        // dropped and never mined again
+        let mock_rpc = MockRpc::new();
+        mock_rpc.push_responses("eth_getTransactionReceipt", [serde_json::Value::Null]);
+        assert!(
+            await_confirmations(&mock_rpc.provider(), tx_hash, 1, Duration::from_millis(50))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn stuck_tx_blocks_the_wallet_until_resolved() {
+        let mock_rpc = MockRpc::new();
+        mock_rpc.push_responses("eth_getTransactionCount", [U256::from(4)]);
+        mock_rpc.push_responses("eth_getBalance", [parse_ether(1).unwrap()]);
+        mock_rpc.push_call_outputs([vec![ethers::abi::Token::Uint(U256::zero())]]);
+        let mut wallet_context = WalletContext {
+            nonce: U256::from(3),
+            eth_balance: parse_ether("0.9").unwrap(),
+            ..Default::default()
+        };
+        let stuck_tx = H256::random();
+
+        let never_mined =
+            std::future::pending::<Result<Option<TransactionReceipt>, std::io::Error>>();
+        let tx_receipt = await_receipt_or_resync(
+            never_mined,
+            stuck_tx,
+            Duration::from_millis(10),
+            &mut wallet_context,
+            Address::random(),
+            Arc::new(mock_rpc.provider()),
+        )
+        .await
+        .unwrap();
+        assert!(tx_receipt.is_none());
+        assert_eq!(wallet_context.stuck_tx, Some(stuck_tx));
+        assert_eq!(wallet_context.eth_balance, parse_ether(1).unwrap());
+
+        // still pending, a new tx could be mined next to it
+        let mock_rpc = MockRpc::new();
+        mock_rpc.push_responses(
+            "eth_getTransactionByHash",
+            [
+                serde_json::to_value(ethers::types::Transaction {
+                    hash: stuck_tx,
+                    ..Default::default()
+                })
+                .unwrap(),
+                serde_json::Value::Null,
+            ],
+        );
+        mock_rpc.push_responses("eth_getTransactionCount", [U256::from(3)]);
+        mock_rpc.push_call_outputs([vec![ethers::abi::Token::Uint(U256::zero())]]);
+        let provider = Arc::new(mock_rpc.provider());
+        assert!(wallet_context
+            .is_stuck(Address::random(), provider.clone())
+            .await
+            .unwrap());
+        assert!(mock_rpc.requests("eth_getTransactionCount").is_empty());
+
+        // dropped, the wallet sends again from the chain nonce
+        assert!(!wallet_context
+            .is_stuck(Address::random(), provider)
+            .await
+            .unwrap());
+        assert_eq!(wallet_context.stuck_tx, None);
+        assert_eq!(wallet_context.nonce, U256::from(3));
+
+        // a mined tx is returned as is, no rpc call
+        let mock_rpc = MockRpc::new();
+        let mined = async { Ok::<_, std::io::Error>(Some(TransactionReceipt::default())) };
+        assert!(await_receipt_or_resync(
+            mined,
+            H256::random(),
+            Duration::from_secs(1),
+            &mut wallet_context,
+            Address::random(),
+            Arc::new(mock_rpc.provider()),
+        )
+        .await
+        .unwrap()
+        .is_some());
+        assert!(mock_rpc.requests("eth_call").is_empty());
+    }
+
+    #[tokio::test]
+    async fn stuck_tx_is_replaced_with_bumped_fees_at_the_same_nonce() {
+        let mock_rpc = MockRpc::new().with_pending_txs(1);
+        let wallet = ethers::signers::LocalWallet::new(&mut rand::thread_rng()).with_chain_id(1u64);
+        let tx: TypedTransaction = TransactionRequest::new()
+            .from(wallet.address())
+            .to(Address::random())
+            .value(1)
+            .gas(21_000)
+            .gas_price(parse_units(1, "gwei").unwrap())
+            .nonce(7)
+            .chain_id(1)
+            .into();
+        let signature = wallet.sign_transaction(&tx).await.unwrap();
+
+        let tx_receipt = send_raw_transaction_with_gas_bump(
+            &mock_rpc.provider(),
+            &wallet,
+            tx.rlp_signed(&signature),
+            Duration::from_millis(50),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+
+        let sent_txs = mock_rpc.sent_txs();
+        assert_eq!(sent_txs.len(), 2);
+        assert_eq!(tx_receipt.transaction_hash, sent_txs[1].hash);
+        assert_eq!(sent_txs[1].nonce, U256::from(7));
+        assert_eq!(
+            sent_txs[1].gas_price,
+            Some(parse_units(1.25, "gwei").unwrap().into())
+        );
+    }
+
+    #[tokio::test]
+    async fn send_errors_of_a_raw_tx_are_classified() {
+        let mock_rpc = MockRpc::new();
+        mock_rpc.push_error("eth_sendRawTransaction", -32000, "nonce too low");
+        let wallet = ethers::signers::LocalWallet::new(&mut rand::thread_rng()).with_chain_id(1u64);
+        let tx: TypedTransaction = TransactionRequest::new()
+            .to(Address::random())
+            .gas(21_000)
+            .gas_price(1)
+            .nonce(0)
+            .chain_id(1)
+            .into();
+        let signature = wallet.sign_transaction(&tx).await.unwrap();
+
+        let err = send_raw_transaction_with_gas_bump(
+            &mock_rpc.provider(),
+            &wallet,
+            tx.rlp_signed(&signature),
+            Duration::from_secs(1),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(
+            provider_utils::tx_error::TxError::classify(err.as_ref()),
+            provider_utils::tx_error::TxError::NonceTooLow(_)
+        ));
+    }
+}
@@ -0,0 +1,222 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use ethers::{
+    abi::AbiDecode,
+    contract::{
+        multicall_contract::{Call3Value, Multicall3},
+        MULTICALL_ADDRESS,
+    },
+    providers::{Http, Provider},
+    types::{Address, Bytes, U256},
+    utils::parse_ether,
+};
+use mm_token_utils::abi::{MemeTokenAbigen, UniswapV2Router02Abigen};
+
+use crate::constants::SwapMethodMode;
+
+// token address => is fee on transfer, only successful detections are cached
+static FEE_ON_TRANSFER_CACHE: Mutex<BTreeMap<Address, bool>> = Mutex::new(BTreeMap::new());
+
+/**
+ * simulate a tiny buy received by the multicall contract in a single eth_call,
+ * a fee on transfer token delivers less than the router quote the pair sends
+ */
+pub async fn detect_fee_on_transfer(
+    token_address: Address,
+    weth_address: Address,
+    router_address: Address,
+    http_provider: Arc<Provider<Http>>,
+) -> anyhow::Result<bool> {
+    let router = UniswapV2Router02Abigen::new(router_address, http_provider.clone());
+    let token = MemeTokenAbigen::new(token_address, http_provider.clone());
+    let multicall = Multicall3::new(MULTICALL_ADDRESS, http_provider);
+    let probe_amount = parse_ether("0.0001").unwrap();
+    let path = vec![weth_address, token_address];
+    let deadline = compute_deadline(60);
+
+    let calls = vec![
+        Call3Value {
+            target: router_address,
+            allow_failure: false,
+            value: U256::zero(),
+            call_data: router
+                .get_amounts_out(probe_amount, path.clone())
+                .calldata()
+                .unwrap(),
+        },
+        Call3Value {
+            target: router_address,
+            allow_failure: false,
+            value: probe_amount,
+            call_data: router
+                .swap_exact_eth_for_tokens_supporting_fee_on_transfer_tokens(
+                    U256::zero(),
+                    path,
+                    MULTICALL_ADDRESS,
+                    deadline,
+                )
+                .calldata()
+                .unwrap(),
+        },
+        Call3Value {
+            target: token_address,
+            allow_failure: false,
+            value: U256::zero(),
+            call_data: token.balance_of(MULTICALL_ADDRESS).calldata().unwrap(),
+        },
+    ];
+    // weth holds enough eth to fund the simulated buy
+    let results = multicall
+        .aggregate_3_value(calls)
+        .from(weth_address)
+        .value(probe_amount)
+        .call()
+        .await?;
+
+    let amounts = Vec::<U256>::decode(&results[0].return_data)?;
+    let received = U256::decode(&results[2].return_data)?;
+    let sent = amounts.last().copied().unwrap_or_default();
+
+    Ok(received < sent)
+}
+
+/**
+ * whether to use the `SupportingFeeOnTransferTokens` swap methods,
+ * falls back to them when the detection fails since they also work for most standard tokens
+ */
+pub async fn resolve_fee_on_transfer(
+    mode: SwapMethodMode,
+    token_address: Address,
+    weth_address: Address,
+    router_address: Address,
+    http_provider: Arc<Provider<Http>>,
+) -> bool {
+    match mode {
+        SwapMethodMode::FeeOnTransfer => return true,
+        SwapMethodMode::Standard => return false,
+        SwapMethodMode::Auto => {}
+    }
+    if let Some(is_fee_on_transfer) = FEE_ON_TRANSFER_CACHE.lock().unwrap().get(&token_address) {
+        return *is_fee_on_transfer;
+    }
+
+    match detect_fee_on_transfer(token_address, weth_address, router_address, http_provider).await {
+        Ok(is_fee_on_transfer) => {
+            log::info!(
+                "token {:?} is_fee_on_transfer {:?}",
+                token_address,
+                is_fee_on_transfer
+            );
+            FEE_ON_TRANSFER_CACHE
+                .lock()
+                .unwrap()
+                .insert(token_address, is_fee_on_transfer);
+            is_fee_on_transfer
+        }
+        Err(err) => {
+            log::warn!(
+                "failed to detect fee on transfer of {:?}, use fee on transfer methods: {:?}",
+                token_address,
+                err
+            );
+            true
+        }
+    }
+}
+
+/**
+ * swap deadline `deadline_secs` from now
+ */
+pub fn compute_deadline(deadline_secs: u64) -> U256 {
+    U256::from(Utc::now().timestamp()) + U256::from(deadline_secs)
+}
+
+/**
+ * token price in native from the pool reserves, raw amounts of two 18 decimals tokens
+ */
+pub fn compute_reserve_price(mm_token_reserve: u128, weth_reserve: u128) -> anyhow::Result<f64> {
+    if mm_token_reserve == 0 {
+        return Err(anyhow::anyhow!("pool without token reserve"));
+    }
+    Ok(
+        (BigDecimal::from(weth_reserve) / BigDecimal::from(mm_token_reserve))
+            .round(18)
+            .to_string()
+            .parse::<f64>()?,
+    )
+}
+
+/**
+ * signed distance in bps of the execution price (weth per token) from the mid price of the pool,
+ * positive when a buy pays above it, negative when a sell receives below it
+ */
+pub fn compute_price_impact_bps(
+    mid_price: f64,
+    amount_in: U256,
+    amount_out: U256,
+    is_buy: bool,
+) -> anyhow::Result<i64> {
+    let (weth_amount, token_amount) = if is_buy {
+        (amount_in, amount_out)
+    } else {
+        (amount_out, amount_in)
+    };
+    if token_amount.is_zero() || mid_price <= 0.0 {
+        return Err(anyhow::anyhow!(
+            "cannot price a quote without token amount or mid price"
+        ));
+    }
+    let execution_price =
+        weth_amount.to_string().parse::<f64>()? / token_amount.to_string().parse::<f64>()?;
+    Ok(((execution_price / mid_price - 1.0) * 10_000.0).round() as i64)
+}
+
+/**
+ * v2 swaps return the amounts array and v3 swaps the amount out, both end with the amount out
+ */
+pub fn decode_amount_out(output: &Bytes) -> Option<U256> {
+    if output.len() < 32 {
+        return None;
+    }
+    Some(U256::from_big_endian(&output[output.len() - 32..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reserve_price_is_reserve_ratio() {
+        let mm_token_reserve = 1_000_000 * 10_u128.pow(18);
+        let weth_reserve = 25 * 10_u128.pow(17);
+        let price = compute_reserve_price(mm_token_reserve, weth_reserve).unwrap();
+        assert!((price - weth_reserve as f64 / mm_token_reserve as f64).abs() < 1e-15);
+        assert!(compute_reserve_price(0, weth_reserve).is_err());
+    }
+
+    #[test]
+    fn amount_out_is_last_word() {
+        let amounts = ethers::abi::encode(&[ethers::abi::Token::Array(vec![
+            ethers::abi::Token::Uint(U256::from(100)),
+            ethers::abi::Token::Uint(U256::from(42)),
+        ])]);
+        assert_eq!(
+            decode_amount_out(&Bytes::from(amounts)),
+            Some(U256::from(42))
+        );
+        assert_eq!(decode_amount_out(&Bytes::new()), None);
+    }
+
+    #[test]
+    fn deadline_is_configured_secs_from_now() {
+        let now = U256::from(Utc::now().timestamp());
+        let deadline = compute_deadline(300);
+        assert!(deadline >= now + U256::from(300));
+        assert!(deadline <= now + U256::from(302));
+    }
+}
@@ -0,0 +1,210 @@
+use std::collections::VecDeque;
+
+use ethers::{
+    types::{Address, U256},
+    utils::parse_ether,
+};
+
+use crate::types::MmSettings;
+
+// the volume caps are per rolling day
+pub const VOLUME_WINDOW_SECS: i64 = 24 * 60 * 60;
+
+/**
+ * buy volume of a market maker group over the last `VOLUME_WINDOW_SECS`, in memory,
+ * bounded by `maxDailyVolumeEth` for the group and `maxPerWalletVolumeEth` for each wallet
+ */
+#[derive(Debug, Clone, Default)]
+pub struct VolumeLedger {
+    max_daily_volume: Option<U256>,
+    max_per_wallet_volume: Option<U256>,
+    // (unix timestamp, wallet, eth amount) of each buy, oldest first
+    buys: VecDeque<(i64, Address, U256)>,
+}
+
+impl VolumeLedger {
+    pub fn new(max_daily_volume: Option<U256>, max_per_wallet_volume: Option<U256>) -> Self {
+        Self {
+            max_daily_volume,
+            max_per_wallet_volume,
+            buys: VecDeque::new(),
+        }
+    }
+
+    pub fn from_settings(mm_settings: &MmSettings) -> anyhow::Result<Self> {
+        let parse_cap = |cap: Option<f32>| -> anyhow::Result<Option<U256>> {
+            cap.map(|cap| parse_ether(cap.to_string()))
+                .transpose()
+                .map_err(anyhow::Error::from)
+        };
+        Ok(Self::new(
+            parse_cap(mm_settings.max_daily_volume_eth)?,
+            parse_cap(mm_settings.max_per_wallet_volume_eth)?,
+        ))
+    }
+
+    /// volume of the group in the window ending at `now`
+    pub fn volume(&self, now: i64) -> U256 {
+        self.window_volume(now, None)
+    }
+
+    pub fn wallet_volume(&self, wallet: Address, now: i64) -> U256 {
+        self.window_volume(now, Some(wallet))
+    }
+
+    pub fn record(&mut self, wallet: Address, amount: U256, now: i64) {
+        while self
+            .buys
+            .front()
+            .is_some_and(|(timestamp, _, _)| *timestamp <= now - VOLUME_WINDOW_SECS)
+        {
+            self.buys.pop_front();
+        }
+        self.buys.push_back((now, wallet, amount));
+    }
+
+    /// none when a buy of `amount` by `wallet` fits the caps at `now`,
+    /// otherwise the unix timestamp enough volume has rolled out of the window
+    pub fn resume_at(
+        &self,
+        wallet: Address,
+        amount: U256,
+        now: i64,
+    ) -> anyhow::Result<Option<i64>> {
+        let daily_resume_at = self.cap_resume_at(self.max_daily_volume, None, amount, now)?;
+        let wallet_resume_at =
+            self.cap_resume_at(self.max_per_wallet_volume, Some(wallet), amount, now)?;
+
+        Ok(daily_resume_at.max(wallet_resume_at))
+    }
+
+    fn cap_resume_at(
+        &self,
+        cap: Option<U256>,
+        wallet: Option<Address>,
+        amount: U256,
+        now: i64,
+    ) -> anyhow::Result<Option<i64>> {
+        let Some(cap) = cap else {
+            return Ok(None);
+        };
+        if amount > cap {
+            return Err(anyhow::anyhow!(
+                "[VolumeLedger] buy of {:?} above the volume cap {:?}",
+                amount,
+                cap
+            ));
+        }
+
+        let mut volume = self.window_volume(now, wallet);
+        if volume + amount <= cap {
+            return Ok(None);
+        }
+        for (timestamp, _, buy_amount) in self.window_buys(now, wallet) {
+            volume -= *buy_amount;
+            if volume + amount <= cap {
+                return Ok(Some(timestamp + VOLUME_WINDOW_SECS));
+            }
+        }
+
+        // unreachable since amount <= cap
+        Ok(Some(now + VOLUME_WINDOW_SECS))
+    }
+
+    fn window_volume(&self, now: i64, wallet: Option<Address>) -> U256 {
+        self.window_buys(now, wallet)
+            .fold(U256::zero(), |volume, (_, _, amount)| volume + amount)
+    }
+
+    fn window_buys(
+        &self,
+        now: i64,
+        wallet: Option<Address>,
+    ) -> impl Iterator<Item = &(i64, Address, U256)> {
+        self.buys.iter().filter(move |(timestamp, buy_wallet, _)| {
+            *timestamp > now - VOLUME_WINDOW_SECS
+                && wallet.is_none_or(|wallet| wallet == *buy_wallet)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volume_accumulates_in_the_rolling_window() {
+        let (wallet, other_wallet) = (Address::random(), Address::random());
+        let mut volume_ledger = VolumeLedger::new(None, None);
+        volume_ledger.record(wallet, parse_ether("0.5").unwrap(), 1_000);
+        volume_ledger.record(other_wallet, parse_ether("0.2").unwrap(), 2_000);
+        volume_ledger.record(wallet, parse_ether("0.1").unwrap(), 3_000);
+
+        assert_eq!(volume_ledger.volume(3_000), parse_ether("0.8").unwrap());
+        assert_eq!(
+            volume_ledger.wallet_volume(wallet, 3_000),
+            parse_ether("0.6").unwrap()
+        );
+        // the first buy rolls out of the window 24h later
+        let rolled = 1_000 + VOLUME_WINDOW_SECS;
+        assert_eq!(volume_ledger.volume(rolled), parse_ether("0.3").unwrap());
+        assert_eq!(
+            volume_ledger.wallet_volume(wallet, rolled),
+            parse_ether("0.1").unwrap()
+        );
+        // no cap, never paused
+        assert_eq!(
+            volume_ledger
+                .resume_at(wallet, parse_ether(100).unwrap(), 3_000)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn group_pauses_at_the_volume_cap() {
+        let (wallet, other_wallet) = (Address::random(), Address::random());
+        let mut volume_ledger = VolumeLedger::new(
+            Some(parse_ether(1).unwrap()),
+            Some(parse_ether("0.6").unwrap()),
+        );
+        let amount = parse_ether("0.4").unwrap();
+
+        assert_eq!(
+            volume_ledger.resume_at(wallet, amount, 1_000).unwrap(),
+            None
+        );
+        volume_ledger.record(wallet, amount, 1_000);
+        // the wallet cap: 0.4 + 0.4 > 0.6, fits once the first buy rolls out
+        assert_eq!(
+            volume_ledger.resume_at(wallet, amount, 2_000).unwrap(),
+            Some(1_000 + VOLUME_WINDOW_SECS)
+        );
+        assert_eq!(
+            volume_ledger
+                .resume_at(other_wallet, amount, 2_000)
+                .unwrap(),
+            None
+        );
+        volume_ledger.record(other_wallet, amount, 2_000);
+
+        // the daily cap: 0.8 + 0.4 > 1, fits once the first buy rolls out
+        let third_wallet = Address::random();
+        assert_eq!(
+            volume_ledger
+                .resume_at(third_wallet, amount, 3_000)
+                .unwrap(),
+            Some(1_000 + VOLUME_WINDOW_SECS)
+        );
+        assert_eq!(
+            volume_ledger
+                .resume_at(third_wallet, amount, 1_000 + VOLUME_WINDOW_SECS)
+                .unwrap(),
+            None
+        );
+        // a single buy above the cap never fits
+        assert!(volume_ledger
+            .resume_at(third_wallet, parse_ether(2).unwrap(), 3_000)
+            .is_err());
+    }
+}
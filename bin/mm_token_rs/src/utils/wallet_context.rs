@@ -0,0 +1,156 @@
+use std::{
+    collections::HashMap,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use ethers::{
+    providers::{Http, Middleware, Provider},
+    signers::Signer,
+    types::{Address, BlockNumber, H256, U256},
+};
+use mm_token_utils::{abi::MemeTokenAbigen, utils::load_mnemonic_wallet};
+use provider_utils::enums::ENetwork;
+use tokio::sync::RwLock;
+
+use super::NonceManager;
+
+/**
+ * get all system wallet nonces and balances
+ */
+pub async fn compute_system_wallets(
+    mnemonic: &str,
+    wallets_size: u32,
+    network: ENetwork,
+    token_address: &Address,
+    http_provider: Arc<Provider<Http>>,
+) -> anyhow::Result<HashMap<Address, Arc<RwLock<WalletContext>>>> {
+    let mut addresses = HashMap::new();
+    let token_contract = MemeTokenAbigen::new(*token_address, http_provider.clone());
+    let nonce_manager = NonceManager::new();
+
+    for index in 0..wallets_size {
+        let wallet = load_mnemonic_wallet(mnemonic, index, network, None)?;
+        let wallet_address = wallet.address();
+        let balance_of = token_contract.balance_of(wallet_address);
+        let (token_balance, eth_balance, nonce, mined_nonce) = tokio::join!(
+            balance_of.call(),
+            http_provider.get_balance(wallet_address, None),
+            http_provider.get_transaction_count(wallet_address, Some(BlockNumber::Pending.into())),
+            http_provider.get_transaction_count(wallet_address, Some(BlockNumber::Latest.into()))
+        );
+        let token_balance = token_balance?;
+        let eth_balance = eth_balance?;
+        // resume after the nonces submitted before a restart that may still be pending
+        let nonce = nonce_manager
+            .reconcile(&wallet_address, nonce?, mined_nonce?)
+            .await;
+
+        addresses.insert(
+            wallet_address,
+            Arc::new(RwLock::new(WalletContext {
+                index,
+                address: wallet_address,
+                nonce,
+                token_balance,
+                eth_balance,
+                suspected_honeypot: false,
+                last_buy_at: None,
+                stuck_tx: None,
+            })),
+        );
+    }
+
+    Ok(addresses)
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct WalletContext {
+    pub index: u32,
+    pub address: Address,
+    pub nonce: U256,
+    pub token_balance: U256,
+    pub eth_balance: U256,
+    // a successful buy delivered no token, the wallet is not used for buys anymore
+    pub suspected_honeypot: bool,
+    // set when a buy is submitted, `MIN_WALLET_COOLDOWN_SECS` counts from it
+    pub last_buy_at: Option<Instant>,
+    // a tx not mined within its timeout, the wallet sends nothing until it is mined or dropped
+    pub stuck_tx: Option<H256>,
+}
+
+impl WalletContext {
+    /// a wallet is not picked again until `cooldown` passed since its last submitted buy
+    pub fn is_cooling_down(&self, cooldown: Duration, now: Instant) -> bool {
+        self.last_buy_at
+            .is_some_and(|last_buy_at| now.saturating_duration_since(last_buy_at) < cooldown)
+    }
+
+    /// balances and pending nonce read again from chain, the optimistic updates of a reorged tx are dropped
+    pub async fn resync<M: Middleware + 'static>(
+        &mut self,
+        token_address: Address,
+        provider: Arc<M>,
+    ) -> anyhow::Result<()> {
+        let token_contract = MemeTokenAbigen::new(token_address, provider.clone());
+        let balance_of = token_contract.balance_of(self.address);
+        let (token_balance, eth_balance, nonce) = tokio::join!(
+            balance_of.call(),
+            provider.get_balance(self.address, None),
+            provider.get_transaction_count(self.address, Some(BlockNumber::Pending.into()))
+        );
+        self.token_balance = token_balance?;
+        self.eth_balance = eth_balance.map_err(|err| anyhow::anyhow!("{:?}", err))?;
+        self.nonce = nonce.map_err(|err| anyhow::anyhow!("{:?}", err))?;
+        Ok(())
+    }
+
+    /// true while the stuck tx is pending, a new tx could land next to it.
+    /// Once it is mined or dropped the wallet is resynced from chain and sends again
+    pub async fn is_stuck<M: Middleware + 'static>(
+        &mut self,
+        token_address: Address,
+        provider: Arc<M>,
+    ) -> anyhow::Result<bool> {
+        let Some(stuck_tx) = self.stuck_tx else {
+            return Ok(false);
+        };
+        let tx = provider
+            .get_transaction(stuck_tx)
+            .await
+            .map_err(|err| anyhow::anyhow!("{:?}", err))?;
+        if tx.is_some_and(|tx| tx.block_number.is_none()) {
+            return Ok(true);
+        }
+
+        log::info!(
+            "[WalletContext] stuck tx {:?} of wallet {:?} resolved",
+            stuck_tx,
+            self.address
+        );
+        self.stuck_tx = None;
+        self.resync(token_address, provider).await?;
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wallet_is_skipped_during_its_cooldown() {
+        let cooldown = Duration::from_secs(30);
+        let used_at = Instant::now();
+        let wallet_context = WalletContext {
+            last_buy_at: Some(used_at),
+            ..Default::default()
+        };
+
+        assert!(!WalletContext::default().is_cooling_down(cooldown, used_at));
+        assert!(wallet_context.is_cooling_down(cooldown, used_at));
+        assert!(wallet_context.is_cooling_down(cooldown, used_at + Duration::from_secs(29)));
+        assert!(!wallet_context.is_cooling_down(cooldown, used_at + cooldown));
+        assert!(!wallet_context.is_cooling_down(Duration::ZERO, used_at));
+    }
+}
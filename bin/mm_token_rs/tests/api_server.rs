@@ -0,0 +1,191 @@
+mod common;
+
+use common::{address_above, service_env, MockRpc, MockRpcExt, V2PairFixture, V3PoolFixture};
+use ethers::{abi::Token, types::Address, utils::parse_ether};
+use mm_token_rs::{api_server::build_api_server, constants::Env, core::ApiService};
+use mm_token_utils::constants::{
+    UNISWAP2_ROUTERS, UNISWAP3_QUOTER_V2, UNISWAP3_ROUTERS, WRAPPED_NATIVE_TOKENS,
+};
+use provider_utils::enums::ENetwork;
+use rocket::{http::Status, local::asynchronous::Client};
+use serde_json::Value;
+
+const TOKEN_ADDRESS: &str = "0x1111111111111111111111111111111111111111";
+
+#[tokio::test]
+async fn network_status_returns_token_info_of_provider() {
+    let mnemonic = "test test test test test test test test test test test junk";
+    for (key, value) in [
+        ("LISTEN_NETWORK", "BLAST_SEPOLIA"),
+        ("TOKEN_ADDRESS", TOKEN_ADDRESS),
+        ("ACTIVE_ROUTER", "UNISWAP2_ROUTERS"),
+        ("TRADING_SLIPPAGE", "1"),
+        (
+            "DEPLOYER_PRIVATE_KEY",
+            "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+        ),
+        ("BUYER_MNEMONIC", mnemonic),
+        ("BUYER_SURPLUS_BALANCE", "0.01"),
+        ("BUYER_WALLETS_COUNT", "1"),
+        ("AUTO_BUYER_MNEMONIC", mnemonic),
+        ("AUTO_BUYER_WALLETS_COUNT", "1"),
+        ("SELLER_MNEMONIC", mnemonic),
+        ("SELLER_WALLETS_COUNT", "1"),
+        ("AUTO_SELL_MIN_PERCENT", "10"),
+        ("AUTO_SELL_MAX_PERCENT", "20"),
+        ("TOKEN_METADATA_RETRIES", "0"),
+    ] {
+        std::env::set_var(key, value);
+    }
+    let token_address: Address = TOKEN_ADDRESS.parse().unwrap();
+    let http_provider = MockRpc::new()
+        .with_erc20(token_address, "MM", 18, parse_ether(1_000_000_000).unwrap())
+        .on_call(
+            token_address,
+            "name()",
+            &[],
+            &[Token::String("Meme".to_string())],
+        )
+        .launch()
+        .await;
+    let api_service = ApiService::new_with_provider(Env::new(), http_provider);
+
+    let client = Client::tracked(build_api_server(api_service))
+        .await
+        .unwrap();
+    let response = client.get("/api/network_status").dispatch().await;
+
+    assert_eq!(response.status(), Status::Ok);
+    let network_status: Value = response.into_json().await.unwrap();
+    assert_eq!(network_status["network"]["name"], "BLAST_SEPOLIA");
+    assert_eq!(network_status["network"]["blockNumber"], 100);
+    assert_eq!(network_status["token"]["address"], TOKEN_ADDRESS);
+    assert_eq!(network_status["token"]["symbol"], "MM");
+    assert_eq!(network_status["token"]["name"], "Meme");
+    assert_eq!(network_status["token"]["decimals"], 18);
+    assert_eq!(network_status["token"]["totalSupply"], 1_000_000_000u64);
+}
+
+/// api service of a base mainnet token trading on `router`, reading through `mock_rpc`
+async fn router_api_service(token_address: Address, router: &str, mock_rpc: MockRpc) -> ApiService {
+    let mnemonic = "test test test test test test test test test test test junk";
+    // the envs are process wide, same values as the other tests of this binary
+    for (key, value) in [
+        ("LISTEN_NETWORK", "BLAST_SEPOLIA"),
+        ("BUYER_MNEMONIC", mnemonic),
+        ("BUYER_SURPLUS_BALANCE", "0.01"),
+        ("BUYER_WALLETS_COUNT", "1"),
+        ("AUTO_BUYER_MNEMONIC", mnemonic),
+        ("AUTO_BUYER_WALLETS_COUNT", "1"),
+        ("SELLER_MNEMONIC", mnemonic),
+        ("SELLER_WALLETS_COUNT", "1"),
+        ("AUTO_SELL_MIN_PERCENT", "10"),
+        ("AUTO_SELL_MAX_PERCENT", "20"),
+        ("TOKEN_METADATA_RETRIES", "0"),
+    ] {
+        std::env::set_var(key, value);
+    }
+    let mut env = service_env(token_address);
+    env.token.router = Some(router.to_string());
+    let http_provider = mock_rpc
+        .with_erc20(token_address, "MM", 18, parse_ether(1_000_000_000).unwrap())
+        .launch()
+        .await;
+    ApiService::new_with_provider(env, http_provider)
+}
+
+#[tokio::test]
+async fn network_status_reports_the_v2_pair() {
+    let weth_address = WRAPPED_NATIVE_TOKENS[&ENetwork::BaseMainnet].address;
+    let token_address = address_above(weth_address);
+    let factory = Address::random();
+    let pair = V2PairFixture::new(token_address, weth_address, 1_000_000, 10);
+    let api_service = router_api_service(
+        token_address,
+        "UNISWAP2_ROUTERS",
+        MockRpc::new()
+            .with_router_factory(UNISWAP2_ROUTERS[&ENetwork::BaseMainnet], factory)
+            .with_v2_pair(factory, &pair),
+    )
+    .await;
+
+    let network_status = api_service.get_network_status().await.unwrap();
+    assert_eq!(network_status.token.pair_contract, pair.address);
+    assert_eq!(network_status.token.pair_fee_tier, None);
+}
+
+#[tokio::test]
+async fn network_status_reports_the_v3_pool_and_its_fee_tier() {
+    let weth_address = WRAPPED_NATIVE_TOKENS[&ENetwork::BaseMainnet].address;
+    let token_address = address_above(weth_address);
+    let factory = Address::random();
+    let pool = V3PoolFixture::new(token_address, weth_address, 10000, 0.5);
+    let api_service = router_api_service(
+        token_address,
+        "UNISWAP3_ROUTERS",
+        MockRpc::new()
+            .with_router_factory(UNISWAP3_ROUTERS[&ENetwork::BaseMainnet], factory)
+            .on_any_call(
+                factory,
+                "getPool(address,address,uint24)",
+                &[Token::Address(Address::zero())],
+            )
+            .with_v3_pool(
+                factory,
+                UNISWAP3_QUOTER_V2[&ENetwork::BaseMainnet],
+                &pool,
+                parse_ether(1).unwrap(),
+            ),
+    )
+    .await;
+
+    let network_status = api_service.get_network_status().await.unwrap();
+    assert_eq!(network_status.token.pair_contract, pool.address);
+    assert_eq!(network_status.token.pair_fee_tier, Some(10000));
+}
+
+#[tokio::test]
+async fn network_status_reports_a_zero_pair_before_it_is_created() {
+    let token_address = Address::random();
+    let factory = Address::random();
+    let api_service = router_api_service(
+        token_address,
+        "UNISWAP2_ROUTERS",
+        MockRpc::new()
+            .with_router_factory(UNISWAP2_ROUTERS[&ENetwork::BaseMainnet], factory)
+            .on_any_call(
+                factory,
+                "getPair(address,address)",
+                &[Token::Address(Address::zero())],
+            ),
+    )
+    .await;
+
+    let network_status = api_service.get_network_status().await.unwrap();
+    assert_eq!(network_status.token.pair_contract, Address::zero());
+    assert_eq!(network_status.token.pair_fee_tier, None);
+}
+
+#[tokio::test]
+async fn dump_waits_for_its_confirmation() {
+    let token_address = Address::random();
+    let mock_rpc = MockRpc::new();
+    let api_service = router_api_service(token_address, "UNISWAP2_ROUTERS", mock_rpc.clone()).await;
+    let client = Client::tracked(build_api_server(api_service))
+        .await
+        .unwrap();
+
+    let response = client
+        .post("/api/dump?group=seller&interval_min=0&interval_max=0")
+        .dispatch()
+        .await;
+    assert_eq!(response.status(), Status::BadRequest);
+    assert!(response
+        .into_string()
+        .await
+        .unwrap()
+        .contains("confirm=true"));
+    // nothing was read nor sent for the dump
+    assert!(mock_rpc.requests("eth_gasPrice").is_empty());
+    assert!(mock_rpc.sent_txs().is_empty());
+}
@@ -0,0 +1,243 @@
+mod common;
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use common::{provider_pool, service_env, MockRpc, MockRpcExt, V2PairFixture};
+use ethers::{
+    abi::{encode, Token},
+    signers::Signer,
+    types::{Address, Block, Log, TransactionReceipt, H256, U256, U64},
+    utils::{hex, id, keccak256, parse_ether, parse_units},
+};
+use mm_token_rs::{core::BuyService, utils::WalletContext};
+use mm_token_utils::{
+    constants::{UNISWAP2_ROUTERS, WRAPPED_NATIVE_TOKENS},
+    utils::load_mnemonic_wallet,
+};
+use provider_utils::enums::ENetwork;
+use tokio::sync::RwLock;
+
+const BUYER_MNEMONIC: &str = "test test test test test test test test test test test junk";
+const SWAP_SIGNATURE: &str = "swapExactETHForTokens(uint256,address[],address,uint256)";
+
+/// a v2 pair of `token_address` the router swaps 1 ETH for 1000 tokens on
+fn buy_rpc(token_address: Address) -> MockRpc {
+    let weth_address = WRAPPED_NATIVE_TOKENS[&ENetwork::BaseMainnet].address;
+    let router = UNISWAP2_ROUTERS[&ENetwork::BaseMainnet];
+    let factory = Address::random();
+    let pair = V2PairFixture::new(
+        token_address,
+        weth_address,
+        parse_ether(1_000_000).unwrap().as_u128(),
+        parse_ether(10).unwrap().as_u128(),
+    );
+    MockRpc::new()
+        .with_eth_balance(parse_ether(1).unwrap())
+        .with_erc20(token_address, "MM", 18, parse_ether(1_000_000_000).unwrap())
+        .with_router_factory(router, factory)
+        .with_v2_pair(factory, &pair)
+        .on_any_call(
+            router,
+            "getAmountOut(uint256,uint256,uint256)",
+            &[Token::Uint(parse_ether(1_000).unwrap())],
+        )
+        .on_any_call(
+            router,
+            SWAP_SIGNATURE,
+            &[Token::Array(vec![
+                Token::Uint(parse_ether(1).unwrap()),
+                Token::Uint(parse_ether(1_000).unwrap()),
+            ])],
+        )
+}
+
+// own test binary, the service reads the buyer envs when it is created
+async fn buy_service(token_address: Address, mock_rpc: MockRpc, dry_run: bool) -> BuyService {
+    for (key, value) in [
+        ("BUYER_MNEMONIC", BUYER_MNEMONIC),
+        ("BUYER_WALLETS_COUNT", "1"),
+        ("BUYER_SURPLUS_BALANCE", "0.01"),
+        ("SWAP_METHOD_MODE", "Standard"),
+        ("PNL_LEDGER_PATH", ""),
+        ("REORG_DEPTH", "3"),
+    ] {
+        std::env::set_var(key, value);
+    }
+    let mut env = service_env(token_address);
+    env.token.router = Some("UNISWAP2_ROUTERS".to_string());
+    env.dry_run = dry_run;
+    let http_provider = mock_rpc.launch().await;
+    BuyService::new(
+        env,
+        Arc::new(RwLock::new(parse_units(1, "gwei").unwrap().into())),
+        provider_pool(&http_provider),
+        http_provider,
+    )
+}
+
+fn wallet_context() -> Arc<RwLock<WalletContext>> {
+    let wallet = load_mnemonic_wallet(BUYER_MNEMONIC, 0, ENetwork::BaseMainnet, None).unwrap();
+    Arc::new(RwLock::new(WalletContext {
+        address: wallet.address(),
+        eth_balance: parse_ether(1).unwrap(),
+        ..Default::default()
+    }))
+}
+
+/// `eth_call`s of `to` starting with the selector of `signature`
+fn calls_to(mock_rpc: &MockRpc, to: Address, signature: &str) -> usize {
+    let selector = format!("0x{}", hex::encode(id(signature)));
+    mock_rpc
+        .requests("eth_call")
+        .iter()
+        .filter(|params| {
+            params[0]["to"] == serde_json::json!(to)
+                && params[0]["data"]
+                    .as_str()
+                    .is_some_and(|data| data.starts_with(&selector))
+        })
+        .count()
+}
+
+#[tokio::test]
+async fn dry_run_buys_are_simulated_not_sent() {
+    let token_address = Address::random();
+    let mock_rpc = buy_rpc(token_address);
+    let buy_service = buy_service(token_address, mock_rpc.clone(), true).await;
+    let wallet_context = wallet_context();
+
+    // a dry run buy is done with the wallet, nothing is left to send
+    assert!(!buy_service.try_buy(&wallet_context).await.unwrap());
+
+    let router = UNISWAP2_ROUTERS[&ENetwork::BaseMainnet];
+    assert_eq!(calls_to(&mock_rpc, router, SWAP_SIGNATURE), 1);
+    assert!(mock_rpc.requests("eth_sendRawTransaction").is_empty());
+    let wallet_context = wallet_context.read().await;
+    assert_eq!(wallet_context.nonce, U256::zero());
+    assert_eq!(wallet_context.eth_balance, parse_ether(1).unwrap());
+}
+
+#[tokio::test]
+async fn zero_token_delivery_flags_the_wallet_as_suspected_honeypot() {
+    let token_address = Address::random();
+    // the mined buy receipt has no token transfer
+    let mock_rpc = buy_rpc(token_address);
+    let buy_service = buy_service(token_address, mock_rpc.clone(), false).await;
+    let wallet_context = wallet_context();
+
+    assert!(!buy_service.try_buy(&wallet_context).await.unwrap());
+    {
+        let wallet_context = wallet_context.read().await;
+        assert!(wallet_context.suspected_honeypot);
+        assert!(wallet_context.token_balance.is_zero());
+        assert_eq!(wallet_context.nonce, U256::one());
+    }
+    assert_eq!(mock_rpc.sent_txs().len(), 1);
+    // the tokens are read from the receipt, not from the token balance of the wallet
+    assert_eq!(calls_to(&mock_rpc, token_address, "balanceOf(address)"), 0);
+
+    // the flagged wallet does not buy again
+    assert!(!buy_service.try_buy(&wallet_context).await.unwrap());
+    assert_eq!(mock_rpc.sent_txs().len(), 1);
+}
+
+#[tokio::test]
+async fn delivered_tokens_are_added_to_the_wallet() {
+    let token_address = Address::random();
+    let wallet_context = wallet_context();
+    let wallet_address = wallet_context.read().await.address;
+    let mock_rpc = buy_rpc(token_address);
+    mock_rpc.push_responses(
+        "eth_getTransactionReceipt",
+        [TransactionReceipt {
+            transaction_hash: H256::random(),
+            block_number: Some(U64::from(100)),
+            status: Some(U64::one()),
+            logs: vec![Log {
+                address: token_address,
+                topics: vec![
+                    H256::from(keccak256("Transfer(address,address,uint256)")),
+                    H256::from(Address::random()),
+                    H256::from(wallet_address),
+                ],
+                data: encode(&[Token::Uint(parse_ether(1_000).unwrap())]).into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    );
+    let buy_service = buy_service(token_address, mock_rpc.clone(), false).await;
+
+    assert!(buy_service.try_buy(&wallet_context).await.unwrap());
+    let wallet_context = wallet_context.read().await;
+    assert!(!wallet_context.suspected_honeypot);
+    assert_eq!(wallet_context.token_balance, parse_ether(1_000).unwrap());
+}
+
+#[tokio::test]
+async fn reorged_buy_restores_the_wallet_balance() {
+    let token_address = Address::random();
+    let wallet_context = wallet_context();
+    let wallet_address = wallet_context.read().await.address;
+    let buy_block = H256::random();
+    let mock_rpc = buy_rpc(token_address).on_call(
+        token_address,
+        "balanceOf(address)",
+        &[Token::Address(wallet_address)],
+        &[Token::Uint(U256::zero())],
+    );
+    // the buy is mined in block 100
+    mock_rpc.push_responses(
+        "eth_getTransactionReceipt",
+        [TransactionReceipt {
+            transaction_hash: H256::random(),
+            block_number: Some(U64::from(100)),
+            block_hash: Some(buy_block),
+            status: Some(U64::one()),
+            logs: vec![Log {
+                address: token_address,
+                topics: vec![
+                    H256::from(keccak256("Transfer(address,address,uint256)")),
+                    H256::from(Address::random()),
+                    H256::from(wallet_address),
+                ],
+                data: encode(&[Token::Uint(parse_ether(1_000).unwrap())]).into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }],
+    );
+    let buy_service = buy_service(token_address, mock_rpc.clone(), false).await;
+
+    assert!(buy_service.try_buy(&wallet_context).await.unwrap());
+    assert!(wallet_context.read().await.eth_balance < parse_ether("0.02").unwrap());
+
+    // block 101 extends the buy block, the next head is built on another block 100
+    let head = |number: u64, hash: H256, parent_hash: H256| Block::<H256> {
+        number: Some(U64::from(number)),
+        hash: Some(hash),
+        parent_hash,
+        ..Default::default()
+    };
+    let block_101 = H256::random();
+    mock_rpc.push_responses(
+        "eth_getBlockByNumber",
+        [
+            head(100, buy_block, H256::random()),
+            head(101, block_101, buy_block),
+            head(102, H256::random(), H256::random()),
+            head(100, H256::random(), H256::random()),
+        ],
+    );
+    buy_service.watch_reorgs(HashMap::from([(wallet_address, wallet_context.clone())]));
+
+    // the balances are read again from chain
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while wallet_context.read().await.eth_balance != parse_ether(1).unwrap() {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("the reorged buy was not rolled back");
+    assert!(wallet_context.read().await.token_balance.is_zero());
+}
@@ -0,0 +1,435 @@
+// every test binary uses a part of the fixtures
+#![allow(dead_code)]
+
+use std::{
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+    sync::{Arc, Once},
+    time::Duration,
+};
+
+use ethers::{
+    abi::Token,
+    providers::{Http, Provider},
+    types::{Address, U256, U64},
+    utils::{parse_ether, parse_units},
+};
+use mm_token_rs::{
+    constants::{Env, TokenConfig},
+    core::MarketMakerService,
+};
+use mm_token_utils::constants::{UNISWAP2_ROUTERS, WRAPPED_NATIVE_TOKENS};
+pub use provider_utils::mock_rpc::MockRpc;
+use provider_utils::{
+    enums::{ENetwork, EProviderStrategy},
+    provider_pool::ProviderPool,
+};
+use rocket::{config::LogLevel, post, routes, serde::json::Json, State};
+use serde_json::{json, Value};
+use tokio::sync::RwLock;
+
+pub const BASE_CHAIN_ID: u64 = 8453;
+
+/// the uniswap fixtures and the http node of the services, on top of `MockRpc`
+pub trait MockRpcExt: Sized {
+    /// `symbol`, `name` (the symbol), `decimals` and `totalSupply` of an erc20
+    fn with_erc20(self, token: Address, symbol: &str, decimals: u8, total_supply: U256) -> Self;
+
+    /// `factory()` of the v2 or v3 router
+    fn with_router_factory(self, router: Address, factory: Address) -> Self;
+
+    /// `getPair` in both token orders, `token0`, `token1` and `getReserves` of the pair
+    fn with_v2_pair(self, factory: Address, pair: &V2PairFixture) -> Self;
+
+    /// `getPool` in both token orders, the pool state read by pricing and liquidity checks,
+    /// and `quote_amount_out` as the quote of any `quoteExactInputSingle`
+    fn with_v3_pool(
+        self,
+        factory: Address,
+        quoter: Address,
+        pool: &V3PoolFixture,
+        quote_amount_out: U256,
+    ) -> Self;
+
+    /// serves the base mainnet node on a random local port
+    async fn launch(self) -> Arc<Provider<Http>>;
+
+    /// serves the base mainnet node on a random local port, returns the rpc url
+    async fn serve(self) -> String;
+}
+
+impl MockRpcExt for MockRpc {
+    fn with_erc20(self, token: Address, symbol: &str, decimals: u8, total_supply: U256) -> Self {
+        self.on_call(token, "symbol()", &[], &[Token::String(symbol.to_string())])
+            .on_call(token, "name()", &[], &[Token::String(symbol.to_string())])
+            .on_call(
+                token,
+                "decimals()",
+                &[],
+                &[Token::Uint(U256::from(decimals))],
+            )
+            .on_call(token, "totalSupply()", &[], &[Token::Uint(total_supply)])
+    }
+
+    fn with_router_factory(self, router: Address, factory: Address) -> Self {
+        self.on_call(router, "factory()", &[], &[Token::Address(factory)])
+    }
+
+    fn with_v2_pair(self, factory: Address, pair: &V2PairFixture) -> Self {
+        let (token0, token1) = (Token::Address(pair.token0), Token::Address(pair.token1));
+        self.on_call(
+            factory,
+            "getPair(address,address)",
+            &[token0.clone(), token1.clone()],
+            &[Token::Address(pair.address)],
+        )
+        .on_call(
+            factory,
+            "getPair(address,address)",
+            &[token1.clone(), token0.clone()],
+            &[Token::Address(pair.address)],
+        )
+        .on_call(pair.address, "token0()", &[], &[token0])
+        .on_call(pair.address, "token1()", &[], &[token1])
+        .on_call(
+            pair.address,
+            "getReserves()",
+            &[],
+            &[
+                Token::Uint(U256::from(pair.reserve0)),
+                Token::Uint(U256::from(pair.reserve1)),
+                Token::Uint(U256::zero()),
+            ],
+        )
+    }
+
+    fn with_v3_pool(
+        self,
+        factory: Address,
+        quoter: Address,
+        pool: &V3PoolFixture,
+        quote_amount_out: U256,
+    ) -> Self {
+        let (token0, token1) = (Token::Address(pool.token0), Token::Address(pool.token1));
+        let fee = Token::Uint(U256::from(pool.fee));
+        self.on_call(
+            factory,
+            "getPool(address,address,uint24)",
+            &[token0.clone(), token1.clone(), fee.clone()],
+            &[Token::Address(pool.address)],
+        )
+        .on_call(
+            factory,
+            "getPool(address,address,uint24)",
+            &[token1.clone(), token0.clone(), fee.clone()],
+            &[Token::Address(pool.address)],
+        )
+        .on_call(pool.address, "token0()", &[], &[token0])
+        .on_call(pool.address, "token1()", &[], &[token1])
+        .on_call(pool.address, "fee()", &[], &[fee])
+        .on_call(
+            pool.address,
+            "liquidity()",
+            &[],
+            &[Token::Uint(U256::from(pool.liquidity))],
+        )
+        .on_call(
+            pool.address,
+            "slot0()",
+            &[],
+            &[
+                Token::Uint(pool.sqrt_price_x96),
+                Token::Int(U256::zero()),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::one()),
+                Token::Uint(U256::one()),
+                Token::Uint(U256::zero()),
+                Token::Bool(true),
+            ],
+        )
+        .on_call(
+            pool.token0,
+            "balanceOf(address)",
+            &[Token::Address(pool.address)],
+            &[Token::Uint(pool.balance0)],
+        )
+        .on_call(
+            pool.token1,
+            "balanceOf(address)",
+            &[Token::Address(pool.address)],
+            &[Token::Uint(pool.balance1)],
+        )
+        .on_any_call(
+            quoter,
+            "quoteExactInputSingle((address,address,uint256,uint24,uint160))",
+            &[
+                Token::Uint(quote_amount_out),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+            ],
+        )
+    }
+
+    async fn launch(self) -> Arc<Provider<Http>> {
+        let url = self.serve().await;
+        Arc::new(
+            Provider::<Http>::try_from(url)
+                .unwrap()
+                .interval(Duration::from_millis(100)),
+        )
+    }
+
+    async fn serve(self) -> String {
+        let port = std::net::TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let config = rocket::Config {
+            address: Ipv4Addr::LOCALHOST.into(),
+            port,
+            log_level: LogLevel::Off,
+            ..rocket::Config::debug_default()
+        };
+        tokio::spawn(
+            rocket::custom(config)
+                .manage(self.with_chain_id(BASE_CHAIN_ID))
+                .mount("/", routes![rpc])
+                .launch(),
+        );
+        for _ in 0..50 {
+            if tokio::net::TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+                .await
+                .is_ok()
+            {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+
+        format!("http://127.0.0.1:{}", port)
+    }
+}
+
+#[post("/", data = "<request>")]
+fn rpc(request: Json<Value>, mock_rpc: &State<MockRpc>) -> Json<Value> {
+    let id = request["id"].clone();
+    Json(
+        match mock_rpc.handle(
+            request["method"].as_str().unwrap_or_default(),
+            request["params"].clone(),
+        ) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(err) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": err.code, "message": err.message, "data": err.data }
+            }),
+        },
+    )
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct V2PairFixture {
+    pub address: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub reserve0: u128,
+    pub reserve1: u128,
+}
+
+impl V2PairFixture {
+    /// pair of `token` and `weth`, token0 is the lower address like a real pair
+    pub fn new(token: Address, weth: Address, token_reserve: u128, weth_reserve: u128) -> Self {
+        let (token0, token1, reserve0, reserve1) = if weth < token {
+            (weth, token, weth_reserve, token_reserve)
+        } else {
+            (token, weth, token_reserve, weth_reserve)
+        };
+        Self {
+            address: Address::random(),
+            token0,
+            token1,
+            reserve0,
+            reserve1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct V3PoolFixture {
+    pub address: Address,
+    pub token0: Address,
+    pub token1: Address,
+    pub fee: u32,
+    pub sqrt_price_x96: U256,
+    pub liquidity: u128,
+    pub balance0: U256,
+    pub balance1: U256,
+}
+
+impl V3PoolFixture {
+    /// pool of `token` and `weth` where one token0 is worth `sqrt_price ^ 2` token1,
+    /// token0 is the lower address, deep balances on both sides
+    pub fn new(token: Address, weth: Address, fee: u32, sqrt_price: f64) -> Self {
+        let (token0, token1) = if weth < token {
+            (weth, token)
+        } else {
+            (token, weth)
+        };
+        Self {
+            address: Address::random(),
+            token0,
+            token1,
+            fee,
+            sqrt_price_x96: sqrt_price_x96(sqrt_price),
+            liquidity: 10u128.pow(24),
+            balance0: U256::exp10(24),
+            balance1: U256::exp10(24),
+        }
+    }
+}
+
+/**
+ * `sqrt_price * 2^96`, exact for sqrt prices with a short binary expansion (e.g. 2, 0.5, 0.25)
+ */
+pub fn sqrt_price_x96(sqrt_price: f64) -> U256 {
+    U256::from((sqrt_price * 2f64.powi(48)) as u128) << 48
+}
+
+/// address below or above `address`, to pin which side of a pool is token0
+pub fn address_below(address: Address) -> Address {
+    let mut bytes = address.to_fixed_bytes();
+    bytes[0] = 0x00;
+    Address::from(bytes)
+}
+
+pub fn address_above(address: Address) -> Address {
+    let mut bytes = address.to_fixed_bytes();
+    bytes[0] = 0xff;
+    Address::from(bytes)
+}
+
+/// pool of the mock node only, the services switch to no other provider
+pub fn provider_pool(http_provider: &Provider<Http>) -> ProviderPool {
+    ProviderPool::new(
+        vec![http_provider.clone()],
+        EProviderStrategy::default(),
+        Vec::new(),
+        Arc::new(RwLock::new(0)),
+    )
+}
+
+/// env of a base mainnet token with the envs the router services require
+pub fn service_env(token_address: Address) -> Env {
+    static SET_ENV: Once = Once::new();
+    SET_ENV.call_once(|| {
+        for (key, value) in [
+            (
+                "DEPLOYER_PRIVATE_KEY",
+                "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80",
+            ),
+            ("TRADING_SLIPPAGE", "1"),
+        ] {
+            std::env::set_var(key, value);
+        }
+    });
+
+    Env {
+        listen_network: ENetwork::BaseMainnet,
+        chain_id: U64::from(BASE_CHAIN_ID),
+        token_address,
+        token: TokenConfig {
+            address: token_address,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+pub const MM_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+/// v2 market making node of the token: every wallet holds 1 ETH and `token_balance` tokens
+/// approved to the router, which quotes 1 gwei for any swap
+pub fn mm_rpc(token_address: Address, token_balance: U256) -> MockRpc {
+    let router = UNISWAP2_ROUTERS[&ENetwork::BaseMainnet];
+    let factory = Address::random();
+    let pair = V2PairFixture::new(
+        token_address,
+        WRAPPED_NATIVE_TOKENS[&ENetwork::BaseMainnet].address,
+        parse_ether(1_000_000).unwrap().as_u128(),
+        parse_ether(10).unwrap().as_u128(),
+    );
+    MockRpc::new()
+        .with_eth_balance(parse_ether(1).unwrap())
+        .with_erc20(token_address, "MM", 18, parse_ether(1_000_000_000).unwrap())
+        .with_router_factory(router, factory)
+        .with_v2_pair(factory, &pair)
+        .on_any_call(
+            router,
+            "getAmountOut(uint256,uint256,uint256)",
+            &[Token::Uint(parse_units(1, "gwei").unwrap().into())],
+        )
+        .on_any_call(
+            token_address,
+            "balanceOf(address)",
+            &[Token::Uint(token_balance)],
+        )
+        .on_any_call(
+            token_address,
+            "allowance(address,address)",
+            &[Token::Uint(U256::MAX)],
+        )
+}
+
+/// temp dir `name` of the process holding `mm_config` as mm_config.json, the state files are written next to it
+pub fn mm_work_dir(name: &str, mm_config: Value) -> PathBuf {
+    let work_dir = std::env::temp_dir().join(format!("{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&work_dir).unwrap();
+    std::fs::write(work_dir.join("mm_config.json"), mm_config.to_string()).unwrap();
+    work_dir
+}
+
+/// `service_env` trading the v2 router with the mm_config.json of `work_dir`,
+/// the market maker writes its state in `work_dir` and reads `envs` on top of the test defaults
+pub fn mm_service_env(token_address: Address, work_dir: &Path, envs: &[(&str, &str)]) -> Env {
+    let mm_state_path = work_dir.join("mm_state.json");
+    for (key, value) in [
+        ("MM_PREFLIGHT", "false"),
+        ("SWAP_METHOD_MODE", "Standard"),
+        ("PNL_LEDGER_PATH", ""),
+        ("MM_STATE_PATH", mm_state_path.to_str().unwrap()),
+    ]
+    .iter()
+    .chain(envs)
+    {
+        std::env::set_var(key, value);
+    }
+
+    let mut env = service_env(token_address);
+    env.token.router = Some("UNISWAP2_ROUTERS".to_string());
+    env.token.mm_config_path = Some(
+        work_dir
+            .join("mm_config.json")
+            .to_string_lossy()
+            .to_string(),
+    );
+    env.tokens = vec![env.token.clone()];
+    env
+}
+
+/// runs the market maker of `env` on `mock_rpc` until its shutdown, at most a minute
+pub async fn run_market_maker(env: Env, mock_rpc: &MockRpc) {
+    let rpc_url = mock_rpc.clone().serve().await;
+    let http_provider = Arc::new(Provider::<Http>::try_from(rpc_url).unwrap());
+    let gas_price = Arc::new(RwLock::new(parse_units(1, "gwei").unwrap().into()));
+    tokio::time::timeout(
+        Duration::from_secs(60),
+        MarketMakerService::new(env, gas_price, provider_pool(&http_provider), http_provider)
+            .market_make(),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+}
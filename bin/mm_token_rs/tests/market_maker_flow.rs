@@ -0,0 +1,94 @@
+mod common;
+
+use std::{collections::HashMap, time::Duration};
+
+use common::{mm_rpc, mm_service_env, mm_work_dir, run_market_maker, MM_MNEMONIC};
+use ethers::{
+    signers::Signer,
+    types::{Address, U256},
+    utils::parse_ether,
+};
+use mm_token_rs::utils::{shutdown_on, FlowState};
+use mm_token_utils::{constants::UNISWAP2_ROUTERS, utils::load_mnemonic_wallet};
+use provider_utils::enums::ENetwork;
+use serde_json::json;
+
+// own test binary, the service reads its envs and writes its state in a temp dir
+#[tokio::test]
+async fn sell_only_iteration_sells_the_tokens_of_a_buy_only_wallet() {
+    let token_address = Address::random();
+    let router = UNISWAP2_ROUTERS[&ENetwork::BaseMainnet];
+    let wallet = |index| {
+        load_mnemonic_wallet(MM_MNEMONIC, index, ENetwork::BaseMainnet, None)
+            .unwrap()
+            .address()
+    };
+    // every wallet holds 1000 tokens approved to the router
+    let mock_rpc = mm_rpc(token_address, parse_ether(1_000).unwrap());
+    // the group starts at the funded wallet 1, the token holder wallet 0 has no gas left
+    mock_rpc.push_responses("eth_getBalance", [parse_ether(1).unwrap(), U256::zero()]);
+
+    let work_dir = mm_work_dir(
+        "mm_flow",
+        json!({
+            "defaultSettings": {
+                "maxWalletsCount": 3,
+                "minBuyVolume": 0.01,
+                "maxBuyVolume": 0.01,
+                "minDelayTime": 0,
+                "maxDelayTime": 0,
+                "minRetainToken": 0,
+                "maxRetainToken": 0,
+                "buySellRatio": 0.5,
+                "actionRandomness": 0
+            },
+            "groups": [{ "mnemonic": MM_MNEMONIC, "startIndex": 1 }]
+        }),
+    );
+    // an earlier buy only iteration of wallet 0 kept its tokens
+    let flow_state_path = work_dir.join("mm_flow_state.json");
+    std::fs::write(
+        &flow_state_path,
+        json!({
+            format!("{:?}", wallet(0)): { "bought": 0.02, "sold": 0.0, "tokenHolders": [0] }
+        })
+        .to_string(),
+    )
+    .unwrap();
+    let env = mm_service_env(
+        token_address,
+        &work_dir,
+        &[("MM_FLOW_STATE_PATH", flow_state_path.to_str().unwrap())],
+    );
+
+    // stop once wallet 1 moved on to wallet 2
+    let signal_rpc = mock_rpc.clone();
+    tokio::spawn(shutdown_on(env.exit.clone(), async move {
+        while signal_rpc.sent_txs().len() < 4 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }));
+    run_market_maker(env, &mock_rpc).await;
+
+    let sent_txs: Vec<(Address, Option<Address>)> = mock_rpc
+        .sent_txs()
+        .iter()
+        .map(|tx| (tx.from, tx.to))
+        .collect();
+    // no buy: gas to the holder, its sell, its eth back, then the migration of wallet 1
+    assert_eq!(
+        sent_txs[..4],
+        [
+            (wallet(1), Some(wallet(0))),
+            (wallet(0), Some(router)),
+            (wallet(0), Some(wallet(1))),
+            (wallet(1), Some(wallet(2))),
+        ]
+    );
+    let flow_states: HashMap<Address, FlowState> =
+        serde_json::from_str(&std::fs::read_to_string(&flow_state_path).unwrap()).unwrap();
+    let flow_state = &flow_states[&wallet(0)];
+    assert_eq!(flow_state.bought, 0.02);
+    assert!(flow_state.token_holders.is_empty());
+    std::fs::remove_dir_all(work_dir).unwrap();
+}
@@ -0,0 +1,58 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{mm_rpc, mm_service_env, mm_work_dir, run_market_maker, MockRpc, MM_MNEMONIC};
+use ethers::types::{Address, U256};
+use mm_token_rs::utils::shutdown_on;
+use mm_token_utils::constants::UNISWAP2_ROUTERS;
+use provider_utils::enums::ENetwork;
+use serde_json::json;
+
+// own test binary, the service reads its envs and writes its state in a temp dir
+#[tokio::test]
+async fn failed_preflight_simulation_broadcasts_nothing() {
+    let token_address = Address::random();
+    let router = UNISWAP2_ROUTERS[&ENetwork::BaseMainnet];
+    // the swaps revert in eth_call, a broadcast preflight would send its buy anyway
+    let mock_rpc = mm_rpc(token_address, U256::zero());
+
+    let work_dir = mm_work_dir(
+        "mm_preflight",
+        json!({
+            "defaultSettings": {
+                "maxWalletsCount": 2,
+                "minBuyVolume": 0.01,
+                "maxBuyVolume": 0.01,
+                "minDelayTime": 0,
+                "maxDelayTime": 0,
+                "minRetainToken": 0,
+                "maxRetainToken": 0
+            },
+            "groups": [{ "mnemonic": MM_MNEMONIC }]
+        }),
+    );
+    let env = mm_service_env(token_address, &work_dir, &[("MM_PREFLIGHT", "true")]);
+
+    // the shutdown arrives once the preflight buy is simulated
+    let is_buy_simulated = move |mock_rpc: &MockRpc| {
+        mock_rpc
+            .requests("eth_call")
+            .iter()
+            .any(|params| params[0]["to"] == json!(router) && params[0]["value"].is_string())
+    };
+    let signal_rpc = mock_rpc.clone();
+    tokio::spawn(shutdown_on(env.exit.clone(), async move {
+        while !is_buy_simulated(&signal_rpc) {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        // the aborted group would trade right after the preflight
+        tokio::time::sleep(Duration::from_millis(500)).await;
+    }));
+    run_market_maker(env, &mock_rpc).await;
+
+    // the preflight buy was simulated, the group aborted before any trade
+    assert!(is_buy_simulated(&mock_rpc));
+    assert!(mock_rpc.sent_txs().is_empty());
+    std::fs::remove_dir_all(work_dir).unwrap();
+}
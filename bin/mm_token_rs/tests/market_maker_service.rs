@@ -0,0 +1,67 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{mm_rpc, mm_service_env, mm_work_dir, run_market_maker, MM_MNEMONIC};
+use ethers::{signers::Signer, types::Address, utils::parse_ether};
+use mm_token_rs::utils::shutdown_on;
+use mm_token_utils::{constants::UNISWAP2_ROUTERS, utils::load_mnemonic_wallet};
+use provider_utils::enums::ENetwork;
+use serde_json::json;
+
+#[tokio::test]
+async fn shutdown_mid_loop_finishes_current_wallet() {
+    let token_address = Address::random();
+    let router = UNISWAP2_ROUTERS[&ENetwork::BaseMainnet];
+    // every wallet holds 1 ETH and 1000 tokens approved to the router
+    let mock_rpc = mm_rpc(token_address, parse_ether(1_000).unwrap());
+
+    // mm_state.json is written in the temp dir
+    let work_dir = mm_work_dir(
+        "mm_shutdown",
+        json!({
+            "defaultSettings": {
+                "maxWalletsCount": 3,
+                "minBuyVolume": 0.01,
+                "maxBuyVolume": 0.01,
+                "minDelayTime": 0,
+                "maxDelayTime": 0,
+                "minRetainToken": 0,
+                "maxRetainToken": 0
+            },
+            "groups": [{ "mnemonic": MM_MNEMONIC }]
+        }),
+    );
+    let env = mm_service_env(token_address, &work_dir, &[]);
+
+    // the shutdown arrives once the buy of the first wallet is sent
+    let signal_rpc = mock_rpc.clone();
+    tokio::spawn(shutdown_on(env.exit.clone(), async move {
+        while signal_rpc.sent_txs().is_empty() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }));
+    run_market_maker(env, &mock_rpc).await;
+
+    let wallet = |index| {
+        load_mnemonic_wallet(MM_MNEMONIC, index, ENetwork::BaseMainnet, None)
+            .unwrap()
+            .address()
+    };
+    let sent_txs: Vec<(Address, Option<Address>)> = mock_rpc
+        .sent_txs()
+        .iter()
+        .map(|tx| (tx.from, tx.to))
+        .collect();
+    // buy, sell and migration of the first wallet, then the refund of the second one
+    assert_eq!(
+        sent_txs,
+        vec![
+            (wallet(0), Some(router)),
+            (wallet(0), Some(router)),
+            (wallet(0), Some(wallet(1))),
+            (wallet(1), Some(wallet(0))),
+        ]
+    );
+    std::fs::remove_dir_all(work_dir).unwrap();
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::{service_env, MockRpc, MockRpcExt};
+use ethers::{
+    abi::Token,
+    signers::Signer,
+    types::{Address, U256},
+    utils::parse_ether,
+};
+use mm_token_rs::core::{WalletRole, WalletService};
+use mm_token_utils::{
+    constants::{DISPERSE_ROUTERS, UNISWAP2_ROUTERS},
+    utils::{load_mnemonic_wallet, load_private_key_wallet},
+};
+use provider_utils::enums::ENetwork;
+use tokio::sync::RwLock;
+
+const SELLER_MNEMONIC: &str = "test test test test test test test test test test test junk";
+const DISPERSE_ETH_PRIVATE_KEY: &str =
+    "59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d";
+
+fn seller_wallet(index: u32) -> Address {
+    load_mnemonic_wallet(SELLER_MNEMONIC, index, ENetwork::EthSepolia, None)
+        .unwrap()
+        .address()
+}
+
+/// both seller wallets hold 1000 tokens and `eth_balance`, the first one allows `allowance` to the router
+fn repair_rpc(token_address: Address, eth_balance: U256, allowance: U256) -> MockRpc {
+    MockRpc::new()
+        .with_eth_balance(eth_balance)
+        .on_any_call(
+            token_address,
+            "balanceOf(address)",
+            &[Token::Uint(U256::from(1_000))],
+        )
+        .on_any_call(
+            token_address,
+            "allowance(address,address)",
+            &[Token::Uint(U256::MAX)],
+        )
+        .on_call(
+            token_address,
+            "allowance(address,address)",
+            &[
+                Token::Address(seller_wallet(0)),
+                Token::Address(UNISWAP2_ROUTERS[&ENetwork::EthSepolia]),
+            ],
+            &[Token::Uint(allowance)],
+        )
+}
+
+// own test binary, the service reads the seller and repair envs
+#[tokio::test]
+async fn repair_tops_up_and_approves_the_flagged_wallets_once() {
+    for (key, value) in [
+        ("SELLER_MNEMONIC", SELLER_MNEMONIC),
+        ("SELLER_WALLETS_COUNT", "2"),
+        ("DISPERSE_ETH_PRIVATE_KEY", DISPERSE_ETH_PRIVATE_KEY),
+        ("REPAIR_MIN_ETH_BALANCE", "0.005"),
+        ("REPAIR_TOP_UP_ETH_BALANCE", "0.01"),
+    ] {
+        std::env::set_var(key, value);
+    }
+    let token_address = Address::random();
+    let mut env = service_env(token_address);
+    // the disperse router is not deployed on base mainnet
+    env.listen_network = ENetwork::EthSepolia;
+    env.token.router = Some("UNISWAP2_ROUTERS".to_string());
+    let gas_price = Arc::new(RwLock::new(U256::zero()));
+
+    // both wallets are low on eth, only the first one is under approved
+    let mock_rpc = repair_rpc(token_address, parse_ether("0.002").unwrap(), U256::zero());
+    // the disperse estimation, then the approval one
+    mock_rpc.push_responses("eth_estimateGas", [U256::from(100_000), U256::from(50_000)]);
+    let wallet_service = WalletService::new(env.clone(), mock_rpc.clone().launch().await);
+    wallet_service
+        .repair_wallets(WalletRole::Seller, gas_price.clone())
+        .await
+        .unwrap();
+
+    let sent_txs = mock_rpc.sent_txs();
+    assert_eq!(sent_txs.len(), 2);
+    let disperse_wallet = load_private_key_wallet(DISPERSE_ETH_PRIVATE_KEY).unwrap();
+    assert_eq!(sent_txs[0].from, disperse_wallet.address());
+    assert_eq!(
+        sent_txs[0].to,
+        Some(DISPERSE_ROUTERS[&ENetwork::EthSepolia])
+    );
+    // each wallet is raised to the top up balance
+    assert_eq!(sent_txs[0].value, parse_ether("0.016").unwrap());
+    assert_eq!(sent_txs[1].from, seller_wallet(0));
+    assert_eq!(sent_txs[1].to, Some(token_address));
+
+    // consistent wallets are left as they are
+    let mock_rpc = repair_rpc(token_address, parse_ether("0.01").unwrap(), U256::MAX);
+    let wallet_service = WalletService::new(env, mock_rpc.clone().launch().await);
+    wallet_service
+        .repair_wallets(WalletRole::Seller, gas_price)
+        .await
+        .unwrap();
+    assert!(mock_rpc.sent_txs().is_empty());
+}
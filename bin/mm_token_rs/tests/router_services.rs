@@ -0,0 +1,762 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::{
+    address_above, address_below, service_env, MockRpc, MockRpcExt, V2PairFixture, V3PoolFixture,
+};
+use ethers::{
+    abi::{encode, Token},
+    middleware::SignerMiddleware,
+    signers::Signer,
+    types::{Address, Transaction, U256},
+    utils::{id, parse_ether, parse_units, rlp::Rlp},
+};
+use mm_token_rs::{
+    routers::{RouterService, Uniswap2Service, Uniswap3Service},
+    utils::{fetch_token_info, GasCeiling},
+};
+use mm_token_utils::{
+    constants::{
+        ERouter, PERMIT2_ADDRESS, UNISWAP2_ROUTERS, UNISWAP3_QUOTER_V2, UNISWAP3_ROUTERS,
+        UNIVERSAL_ADDRESS_THIS, UNIVERSAL_ROUTERS, WRAPPED_NATIVE_TOKENS,
+    },
+    utils::{encode_v2_swap_exact_in, load_mnemonic_wallet},
+};
+use provider_utils::enums::ENetwork;
+use tokio::sync::RwLock;
+
+fn weth_address() -> Address {
+    WRAPPED_NATIVE_TOKENS[&ENetwork::BaseMainnet].address
+}
+
+/// pairs missing from `mock_rpc` are not created in `factory`
+async fn uniswap2_service(
+    token_address: Address,
+    factory: Address,
+    mock_rpc: MockRpc,
+) -> Uniswap2Service {
+    let http_provider = mock_rpc
+        .with_router_factory(UNISWAP2_ROUTERS[&ENetwork::BaseMainnet], factory)
+        .on_any_call(
+            factory,
+            "getPair(address,address)",
+            &[Token::Address(Address::zero())],
+        )
+        .launch()
+        .await;
+    Uniswap2Service::new(
+        service_env(token_address),
+        Arc::new(RwLock::new(U256::zero())),
+        http_provider,
+    )
+}
+
+#[tokio::test]
+async fn v2_price_when_the_token_is_token0() {
+    let token_address = address_below(weth_address());
+    let pair = V2PairFixture::new(
+        token_address,
+        weth_address(),
+        parse_ether(1_000).unwrap().as_u128(),
+        parse_ether(2).unwrap().as_u128(),
+    );
+    let factory = Address::random();
+    let uniswap2_service = uniswap2_service(
+        token_address,
+        factory,
+        MockRpc::new().with_v2_pair(factory, &pair),
+    )
+    .await;
+
+    let (price, token_reserve, weth_reserve) =
+        uniswap2_service.get_token_native_price().await.unwrap();
+    assert_eq!(price, 0.002);
+    assert_eq!(token_reserve, pair.reserve0);
+    assert_eq!(weth_reserve, pair.reserve1);
+}
+
+#[tokio::test]
+async fn v2_price_when_weth_is_token0() {
+    let token_address = address_above(weth_address());
+    let pair = V2PairFixture::new(
+        token_address,
+        weth_address(),
+        parse_ether(1_000).unwrap().as_u128(),
+        parse_ether(2).unwrap().as_u128(),
+    );
+    let factory = Address::random();
+    let uniswap2_service = uniswap2_service(
+        token_address,
+        factory,
+        MockRpc::new().with_v2_pair(factory, &pair),
+    )
+    .await;
+
+    let (price, token_reserve, weth_reserve) =
+        uniswap2_service.get_token_native_price().await.unwrap();
+    assert_eq!(price, 0.002);
+    assert_eq!(token_reserve, pair.reserve1);
+    assert_eq!(weth_reserve, pair.reserve0);
+
+    let (pair_address, is_token0) = uniswap2_service
+        .compute_pair_address(&weth_address(), &token_address)
+        .await
+        .unwrap();
+    assert_eq!(pair_address, pair.address);
+    assert!(is_token0);
+}
+
+#[tokio::test]
+async fn v2_pair_not_created() {
+    let uniswap2_service =
+        uniswap2_service(Address::random(), Address::random(), MockRpc::new()).await;
+
+    assert!(uniswap2_service.get_token_native_price().await.is_err());
+}
+
+async fn uniswap3_service(token_address: Address, mock_rpc: MockRpc) -> Uniswap3Service {
+    Uniswap3Service::new(
+        service_env(token_address),
+        Arc::new(RwLock::new(U256::zero())),
+        mock_rpc.launch().await,
+    )
+}
+
+#[tokio::test]
+async fn v3_price_from_sqrt_price() {
+    let quoter = UNISWAP3_QUOTER_V2[&ENetwork::BaseMainnet];
+    let factory = Address::random();
+
+    // token is token0, one token is worth 2^2 WETH
+    let token_address = address_below(weth_address());
+    let pool = V3PoolFixture::new(token_address, weth_address(), 3000, 2.0);
+    let service = uniswap3_service(
+        token_address,
+        MockRpc::new().with_v3_pool(factory, quoter, &pool, U256::one()),
+    )
+    .await;
+    assert_eq!(
+        service.get_token_native_price(pool.address).await.unwrap(),
+        4.0
+    );
+
+    // WETH is token0, one WETH is worth 2^2 token
+    let token_address = address_above(weth_address());
+    let pool = V3PoolFixture::new(token_address, weth_address(), 3000, 2.0);
+    let service = uniswap3_service(
+        token_address,
+        MockRpc::new().with_v3_pool(factory, quoter, &pool, U256::one()),
+    )
+    .await;
+    assert_eq!(
+        service.get_token_native_price(pool.address).await.unwrap(),
+        0.25
+    );
+}
+
+#[tokio::test]
+async fn v3_pair_is_the_first_existing_fee_tier() {
+    let quoter = UNISWAP3_QUOTER_V2[&ENetwork::BaseMainnet];
+    let factory = Address::random();
+    let token_address = address_above(weth_address());
+    // no 500 pool, the 3000 pool comes before the 10000 one
+    let pool_3000 = V3PoolFixture::new(token_address, weth_address(), 3000, 0.5);
+    let pool_10000 = V3PoolFixture::new(token_address, weth_address(), 10000, 0.5);
+    let uniswap3_service = uniswap3_service(
+        token_address,
+        MockRpc::new()
+            .with_router_factory(UNISWAP3_ROUTERS[&ENetwork::BaseMainnet], factory)
+            .on_any_call(
+                factory,
+                "getPool(address,address,uint24)",
+                &[Token::Address(Address::zero())],
+            )
+            .with_v3_pool(factory, quoter, &pool_3000, parse_ether(1).unwrap())
+            .with_v3_pool(factory, quoter, &pool_10000, parse_ether(1).unwrap()),
+    )
+    .await;
+
+    let (pool_address, is_weth_token0) = uniswap3_service
+        .compute_pair_address(&weth_address(), &token_address, true, None)
+        .await
+        .unwrap();
+    assert_eq!(pool_address, pool_3000.address);
+    assert!(is_weth_token0);
+
+    let (pool_address, is_token0) = uniswap3_service
+        .compute_pair_address(&token_address, &weth_address(), false, Some(10000))
+        .await
+        .unwrap();
+    assert_eq!(pool_address, pool_10000.address);
+    assert!(!is_token0);
+}
+
+#[tokio::test]
+async fn token_info_of_the_erc20_fixture() {
+    let token_address = Address::random();
+    let total_supply = parse_ether(1_000_000_000).unwrap();
+    let http_provider = MockRpc::new()
+        .with_erc20(token_address, "MM", 9, total_supply)
+        .launch()
+        .await;
+
+    let token_info = fetch_token_info(token_address, http_provider)
+        .await
+        .unwrap();
+    assert_eq!(token_info.symbol, "MM");
+    assert_eq!(token_info.decimals, 9);
+    assert_eq!(token_info.total_supply, total_supply);
+}
+
+#[tokio::test]
+async fn trades_wait_for_the_gas_price_under_the_ceiling() {
+    let mut env = service_env(Address::random());
+    env.token.router = Some("UNISWAP2_ROUTERS".to_string());
+    let gas_price = Arc::new(RwLock::new(parse_units(30, "gwei").unwrap().into()));
+    let mut router_service =
+        RouterService::new(env, gas_price.clone(), MockRpc::new().launch().await);
+    router_service.gas_ceiling = GasCeiling::new(Some(parse_units(20, "gwei").unwrap().into()));
+
+    // above the ceiling the trade is skipped
+    assert!(!router_service.is_gas_acceptable().await);
+
+    // the fetched gas price falls back under it, trades proceed
+    *gas_price.write().await = parse_units(15, "gwei").unwrap().into();
+    assert!(router_service.is_gas_acceptable().await);
+}
+
+#[tokio::test]
+async fn v3_weth_buys_approve_the_router_once() {
+    let router = UNISWAP3_ROUTERS[&ENetwork::BaseMainnet];
+    let wallet = load_mnemonic_wallet(
+        "test test test test test test test test test test test junk",
+        0,
+        ENetwork::BaseMainnet,
+        None,
+    )
+    .unwrap();
+    std::env::set_var("V3_BUY_WITH_NATIVE", "false");
+
+    // no allowance yet: WETH is approved to the router
+    let mock_rpc = MockRpc::new().on_call(
+        weth_address(),
+        "allowance(address,address)",
+        &[Token::Address(wallet.address()), Token::Address(router)],
+        &[Token::Uint(U256::zero())],
+    );
+    let v3_service = uniswap3_service(Address::random(), mock_rpc.clone()).await;
+    assert!(v3_service
+        .approve_weth_if_needed(&wallet, None, parse_ether(1).unwrap())
+        .await
+        .unwrap());
+    let sent_txs = mock_rpc.sent_txs();
+    assert_eq!(sent_txs.len(), 1);
+    assert_eq!(sent_txs[0].to, Some(weth_address()));
+    assert_eq!(
+        sent_txs[0].input.to_vec(),
+        [
+            id("approve(address,uint256)").to_vec(),
+            encode(&[Token::Address(router), Token::Uint(U256::MAX)]),
+        ]
+        .concat()
+    );
+
+    // the allowance covers the buy: nothing is sent
+    let mock_rpc = MockRpc::new().on_call(
+        weth_address(),
+        "allowance(address,address)",
+        &[Token::Address(wallet.address()), Token::Address(router)],
+        &[Token::Uint(U256::MAX)],
+    );
+    let v3_service = uniswap3_service(Address::random(), mock_rpc.clone()).await;
+    assert!(!v3_service
+        .approve_weth_if_needed(&wallet, None, parse_ether(1).unwrap())
+        .await
+        .unwrap());
+    assert!(mock_rpc.sent_txs().is_empty());
+}
+
+#[tokio::test]
+async fn universal_sells_are_approved_through_permit2() {
+    let token_address = Address::random();
+    let mut env = service_env(token_address);
+    // the universal router is not deployed on base
+    env.listen_network = ENetwork::EthMainnet;
+    env.token.router = Some("UNIVERSAL_ROUTERS".to_string());
+    let universal_router = UNIVERSAL_ROUTERS[&ENetwork::EthMainnet];
+    let weth_address = WRAPPED_NATIVE_TOKENS[&ENetwork::EthMainnet].address;
+    let wallet = load_mnemonic_wallet(
+        "test test test test test test test test test test test junk",
+        0,
+        ENetwork::BaseMainnet,
+        None,
+    )
+    .unwrap();
+    let mock_rpc = MockRpc::new();
+    mock_rpc.push_responses("eth_estimateGas", [U256::from(60_000), U256::from(60_000)]);
+    let http_provider = mock_rpc.clone().launch().await;
+    let router_service = RouterService::new(
+        env,
+        Arc::new(RwLock::new(parse_units(1, "gwei").unwrap().into())),
+        http_provider.clone(),
+    );
+    assert_eq!(
+        router_service.get_spender_addresses().unwrap(),
+        vec![*PERMIT2_ADDRESS]
+    );
+
+    let sell_amount = parse_ether(1).unwrap();
+    let nonce = router_service
+        .approve_sell_if_under_approved(
+            U256::zero(),
+            sell_amount,
+            U256::zero(),
+            Arc::new(SignerMiddleware::new(http_provider, wallet.clone())),
+        )
+        .await
+        .unwrap();
+    assert_eq!(nonce, U256::from(2));
+    let sent_txs = mock_rpc.sent_txs();
+    assert_eq!(sent_txs.len(), 2);
+    // the token is approved to Permit2
+    assert_eq!(sent_txs[0].to, Some(token_address));
+    assert_eq!(
+        sent_txs[0].input.to_vec(),
+        [
+            id("approve(address,uint256)").to_vec(),
+            encode(&[Token::Address(*PERMIT2_ADDRESS), Token::Uint(U256::MAX)]),
+        ]
+        .concat()
+    );
+    // Permit2 lets the universal router pull it
+    assert_eq!(sent_txs[1].to, Some(*PERMIT2_ADDRESS));
+    assert_eq!(
+        sent_txs[1].input.to_vec(),
+        [
+            id("approve(address,address,uint160,uint48)").to_vec(),
+            encode(&[
+                Token::Address(token_address),
+                Token::Address(universal_router),
+                Token::Uint((U256::one() << 160) - 1),
+                Token::Uint(U256::from((1u64 << 48) - 1)),
+            ]),
+        ]
+        .concat()
+    );
+
+    // the sell goes to the universal router, which pulls the token from the seller
+    let signed_sell_tx = router_service
+        .construct_sell_token_tx(
+            &wallet,
+            Some(nonce),
+            sell_amount,
+            &Address::random(),
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+    let sell_tx: Transaction = Rlp::new(&signed_sell_tx).as_val().unwrap();
+    assert_eq!(sell_tx.to, Some(universal_router));
+    let swap_input = encode_v2_swap_exact_in(
+        *UNIVERSAL_ADDRESS_THIS,
+        sell_amount,
+        U256::zero(),
+        vec![token_address, weth_address],
+        true,
+    );
+    assert!(sell_tx
+        .input
+        .windows(swap_input.len())
+        .any(|window| window == swap_input));
+}
+
+/// ACTIVE_ROUTER=AUTO over the v2 `pair` and the v3 `pool` of the token,
+/// the v2 router and the v3 quoter answer `v2_quote` and `v3_quote`, returned with its rpc
+async fn auto_router_service(
+    token_address: Address,
+    pair: &V2PairFixture,
+    pool: &V3PoolFixture,
+    v2_quote: U256,
+    v3_quote: U256,
+) -> (RouterService, MockRpc) {
+    let v2_router = UNISWAP2_ROUTERS[&ENetwork::BaseMainnet];
+    let (v2_factory, v3_factory) = (Address::random(), Address::random());
+    let mock_rpc = MockRpc::new()
+        .with_router_factory(v2_router, v2_factory)
+        .with_v2_pair(v2_factory, pair)
+        .on_any_call(
+            v2_router,
+            "getAmountOut(uint256,uint256,uint256)",
+            &[Token::Uint(v2_quote)],
+        )
+        .with_router_factory(UNISWAP3_ROUTERS[&ENetwork::BaseMainnet], v3_factory)
+        .on_any_call(
+            v3_factory,
+            "getPool(address,address,uint24)",
+            &[Token::Address(Address::zero())],
+        )
+        .with_v3_pool(
+            v3_factory,
+            UNISWAP3_QUOTER_V2[&ENetwork::BaseMainnet],
+            pool,
+            v3_quote,
+        );
+    let http_provider = mock_rpc.clone().launch().await;
+    let mut env = service_env(token_address);
+    env.token.router = Some("AUTO".to_string());
+    (
+        RouterService::new(env, Arc::new(RwLock::new(U256::zero())), http_provider),
+        mock_rpc,
+    )
+}
+
+#[tokio::test]
+async fn auto_route_takes_the_venue_with_the_better_quote() {
+    let token_address = address_above(weth_address());
+    let pair = V2PairFixture::new(
+        token_address,
+        weth_address(),
+        parse_ether(1_000).unwrap().as_u128(),
+        parse_ether(2).unwrap().as_u128(),
+    );
+    let pool = V3PoolFixture::new(token_address, weth_address(), 3000, 1.0);
+    let amount_in = parse_ether("0.1").unwrap();
+
+    let (router_service, _) = auto_router_service(
+        token_address,
+        &pair,
+        &pool,
+        parse_ether(45).unwrap(),
+        parse_ether(50).unwrap(),
+    )
+    .await;
+    assert_eq!(
+        router_service
+            .best_route(&token_address, &weth_address(), amount_in, true)
+            .await
+            .unwrap(),
+        (ERouter::Uniswap3Routers, pool.address)
+    );
+    // a sell may take either router, both are approved
+    assert_eq!(
+        router_service.get_spender_addresses().unwrap(),
+        vec![
+            UNISWAP2_ROUTERS[&ENetwork::BaseMainnet],
+            UNISWAP3_ROUTERS[&ENetwork::BaseMainnet],
+        ]
+    );
+
+    let (router_service, _) = auto_router_service(
+        token_address,
+        &pair,
+        &pool,
+        parse_ether(45).unwrap(),
+        parse_ether(40).unwrap(),
+    )
+    .await;
+    assert_eq!(
+        router_service
+            .best_route(&token_address, &weth_address(), amount_in, true)
+            .await
+            .unwrap(),
+        (ERouter::Uniswap2Routers, pair.address)
+    );
+}
+
+#[tokio::test]
+async fn auto_route_is_cached_per_side_and_amount_bucket() {
+    let token_address = address_above(weth_address());
+    let pair = V2PairFixture::new(
+        token_address,
+        weth_address(),
+        parse_ether(1_000).unwrap().as_u128(),
+        parse_ether(2).unwrap().as_u128(),
+    );
+    let pool = V3PoolFixture::new(token_address, weth_address(), 3000, 1.0);
+    let (router_service, mock_rpc) = auto_router_service(
+        token_address,
+        &pair,
+        &pool,
+        parse_ether(45).unwrap(),
+        parse_ether(50).unwrap(),
+    )
+    .await;
+    let weth_address = weth_address();
+    let best_route = |amount_in: &str, is_buy| {
+        router_service.best_route(
+            &token_address,
+            &weth_address,
+            parse_ether(amount_in).unwrap(),
+            is_buy,
+        )
+    };
+
+    let route = best_route("0.1", true).await.unwrap();
+    assert_eq!(route, (ERouter::Uniswap3Routers, pool.address));
+    let quote_calls = mock_rpc.requests("eth_call").len();
+    // 0.105 shares the two leading digits of 0.1, the venues are not quoted again
+    assert_eq!(best_route("0.105", true).await.unwrap(), route);
+    assert_eq!(mock_rpc.requests("eth_call").len(), quote_calls);
+
+    // another bucket or side is quoted on its own
+    best_route("0.11", true).await.unwrap();
+    let quote_calls_of_other_bucket = mock_rpc.requests("eth_call").len();
+    assert!(quote_calls_of_other_bucket > quote_calls);
+    best_route("0.1", false).await.unwrap();
+    assert!(mock_rpc.requests("eth_call").len() > quote_calls_of_other_bucket);
+}
+
+#[tokio::test]
+async fn v2_quote_is_priced_on_the_pair_reserves() {
+    let token_address = address_above(weth_address());
+    let (token_reserve, weth_reserve) =
+        (parse_ether(1_000_000).unwrap(), parse_ether(100).unwrap());
+    let pair = V2PairFixture::new(
+        token_address,
+        weth_address(),
+        token_reserve.as_u128(),
+        weth_reserve.as_u128(),
+    );
+    let v2_router = UNISWAP2_ROUTERS[&ENetwork::BaseMainnet];
+    let factory = Address::random();
+    let (buy_amount, sell_amount) = (parse_ether(1).unwrap(), parse_ether(10_000).unwrap());
+    // UniswapV2Library.getAmountOut, the router only answers the reserves in trade order
+    let v2_amount_out = |amount_in: U256, reserve_in: U256, reserve_out: U256| {
+        amount_in * 997 * reserve_out / (reserve_in * 1000 + amount_in * 997)
+    };
+    let bought = v2_amount_out(buy_amount, weth_reserve, token_reserve);
+    let sold = v2_amount_out(sell_amount, token_reserve, weth_reserve);
+    let http_provider = MockRpc::new()
+        .with_router_factory(v2_router, factory)
+        .with_v2_pair(factory, &pair)
+        .on_call(
+            v2_router,
+            "getAmountOut(uint256,uint256,uint256)",
+            &[
+                Token::Uint(buy_amount),
+                Token::Uint(weth_reserve),
+                Token::Uint(token_reserve),
+            ],
+            &[Token::Uint(bought)],
+        )
+        .on_call(
+            v2_router,
+            "getAmountOut(uint256,uint256,uint256)",
+            &[
+                Token::Uint(sell_amount),
+                Token::Uint(token_reserve),
+                Token::Uint(weth_reserve),
+            ],
+            &[Token::Uint(sold)],
+        )
+        .launch()
+        .await;
+    let mut env = service_env(token_address);
+    env.token.router = Some("UNISWAP2_ROUTERS".to_string());
+    let router_service =
+        RouterService::new(env, Arc::new(RwLock::new(U256::zero())), http_provider);
+
+    // 1% of the pool plus the 0.3% fee, the execution price is ~130 bps worse than the mid price
+    assert_eq!(
+        router_service.quote(buy_amount, true).await.unwrap(),
+        (bought, 130, pair.address, ERouter::Uniswap2Routers)
+    );
+    assert_eq!(
+        router_service.quote(sell_amount, false).await.unwrap(),
+        (sold, -128, pair.address, ERouter::Uniswap2Routers)
+    );
+}
+
+#[tokio::test]
+async fn tuned_slippage_sets_the_buy_min_out() {
+    let token_address = address_above(weth_address());
+    let pair = V2PairFixture::new(
+        token_address,
+        weth_address(),
+        parse_ether(1_000_000).unwrap().as_u128(),
+        parse_ether(100).unwrap().as_u128(),
+    );
+    let v2_router = UNISWAP2_ROUTERS[&ENetwork::BaseMainnet];
+    let factory = Address::random();
+    let http_provider = MockRpc::new()
+        .with_router_factory(v2_router, factory)
+        .with_v2_pair(factory, &pair)
+        .on_any_call(
+            v2_router,
+            "getAmountOut(uint256,uint256,uint256)",
+            &[Token::Uint(U256::from(10_000))],
+        )
+        .launch()
+        .await;
+    let mut env = service_env(token_address);
+    env.token.router = Some("UNISWAP2_ROUTERS".to_string());
+    let mut router_service =
+        RouterService::new(env, Arc::new(RwLock::new(U256::zero())), http_provider);
+    let wallet = load_mnemonic_wallet(
+        "test test test test test test test test test test test junk",
+        0,
+        ENetwork::BaseMainnet,
+        None,
+    )
+    .unwrap();
+    let buy_min_out = |signed_buy_tx: &[u8]| {
+        let buy_tx: Transaction = Rlp::new(signed_buy_tx).as_val().unwrap();
+        // amountOutMin is the first argument of the swap
+        U256::from_big_endian(&buy_tx.input[4..36])
+    };
+
+    // TRADING_SLIPPAGE=1
+    let signed_buy_tx = router_service
+        .construct_buy_token_tx(
+            &wallet,
+            Some(U256::zero()),
+            parse_ether(1).unwrap(),
+            &pair.address,
+            true,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(buy_min_out(&signed_buy_tx), U256::from(9_900));
+
+    // 250 bps, tuned at runtime
+    router_service.set_slippage_bps(250).unwrap();
+    let signed_buy_tx = router_service
+        .construct_buy_token_tx(
+            &wallet,
+            Some(U256::zero()),
+            parse_ether(1).unwrap(),
+            &pair.address,
+            true,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(buy_min_out(&signed_buy_tx), U256::from(9_750));
+
+    // beyond 100% is rejected and the tuned slippage is kept
+    assert!(router_service.set_slippage_bps(10_001).is_err());
+    let signed_buy_tx = router_service
+        .construct_buy_token_tx(
+            &wallet,
+            Some(U256::zero()),
+            parse_ether(1).unwrap(),
+            &pair.address,
+            true,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(buy_min_out(&signed_buy_tx), U256::from(9_750));
+}
+
+#[tokio::test]
+async fn v2_reserve_math_matches_get_amounts_out_on_a_two_token_path() {
+    let token_address = address_above(weth_address());
+    let (token_reserve, weth_reserve) =
+        (parse_ether(1_000_000).unwrap(), parse_ether(100).unwrap());
+    let pair = V2PairFixture::new(
+        token_address,
+        weth_address(),
+        token_reserve.as_u128(),
+        weth_reserve.as_u128(),
+    );
+    let v2_router = UNISWAP2_ROUTERS[&ENetwork::BaseMainnet];
+    let factory = Address::random();
+    let buy_amount = parse_ether(1).unwrap();
+    // UniswapV2Library.getAmountOut with the 0.3% pair fee
+    let bought = buy_amount * 997 * token_reserve / (weth_reserve * 1000 + buy_amount * 997);
+    // a path through another token is quoted by the router only
+    let other_token = Address::random();
+    let routed = bought / 2;
+    let http_provider = MockRpc::new()
+        .with_router_factory(v2_router, factory)
+        .with_v2_pair(factory, &pair)
+        .on_call(
+            v2_router,
+            "getAmountOut(uint256,uint256,uint256)",
+            &[
+                Token::Uint(buy_amount),
+                Token::Uint(weth_reserve),
+                Token::Uint(token_reserve),
+            ],
+            &[Token::Uint(bought)],
+        )
+        .on_call(
+            v2_router,
+            "getAmountsOut(uint256,address[])",
+            &[
+                Token::Uint(buy_amount),
+                Token::Array(vec![
+                    Token::Address(weth_address()),
+                    Token::Address(token_address),
+                ]),
+            ],
+            &[Token::Array(vec![
+                Token::Uint(buy_amount),
+                Token::Uint(bought),
+            ])],
+        )
+        .on_call(
+            v2_router,
+            "getAmountsOut(uint256,address[])",
+            &[
+                Token::Uint(buy_amount),
+                Token::Array(vec![
+                    Token::Address(weth_address()),
+                    Token::Address(other_token),
+                ]),
+            ],
+            &[Token::Array(vec![
+                Token::Uint(buy_amount),
+                Token::Uint(routed),
+            ])],
+        )
+        .launch()
+        .await;
+    let mut env = service_env(token_address);
+    env.token.router = Some("UNISWAP2_ROUTERS".to_string());
+    let uniswap2_service = Uniswap2Service::new(
+        env.clone(),
+        Arc::new(RwLock::new(U256::zero())),
+        http_provider.clone(),
+    );
+    let router_service =
+        RouterService::new(env, Arc::new(RwLock::new(U256::zero())), http_provider);
+    let get_amount_out = |path: Option<Vec<Address>>| {
+        let router_service = router_service.clone();
+        async move {
+            router_service
+                .get_amount_out(
+                    ERouter::Uniswap2Routers,
+                    &pair.address,
+                    true,
+                    None,
+                    None,
+                    path.as_deref(),
+                    buy_amount,
+                    0,
+                )
+                .await
+                .unwrap()
+        }
+    };
+
+    // the weth -> token hop keeps the reserve fast path, the router agrees with it
+    let by_reserves = get_amount_out(Some(vec![weth_address(), token_address])).await;
+    assert_eq!(by_reserves, bought);
+    assert_eq!(get_amount_out(None).await, by_reserves);
+    let by_router = uniswap2_service
+        .get_amounts_out(vec![weth_address(), token_address], buy_amount)
+        .await
+        .unwrap();
+    assert_eq!(by_router, by_reserves);
+
+    // any other path is quoted with getAmountsOut
+    assert_eq!(
+        get_amount_out(Some(vec![weth_address(), other_token])).await,
+        routed
+    );
+}
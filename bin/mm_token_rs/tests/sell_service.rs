@@ -0,0 +1,127 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::{provider_pool, service_env, MockRpc, MockRpcExt};
+use ethers::{
+    abi::{encode, Token},
+    signers::Signer,
+    types::{Address, H256, U256},
+    utils::{id, parse_ether, parse_units},
+};
+use mm_token_rs::{core::SellService, utils::WalletContext};
+use mm_token_utils::{constants::UNISWAP2_ROUTERS, utils::load_mnemonic_wallet};
+use provider_utils::enums::ENetwork;
+use tokio::sync::RwLock;
+
+const SELLER_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+async fn sell_service(token_address: Address, mock_rpc: MockRpc) -> SellService {
+    let mut env = service_env(token_address);
+    env.token.router = Some("UNISWAP2_ROUTERS".to_string());
+    let http_provider = mock_rpc.launch().await;
+    SellService::new(
+        env,
+        Arc::new(RwLock::new(parse_units(1, "gwei").unwrap().into())),
+        provider_pool(&http_provider),
+        http_provider,
+    )
+}
+
+// own test binary, the service reads AUTO_APPROVE_ON_SELL when it is created
+#[tokio::test]
+async fn sellers_approve_the_router_once_or_are_skipped() {
+    for (key, value) in [
+        ("SELLER_MNEMONIC", SELLER_MNEMONIC),
+        ("SELLER_WALLETS_COUNT", "1"),
+        ("BUYER_MNEMONIC", SELLER_MNEMONIC),
+        ("BUYER_WALLETS_COUNT", "1"),
+        ("AUTO_BUYER_MNEMONIC", SELLER_MNEMONIC),
+        ("AUTO_BUYER_WALLETS_COUNT", "1"),
+        ("AUTO_SELL_VOLUME_THRESHOLD", "0"),
+        ("AUTO_SELL_MIN_PERCENT", "100"),
+        ("AUTO_SELL_MAX_PERCENT", "100"),
+        ("SELL_TX_TIMEOUT_SECS", "1"),
+    ] {
+        std::env::set_var(key, value);
+    }
+    let token_address = Address::random();
+    let router = UNISWAP2_ROUTERS[&ENetwork::BaseMainnet];
+    let wallet = load_mnemonic_wallet(SELLER_MNEMONIC, 0, ENetwork::BaseMainnet, None).unwrap();
+    let sell_amount = parse_ether(1_000).unwrap();
+    let new_wallet_context = || WalletContext {
+        address: wallet.address(),
+        ..Default::default()
+    };
+    let unapproved_rpc = || {
+        let mock_rpc = MockRpc::new().on_any_call(
+            token_address,
+            "allowance(address,address)",
+            &[Token::Uint(U256::zero())],
+        );
+        mock_rpc.push_responses("eth_estimateGas", [U256::from(60_000)]);
+        mock_rpc
+    };
+
+    // AUTO_APPROVE_ON_SELL=true: the router is approved, the sell goes after it
+    std::env::set_var("AUTO_APPROVE_ON_SELL", "true");
+    let mock_rpc = unapproved_rpc();
+    let sell_service = sell_service(token_address, mock_rpc.clone()).await;
+    let mut wallet_context = new_wallet_context();
+    assert!(sell_service
+        .approve_router_if_needed(&mut wallet_context, &wallet, sell_amount, H256::random())
+        .await
+        .unwrap());
+    let sent_txs = mock_rpc.sent_txs();
+    assert_eq!(sent_txs.len(), 1);
+    assert_eq!(sent_txs[0].to, Some(token_address));
+    assert_eq!(
+        sent_txs[0].input.to_vec(),
+        [
+            id("approve(address,uint256)").to_vec(),
+            encode(&[Token::Address(router), Token::Uint(U256::MAX)]),
+        ]
+        .concat()
+    );
+    assert_eq!(wallet_context.nonce, U256::one());
+
+    // the next sells of the wallet neither read its allowance again nor approve
+    let allowance_calls = mock_rpc.requests("eth_call").len();
+    assert!(sell_service
+        .approve_router_if_needed(&mut wallet_context, &wallet, sell_amount, H256::random())
+        .await
+        .unwrap());
+    assert_eq!(mock_rpc.requests("eth_call").len(), allowance_calls);
+    assert_eq!(mock_rpc.sent_txs().len(), 1);
+
+    // an approve not mined within SELL_TX_TIMEOUT_SECS skips the sell, the next nonce goes after it
+    let mock_rpc = unapproved_rpc().with_pending_txs(1);
+    let sell_service_of_pending = self::sell_service(token_address, mock_rpc.clone()).await;
+    let mut wallet_context = new_wallet_context();
+    assert!(!tokio::time::timeout(
+        std::time::Duration::from_secs(10),
+        sell_service_of_pending.approve_router_if_needed(
+            &mut wallet_context,
+            &wallet,
+            sell_amount,
+            H256::random()
+        ),
+    )
+    .await
+    .unwrap()
+    .unwrap());
+    assert_eq!(mock_rpc.sent_txs().len(), 1);
+    assert_eq!(wallet_context.nonce, U256::one());
+
+    // AUTO_APPROVE_ON_SELL=false: the sell is skipped, nothing is sent
+    std::env::set_var("AUTO_APPROVE_ON_SELL", "false");
+    let mock_rpc = unapproved_rpc();
+    let sell_service = self::sell_service(token_address, mock_rpc.clone()).await;
+    let mut wallet_context = new_wallet_context();
+    assert!(!sell_service
+        .approve_router_if_needed(&mut wallet_context, &wallet, sell_amount, H256::random())
+        .await
+        .unwrap());
+    assert!(mock_rpc.sent_txs().is_empty());
+    assert_eq!(wallet_context.nonce, U256::zero());
+}
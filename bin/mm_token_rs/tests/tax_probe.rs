@@ -0,0 +1,86 @@
+mod common;
+
+use common::{service_env, MockRpc, MockRpcExt};
+use ethers::{
+    abi::{encode, Token},
+    types::{Address, U256},
+    utils::parse_ether,
+};
+use mm_token_rs::utils::TaxProbe;
+
+/// `aggregate3Value` output of successful calls returning `outputs`
+fn aggregate_output(outputs: Vec<Vec<Token>>) -> Vec<Token> {
+    vec![Token::Array(
+        outputs
+            .into_iter()
+            .map(|output| Token::Tuple(vec![Token::Bool(true), Token::Bytes(encode(&output))]))
+            .collect(),
+    )]
+}
+
+/// the buy then the sell multicall of the probe, the receivers get `received_percent` of the router quotes
+fn probe_rpc(received_percent: u64) -> MockRpc {
+    let (token_quote, weth_quote) = (parse_ether(1_000).unwrap(), parse_ether("0.00009").unwrap());
+    let received = |quote: U256| quote * received_percent / 100;
+    let amount = |amount: U256| vec![Token::Uint(amount)];
+    let amounts_out = |amount_in: U256, amount_out: U256| {
+        vec![Token::Array(vec![
+            Token::Uint(amount_in),
+            Token::Uint(amount_out),
+        ])]
+    };
+
+    let mock_rpc = MockRpc::new();
+    mock_rpc.push_call_outputs([
+        // token balance, getAmountsOut, swap, token balance
+        aggregate_output(vec![
+            amount(U256::zero()),
+            amounts_out(parse_ether("0.0001").unwrap(), token_quote),
+            vec![],
+            amount(received(token_quote)),
+        ]),
+        // swap, approve, weth balance, getAmountsOut, swap, weth balance
+        aggregate_output(vec![
+            vec![],
+            vec![Token::Bool(true)],
+            amount(U256::zero()),
+            amounts_out(received(token_quote), weth_quote),
+            vec![],
+            amount(received(weth_quote)),
+        ]),
+    ]);
+    mock_rpc
+}
+
+/// the probed tax is resolved for both sides from one simulation, then cached
+async fn assert_probed_tax(received_percent: u64, expected_tax: f32) {
+    let token_address = Address::random();
+    let mock_rpc = probe_rpc(received_percent);
+    let tax_probe =
+        TaxProbe::from_env(&service_env(token_address), mock_rpc.clone().launch().await).unwrap();
+
+    assert_eq!(
+        tax_probe.resolve_tax(token_address, true, 3.0).await,
+        expected_tax
+    );
+    assert_eq!(mock_rpc.requests("eth_call").len(), 2);
+    // the second call hits the cache, not the node
+    assert_eq!(
+        tax_probe.resolve_tax(token_address, false, 5.0).await,
+        expected_tax
+    );
+    assert_eq!(mock_rpc.requests("eth_call").len(), 2);
+}
+
+// own test binary, the probe is enabled by AUTO_TAX_DETECT
+#[tokio::test]
+async fn untaxed_token_probes_zero_percent() {
+    std::env::set_var("AUTO_TAX_DETECT", "true");
+    assert_probed_tax(100, 0.0).await;
+}
+
+#[tokio::test]
+async fn taxed_token_probes_the_missing_share() {
+    std::env::set_var("AUTO_TAX_DETECT", "true");
+    assert_probed_tax(90, 10.0).await;
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::{address_above, address_below, service_env, MockRpc, MockRpcExt, V3PoolFixture};
+use ethers::{
+    abi::Token,
+    types::{Address, U256},
+    utils::parse_ether,
+};
+use mm_token_rs::routers::Uniswap3Service;
+use mm_token_utils::constants::{UNISWAP3_QUOTER_V2, UNISWAP3_ROUTERS, WRAPPED_NATIVE_TOKENS};
+use provider_utils::enums::ENetwork;
+use tokio::sync::RwLock;
+
+// own test binary, `V3_INTERMEDIARY_TOKENS` is read from the process env
+#[tokio::test]
+async fn multi_hop_price_goes_through_the_intermediary_weth_pool() {
+    let weth_address = WRAPPED_NATIVE_TOKENS[&ENetwork::BaseMainnet].address;
+    let quoter = UNISWAP3_QUOTER_V2[&ENetwork::BaseMainnet];
+    let factory = Address::random();
+    let intermediary = address_below(weth_address);
+    let token_address = address_above(intermediary);
+    std::env::set_var("V3_INTERMEDIARY_TOKENS", format!("{:?}", intermediary));
+
+    // one intermediary is worth 2^2 token, one intermediary is worth 0.25^2 WETH
+    let token_pool = V3PoolFixture::new(token_address, intermediary, 3000, 2.0);
+    let weth_pool = V3PoolFixture::new(intermediary, weth_address, 500, 0.25);
+    let http_provider = MockRpc::new()
+        .with_router_factory(UNISWAP3_ROUTERS[&ENetwork::BaseMainnet], factory)
+        .on_any_call(
+            factory,
+            "getPool(address,address,uint24)",
+            &[Token::Address(Address::zero())],
+        )
+        .with_v3_pool(factory, quoter, &token_pool, parse_ether(1).unwrap())
+        .with_v3_pool(factory, quoter, &weth_pool, parse_ether(1).unwrap())
+        .on_any_call(
+            quoter,
+            "quoteExactInput(bytes,uint256)",
+            &[
+                Token::Uint(parse_ether(1).unwrap()),
+                Token::Array(vec![]),
+                Token::Array(vec![]),
+                Token::Uint(U256::zero()),
+            ],
+        )
+        .launch()
+        .await;
+    let uniswap3_service = Uniswap3Service::new(
+        service_env(token_address),
+        Arc::new(RwLock::new(U256::zero())),
+        http_provider,
+    );
+
+    let (pool_address, _) = uniswap3_service
+        .compute_pair_address(&weth_address, &token_address, true, None)
+        .await
+        .unwrap();
+    assert_eq!(pool_address, token_pool.address);
+
+    // 0.25 intermediary per token, 0.0625 WETH per intermediary
+    assert_eq!(
+        uniswap3_service
+            .get_token_native_price(pool_address)
+            .await
+            .unwrap(),
+        0.015625
+    );
+}
@@ -0,0 +1,146 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::{mm_work_dir, service_env, MockRpc, MockRpcExt};
+use ethers::{
+    abi::Token,
+    signers::Signer,
+    types::{Address, U256},
+};
+use mm_token_rs::{
+    core::{WalletRole, WalletService},
+    routers::RouterService,
+};
+use mm_token_utils::{constants::UNISWAP2_ROUTERS, utils::load_mnemonic_wallet};
+use provider_utils::enums::ENetwork;
+use serde_json::json;
+use tokio::sync::RwLock;
+
+const BUYER_MNEMONIC: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+const SELLER_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+#[tokio::test]
+async fn dump_all_iterates_the_wallets_of_the_mnemonic() {
+    let token_address = Address::random();
+    let mut env = service_env(token_address);
+    env.token.router = Some("UNISWAP2_ROUTERS".to_string());
+
+    // only the seller wallets hold no token, a balance read of any other wallet reverts
+    let mut mock_rpc = MockRpc::new().on_any_call(
+        token_address,
+        "allowance(address,address)",
+        &[Token::Uint(U256::zero())],
+    );
+    for index in 0..2 {
+        let seller = load_mnemonic_wallet(SELLER_MNEMONIC, index, ENetwork::BaseMainnet, None)
+            .unwrap()
+            .address();
+        mock_rpc = mock_rpc.on_call(
+            token_address,
+            "balanceOf(address)",
+            &[Token::Address(seller)],
+            &[Token::Uint(U256::zero())],
+        );
+    }
+    let wallet_service = WalletService::new(env, mock_rpc.launch().await);
+    let gas_price = Arc::new(RwLock::new(U256::zero()));
+
+    wallet_service
+        .dump_all(SELLER_MNEMONIC, 2, gas_price.clone(), 0, 0)
+        .await
+        .unwrap();
+    assert!(wallet_service
+        .dump_all(BUYER_MNEMONIC, 2, gas_price, 0, 0)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn batch_approve_of_an_mm_group_goes_through_the_nonce_store() {
+    let token_address = Address::random();
+    let work_dir = mm_work_dir(
+        "batch_approve",
+        json!({
+            "defaultSettings": {
+                "maxWalletsCount": 2,
+                "minBuyVolume": 0.01,
+                "maxBuyVolume": 0.01,
+                "minDelayTime": 0,
+                "maxDelayTime": 0,
+                "minRetainToken": 0,
+                "maxRetainToken": 0
+            },
+            "groups": [{ "mnemonic": SELLER_MNEMONIC }]
+        }),
+    );
+    let mm_config_path = work_dir.join("mm_config.json");
+    let mm_wallet = |index| {
+        load_mnemonic_wallet(SELLER_MNEMONIC, index, ENetwork::BaseMainnet, None)
+            .unwrap()
+            .address()
+    };
+    // a nonce submitted before a restart and not mined yet
+    let nonce_store_path = work_dir.join("nonces.json");
+    std::fs::write(
+        &nonce_store_path,
+        json!({ format!("{:?}", mm_wallet(0)): "0x5" }).to_string(),
+    )
+    .unwrap();
+    std::env::set_var("NONCE_STORE_PATH", &nonce_store_path);
+
+    let mut env = service_env(token_address);
+    env.token.router = Some("UNISWAP2_ROUTERS".to_string());
+    env.token.mm_config_path = Some(mm_config_path.to_string_lossy().to_string());
+    // only the first wallet of the group is under approved
+    let mock_rpc = MockRpc::new()
+        .on_any_call(
+            token_address,
+            "balanceOf(address)",
+            &[Token::Uint(U256::from(1_000))],
+        )
+        .on_any_call(
+            token_address,
+            "allowance(address,address)",
+            &[Token::Uint(U256::MAX)],
+        )
+        .on_call(
+            token_address,
+            "allowance(address,address)",
+            &[
+                Token::Address(mm_wallet(0)),
+                Token::Address(UNISWAP2_ROUTERS[&ENetwork::BaseMainnet]),
+            ],
+            &[Token::Uint(U256::zero())],
+        );
+    mock_rpc.push_responses("eth_estimateGas", [U256::from(50_000)]);
+    let http_provider = mock_rpc.clone().launch().await;
+    let wallet_service = WalletService::new(env.clone(), http_provider.clone());
+    let router_service =
+        RouterService::new(env, Arc::new(RwLock::new(U256::zero())), http_provider);
+
+    let (mnemonic, wallets_count) = wallet_service
+        .get_role_wallets(WalletRole::MarketMaker(0))
+        .unwrap();
+    assert_eq!(wallets_count, 2);
+    let batch_approve = wallet_service
+        .batch_approve(&mnemonic, 0, wallets_count - 1, &router_service)
+        .await
+        .unwrap();
+
+    assert_eq!(batch_approve.skipped, vec![mm_wallet(1)]);
+    assert!(batch_approve.failed.is_empty());
+    let sent_txs = mock_rpc.sent_txs();
+    assert_eq!(sent_txs.len(), 1);
+    assert_eq!(sent_txs[0].from, mm_wallet(0));
+    // sent after the stored nonce that may still be pending, then recorded for the next restart
+    assert_eq!(sent_txs[0].nonce, U256::from(6));
+    let stored_nonces: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&nonce_store_path).unwrap()).unwrap();
+    assert_eq!(
+        stored_nonces,
+        json!({ format!("{:?}", mm_wallet(0)): "0x6" })
+    );
+    std::fs::remove_dir_all(work_dir).unwrap();
+}
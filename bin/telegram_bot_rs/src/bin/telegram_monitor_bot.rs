@@ -1,11 +1,17 @@
-use mm_token_utils::{env::get_env, log::setup_logger};
-use telegram_bot_rs::{core::CommandService, types::BotCommand};
+use mm_token_utils::{
+    env::{get_env, load_env},
+    log::setup_logger,
+};
+use telegram_bot_rs::{
+    core::{CommandService, DUMP_CONFIRM_WINDOW, PANIC_CONFIRM_WINDOW},
+    types::BotCommand,
+};
 use teloxide::prelude::*;
 use teloxide::utils::command::BotCommands;
 
 #[tokio::main]
 async fn main() {
-    dotenv::dotenv().ok();
+    load_env();
     let _ = setup_logger(None);
     let telegram_bot_token = get_env("TELEGRAM_BOT_TOKEN", None);
     log::info!("Starting monitor bot...");
@@ -25,9 +31,11 @@ async fn answer(bot: Bot, msg: Message, cmd: BotCommand) -> ResponseResult<()> {
         }
         // call APIs command
         BotCommand::GetNetworkStatus => {
-            let response = command_service.get_network_status().await;
-            bot.send_message(msg.chat.id, format!("Response: {:#?}.", response))
-                .await?
+            let response = match command_service.get_network_status().await {
+                Ok(network_status) => format!("Response: {:#?}.", network_status),
+                Err(err) => format!("Error: {}", err),
+            };
+            bot.send_message(msg.chat.id, response).await?
         }
         // BotCommand::GetDeploymentChecklist => {
         //     let response = command_service.get_deployment_checklist().await;
@@ -59,8 +67,91 @@ async fn answer(bot: Bot, msg: Message, cmd: BotCommand) -> ResponseResult<()> {
         }
         BotCommand::LaunchProcess => {
             let response = command_service.launch_process().await;
-            bot.send_message(msg.chat.id, format!("Response: {:#?}.", response))
+            let banner = if response.dry_run {
+                "⚠️ DRY RUN: trades were simulated, no tx was broadcast ⚠️\n"
+            } else {
+                ""
+            };
+            bot.send_message(msg.chat.id, format!("{}Response: {:#?}.", banner, response))
                 .await?
+        }
+        BotCommand::BatchApprove {
+            role,
+            index_from,
+            index_to,
+        } => {
+            let response = match command_service
+                .batch_approve(&role, index_from, index_to)
+                .await
+            {
+                Ok(batch_approve) => batch_approve.summary(),
+                Err(err) => format!("Error: {}", err),
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        BotCommand::DumpAll {
+            group,
+            interval_min,
+            interval_max,
+        } => {
+            let response = match command_service.request_dump(
+                msg.chat.id.0,
+                &group,
+                interval_min,
+                interval_max,
+            ) {
+                Ok(group) => format!(
+                    "⚠️ Every {:?} wallet will sell all its tokens ⚠️\nSend /dump_confirm within {}s to proceed.",
+                    group,
+                    DUMP_CONFIRM_WINDOW.as_secs()
+                ),
+                Err(err) => format!("Error: {}", err),
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        BotCommand::DumpConfirm => {
+            let response = match command_service.confirm_dump(msg.chat.id.0).await {
+                Some(Ok(dump)) => dump.summary(),
+                Some(Err(err)) => format!("Error: {}", err),
+                None => "No pending /dump_all, send /dump_all first.".to_string(),
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        BotCommand::Quote { eth_amount } => {
+            let response = match command_service.get_quote(&eth_amount).await {
+                Ok(quote) => quote.summary(),
+                Err(err) => format!("Error: {}", err),
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        BotCommand::Pnl => {
+            let response = match command_service.get_pnl().await {
+                Ok(pnl) => pnl,
+                Err(err) => format!("Error: {}", err),
+            };
+            bot.send_message(msg.chat.id, response).await?
+        }
+        BotCommand::Panic => {
+            command_service.request_panic(msg.chat.id.0);
+            let response = format!(
+                "⚠️ Every buyer, seller, auto buyer and market maker wallet will sell all its tokens at any price ⚠️\nSend /panic_confirm within {}s to proceed.",
+                PANIC_CONFIRM_WINDOW.as_secs()
+            );
+            bot.send_message(msg.chat.id, response).await?
+        }
+        BotCommand::PanicConfirm => {
+            if !command_service.confirm_panic(msg.chat.id.0) {
+                bot.send_message(msg.chat.id, "No pending /panic, send /panic first.")
+                    .await?;
+                return Ok(());
+            }
+            bot.send_message(msg.chat.id, "Panic sell started...")
+                .await?;
+            let response = match command_service.panic_sell_all().await {
+                Ok(panic_sell) => panic_sell.summary(),
+                Err(err) => format!("Error: {}", err),
+            };
+            bot.send_message(msg.chat.id, response).await?
         } // launch process command
           // BotCommand::LaunchBuyBot => {
           //     let response = command_service.launch_buy_bot().await;
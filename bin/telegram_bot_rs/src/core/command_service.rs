@@ -1,17 +1,39 @@
-use bigdecimal::BigDecimal;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
 use ethers::{
     types::U256,
     utils::{format_ether, format_units, parse_ether},
 };
 use mm_token_rs::{
-    core::ApiService,
-    types::{Buyers, Deployer, DeploymentChecklist, LaunchStatus, MarketMakers, NetworkStatus},
+    core::{ApiService, DumpGroup},
+    routers::{RouterService, Uniswap2Service},
+    types::{
+        BatchApprove, Buyers, Deployer, DeploymentChecklist, Dump, LaunchStatus, MarketMakers,
+        NetworkStatus, PanicSell, Quote,
+    },
+    utils::{fetch_token_info, format_token_amount, get_native_symbol},
 };
-use mm_token_utils::abi::MemeTokenAbigen;
+use mm_token_utils::abi::IUniswapV2PairAbigen;
+use tokio::sync::RwLock;
+
+// chat id -> when /panic was sent, the panic sell only runs on a /panic_confirm within the window
+static PANIC_REQUESTS: Mutex<BTreeMap<i64, Instant>> = Mutex::new(BTreeMap::new());
+pub const PANIC_CONFIRM_WINDOW: Duration = Duration::from_secs(60);
+// chat id -> the /dump_all waiting for its /dump_confirm: when it was sent, group, interval min and max
+static DUMP_REQUESTS: Mutex<BTreeMap<i64, (Instant, DumpGroup, u32, u32)>> =
+    Mutex::new(BTreeMap::new());
+pub const DUMP_CONFIRM_WINDOW: Duration = Duration::from_secs(60);
+
 #[derive(Debug, Clone)]
 pub struct CommandService {
     // env: Env,
     api_service: ApiService,
+    uniswap2_service: Uniswap2Service,
+    router_service: RouterService,
 }
 
 impl Default for CommandService {
@@ -22,12 +44,32 @@ impl Default for CommandService {
 
 impl CommandService {
     pub fn new() -> Self {
-        let api_service = ApiService::new();
-        Self { api_service }
+        Self::new_with_api_service(ApiService::new())
+    }
+
+    /// `api_service` and its provider instead of the first rpc of the network
+    pub fn new_with_api_service(api_service: ApiService) -> Self {
+        // only read prices and reserves, no tx is built
+        let gas_price = Arc::new(RwLock::new(U256::zero()));
+        let uniswap2_service = Uniswap2Service::new(
+            api_service.env.clone(),
+            gas_price.clone(),
+            api_service.http_provider.clone(),
+        );
+        let router_service = RouterService::new(
+            api_service.env.clone(),
+            gas_price,
+            api_service.http_provider.clone(),
+        );
+        Self {
+            api_service,
+            uniswap2_service,
+            router_service,
+        }
     }
 
     // APIs
-    pub async fn get_network_status(&self) -> NetworkStatus {
+    pub async fn get_network_status(&self) -> anyhow::Result<NetworkStatus> {
         self.api_service.get_network_status().await
     }
 
@@ -47,13 +89,16 @@ impl CommandService {
         let buyers_info = self.api_service.get_auto_buyers().await;
         log::info!("buyers_info: {:#?}", buyers_info);
 
-        let auto_buyers_total_eth = buyers_info.status.total_balance;
-        let auto_buyers_total_token = buyers_info.status.total_token_balance;
+        let auto_buyers_total_eth = buyers_info.status.raw_total_balance;
+        let auto_buyers_total_token = buyers_info.status.raw_total_token_balance;
 
-        let auto_buyers_info_summary_content = self
-            .process_summary_info(&auto_buyers_total_eth, &auto_buyers_total_token)
+        let auto_buyers_info_summary_content = match self
+            .process_summary_info(auto_buyers_total_eth, auto_buyers_total_token)
             .await
-            .unwrap();
+        {
+            Ok(summary_content) => summary_content,
+            Err(err) => format!("\nError: {}", err),
+        };
 
         let title = "💶 Auto Buy Summary 💶\n".to_string();
 
@@ -64,13 +109,16 @@ impl CommandService {
         let sellers_info = self.api_service.get_sellers().await;
         log::info!("sellers_info: {:#?}", sellers_info);
 
-        let sellers_total_eth = sellers_info.status.total_balance;
-        let sellers_total_token = sellers_info.status.total_token_balance;
+        let sellers_total_eth = sellers_info.status.raw_total_balance;
+        let sellers_total_token = sellers_info.status.raw_total_token_balance;
 
-        let sellers_info_summary_content = self
-            .process_summary_info(&sellers_total_eth, &sellers_total_token)
+        let sellers_info_summary_content = match self
+            .process_summary_info(sellers_total_eth, sellers_total_token)
             .await
-            .unwrap();
+        {
+            Ok(summary_content) => summary_content,
+            Err(err) => format!("\nError: {}", err),
+        };
 
         let title = "📊 Auto Sell Summary 📊\n".to_string();
 
@@ -85,6 +133,77 @@ impl CommandService {
         self.api_service.launch_process().await
     }
 
+    pub async fn batch_approve(
+        &self,
+        role: &str,
+        index_from: u32,
+        index_to: u32,
+    ) -> anyhow::Result<BatchApprove> {
+        self.api_service
+            .batch_approve(role.parse()?, index_from, index_to)
+            .await
+    }
+
+    /// the dump only starts on a /dump_confirm of the chat, an invalid group is rejected right away
+    pub fn request_dump(
+        &self,
+        chat_id: i64,
+        group: &str,
+        interval_min: u32,
+        interval_max: u32,
+    ) -> anyhow::Result<DumpGroup> {
+        let group: DumpGroup = group.parse()?;
+        DUMP_REQUESTS
+            .lock()
+            .unwrap()
+            .insert(chat_id, (Instant::now(), group, interval_min, interval_max));
+        Ok(group)
+    }
+
+    /// the /dump_all of the chat once, when confirmed within DUMP_CONFIRM_WINDOW
+    pub async fn confirm_dump(&self, chat_id: i64) -> Option<anyhow::Result<Dump>> {
+        let (requested_at, group, interval_min, interval_max) =
+            DUMP_REQUESTS.lock().unwrap().remove(&chat_id)?;
+        if requested_at.elapsed() > DUMP_CONFIRM_WINDOW {
+            return None;
+        }
+        Some(
+            self.api_service
+                .dump(group, interval_min, interval_max)
+                .await,
+        )
+    }
+
+    pub async fn get_quote(&self, eth_amount: &str) -> anyhow::Result<Quote> {
+        self.api_service.get_quote(eth_amount, true).await
+    }
+
+    pub async fn get_pnl(&self) -> anyhow::Result<String> {
+        let pnl_report = self.api_service.get_pnl().await?;
+        let native_usd_price = self.api_service.price_oracle.get_native_usd_price().await?;
+        Ok(pnl_report.summary(native_usd_price))
+    }
+
+    pub fn request_panic(&self, chat_id: i64) {
+        PANIC_REQUESTS
+            .lock()
+            .unwrap()
+            .insert(chat_id, Instant::now());
+    }
+
+    /// true once per /panic of the chat, when confirmed within PANIC_CONFIRM_WINDOW
+    pub fn confirm_panic(&self, chat_id: i64) -> bool {
+        PANIC_REQUESTS
+            .lock()
+            .unwrap()
+            .remove(&chat_id)
+            .is_some_and(|requested_at| requested_at.elapsed() <= PANIC_CONFIRM_WINDOW)
+    }
+
+    pub async fn panic_sell_all(&self) -> anyhow::Result<PanicSell> {
+        self.api_service.panic_sell_all().await
+    }
+
     // launch process commands
     pub async fn launch_buy_bot(&self) -> anyhow::Result<()> {
         Ok(())
@@ -97,8 +216,8 @@ impl CommandService {
     // common func
     pub async fn process_summary_info(
         &self,
-        total_eth_str: &str,
-        total_token_str: &str,
+        total_eth: U256,
+        total_token: U256,
     ) -> anyhow::Result<String> {
         // - ETH price: $3200. Balance: 100 ETH ~ $320,000
         // - Token price: 0.0001 ETH ~ $0.32. Balance: 1M token ~ 100 ETH ~ $320,000
@@ -106,77 +225,60 @@ impl CommandService {
         // - FDV: $10,000,000 (lấy giá token nhân với total supply)
 
         let mut res_message = "\n".to_string();
-        // URL of the CoinGecko API to get Ethereum price
-        let url = "https://api.coingecko.com/api/v3/simple/price?ids=ethereum&vs_currencies=usd";
-        let response = reqwest::get(url).await?;
-
-        // ETH info
-        let mut eth_price: f64 = 0.0;
-        if response.status().is_success() {
-            let body = response.text().await?;
-            let json: serde_json::Value = serde_json::from_str(&body)?;
-
-            // Extract the price of Ethereum (ETH) from the JSON
-            if let Some(price) = json["ethereum"]["usd"].as_f64() {
-                eth_price = price;
-                log::info!("Current Ethereum (ETH) price: ${}", price);
-            } else {
-                log::warn!("Price data not found in the response.");
-            }
-        } else {
-            log::warn!(
-                "Failed to get Ethereum price. Status code: {}",
-                response.status()
-            );
-        }
 
-        let total_balance_dollar = total_eth_str.parse::<f64>().unwrap() * eth_price;
+        // ETH info, BNB / FTM on bsc / fantom
+        let native_symbol = get_native_symbol(&self.api_service.env.listen_network);
+        let eth_price = self.api_service.price_oracle.get_native_usd_price().await?;
+
+        let weth_decimals = self.api_service.weth.decimals;
+        let total_eth: f64 = format_units(total_eth, weth_decimals as usize)?.parse()?;
+        let total_balance_dollar = total_eth * eth_price;
         let eth_info = format!(
-            "- ETH price: ${:#?}. Balance: {:.4} ETH ~ ${:.2}\n\n",
-            eth_price,
-            total_eth_str.parse::<f64>().unwrap(),
-            total_balance_dollar
+            "- {} price: ${:#?}. Balance: {:.4} {} ~ ${:.2}\n\n",
+            native_symbol, eth_price, total_eth, native_symbol, total_balance_dollar
         );
         res_message.push_str(&eth_info);
 
         // Token Info
-        let (mm_token_reserve, weth_reserve, token_total_supply, token_decimals, token_symbol) =
-            self.get_reverse_and_total_supply().await?;
+        let (
+            token_price_eth,
+            mm_token_reserve,
+            weth_reserve,
+            token_total_supply,
+            token_decimals,
+            token_symbol,
+        ) = self.get_reverse_and_total_supply().await?;
         log::info!(
             "mm_token_reserve: {:#?}, weth_reserve: {:#?}",
             mm_token_reserve,
             weth_reserve
         );
-        let token_price_eth = (BigDecimal::from(weth_reserve) / BigDecimal::from(mm_token_reserve))
-            .round(18)
-            .to_string()
-            .parse::<f64>()?;
         log::info!("token_price_eth: {:#?}", token_price_eth);
         let token_price_dollar = token_price_eth * eth_price;
 
-        let total_token =
-            total_token_str.replace('M', "").parse::<f64>().unwrap() * f64::powi(10.0, 6);
+        let total_token_str = format_token_amount(total_token, token_decimals);
+        let total_token: f64 = format_units(total_token, token_decimals as usize)?.parse()?;
 
         let total_token_price_eth = token_price_eth * total_token;
         let total_token_price_dollar = total_token_price_eth * eth_price;
 
         let token_info = format!(
-            "- Token price: {:#?} ETH ~ ${:#?}. Balance: {:.6}M {:#?} ~ {:.4} ETH ~ ${:.2}\n\n",
+            "- Token price: {:#?} {} ~ ${:#?}. Balance: {} {:#?} ~ {:.4} {} ~ ${:.2}\n\n",
             token_price_eth,
+            native_symbol,
             token_price_dollar,
-            total_token_str.replace('M', "").parse::<f64>().unwrap(),
+            total_token_str,
             token_symbol,
             total_token_price_eth,
+            native_symbol,
             total_token_price_dollar
         );
         res_message.push_str(&token_info);
 
         // Pool info
-        let weth_decimals = self.api_service.weth.decimals;
         let weth_pool_reverse =
             format_units(weth_reserve, weth_decimals as usize).expect("Failed to format units");
-        let token_pool_reverse = format_units(mm_token_reserve, token_decimals as usize + 6)
-            .expect("Failed to format units");
+        let token_pool_reverse = format_token_amount(U256::from(mm_token_reserve), token_decimals);
 
         let liquidity = format_ether(
             parse_ether(token_price_dollar)? * U256::from(mm_token_reserve)
@@ -186,9 +288,10 @@ impl CommandService {
         );
 
         let pool_info = format!(
-            "- Pool: {:.4} ETH + {:.6}M {:#?}. Liquidity: ${:.6}\n\n",
+            "- Pool: {:.4} {} + {} {:#?}. Liquidity: ${:.6}\n\n",
             weth_pool_reverse.parse::<f64>().unwrap(),
-            token_pool_reverse.parse::<f64>().unwrap(),
+            native_symbol,
+            token_pool_reverse,
             token_symbol,
             liquidity.parse::<f64>().unwrap()
         );
@@ -207,25 +310,65 @@ impl CommandService {
         Ok(res_message)
     }
 
-    async fn get_reverse_and_total_supply(&self) -> anyhow::Result<(u128, u128, U256, u8, String)> {
+    /// token price in ETH and the pool reserves, v2 pair first then the pool of the active router
+    async fn get_reverse_and_total_supply(
+        &self,
+    ) -> anyhow::Result<(f64, u128, u128, U256, u8, String)> {
         let api_service = self.api_service.clone();
 
-        let token_info_call = MemeTokenAbigen::new(
+        let token_info = fetch_token_info(
             api_service.env.token_address,
             api_service.http_provider.clone(),
-        );
-        let token_symbol: String = token_info_call.symbol().call().await.unwrap();
-        // let token_name: String = token_info_call.name().call().await.unwrap();
-        let token_decimals: u8 = token_info_call.decimals().call().await.unwrap();
-        let token_total_supply: U256 = token_info_call.total_supply().call().await.unwrap();
+        )
+        .await?;
+
+        let (token_price_eth, mm_token_reserve, weth_reserve) =
+            match self.uniswap2_service.get_token_native_price().await {
+                Ok(price_and_reserves) => price_and_reserves,
+                Err(err) => {
+                    log::warn!(
+                        "[CommandService] v2 pair not available, fallback to {:?}: {:?}",
+                        self.router_service.active_router,
+                        err
+                    );
+                    self.get_active_router_price_and_reserves().await?
+                }
+            };
 
-        // TODO: reverse ?
         Ok((
-            0_u128,
-            0_u128,
-            token_total_supply,
-            token_decimals,
-            token_symbol,
+            token_price_eth,
+            mm_token_reserve,
+            weth_reserve,
+            token_info.total_supply,
+            token_info.decimals,
+            token_info.symbol,
+        ))
+    }
+
+    /// v3 pools have no reserves getter, use the pool balances instead
+    async fn get_active_router_price_and_reserves(&self) -> anyhow::Result<(f64, u128, u128)> {
+        let token_address = self.api_service.env.token_address;
+        let weth_address = self.api_service.weth.address;
+        let (pool_address, _) = self
+            .router_service
+            .get_pair_address(&token_address, &weth_address, false)
+            .await?;
+        let token_price_eth = self
+            .router_service
+            .get_token_native_price(self.router_service.active_router, pool_address, true)
+            .await?;
+
+        let token_contract =
+            IUniswapV2PairAbigen::new(token_address, self.api_service.http_provider.clone());
+        let weth_contract =
+            IUniswapV2PairAbigen::new(weth_address, self.api_service.http_provider.clone());
+        let mm_token_reserve: U256 = token_contract.balance_of(pool_address).call().await?;
+        let weth_reserve: U256 = weth_contract.balance_of(pool_address).call().await?;
+
+        Ok((
+            token_price_eth,
+            mm_token_reserve.as_u128(),
+            weth_reserve.as_u128(),
         ))
     }
 }
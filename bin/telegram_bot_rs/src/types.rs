@@ -25,6 +25,36 @@ pub enum BotCommand {
     GetMarketMakers,
     #[command(description = "launch process.")]
     LaunchProcess,
+    #[command(
+        description = "approve the router from wallets: <buyer|auto_buyer|seller|market_maker_<n>> <index_from> <index_to>.",
+        parse_with = "split"
+    )]
+    BatchApprove {
+        role: String,
+        index_from: u32,
+        index_to: u32,
+    },
+    #[command(
+        description = "sell the tokens of wallets one by one: <buyer|auto_buyer|seller|market_maker> <interval_min_s> <interval_max_s>, asks for /dump_confirm.",
+        parse_with = "split"
+    )]
+    DumpAll {
+        group: String,
+        interval_min: u32,
+        interval_max: u32,
+    },
+    #[command(description = "confirm /dump_all within a minute.")]
+    DumpConfirm,
+    #[command(description = "quote a buy without sending a tx: <eth_amount>.")]
+    Quote { eth_amount: String },
+    #[command(description = "eth in / out and holdings value of each wallet group.")]
+    Pnl,
+    #[command(
+        description = "sell every wallet holding tokens at any price, asks for /panic_confirm."
+    )]
+    Panic,
+    #[command(description = "confirm /panic within a minute.")]
+    PanicConfirm,
     // launch process command
     // #[command(description = "launch buy bot")]
     // LaunchBuyBot,
@@ -0,0 +1,97 @@
+// the rpc fixtures of the mm_token_rs tests
+#[path = "../../mm_token_rs/tests/common/mod.rs"]
+mod common;
+
+use common::{address_below, service_env, MockRpc, MockRpcExt, V2PairFixture};
+use ethers::{
+    abi::Token,
+    types::{Address, I256, U256},
+    utils::parse_ether,
+};
+use mm_token_rs::{core::ApiService, utils::PriceOracle};
+use mm_token_utils::constants::{UNISWAP2_ROUTERS, WRAPPED_NATIVE_TOKENS};
+use provider_utils::enums::ENetwork;
+use telegram_bot_rs::core::CommandService;
+
+#[tokio::test]
+async fn summary_reports_the_v2_pool_price_liquidity_and_fdv() {
+    let mnemonic = "test test test test test test test test test test test junk";
+    for (key, value) in [
+        ("ACTIVE_ROUTER", "UNISWAP2_ROUTERS"),
+        ("BUYER_MNEMONIC", mnemonic),
+        ("BUYER_SURPLUS_BALANCE", "0.01"),
+        ("BUYER_WALLETS_COUNT", "1"),
+        ("AUTO_BUYER_MNEMONIC", mnemonic),
+        ("AUTO_BUYER_WALLETS_COUNT", "1"),
+        ("SELLER_MNEMONIC", mnemonic),
+        ("SELLER_WALLETS_COUNT", "1"),
+        ("AUTO_SELL_MIN_PERCENT", "10"),
+        ("AUTO_SELL_MAX_PERCENT", "20"),
+        ("TOKEN_METADATA_RETRIES", "0"),
+    ] {
+        std::env::set_var(key, value);
+    }
+    let weth_address = WRAPPED_NATIVE_TOKENS[&ENetwork::BaseMainnet].address;
+    let token_address = address_below(weth_address);
+    let (factory, eth_usd_feed) = (Address::random(), Address::random());
+    // 1M tokens against 10 ETH: 0.00001 ETH per token
+    let pair = V2PairFixture::new(
+        token_address,
+        weth_address,
+        parse_ether(1_000_000).unwrap().as_u128(),
+        parse_ether(10).unwrap().as_u128(),
+    );
+    let http_provider = MockRpc::new()
+        .with_erc20(token_address, "MM", 18, parse_ether(1_000_000_000).unwrap())
+        .with_router_factory(UNISWAP2_ROUTERS[&ENetwork::BaseMainnet], factory)
+        .with_v2_pair(factory, &pair)
+        // ETH at $2000 on the Chainlink feed
+        .on_any_call(
+            eth_usd_feed,
+            "latestRoundData()",
+            &[
+                Token::Uint(U256::one()),
+                Token::Int(I256::from(2_000 * 100_000_000i64).into_raw()),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::zero()),
+                Token::Uint(U256::one()),
+            ],
+        )
+        .on_any_call(eth_usd_feed, "decimals()", &[Token::Uint(U256::from(8))])
+        .launch()
+        .await;
+
+    let mut api_service = ApiService::new_with_provider(service_env(token_address), http_provider);
+    // CoinGecko is unreachable, the price comes from the feed
+    api_service.price_oracle = PriceOracle::new(
+        60,
+        "ethereum".to_string(),
+        "http://127.0.0.1:9/simple/price".to_string(),
+        None,
+        Some((eth_usd_feed, api_service.http_provider.clone())),
+    );
+    let summary = CommandService::new_with_api_service(api_service)
+        .process_summary_info(parse_ether(1).unwrap(), parse_ether(1_234_567).unwrap())
+        .await
+        .unwrap();
+
+    assert!(
+        summary.contains("- Token price: 1e-5 ETH ~ $0.02."),
+        "{}",
+        summary
+    );
+    // the balance is priced from the raw 1,234,567 tokens, not the rounded 1.235M shown
+    assert!(
+        summary.contains("Balance: 1.235M \"MM\" ~ 12.3457 ETH ~ $24691.34"),
+        "{}",
+        summary
+    );
+    // 1M tokens at $0.02 + 10 ETH at $2000
+    assert!(
+        summary.contains("- Pool: 10.0000 ETH + 1M \"MM\". Liquidity: $40000.000000"),
+        "{}",
+        summary
+    );
+    // 1B tokens at $0.02
+    assert!(summary.ends_with("- FDV: $20000000.00"), "{}", summary);
+}
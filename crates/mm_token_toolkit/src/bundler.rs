@@ -1,24 +1,43 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::Utc;
 use ethers::prelude::*;
 use ethers_flashbots::*;
-use mm_token_utils::utils::format_lower_hex;
+use mm_token_utils::{constants::BLOXROUTE_RELAY_NETWORKS, utils::format_lower_hex};
 use provider_utils::enums::ENetwork;
+use strum_macros::{Display, EnumString};
 use url::Url;
 
-use crate::bloxroute::{BloxrouteBundleNetwork, BloxrouteMiddleware};
+use crate::{
+    bloxroute::{BloxrouteBundleNetwork, BloxrouteMiddleware},
+    flashbots::FlashbotsMiddleware,
+};
 
 // type SignerProvider = SignerMiddleware<Provider<Http>, LocalWallet>;
 
+/// relay the bundles are sent to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, EnumString, Display)]
+pub enum BundlerBackend {
+    /// `blxr_submit_bundle`, tipped by a transfer to the bloxroute tip address (bsc only)
+    #[default]
+    Bloxroute,
+    /// `eth_sendBundle`, builders are paid by the priority fee / coinbase transfer of the bundle txs
+    Flashbots,
+}
+
 #[derive(Debug)]
 pub struct Bundler {
     pub network: ENetwork,
-    pub bloxroute_builder: BloxrouteMiddleware,
+    pub backend: BundlerBackend,
+    pub bloxroute_builder: Option<BloxrouteMiddleware>,
+    pub flashbots_builder: Option<FlashbotsMiddleware>,
 }
 
 #[allow(clippy::new_without_default)]
 impl Bundler {
     pub fn new(network: ENetwork, bloxroute_config: BloxrouteConfig) -> Self {
+        if let Err(err) = check_bloxroute_relay_url(&network, &bloxroute_config.relay_url) {
+            panic!("{}", err);
+        }
         let bloxroute_builder = BloxrouteMiddleware::new(
             Url::parse(&bloxroute_config.relay_url).unwrap(),
             &bloxroute_config.authorization_key,
@@ -26,7 +45,24 @@ impl Bundler {
 
         Self {
             network,
-            bloxroute_builder,
+            backend: BundlerBackend::Bloxroute,
+            bloxroute_builder: Some(bloxroute_builder),
+            flashbots_builder: None,
+        }
+    }
+
+    pub fn new_flashbots(network: ENetwork, flashbots_config: FlashbotsConfig) -> Self {
+        let searcher_signer: LocalWallet = flashbots_config.searcher_key.parse().unwrap();
+        let flashbots_builder = FlashbotsMiddleware::new(
+            Url::parse(&flashbots_config.relay_url).unwrap(),
+            searcher_signer,
+        );
+
+        Self {
+            network,
+            backend: BundlerBackend::Flashbots,
+            bloxroute_builder: None,
+            flashbots_builder: Some(flashbots_builder),
         }
     }
 
@@ -53,12 +89,18 @@ impl Bundler {
     }
 
     pub async fn send_bundle(&self, bundle: &BundleRequest) -> Result<Vec<String>> {
-        if [ENetwork::BscMainnet, ENetwork::BscTestnet].contains(&self.network) {
-            let bloxroute_bundle_hash = self
-                .bloxroute_builder
-                .send_bundle(bundle, Some(BloxrouteBundleNetwork::BscMainnet))
-                .await?;
-            return Ok(vec![format_lower_hex(&bloxroute_bundle_hash)]);
+        if let Some(flashbots_builder) = &self.flashbots_builder {
+            let flashbots_bundle_hash = flashbots_builder.send_bundle(bundle).await?;
+            return Ok(vec![format_lower_hex(&flashbots_bundle_hash)]);
+        }
+
+        if let Some(bloxroute_builder) = &self.bloxroute_builder {
+            if [ENetwork::BscMainnet, ENetwork::BscTestnet].contains(&self.network) {
+                let bloxroute_bundle_hash = bloxroute_builder
+                    .send_bundle(bundle, Some(BloxrouteBundleNetwork::BscMainnet))
+                    .await?;
+                return Ok(vec![format_lower_hex(&bloxroute_bundle_hash)]);
+            }
         }
 
         Ok(vec![])
@@ -70,3 +112,57 @@ pub struct BloxrouteConfig {
     pub relay_url: String,
     pub authorization_key: String,
 }
+
+#[derive(Debug, Default)]
+pub struct FlashbotsConfig {
+    pub relay_url: String,
+    /// private key signing the relay requests, a dedicated key without funds
+    pub searcher_key: String,
+}
+
+/// public flashbots relay of the network
+pub fn get_flashbots_relay_url(network: &ENetwork) -> Option<&'static str> {
+    match network {
+        ENetwork::EthMainnet => Some("https://relay.flashbots.net"),
+        ENetwork::EthSepolia => Some("https://relay-sepolia.flashbots.net"),
+        _ => None,
+    }
+}
+
+/// regional gateways are chain specific, `virginia.bsc.blxrbdn.com` cannot relay eth bundles
+pub fn check_bloxroute_relay_url(network: &ENetwork, relay_url: &str) -> Result<()> {
+    let relay_url = Url::parse(relay_url)
+        .map_err(|err| anyhow!("BLOXROUTE_RELAY_URL {:?} invalid: {}", relay_url, err))?;
+    let host = relay_url.host_str().unwrap_or_default();
+    let relay_network = BLOXROUTE_RELAY_NETWORKS
+        .values()
+        .find(|label| host.split('.').any(|host_label| host_label == **label));
+    match relay_network {
+        Some(relay_network) if BLOXROUTE_RELAY_NETWORKS.get(network) != Some(relay_network) => {
+            Err(anyhow!(
+                "BLOXROUTE_RELAY_URL {} is a {} gateway, not a {:?} one",
+                relay_url,
+                relay_network,
+                network
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bloxroute_relay_url_must_match_the_network() {
+        let bsc_gateway = "https://virginia.bsc.blxrbdn.com";
+        assert!(check_bloxroute_relay_url(&ENetwork::BscMainnet, bsc_gateway).is_ok());
+        assert!(check_bloxroute_relay_url(&ENetwork::EthMainnet, bsc_gateway).is_err());
+        // the cloud api serves every network
+        assert!(
+            check_bloxroute_relay_url(&ENetwork::EthMainnet, "https://api.blxrbdn.com").is_ok()
+        );
+        assert!(check_bloxroute_relay_url(&ENetwork::BscMainnet, "not a url").is_err());
+    }
+}
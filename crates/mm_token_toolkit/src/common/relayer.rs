@@ -1,4 +1,5 @@
 // use ethers::core::types::U64;
+use ethers::signers::{LocalWallet, WalletError};
 use ethers_flashbots::BundleHash;
 use reqwest::{Client, Error as ReqwestError};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
@@ -7,6 +8,7 @@ use thiserror::Error;
 use url::Url;
 
 use super::jsonrpc::{JsonRpcError, Request, Response};
+use crate::flashbots::flashbots_signature;
 
 /// A Flashbots relay client.
 ///
@@ -22,6 +24,8 @@ pub struct Relay {
     client: Client,
     url: Url,
     authorization_key: String,
+    // flashbots relays authenticate the searcher by a signature of the body instead
+    signer: Option<LocalWallet>,
 }
 
 /// Errors for relay requests.
@@ -45,6 +49,9 @@ pub enum RelayError {
         err: serde_json::Error,
         text: String,
     },
+    /// The request body could not be signed.
+    #[error(transparent)]
+    SignerError(#[from] WalletError),
 }
 
 impl Relay {
@@ -55,6 +62,18 @@ impl Relay {
             client: Client::new(),
             url: url.into(),
             authorization_key: authorization_key.to_string(),
+            signer: None,
+        }
+    }
+
+    /// Initializes a relay client signing every request in the `X-Flashbots-Signature` header.
+    pub fn new_with_signer(url: impl Into<Url>, signer: LocalWallet) -> Self {
+        Self {
+            id: AtomicU64::new(0),
+            client: Client::new(),
+            url: url.into(),
+            authorization_key: Default::default(),
+            signer: Some(signer),
         }
     }
 
@@ -70,11 +89,21 @@ impl Relay {
 
         let payload = Request::new(next_id, method, params);
 
-        let mut req = self.client.post(self.url.as_ref());
+        let body = serde_json::to_string(&payload)?;
 
-        req = req.header("Authorization", self.authorization_key.clone());
+        let mut req = self
+            .client
+            .post(self.url.as_ref())
+            .header("Content-Type", "application/json");
+
+        req = match &self.signer {
+            Some(signer) => {
+                req.header("X-Flashbots-Signature", flashbots_signature(signer, &body)?)
+            }
+            None => req.header("Authorization", self.authorization_key.clone()),
+        };
 
-        let res = req.json(&payload).send().await?;
+        let res = req.body(body).send().await?;
         let status = res.error_for_status_ref();
 
         match status {
@@ -107,6 +136,7 @@ impl Clone for Relay {
             client: self.client.clone(),
             url: self.url.clone(),
             authorization_key: self.authorization_key.clone(),
+            signer: self.signer.clone(),
         }
     }
 }
@@ -0,0 +1,100 @@
+use ethers::{
+    signers::{LocalWallet, Signer, WalletError},
+    types::{Bytes, H256, U64},
+    utils::{hash_message, hex, keccak256},
+};
+use ethers_flashbots::{BundleRequest, BundleTransaction};
+use serde::{Serialize, Serializer};
+
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlashbotsBundleRequest {
+    #[serde(serialize_with = "flashbots_serialize_txs")]
+    pub txs: Vec<BundleTransaction>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reverting_tx_hashes: Vec<H256>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<U64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_timestamp: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_timestamp: Option<u64>,
+}
+
+impl From<&BundleRequest> for FlashbotsBundleRequest {
+    fn from(value: &BundleRequest) -> Self {
+        Self {
+            txs: value.transactions().clone(),
+            reverting_tx_hashes: Default::default(),
+            block_number: value.block(),
+            min_timestamp: value.min_timestamp(),
+            max_timestamp: value.max_timestamp(),
+        }
+    }
+}
+
+pub fn flashbots_serialize_txs<S>(txs: &[BundleTransaction], s: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    // flashbots takes 0x prefixed raw txs
+    let raw_txs: Vec<Bytes> = txs
+        .iter()
+        .map(|tx| match tx {
+            BundleTransaction::Signed(inner) => inner.rlp(),
+            BundleTransaction::Raw(inner) => inner.clone(),
+        })
+        .collect();
+
+    raw_txs.serialize(s)
+}
+
+/// `X-Flashbots-Signature` header of a request body: `searcher address:signature`
+/// of the hex keccak256 of the body, signed as an EIP-191 message
+pub fn flashbots_signature(signer: &LocalWallet, body: &str) -> Result<String, WalletError> {
+    let body_hash = format!("0x{}", hex::encode(keccak256(body.as_bytes())));
+    let signature = signer.sign_hash(hash_message(body_hash))?;
+    Ok(format!("{:?}:0x{}", signer.address(), signature))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use ethers::types::Signature;
+
+    use super::*;
+    use crate::common::Request;
+
+    #[test]
+    fn flashbots_bundle_is_signed_and_serialized() {
+        let bundle = BundleRequest::new()
+            .push_transaction(Bytes::from(vec![0x02, 0xab]))
+            .push_transaction(Bytes::from(vec![0x02, 0xcd]))
+            .set_block(U64::from(20_000_000))
+            .set_min_timestamp(1_700_000_000)
+            .set_max_timestamp(1_700_000_060);
+        let request = Request::new(1, "eth_sendBundle", [FlashbotsBundleRequest::from(&bundle)]);
+        let body = serde_json::to_string(&request).unwrap();
+        assert_eq!(
+            body,
+            r#"{"id":1,"jsonrpc":"2.0","method":"eth_sendBundle","params":[{"txs":["0x02ab","0x02cd"],"blockNumber":"0x1312d00","minTimestamp":1700000000,"maxTimestamp":1700000060}]}"#
+        );
+
+        let signer = LocalWallet::from_str(
+            "0x4c0883a69102937d6231471b5dbb6204fe5129617082792ae468d01a3f362318",
+        )
+        .unwrap();
+        let header = flashbots_signature(&signer, &body).unwrap();
+        let (address, signature) = header.split_once(':').unwrap();
+        assert_eq!(address, format!("{:?}", signer.address()));
+
+        let body_hash = format!("0x{}", hex::encode(keccak256(body.as_bytes())));
+        let signature = Signature::from_str(signature).unwrap();
+        assert_eq!(signature.recover(body_hash).unwrap(), signer.address());
+    }
+}
@@ -0,0 +1,69 @@
+use ethers::signers::LocalWallet;
+use ethers_flashbots::{BundleHash, BundleRequest};
+use thiserror::Error;
+use url::Url;
+
+use crate::common::{Relay, RelayError, SendBundleResponse};
+
+use super::bundle::FlashbotsBundleRequest;
+
+/// Errors for the Flashbots middleware.
+#[derive(Error, Debug)]
+pub enum FlashbotsMiddlewareError {
+    /// The target block must be set, `min_timestamp` and `max_timestamp`
+    /// must both be set or unset.
+    #[error("Some parameters were missing")]
+    MissingParameters,
+    /// The relay responded with an error.
+    #[error(transparent)]
+    RelayError(#[from] RelayError),
+}
+
+/// A middleware used to send bundles to a Flashbots relay.
+///
+/// Every request is signed by the searcher key in the `X-Flashbots-Signature` header,
+/// the searcher key only builds reputation and never holds funds.
+#[derive(Debug)]
+pub struct FlashbotsMiddleware {
+    relay: Relay,
+}
+
+impl FlashbotsMiddleware {
+    pub fn new(relay_url: impl Into<Url>, searcher_signer: LocalWallet) -> Self {
+        Self {
+            relay: Relay::new_with_signer(relay_url, searcher_signer),
+        }
+    }
+
+    /// Get the relay client used by the middleware.
+    pub fn relay(&self) -> &Relay {
+        &self.relay
+    }
+
+    /// Send a bundle to the relayer.
+    ///
+    /// See [`eth_sendBundle`][fb_sendBundle] for more information.
+    ///
+    /// [fb_sendBundle]: https://docs.flashbots.net/flashbots-auction/searchers/advanced/rpc-endpoint#eth_sendbundle
+    pub async fn send_bundle(
+        &self,
+        bundle: &BundleRequest,
+    ) -> Result<BundleHash, FlashbotsMiddlewareError> {
+        // The target block must be set
+        bundle
+            .block()
+            .ok_or(FlashbotsMiddlewareError::MissingParameters)?;
+
+        // `min_timestamp` and `max_timestamp` must both either be unset or set.
+        if bundle.min_timestamp().xor(bundle.max_timestamp()).is_some() {
+            return Err(FlashbotsMiddlewareError::MissingParameters);
+        }
+
+        let response: SendBundleResponse = self
+            .relay
+            .request("eth_sendBundle", [FlashbotsBundleRequest::from(bundle)])
+            .await
+            .map_err(FlashbotsMiddlewareError::RelayError)?;
+        Ok(response.bundle_hash)
+    }
+}
@@ -0,0 +1,5 @@
+mod bundle;
+mod middleware;
+
+pub use bundle::*;
+pub use middleware::*;
@@ -1,4 +1,5 @@
 pub mod bloxroute;
 pub mod bundler;
 pub mod common;
+pub mod flashbots;
 pub mod utils;
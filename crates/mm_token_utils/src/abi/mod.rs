@@ -1,6 +1,16 @@
 use ethers::contract::abigen;
 
 abigen!(AvabotRouterAbigen, "src/abi/AvabotRouter.json");
+abigen!(
+    ChainlinkAggregatorAbigen,
+    "src/abi/ChainlinkAggregator.json"
+);
+abigen!(AerodromeRouterAbigen, "src/abi/AerodromeRouter.json");
+abigen!(AerodromePoolAbigen, "src/abi/AerodromePool.json");
+abigen!(
+    AerodromePoolFactoryAbigen,
+    "src/abi/AerodromePoolFactory.json"
+);
 abigen!(UniswapV2Router02Abigen, "src/abi/UniswapV2Router02.json");
 abigen!(IUniswapV2PairAbigen, "src/abi/IUniswapV2Pair.json");
 abigen!(UniswapV2FactoryAbigen, "src/abi/UniswapV2Factory.json");
@@ -10,6 +20,8 @@ abigen!(UniswapV3FactoryAbigen, "src/abi/UniswapV3Factory.json");
 abigen!(QuoterV2Abigen, "src/abi/QuoterV2.json");
 abigen!(MemeTokenAbigen, "src/abi/MemeToken.json");
 abigen!(DisperseAbigen, "src/abi/Disperse.json");
+abigen!(Permit2Abigen, "src/abi/Permit2.json");
+abigen!(Weth9Abigen, "src/abi/Weth9.json");
 abigen!(
     MemeTokenControllerAbigen,
     "src/abi/MemeTokenController.json"
@@ -0,0 +1,12 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ethers::{prelude::Lazy, types::Address};
+use provider_utils::enums::ENetwork;
+
+// solidly style router of base, routes carry a stable flag instead of a fee tier
+pub static AERODROME_ROUTERS: Lazy<HashMap<ENetwork, Address>> = Lazy::new(|| {
+    HashMap::from([(
+        ENetwork::BaseMainnet,
+        Address::from_str("0xcF77a3Ba9A5CA399B7c97c74d54e5b1Beb874E43").unwrap(),
+    )])
+});
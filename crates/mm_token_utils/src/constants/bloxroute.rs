@@ -0,0 +1,22 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ethers::{prelude::Lazy, types::Address};
+use provider_utils::enums::ENetwork;
+
+// bloxroute tip wallets, the tip transfer opens every bloxroute bundle
+pub static BLOXROUTE_TIP_ADDRESSES: Lazy<HashMap<ENetwork, Address>> = Lazy::new(|| {
+    HashMap::from([
+        (
+            ENetwork::EthMainnet,
+            Address::from_str("0x965Df5Ff6116C395187E288e5C87fb96CfB8141c").unwrap(),
+        ),
+        (
+            ENetwork::BscMainnet,
+            Address::from_str("0x74c5F8C6ffe41AD4789602BDB9a48E6Cad623520").unwrap(),
+        ),
+    ])
+});
+
+// chain label of the regional bloxroute gateways, e.g. `virginia.bsc.blxrbdn.com`
+pub static BLOXROUTE_RELAY_NETWORKS: Lazy<HashMap<ENetwork, &'static str>> =
+    Lazy::new(|| HashMap::from([(ENetwork::EthMainnet, "eth"), (ENetwork::BscMainnet, "bsc")]));
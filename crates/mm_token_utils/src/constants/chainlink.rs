@@ -0,0 +1,30 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ethers::{prelude::Lazy, types::Address};
+use provider_utils::enums::ENetwork;
+
+// chainlink native/USD aggregators (ETH/USD, BNB/USD), the on-chain fallback of the CoinGecko price
+pub static CHAINLINK_NATIVE_USD_FEEDS: Lazy<HashMap<ENetwork, Address>> = Lazy::new(|| {
+    HashMap::from([
+        (
+            ENetwork::EthMainnet,
+            Address::from_str("0x5f4eC3Df9cbd43714FE2740f5E3616155c5b8419").unwrap(),
+        ),
+        (
+            ENetwork::EthSepolia,
+            Address::from_str("0x694AA1769357215DE4FAC081bf1f309aDC325306").unwrap(),
+        ),
+        (
+            ENetwork::BaseMainnet,
+            Address::from_str("0x71041dddad3595F9CEd3DcCFBe3D1F4b0a16Bb70").unwrap(),
+        ),
+        (
+            ENetwork::BaseSepolia,
+            Address::from_str("0x4aDC67696bA383F43DD60A9e78F2C97Fbbfc7cb1").unwrap(),
+        ),
+        (
+            ENetwork::BscMainnet,
+            Address::from_str("0x0567F2323251f0Aab15c8dFb1967E4e8A7D42aeE").unwrap(),
+        ),
+    ])
+});
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{EnumString, VariantNames};
+
+// how transactions are priced, legacy stays the default for existing configs
+#[derive(
+    Debug, Default, PartialEq, Eq, Clone, Copy, EnumString, VariantNames, Serialize, Deserialize,
+)]
+pub enum EGasMode {
+    #[default]
+    Legacy,
+    // maxFeePerGas / maxPriorityFeePerGas from the fee history
+    Eip1559,
+}
@@ -1,11 +1,21 @@
 mod addresses;
+mod aerodrome_router;
 mod avabot_router;
+mod bloxroute;
+mod chainlink;
 mod disperse_router;
+mod gas;
+mod native;
 mod uniswap;
 mod weth;
 
 pub use addresses::*;
+pub use aerodrome_router::*;
 pub use avabot_router::*;
+pub use bloxroute::*;
+pub use chainlink::*;
 pub use disperse_router::*;
+pub use gas::*;
+pub use native::*;
 pub use uniswap::*;
 pub use weth::*;
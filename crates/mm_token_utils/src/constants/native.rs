@@ -0,0 +1,36 @@
+use std::collections::HashMap;
+
+use ethers::prelude::Lazy;
+use provider_utils::enums::ENetwork;
+
+// symbol of the gas token, the amounts of the user facing messages are labelled with it
+pub static NATIVE_SYMBOLS: Lazy<HashMap<ENetwork, &'static str>> = Lazy::new(|| {
+    HashMap::from([
+        (ENetwork::EthMainnet, "ETH"),
+        (ENetwork::EthSepolia, "ETH"),
+        (ENetwork::BlastMainnet, "ETH"),
+        (ENetwork::BlastSepolia, "ETH"),
+        (ENetwork::BaseMainnet, "ETH"),
+        (ENetwork::BaseSepolia, "ETH"),
+        (ENetwork::BscMainnet, "BNB"),
+        (ENetwork::BscTestnet, "BNB"),
+        (ENetwork::FtmMainnet, "FTM"),
+        (ENetwork::FtmTestnet, "FTM"),
+    ])
+});
+
+// coingecko id of the gas token, its USD price comes from `simple/price?ids=<id>`
+pub static COINGECKO_NATIVE_IDS: Lazy<HashMap<ENetwork, &'static str>> = Lazy::new(|| {
+    HashMap::from([
+        (ENetwork::EthMainnet, "ethereum"),
+        (ENetwork::EthSepolia, "ethereum"),
+        (ENetwork::BlastMainnet, "ethereum"),
+        (ENetwork::BlastSepolia, "ethereum"),
+        (ENetwork::BaseMainnet, "ethereum"),
+        (ENetwork::BaseSepolia, "ethereum"),
+        (ENetwork::BscMainnet, "binancecoin"),
+        (ENetwork::BscTestnet, "binancecoin"),
+        (ENetwork::FtmMainnet, "fantom"),
+        (ENetwork::FtmTestnet, "fantom"),
+    ])
+});
@@ -88,6 +88,33 @@ pub static UNISWAP3_ROUTERS: Lazy<HashMap<ENetwork, Address>> = Lazy::new(|| {
         ),
         (ENetwork::BlastSepolia, *ZERO_ADDRESS),
         (ENetwork::BlastMainnet, *ZERO_ADDRESS),
+        (
+            ENetwork::BaseMainnet,
+            Address::from_str("0x2626664c2603336E57B271c5C0b26F421741e481").unwrap(),
+        ),
+        // pancakeswap v3 smart router
+        (
+            ENetwork::BscMainnet,
+            Address::from_str("0x13f4EA83D0bd40E75C8222255bc855a974568Dd4").unwrap(),
+        ),
+        (
+            ENetwork::BscTestnet,
+            Address::from_str("0x9a489505a00cE272eAa5e07Dba6491314CaE3796").unwrap(),
+        ),
+    ])
+});
+
+// v3 factories, networks without an entry read `factory()` from the router
+pub static UNISWAP3_FACTORIES: Lazy<HashMap<ENetwork, Address>> = Lazy::new(|| {
+    HashMap::from([
+        (
+            ENetwork::BscMainnet,
+            Address::from_str("0x0BFbCF9fa4f9C56B0F40a671Ad40E0805A091865").unwrap(),
+        ),
+        (
+            ENetwork::BscTestnet,
+            Address::from_str("0x0BFbCF9fa4f9C56B0F40a671Ad40E0805A091865").unwrap(),
+        ),
     ])
 });
 
@@ -102,6 +129,18 @@ pub static UNISWAP3_QUOTER_V2: Lazy<HashMap<ENetwork, Address>> = Lazy::new(|| {
         (ENetwork::FtmMainnet, *ZERO_ADDRESS),
         (ENetwork::BlastSepolia, *ZERO_ADDRESS),
         (ENetwork::BlastMainnet, *ZERO_ADDRESS),
+        (
+            ENetwork::BaseMainnet,
+            Address::from_str("0x3d4e44Eb1374240CE5F1B871ab261CD16335B76a").unwrap(),
+        ),
+        (
+            ENetwork::BscMainnet,
+            Address::from_str("0xB048Bbc1Ee6b733FFfCFb9e9CeF7375518e25997").unwrap(),
+        ),
+        (
+            ENetwork::BscTestnet,
+            Address::from_str("0xB048Bbc1Ee6b733FFfCFb9e9CeF7375518e25997").unwrap(),
+        ),
     ])
 });
 
@@ -109,6 +148,18 @@ pub const V3_SWAP_EXACT_IN: u8 = 0;
 pub const V3_SWAP_EXACT_OUT: u8 = 1;
 pub const V2_SWAP_EXACT_IN: u8 = 8;
 pub const V2_SWAP_EXACT_OUT: u8 = 9;
+pub const WRAP_ETH: u8 = 11;
+pub const UNWRAP_WETH: u8 = 12;
+
+// universal router recipient placeholders
+pub static UNIVERSAL_MSG_SENDER: Lazy<Address> =
+    Lazy::new(|| Address::from_str("0x0000000000000000000000000000000000000001").unwrap());
+pub static UNIVERSAL_ADDRESS_THIS: Lazy<Address> =
+    Lazy::new(|| Address::from_str("0x0000000000000000000000000000000000000002").unwrap());
+
+// the universal router pulls sold tokens through Permit2, deployed at the same address on every network
+pub static PERMIT2_ADDRESS: Lazy<Address> =
+    Lazy::new(|| Address::from_str("0x000000000022D473030F116dDEE9F6B43aC78BA3").unwrap());
 
 #[derive(
     Debug,
@@ -130,4 +181,29 @@ pub enum ERouter {
     Uniswap2Routers,
     UniversalRouters,
     Uniswap3Routers,
+    Aerodrome,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bsc_pancakeswap_addresses() {
+        for network in [ENetwork::BscMainnet, ENetwork::BscTestnet] {
+            for addresses in [
+                &*UNISWAP2_ROUTERS,
+                &*UNISWAP3_ROUTERS,
+                &*UNISWAP3_FACTORIES,
+                &*UNISWAP3_QUOTER_V2,
+            ] {
+                let address = addresses.get(&network).unwrap();
+                assert_ne!(*address, *ZERO_ADDRESS, "{:?}", network);
+            }
+        }
+        assert_eq!(
+            UNISWAP2_ROUTERS[&ENetwork::BscMainnet],
+            Address::from_str("0x10ED43C718714eb63d5aA57B78B54704E256024E").unwrap()
+        );
+    }
 }
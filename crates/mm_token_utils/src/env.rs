@@ -1,6 +1,19 @@
+use crate::profile::{get_profile_name, load_profile};
+
 pub fn get_env(key: &str, default_value: Option<String>) -> String {
     match default_value {
         Some(value) => std::env::var(key).unwrap_or(value),
         None => std::env::var(key).unwrap_or_else(|_| panic!("expect env {}", key)),
     }
 }
+
+/// Load the campaign profile given by `--profile NAME` (or `PROFILE`), `.env` otherwise
+pub fn load_env() {
+    let Some(profile_name) = get_profile_name() else {
+        dotenv::dotenv().ok();
+        return;
+    };
+    if let Err(err) = load_profile(&profile_name) {
+        panic!("{}", err);
+    }
+}
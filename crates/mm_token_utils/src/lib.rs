@@ -2,4 +2,6 @@ pub mod abi;
 pub mod constants;
 pub mod env;
 pub mod log;
+pub mod profile;
+pub mod signer;
 pub mod utils;
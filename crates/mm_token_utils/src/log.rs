@@ -1,9 +1,21 @@
 use std::str::FromStr;
 
 use fern::colors::{Color, ColoredLevelConfig};
+use log::kv::{self, VisitSource};
+use serde_json::{Map, Value};
+use strum_macros::EnumString;
 
 use crate::env::get_env;
 
+// `LOG_FORMAT`, json prints one object per line for log aggregation
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, EnumString)]
+#[strum(serialize_all = "lowercase")]
+pub enum ELogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
 pub fn setup_logger(
     levels: Option<Vec<(String, log::LevelFilter)>>,
 ) -> Result<(), log::SetLoggerError> {
@@ -19,14 +31,22 @@ pub fn setup_logger(
     let cargo_pkg_name = get_env("CARGO_PKG_NAME", None);
     let cargo_bin_name = current_bin_name().unwrap_or(cargo_pkg_name.clone());
 
+    let log_format = ELogFormat::from_str(&get_env("LOG_FORMAT", Some("text".to_string())))
+        .expect("LOG_FORMAT invalid, expect text or json");
+
     let mut dispatch = fern::Dispatch::new()
-        .format(move |out, message, record| {
-            out.finish(format_args!(
-                "{}[{}] {}",
+        .format(move |out, message, record| match log_format {
+            ELogFormat::Text => out.finish(format_args!(
+                "{}[{}] {}{}",
                 chrono::Local::now().format("[%H:%M:%S.%f]"),
                 colors.color(record.level()),
-                message
-            ))
+                message,
+                format_text_fields(record)
+            )),
+            ELogFormat::Json => out.finish(format_args!(
+                "{}",
+                format_json_record(record, &message.to_string())
+            )),
         })
         .chain(std::io::stdout())
         .level(log::LevelFilter::Warn)
@@ -48,6 +68,58 @@ pub fn setup_logger(
     Ok(())
 }
 
+/**
+ * one json object of the record, key values of the log macro (`event = "buy_sent", tx_hash:? = ...;`) become fields
+ */
+pub fn format_json_record(record: &log::Record, message: &str) -> Value {
+    let mut fields = Map::new();
+    fields.insert(
+        "timestamp".to_string(),
+        Value::from(chrono::Utc::now().to_rfc3339()),
+    );
+    fields.insert("level".to_string(), Value::from(record.level().as_str()));
+    fields.insert("target".to_string(), Value::from(record.target()));
+    fields.insert("message".to_string(), Value::from(message));
+    let _ = record.key_values().visit(&mut JsonFields(&mut fields));
+
+    Value::Object(fields)
+}
+
+/**
+ * key values appended to a text line as ` key=value`
+ */
+fn format_text_fields(record: &log::Record) -> String {
+    let mut fields = Map::new();
+    let _ = record.key_values().visit(&mut JsonFields(&mut fields));
+    fields
+        .iter()
+        .map(|(key, value)| match value {
+            Value::String(value) => format!(" {}={}", key, value),
+            value => format!(" {}={}", key, value),
+        })
+        .collect()
+}
+
+struct JsonFields<'a>(&'a mut Map<String, Value>);
+
+impl<'kvs> VisitSource<'kvs> for JsonFields<'_> {
+    fn visit_pair(&mut self, key: kv::Key<'kvs>, value: kv::Value<'kvs>) -> Result<(), kv::Error> {
+        let value = if let Some(value) = value.to_u64() {
+            Value::from(value)
+        } else if let Some(value) = value.to_i64() {
+            Value::from(value)
+        } else if let Some(value) = value.to_f64() {
+            Value::from(value)
+        } else if let Some(value) = value.to_bool() {
+            Value::from(value)
+        } else {
+            Value::from(value.to_string())
+        };
+        self.0.insert(key.to_string(), value);
+        Ok(())
+    }
+}
+
 fn current_bin_name() -> Option<String> {
     std::env::current_exe()
         .ok()?
@@ -56,3 +128,53 @@ fn current_bin_name() -> Option<String> {
         .to_owned()
         .into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buy_event_serializes_fields() {
+        let tx_hash = "0xabc";
+        let fields: &[(&str, kv::Value)] = &[
+            ("event", kv::Value::from("buy_sent")),
+            ("wallet_index", kv::Value::from(3u32)),
+            ("tx_hash", kv::Value::from(tx_hash)),
+            ("amount", kv::Value::from("0.1")),
+            ("token_price", kv::Value::from(0.000012f64)),
+        ];
+        let record = log::Record::builder()
+            .level(log::Level::Info)
+            .target("mm_token_rs::core::market_maker_service")
+            .key_values(&fields)
+            .build();
+
+        let json = format_json_record(&record, "[MarketMakerService] buy sent");
+        let keys: Vec<&str> = json
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|key| key.as_str())
+            .collect();
+        for key in [
+            "timestamp",
+            "level",
+            "target",
+            "message",
+            "event",
+            "wallet_index",
+            "tx_hash",
+            "amount",
+            "token_price",
+        ] {
+            assert!(keys.contains(&key), "{} missing in {}", key, json);
+        }
+        assert_eq!(json["event"], "buy_sent");
+        assert_eq!(json["wallet_index"], 3);
+        assert_eq!(json["level"], "INFO");
+        assert_eq!(
+            format_text_fields(&record),
+            " amount=0.1 event=buy_sent token_price=0.000012 tx_hash=0xabc wallet_index=3"
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use std::{collections::BTreeMap, path::PathBuf, str::FromStr};
+
+use bip39::{Language, Mnemonic};
+use ethers::types::Address;
+use provider_utils::enums::ENetwork;
+use thiserror::Error;
+
+use crate::{constants::ERouter, env::get_env};
+
+// fields every campaign needs, the rest keeps the defaults of each bin
+const REQUIRED_FIELDS: [&str; 4] = [
+    "LISTEN_NETWORK",
+    "TOKEN_ADDRESS",
+    "ACTIVE_ROUTER",
+    "TRADING_SLIPPAGE",
+];
+
+#[derive(Debug, Error)]
+pub enum ProfileError {
+    #[error("cannot read profile {0:?}: {1}")]
+    Io(PathBuf, std::io::Error),
+    #[error("cannot decrypt profile {0:?}: {1}")]
+    Decrypt(PathBuf, eth_keystore::KeystoreError),
+    #[error("cannot encrypt profile {0:?}: {1}")]
+    Encrypt(PathBuf, eth_keystore::KeystoreError),
+    #[error("profile {0:?} is not a json object of string values: {1}")]
+    Parse(PathBuf, serde_json::Error),
+    #[error("profile {0:?} not found, expect {1:?} or {2:?}")]
+    NotFound(String, PathBuf, PathBuf),
+    #[error("profile {0:?} is invalid:\n{1}")]
+    Invalid(String, String),
+}
+
+/// Campaign config, the same keys `get_env` reads
+pub type Profile = BTreeMap<String, String>;
+
+/// `--profile NAME` from the command line, or `PROFILE`
+pub fn get_profile_name() -> Option<String> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg == "--profile" {
+            return args.next();
+        }
+        if let Some(name) = arg.strip_prefix("--profile=") {
+            return Some(name.to_string());
+        }
+    }
+    let name = get_env("PROFILE", Some("".to_string()));
+    if name.is_empty() {
+        return None;
+    }
+    Some(name)
+}
+
+/// `PROFILE_DIR/NAME.json` in plain text, `PROFILE_DIR/NAME.enc.json` encrypted with `PROFILE_PASSWORD`
+pub fn get_profile_paths(name: &str) -> (PathBuf, PathBuf) {
+    let profile_dir = PathBuf::from(get_env("PROFILE_DIR", Some("profiles".to_string())));
+    (
+        profile_dir.join(format!("{}.json", name)),
+        profile_dir.join(format!("{}.enc.json", name)),
+    )
+}
+
+pub fn read_profile(name: &str) -> Result<Profile, ProfileError> {
+    let (plain_path, encrypted_path) = get_profile_paths(name);
+    let (path, content) = if encrypted_path.exists() {
+        let password = get_env("PROFILE_PASSWORD", None);
+        let content = eth_keystore::decrypt_key(&encrypted_path, password)
+            .map_err(|err| ProfileError::Decrypt(encrypted_path.clone(), err))?;
+        (encrypted_path, content)
+    } else if plain_path.exists() {
+        let content =
+            std::fs::read(&plain_path).map_err(|err| ProfileError::Io(plain_path.clone(), err))?;
+        (plain_path, content)
+    } else {
+        return Err(ProfileError::NotFound(
+            name.to_string(),
+            plain_path,
+            encrypted_path,
+        ));
+    };
+
+    serde_json::from_slice(&content).map_err(|err| ProfileError::Parse(path, err))
+}
+
+/// Encrypt `NAME.json` into `NAME.enc.json` with `PROFILE_PASSWORD`, the plain file is left to the operator
+pub fn encrypt_profile(name: &str) -> Result<PathBuf, ProfileError> {
+    let (plain_path, encrypted_path) = get_profile_paths(name);
+    let content =
+        std::fs::read(&plain_path).map_err(|err| ProfileError::Io(plain_path.clone(), err))?;
+    let _: Profile = serde_json::from_slice(&content)
+        .map_err(|err| ProfileError::Parse(plain_path.clone(), err))?;
+
+    let password = get_env("PROFILE_PASSWORD", None);
+    let profile_dir = encrypted_path.parent().unwrap_or(&encrypted_path);
+    let file_name = format!("{}.enc.json", name);
+    eth_keystore::encrypt_key(
+        profile_dir,
+        &mut rand::thread_rng(),
+        content,
+        password,
+        Some(&file_name),
+    )
+    .map_err(|err| ProfileError::Encrypt(encrypted_path.clone(), err))?;
+
+    Ok(encrypted_path)
+}
+
+/// Check the profile as a unit, returns one message per invalid field
+pub fn validate_profile(profile: &Profile) -> Vec<String> {
+    let mut errors = Vec::new();
+    for field in REQUIRED_FIELDS {
+        if profile.get(field).is_none_or(|value| value.is_empty()) {
+            errors.push(format!("{} is required", field));
+        }
+    }
+
+    for (key, value) in profile {
+        if value.is_empty() {
+            continue;
+        }
+        let error = if key == "LISTEN_NETWORK" {
+            ENetwork::from_str(value).err().map(|_| "unknown network")
+        } else if key == "ACTIVE_ROUTER" {
+            (!value.eq_ignore_ascii_case("AUTO") && ERouter::from_str(value).is_err())
+                .then_some("unknown router")
+        } else if key.ends_with("_ADDRESS") {
+            Address::from_str(value).err().map(|_| "invalid address")
+        } else if key.ends_with("_MNEMONIC") {
+            // remote signer mnemonics hold the signer addresses
+            let is_addresses = value
+                .split(',')
+                .all(|address| Address::from_str(address.trim()).is_ok());
+            (Mnemonic::validate(value, Language::English).is_err() && !is_addresses)
+                .then_some("invalid mnemonic")
+        } else if key.ends_with("_COUNT") || key == "CHAIN_ID" {
+            value.parse::<u64>().err().map(|_| "expect an integer")
+        } else if key.ends_with("_ENABLED") {
+            value.parse::<bool>().err().map(|_| "expect true or false")
+        } else if key.ends_with("_SLIPPAGE") || key.ends_with("_TAX") {
+            value.parse::<f64>().err().map(|_| "expect a number")
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            errors.push(format!("{} {:?}: {}", key, value, error));
+        }
+    }
+
+    errors
+}
+
+/// Load the validated profile into the process env, env vars already set take precedence
+pub fn load_profile(name: &str) -> Result<(), ProfileError> {
+    let mut profile = read_profile(name)?;
+    // overrides are validated together with the profile
+    let keys: Vec<String> = profile
+        .keys()
+        .cloned()
+        .chain(REQUIRED_FIELDS.map(String::from))
+        .collect();
+    for key in keys {
+        if let Ok(env_value) = std::env::var(&key) {
+            profile.insert(key, env_value);
+        }
+    }
+    let errors = validate_profile(&profile);
+    if !errors.is_empty() {
+        return Err(ProfileError::Invalid(name.to_string(), errors.join("\n")));
+    }
+
+    for (key, value) in profile {
+        if std::env::var(&key).is_err() {
+            std::env::set_var(key, value);
+        }
+    }
+
+    Ok(())
+}
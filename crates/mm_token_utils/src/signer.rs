@@ -0,0 +1,352 @@
+use std::{future::Future, str::FromStr};
+
+use async_trait::async_trait;
+use ethers::{
+    providers::{Http, Provider, ProviderError},
+    signers::{LocalWallet, Signer, WalletError},
+    types::{
+        transaction::{eip2718::TypedTransaction, eip712::Eip712},
+        Address, Bytes, Signature, U64,
+    },
+    utils::rlp::Rlp,
+};
+use thiserror::Error;
+
+use crate::env::get_env;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SignerMode {
+    // keys derived in process from the mnemonics / private keys
+    #[default]
+    Local,
+    // keys held by a remote signer (web3signer, clef, kms proxy...), envs hold the addresses
+    Remote,
+}
+
+impl FromStr for SignerMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode {
+            "Local" => Ok(SignerMode::Local),
+            "Remote" => Ok(SignerMode::Remote),
+            _ => Err(format!("invalid SIGNER_MODE {:?}", mode)),
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum WalletSignerError {
+    #[error(transparent)]
+    Local(#[from] WalletError),
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+    #[error("remote signer: {0}")]
+    Remote(String),
+}
+
+/// Signs a tx into its raw rlp encoding
+pub trait TxSigner {
+    type Error;
+
+    fn sign_tx(
+        &self,
+        tx: &TypedTransaction,
+    ) -> impl Future<Output = Result<Bytes, Self::Error>> + Send;
+}
+
+impl<S: Signer> TxSigner for S {
+    type Error = S::Error;
+
+    async fn sign_tx(&self, tx: &TypedTransaction) -> Result<Bytes, Self::Error> {
+        let signature = self.sign_transaction(tx).await?;
+        Ok(tx.rlp_signed(&signature))
+    }
+}
+
+/// Delegates signing to a json-rpc signer (`eth_signTransaction` / `eth_sign`), the key never enters the process
+#[derive(Debug, Clone)]
+pub struct RemoteSigner {
+    provider: Provider<Http>,
+    address: Address,
+    chain_id: u64,
+}
+
+impl RemoteSigner {
+    pub fn new(provider: Provider<Http>, address: Address) -> Self {
+        Self {
+            provider,
+            address,
+            chain_id: 1,
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for RemoteSigner {
+    type Error = WalletSignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        let message = Bytes::from(message.as_ref().to_vec());
+        let signature: Bytes = self
+            .provider
+            .request("eth_sign", (self.address, message))
+            .await?;
+        Signature::try_from(signature.as_ref())
+            .map_err(|err| WalletSignerError::Remote(err.to_string()))
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        let mut tx = tx.clone();
+        tx.set_from(self.address);
+        tx.set_chain_id(self.chain_id);
+        // the chain id of a tx request is not serialized by ethers, the signer needs it for the replay protection
+        let mut tx_request =
+            serde_json::to_value(&tx).map_err(|err| WalletSignerError::Remote(err.to_string()))?;
+        tx_request["chainId"] = serde_json::to_value(U64::from(self.chain_id))
+            .map_err(|err| WalletSignerError::Remote(err.to_string()))?;
+        let signed_tx: Bytes = self
+            .provider
+            .request("eth_signTransaction", [tx_request])
+            .await?;
+        let (_, signature) = TypedTransaction::decode_signed(&Rlp::new(&signed_tx))
+            .map_err(|err| WalletSignerError::Remote(err.to_string()))?;
+        Ok(signature)
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        _payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        Err(WalletSignerError::Remote(
+            "typed data signing not supported".to_string(),
+        ))
+    }
+
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    fn with_chain_id<T: Into<u64>>(mut self, chain_id: T) -> Self {
+        self.chain_id = chain_id.into();
+        self
+    }
+}
+
+/// The wallet every service signs with, local by default
+#[derive(Debug, Clone)]
+pub enum WalletSigner {
+    Local(LocalWallet),
+    Remote(RemoteSigner),
+}
+
+#[async_trait]
+impl Signer for WalletSigner {
+    type Error = WalletSignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> Result<Signature, Self::Error> {
+        match self {
+            WalletSigner::Local(wallet) => Ok(wallet.sign_message(message).await?),
+            WalletSigner::Remote(signer) => signer.sign_message(message).await,
+        }
+    }
+
+    async fn sign_transaction(&self, tx: &TypedTransaction) -> Result<Signature, Self::Error> {
+        match self {
+            WalletSigner::Local(wallet) => Ok(wallet.sign_transaction(tx).await?),
+            WalletSigner::Remote(signer) => signer.sign_transaction(tx).await,
+        }
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> Result<Signature, Self::Error> {
+        match self {
+            WalletSigner::Local(wallet) => Ok(wallet.sign_typed_data(payload).await?),
+            WalletSigner::Remote(signer) => signer.sign_typed_data(payload).await,
+        }
+    }
+
+    fn address(&self) -> Address {
+        match self {
+            WalletSigner::Local(wallet) => wallet.address(),
+            WalletSigner::Remote(signer) => signer.address(),
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        match self {
+            WalletSigner::Local(wallet) => wallet.chain_id(),
+            WalletSigner::Remote(signer) => signer.chain_id(),
+        }
+    }
+
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match self {
+            WalletSigner::Local(wallet) => WalletSigner::Local(wallet.with_chain_id(chain_id)),
+            WalletSigner::Remote(signer) => WalletSigner::Remote(signer.with_chain_id(chain_id)),
+        }
+    }
+}
+
+pub fn get_signer_mode() -> SignerMode {
+    get_env("SIGNER_MODE", Some("Local".to_string()))
+        .parse()
+        .unwrap()
+}
+
+/// In remote mode `address` is one of the addresses held by `REMOTE_SIGNER_URL`
+pub fn load_remote_signer(address: &str) -> Result<WalletSigner, WalletSignerError> {
+    let provider = Provider::<Http>::try_from(get_env("REMOTE_SIGNER_URL", None))
+        .map_err(|err| WalletSignerError::Remote(err.to_string()))?;
+    let address = Address::from_str(address.trim())
+        .map_err(|err| WalletSignerError::Remote(format!("{:?}: {}", address, err)))?;
+    Ok(WalletSigner::Remote(RemoteSigner::new(provider, address)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::to_signed_tx;
+    use ethers::types::{Eip1559TransactionRequest, U256};
+    use std::sync::{Arc, Mutex};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// json-rpc signer answering every request with `response` (the `result` or `error` member),
+    /// the request bodies are kept
+    async fn serve_remote_signer(response: serde_json::Value) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let received = requests.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 4096];
+                // headers, then a body of content-length bytes
+                let body = loop {
+                    let read = stream.read(&mut buffer).await.unwrap();
+                    request.extend_from_slice(&buffer[..read]);
+                    let request = String::from_utf8_lossy(&request).to_string();
+                    let Some((headers, body)) = request.split_once("\r\n\r\n") else {
+                        continue;
+                    };
+                    let content_length: usize = headers
+                        .lines()
+                        .find_map(|line| {
+                            let (name, value) = line.split_once(':')?;
+                            name.eq_ignore_ascii_case("content-length")
+                                .then(|| value.trim().parse().unwrap())
+                        })
+                        .unwrap_or(0);
+                    if body.len() >= content_length {
+                        break body.to_string();
+                    }
+                };
+                let id = serde_json::from_str::<serde_json::Value>(&body).unwrap()["id"].clone();
+                received.lock().unwrap().push(body);
+
+                let mut reply = serde_json::json!({ "jsonrpc": "2.0", "id": id });
+                for (key, value) in response.as_object().unwrap() {
+                    reply[key] = value.clone();
+                }
+                let reply = reply.to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    reply.len(),
+                    reply
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests)
+    }
+
+    fn remote_wallet(url: &str, address: Address) -> WalletSigner {
+        let provider = Provider::<Http>::try_from(url).unwrap();
+        WalletSigner::Remote(RemoteSigner::new(provider, address)).with_chain_id(8453u64)
+    }
+
+    #[test]
+    fn signer_mode_from_env_value() {
+        assert_eq!("Local".parse::<SignerMode>(), Ok(SignerMode::Local));
+        assert_eq!("Remote".parse::<SignerMode>(), Ok(SignerMode::Remote));
+        assert!("remote".parse::<SignerMode>().is_err());
+    }
+
+    #[tokio::test]
+    async fn remote_signed_tx_matches_the_local_one() {
+        // the anvil / hardhat first account
+        let local_wallet: LocalWallet =
+            "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+                .parse::<LocalWallet>()
+                .unwrap()
+                .with_chain_id(8453u64);
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .from(local_wallet.address())
+            .to(Address::random())
+            .value(U256::exp10(15))
+            .nonce(7)
+            .gas(21_000)
+            .max_fee_per_gas(U256::exp10(9))
+            .max_priority_fee_per_gas(U256::exp10(8))
+            .chain_id(8453u64)
+            .into();
+        let raw_tx = local_wallet.sign_tx(&tx).await.unwrap();
+
+        let (url, requests) = serve_remote_signer(serde_json::json!({ "result": raw_tx })).await;
+        let remote_wallet = remote_wallet(&url, local_wallet.address());
+        assert_eq!(to_signed_tx(&remote_wallet, &tx).await.unwrap(), raw_tx);
+
+        // the remote signer is asked for the tx of the wallet, on the chain of the wallet
+        let request: serde_json::Value =
+            serde_json::from_str(&requests.lock().unwrap()[0]).unwrap();
+        assert_eq!(request["method"], "eth_signTransaction");
+        assert_eq!(
+            request["params"][0]["from"],
+            serde_json::json!(local_wallet.address())
+        );
+        assert_eq!(request["params"][0]["chainId"], "0x2105");
+    }
+
+    #[tokio::test]
+    async fn remote_signer_rejections_are_errors() {
+        let tx: TypedTransaction = Eip1559TransactionRequest::new()
+            .to(Address::random())
+            .chain_id(8453u64)
+            .into();
+
+        // locked account: the json-rpc error of the signer
+        let (url, _) = serve_remote_signer(serde_json::json!({
+            "error": { "code": -32000, "message": "authentication needed: password or unlock" }
+        }))
+        .await;
+        let err = to_signed_tx(&remote_wallet(&url, Address::random()), &tx)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, WalletSignerError::Provider(_)));
+        assert!(err.to_string().contains("authentication needed"));
+
+        // an answer that is not a signed tx
+        let (url, _) = serve_remote_signer(serde_json::json!({ "result": "0x1234" })).await;
+        let err = to_signed_tx(&remote_wallet(&url, Address::random()), &tx)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, WalletSignerError::Remote(_)));
+    }
+}
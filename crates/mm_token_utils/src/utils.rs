@@ -4,15 +4,22 @@ use ::serde::{Deserialize, Serialize};
 use bip39::{Language, Mnemonic, MnemonicType};
 use ethers::{
     abi::{ethabi, ParamType, Token, Tokenizable},
-    signers::{coins_bip39::English, LocalWallet, MnemonicBuilder, Signer, WalletError},
+    signers::{coins_bip39::English, LocalWallet, MnemonicBuilder},
     types::{
-        transaction::eip2718::TypedTransaction, Address, Bytes, TransactionRequest, H160, U256,
+        transaction::eip2718::TypedTransaction, Address, Bytes, Eip1559TransactionRequest,
+        TransactionRequest, H160, U256,
     },
     utils::keccak256,
 };
 use provider_utils::enums::ENetwork;
 
-use crate::constants::{V2_SWAP_EXACT_IN, V2_SWAP_EXACT_OUT, V3_SWAP_EXACT_IN, V3_SWAP_EXACT_OUT};
+use crate::constants::{
+    EGasMode, V2_SWAP_EXACT_IN, V2_SWAP_EXACT_OUT, V3_SWAP_EXACT_IN, V3_SWAP_EXACT_OUT,
+};
+use crate::env::get_env;
+use crate::signer::{
+    get_signer_mode, load_remote_signer, SignerMode, TxSigner, WalletSigner, WalletSignerError,
+};
 
 pub fn compute_transaction_hash(raw_tx: &Bytes) -> String {
     format!("0x{}", hex::encode(keccak256(raw_tx)))
@@ -28,21 +35,112 @@ pub fn to_legacy_tx(tx: TypedTransaction) -> TypedTransaction {
     }
 }
 
-pub async fn to_signed_tx(
-    wallet: &LocalWallet,
+pub fn to_eip1559_tx(
+    tx: TypedTransaction,
+    max_fee: U256,
+    max_priority_fee: U256,
+) -> TypedTransaction {
+    let tx = match tx {
+        TypedTransaction::Legacy(inner) => legacy_to_eip1559(inner),
+        TypedTransaction::Eip2930(inner) => legacy_to_eip1559(inner.tx),
+        TypedTransaction::Eip1559(inner) => inner,
+    };
+    TypedTransaction::Eip1559(
+        tx.max_fee_per_gas(max_fee)
+            .max_priority_fee_per_gas(max_priority_fee),
+    )
+}
+
+fn legacy_to_eip1559(tx: TransactionRequest) -> Eip1559TransactionRequest {
+    Eip1559TransactionRequest {
+        from: tx.from,
+        to: tx.to,
+        gas: tx.gas,
+        value: tx.value,
+        data: tx.data,
+        nonce: tx.nonce,
+        chain_id: tx.chain_id,
+        ..Default::default()
+    }
+}
+
+pub fn get_gas_mode() -> EGasMode {
+    get_env("GAS_MODE", Some("Legacy".to_string()))
+        .parse()
+        .unwrap()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Eip1559Fees {
+    // base fee of the next block
+    pub base_fee: U256,
+    pub priority_fee: U256,
+}
+
+impl Eip1559Fees {
+    /// room for the base fee to double before the tx stops being includable
+    pub fn max_fee(&self) -> U256 {
+        self.base_fee * 2 + self.priority_fee
+    }
+}
+
+/// `eth_feeHistory` result: the last base fee is the next block's, the tip is the median of the blocks' rewards
+pub fn compute_eip1559_fees(base_fee_per_gas: &[U256], rewards: &[Vec<U256>]) -> Eip1559Fees {
+    let mut tips: Vec<U256> = rewards
+        .iter()
+        .filter_map(|reward| reward.first().copied())
+        .collect();
+    tips.sort();
+
+    Eip1559Fees {
+        base_fee: base_fee_per_gas.last().copied().unwrap_or_default(),
+        priority_fee: tips.get(tips.len() / 2).copied().unwrap_or_default(),
+    }
+}
+
+pub async fn to_signed_tx<S: TxSigner>(
+    wallet: &S,
     tx: &TypedTransaction,
-) -> Result<Bytes, WalletError> {
-    let signature = wallet.sign_transaction(tx).await?;
-    let signed = tx.rlp_signed(&signature);
-    Ok(signed)
+) -> Result<Bytes, S::Error> {
+    wallet.sign_tx(tx).await
 }
 
-pub fn load_mnemonic_wallet(mnemonic: &str, index: u32) -> Result<LocalWallet, WalletError> {
+/// The wallet at `{path_prefix}{index}`, `path_prefix` defaults to the one of `network` (`get_wallet_path_prefix`)
+/// With `SIGNER_MODE=Remote`, the mnemonic is the comma separated addresses held by the remote signer
+pub fn load_mnemonic_wallet(
+    mnemonic: &str,
+    index: u32,
+    network: ENetwork,
+    path_prefix: Option<&str>,
+) -> Result<WalletSigner, WalletSignerError> {
+    if get_signer_mode() == SignerMode::Remote {
+        let Some(address) = mnemonic.split(',').nth(index as usize) else {
+            return Err(WalletSignerError::Remote(format!(
+                "no address at index {:?}",
+                index
+            )));
+        };
+        return load_remote_signer(address);
+    }
+
+    let path_prefix = path_prefix
+        .map(str::to_string)
+        .unwrap_or_else(|| get_wallet_path_prefix(network));
     let wallet = MnemonicBuilder::<English>::default()
         .phrase(mnemonic)
-        .index(index)?
+        .derivation_path(&format!("{}{}", path_prefix, index))?
         .build()?;
-    Ok(wallet)
+    Ok(WalletSigner::Local(wallet))
+}
+
+/// With `SIGNER_MODE=Remote`, the private key is the address held by the remote signer
+pub fn load_private_key_wallet(private_key: &str) -> Result<WalletSigner, WalletSignerError> {
+    if get_signer_mode() == SignerMode::Remote {
+        return load_remote_signer(private_key);
+    }
+
+    let wallet = private_key.parse::<LocalWallet>()?;
+    Ok(WalletSigner::Local(wallet))
 }
 
 /// Generate a random 12-words mnemonic phrase
@@ -51,11 +149,11 @@ pub fn random_mnemonic_phrase() -> String {
     mnemonic.into_phrase()
 }
 
+/// The evm chains share the ethereum coin type, the wallets already derived there stay the same
 pub fn get_wallet_path_prefix(network: ENetwork) -> String {
     match network {
-        ENetwork::EthSepolia | ENetwork::EthMainnet => "m/44'/60'/0'/0/".to_string(),
         ENetwork::FtmTestnet | ENetwork::FtmMainnet => "m/44'/214'/0'/0/".to_string(),
-        _ => "m/0'/0'/0'/0/".to_string(),
+        _ => "m/44'/60'/0'/0/".to_string(),
     }
 }
 
@@ -199,6 +297,70 @@ pub fn decode_v3_swap_exact_out(input: Vec<u8>) -> SwapUniversalRouterInfo {
     }
 }
 
+pub fn encode_v2_swap_exact_in(
+    recipient: Address,
+    amount_in: U256,
+    amount_out_min: U256,
+    path: Vec<Address>,
+    payer_is_user: bool,
+) -> Vec<u8> {
+    ethabi::encode(&[
+        Token::Address(recipient),
+        Token::Uint(amount_in),
+        Token::Uint(amount_out_min),
+        Token::Array(path.into_iter().map(Token::Address).collect()),
+        Token::Bool(payer_is_user),
+    ])
+}
+
+pub fn encode_v3_swap_exact_in(
+    recipient: Address,
+    amount_in: U256,
+    amount_out_min: U256,
+    path: Vec<u8>,
+    payer_is_user: bool,
+) -> Vec<u8> {
+    ethabi::encode(&[
+        Token::Address(recipient),
+        Token::Uint(amount_in),
+        Token::Uint(amount_out_min),
+        Token::Bytes(path),
+        Token::Bool(payer_is_user),
+    ])
+}
+
+/// WRAP_ETH and UNWRAP_WETH share the same (recipient, amountMin) input
+pub fn encode_wrap_unwrap(recipient: Address, amount_min: U256) -> Vec<u8> {
+    ethabi::encode(&[Token::Address(recipient), Token::Uint(amount_min)])
+}
+
+/// token, 3 bytes fee, token, the reverse of `extract_path_from_v3`
+pub fn encode_v3_path(token_in: Address, fee: u32, token_out: Address) -> Vec<u8> {
+    encode_v3_multi_hop_path(&[(token_in, fee)], token_out)
+}
+
+/// every hop is (token, fee of the pool to the next token), ends with `token_out`
+pub fn encode_v3_multi_hop_path(hops: &[(Address, u32)], token_out: Address) -> Vec<u8> {
+    let mut path = Vec::with_capacity(hops.len() * 23 + 20);
+    for (token, fee) in hops {
+        path.extend_from_slice(token.as_bytes());
+        path.extend_from_slice(&fee.to_be_bytes()[1..]);
+    }
+    path.extend_from_slice(token_out.as_bytes());
+    path
+}
+
+/// calldata of `execute(bytes commands, bytes[] inputs, uint256 deadline)`
+pub fn encode_universal_execute(commands: Vec<u8>, inputs: Vec<Vec<u8>>, deadline: U256) -> Bytes {
+    let selector = &keccak256("execute(bytes,bytes[],uint256)")[..4];
+    let params = ethabi::encode(&[
+        Token::Bytes(commands),
+        Token::Array(inputs.into_iter().map(Token::Bytes).collect()),
+        Token::Uint(deadline),
+    ]);
+    [selector, &params].concat().into()
+}
+
 pub fn extract_path_from_v3(full_path: Vec<u8>, is_reverse: bool) -> Vec<H160> {
     let mut path = Vec::new();
     let mut current_address = Vec::new();
@@ -220,3 +382,157 @@ pub fn extract_path_from_v3(full_path: Vec<u8>, is_reverse: bool) -> Vec<H160> {
     }
     path
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{UNIVERSAL_ADDRESS_THIS, WRAP_ETH};
+    use ethers::abi::AbiParser;
+
+    fn decode_execute(calldata: &Bytes) -> (Vec<u8>, Vec<Vec<u8>>) {
+        let func = AbiParser::default()
+            .parse_function("function execute(bytes,bytes[],uint256) external payable")
+            .unwrap();
+        assert_eq!(calldata[..4], func.short_signature());
+        let decoded = func.decode_input(&calldata[4..]).unwrap();
+        let commands = decoded[0].clone().into_bytes().unwrap();
+        let inputs = decoded[1]
+            .clone()
+            .into_array()
+            .unwrap()
+            .into_iter()
+            .map(|input| input.into_bytes().unwrap())
+            .collect();
+        (commands, inputs)
+    }
+
+    #[test]
+    fn eip1559_fees_from_fee_history() {
+        let base_fee_per_gas = vec![U256::from(10), U256::from(12), U256::from(14)];
+        let rewards = vec![
+            vec![U256::from(3)],
+            vec![U256::from(1)],
+            vec![U256::from(2)],
+        ];
+        let fees = compute_eip1559_fees(&base_fee_per_gas, &rewards);
+        assert_eq!(fees.base_fee, U256::from(14));
+        assert_eq!(fees.priority_fee, U256::from(2));
+        assert_eq!(fees.max_fee(), U256::from(30));
+
+        let tx = to_eip1559_tx(
+            TransactionRequest::new().gas_price(99).into(),
+            fees.max_fee(),
+            fees.priority_fee,
+        );
+        assert_eq!(tx.gas_price(), Some(U256::from(30)));
+        assert!(matches!(tx, TypedTransaction::Eip1559(_)));
+    }
+
+    #[test]
+    fn universal_v2_swap_round_trip() {
+        let weth = Address::random();
+        let token = Address::random();
+        let amount_in = U256::from(10).pow(18.into());
+        let calldata = encode_universal_execute(
+            vec![WRAP_ETH, V2_SWAP_EXACT_IN],
+            vec![
+                encode_wrap_unwrap(*UNIVERSAL_ADDRESS_THIS, amount_in),
+                encode_v2_swap_exact_in(
+                    Address::random(),
+                    amount_in,
+                    U256::one(),
+                    vec![weth, token],
+                    false,
+                ),
+            ],
+            U256::from(1_700_000_000),
+        );
+
+        let (commands, inputs) = decode_execute(&calldata);
+        assert_eq!(commands, vec![WRAP_ETH, V2_SWAP_EXACT_IN]);
+        let swap_info = universal_decode(commands[1], inputs[1].clone());
+        assert_eq!(swap_info.amount_in, amount_in);
+        assert_eq!(swap_info.path, vec![weth, token]);
+    }
+
+    #[test]
+    fn universal_v3_swap_round_trip() {
+        let weth = Address::random();
+        let token = Address::random();
+        let amount_in = U256::from(12345);
+        let calldata = encode_universal_execute(
+            vec![V3_SWAP_EXACT_IN],
+            vec![encode_v3_swap_exact_in(
+                Address::random(),
+                amount_in,
+                U256::zero(),
+                encode_v3_path(token, 3000, weth),
+                true,
+            )],
+            U256::from(1_700_000_000),
+        );
+
+        let (commands, inputs) = decode_execute(&calldata);
+        let swap_info = universal_decode(commands[0], inputs[0].clone());
+        assert_eq!(swap_info.amount_in, amount_in);
+        assert_eq!(swap_info.path, vec![token, weth]);
+    }
+
+    #[test]
+    fn v3_multi_hop_path_round_trip() {
+        let weth = Address::random();
+        let usdc = Address::random();
+        let token = Address::random();
+
+        let path = encode_v3_multi_hop_path(&[(weth, 500), (usdc, 10000)], token);
+        assert_eq!(path.len(), 66);
+        assert_eq!(&path[20..23], &[0x00, 0x01, 0xf4]); // 500
+        assert_eq!(&path[43..46], &[0x00, 0x27, 0x10]); // 10000
+        assert_eq!(
+            extract_path_from_v3(path.clone(), false),
+            vec![weth, usdc, token]
+        );
+        assert_eq!(extract_path_from_v3(path, true), vec![token, usdc, weth]);
+
+        assert_eq!(
+            encode_v3_multi_hop_path(&[(weth, 3000)], token),
+            encode_v3_path(weth, 3000, token)
+        );
+    }
+
+    #[test]
+    fn mnemonic_wallets_follow_the_network_path_prefix() {
+        use ethers::signers::Signer;
+
+        // the anvil / hardhat default accounts
+        let mnemonic = "test test test test test test test test test test test junk";
+        let address = |index, network, path_prefix| {
+            load_mnemonic_wallet(mnemonic, index, network, path_prefix)
+                .unwrap()
+                .address()
+        };
+        let first_account: Address = "0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266"
+            .parse()
+            .unwrap();
+        let second_account: Address = "0x70997970C51812dc3A010C7d01b50e0d17dc79C8"
+            .parse()
+            .unwrap();
+        assert_eq!(address(0, ENetwork::BaseMainnet, None), first_account);
+        assert_eq!(address(1, ENetwork::EthMainnet, None), second_account);
+        // same wallets as the mnemonic builder default path
+        let default_path_wallet = MnemonicBuilder::<English>::default()
+            .phrase(mnemonic)
+            .index(1u32)
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(default_path_wallet.address(), second_account);
+
+        let fantom_address = address(0, ENetwork::FtmMainnet, None);
+        assert_ne!(fantom_address, first_account);
+        assert_eq!(
+            address(0, ENetwork::BaseMainnet, Some("m/44'/214'/0'/0/")),
+            fantom_address
+        );
+    }
+}
@@ -1,2 +1,13 @@
 pub const DESERIALIZATION_ERROR_MSG: &str =
     "Deserialization Error: expected value at line 1 column 1";
+
+// send_raw_transaction_with_retry defaults, the delay doubles on every attempt
+pub const SEND_TX_MAX_ATTEMPTS: u32 = 3;
+pub const SEND_TX_BASE_DELAY_MS: u64 = 200;
+
+// pending txs buffered between the mempool source and its consumer
+pub const MEMPOOL_STREAM_SIZE: usize = 128;
+
+// resilient ws subscriptions wait this long before resubscribing, doubled on every failed attempt
+pub const WS_RECONNECT_BASE_DELAY_MS: u64 = 1_000;
+pub const WS_RECONNECT_MAX_DELAY_MS: u64 = 60_000;
@@ -29,3 +29,28 @@ pub enum ENetwork {
     FtmTestnet,
     FtmMainnet,
 }
+
+/// How the shared provider index is refreshed, `PROVIDER_STRATEGY`
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, EnumString, Serialize, Deserialize)]
+pub enum EProviderStrategy {
+    /// next reachable provider on every refresh
+    RoundRobin,
+    /// random reachable provider, picked proportionally to `PROVIDER_WEIGHTS`
+    Weighted,
+    /// reachable provider with the lowest `eth_blockNumber` latency
+    #[default]
+    LeastLatency,
+}
+
+impl ENetwork {
+    /// base and blast nodes do not stream pending txs, their mempool is polled instead
+    pub fn is_pending_tx_subscription_supported(&self) -> bool {
+        !matches!(
+            self,
+            ENetwork::BlastMainnet
+                | ENetwork::BlastSepolia
+                | ENetwork::BaseMainnet
+                | ENetwork::BaseSepolia
+        )
+    }
+}
@@ -1,11 +1,18 @@
 use anyhow::{anyhow, Error};
-use ethers::providers::{Http, Middleware, Provider, StreamExt};
+use ethers::{
+    providers::{Http, Middleware, Provider, StreamExt},
+    types::U64,
+};
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
 use std::{
+    fmt::Display,
+    future::Future,
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
 use tokio::{
     sync::RwLock,
@@ -13,7 +20,26 @@ use tokio::{
 };
 use tokio_stream::wrappers::IntervalStream;
 
-use crate::{constants::DESERIALIZATION_ERROR_MSG, enums::ENetwork, networks::NETWORKS};
+use crate::{
+    constants::DESERIALIZATION_ERROR_MSG, enums::ENetwork, networks::NETWORKS,
+    provider_pool::ProviderPool,
+};
+
+// a provider slower than this counts as unreachable
+const PROVIDER_PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// every provider is probed with one eth_blockNumber per interval
+const DEFAULT_PROVIDER_PROBE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Result of one `eth_blockNumber` probe, `url` is the origin only so api keys in the path stay hidden
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderHealth {
+    pub url: String,
+    pub reachable: bool,
+    pub latency_ms: u64,
+    pub block_number: Option<u64>,
+}
 
 pub struct HttpProviders;
 
@@ -97,41 +123,91 @@ impl HttpProviders {
         Err(anyhow!("All providers failed to retrieve the block number"))
     }
 
+    /// The lowest latency reachable provider
     pub async fn init_provider_index(
         network: &ENetwork,
         is_external_rpc: bool,
     ) -> anyhow::Result<usize, Error> {
+        let provider_healths = Self::probe_all(network, is_external_rpc).await;
+        for provider_health in provider_healths.iter().filter(|health| !health.reachable) {
+            log::info!("Provider {:?} is unavailable !!", provider_health.url);
+        }
+
+        Self::fastest_provider_index(&provider_healths)
+            .ok_or(anyhow!("All providers failed to retrieve the block number"))
+    }
+
+    /// Probe every provider of the network concurrently, in the order of the network config
+    pub async fn probe_all(network: &ENetwork, is_external_rpc: bool) -> Vec<ProviderHealth> {
         let providers = Self::get_providers(network, is_external_rpc).unwrap();
 
-        for (index, provider) in providers.iter().enumerate() {
-            match provider.get_block_number().await {
-                Ok(_) => {
-                    return Ok(index);
-                }
-                Err(err) => {
-                    let err_string = err.to_string();
-                    if err_string.contains("failed to lookup address information: nodename nor servname provided, or not known")
-                        || err_string.contains(DESERIALIZATION_ERROR_MSG) {
-                        log::info!("Provider {:?} is unavailable !!", provider.url().host());
-                        continue;
-                    }
-                    return Err(err.into());
-                }
+        Self::probe_providers(&providers).await
+    }
+
+    /// Probe the given providers concurrently, in order
+    pub async fn probe_providers(providers: &[Provider<Http>]) -> Vec<ProviderHealth> {
+        join_all(providers.iter().map(|provider| {
+            let url = provider.url();
+            Self::probe(
+                format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default()),
+                provider.get_block_number(),
+                PROVIDER_PROBE_TIMEOUT,
+            )
+        }))
+        .await
+    }
+
+    /// Time `block_number`, a failure or a timeout is unreachable
+    pub async fn probe<E: Display>(
+        url: String,
+        block_number: impl Future<Output = Result<U64, E>>,
+        probe_timeout: Duration,
+    ) -> ProviderHealth {
+        let started_at = Instant::now();
+        let block_number = match timeout(probe_timeout, block_number).await {
+            Ok(Ok(block_number)) => Some(block_number.as_u64()),
+            Ok(Err(err)) => {
+                log::debug!("[HttpProviders.probe] {:?} failed: {}", url, err);
+                None
             }
+            Err(_) => {
+                log::debug!("[HttpProviders.probe] {:?} timed out", url);
+                None
+            }
+        };
+
+        ProviderHealth {
+            url,
+            reachable: block_number.is_some(),
+            latency_ms: started_at.elapsed().as_millis() as u64,
+            block_number,
         }
+    }
 
-        Err(anyhow!("All providers failed to retrieve the block number"))
+    pub fn fastest_provider_index(provider_healths: &[ProviderHealth]) -> Option<usize> {
+        provider_healths
+            .iter()
+            .enumerate()
+            .filter(|(_, health)| health.reachable)
+            .min_by_key(|(_, health)| health.latency_ms)
+            .map(|(index, _)| index)
     }
 
-    // Update the provider index
+    // Update the provider index by the PROVIDER_STRATEGY of the pool every PROVIDER_PROBE_INTERVAL_SECS
     pub async fn fetch_periodically(
-        network: ENetwork,
-        is_external_rpc: bool,
         exit: Option<Arc<AtomicBool>>,
-        provider_index: Arc<RwLock<usize>>,
+        provider_pool: ProviderPool,
     ) -> anyhow::Result<()> {
-        let mut stream = IntervalStream::new(time::interval(Duration::from_millis(500)));
-        let providers = Self::get_providers(&network, is_external_rpc).unwrap();
+        let probe_interval =
+            std::env::var("PROVIDER_PROBE_INTERVAL_SECS")
+                .ok()
+                .filter(|secs| !secs.is_empty())
+                .map_or(DEFAULT_PROVIDER_PROBE_INTERVAL, |secs| {
+                    Duration::from_secs(secs.parse().unwrap_or_else(|_| {
+                        panic!("PROVIDER_PROBE_INTERVAL_SECS {:?} invalid", secs)
+                    }))
+                });
+        let mut stream = IntervalStream::new(time::interval(probe_interval));
         loop {
             if let Some(exit) = &exit {
                 if exit.load(Ordering::Relaxed) {
@@ -143,34 +219,72 @@ impl HttpProviders {
                 continue;
             };
 
-            for (index, provider) in providers.iter().enumerate() {
-                match provider.get_block_number().await {
-                    Ok(_) => {
-                        let mut provider_index = provider_index.write().await;
-                        *provider_index = index;
-                        drop(provider_index);
-                        break;
-                    }
-                    Err(err) => {
-                        let err_string = err.to_string();
-                        if err_string.contains("failed to lookup address information: nodename nor servname provided, or not known")
-                        || err_string.contains(DESERIALIZATION_ERROR_MSG) {
-                            log::info!("Provider {:?} is down !!", provider.url().host());
-                            if index == providers.len() - 1 {
-                                if let Some(exit) = &exit {
-                                    log::info!("All Providers are down !!");
-                                    exit.store(true, Ordering::Relaxed);
-                                }
-                            }
-                            continue;
-                        }
-                        if let Some(exit) = &exit {
-                            exit.store(true, Ordering::Relaxed);
-                        }
-                        return Err(err.into());
-                    }
-                }
+            if provider_pool.refresh().await.is_some() {
+                continue;
+            }
+            for provider_health in provider_pool.provider_healths().await {
+                log::info!("Provider {:?} is down !!", provider_health.url);
             }
+            if let Some(exit) = &exit {
+                log::info!("All Providers are down !!");
+                exit.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_rpc::MockRpc;
+
+    async fn probe_delayed(delay_ms: u64, block_number: Option<u64>) -> ProviderHealth {
+        let mock_rpc = MockRpc::new();
+        match block_number {
+            Some(block_number) => {
+                mock_rpc.push_responses("eth_blockNumber", [U64::from(block_number)])
+            }
+            None => mock_rpc.push_error("eth_blockNumber", -32000, "rpc unavailable"),
         }
+        let provider = mock_rpc.provider();
+        HttpProviders::probe(
+            format!("http://rpc-{}", delay_ms),
+            async move {
+                tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                provider.get_block_number().await
+            },
+            Duration::from_millis(200),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn picks_fastest_reachable_provider() {
+        let provider_healths = join_all([
+            probe_delayed(120, Some(100)),
+            // the rpc fails
+            probe_delayed(0, None),
+            probe_delayed(30, Some(101)),
+            probe_delayed(500, Some(102)),
+        ])
+        .await;
+
+        assert!(provider_healths[0].reachable);
+        assert_eq!(provider_healths[0].block_number, Some(100));
+        assert!(!provider_healths[1].reachable);
+        assert_eq!(provider_healths[2].block_number, Some(101));
+        assert!(provider_healths[2].latency_ms < provider_healths[0].latency_ms);
+        // slower than the probe timeout
+        assert!(!provider_healths[3].reachable);
+        assert_eq!(provider_healths[3].block_number, None);
+
+        assert_eq!(
+            HttpProviders::fastest_provider_index(&provider_healths),
+            Some(2)
+        );
+        assert_eq!(
+            HttpProviders::fastest_provider_index(&provider_healths[1..2]),
+            None
+        );
     }
 }
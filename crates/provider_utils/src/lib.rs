@@ -1,5 +1,11 @@
 pub mod constants;
 pub mod enums;
 pub mod http_providers;
+#[cfg(any(test, feature = "mock"))]
+pub mod mock_rpc;
 pub mod networks;
+pub mod polling_mempool;
+pub mod provider_pool;
+pub mod send_transaction;
+pub mod tx_error;
 pub mod ws_providers;
@@ -0,0 +1,302 @@
+use async_trait::async_trait;
+use ethers::{
+    abi::{encode, Token},
+    providers::{JsonRpcClient, JsonRpcError, MockError, Provider},
+    types::{Address, Block, Bytes, FeeHistory, Transaction, TransactionReceipt, H256, U256, U64},
+    utils::{hex, id, parse_units, rlp::Rlp},
+};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+// json-rpc error code of an `eth_call` revert
+const REVERT_ERROR_CODE: i64 = 3;
+const METHOD_NOT_FOUND_ERROR_CODE: i64 = -32601;
+
+/**
+ * json-rpc node of the tests, in process through `provider()` or served over http by the tests of
+ * the http bound services. Responses pushed for a method are served first, in the order they were pushed.
+ * Otherwise an `eth_call` matches its exact calldata first, then its selector, everything else reverts.
+ * Raw txs are accepted and mined right away with status 1 (but the `with_pending_txs` ones), every wallet holds the same eth balance,
+ * the latest block and the fee history price EIP-1559 txs at the gas price
+ */
+#[derive(Debug, Clone, Default)]
+pub struct MockRpc {
+    state: Arc<Mutex<MockRpcState>>,
+}
+
+#[derive(Debug, Default)]
+struct MockRpcState {
+    calls: HashMap<String, Bytes>,
+    selector_calls: HashMap<String, Bytes>,
+    responses: HashMap<String, VecDeque<Result<Value, JsonRpcError>>>,
+    requests: Vec<(String, Value)>,
+    chain_id: u64,
+    block_number: u64,
+    gas_price: U256,
+    eth_balance: U256,
+    sent_txs: Vec<Transaction>,
+    pending_tx_count: usize,
+}
+
+impl MockRpc {
+    pub fn new() -> Self {
+        let mock_rpc = Self::default();
+        {
+            let mut state = mock_rpc.state.lock().unwrap();
+            state.chain_id = 1;
+            state.block_number = 100;
+            state.gas_price = parse_units(1, "gwei").unwrap().into();
+        }
+        mock_rpc
+    }
+
+    /// in process provider, polling pending txs every 10ms
+    pub fn provider(&self) -> Provider<MockRpc> {
+        Provider::new(self.clone()).interval(Duration::from_millis(10))
+    }
+
+    pub fn with_chain_id(self, chain_id: u64) -> Self {
+        self.state.lock().unwrap().chain_id = chain_id;
+        self
+    }
+
+    /// eth balance of every wallet
+    pub fn with_eth_balance(self, eth_balance: U256) -> Self {
+        self.state.lock().unwrap().eth_balance = eth_balance;
+        self
+    }
+
+    /// the next `count` raw txs stay pending, they have no block and no receipt
+    pub fn with_pending_txs(self, count: usize) -> Self {
+        self.state.lock().unwrap().pending_tx_count = count;
+        self
+    }
+
+    /// `signature` is the solidity signature, e.g. `getPair(address,address)`
+    pub fn on_call(self, to: Address, signature: &str, args: &[Token], output: &[Token]) -> Self {
+        let calldata = [id(signature).to_vec(), encode(args)].concat();
+        self.state
+            .lock()
+            .unwrap()
+            .calls
+            .insert(call_key(to, &calldata), Bytes::from(encode(output)));
+        self
+    }
+
+    /// any arguments of `signature` on `to`
+    pub fn on_any_call(self, to: Address, signature: &str, output: &[Token]) -> Self {
+        self.state
+            .lock()
+            .unwrap()
+            .selector_calls
+            .insert(call_key(to, &id(signature)), Bytes::from(encode(output)));
+        self
+    }
+
+    /// answers of the next `method` requests, in order
+    pub fn push_responses<T: Serialize>(
+        &self,
+        method: &str,
+        responses: impl IntoIterator<Item = T>,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        let queue = state.responses.entry(method.to_string()).or_default();
+        for response in responses {
+            queue.push_back(Ok(serde_json::to_value(response).unwrap()));
+        }
+    }
+
+    /// outputs of the next `eth_call` requests that match no call, in order
+    pub fn push_call_outputs(&self, outputs: impl IntoIterator<Item = Vec<Token>>) {
+        self.push_responses(
+            "eth_call",
+            outputs
+                .into_iter()
+                .map(|output| Bytes::from(encode(&output))),
+        );
+    }
+
+    /// the next `method` request fails with `code` and `message`
+    pub fn push_error(&self, method: &str, code: i64, message: &str) {
+        self.push_json_rpc_error(
+            method,
+            JsonRpcError {
+                code,
+                message: message.to_string(),
+                data: None,
+            },
+        );
+    }
+
+    /// the next `method` request fails with `err`, e.g. a revert carrying its data
+    pub fn push_json_rpc_error(&self, method: &str, err: JsonRpcError) {
+        self.state
+            .lock()
+            .unwrap()
+            .responses
+            .entry(method.to_string())
+            .or_default()
+            .push_back(Err(err));
+    }
+
+    /// params of every `method` request received, in order
+    pub fn requests(&self, method: &str) -> Vec<Value> {
+        self.state
+            .lock()
+            .unwrap()
+            .requests
+            .iter()
+            .filter(|(request_method, _)| request_method == method)
+            .map(|(_, params)| params.clone())
+            .collect()
+    }
+
+    /// txs sent through `eth_sendRawTransaction`, in order
+    pub fn sent_txs(&self) -> Vec<Transaction> {
+        self.state.lock().unwrap().sent_txs.clone()
+    }
+
+    /// answer of a json-rpc request
+    pub fn handle(&self, method: &str, params: Value) -> Result<Value, JsonRpcError> {
+        let mut state = self.state.lock().unwrap();
+        state.requests.push((method.to_string(), params.clone()));
+        if let Some(response) = state
+            .responses
+            .get_mut(method)
+            .and_then(|queue| queue.pop_front())
+        {
+            return response;
+        }
+
+        let result = match method {
+            "eth_blockNumber" => Some(json!(U64::from(state.block_number))),
+            "eth_chainId" => Some(json!(U64::from(state.chain_id))),
+            "eth_gasPrice" => Some(json!(state.gas_price)),
+            "eth_getBlockByNumber" => Some(json!(Block::<H256> {
+                number: Some(U64::from(state.block_number)),
+                base_fee_per_gas: Some(state.gas_price),
+                ..Default::default()
+            })),
+            "eth_feeHistory" => Some(json!(FeeHistory {
+                base_fee_per_gas: vec![state.gas_price, state.gas_price],
+                gas_used_ratio: vec![0.5],
+                oldest_block: U256::from(state.block_number),
+                reward: vec![vec![state.gas_price / 10]],
+            })),
+            "eth_getBalance" => Some(json!(state.eth_balance)),
+            "eth_getTransactionCount" => state.nonce(&params[0]).map(|nonce| json!(nonce)),
+            "eth_call" => state
+                .eth_call(&params[0])
+                .map(|output| serde_json::to_value(output).unwrap()),
+            "eth_sendRawTransaction" => state
+                .send_raw_transaction(&params[0])
+                .map(|tx_hash| json!(tx_hash)),
+            "eth_getTransactionByHash" => state
+                .sent_tx(&params[0])
+                .map(|tx| serde_json::to_value(tx).unwrap()),
+            "eth_getTransactionReceipt" => state.sent_tx(&params[0]).map(|tx| {
+                if tx.block_number.is_none() {
+                    return Value::Null;
+                }
+                serde_json::to_value(TransactionReceipt {
+                    transaction_hash: tx.hash,
+                    transaction_index: tx.transaction_index.unwrap(),
+                    block_hash: tx.block_hash,
+                    block_number: tx.block_number,
+                    from: tx.from,
+                    to: tx.to,
+                    gas_used: Some(U256::from(21_000)),
+                    effective_gas_price: tx.gas_price,
+                    status: Some(U64::one()),
+                    ..Default::default()
+                })
+                .unwrap()
+            }),
+            "eth_estimateGas" => None,
+            _ => {
+                return Err(JsonRpcError {
+                    code: METHOD_NOT_FOUND_ERROR_CODE,
+                    message: format!("the method {} does not exist/is not available", method),
+                    data: None,
+                })
+            }
+        };
+
+        result.ok_or_else(|| JsonRpcError {
+            code: REVERT_ERROR_CODE,
+            message: "execution reverted".to_string(),
+            data: None,
+        })
+    }
+}
+
+impl MockRpcState {
+    fn eth_call(&self, call: &Value) -> Option<Bytes> {
+        let to: Address = serde_json::from_value(call["to"].clone()).ok()?;
+        let data = call["data"].as_str().or(call["input"].as_str())?;
+        let calldata = hex::decode(data.trim_start_matches("0x")).ok()?;
+        self.calls
+            .get(&call_key(to, &calldata))
+            .or_else(|| {
+                calldata
+                    .get(..4)
+                    .and_then(|selector| self.selector_calls.get(&call_key(to, selector)))
+            })
+            .cloned()
+    }
+
+    fn send_raw_transaction(&mut self, raw_tx: &Value) -> Option<H256> {
+        let raw_tx = hex::decode(raw_tx.as_str()?.trim_start_matches("0x")).ok()?;
+        let mut tx: Transaction = Rlp::new(&raw_tx).as_val().ok()?;
+        tx.recover_from_mut().ok()?;
+        if self.pending_tx_count > 0 {
+            self.pending_tx_count -= 1;
+            let tx_hash = tx.hash;
+            self.sent_txs.push(tx);
+            return Some(tx_hash);
+        }
+        tx.block_number = Some(U64::from(self.block_number));
+        tx.block_hash = Some(H256::from_low_u64_be(self.block_number));
+        tx.transaction_index = Some(U64::from(self.sent_txs.len()));
+        let tx_hash = tx.hash;
+        self.sent_txs.push(tx);
+        Some(tx_hash)
+    }
+
+    fn sent_tx(&self, tx_hash: &Value) -> Option<Transaction> {
+        let tx_hash: H256 = serde_json::from_value(tx_hash.clone()).ok()?;
+        self.sent_txs.iter().find(|tx| tx.hash == tx_hash).cloned()
+    }
+
+    fn nonce(&self, wallet: &Value) -> Option<U256> {
+        let wallet: Address = serde_json::from_value(wallet.clone()).ok()?;
+        let sent_count = self.sent_txs.iter().filter(|tx| tx.from == wallet).count();
+        Some(U256::from(sent_count))
+    }
+}
+
+fn call_key(to: Address, calldata: &[u8]) -> String {
+    format!("{:?}:{}", to, hex::encode(calldata))
+}
+
+#[async_trait]
+impl JsonRpcClient for MockRpc {
+    type Error = MockError;
+
+    async fn request<T: Serialize + Send + Sync, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: T,
+    ) -> Result<R, MockError> {
+        let params = serde_json::to_value(params)?;
+        match self.handle(method, params) {
+            Ok(result) => Ok(serde_json::from_value(result)?),
+            Err(err) => Err(MockError::JsonRpcError(err)),
+        }
+    }
+}
@@ -0,0 +1,269 @@
+use cached::{Cached, TimedCache};
+use ethers::{
+    providers::{Http, Middleware, MiddlewareError, Provider},
+    types::{BlockNumber, Transaction},
+};
+use std::{sync::Arc, time::Duration};
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::{wrappers::ReceiverStream, StreamExt};
+
+use crate::{constants::MEMPOOL_STREAM_SIZE, enums::ENetwork, ws_providers::WsProviders};
+
+// json-rpc error codes of a node without the txpool api
+const METHOD_NOT_FOUND_ERROR_CODES: [i64; 2] = [-32601, -32004];
+// a busy pool holds thousands of pending txs per poll
+const SEEN_TX_HASHES_CAPACITY: usize = 100_000;
+
+/// Pending txs for chains without a pending tx subscription (base, blast).
+/// Polls `txpool_content`, or the pending block once the node rejects the txpool api
+pub struct PollingMempool<M> {
+    provider: Arc<M>,
+    poll_interval: Duration,
+}
+
+impl<M: Middleware + 'static> PollingMempool<M> {
+    pub fn new(provider: Arc<M>, poll_interval: Duration) -> Self {
+        Self {
+            provider,
+            poll_interval,
+        }
+    }
+
+    /// every pending tx once, polling stops when the stream is dropped
+    pub fn stream(self) -> ReceiverStream<Transaction> {
+        let (tx_sender, tx_receiver) = mpsc::channel(MEMPOOL_STREAM_SIZE);
+        tokio::spawn(self.poll(tx_sender));
+        ReceiverStream::new(tx_receiver)
+    }
+
+    async fn poll(self, tx_sender: mpsc::Sender<Transaction>) {
+        // a pending tx stays in the pool or block for several polls
+        let mut seen_tx_hashes =
+            TimedCache::with_lifespan_and_capacity(180, SEEN_TX_HASHES_CAPACITY);
+        let mut is_txpool_supported = true;
+        let mut interval = tokio::time::interval(self.poll_interval);
+        loop {
+            interval.tick().await;
+            // expired hashes are only dropped on flush
+            seen_tx_hashes.flush();
+            let txs = match self.fetch_pending_txs(&mut is_txpool_supported).await {
+                Ok(txs) => txs,
+                Err(err) => {
+                    log::warn!("[PollingMempool] poll failed {:?}", err);
+                    continue;
+                }
+            };
+            for tx in txs {
+                if seen_tx_hashes.cache_set(tx.hash, true).is_some() {
+                    continue;
+                }
+                if tx_sender.send(tx).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn fetch_pending_txs(
+        &self,
+        is_txpool_supported: &mut bool,
+    ) -> anyhow::Result<Vec<Transaction>> {
+        if *is_txpool_supported {
+            match self.provider.txpool_content().await {
+                Ok(content) => {
+                    return Ok(content
+                        .pending
+                        .into_values()
+                        .flat_map(|txs| txs.into_values())
+                        .collect());
+                }
+                Err(err)
+                    if err.as_error_response().is_some_and(|response| {
+                        METHOD_NOT_FOUND_ERROR_CODES.contains(&response.code)
+                    }) =>
+                {
+                    log::warn!(
+                        "[PollingMempool] txpool_content not supported, poll the pending block: {}",
+                        err
+                    );
+                    *is_txpool_supported = false;
+                }
+                // a transient failure keeps the txpool api for the next poll
+                Err(err) => return Err(anyhow::anyhow!("txpool_content: {}", err)),
+            }
+        }
+
+        let block = self
+            .provider
+            .get_block_with_txs(BlockNumber::Pending)
+            .await
+            .map_err(|err| anyhow::anyhow!("pending block: {}", err))?;
+
+        Ok(block.map(|block| block.transactions).unwrap_or_default())
+    }
+}
+
+/// Pending txs of `network`, from the ws subscription or polled over http where it is not supported
+pub async fn subscribe_mempool(
+    network: &ENetwork,
+    http_provider: Arc<Provider<Http>>,
+    poll_interval: Duration,
+) -> anyhow::Result<ReceiverStream<Transaction>> {
+    if !network.is_pending_tx_subscription_supported() {
+        log::info!(
+            "[subscribe_mempool] {:?} has no pending tx subscription, poll every {:?}",
+            network,
+            poll_interval
+        );
+        return Ok(PollingMempool::new(http_provider, poll_interval).stream());
+    }
+
+    let ws_provider = WsProviders::get_ws_providers(network, false)
+        .await?
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("[subscribe_mempool] no ws provider in {:?}", network))?;
+    let (tx_sender, tx_receiver) = mpsc::channel(MEMPOOL_STREAM_SIZE);
+    let (subscribed_sender, subscribed_receiver) = oneshot::channel();
+    tokio::spawn(async move {
+        let stream = match ws_provider.subscribe_pending_txs().await {
+            Ok(stream) => {
+                let _ = subscribed_sender.send(Ok(()));
+                stream
+            }
+            Err(err) => {
+                let _ = subscribed_sender.send(Err(err));
+                return;
+            }
+        };
+        let mut stream = stream.transactions_unordered(MEMPOOL_STREAM_SIZE);
+        while let Some(result) = stream.next().await {
+            // dropped before being fetched
+            let Ok(tx) = result else {
+                continue;
+            };
+            if tx_sender.send(tx).await.is_err() {
+                return;
+            }
+        }
+    });
+
+    match subscribed_receiver.await? {
+        Ok(()) => Ok(ReceiverStream::new(tx_receiver)),
+        Err(err) => {
+            log::error!(
+                "[subscribe_mempool] subscribe_pending_txs failed {:?}, poll every {:?}",
+                err,
+                poll_interval
+            );
+            Ok(PollingMempool::new(http_provider, poll_interval).stream())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_rpc::MockRpc;
+    use ethers::types::{Block, TxpoolContent, H256};
+    use std::collections::BTreeMap;
+
+    fn pending_tx(index: u64) -> Transaction {
+        Transaction {
+            hash: H256::from_low_u64_be(index),
+            nonce: index.into(),
+            ..Default::default()
+        }
+    }
+
+    async fn take_txs(stream: &mut ReceiverStream<Transaction>, count: usize) -> Vec<H256> {
+        let mut tx_hashes = Vec::new();
+        while tx_hashes.len() < count {
+            let tx = tokio::time::timeout(Duration::from_secs(1), stream.next())
+                .await
+                .unwrap()
+                .unwrap();
+            tx_hashes.push(tx.hash);
+        }
+        tx_hashes.sort();
+        tx_hashes
+    }
+
+    #[tokio::test]
+    async fn polls_txpool_content() {
+        let mock_rpc = MockRpc::new();
+        let mut pending = BTreeMap::new();
+        pending.insert(
+            ethers::types::Address::random(),
+            (1..=3)
+                .map(|index| (index.to_string(), pending_tx(index)))
+                .collect::<BTreeMap<_, _>>(),
+        );
+        mock_rpc.push_responses(
+            "txpool_content",
+            [TxpoolContent {
+                pending,
+                queued: BTreeMap::new(),
+            }],
+        );
+
+        let mut stream =
+            PollingMempool::new(Arc::new(mock_rpc.provider()), Duration::from_millis(10)).stream();
+        assert_eq!(
+            take_txs(&mut stream, 3).await,
+            (1..=3).map(H256::from_low_u64_be).collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_pending_block() {
+        let mock_rpc = MockRpc::new();
+        let block = Block {
+            transactions: (1..=3).map(pending_tx).collect(),
+            ..Default::default()
+        };
+        mock_rpc.push_error(
+            "txpool_content",
+            -32601,
+            "the method txpool_content does not exist",
+        );
+        // twice the same block to check dedup
+        mock_rpc.push_responses("eth_getBlockByNumber", [block.clone(), block]);
+
+        let mut stream =
+            PollingMempool::new(Arc::new(mock_rpc.provider()), Duration::from_millis(10)).stream();
+        assert_eq!(
+            take_txs(&mut stream, 3).await,
+            (1..=3).map(H256::from_low_u64_be).collect::<Vec<_>>()
+        );
+        // the second poll of the same block yields nothing new
+        let next_tx = tokio::time::timeout(Duration::from_millis(100), stream.next()).await;
+        assert!(next_tx.is_err());
+    }
+
+    #[tokio::test]
+    async fn keeps_txpool_after_a_transient_error() {
+        let mock_rpc = MockRpc::new();
+        let mut pending = BTreeMap::new();
+        pending.insert(
+            ethers::types::Address::random(),
+            BTreeMap::from([("1".to_string(), pending_tx(1))]),
+        );
+        mock_rpc.push_error("txpool_content", -32000, "header not found");
+        mock_rpc.push_responses(
+            "txpool_content",
+            [TxpoolContent {
+                pending,
+                queued: BTreeMap::new(),
+            }],
+        );
+
+        let mut stream =
+            PollingMempool::new(Arc::new(mock_rpc.provider()), Duration::from_millis(10)).stream();
+        // the txpool api is polled again after the failure
+        assert_eq!(
+            take_txs(&mut stream, 1).await,
+            vec![H256::from_low_u64_be(1)]
+        );
+    }
+}
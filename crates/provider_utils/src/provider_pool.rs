@@ -0,0 +1,307 @@
+use ethers::providers::{Http, Provider};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use std::{str::FromStr, sync::Arc};
+use tokio::sync::RwLock;
+
+use crate::{
+    enums::{ENetwork, EProviderStrategy},
+    http_providers::{HttpProviders, ProviderHealth},
+};
+
+// LeastLatency keeps the current provider unless another one is this much faster, latencies jitter
+// between probes and the index would flap between providers of about the same speed
+const LATENCY_SWITCH_MARGIN_PERCENT: u64 = 20;
+
+/// Providers of a network and the strategy picking the shared `provider_index`, services read the
+/// selected provider with `get_provider`, unreachable providers are never picked
+#[derive(Debug, Clone)]
+pub struct ProviderPool {
+    providers: Vec<Provider<Http>>,
+    strategy: EProviderStrategy,
+    weights: Vec<u32>,
+    provider_index: Arc<RwLock<usize>>,
+    provider_healths: Arc<RwLock<Vec<ProviderHealth>>>,
+}
+
+impl ProviderPool {
+    pub fn new(
+        providers: Vec<Provider<Http>>,
+        strategy: EProviderStrategy,
+        weights: Vec<u32>,
+        provider_index: Arc<RwLock<usize>>,
+    ) -> Self {
+        Self {
+            providers,
+            strategy,
+            weights,
+            provider_index,
+            provider_healths: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// `PROVIDER_STRATEGY` (default LeastLatency) and `PROVIDER_WEIGHTS`,
+    /// comma separated `pattern=weight` where pattern is part of the rpc url (e.g. its host), 1 when no pattern matches
+    pub fn from_env(
+        network: &ENetwork,
+        is_external_rpc: bool,
+        provider_index: Arc<RwLock<usize>>,
+    ) -> Self {
+        let strategy = std::env::var("PROVIDER_STRATEGY").unwrap_or_default();
+        let strategy = if strategy.is_empty() {
+            EProviderStrategy::default()
+        } else {
+            EProviderStrategy::from_str(&strategy)
+                .unwrap_or_else(|_| panic!("PROVIDER_STRATEGY {:?} invalid", strategy))
+        };
+        let providers = HttpProviders::get_providers(network, is_external_rpc).unwrap();
+        let urls: Vec<String> = providers
+            .iter()
+            .map(|provider| provider.url().to_string())
+            .collect();
+        let weights = parse_provider_weights(
+            &std::env::var("PROVIDER_WEIGHTS").unwrap_or_default(),
+            &urls,
+        );
+
+        Self::new(providers, strategy, weights, provider_index)
+    }
+
+    /// `from_env` starting on the lowest latency reachable provider
+    pub async fn init(network: &ENetwork, is_external_rpc: bool) -> anyhow::Result<Self> {
+        let provider_index = HttpProviders::init_provider_index(network, is_external_rpc).await?;
+
+        Ok(Self::from_env(
+            network,
+            is_external_rpc,
+            Arc::new(RwLock::new(provider_index)),
+        ))
+    }
+
+    pub async fn get_provider(&self) -> Provider<Http> {
+        self.providers[*self.provider_index.read().await].clone()
+    }
+
+    /// health of the last refresh
+    pub async fn provider_healths(&self) -> Vec<ProviderHealth> {
+        self.provider_healths.read().await.clone()
+    }
+
+    /// Probe every provider and move `provider_index` by the strategy, none when no provider is reachable
+    pub async fn refresh(&self) -> Option<usize> {
+        let provider_healths = HttpProviders::probe_providers(&self.providers).await;
+        let current_index = *self.provider_index.read().await;
+        let selected_index = self.select(&provider_healths, current_index, &mut rand::thread_rng());
+        if let Some(selected_index) = selected_index {
+            if selected_index != current_index {
+                // round robin switches on every refresh
+                let level = if self.strategy == EProviderStrategy::RoundRobin {
+                    log::Level::Debug
+                } else {
+                    log::Level::Info
+                };
+                log::log!(
+                    level,
+                    event = "provider_switch",
+                    provider_index = selected_index;
+                    "[ProviderPool] switch provider {:?} -> {:?}",
+                    provider_healths[current_index].url,
+                    provider_healths[selected_index].url
+                );
+            }
+            *self.provider_index.write().await = selected_index;
+        }
+        *self.provider_healths.write().await = provider_healths;
+
+        selected_index
+    }
+
+    pub fn select(
+        &self,
+        provider_healths: &[ProviderHealth],
+        current_index: usize,
+        rng: &mut impl Rng,
+    ) -> Option<usize> {
+        let reachable_indexes: Vec<usize> = provider_healths
+            .iter()
+            .enumerate()
+            .filter(|(_, health)| health.reachable)
+            .map(|(index, _)| index)
+            .collect();
+        if reachable_indexes.is_empty() {
+            return None;
+        }
+
+        match self.strategy {
+            EProviderStrategy::RoundRobin => reachable_indexes
+                .iter()
+                .find(|index| **index > current_index)
+                .or(reachable_indexes.first())
+                .copied(),
+            EProviderStrategy::Weighted => {
+                let weights = reachable_indexes
+                    .iter()
+                    .map(|index| self.weights.get(*index).copied().unwrap_or(1));
+                match WeightedIndex::new(weights) {
+                    Ok(weighted_index) => Some(reachable_indexes[weighted_index.sample(rng)]),
+                    // every reachable provider has a zero weight
+                    Err(_) => reachable_indexes.first().copied(),
+                }
+            }
+            EProviderStrategy::LeastLatency => {
+                let fastest_index = HttpProviders::fastest_provider_index(provider_healths)?;
+                let is_current_faster_enough = provider_healths
+                    .get(current_index)
+                    .filter(|health| health.reachable)
+                    .is_some_and(|health| {
+                        provider_healths[fastest_index].latency_ms
+                            * (100 + LATENCY_SWITCH_MARGIN_PERCENT)
+                            >= health.latency_ms * 100
+                    });
+                if is_current_faster_enough {
+                    Some(current_index)
+                } else {
+                    Some(fastest_index)
+                }
+            }
+        }
+    }
+}
+
+/**
+ * weight of each url, `pattern=weight` entries matched as substrings of the url, 1 by default
+ */
+pub fn parse_provider_weights(provider_weights: &str, urls: &[String]) -> Vec<u32> {
+    let patterns: Vec<(&str, u32)> = provider_weights
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let Some((pattern, weight)) = entry.rsplit_once('=') else {
+                panic!(
+                    "PROVIDER_WEIGHTS entry {:?} invalid, expect pattern=weight",
+                    entry
+                );
+            };
+            let Ok(weight) = weight.trim().parse() else {
+                panic!("PROVIDER_WEIGHTS weight {:?} invalid", weight);
+            };
+            (pattern.trim(), weight)
+        })
+        .collect();
+
+    urls.iter()
+        .map(|url| {
+            patterns
+                .iter()
+                .find(|(pattern, _)| url.contains(pattern))
+                .map_or(1, |(_, weight)| *weight)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn pool(
+        strategy: EProviderStrategy,
+        weights: Vec<u32>,
+        providers_count: usize,
+    ) -> ProviderPool {
+        let providers = (0..providers_count)
+            .map(|index| Provider::<Http>::try_from(format!("http://rpc-{}", index)).unwrap())
+            .collect();
+        ProviderPool::new(providers, strategy, weights, Arc::new(RwLock::new(0)))
+    }
+
+    fn health(reachable: bool, latency_ms: u64) -> ProviderHealth {
+        ProviderHealth {
+            url: "http://rpc".to_string(),
+            reachable,
+            latency_ms,
+            block_number: reachable.then_some(100),
+        }
+    }
+
+    #[test]
+    fn round_robin_cycles_reachable_providers() {
+        let pool = pool(EProviderStrategy::RoundRobin, Vec::new(), 4);
+        let provider_healths = [
+            health(true, 10),
+            health(true, 10),
+            health(false, 10),
+            health(true, 10),
+        ];
+        let mut rng = StdRng::seed_from_u64(1);
+
+        let mut current_index = 0;
+        let mut selected = Vec::new();
+        for _ in 0..6 {
+            current_index = pool
+                .select(&provider_healths, current_index, &mut rng)
+                .unwrap();
+            selected.push(current_index);
+        }
+        // the unreachable provider 2 is skipped
+        assert_eq!(selected, vec![1, 3, 0, 1, 3, 0]);
+
+        let unreachable = [health(false, 10), health(false, 10)];
+        assert_eq!(pool.select(&unreachable, 0, &mut rng), None);
+    }
+
+    #[test]
+    fn weighted_respects_the_distribution() {
+        let urls = vec![
+            "http://10.2.15.108:9545/".to_string(),
+            "https://smart.quiknode.pro/key/".to_string(),
+            "https://other.rpc/".to_string(),
+        ];
+        let weights = parse_provider_weights("10.2.15.108=1, quiknode=3", &urls);
+        assert_eq!(weights, vec![1, 3, 1]);
+
+        let pool = pool(EProviderStrategy::Weighted, weights, 3);
+        let provider_healths = [health(true, 10), health(true, 10), health(false, 10)];
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut counts = [0; 3];
+        for _ in 0..10_000 {
+            let index = pool.select(&provider_healths, 0, &mut rng).unwrap();
+            counts[index] += 1;
+        }
+
+        // 1:3 between the reachable providers, the unreachable one is never picked
+        assert!((2_200..=2_800).contains(&counts[0]), "{:?}", counts);
+        assert!((7_200..=7_800).contains(&counts[1]), "{:?}", counts);
+        assert_eq!(counts[2], 0);
+    }
+
+    #[test]
+    fn least_latency_picks_fastest() {
+        let pool = pool(EProviderStrategy::LeastLatency, Vec::new(), 3);
+        let provider_healths = [health(true, 80), health(true, 20), health(false, 1)];
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(pool.select(&provider_healths, 0, &mut rng), Some(1));
+        // the unreachable current provider is left for the fastest one
+        assert_eq!(pool.select(&provider_healths, 2, &mut rng), Some(1));
+    }
+
+    #[test]
+    fn least_latency_does_not_flap_between_close_providers() {
+        let pool = pool(EProviderStrategy::LeastLatency, Vec::new(), 2);
+        let mut rng = StdRng::seed_from_u64(1);
+
+        // within the margin either way, the current provider stays
+        let mut current_index = 0;
+        for (latency_0, latency_1) in [(100, 90), (90, 100), (110, 95), (95, 100)] {
+            let provider_healths = [health(true, latency_0), health(true, latency_1)];
+            current_index = pool
+                .select(&provider_healths, current_index, &mut rng)
+                .unwrap();
+            assert_eq!(current_index, 0);
+        }
+
+        // clearly faster, switch
+        let provider_healths = [health(true, 100), health(true, 70)];
+        assert_eq!(pool.select(&provider_healths, 0, &mut rng), Some(1));
+    }
+}
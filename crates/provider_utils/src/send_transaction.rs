@@ -0,0 +1,176 @@
+use ethers::{
+    providers::{Middleware, MiddlewareError, PendingTransaction, ProviderError},
+    types::{Bytes, H256},
+    utils::keccak256,
+};
+use rand::Rng;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::tx_error::TxError;
+
+#[derive(Debug, Error)]
+pub enum SendTxError {
+    // the node rejected the tx itself, resending the same raw tx cannot succeed
+    #[error("tx rejected: {0}")]
+    Rejected(String),
+    #[error("tx not sent after {0} attempts: {1}")]
+    RetriesExhausted(u32, String),
+    #[error(transparent)]
+    Provider(#[from] ProviderError),
+}
+
+/// `base_delay * 2^attempt` plus up to 50% jitter
+pub fn get_backoff_delay(base_delay: Duration, attempt: u32) -> Duration {
+    let delay = base_delay.saturating_mul(2u32.saturating_pow(attempt));
+    let jitter_ms = rand::thread_rng().gen_range(0..=delay.as_millis() as u64 / 2);
+    delay + Duration::from_millis(jitter_ms)
+}
+
+/// `send_raw_transaction`, retrying transient RPC errors with exponential backoff
+pub async fn send_raw_transaction_with_retry<M: Middleware>(
+    provider: &M,
+    raw_tx: Bytes,
+    max_attempts: u32,
+    base_delay: Duration,
+) -> Result<PendingTransaction<'_, M::Provider>, SendTxError> {
+    let mut attempt = 0;
+    loop {
+        let tx_error = match provider.send_raw_transaction(raw_tx.clone()).await {
+            Ok(pending_tx) => return Ok(pending_tx),
+            Err(err) => match err.as_provider_error() {
+                Some(provider_err) => TxError::from_provider_error(provider_err),
+                None => TxError::from_message(&err.to_string()),
+            },
+        };
+        attempt += 1;
+
+        let is_sent = match tx_error {
+            TxError::AlreadyKnown(_) => true,
+            // after a retry, the nonce was used by an earlier attempt that reached the node
+            TxError::NonceTooLow(_) => attempt > 1,
+            _ => false,
+        };
+        if is_sent {
+            let tx_hash = H256::from_slice(&keccak256(&raw_tx));
+            return Ok(PendingTransaction::new(tx_hash, provider.provider()));
+        }
+        // revert, insufficient funds, underpriced... resending the same raw tx cannot succeed
+        if !tx_error.is_transient() {
+            return Err(SendTxError::Rejected(tx_error.to_string()));
+        }
+        if attempt >= max_attempts {
+            return Err(SendTxError::RetriesExhausted(attempt, tx_error.to_string()));
+        }
+
+        let delay = get_backoff_delay(base_delay, attempt - 1);
+        log::warn!(
+            "[send_raw_transaction_with_retry] attempt {:?}/{:?} failed, retry in {:?}: {}",
+            attempt,
+            max_attempts,
+            delay,
+            tx_error
+        );
+        tokio::time::sleep(delay).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mock_rpc::MockRpc;
+
+    const SEND_RAW_TX: &str = "eth_sendRawTransaction";
+
+    fn push_error(mock_rpc: &MockRpc, message: &str) {
+        mock_rpc.push_error(SEND_RAW_TX, -32000, message);
+    }
+
+    #[tokio::test]
+    async fn retries_transient_errors_then_succeeds() {
+        let mock_rpc = MockRpc::new();
+        let raw_tx = Bytes::from(vec![1, 2, 3]);
+        let tx_hash = H256::random();
+        push_error(&mock_rpc, "request timed out");
+        push_error(&mock_rpc, "connection reset by peer");
+        mock_rpc.push_responses(SEND_RAW_TX, [tx_hash]);
+
+        let provider = mock_rpc.provider();
+        let pending_tx =
+            send_raw_transaction_with_retry(&provider, raw_tx, 3, Duration::from_millis(1))
+                .await
+                .unwrap();
+        assert_eq!(pending_tx.tx_hash(), tx_hash);
+        assert_eq!(mock_rpc.requests(SEND_RAW_TX).len(), 3);
+    }
+
+    #[tokio::test]
+    async fn gives_up_after_max_attempts() {
+        let mock_rpc = MockRpc::new();
+        for _ in 0..3 {
+            push_error(&mock_rpc, "connection refused");
+        }
+
+        let err = send_raw_transaction_with_retry(
+            &mock_rpc.provider(),
+            Bytes::from(vec![1]),
+            3,
+            Duration::from_millis(1),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, SendTxError::RetriesExhausted(3, _)));
+    }
+
+    #[tokio::test]
+    async fn nonce_too_low_after_a_retry_is_the_earlier_attempt() {
+        let mock_rpc = MockRpc::new();
+        let raw_tx = Bytes::from(vec![1, 2, 3]);
+        push_error(&mock_rpc, "request timed out");
+        push_error(&mock_rpc, "nonce too low: next nonce 12, tx nonce 11");
+
+        let provider = mock_rpc.provider();
+        let pending_tx =
+            send_raw_transaction_with_retry(&provider, raw_tx.clone(), 3, Duration::from_millis(1))
+                .await
+                .unwrap();
+        assert_eq!(pending_tx.tx_hash(), H256::from(keccak256(&raw_tx)));
+
+        // the nonce was used before the first attempt
+        let mock_rpc = MockRpc::new();
+        push_error(&mock_rpc, "nonce too low: next nonce 12, tx nonce 11");
+        let err = send_raw_transaction_with_retry(
+            &mock_rpc.provider(),
+            raw_tx,
+            3,
+            Duration::from_millis(1),
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, SendTxError::Rejected(_)));
+        assert_eq!(mock_rpc.requests(SEND_RAW_TX).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn does_not_retry_permanent_errors() {
+        // the same raw tx keeps its gas price, an underpriced tx needs a new one
+        for message in [
+            "insufficient funds for gas * price + value",
+            "replacement transaction underpriced",
+        ] {
+            let mock_rpc = MockRpc::new();
+            push_error(&mock_rpc, message);
+
+            let err = send_raw_transaction_with_retry(
+                &mock_rpc.provider(),
+                Bytes::from(vec![1]),
+                3,
+                Duration::from_millis(1),
+            )
+            .await
+            .unwrap_err();
+            assert!(matches!(err, SendTxError::Rejected(_)), "{}", message);
+            assert_eq!(mock_rpc.requests(SEND_RAW_TX).len(), 1, "{}", message);
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use ethers::providers::{ProviderError, RpcError};
+use std::error::Error as StdError;
+use thiserror::Error;
+
+use crate::constants::DESERIALIZATION_ERROR_MSG;
+
+// json-rpc error code of an `eth_call` / `eth_estimateGas` revert
+const REVERT_ERROR_CODE: i64 = 3;
+
+/// What went wrong with an rpc call or a tx, the retry decisions match on it instead of the error message
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum TxError {
+    // the node answered something that is not json, usually a flaky rpc
+    #[error("deserialization error: {0}")]
+    Deserialization(String),
+    // the node already holds the same tx
+    #[error("already known: {0}")]
+    AlreadyKnown(String),
+    #[error("nonce too low: {0}")]
+    NonceTooLow(String),
+    #[error("underpriced: {0}")]
+    Underpriced(String),
+    #[error("insufficient funds: {0}")]
+    InsufficientFunds(String),
+    #[error("reverted: {0}")]
+    Reverted(String),
+    #[error("timeout: {0}")]
+    Timeout(String),
+    // rate limited or the node is down, the same request can succeed later
+    #[error("rpc unavailable: {0}")]
+    Unavailable(String),
+    #[error("{0}")]
+    Other(String),
+}
+
+const ALREADY_KNOWN_ERRORS: [&str; 2] = ["already known", "known transaction"];
+const NONCE_TOO_LOW_ERRORS: [&str; 2] = ["nonce too low", "nonce has already been used"];
+const UNDERPRICED_ERRORS: [&str; 4] = [
+    "underpriced",
+    "max fee per gas less than block base fee",
+    "fee too low",
+    "gas price too low",
+];
+const TIMEOUT_ERRORS: [&str; 3] = ["timed out", "timeout", "deadline has elapsed"];
+const UNAVAILABLE_ERRORS: [&str; 6] = [
+    "connection",
+    "too many requests",
+    "429",
+    "502",
+    "503",
+    "temporarily unavailable",
+];
+
+impl TxError {
+    /// the provider error of `err` (or of its sources) first, its message otherwise
+    pub fn classify(err: &(dyn StdError + 'static)) -> Self {
+        let mut source = Some(err);
+        while let Some(err) = source {
+            if let Some(provider_err) = err.downcast_ref::<ProviderError>() {
+                return Self::from_provider_error(provider_err);
+            }
+            source = err.source();
+        }
+        Self::from_message(&err.to_string())
+    }
+
+    pub fn from_provider_error(err: &ProviderError) -> Self {
+        if err.as_serde_error().is_some() {
+            return Self::Deserialization(err.to_string());
+        }
+        match err.as_error_response() {
+            Some(response) if response.code == REVERT_ERROR_CODE => {
+                Self::Reverted(response.message.clone())
+            }
+            Some(response) => Self::from_message(&response.message),
+            None => Self::from_message(&err.to_string()),
+        }
+    }
+
+    pub fn from_message(message: &str) -> Self {
+        let lowercase = message.to_lowercase();
+        let contains_any = |patterns: &[&str]| {
+            patterns
+                .iter()
+                .any(|pattern| lowercase.contains(&pattern.to_lowercase()))
+        };
+        let message = message.to_string();
+        if contains_any(&[DESERIALIZATION_ERROR_MSG]) {
+            Self::Deserialization(message)
+        } else if contains_any(&ALREADY_KNOWN_ERRORS) {
+            Self::AlreadyKnown(message)
+        } else if contains_any(&NONCE_TOO_LOW_ERRORS) {
+            Self::NonceTooLow(message)
+        } else if contains_any(&UNDERPRICED_ERRORS) {
+            Self::Underpriced(message)
+        } else if contains_any(&["insufficient funds"]) {
+            Self::InsufficientFunds(message)
+        } else if contains_any(&["revert"]) {
+            Self::Reverted(message)
+        } else if contains_any(&TIMEOUT_ERRORS) {
+            Self::Timeout(message)
+        } else if contains_any(&UNAVAILABLE_ERRORS) {
+            Self::Unavailable(message)
+        } else {
+            Self::Other(message)
+        }
+    }
+
+    /// a fresh nonce, gas price or rpc response can succeed, reverts and missing funds cannot
+    pub fn is_retryable(&self) -> bool {
+        self.is_transient() || matches!(self, Self::NonceTooLow(_) | Self::Underpriced(_))
+    }
+
+    /// the rpc failed rather than the tx, the same request can succeed
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Self::Deserialization(_) | Self::Timeout(_) | Self::Unavailable(_)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ethers::providers::{JsonRpcError, MockError};
+
+    #[test]
+    fn messages_map_to_their_variant() {
+        let cases = [
+            (
+                "Deserialization Error: expected value at line 1 column 1. Response: <html>",
+                TxError::Deserialization(String::new()),
+            ),
+            (
+                "(code: -32000, message: nonce too low: next nonce 12, tx nonce 11, data: None)",
+                TxError::NonceTooLow(String::new()),
+            ),
+            (
+                "replacement transaction underpriced",
+                TxError::Underpriced(String::new()),
+            ),
+            (
+                "max fee per gas less than block base fee: address 0x1, maxFeePerGas: 1 baseFee: 2",
+                TxError::Underpriced(String::new()),
+            ),
+            (
+                "insufficient funds for gas * price + value",
+                TxError::InsufficientFunds(String::new()),
+            ),
+            (
+                "execution reverted: UniswapV2: INSUFFICIENT_OUTPUT_AMOUNT",
+                TxError::Reverted(String::new()),
+            ),
+            ("request timed out", TxError::Timeout(String::new())),
+            ("already known", TxError::AlreadyKnown(String::new())),
+            (
+                "HTTP error 429 Too Many Requests",
+                TxError::Unavailable(String::new()),
+            ),
+            ("unknown account", TxError::Other(String::new())),
+        ];
+        for (message, expected) in cases {
+            let tx_error = TxError::from_message(message);
+            assert_eq!(
+                std::mem::discriminant(&tx_error),
+                std::mem::discriminant(&expected),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn provider_errors_are_classified_from_the_rpc_response() {
+        let revert = ProviderError::from(MockError::JsonRpcError(JsonRpcError {
+            code: REVERT_ERROR_CODE,
+            message: "execution reverted".to_string(),
+            data: None,
+        }));
+        assert_eq!(
+            TxError::classify(&revert),
+            TxError::Reverted("execution reverted".to_string())
+        );
+
+        let underpriced = ProviderError::from(MockError::JsonRpcError(JsonRpcError {
+            code: -32000,
+            message: "transaction underpriced".to_string(),
+            data: None,
+        }));
+        let anyhow_err = anyhow::Error::from(underpriced).context("sell failed");
+        assert!(matches!(
+            TxError::classify(anyhow_err.as_ref()),
+            TxError::Underpriced(_)
+        ));
+        assert!(TxError::classify(anyhow_err.as_ref()).is_retryable());
+
+        let serde_err = serde_json::from_str::<u64>("<html>").unwrap_err();
+        assert!(matches!(
+            TxError::classify(&ProviderError::SerdeJson(serde_err)),
+            TxError::Deserialization(_)
+        ));
+        assert!(!TxError::from_message("insufficient funds").is_retryable());
+    }
+}
@@ -1,16 +1,20 @@
 use cached::{Cached, TimedCache};
 use ethers::{
     providers::{Middleware, Provider, Ws, WsClientError},
-    types::{Filter, Log, H256},
+    types::{Block, Filter, Log, H256, U64},
 };
-use std::sync::Arc;
+use std::{future::Future, sync::Arc, time::Duration};
 use tokio::sync::{
     broadcast::{self, Sender},
     Mutex,
 };
 use tokio_stream::StreamExt;
 
-use crate::{enums::ENetwork, networks::NETWORKS};
+use crate::{
+    constants::{WS_RECONNECT_BASE_DELAY_MS, WS_RECONNECT_MAX_DELAY_MS},
+    enums::ENetwork,
+    networks::NETWORKS,
+};
 
 pub struct WsProviders;
 
@@ -24,11 +28,16 @@ impl WsProviders {
         let ws_providers = Self::get_ws_providers(network, is_external).await?;
         let tx_hashes_cache: Arc<Mutex<TimedCache<H256, bool>>> =
             Arc::new(Mutex::new(TimedCache::with_lifespan(180)));
+        // removed logs (chain reorg) share the tx hash of the original log,
+        // so they are deduped separately to still reach the receiver
+        let removed_tx_hashes_cache: Arc<Mutex<TimedCache<H256, bool>>> =
+            Arc::new(Mutex::new(TimedCache::with_lifespan(180)));
 
         for ws_provider in ws_providers {
             let event_sender_clone = event_sender.clone();
             let filter_clone = filter.clone();
             let tx_hashes_cache_clone = tx_hashes_cache.clone();
+            let removed_tx_hashes_cache_clone = removed_tx_hashes_cache.clone();
 
             tokio::spawn(async move {
                 let mut stream = ws_provider
@@ -39,7 +48,11 @@ impl WsProviders {
 
                 while let Some(event) = stream.next().await {
                     let tx_hash = event.transaction_hash.unwrap();
-                    let mut tx_hashes_cache = tx_hashes_cache_clone.lock().await;
+                    let mut tx_hashes_cache = if event.removed == Some(true) {
+                        removed_tx_hashes_cache_clone.lock().await
+                    } else {
+                        tx_hashes_cache_clone.lock().await
+                    };
                     if tx_hashes_cache.cache_get(&tx_hash).is_none() {
                         event_sender_clone.send(event).unwrap();
                         tx_hashes_cache.cache_set(tx_hash, true);
@@ -51,6 +64,81 @@ impl WsProviders {
         Ok(event_receiver)
     }
 
+    /// `subscribe_logs_stream` surviving socket drops, each ws url resubscribes with exponential backoff
+    /// and replays the logs from the last seen block, the receiver only closes when every receiver is dropped
+    pub async fn subscribe_logs_stream_resilient(
+        network: &ENetwork,
+        filter: Filter,
+        is_external: bool,
+    ) -> anyhow::Result<broadcast::Receiver<Log>> {
+        let Some(network_config) = NETWORKS.get(network) else {
+            panic!("NETWORKS {:?} not found", network);
+        };
+        let urls = if is_external {
+            network_config.ws_url.external.clone()
+        } else {
+            network_config.ws_url.internal.clone()
+        };
+        if urls.is_empty() {
+            return Err(anyhow::anyhow!("[WsProviders] no ws url for {:?}", network));
+        }
+
+        let (event_sender, event_receiver) = broadcast::channel(128);
+        let log_forwarder = LogForwarder::new(event_sender);
+        for url in urls {
+            let filter = filter.clone();
+            let log_forwarder = log_forwarder.clone();
+            tokio::spawn(forward_logs_resilient(
+                move |from_block, log_forwarder| {
+                    run_ws_logs_session(url.clone(), filter.clone(), from_block, log_forwarder)
+                },
+                log_forwarder,
+                Duration::from_millis(WS_RECONNECT_BASE_DELAY_MS),
+                Duration::from_millis(WS_RECONNECT_MAX_DELAY_MS),
+            ));
+        }
+
+        Ok(event_receiver)
+    }
+
+    /// new heads of every ws url, each block number is sent once. The receiver closes once every
+    /// subscription ended, the caller falls back to polling then
+    pub async fn subscribe_blocks(
+        network: &ENetwork,
+        is_external: bool,
+    ) -> anyhow::Result<broadcast::Receiver<Block<H256>>> {
+        let ws_providers = Self::get_ws_providers(network, is_external).await?;
+        if ws_providers.is_empty() {
+            return Err(anyhow::anyhow!("[WsProviders] no ws url for {:?}", network));
+        }
+
+        let (block_sender, block_receiver) = broadcast::channel(16);
+        let last_block_number: Arc<std::sync::Mutex<Option<U64>>> = Default::default();
+        for ws_provider in ws_providers {
+            let block_sender = block_sender.clone();
+            let last_block_number = last_block_number.clone();
+            tokio::spawn(async move {
+                let mut stream = match ws_provider.subscribe_blocks().await {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        log::warn!("[WsProviders] new heads subscription failed: {:?}", err);
+                        return;
+                    }
+                };
+                while let Some(block) = stream.next().await {
+                    if !is_new_head(&last_block_number, block.number) {
+                        continue;
+                    }
+                    if block_sender.send(block).is_err() {
+                        return;
+                    }
+                }
+            });
+        }
+
+        Ok(block_receiver)
+    }
+
     pub async fn get_ws_providers(
         network: &ENetwork,
         is_external: bool,
@@ -74,3 +162,230 @@ impl WsProviders {
         Ok(providers)
     }
 }
+
+/// the first ws url delivering a block number wins, the others and older heads are skipped
+fn is_new_head(
+    last_block_number: &std::sync::Mutex<Option<U64>>,
+    block_number: Option<U64>,
+) -> bool {
+    let Some(block_number) = block_number else {
+        return false;
+    };
+    let mut last_block_number = last_block_number.lock().unwrap();
+    if last_block_number.is_some_and(|last| last >= block_number) {
+        return false;
+    }
+    *last_block_number = Some(block_number);
+    true
+}
+
+/**
+ * sends logs to the receivers once per tx hash, removed logs (chain reorg) are deduped separately,
+ * and keeps the last seen block for the replay after a reconnection
+ */
+#[derive(Debug, Clone)]
+pub struct LogForwarder {
+    event_sender: Sender<Log>,
+    tx_hashes_cache: Arc<Mutex<TimedCache<H256, bool>>>,
+    removed_tx_hashes_cache: Arc<Mutex<TimedCache<H256, bool>>>,
+    last_block: Arc<std::sync::Mutex<Option<U64>>>,
+}
+
+impl LogForwarder {
+    pub fn new(event_sender: Sender<Log>) -> Self {
+        Self {
+            event_sender,
+            tx_hashes_cache: Arc::new(Mutex::new(TimedCache::with_lifespan(180))),
+            removed_tx_hashes_cache: Arc::new(Mutex::new(TimedCache::with_lifespan(180))),
+            last_block: Arc::new(std::sync::Mutex::new(None)),
+        }
+    }
+
+    pub fn last_block(&self) -> Option<U64> {
+        *self.last_block.lock().unwrap()
+    }
+
+    /// every receiver is dropped, nothing left to forward to
+    pub fn is_closed(&self) -> bool {
+        self.event_sender.receiver_count() == 0
+    }
+
+    /// false once every receiver is dropped
+    pub async fn forward(&self, log: Log) -> bool {
+        let Some(tx_hash) = log.transaction_hash else {
+            return !self.is_closed();
+        };
+        let is_removed = log.removed == Some(true);
+        if !is_removed {
+            if let Some(block_number) = log.block_number {
+                let mut last_block = self.last_block.lock().unwrap();
+                *last_block = Some(last_block.map_or(block_number, |last| last.max(block_number)));
+            }
+        }
+
+        let mut tx_hashes_cache = if is_removed {
+            self.removed_tx_hashes_cache.lock().await
+        } else {
+            self.tx_hashes_cache.lock().await
+        };
+        if tx_hashes_cache.cache_get(&tx_hash).is_some() {
+            return !self.is_closed();
+        }
+        tx_hashes_cache.cache_set(tx_hash, true);
+        self.event_sender.send(log).is_ok()
+    }
+}
+
+/**
+ * runs `session` again whenever it ends, a failed session doubles the delay up to `max_backoff`,
+ * a session that subscribed resets it. Returns once every receiver is dropped
+ */
+pub async fn forward_logs_resilient<F, Fut>(
+    mut session: F,
+    log_forwarder: LogForwarder,
+    base_backoff: Duration,
+    max_backoff: Duration,
+) where
+    F: FnMut(Option<U64>, LogForwarder) -> Fut,
+    Fut: Future<Output = anyhow::Result<()>>,
+{
+    let mut backoff = base_backoff;
+    loop {
+        let result = session(log_forwarder.last_block(), log_forwarder.clone()).await;
+        if log_forwarder.is_closed() {
+            return;
+        }
+        let delay = match result {
+            Ok(()) => {
+                log::warn!(
+                    "[WsProviders] logs subscription ended, resubscribe from block {:?}",
+                    log_forwarder.last_block()
+                );
+                backoff = base_backoff;
+                backoff
+            }
+            Err(err) => {
+                log::warn!(
+                    "[WsProviders] logs subscription failed, retry in {:?}: {:?}",
+                    backoff,
+                    err
+                );
+                let delay = backoff;
+                backoff = (backoff * 2).min(max_backoff);
+                delay
+            }
+        };
+        tokio::time::sleep(delay).await;
+    }
+}
+
+/**
+ * subscribe the logs of `filter` on `url` then replay the logs since `from_block`, missed while disconnected,
+ * the overlap is deduped by the forwarder. Ok once the socket drops
+ */
+async fn run_ws_logs_session(
+    url: String,
+    filter: Filter,
+    from_block: Option<U64>,
+    log_forwarder: LogForwarder,
+) -> anyhow::Result<()> {
+    let provider = Provider::new(Ws::connect(&url).await?);
+    let mut stream = provider.subscribe_logs(&filter).await?;
+
+    if let Some(from_block) = from_block {
+        let missed_logs = provider
+            .get_logs(&filter.clone().from_block(from_block))
+            .await?;
+        for log in missed_logs {
+            if !log_forwarder.forward(log).await {
+                return Ok(());
+            }
+        }
+    }
+
+    while let Some(log) = stream.next().await {
+        if !log_forwarder.forward(log).await {
+            return Ok(());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn log_at(block_number: u64, tx_hash: H256) -> Log {
+        Log {
+            transaction_hash: Some(tx_hash),
+            block_number: Some(U64::from(block_number)),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn resubscribes_after_disconnect_without_missing_logs() {
+        let tx_hashes: Vec<H256> = (0..4).map(|_| H256::random()).collect();
+        let (event_sender, mut event_receiver) = broadcast::channel(16);
+        let attempts = Arc::new(AtomicU32::new(0));
+
+        let session_attempts = attempts.clone();
+        let session_tx_hashes = tx_hashes.clone();
+        let handle = tokio::spawn(forward_logs_resilient(
+            move |from_block, log_forwarder: LogForwarder| {
+                let attempt = session_attempts.fetch_add(1, Ordering::SeqCst);
+                let tx_hashes = session_tx_hashes.clone();
+                async move {
+                    match attempt {
+                        // two logs then the socket drops mid-stream
+                        0 => {
+                            assert_eq!(from_block, None);
+                            log_forwarder.forward(log_at(10, tx_hashes[0])).await;
+                            log_forwarder.forward(log_at(11, tx_hashes[1])).await;
+                            Ok(())
+                        }
+                        1 => {
+                            assert_eq!(from_block, Some(U64::from(11)));
+                            Err(anyhow::anyhow!("connection refused"))
+                        }
+                        // the replay from block 11 overlaps the first session
+                        2 => {
+                            assert_eq!(from_block, Some(U64::from(11)));
+                            log_forwarder.forward(log_at(11, tx_hashes[1])).await;
+                            log_forwarder.forward(log_at(12, tx_hashes[2])).await;
+                            log_forwarder.forward(log_at(13, tx_hashes[3])).await;
+                            Ok(())
+                        }
+                        _ => {
+                            tokio::time::sleep(Duration::from_millis(10)).await;
+                            Ok(())
+                        }
+                    }
+                }
+            },
+            LogForwarder::new(event_sender),
+            Duration::from_millis(1),
+            Duration::from_millis(5),
+        ));
+
+        let mut received = Vec::new();
+        for _ in 0..tx_hashes.len() {
+            let log = tokio::time::timeout(Duration::from_secs(1), event_receiver.recv())
+                .await
+                .unwrap()
+                .unwrap();
+            received.push(log.transaction_hash.unwrap());
+        }
+        assert_eq!(received, tx_hashes);
+        assert!(attempts.load(Ordering::SeqCst) >= 3);
+
+        // the forwarding stops once the receiver is gone
+        drop(event_receiver);
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}